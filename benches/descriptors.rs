@@ -0,0 +1,77 @@
+// Throughput of opening and closing files from several threads at once, with the file descriptor table
+// and its numbers in a single shard or split over several, for each number of threads.
+//
+// Each thread opens and closes a file of its own, so that the descriptor table is all they share.
+
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use memfs::memfs::MemFS;
+use memfs::tuning::MapTuning;
+use memfs::utils::OpenFlag;
+use memfs::workload::BACKEND;
+
+const TOTAL_WORKS: usize = 1usize << 14;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+const SHARDS: [usize; 2] = [1, 16];
+
+fn open_and_close(fs: &MemFS, thread_count: usize) {
+    std::thread::scope(|scope| {
+        for t in 0..thread_count {
+            scope.spawn(move || {
+                let path = format!("/file_{t}");
+
+                for _ in 0..TOTAL_WORKS / thread_count {
+                    let fd = fs.open(&path, OpenFlag::O_RDONLY).unwrap();
+                    fs.close(fd).unwrap();
+                }
+            });
+        }
+    });
+}
+
+fn bench_open_close(c: &mut Criterion) {
+    let mut group = c.benchmark_group("open_close");
+    group.throughput(Throughput::Elements(TOTAL_WORKS as u64));
+
+    for shards in SHARDS {
+        for thread_count in THREADS {
+            let id = BenchmarkId::new(format!("{BACKEND}/{shards}_shards"), thread_count);
+
+            group.bench_with_input(id, &thread_count, |b, &thread_count| {
+                b.iter_custom(|iters| {
+                    let mut measured = Duration::ZERO;
+
+                    for _ in 0..iters {
+                        let fs = MemFS::builder()
+                            .map_tuning(MapTuning {
+                                descriptor_shards: shards,
+                                ..Default::default()
+                            })
+                            .build();
+
+                        for t in 0..thread_count {
+                            let fd = fs.open(&format!("/file_{t}"), OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+                            fs.close(fd).unwrap();
+                        }
+
+                        let timer = Instant::now();
+                        open_and_close(&fs, thread_count);
+                        measured += timer.elapsed();
+                    }
+
+                    measured
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20).noise_threshold(0.05);
+    targets = bench_open_close
+}
+criterion_main!(benches);
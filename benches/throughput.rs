@@ -0,0 +1,48 @@
+// Throughput of the workloads of memfs::workload, for each number of threads.
+//
+// Benchmarks are named after the backend MemFS is built with, so that running them once per backend
+// puts the backends side by side in every group of the report, and criterion compares each of them
+// against its own previous run, or against a baseline saved with `--save-baseline`.
+
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use memfs::workload::{BACKEND, Workload};
+
+const TOTAL_WORKS: usize = 1usize << 12;
+const THREADS: [usize; 5] = [1, 2, 4, 8, 16];
+
+fn bench_workloads(c: &mut Criterion) {
+    for workload in Workload::ALL {
+        let mut group = c.benchmark_group(workload.name());
+        group.throughput(Throughput::Elements(TOTAL_WORKS as u64));
+
+        for thread_count in THREADS {
+            group.bench_with_input(BenchmarkId::new(BACKEND, thread_count), &thread_count, |b, &thread_count| {
+                b.iter_custom(|iters| {
+                    let mut measured = Duration::ZERO;
+
+                    for _ in 0..iters {
+                        let prepared = workload.prepare(thread_count, TOTAL_WORKS);
+                        let timer = Instant::now();
+                        let successes = prepared.run();
+                        measured += timer.elapsed();
+
+                        assert_eq!(successes, workload.expected_successes(thread_count, TOTAL_WORKS));
+                    }
+
+                    measured
+                });
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20).noise_threshold(0.05);
+    targets = bench_workloads
+}
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use memfs::memfs::MemFS;
+use memfs::utils::{FallocateMode, OpenFlag, SeekFlag};
+
+const PATHS: [&str; 4] = ["/a", "/b", "/d", "/d/c"];
+
+/// Calls on descriptors. Descriptors are picked among the ones opened so far, or taken as is once
+/// none is open, so that closed and unknown descriptors are used too. Offsets and lengths go past
+/// [memfs::utils::FILE_MAX_SIZE], up to overflowing.
+#[derive(Arbitrary, Debug)]
+enum Call {
+    Open { path: u8, flags: u16 },
+    Close { fd: u8 },
+    Read { fd: u8, len: u16 },
+    Write { fd: u8, data: Vec<u8> },
+    Pread { fd: u8, len: u16, offset: u64 },
+    Pwrite { fd: u8, data: Vec<u8>, offset: u64 },
+    Lseek { fd: u8, offset: i64, whence: u8 },
+    Ftruncate { fd: u8, len: u64 },
+    Fallocate { fd: u8, mode: u8, offset: u64, len: u64 },
+    Fsync { fd: u8 },
+}
+
+fn seek_flag(whence: u8) -> SeekFlag {
+    match whence % 5 {
+        0 => SeekFlag::SEEK_SET,
+        1 => SeekFlag::SEEK_CUR,
+        2 => SeekFlag::SEEK_END,
+        3 => SeekFlag::SEEK_DATA,
+        _ => SeekFlag::SEEK_HOLE,
+    }
+}
+
+fn fallocate_mode(mode: u8) -> FallocateMode {
+    match mode % 3 {
+        0 => FallocateMode::Allocate,
+        1 => FallocateMode::KeepSize,
+        _ => FallocateMode::PunchHole,
+    }
+}
+
+fuzz_target!(|calls: Vec<Call>| {
+    let fs = MemFS::new();
+    let mut fds = Vec::new();
+    let pick = |fds: &Vec<usize>, fd: u8| fds.get(usize::from(fd) % fds.len().max(1)).copied().unwrap_or(fd.into());
+
+    fs.mkdir("/d").unwrap();
+
+    for call in calls {
+        match call {
+            Call::Open { path, flags } => {
+                let path = PATHS[usize::from(path) % PATHS.len()];
+
+                if let Ok(fd) = fs.open(path, OpenFlag::from_bits_truncate(u32::from(flags))) {
+                    fds.push(fd);
+                }
+            }
+            Call::Close { fd } => {
+                let fd = pick(&fds, fd);
+
+                if fs.close(fd).is_ok() {
+                    fds.retain(|open| *open != fd);
+                }
+            }
+            Call::Read { fd, len } => {
+                let mut buffer = vec![0; usize::from(len)];
+
+                if let Ok(read) = fs.read(pick(&fds, fd), &mut buffer) {
+                    assert!(read <= buffer.len());
+                }
+            }
+            Call::Write { fd, data } => {
+                if let Ok(written) = fs.write(pick(&fds, fd), &data) {
+                    assert!(written <= data.len());
+                }
+            }
+            Call::Pread { fd, len, offset } => {
+                let mut buffer = vec![0; usize::from(len)];
+
+                if let Ok(read) = fs.pread(pick(&fds, fd), &mut buffer, offset as usize) {
+                    assert!(read <= buffer.len());
+                }
+            }
+            Call::Pwrite { fd, data, offset } => {
+                if let Ok(written) = fs.pwrite(pick(&fds, fd), &data, offset as usize) {
+                    assert!(written <= data.len());
+                }
+            }
+            Call::Lseek { fd, offset, whence } => drop(fs.lseek(pick(&fds, fd), offset, seek_flag(whence))),
+            Call::Ftruncate { fd, len } => drop(fs.ftruncate(pick(&fds, fd), len as usize)),
+            Call::Fallocate { fd, mode, offset, len } => {
+                drop(fs.fallocate(pick(&fds, fd), fallocate_mode(mode), offset as usize, len as usize))
+            }
+            Call::Fsync { fd } => drop(fs.fsync(pick(&fds, fd))),
+        }
+    }
+});
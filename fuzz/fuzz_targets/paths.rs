@@ -0,0 +1,48 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use memfs::memfs::MemFS;
+use memfs::utils::OpenFlag;
+
+/// Calls taking paths, made of arbitrary bytes. Bytes which are not UTF-8 are replaced, as MemFS takes `&str`.
+#[derive(Arbitrary, Debug)]
+enum PathCall {
+    Open(Vec<u8>, u16),
+    Mkdir(Vec<u8>),
+    Rmdir(Vec<u8>),
+    Unlink(Vec<u8>),
+    Chdir(Vec<u8>),
+    Stat(Vec<u8>),
+    Readdir(Vec<u8>),
+    Symlink(Vec<u8>, Vec<u8>),
+    Rename(Vec<u8>, Vec<u8>),
+    Canonicalize(Vec<u8>),
+}
+
+fn path(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fuzz_target!(|calls: Vec<PathCall>| {
+    let fs = MemFS::new();
+
+    for call in calls {
+        match call {
+            PathCall::Open(bytes, flags) => {
+                if let Ok(fd) = fs.open(&path(&bytes), OpenFlag::from_bits_truncate(u32::from(flags))) {
+                    fs.close(fd).unwrap();
+                }
+            }
+            PathCall::Mkdir(bytes) => drop(fs.mkdir(&path(&bytes))),
+            PathCall::Rmdir(bytes) => drop(fs.rmdir(&path(&bytes))),
+            PathCall::Unlink(bytes) => drop(fs.unlink(&path(&bytes))),
+            PathCall::Chdir(bytes) => drop(fs.chdir(&path(&bytes))),
+            PathCall::Stat(bytes) => drop(fs.stat(&path(&bytes))),
+            PathCall::Readdir(bytes) => drop(fs.readdir(&path(&bytes))),
+            PathCall::Symlink(target, link) => drop(fs.symlink(&path(&target), &path(&link))),
+            PathCall::Rename(old, new) => drop(fs.rename(&path(&old), &path(&new))),
+            PathCall::Canonicalize(bytes) => drop(fs.canonicalize(&path(&bytes))),
+        }
+    }
+});
@@ -0,0 +1,134 @@
+use std::{
+    io::{self, SeekFrom},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::memfs::MemFS;
+use crate::utils::{MemFSErr, OpenFlag, Result, SeekFlag};
+
+/// Asynchronous front of a [MemFS], for async tasks using it as a backing store.
+///
+/// Operations of MemFS never wait for I/O, so every future runs its operation when first polled,
+/// on the polling task, and there is no need for `spawn_blocking`. Operations which wait on purpose
+/// still block the task: mutations of a file system frozen with [crate::freeze::FreezeMode::Block],
+/// and delays of a [crate::latency::LatencyProfile].
+#[derive(Clone)]
+pub struct AsyncMemFS {
+    fs: Arc<MemFS>,
+}
+
+impl AsyncMemFS {
+    pub fn new(fs: MemFS) -> Self {
+        Self::from_shared(Arc::new(fs))
+    }
+
+    /// Wraps a file system which is also used directly elsewhere.
+    pub fn from_shared(fs: Arc<MemFS>) -> Self {
+        Self { fs }
+    }
+
+    pub fn inner(&self) -> &Arc<MemFS> {
+        &self.fs
+    }
+
+    pub async fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        self.fs.open(path, flag)
+    }
+
+    pub async fn close(&self, fd: usize) -> Result<()> {
+        self.fs.close(fd)
+    }
+
+    pub async fn read(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        self.fs.read(fd, buffer)
+    }
+
+    pub async fn write(&self, fd: usize, data: &[u8]) -> Result<usize> {
+        self.fs.write(fd, data)
+    }
+
+    /// Opens a file as a [File], which closes the descriptor when dropped.
+    pub async fn open_file(&self, path: &str, flag: OpenFlag) -> Result<File> {
+        let fd = self.fs.open(path, flag)?;
+
+        Ok(File {
+            fs: self.fs.clone(),
+            fd,
+            seek_result: None,
+        })
+    }
+}
+
+/// File opened by [AsyncMemFS::open_file], implementing the I/O traits of tokio.
+///
+/// Flushing syncs the file with [MemFS::fsync]. The descriptor is closed when the file is dropped.
+pub struct File {
+    fs: Arc<MemFS>,
+    fd: usize,
+    seek_result: Option<io::Result<u64>>,
+}
+
+impl File {
+    /// Descriptor of the file, usable with the [MemFS] behind it.
+    pub fn fd(&self) -> usize {
+        self.fd
+    }
+}
+
+impl AsyncRead for File {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let read = self.fs.read(self.fd, buf.initialize_unfilled())?;
+        buf.advance(read);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for File {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.fs.write(self.fd, buf).map_err(io::Error::from))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.fs.fsync(self.fd).map_err(io::Error::from))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for File {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let (offset, flag) = match position {
+            SeekFrom::Start(offset) => (
+                i64::try_from(offset).map_err(|_| io::Error::from(MemFSErr::invalid_value()))?,
+                SeekFlag::SEEK_SET,
+            ),
+            SeekFrom::Current(offset) => (offset, SeekFlag::SEEK_CUR),
+            SeekFrom::End(offset) => (offset, SeekFlag::SEEK_END),
+        };
+
+        let result = self.fs.lseek(self.fd, offset, flag);
+        self.seek_result = Some(result.map(|offset| offset as u64).map_err(io::Error::from));
+
+        Ok(())
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        match self.seek_result.take() {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Ready(self.fs.lseek(self.fd, 0, SeekFlag::SEEK_CUR).map(|offset| offset as u64).map_err(io::Error::from)),
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = self.fs.close(self.fd);
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    cell::UnsafeCell,
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::Deref,
+    ptr::NonNull,
+    sync::{
+        Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicUsize, Ordering, fence},
+    },
+};
+
+/// Number of slots allocated together when an arena runs out of free slots.
+const CHUNK_SLOTS: usize = 64;
+
+/// Types with an arena of their own.
+pub(crate) trait ArenaAllocated: Sized + 'static {
+    fn arena() -> &'static Arena<Self>;
+}
+
+struct Slot<T> {
+    strong: AtomicUsize,
+
+    /// Weak references, plus one shared by every strong reference.
+    weak: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+
+    /// Address of the chunk holding the slot, which keys it in [ArenaState::chunks].
+    chunk: usize,
+}
+
+struct SlotPtr<T>(NonNull<Slot<T>>);
+
+unsafe impl<T: Send> Send for SlotPtr<T> {}
+
+/// Slab arena for the nodes of the file system tree.
+///
+/// Nodes are allocated in chunks of adjacent slots and handed out as [NodeArc], which counts references
+/// like `Arc`. A slot goes back to the free list of its chunk only when its last strong and weak
+/// references are dropped, so frees deferred by the lock-free backend (where a removed node is dropped
+/// once no reader can still see it) are safe: a reader holding a guard keeps the slot alive through
+/// the map that still owns it.
+///
+/// New nodes go to the chunk at the lowest address with a free slot, which packs live nodes together so
+/// that the other chunks empty out. A chunk whose slots are all free goes back to the system allocator,
+/// except for a single one kept aside, so that creating and removing a node at the edge of a chunk
+/// does not allocate a chunk every time.
+///
+/// File descriptors are not allocated here: the descriptor table stores them by value, so they already
+/// sit in the slots of its maps, and an arena would only add an indirection to every access.
+pub(crate) struct Arena<T> {
+    state: Mutex<ArenaState<T>>,
+}
+
+struct ArenaState<T> {
+    chunks: BTreeMap<usize, Chunk<T>>,
+
+    /// Chunks with at least one free slot.
+    available: BTreeSet<usize>,
+
+    /// Number of chunks whose slots are all free.
+    empty: usize,
+}
+
+struct Chunk<T> {
+    /// Owns the slots, which are only reached through pointers.
+    _slots: Box<[Slot<T>]>,
+    free: Vec<SlotPtr<T>>,
+}
+
+unsafe impl<T: Send> Send for Arena<T> {}
+unsafe impl<T: Send> Sync for Arena<T> {}
+
+impl<T> Arena<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(ArenaState {
+                chunks: BTreeMap::new(),
+                available: BTreeSet::new(),
+                empty: 0,
+            }),
+        }
+    }
+
+    fn allocate(&self, value: T) -> NonNull<Slot<T>> {
+        let slot = self.take_slot();
+
+        // The slot is not reachable from anywhere else until it is returned.
+        unsafe {
+            let slot = slot.as_ref();
+            slot.strong.store(1, Ordering::Relaxed);
+            slot.weak.store(1, Ordering::Relaxed);
+            (*slot.value.get()).write(value);
+        }
+
+        slot
+    }
+
+    fn take_slot(&self) -> NonNull<Slot<T>> {
+        let mut state = self.lock();
+
+        let key = match state.available.first() {
+            Some(&key) => key,
+            None => state.grow(),
+        };
+
+        let chunk = state.chunks.get_mut(&key).expect("available chunks are in the arena");
+        let was_empty = chunk.free.len() == CHUNK_SLOTS;
+        let slot = chunk.free.pop().expect("available chunks have a free slot").0;
+        let is_full = chunk.free.is_empty();
+
+        if was_empty {
+            state.empty -= 1;
+        }
+
+        if is_full {
+            state.available.remove(&key);
+        }
+
+        slot
+    }
+
+    fn release(&self, slot: NonNull<Slot<T>>) {
+        let key = unsafe { slot.as_ref() }.chunk;
+        let mut state = self.lock();
+
+        let chunk = state.chunks.get_mut(&key).expect("released slots are in the arena");
+        chunk.free.push(SlotPtr(slot));
+        let is_empty = chunk.free.len() == CHUNK_SLOTS;
+
+        state.available.insert(key);
+
+        if !is_empty {
+            return;
+        }
+
+        if state.empty == 0 {
+            state.empty = 1;
+        } else {
+            // No slot of the chunk is referenced anymore, and its values were dropped already.
+            state.available.remove(&key);
+            state.chunks.remove(&key);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ArenaState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> ArenaState<T> {
+    /// Adds a chunk whose slots are all free, and returns its key.
+    fn grow(&mut self) -> usize {
+        let mut slots: Box<[Slot<T>]> = (0..CHUNK_SLOTS)
+            .map(|_| Slot {
+                strong: AtomicUsize::new(0),
+                weak: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                chunk: 0,
+            })
+            .collect();
+
+        // Slots stay at the same address when the chunk moves in the map, since they are boxed.
+        let key = slots.as_ptr() as usize;
+
+        for slot in slots.iter_mut() {
+            slot.chunk = key;
+        }
+
+        // Lowest addresses are handed out first.
+        let free = slots.iter().rev().map(|slot| SlotPtr(NonNull::from(slot))).collect();
+
+        self.chunks.insert(key, Chunk { _slots: slots, free });
+        self.available.insert(key);
+        self.empty += 1;
+
+        key
+    }
+}
+
+/// Counted reference to a value in an arena, like `Arc`.
+pub(crate) struct NodeArc<T: ArenaAllocated> {
+    slot: NonNull<Slot<T>>,
+    _marker: PhantomData<T>,
+}
+
+/// Weak reference to a value in an arena, like `sync::Weak`.
+pub(crate) struct NodeWeak<T: ArenaAllocated> {
+    slot: NonNull<Slot<T>>,
+}
+
+unsafe impl<T: ArenaAllocated + Send + Sync> Send for NodeArc<T> {}
+unsafe impl<T: ArenaAllocated + Send + Sync> Sync for NodeArc<T> {}
+unsafe impl<T: ArenaAllocated + Send + Sync> Send for NodeWeak<T> {}
+unsafe impl<T: ArenaAllocated + Send + Sync> Sync for NodeWeak<T> {}
+
+impl<T: ArenaAllocated> NodeArc<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            slot: T::arena().allocate(value),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn downgrade(this: &Self) -> NodeWeak<T> {
+        this.slot().weak.fetch_add(1, Ordering::Relaxed);
+
+        NodeWeak { slot: this.slot }
+    }
+
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.slot().value.get() as *const T
+    }
+
+    fn slot(&self) -> &Slot<T> {
+        unsafe { self.slot.as_ref() }
+    }
+}
+
+impl<T: ArenaAllocated> Clone for NodeArc<T> {
+    fn clone(&self) -> Self {
+        self.slot().strong.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            slot: self.slot,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ArenaAllocated> Deref for NodeArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { (*self.slot().value.get()).assume_init_ref() }
+    }
+}
+
+impl<T: ArenaAllocated> Drop for NodeArc<T> {
+    fn drop(&mut self) {
+        if self.slot().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+
+        fence(Ordering::Acquire);
+
+        unsafe { (*self.slot().value.get()).assume_init_drop() };
+
+        release_weak(self.slot);
+    }
+}
+
+impl<T: ArenaAllocated> NodeWeak<T> {
+    pub fn upgrade(&self) -> Option<NodeArc<T>> {
+        let strong = &unsafe { self.slot.as_ref() }.strong;
+        let mut count = strong.load(Ordering::Relaxed);
+
+        loop {
+            if count == 0 {
+                return None;
+            }
+
+            match strong.compare_exchange_weak(count, count + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => {
+                    return Some(NodeArc {
+                        slot: self.slot,
+                        _marker: PhantomData,
+                    });
+                }
+                Err(current) => count = current,
+            }
+        }
+    }
+}
+
+impl<T: ArenaAllocated> Clone for NodeWeak<T> {
+    fn clone(&self) -> Self {
+        unsafe { self.slot.as_ref() }.weak.fetch_add(1, Ordering::Relaxed);
+
+        Self { slot: self.slot }
+    }
+}
+
+impl<T: ArenaAllocated> Drop for NodeWeak<T> {
+    fn drop(&mut self) {
+        release_weak(self.slot);
+    }
+}
+
+fn release_weak<T: ArenaAllocated>(slot: NonNull<Slot<T>>) {
+    if unsafe { slot.as_ref() }.weak.fetch_sub(1, Ordering::Release) == 1 {
+        fence(Ordering::Acquire);
+        T::arena().release(slot);
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, PoisonError},
+};
+
+use crate::watch::WatchEventKind;
+
+/// Latest change of a path, returned by [crate::memfs::MemFS::changes_since].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Absolute, normalized path.
+    pub path: String,
+    pub kind: WatchEventKind,
+
+    /// Sequence number of the change. Later changes have larger ones.
+    pub seq: u64,
+}
+
+#[derive(Default)]
+struct ChangeIndex {
+    last_seq: u64,
+    by_seq: BTreeMap<u64, (String, WatchEventKind)>,
+    by_path: HashMap<String, u64>,
+}
+
+/// Latest change of every path changed so far, indexed by sequence number so that the changes after
+/// a given one are found without looking at older ones.
+///
+/// Only the latest change of a path is kept, which bounds the log by the number of distinct paths ever changed.
+#[derive(Default)]
+pub(crate) struct ChangeLog {
+    index: Mutex<ChangeIndex>,
+}
+
+impl ChangeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a change of `path` and returns its sequence number. The first change gets 1.
+    pub fn record(&self, kind: WatchEventKind, path: &str) -> u64 {
+        let mut index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+
+        index.last_seq += 1;
+        let seq = index.last_seq;
+
+        if let Some(previous) = index.by_path.insert(path.to_string(), seq) {
+            index.by_seq.remove(&previous);
+        }
+
+        index.by_seq.insert(seq, (path.to_string(), kind));
+
+        seq
+    }
+
+    /// Sequence number of the latest change, or 0 if nothing changed yet.
+    pub fn last_seq(&self) -> u64 {
+        self.index
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_seq
+    }
+
+    /// Returns the latest change of every path changed after `seq`, oldest first.
+    pub fn since(&self, seq: u64) -> Vec<Change> {
+        let index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+
+        index
+            .by_seq
+            .range(seq.saturating_add(1)..)
+            .map(|(seq, (path, kind))| Change {
+                path: path.clone(),
+                kind: *kind,
+                seq: *seq,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long a thread waiting for a deadline of the virtual clock blocks before checking the time again.
+const VIRTUAL_POLL: Duration = Duration::from_millis(1);
+
+type Listener = Arc<dyn Fn() -> bool + Send + Sync>;
+
+thread_local! {
+    /// Clock of the file system running an operation on this thread, unless it is the system clock.
+    static CURRENT: RefCell<Option<Clock>> = const { RefCell::new(None) };
+}
+
+/// Time which only moves when it is advanced, to run scenarios independently of the wall clock.
+/// Set with [crate::memfs::MemFSBuilder::virtual_clock].
+///
+/// A file system on a virtual clock stamps its files with it, runs maintenance tasks when it passes
+/// their next run, measures timeouts with it, and advances it by injected latencies instead of sleeping.
+/// Clones share the same time.
+#[derive(Clone)]
+pub struct VirtualClock {
+    time: Arc<VirtualTime>,
+}
+
+struct VirtualTime {
+    /// Nanoseconds since the Unix epoch.
+    nanos: AtomicU64,
+
+    /// Called after each advance, until they return false.
+    listeners: Mutex<Vec<Listener>>,
+}
+
+impl VirtualClock {
+    /// Starts at `start`, or at the Unix epoch if `start` is before it.
+    pub fn new(start: SystemTime) -> Self {
+        let nanos = start.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64);
+
+        Self {
+            time: Arc::new(VirtualTime {
+                nanos: AtomicU64::new(nanos),
+                listeners: Mutex::default(),
+            }),
+        }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        UNIX_EPOCH + self.since_epoch()
+    }
+
+    /// Moves the time forward by `by`, then runs the maintenance tasks which became due on this thread.
+    /// A task due several times over is run once, as it would be by a late maintenance thread.
+    pub fn advance(&self, by: Duration) {
+        self.time.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+
+        let listeners = self.time.listeners.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        let finished = listeners.iter().filter(|listener| !listener()).collect::<Vec<_>>();
+
+        if !finished.is_empty() {
+            let mut listeners = self.time.listeners.lock().unwrap_or_else(PoisonError::into_inner);
+            listeners.retain(|listener| !finished.iter().any(|done| Arc::ptr_eq(listener, done)));
+        }
+    }
+
+    /// Calls `listener` after each advance, until it returns false.
+    pub(crate) fn on_advance(&self, listener: impl Fn() -> bool + Send + Sync + 'static) {
+        let mut listeners = self.time.listeners.lock().unwrap_or_else(PoisonError::into_inner);
+        listeners.push(Arc::new(listener));
+    }
+
+    fn since_epoch(&self) -> Duration {
+        Duration::from_nanos(self.time.nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl std::fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualClock").field("now", &self.now()).finish()
+    }
+}
+
+/// Clock of a file system: the system clock, or a virtual one.
+#[derive(Clone)]
+pub(crate) enum Clock {
+    System { origin: Instant },
+    Virtual(VirtualClock),
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::System { origin: Instant::now() }
+    }
+}
+
+impl Clock {
+    pub fn now(&self) -> SystemTime {
+        match self {
+            Clock::System { .. } => SystemTime::now(),
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+
+    /// Monotonic time since the clock started. The virtual clock counts it from the Unix epoch.
+    pub fn ticks(&self) -> Duration {
+        match self {
+            Clock::System { origin } => origin.elapsed(),
+            Clock::Virtual(clock) => clock.since_epoch(),
+        }
+    }
+
+    /// Sleeps for `duration`, or advances the virtual clock by it.
+    pub fn sleep(&self, duration: Duration) {
+        match self {
+            Clock::System { .. } => std::thread::sleep(duration),
+            Clock::Virtual(clock) => clock.advance(duration),
+        }
+    }
+
+    /// How long to block on a condition variable before checking for `deadline`, in [Clock::ticks], again.
+    /// Another thread advances the virtual clock, so waiting for it only polls.
+    pub fn wait_for(&self, deadline: Duration) -> Duration {
+        match self {
+            Clock::System { .. } => deadline.saturating_sub(self.ticks()),
+            Clock::Virtual(_) => VIRTUAL_POLL,
+        }
+    }
+
+    /// Makes [now] return the time of this clock on this thread, until the scope is dropped.
+    pub fn enter(&self) -> ClockScope {
+        let virtual_clock = matches!(self, Clock::Virtual(_));
+
+        CURRENT.with_borrow_mut(|current| {
+            // The system clock is the default, so nothing is set unless an operation on a virtual clock is running.
+            if !virtual_clock && current.is_none() {
+                return ClockScope { previous: None };
+            }
+
+            ClockScope {
+                previous: Some(std::mem::replace(current, virtual_clock.then(|| self.clone()))),
+            }
+        })
+    }
+}
+
+/// Restores the clock which was current before [Clock::enter].
+pub(crate) struct ClockScope {
+    previous: Option<Option<Clock>>,
+}
+
+impl Drop for ClockScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CURRENT.with_borrow_mut(|current| *current = previous);
+        }
+    }
+}
+
+/// Current time of the file system running an operation on this thread, as stamped on its files.
+pub(crate) fn now() -> SystemTime {
+    CURRENT.with_borrow(|current| current.as_ref().map_or_else(SystemTime::now, Clock::now))
+}
@@ -0,0 +1,131 @@
+use crate::page::Page;
+use crate::utils::PAGE_SIZE;
+
+/// Compression of file contents over the whole file system, returned by [crate::memfs::MemFS::compression_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Files whose contents are compressed.
+    pub files: usize,
+
+    /// Bytes the pages of those files would take uncompressed.
+    pub page_bytes: u64,
+
+    /// Bytes those pages take once compressed.
+    pub compressed_bytes: u64,
+}
+
+impl CompressionStats {
+    /// Uncompressed bytes per compressed byte, or 1 if nothing is compressed.
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            return 1.0;
+        }
+
+        self.page_bytes as f64 / self.compressed_bytes as f64
+    }
+
+    pub(crate) fn add(&mut self, pages: &CompressedPages) {
+        self.files += 1;
+        self.page_bytes += (pages.len() * PAGE_SIZE) as u64;
+        self.compressed_bytes += pages.compressed_len() as u64;
+    }
+}
+
+/// Pages of a file, each compressed on its own with LZ4, so that a read or write only decompresses the
+/// pages it spans. Pages of zeroes take nothing.
+#[derive(Clone, Default)]
+pub(crate) struct CompressedPages {
+    pages: Vec<Option<Box<[u8]>>>,
+}
+
+impl CompressedPages {
+    /// Returns page `index`, decompressed.
+    pub fn page(&self, index: usize) -> Page {
+        let mut page = [0; PAGE_SIZE];
+
+        if let Some(Some(compressed)) = self.pages.get(index) {
+            lz4_flex::block::decompress_into(compressed, &mut page).expect("pages are compressed by the file");
+        }
+
+        page
+    }
+
+    /// Replaces page `index` with `page`, compressed.
+    pub fn set_page(&mut self, index: usize, page: &Page) {
+        let compressed = page
+            .iter()
+            .any(|byte| *byte != 0)
+            .then(|| lz4_flex::block::compress(page).into_boxed_slice());
+
+        if index >= self.pages.len() {
+            if compressed.is_none() {
+                return;
+            }
+
+            self.pages.resize(index + 1, None);
+        }
+
+        self.pages[index] = compressed;
+    }
+
+    /// Copies the contents from `offset` into the whole of `buffer`.
+    pub fn read(&self, buffer: &mut [u8], offset: usize) {
+        Self::for_each_page(offset, buffer.len(), |index, within, done, len| {
+            buffer[done..done + len].copy_from_slice(&self.page(index)[within..within + len]);
+        });
+    }
+
+    /// Copies `data` into the contents at `offset`.
+    pub fn write(&mut self, data: &[u8], offset: usize) {
+        Self::for_each_page(offset, data.len(), |index, within, done, len| {
+            let mut page = self.page(index);
+
+            page[within..within + len].copy_from_slice(&data[done..done + len]);
+            self.set_page(index, &page);
+        });
+    }
+
+    /// Zeroes `start..end` of the contents.
+    pub fn zero(&mut self, start: usize, end: usize) {
+        Self::for_each_page(start, end.saturating_sub(start), |index, within, _, len| {
+            if matches!(self.pages.get(index), Some(Some(_))) {
+                let mut page = self.page(index);
+
+                page[within..within + len].fill(0);
+                self.set_page(index, &page);
+            }
+        });
+    }
+
+    /// Indices of the pages holding anything but zeroes.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, page)| page.as_ref().map(|_| index))
+    }
+
+    /// Number of pages holding anything but zeroes.
+    pub fn len(&self) -> usize {
+        self.indices().count()
+    }
+
+    pub fn compressed_len(&self) -> usize {
+        self.pages.iter().flatten().map(|page| page.len()).sum()
+    }
+
+    /// Runs `visit` on every page `offset..offset + len` spans, with the index of the page, the offset of the
+    /// range within the page, the number of bytes of the range visited before, and the length within the page.
+    fn for_each_page(offset: usize, len: usize, mut visit: impl FnMut(usize, usize, usize, usize)) {
+        let mut done = 0;
+
+        while done < len {
+            let position = offset + done;
+            let within = position % PAGE_SIZE;
+            let chunk = (PAGE_SIZE - within).min(len - done);
+
+            visit(position / PAGE_SIZE, within, done, chunk);
+            done += chunk;
+        }
+    }
+}
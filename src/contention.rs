@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::utils::FileType;
+
+/// Contention statistics of a single directory or file, returned by [crate::memfs::MemFS::hot_nodes].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeContention {
+    pub path: String,
+    pub file_type: FileType,
+
+    /// Number of times the children of a directory, or the contents of a file, were accessed.
+    pub acquisitions: u64,
+
+    /// Number of accesses which had to wait for, or collided with, another thread.
+    pub contended: u64,
+}
+
+/// Counters of a single directory or file, kept in the node so that they go away along with it.
+#[derive(Debug, Default)]
+pub(crate) struct ContentionCounters {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+}
+
+impl ContentionCounters {
+    pub fn record_acquisition(&self, contended: bool) {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+
+        if contended {
+            self.contended.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns acquisitions and contended acquisitions of the node, if it was ever accessed.
+    pub fn counters(&self) -> Option<(u64, u64)> {
+        let acquisitions = self.acquisitions.load(Ordering::Relaxed);
+
+        (acquisitions > 0).then(|| (acquisitions, self.contended.load(Ordering::Relaxed)))
+    }
+}
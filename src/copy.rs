@@ -0,0 +1,128 @@
+use crate::memfs::MemFS;
+use crate::utils::{FILE_MAX_SIZE, FileType, MemFSErr, OpenFlag, Result};
+
+/// How [MemFS::copy_with] and [MemFS::copy_recursive_with] duplicate the contents of files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CopyMode {
+    /// The copy gets contents of its own, written as the source is read.
+    #[default]
+    Deep,
+
+    /// The copy shares the contents of the source until either is written, as with [MemFS::reflink].
+    Reflink,
+}
+
+impl MemFS {
+    /// Creates a file at `target_path` with a copy of the contents of the file at `source_path`, following
+    /// symbolic links. Fails with EISDIR if the source is a directory, and EEXIST if `target_path` exists.
+    pub fn copy(&self, source_path: &str, target_path: &str) -> Result<()> {
+        self.copy_with(source_path, target_path, CopyMode::Deep)
+    }
+
+    /// Same as [MemFS::copy], where `mode` decides whether the contents are copied or shared.
+    pub fn copy_with(&self, source_path: &str, target_path: &str, mode: CopyMode) -> Result<()> {
+        match mode {
+            CopyMode::Deep => self.copy_contents(source_path, target_path),
+            CopyMode::Reflink => self.reflink(source_path, target_path),
+        }
+        .map_err(|err| err.with_context("copy", Some(source_path)))
+    }
+
+    /// Creates the directory `target_dir` with a copy of everything under the directory `source_dir`.
+    /// Files are copied as with [MemFS::copy], and symbolic links with the same target. A file with several
+    /// links gets a copy for each name. Copies have the permission bits and owner of new entries.
+    ///
+    /// The copy is not atomic, so entries changed meanwhile may be copied before or after the change.
+    /// It stops at the first entry which fails, keeping the ones copied before. Fails with ENOTDIR if
+    /// the source is not a directory, EEXIST if `target_dir` exists, and EINVAL if it would be inside the source.
+    pub fn copy_recursive(&self, source_dir: &str, target_dir: &str) -> Result<()> {
+        self.copy_recursive_with(source_dir, target_dir, CopyMode::Deep)
+    }
+
+    /// Same as [MemFS::copy_recursive], where `mode` decides whether the contents of files are copied or shared.
+    pub fn copy_recursive_with(&self, source_dir: &str, target_dir: &str, mode: CopyMode) -> Result<()> {
+        let copy = || {
+            let source = self.stat(source_dir)?;
+
+            if source.file_type != FileType::Directory {
+                return Err(MemFSErr::is_not_directory());
+            }
+
+            self.check_outside(source.ino, target_dir)?;
+            self.mkdir(target_dir)?;
+            self.copy_directory(source_dir, target_dir, mode)
+        };
+
+        copy().map_err(|err| err.with_context("copy_recursive", Some(source_dir)))
+    }
+
+    fn copy_directory(&self, source_dir: &str, target_dir: &str, mode: CopyMode) -> Result<()> {
+        for entry in self.readdir(source_dir)? {
+            let source_path = format!("{}/{}", source_dir.trim_end_matches('/'), entry.name);
+            let target_path = format!("{}/{}", target_dir.trim_end_matches('/'), entry.name);
+
+            match entry.file_type {
+                FileType::Directory => {
+                    self.mkdir(&target_path)?;
+                    self.copy_directory(&source_path, &target_path, mode)?;
+                }
+                FileType::File => self.copy_with(&source_path, &target_path, mode)?,
+                FileType::Symlink => self.symlink(&self.readlink(&source_path)?, &target_path)?,
+                FileType::CharDevice => {
+                    self.mknod(&target_path, self.device(&source_path)?, self.stat(&source_path)?.mode)?
+                }
+                FileType::Fifo => self.mkfifo(&target_path, self.stat(&source_path)?.mode)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn copy_contents(&self, source_path: &str, target_path: &str) -> Result<()> {
+        let source = self.open(source_path, OpenFlag::O_RDONLY)?;
+        let copied = self.fstat(source).and_then(|stat| {
+            if stat.file_type == FileType::Directory {
+                return Err(MemFSErr::is_directory());
+            }
+
+            let target = self.open(target_path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)?;
+            let copied = self.copy_descriptor(source, target);
+            self.close(target)?;
+
+            copied
+        });
+        self.close(source)?;
+
+        copied
+    }
+
+    fn copy_descriptor(&self, source: usize, target: usize) -> Result<()> {
+        while self.copy_file_range(source, None, target, None, FILE_MAX_SIZE)? > 0 {}
+
+        Ok(())
+    }
+
+    /// Fails with EINVAL if `path` is in the directory `ino` or under it, going up from the parent of `path`
+    /// until the root directory, which is its own parent.
+    fn check_outside(&self, ino: u64, path: &str) -> Result<()> {
+        let mut ancestor = match path.trim_end_matches('/').rsplit_once('/') {
+            Some(("", _)) => "/".to_string(),
+            Some((parent, _)) => parent.to_string(),
+            None => ".".to_string(),
+        };
+        let mut previous = None;
+
+        loop {
+            let ancestor_ino = self.stat(&ancestor)?.ino;
+
+            if ancestor_ino == ino {
+                return Err(MemFSErr::invalid_value());
+            } else if previous == Some(ancestor_ino) {
+                return Ok(());
+            }
+
+            previous = Some(ancestor_ino);
+            ancestor.push_str("/..");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// Decides which writes made after the last fsync of a file survive a simulated crash.
+/// Namespace changes (creation, unlink, mkdir, rmdir) are always durable, so a file created
+/// but never synced comes back empty, as on common journaling file systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrashModel {
+    /// Every write made after the last fsync is lost.
+    DropUnsynced,
+
+    /// Writes reach the storage in order, and a random number of them is lost from the end.
+    KeepPrefix { seed: u64 },
+
+    /// Each write independently survives or not, so later writes may survive while earlier ones are lost.
+    Reorder { seed: u64 },
+}
+
+/// Outcome of [crate::memfs::MemFS::crash].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrashReport {
+    /// Number of files which lost at least one write.
+    pub files_rolled_back: usize,
+    pub writes_kept: usize,
+    pub writes_dropped: usize,
+    pub descriptors_closed: usize,
+}
+
+/// Change of a file made by a single write or truncation.
+enum Pending {
+    /// Range of the file modified by a write.
+    Extent { offset: usize, data: Vec<u8> },
+
+    /// Size the file was shrunk to.
+    Shrink(usize),
+}
+
+struct TrackedFile<N> {
+    node: N,
+    durable: Vec<u8>,
+    pending: Vec<Pending>,
+}
+
+/// Writes which are not synced yet, tracked per file node.
+/// Files missing from the tracker have no pending writes.
+pub(crate) struct CrashTracker<N> {
+    files: Mutex<HashMap<usize, TrackedFile<N>>>,
+}
+
+impl<N: Clone> CrashTracker<N> {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::default(),
+        }
+    }
+
+    /// Runs a write or truncation of the file identified by `key`. `write` has to return the contents
+    /// of the file before the write, the result of the write and the contents after the write.
+    pub fn track_write<T>(&self, key: usize, node: &N, write: impl FnOnce() -> (Vec<u8>, T, Vec<u8>)) -> T {
+        let mut files = self.files.lock().unwrap();
+        let (before, result, after) = write();
+
+        let file = files.entry(key).or_insert_with(|| TrackedFile {
+            node: node.clone(),
+            durable: before.clone(),
+            pending: Vec::new(),
+        });
+
+        let common = before.len().min(after.len());
+        let differs = |i: &usize| before[*i] != after[*i];
+        let start = (0..common).find(differs).unwrap_or(common);
+        let end = if after.len() > before.len() {
+            after.len()
+        } else {
+            (0..common).rev().find(differs).map_or(start, |i| i + 1)
+        };
+
+        if start < end {
+            file.pending.push(Pending::Extent {
+                offset: start,
+                data: after[start..end].to_vec(),
+            });
+        }
+
+        if after.len() < before.len() {
+            file.pending.push(Pending::Shrink(after.len()));
+        }
+
+        result
+    }
+
+    /// Marks every write of the file as durable.
+    pub fn sync(&self, key: usize) {
+        self.files.lock().unwrap().remove(&key);
+    }
+
+    /// Forgets every pending write, and returns the contents each tracked file has after the crash.
+    pub fn crash(&self, model: CrashModel, report: &mut CrashReport) -> Vec<(N, Vec<u8>)> {
+        let mut files = std::mem::take(&mut *self.files.lock().unwrap());
+        let mut rng = match model {
+            CrashModel::DropUnsynced => None,
+            CrashModel::KeepPrefix { seed } | CrashModel::Reorder { seed } => Some(StdRng::seed_from_u64(seed)),
+        };
+
+        let mut keys: Vec<usize> = files.keys().copied().collect();
+        // Iterate in a fixed order, so that a seed always gives the same outcome.
+        keys.sort_unstable();
+
+        let mut restored = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let file = files.remove(&key).unwrap();
+            let total = file.pending.len();
+            let survives: Vec<bool> = match (model, rng.as_mut()) {
+                (CrashModel::KeepPrefix { .. }, Some(rng)) => {
+                    let kept = rng.random_range(0..=total);
+                    (0..total).map(|i| i < kept).collect()
+                }
+                (CrashModel::Reorder { .. }, Some(rng)) => (0..total).map(|_| rng.random_bool(0.5)).collect(),
+                _ => vec![false; total],
+            };
+
+            let mut contents = file.durable;
+
+            for (change, _) in file.pending.iter().zip(&survives).filter(|(_, s)| **s) {
+                match change {
+                    Pending::Extent { offset, data } => {
+                        let end = offset + data.len();
+
+                        if contents.len() < end {
+                            contents.resize(end, 0);
+                        }
+
+                        contents[*offset..end].copy_from_slice(data);
+                    }
+                    Pending::Shrink(size) => contents.truncate(*size),
+                }
+            }
+
+            let kept = survives.iter().filter(|s| **s).count();
+            report.writes_kept += kept;
+            report.writes_dropped += total - kept;
+
+            if kept < total {
+                report.files_rolled_back += 1;
+            }
+
+            restored.push((file.node, contents));
+        }
+
+        restored
+    }
+}
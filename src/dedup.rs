@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+/// Number of hashes below which the table is never swept.
+const MIN_SWEEP_THRESHOLD: usize = 1024;
+
+/// Deduplication of file contents over the whole file system, returned by [crate::memfs::MemFS::dedup_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Files whose contents are shared.
+    pub files: usize,
+
+    /// Distinct contents those files share.
+    pub contents: usize,
+
+    /// Bytes the contents of those files take once shared.
+    pub shared_bytes: u64,
+
+    /// Bytes those files would take on top of the shared contents, if each had its own copy.
+    pub saved_bytes: u64,
+}
+
+impl DedupStats {
+    /// Sums up the shared contents of every file, once per file.
+    pub(crate) fn of(contents: impl IntoIterator<Item = Arc<Vec<u8>>>) -> Self {
+        let mut stats = Self::default();
+        let mut seen = HashSet::new();
+
+        for contents in contents {
+            let len = contents.len() as u64;
+
+            stats.files += 1;
+
+            if seen.insert(Arc::as_ptr(&contents)) {
+                stats.contents += 1;
+                stats.shared_bytes += len;
+            } else {
+                stats.saved_bytes += len;
+            }
+        }
+
+        stats
+    }
+}
+
+/// Contents shared by files, found by the hash of their bytes.
+///
+/// The table does not keep contents alive. Hashes whose contents are all gone are swept once the table
+/// has doubled since the previous sweep, as the inode table does.
+pub(crate) struct DedupTable {
+    contents: Mutex<DedupMap>,
+}
+
+struct DedupMap {
+    hashes: HashMap<u64, Vec<Weak<Vec<u8>>>>,
+    sweep_threshold: usize,
+}
+
+impl Default for DedupTable {
+    fn default() -> Self {
+        Self {
+            contents: Mutex::new(DedupMap {
+                hashes: HashMap::new(),
+                sweep_threshold: MIN_SWEEP_THRESHOLD,
+            }),
+        }
+    }
+}
+
+impl DedupTable {
+    /// Returns shared contents equal to `contents` if there are any, or shares `contents` from now on.
+    pub fn intern(&self, contents: Arc<Vec<u8>>) -> Arc<Vec<u8>> {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut map = self.contents.lock().unwrap_or_else(PoisonError::into_inner);
+        let candidates = map.hashes.entry(hash).or_default();
+
+        candidates.retain(|candidate| candidate.strong_count() > 0);
+
+        if let Some(existing) = candidates
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|candidate| candidate == &contents)
+        {
+            return existing;
+        }
+
+        candidates.push(Arc::downgrade(&contents));
+
+        if map.hashes.len() >= map.sweep_threshold {
+            map.hashes.retain(|_, candidates| {
+                candidates.retain(|candidate| candidate.strong_count() > 0);
+                !candidates.is_empty()
+            });
+            map.sweep_threshold = (map.hashes.len() * 2).max(MIN_SWEEP_THRESHOLD);
+        }
+
+        contents
+    }
+}
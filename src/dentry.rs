@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+use crate::utils::Result;
+
+/// Counters of the dentry cache, returned by [crate::memfs::MemFS::dentry_cache_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DentryCacheStats {
+    /// Lookups answered from the cache.
+    pub hits: u64,
+
+    /// Lookups which walked the tree, whether their result was cached or not.
+    pub misses: u64,
+
+    /// Number of times the cache was emptied because an entry was removed, renamed or replaced.
+    pub invalidations: u64,
+
+    /// Paths currently cached.
+    pub entries: usize,
+}
+
+/// What a lookup of a path returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Resolution {
+    /// The node at the path, following a symbolic link at the last component.
+    Follow,
+
+    /// The node at the path itself.
+    NoFollow,
+
+    /// The directory holding the last component.
+    Parent,
+}
+
+/// Nodes found by lookups of absolute paths, so that repeated lookups skip the walk of the tree.
+///
+/// Creating entries never changes what an existing path resolves to, so only removals, renames
+/// and replacements invalidate the cache, which empties it as a whole. Failed lookups are not cached.
+pub(crate) struct DentryCache<N> {
+    entries: RwLock<HashMap<String, [Option<N>; 3]>>,
+    capacity: usize,
+
+    /// Bumped by every invalidation, so that a walk which overlapped one does not cache its stale result.
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<N: Clone> DentryCache<N> {
+    /// Creates a cache of at most `capacity` paths. Once full, an arbitrary path makes room for a new one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: RwLock::default(),
+            capacity: capacity.max(1),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the cached node of `path`, or the one `walk` finds, caching it.
+    pub fn lookup(&self, path: &str, resolution: Resolution, walk: impl FnOnce() -> Result<N>) -> Result<N> {
+        let cached = self
+            .entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+            .and_then(|slots| slots[resolution as usize].clone());
+
+        if let Some(node) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(node);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let node = walk()?;
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+
+        if self.generation.load(Ordering::Acquire) == generation {
+            if entries.len() >= self.capacity
+                && !entries.contains_key(path)
+                && let Some(evicted) = entries.keys().next().cloned()
+            {
+                entries.remove(&evicted);
+            }
+
+            entries.entry(path.to_string()).or_default()[resolution as usize] = Some(node.clone());
+        }
+
+        Ok(node)
+    }
+
+    /// Forgets every cached path. Called once a removal, rename or replacement is done.
+    pub fn invalidate(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+        entries.clear();
+    }
+
+    pub fn stats(&self) -> DentryCacheStats {
+        DentryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.entries.read().unwrap_or_else(PoisonError::into_inner).len(),
+        }
+    }
+}
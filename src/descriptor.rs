@@ -0,0 +1,129 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crossbeam::utils::CachePadded;
+
+thread_local! {
+    /// Shard the current thread takes descriptor numbers from first.
+    static HOME_SHARD: usize = NEXT_HOME_SHARD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Home shard of the next thread to open a file, so that threads spread over the shards in turn.
+static NEXT_HOME_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+/// Hands out file descriptor numbers. With a single shard, it is always the lowest one which is not in use,
+/// as POSIX requires.
+///
+/// With more shards, numbers are split between them by their remainder, as in [DescriptorShards], and each
+/// thread takes the lowest free number of a shard of its own, so that threads opening and closing files
+/// at once do not wait for each other. A lower number of another shard may be free then. A thread whose shard
+/// has no number left below the limit takes one from the other shards in turn.
+///
+/// Numbers of a shard below its `next` which were released are kept in a min-heap, so that both allocating
+/// and releasing take logarithmic time in the number of released descriptors.
+pub(crate) struct DescriptorNumbers {
+    shards: Box<[CachePadded<Mutex<NumberState>>]>,
+
+    /// Numbers handed out are below it, as with RLIMIT_NOFILE.
+    limit: usize,
+}
+
+#[derive(Default)]
+struct NumberState {
+    /// Number of numbers of the shard handed out so far, the released ones included.
+    next: usize,
+    released: BinaryHeap<Reverse<usize>>,
+}
+
+impl DescriptorNumbers {
+    /// Creates `shards` shards of numbers, and a single one if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        Self::with_limit(shards, usize::MAX)
+    }
+
+    pub fn with_limit(shards: usize, limit: usize) -> Self {
+        Self {
+            shards: std::iter::repeat_with(CachePadded::default).take(shards.max(1)).collect(),
+            limit,
+        }
+    }
+
+    /// Hands out the lowest number which is not in use, of the shard of the calling thread if there are
+    /// several, or None if every number below the limit is in use.
+    pub fn allocate(&self) -> Option<usize> {
+        let count = self.shards.len();
+        let home = HOME_SHARD.with(|shard| *shard);
+
+        (0..count).find_map(|i| self.allocate_in((home + i) % count))
+    }
+
+    fn allocate_in(&self, shard: usize) -> Option<usize> {
+        let mut state = self.lock(shard);
+
+        match state.released.pop() {
+            Some(Reverse(fd)) => Some(fd),
+            None => {
+                let fd = state.next.checked_mul(self.shards.len())?.checked_add(shard)?;
+
+                (fd < self.limit).then(|| {
+                    state.next += 1;
+                    fd
+                })
+            }
+        }
+    }
+
+    /// Makes `fd` available again. It must have been handed out by [DescriptorNumbers::allocate], and not released since.
+    pub fn release(&self, fd: usize) {
+        let mut state = self.lock(fd % self.shards.len());
+
+        state.released.push(Reverse(fd));
+
+        // Every descriptor of the shard is closed. Start over, so that the heap does not keep the numbers
+        // of a past burst of opens.
+        if state.released.len() == state.next {
+            *state = NumberState::default();
+        }
+    }
+
+    /// Makes every number available again, after every descriptor was closed at once.
+    pub fn reset(&self) {
+        for shard in 0..self.shards.len() {
+            *self.lock(shard) = NumberState::default();
+        }
+    }
+
+    fn lock(&self, shard: usize) -> MutexGuard<'_, NumberState> {
+        self.shards[shard].lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// File descriptor table split in shards by descriptor number modulo the number of shards,
+/// so that threads working on different descriptors rarely go through the same lock or map.
+pub(crate) struct DescriptorShards<T> {
+    shards: Box<[T]>,
+}
+
+impl<T> DescriptorShards<T> {
+    /// Creates `count` shards with `new_shard`, and a single one if `count` is zero.
+    pub fn new(count: usize, new_shard: impl FnMut() -> T) -> Self {
+        Self {
+            shards: std::iter::repeat_with(new_shard).take(count.max(1)).collect(),
+        }
+    }
+
+    /// Returns the shard holding `fd`.
+    pub fn shard(&self, fd: usize) -> &T {
+        &self.shards[fd % self.shards.len()]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.shards.iter()
+    }
+}
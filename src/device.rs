@@ -0,0 +1,50 @@
+use rand::RngCore;
+
+/// Permission bits of the devices created by [crate::memfs::MemFS::create_standard_devices], as on Linux.
+pub const DEFAULT_DEVICE_MODE: u32 = 0o666;
+
+/// Behavior of a character device created with [crate::memfs::MemFS::mknod]. Devices store nothing:
+/// reads and writes through their descriptors are served by the behavior, and their size is always zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Device {
+    /// Reads find the end of the file at once, and writes are discarded, as with `/dev/null`.
+    Null,
+
+    /// Reads fill the buffer with zeroes, and writes are discarded, as with `/dev/zero`.
+    Zero,
+
+    /// Reads fill the buffer with random bytes, and writes are discarded, as with `/dev/urandom`.
+    /// Reads never block, so it also serves as `/dev/random`.
+    Random,
+}
+
+impl Device {
+    /// Devices created by [crate::memfs::MemFS::create_standard_devices], with their paths.
+    pub const STANDARD: [(&'static str, Device); 4] = [
+        ("/dev/null", Device::Null),
+        ("/dev/zero", Device::Zero),
+        ("/dev/random", Device::Random),
+        ("/dev/urandom", Device::Random),
+    ];
+
+    /// Reads into `buffer`, and returns the number of bytes read.
+    pub fn read(&self, buffer: &mut [u8]) -> usize {
+        match self {
+            Device::Null => 0,
+            Device::Zero => {
+                buffer.fill(0);
+                buffer.len()
+            }
+            Device::Random => {
+                rand::rng().fill_bytes(buffer);
+                buffer.len()
+            }
+        }
+    }
+
+    /// Takes `data`, and returns the number of bytes written, which is all of them.
+    pub fn write(&self, data: &[u8]) -> usize {
+        data.len()
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::utils::Stat;
+
+/// File made durable by [crate::memfs::MemFS::fsync], [crate::memfs::MemFS::fdatasync] or
+/// [crate::memfs::MemFS::sync], as handed to a [DurabilityBackend].
+pub struct SyncedFile<'a> {
+    /// Absolute path the file was opened with, or found at by sync.
+    pub path: &'a str,
+    pub stat: &'a Stat,
+    pub contents: &'a [u8],
+
+    /// Set by fdatasync, which only needs the contents of the file to be durable, not its metadata.
+    pub data_only: bool,
+}
+
+/// Persistence strategy layered under MemFS, set with [crate::memfs::MemFSBuilder::durability].
+///
+/// Without one, syncing a file only checks the descriptor. An error of the backend fails the call
+/// which synced the file, as a failing disk would.
+pub trait DurabilityBackend: Send + Sync {
+    fn sync_file(&self, file: &SyncedFile) -> io::Result<()>;
+
+    /// Called by [crate::memfs::MemFS::sync] once every file was handed to [DurabilityBackend::sync_file].
+    fn sync_all(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes every synced file at the same path under a directory of the host, creating its parents.
+pub struct HostMirror {
+    root: PathBuf,
+}
+
+impl HostMirror {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl DurabilityBackend for HostMirror {
+    fn sync_file(&self, file: &SyncedFile) -> io::Result<()> {
+        let target = self.root.join(file.path.trim_start_matches('/'));
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(target, file.contents)
+    }
+}
+
+/// Appends every synced file to a log: a line with the path and the size of the file, separated by a tab,
+/// followed by its contents. The writer is flushed by [crate::memfs::MemFS::sync].
+pub struct SyncLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl SyncLog {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+}
+
+impl DurabilityBackend for SyncLog {
+    fn sync_file(&self, file: &SyncedFile) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+        writeln!(writer, "{}\t{}", file.path, file.contents.len())?;
+        writer.write_all(file.contents)
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner).flush()
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::BTreeMap,
+    ops::Bound::{Excluded, Unbounded},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// What a MemFS does when a write finds its block store full, set with [crate::memfs::MemFSBuilder::eviction].
+///
+/// Unless disabled, the least recently used files which no descriptor has open are removed, as by
+/// [crate::memfs::MemFS::unlink], one after another until the write fits, so that the file system acts
+/// as a cache bounded by its block store. A file is used when it is opened, read or written through
+/// a descriptor. Files put in the tree without a descriptor, as by [crate::memfs::MemFS::restore], and not
+/// used since are the first to go. While eviction is enabled, every use of a file takes a lock shared by
+/// the whole file system, which keeps the files in the order they were used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Eviction {
+    /// Writes fail with ENOMEM.
+    #[default]
+    Disabled,
+
+    /// Files are removed, and their contents lost.
+    Drop,
+
+    /// Files are handed to the durability backend first, as [crate::memfs::MemFS::fsync] does, so that
+    /// their contents can be found there. A file the backend fails to take is kept.
+    Spill,
+}
+
+impl Eviction {
+    pub fn is_enabled(self) -> bool {
+        self != Self::Disabled
+    }
+}
+
+/// Number of entries below which the use order is never swept.
+const MIN_SWEEP_THRESHOLD: usize = 1024;
+
+/// Files which [Eviction] may remove, from the least recently used, so that finding the next one
+/// does not walk the tree.
+///
+/// Entries are keyed by the stamp of the use and the inode number of the file, and hold a weak handle
+/// of the file with the path it was used through. Files found in the tree and never used since are
+/// entered with stamp 0, which makes them the first to go. A later use of a file replaces its entry.
+/// Entries of dropped files are not removed when the file goes away. They are swept once the order has
+/// doubled since the previous sweep, as with the inode table.
+pub(crate) struct UseOrder<W> {
+    state: Mutex<UseOrderState<W>>,
+}
+
+struct UseOrderState<W> {
+    /// Stamp of the latest use.
+    stamp: u64,
+    files: BTreeMap<(u64, u64), (W, String)>,
+    sweep_threshold: usize,
+}
+
+impl<W: Clone> UseOrder<W> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(UseOrderState {
+                stamp: 0,
+                files: BTreeMap::new(),
+                sweep_threshold: MIN_SWEEP_THRESHOLD,
+            }),
+        }
+    }
+
+    /// Moves the file `ino`, last stamped `previous`, to the most recently used end, and hands the new
+    /// stamp to `stamp` before any other use is ordered. Returns true when the order should be swept
+    /// with [UseOrder::sweep].
+    pub fn touch(&self, ino: u64, previous: u64, file: W, path: &str, stamp: impl FnOnce(u64)) -> bool {
+        let mut state = self.lock();
+
+        let path = match state.files.remove(&(previous, ino)) {
+            Some((_, used)) if used == path => used,
+            _ => path.to_string(),
+        };
+
+        state.stamp += 1;
+        let next = state.stamp;
+        state.files.insert((next, ino), (file, path));
+        stamp(next);
+
+        state.files.len() >= state.sweep_threshold
+    }
+
+    /// Enters the file `ino`, found at `path`, as never used, unless it is there already.
+    pub fn enter_unused(&self, ino: u64, file: W, path: String) {
+        self.lock().files.entry((0, ino)).or_insert((file, path));
+    }
+
+    /// Returns the least recently used entry after `key`, or the first one without `key`.
+    pub fn next_after(&self, key: Option<(u64, u64)>) -> Option<((u64, u64), W, String)> {
+        let state = self.lock();
+        let mut entries = match key {
+            Some(key) => state.files.range((Excluded(key), Unbounded)),
+            None => state.files.range(..),
+        };
+
+        entries.next().map(|(key, (file, path))| (*key, file.clone(), path.clone()))
+    }
+
+    pub fn remove(&self, key: (u64, u64)) {
+        self.lock().files.remove(&key);
+    }
+
+    /// Drops the entries whose file is gone, as told by `is_alive`.
+    pub fn sweep(&self, is_alive: impl Fn(&W) -> bool) {
+        let mut state = self.lock();
+
+        state.files.retain(|_, (file, _)| is_alive(file));
+        state.sweep_threshold = (state.files.len() * 2).max(MIN_SWEEP_THRESHOLD);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UseOrderState<W>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    sync::{
+        Condvar, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::clock::Clock;
+use crate::utils::{MemFSErr, Result};
+
+thread_local! {
+    /// Gates the current thread is inside of, with whether it holds them exclusively rather than running
+    /// an operation.
+    static ENTERED: RefCell<Vec<(usize, bool)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Gate which every operation passes through, so that a single thread can hold the whole file system.
+/// A file system has one only if it was built with [crate::memfs::MemFSBuilder::exclusive_lock].
+///
+/// Entering the gate is a pair of atomic operations while it is not held exclusively. A thread which is
+/// inside the gate already, because it holds the gate or runs an operation which calls another one,
+/// passes through without waiting, so that it never waits for itself.
+pub(crate) struct ExclusiveGate {
+    locked: AtomicBool,
+    active: AtomicUsize,
+    lock: Mutex<()>,
+    unlocked: Condvar,
+    drained: Condvar,
+}
+
+/// Marks an operation in progress. Leaves the gate on drop.
+pub(crate) struct OperationGuard<'a> {
+    gate: Option<&'a ExclusiveGate>,
+}
+
+/// Holds every other operation of a file system off until it is dropped, returned by
+/// [crate::memfs::MemFS::lock_exclusive]. The thread holding it can keep using the file system.
+pub struct ExclusiveGuard<'a> {
+    gate: &'a ExclusiveGate,
+
+    /// Released by the thread which acquired it, which let its operations through.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ExclusiveGate {
+    pub fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            active: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            unlocked: Condvar::new(),
+            drained: Condvar::new(),
+        }
+    }
+
+    pub fn enter(&self) -> OperationGuard<'_> {
+        if self.is_entered() {
+            return OperationGuard { gate: None };
+        }
+
+        self.wait_and_enter();
+
+        OperationGuard { gate: Some(self) }
+    }
+
+    /// Runs `f` outside the gate, for an operation which waits on another thread, so that the wait never
+    /// holds off [ExclusiveGate::acquire]. An operation in progress leaves the gate first and enters it again
+    /// afterwards, waiting while another thread holds it. A thread holding the gate exclusively keeps it.
+    pub fn outside<T>(&self, f: impl FnOnce() -> T) -> T {
+        let key = self.key();
+        let in_operation = ENTERED.with_borrow(|entered| entered.contains(&(key, false)));
+
+        if !in_operation {
+            return f();
+        }
+
+        self.exit();
+        self.leave();
+
+        let result = f();
+        self.wait_and_enter();
+
+        result
+    }
+
+    fn wait_and_enter(&self) {
+        loop {
+            self.active.fetch_add(1, Ordering::SeqCst);
+
+            if !self.locked.load(Ordering::SeqCst) {
+                ENTERED.with_borrow_mut(|entered| entered.push((self.key(), false)));
+                return;
+            }
+
+            self.leave();
+
+            let mut guard = self.lock();
+
+            while self.locked.load(Ordering::SeqCst) {
+                guard = self
+                    .unlocked
+                    .wait(guard)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+    }
+
+    fn leave(&self) {
+        if self.active.fetch_sub(1, Ordering::SeqCst) == 1 && self.locked.load(Ordering::SeqCst) {
+            let _guard = self.lock();
+            self.drained.notify_all();
+        }
+    }
+
+    /// Waits until no other thread holds the gate, closes it, and waits until every operation in progress
+    /// finishes. Gives up and opens the gate again with EAGAIN once `timeout` passes on `clock`.
+    /// Fails with EBUSY if the calling thread is inside the gate already, as it would wait for itself.
+    pub fn acquire(&self, timeout: Option<Duration>, clock: &Clock) -> Result<ExclusiveGuard<'_>> {
+        if self.is_entered() {
+            return Err(MemFSErr::busy());
+        }
+
+        let deadline = timeout.map(|t| clock.ticks() + t);
+        let mut guard = self.lock();
+
+        while self.locked.load(Ordering::SeqCst) {
+            guard = match deadline {
+                Some(deadline) if clock.ticks() >= deadline => return Err(MemFSErr::try_again()),
+                Some(deadline) => {
+                    self.unlocked
+                        .wait_timeout(guard, clock.wait_for(deadline))
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .unlocked
+                    .wait(guard)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+
+        self.locked.store(true, Ordering::SeqCst);
+
+        while self.active.load(Ordering::SeqCst) > 0 {
+            if deadline.is_some_and(|deadline| clock.ticks() >= deadline) {
+                self.locked.store(false, Ordering::SeqCst);
+                self.unlocked.notify_all();
+
+                return Err(MemFSErr::try_again());
+            }
+
+            guard = self
+                .drained
+                .wait_timeout(guard, Duration::from_millis(1))
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        ENTERED.with_borrow_mut(|entered| entered.push((self.key(), true)));
+
+        Ok(ExclusiveGuard {
+            gate: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    fn release(&self) {
+        let _guard = self.lock();
+
+        self.locked.store(false, Ordering::SeqCst);
+        self.unlocked.notify_all();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn is_entered(&self) -> bool {
+        ENTERED.with_borrow(|entered| entered.iter().any(|(key, _)| *key == self.key()))
+    }
+
+    fn exit(&self) {
+        ENTERED.with_borrow_mut(|entered| {
+            if let Some(position) = entered.iter().rposition(|(key, _)| *key == self.key()) {
+                entered.swap_remove(position);
+            }
+        });
+    }
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(gate) = self.gate {
+            gate.exit();
+            gate.leave();
+        }
+    }
+}
+
+impl Drop for ExclusiveGuard<'_> {
+    fn drop(&mut self) {
+        self.gate.exit();
+        self.gate.release();
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::sync::{
+    Condvar, Mutex, PoisonError,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::utils::{MemFSErr, Result};
+
+/// Operation of [crate::memfs::MemFS::flock].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockOp {
+    /// Lets other descriptors take shared locks too, but no exclusive lock.
+    Shared,
+
+    /// Excludes every lock of other descriptors.
+    Exclusive,
+
+    /// Drops the lock of the descriptor, if any.
+    Unlock,
+}
+
+/// Identifies the descriptor holding a lock. Numbers of descriptors are reused, and snapshot views
+/// number theirs independently, so every descriptor gets a fresh owner instead.
+pub(crate) fn next_owner() -> u64 {
+    static NEXT_OWNER: AtomicU64 = AtomicU64::new(0);
+
+    NEXT_OWNER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Default)]
+struct LockState {
+    shared: HashSet<u64>,
+    exclusive: Option<u64>,
+}
+
+impl LockState {
+    fn conflicts(&self, owner: u64, op: LockOp) -> bool {
+        match op {
+            LockOp::Shared => self.exclusive.is_some_and(|holder| holder != owner),
+            LockOp::Exclusive => {
+                self.exclusive.is_some_and(|holder| holder != owner)
+                    || self.shared.iter().any(|&holder| holder != owner)
+            }
+            LockOp::Unlock => false,
+        }
+    }
+
+    fn release(&mut self, owner: u64) -> bool {
+        let was_exclusive = self.exclusive.take_if(|holder| *holder == owner).is_some();
+
+        self.shared.remove(&owner) || was_exclusive
+    }
+}
+
+/// Advisory whole-file lock of a file, held by descriptors.
+#[derive(Default)]
+pub(crate) struct FileLock {
+    state: Mutex<LockState>,
+    released: Condvar,
+}
+
+impl FileLock {
+    /// Applies `op` for `owner`. A lock held already is converted: it is dropped first, so that two
+    /// holders of shared locks upgrading at once do not wait for each other forever.
+    /// Without `wait`, fails with EAGAIN instead of waiting for a conflicting lock to go away.
+    pub fn apply(&self, owner: u64, op: LockOp, wait: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.release(owner) {
+            self.released.notify_all();
+        }
+
+        while state.conflicts(owner, op) {
+            if !wait {
+                return Err(MemFSErr::try_again());
+            }
+
+            state = self.released.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        match op {
+            LockOp::Shared => {
+                state.shared.insert(owner);
+            }
+            LockOp::Exclusive => state.exclusive = Some(owner),
+            LockOp::Unlock => {}
+        }
+
+        Ok(())
+    }
+
+    /// Drops the lock of `owner`, if any, as when its descriptor is closed.
+    pub fn release(&self, owner: u64) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.release(owner) {
+            self.released.notify_all();
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::{
+    Condvar, Mutex,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+use crate::utils::{MemFSErr, Result};
+
+/// Behavior of mutating operations while the file system is frozen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreezeMode {
+    /// Mutating operations wait until the file system is thawed.
+    Block,
+
+    /// Mutating operations fail immediately with EAGAIN.
+    Fail,
+}
+
+/// Gate which every mutating operation passes through.
+///
+/// Entering the gate is a pair of atomic operations while the file system is not frozen,
+/// so unfrozen file systems are not slowed down by it.
+pub(crate) struct FreezeGate {
+    frozen: AtomicBool,
+    fail_when_frozen: AtomicBool,
+    active: AtomicUsize,
+    lock: Mutex<()>,
+    thawed: Condvar,
+    drained: Condvar,
+}
+
+/// Marks a mutating operation in progress. Leaves the gate on drop.
+pub(crate) struct MutationGuard<'a> {
+    gate: &'a FreezeGate,
+}
+
+impl FreezeGate {
+    pub fn new() -> Self {
+        Self {
+            frozen: AtomicBool::new(false),
+            fail_when_frozen: AtomicBool::new(false),
+            active: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            thawed: Condvar::new(),
+            drained: Condvar::new(),
+        }
+    }
+
+    pub fn enter(&self) -> Result<MutationGuard<'_>> {
+        loop {
+            self.active.fetch_add(1, Ordering::SeqCst);
+
+            if !self.frozen.load(Ordering::SeqCst) {
+                return Ok(MutationGuard { gate: self });
+            }
+
+            self.leave();
+
+            if self.fail_when_frozen.load(Ordering::SeqCst) {
+                return Err(MemFSErr::try_again());
+            }
+
+            let mut guard = self.lock.lock().map_err(|_| MemFSErr::poisoned_lock())?;
+
+            while self.frozen.load(Ordering::SeqCst) {
+                guard = self
+                    .thawed
+                    .wait(guard)
+                    .map_err(|_| MemFSErr::poisoned_lock())?;
+            }
+        }
+    }
+
+    fn leave(&self) {
+        if self.active.fetch_sub(1, Ordering::SeqCst) == 1 && self.frozen.load(Ordering::SeqCst) {
+            let _guard = self.lock.lock();
+            self.drained.notify_all();
+        }
+    }
+
+    /// Freezes the gate and waits until every mutation in progress finishes.
+    pub fn freeze(&self, mode: FreezeMode) -> Result<()> {
+        let mut guard = self.lock.lock().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if self.frozen.load(Ordering::SeqCst) {
+            return Err(MemFSErr::busy());
+        }
+
+        self.fail_when_frozen
+            .store(mode == FreezeMode::Fail, Ordering::SeqCst);
+        self.frozen.store(true, Ordering::SeqCst);
+
+        while self.active.load(Ordering::SeqCst) > 0 {
+            guard = self
+                .drained
+                .wait_timeout(guard, Duration::from_millis(1))
+                .map_err(|_| MemFSErr::poisoned_lock())?
+                .0;
+        }
+
+        Ok(())
+    }
+
+    pub fn thaw(&self) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if !self.frozen.swap(false, Ordering::SeqCst) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        self.thawed.notify_all();
+
+        Ok(())
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MutationGuard<'_> {
+    fn drop(&mut self) {
+        self.gate.leave();
+    }
+}
@@ -0,0 +1,102 @@
+use std::hash::{BuildHasher, DefaultHasher, Hasher, RandomState};
+
+/// Hash function of directory maps and the file descriptor table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemFSHasher {
+    /// SipHash with random keys, resistant to collision attacks with crafted file names.
+    #[default]
+    Random,
+
+    /// SipHash with fixed keys, so that iteration orders are the same on every run.
+    Deterministic,
+
+    /// FxHash, as used by rustc. Fast and deterministic, but easy to flood with collisions.
+    Fx,
+}
+
+/// [BuildHasher] chosen by a [MemFSHasher].
+#[derive(Clone, Debug)]
+pub(crate) enum HashState {
+    Random(RandomState),
+    Deterministic,
+    Fx,
+}
+
+impl Default for HashState {
+    fn default() -> Self {
+        Self::new(MemFSHasher::default())
+    }
+}
+
+impl HashState {
+    pub fn new(hasher: MemFSHasher) -> Self {
+        match hasher {
+            MemFSHasher::Random => Self::Random(RandomState::new()),
+            MemFSHasher::Deterministic => Self::Deterministic,
+            MemFSHasher::Fx => Self::Fx,
+        }
+    }
+}
+
+impl BuildHasher for HashState {
+    type Hasher = StateHasher;
+
+    fn build_hasher(&self) -> StateHasher {
+        match self {
+            HashState::Random(state) => StateHasher::Sip(state.build_hasher()),
+            HashState::Deterministic => StateHasher::Sip(DefaultHasher::new()),
+            HashState::Fx => StateHasher::Fx(0),
+        }
+    }
+}
+
+pub(crate) enum StateHasher {
+    Sip(DefaultHasher),
+    Fx(u64),
+}
+
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+fn fx_add(hash: u64, word: u64) -> u64 {
+    (hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED)
+}
+
+impl Hasher for StateHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            StateHasher::Sip(hasher) => hasher.write(bytes),
+            StateHasher::Fx(hash) => {
+                let mut chunks = bytes.chunks_exact(8);
+
+                for chunk in &mut chunks {
+                    *hash = fx_add(*hash, u64::from_le_bytes(chunk.try_into().unwrap()));
+                }
+
+                for byte in chunks.remainder() {
+                    *hash = fx_add(*hash, *byte as u64);
+                }
+            }
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        match self {
+            StateHasher::Sip(hasher) => hasher.write_u8(i),
+            StateHasher::Fx(hash) => *hash = fx_add(*hash, i as u64),
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        match self {
+            StateHasher::Sip(hasher) => hasher.write_usize(i),
+            StateHasher::Fx(hash) => *hash = fx_add(*hash, i as u64),
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        match self {
+            StateHasher::Sip(hasher) => hasher.finish(),
+            StateHasher::Fx(hash) => *hash,
+        }
+    }
+}
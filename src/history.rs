@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::timestamp;
+
+/// Previous version of a file, as listed by [crate::memfs::MemFS::versions].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileVersion {
+    /// Number to open the version with [crate::memfs::MemFS::open_version]. Numbers of a file start at 1
+    /// and grow with every saved version.
+    pub number: u64,
+
+    /// Size of the file when the version was saved.
+    pub size: usize,
+
+    /// Time the version was saved.
+    pub saved: SystemTime,
+}
+
+/// When versions of files are saved, and how many of them are kept.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct VersionPolicy {
+    pub on_write: bool,
+    pub limit: Option<usize>,
+}
+
+struct SavedVersion {
+    info: FileVersion,
+
+    /// Generation of the file the contents were taken at.
+    generation: u64,
+    contents: Arc<Vec<u8>>,
+}
+
+/// Versions saved of a single file, oldest first.
+pub(crate) struct VersionHistory {
+    policy: VersionPolicy,
+    versions: Mutex<(u64, VecDeque<SavedVersion>)>,
+}
+
+impl VersionHistory {
+    pub fn new(policy: VersionPolicy) -> Self {
+        Self {
+            policy,
+            versions: Mutex::new((1, VecDeque::new())),
+        }
+    }
+
+    pub fn policy(&self) -> VersionPolicy {
+        self.policy
+    }
+
+    pub fn saves_on_write(&self) -> bool {
+        self.policy.on_write
+    }
+
+    /// Saves `contents`, taken at `generation` of the file, as a new version and returns its number.
+    /// Returns the number of the latest version instead if it was taken at the same generation.
+    /// The oldest versions are dropped past the limit of the policy.
+    pub fn save(&self, generation: u64, contents: Arc<Vec<u8>>) -> u64 {
+        let mut guard = self.versions.lock().unwrap_or_else(PoisonError::into_inner);
+        let (next, versions) = &mut *guard;
+
+        if let Some(latest) = versions.back()
+            && latest.generation == generation
+        {
+            return latest.info.number;
+        }
+
+        let number = *next;
+        *next += 1;
+
+        versions.push_back(SavedVersion {
+            info: FileVersion {
+                number,
+                size: contents.len(),
+                saved: timestamp::now(),
+            },
+            generation,
+            contents,
+        });
+
+        if let Some(limit) = self.policy.limit {
+            while versions.len() > limit {
+                versions.pop_front();
+            }
+        }
+
+        number
+    }
+
+    pub fn list(&self) -> Vec<FileVersion> {
+        let guard = self.versions.lock().unwrap_or_else(PoisonError::into_inner);
+
+        guard.1.iter().map(|version| version.info).collect()
+    }
+
+    pub fn get(&self, number: u64) -> Option<Arc<Vec<u8>>> {
+        let guard = self.versions.lock().unwrap_or_else(PoisonError::into_inner);
+
+        guard
+            .1
+            .iter()
+            .find(|version| version.info.number == number)
+            .map(|version| version.contents.clone())
+    }
+}
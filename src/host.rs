@@ -0,0 +1,199 @@
+use std::{fs, path::Path};
+
+use crate::memfs::MemFS;
+use crate::utils::{FILE_MAX_SIZE, FileType, MemFSErr, OpenFlag, Result};
+
+/// What [MemFS::dump_to_disk_with] does with an entry whose name is already taken on the host.
+/// A directory taken by a directory is always merged with it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionMode {
+    /// The entry is reported as failed with EEXIST, and the host entry is left as it is.
+    #[default]
+    Fail,
+
+    /// The entry is not written, and is counted as skipped.
+    Skip,
+
+    /// Host files and symbolic links are replaced. Host directories are never removed,
+    /// so the entry is reported as failed with EISDIR instead.
+    Overwrite,
+}
+
+/// Outcome of [MemFS::dump_to_disk].
+#[derive(Clone, Debug, Default)]
+pub struct DumpReport {
+    /// Written directories, including the ones merged with a host directory.
+    pub directories: usize,
+    pub files: usize,
+    pub symlinks: usize,
+
+    /// Entries not written because of [CollisionMode::Skip], and devices and FIFOs, which are never written.
+    pub skipped: usize,
+
+    /// Path and error of every entry which could not be written. Entries under a failed directory are not tried.
+    pub failed: Vec<(String, MemFSErr)>,
+}
+
+impl MemFS {
+    /// Mirrors the directory `host_path` of the host file system into the root directory, creating
+    /// directories and copying the contents of files. Symbolic links are copied with the same target,
+    /// and other kinds of entries are skipped.
+    ///
+    /// Fails with EEXIST if an entry already exists here, with EFBIG on a file larger than [FILE_MAX_SIZE],
+    /// and with EINVAL on a name which is not valid UTF-8. Entries copied before the failure are kept.
+    pub fn load_from_disk(&self, host_path: impl AsRef<Path>) -> Result<()> {
+        self.load_directory(host_path.as_ref(), "")
+    }
+
+    /// Writes every directory, file and symbolic link under the root directory into the directory `host_path`
+    /// of the host file system, creating it if needed. A file with several links is written once per name.
+    ///
+    /// An entry which cannot be written does not stop the dump, and is reported in [DumpReport::failed].
+    /// Names already taken on the host are reported with EEXIST; see [MemFS::dump_to_disk_with] to skip
+    /// or replace them. Fails only if `host_path` cannot be created or the root directory cannot be read.
+    pub fn dump_to_disk(&self, host_path: impl AsRef<Path>) -> Result<DumpReport> {
+        self.dump_to_disk_with(host_path, CollisionMode::Fail)
+    }
+
+    /// Same as [MemFS::dump_to_disk], where `mode` decides what to do with names already taken on the host.
+    pub fn dump_to_disk_with(&self, host_path: impl AsRef<Path>, mode: CollisionMode) -> Result<DumpReport> {
+        let host_path = host_path.as_ref();
+        let mut report = DumpReport::default();
+
+        fs::create_dir_all(host_path)?;
+        self.dump_directory("", host_path, mode, &mut report)?;
+
+        Ok(report)
+    }
+
+    fn load_directory(&self, host_dir: &Path, path: &str) -> Result<()> {
+        let mut entries = fs::read_dir(host_dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name().into_string().map_err(|_| MemFSErr::invalid_value())?;
+            let host_path = entry.path();
+            let child_path = format!("{path}/{name}");
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                self.mkdir(&child_path)?;
+                self.load_directory(&host_path, &child_path)?;
+            } else if file_type.is_file() {
+                let contents = fs::read(&host_path)?;
+
+                if contents.len() > FILE_MAX_SIZE {
+                    return Err(MemFSErr::file_too_large());
+                }
+
+                let fd = self.open(&child_path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)?;
+                let written = self.write(fd, &contents);
+                self.close(fd)?;
+                written?;
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(&host_path)?;
+                let target = target.to_str().ok_or(MemFSErr::invalid_value())?;
+
+                self.symlink(target, &child_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dump_directory(&self, path: &str, host_dir: &Path, mode: CollisionMode, report: &mut DumpReport) -> Result<()> {
+        let entries = self.readdir(if path.is_empty() { "/" } else { path })?;
+
+        for entry in entries {
+            let child_path = format!("{path}/{}", entry.name);
+
+            if let Err(err) = self.dump_entry(&child_path, entry.file_type, &host_dir.join(&entry.name), mode, report) {
+                report.failed.push((child_path, err));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dump_entry(
+        &self,
+        path: &str,
+        file_type: FileType,
+        host_path: &Path,
+        mode: CollisionMode,
+        report: &mut DumpReport,
+    ) -> Result<()> {
+        let existing = fs::symlink_metadata(host_path).ok();
+        let merged = file_type == FileType::Directory && existing.as_ref().is_some_and(|m| m.is_dir());
+
+        if let Some(existing) = existing
+            && !merged
+        {
+            match mode {
+                CollisionMode::Fail => return Err(MemFSErr::already_exists()),
+                CollisionMode::Skip => {
+                    report.skipped += 1;
+                    return Ok(());
+                }
+                CollisionMode::Overwrite if existing.is_dir() => return Err(MemFSErr::is_directory()),
+                CollisionMode::Overwrite => fs::remove_file(host_path)?,
+            }
+        }
+
+        match file_type {
+            FileType::Directory => {
+                if !merged {
+                    fs::create_dir(host_path)?;
+                }
+
+                report.directories += 1;
+                self.dump_directory(path, host_path, mode, report)
+            }
+            FileType::File => {
+                // Virtual files are written with what they generate, as their size is always zero.
+                let contents = match self.virtual_file(path) {
+                    Ok(file) => file.contents(),
+                    Err(_) => self.read_contents(path)?,
+                };
+                fs::write(host_path, contents)?;
+                report.files += 1;
+
+                Ok(())
+            }
+            FileType::Symlink => {
+                create_host_symlink(&self.readlink(path)?, host_path)?;
+                report.symlinks += 1;
+
+                Ok(())
+            }
+            // Creating devices on the host takes privileges, and they would not behave as they do here anyway.
+            // FIFOs are left out along with them, as their data lives in the descriptors open on them.
+            FileType::CharDevice | FileType::Fifo => {
+                report.skipped += 1;
+
+                Ok(())
+            }
+        }
+    }
+
+    fn read_contents(&self, path: &str) -> Result<Vec<u8>> {
+        let mut contents = vec![0u8; self.stat(path)?.size];
+        let fd = self.open(path, OpenFlag::O_RDONLY)?;
+        let read = self.read(fd, &mut contents);
+        self.close(fd)?;
+
+        contents.truncate(read?);
+
+        Ok(contents)
+    }
+}
+
+#[cfg(unix)]
+fn create_host_symlink(target: &str, host_path: &Path) -> Result<()> {
+    Ok(std::os::unix::fs::symlink(target, host_path)?)
+}
+
+#[cfg(not(unix))]
+fn create_host_symlink(_target: &str, _host_path: &Path) -> Result<()> {
+    Err(MemFSErr::operation_not_permitted())
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+
+use crate::device::{DEFAULT_DEVICE_MODE, Device};
+use crate::memfs::MemFS;
+use crate::permission::DEFAULT_FILE_MODE;
+use crate::utils::{MemFSErr, OpenFlag, Result};
+
+/// Portable image of a node and everything under it, as written by the [Serialize] implementation of [MemFS].
+///
+/// Entries are kept sorted by name, so that the same tree always gives the same image.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum NodeImage {
+    File(Vec<u8>),
+
+    /// Another name of a file which comes earlier in the image, given by the absolute path of the first name.
+    Link(String),
+
+    Symlink(String),
+    Device(Device),
+
+    /// FIFO, which is restored empty, as data buffered in it belongs to the descriptors open on it.
+    Fifo,
+    Directory(BTreeMap<String, NodeImage>),
+}
+
+/// Writes the whole tree and the contents of every file. Hard links stay links.
+impl Serialize for MemFS {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.image().map_err(ser::Error::custom)?.serialize(serializer)
+    }
+}
+
+/// Builds a new MemFS with default configuration holding the serialized tree.
+impl<'de> Deserialize<'de> for MemFS {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let image = NodeImage::deserialize(deserializer)?;
+        let fs = MemFS::new();
+
+        match &image {
+            NodeImage::Directory(entries) => restore_entries(&fs, "", entries).map_err(de::Error::custom)?,
+            _ => return Err(de::Error::custom("root of the image is not a directory")),
+        }
+
+        Ok(fs)
+    }
+}
+
+fn restore_entries(fs: &MemFS, path: &str, entries: &BTreeMap<String, NodeImage>) -> Result<()> {
+    for (name, image) in entries {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let child_path = format!("{path}/{name}");
+
+        match image {
+            NodeImage::File(contents) => {
+                let fd = fs.open(&child_path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)?;
+                let written = fs.write(fd, contents);
+                fs.close(fd)?;
+                written?;
+            }
+            NodeImage::Link(first) => fs.link(first, &child_path)?,
+            NodeImage::Symlink(target) => fs.symlink(target, &child_path)?,
+            NodeImage::Device(device) => fs.mknod(&child_path, *device, DEFAULT_DEVICE_MODE)?,
+            NodeImage::Fifo => fs.mkfifo(&child_path, DEFAULT_FILE_MODE)?,
+            NodeImage::Directory(children) => {
+                fs.mkdir(&child_path)?;
+                restore_entries(fs, &child_path, children)?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+
+/// Inode number of the root directory of a new file system.
+pub const ROOT_INO: u64 = 1;
+
+/// Number of entries below which the table is never swept.
+const MIN_SWEEP_THRESHOLD: usize = 1024;
+
+/// Hands out inode numbers, and maps them back to weak handles of their nodes.
+///
+/// Entries of dropped nodes are not removed when the node goes away. They are swept once the table
+/// has doubled since the previous sweep, which keeps the cost of sweeping constant per registration.
+pub(crate) struct InodeTable<W> {
+    next: AtomicU64,
+    nodes: DashMap<u64, W>,
+    sweep_threshold: AtomicUsize,
+}
+
+impl<W: Clone> InodeTable<W> {
+    /// Starts numbering at `next`.
+    pub fn new(next: u64) -> Self {
+        Self {
+            next: AtomicU64::new(next),
+            nodes: DashMap::new(),
+            sweep_threshold: AtomicUsize::new(MIN_SWEEP_THRESHOLD),
+        }
+    }
+
+    pub fn allocate(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Makes sure that numbers handed out from now on are above `ino`.
+    pub fn reserve(&self, ino: u64) {
+        self.next.fetch_max(ino + 1, Ordering::Relaxed);
+    }
+
+    /// Maps `ino` to `node`. Returns true when the table should be swept with [InodeTable::sweep].
+    pub fn register(&self, ino: u64, node: W) -> bool {
+        self.nodes.insert(ino, node);
+        self.nodes.len() >= self.sweep_threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn get(&self, ino: u64) -> Option<W> {
+        self.nodes.get(&ino).map(|node| node.clone())
+    }
+
+    /// Number of entries whose node is still there, as told by `is_alive`.
+    pub fn count(&self, is_alive: impl Fn(&W) -> bool) -> usize {
+        self.nodes.iter().filter(|entry| is_alive(entry.value())).count()
+    }
+
+    /// Runs `visit` on every entry, whether its node is still there or not.
+    #[cfg(any(feature = "compression", feature = "dedup"))]
+    pub fn for_each(&self, mut visit: impl FnMut(&W)) {
+        self.nodes.iter().for_each(|entry| visit(entry.value()));
+    }
+
+    /// Drops the entries whose node is gone, as told by `is_alive`.
+    pub fn sweep(&self, is_alive: impl Fn(&W) -> bool) {
+        self.nodes.retain(|_, node| is_alive(node));
+        self.sweep_threshold
+            .store((self.nodes.len() * 2).max(MIN_SWEEP_THRESHOLD), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use tracing::field;
+use tracing::span::EnteredSpan;
+
+use crate::utils::MemFSErr;
+
+/// Span of a single MemFS operation, entered until the operation returns.
+///
+/// The span is named `memfs` and carries the name of the operation in `op`, along with `path` and `fd`
+/// when the operation takes them. Once the operation returns, an event in the span tells its latency in
+/// `latency_us`, and either the value it returned or the error it failed with.
+pub(crate) struct OperationSpan {
+    _span: EnteredSpan,
+    started: Instant,
+}
+
+impl OperationSpan {
+    /// `path` is only called when a subscriber listens to the span, as naming a descriptor takes a lookup.
+    pub fn enter(op: &'static str, fd: Option<usize>, path: impl FnOnce() -> Option<String>) -> Self {
+        let span = tracing::debug_span!("memfs", op, fd, path = field::Empty);
+
+        if !span.is_disabled()
+            && let Some(path) = path()
+        {
+            span.record("path", path.as_str());
+        }
+
+        Self {
+            _span: span.entered(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish(self, result: Result<Option<usize>, &MemFSErr>) {
+        let latency_us = self.started.elapsed().as_micros() as u64;
+
+        match result {
+            Ok(value) => tracing::debug!(latency_us, value, "done"),
+            Err(err) => tracing::debug!(latency_us, errno = ?err.err_type, error = %err.message, "failed"),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::device::Device;
+use crate::memfs::MemFS;
+use crate::utils::{AT_FDCWD, FallocateMode, MemFSErr, OpenFlag, RenameFlag, Result};
+
+/// Mutation recorded in a [Journal]. Paths are absolute. Files are identified by the inode number
+/// they had when they were created, so that writes land in the right file whatever its names became.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JournalEntry {
+    Create { ino: u64, path: String },
+    Tmpfile { ino: u64, dir: String },
+    Write { ino: u64, offset: usize, data: Vec<u8> },
+    Truncate { ino: u64, len: usize },
+    PunchHole { ino: u64, offset: usize, len: usize },
+    Unlink { path: String },
+    Undelete { path: String },
+    Mkdir { path: String },
+    Rmdir { path: String },
+    RemoveAll { path: String },
+    Rename { old: String, new: String },
+    Exchange { old: String, new: String },
+    Link { existing: String, new: String },
+    Linkat { ino: u64, new: String },
+    Symlink { target: String, link: String },
+    Mknod { path: String, device: Device, mode: u32 },
+    Mkfifo { path: String, mode: u32 },
+    Chmod { path: String, mode: u32 },
+    Fchmod { ino: u64, mode: u32 },
+    Chown { path: String, uid: Option<u32>, gid: Option<u32> },
+    Fchown { ino: u64, uid: Option<u32>, gid: Option<u32> },
+    Utimens { path: String, atime: Option<SystemTime>, mtime: Option<SystemTime> },
+    Reflink { ino: u64, source: String, target: String },
+}
+
+/// Mutations of a MemFS in the order they were applied, taken with [MemFS::journal].
+///
+/// It covers the tree, file contents, and the permission bits, owners and times set with chmod, chown
+/// and utimens. Times stamped by other mutations are not journaled, nor are snapshot restores and simulated
+/// crashes. A change of metadata through a descriptor names the file by inode number, and anything else
+/// by the path the descriptor was opened with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new(entries: Vec<JournalEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Applies the entries to `fs`, which is usually a fresh MemFS, stopping at the first one which fails.
+    /// Replaying a prefix of a journal rebuilds the tree as it was at that point.
+    pub fn replay(&self, fs: &MemFS) -> Result<()> {
+        let mut files = HashMap::new();
+        let result = self
+            .entries
+            .iter()
+            .try_for_each(|entry| Self::apply(fs, entry, &mut files));
+
+        for fd in files.into_values() {
+            let _ = fs.close(fd);
+        }
+
+        result
+    }
+
+    /// Applies one entry. Created files stay open in `files` until the end of the replay,
+    /// so that writes reach them even after they were unlinked.
+    fn apply(fs: &MemFS, entry: &JournalEntry, files: &mut HashMap<u64, usize>) -> Result<()> {
+        let fd = |ino: &u64| files.get(ino).copied().ok_or(MemFSErr::bad_file_descriptor());
+
+        match entry {
+            JournalEntry::Create { ino, path } => {
+                let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_RDWR)?;
+                files.insert(*ino, fd);
+
+                Ok(())
+            }
+            JournalEntry::Tmpfile { ino, dir } => {
+                files.insert(*ino, fs.open(dir, OpenFlag::O_TMPFILE | OpenFlag::O_RDWR)?);
+
+                Ok(())
+            }
+            JournalEntry::Reflink { ino, source, target } => {
+                fs.reflink(source, target)?;
+                files.insert(*ino, fs.open(target, OpenFlag::O_RDWR)?);
+
+                Ok(())
+            }
+            JournalEntry::Write { ino, offset, data } => fs.pwrite(fd(ino)?, data, *offset).map(|_| ()),
+            JournalEntry::Truncate { ino, len } => fs.ftruncate(fd(ino)?, *len),
+            JournalEntry::PunchHole { ino, offset, len } => {
+                fs.fallocate(fd(ino)?, FallocateMode::PunchHole, *offset, *len)
+            }
+            JournalEntry::Unlink { path } => fs.unlink(path),
+            JournalEntry::Undelete { path } => fs.undelete(path),
+            JournalEntry::Mkdir { path } => fs.mkdir(path),
+            JournalEntry::Rmdir { path } => fs.rmdir(path),
+            JournalEntry::RemoveAll { path } => fs.remove_dir_all(path),
+            JournalEntry::Rename { old, new } => fs.rename(old, new),
+            JournalEntry::Exchange { old, new } => fs.renameat2(AT_FDCWD, old, AT_FDCWD, new, RenameFlag::RENAME_EXCHANGE),
+            JournalEntry::Link { existing, new } => fs.link(existing, new),
+            JournalEntry::Linkat { ino, new } => fs.linkat(fd(ino)?, new),
+            JournalEntry::Symlink { target, link } => fs.symlink(target, link),
+            JournalEntry::Mknod { path, device, mode } => fs.mknod(path, *device, *mode),
+            JournalEntry::Mkfifo { path, mode } => fs.mkfifo(path, *mode),
+            JournalEntry::Chmod { path, mode } => fs.chmod(path, *mode),
+            JournalEntry::Fchmod { ino, mode } => fs.fchmod(fd(ino)?, *mode),
+            JournalEntry::Chown { path, uid, gid } => fs.chown(path, *uid, *gid),
+            JournalEntry::Fchown { ino, uid, gid } => fs.fchown(fd(ino)?, *uid, *gid),
+            JournalEntry::Utimens { path, atime, mtime } => fs.utimens(path, *atime, *mtime),
+        }
+    }
+}
+
+/// Journal being recorded by a MemFS.
+#[derive(Default)]
+pub(crate) struct JournalRecorder {
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl JournalRecorder {
+    /// Runs `mutate`, and records what `entry` describes of it once it succeeded.
+    /// Mutations are serialized, so that entries are in the order they were applied.
+    pub fn record<T>(
+        &self,
+        mutate: impl FnOnce() -> Result<T>,
+        entry: impl FnOnce(&T) -> Option<JournalEntry>,
+    ) -> Result<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let value = mutate()?;
+
+        entries.extend(entry(&value));
+
+        Ok(value)
+    }
+
+    pub fn journal(&self) -> Journal {
+        Journal::new(self.entries.lock().unwrap_or_else(PoisonError::into_inner).clone())
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use rand::Rng;
+
+use crate::clock::Clock;
+use crate::metrics::MemFSOp;
+
+/// Distribution of the latency added to a system call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatencyDistribution {
+    Fixed(Duration),
+
+    /// Uniformly distributed between `min` and `max`, both inclusive.
+    Uniform { min: Duration, max: Duration },
+
+    /// Exponentially distributed with the given mean, which gives the long tail of a busy device or network.
+    Exponential { mean: Duration },
+}
+
+impl LatencyDistribution {
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        match *self {
+            LatencyDistribution::Fixed(latency) => latency,
+            LatencyDistribution::Uniform { min, max } if min < max => {
+                Duration::from_nanos(rng.random_range(min.as_nanos() as u64..=max.as_nanos() as u64))
+            }
+            LatencyDistribution::Uniform { min, .. } => min,
+            LatencyDistribution::Exponential { mean } => {
+                let uniform: f64 = rng.random();
+                mean.mul_f64(-(1.0 - uniform).ln())
+            }
+        }
+    }
+}
+
+/// Latencies and bandwidth caps applied to system calls, so that MemFS can stand in for a slow disk
+/// or network file system. Set with [crate::memfs::MemFSBuilder::latency].
+///
+/// The latency of a call is the sum of the latency of its operation and of every path pattern which
+/// matches its path; calls on file descriptors match the path the descriptor was opened with.
+/// In patterns, `*` matches any sequence of characters, including slashes, so `/net/*` matches
+/// everything under `/net`.
+///
+/// Bandwidth caps are shared by every thread, like the channel of a single device: a transfer starts
+/// once the transfers before it are done, and takes its size divided by the bandwidth.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyProfile {
+    ops: HashMap<MemFSOp, LatencyDistribution>,
+    paths: Vec<(String, LatencyDistribution)>,
+    read_bandwidth: Option<u64>,
+    write_bandwidth: Option<u64>,
+}
+
+impl LatencyProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `latency` to every call of `op`, replacing the latency set for it before.
+    pub fn op(mut self, op: MemFSOp, latency: LatencyDistribution) -> Self {
+        self.ops.insert(op, latency);
+        self
+    }
+
+    /// Adds `latency` to every call on a path matching `pattern`.
+    pub fn path(mut self, pattern: &str, latency: LatencyDistribution) -> Self {
+        self.paths.push((pattern.to_string(), latency));
+        self
+    }
+
+    /// Caps reads of every file to `bytes_per_second` in total.
+    pub fn read_bandwidth(mut self, bytes_per_second: u64) -> Self {
+        self.read_bandwidth = Some(bytes_per_second.max(1));
+        self
+    }
+
+    /// Caps writes of every file to `bytes_per_second` in total.
+    pub fn write_bandwidth(mut self, bytes_per_second: u64) -> Self {
+        self.write_bandwidth = Some(bytes_per_second.max(1));
+        self
+    }
+
+    fn has_path_rules(&self) -> bool {
+        !self.paths.is_empty()
+    }
+}
+
+/// Applies a [LatencyProfile] to the system calls of a file system.
+pub(crate) struct LatencyInjector {
+    profile: LatencyProfile,
+
+    /// Times, in ticks of the clock, at which the read and write channels finish the transfers reserved so far.
+    read_channel: Mutex<Duration>,
+    write_channel: Mutex<Duration>,
+    clock: Clock,
+}
+
+impl LatencyInjector {
+    /// Sleeps on `clock`, which only advances a virtual clock.
+    pub fn new(profile: LatencyProfile, clock: Clock) -> Self {
+        let now = clock.ticks();
+
+        Self {
+            profile,
+            read_channel: Mutex::new(now),
+            write_channel: Mutex::new(now),
+            clock,
+        }
+    }
+
+    pub fn has_path_rules(&self) -> bool {
+        self.profile.has_path_rules()
+    }
+
+    /// Sleeps for the latency of a call of `op` on `path`.
+    pub fn delay(&self, op: MemFSOp, path: Option<&str>) {
+        let mut rng = rand::rng();
+        let mut latency = self
+            .profile
+            .ops
+            .get(&op)
+            .map_or(Duration::ZERO, |d| d.sample(&mut rng));
+
+        if let Some(path) = path {
+            for (pattern, distribution) in &self.profile.paths {
+                if matches_pattern(pattern, path) {
+                    latency += distribution.sample(&mut rng);
+                }
+            }
+        }
+
+        if !latency.is_zero() {
+            self.clock.sleep(latency);
+        }
+    }
+
+    /// Sleeps until `bytes` went through the read or write channel, if its bandwidth is capped.
+    pub fn transfer(&self, op: MemFSOp, bytes: usize) {
+        let (channel, bandwidth) = match op {
+            MemFSOp::Read | MemFSOp::Pread => (&self.read_channel, self.profile.read_bandwidth),
+            MemFSOp::Write | MemFSOp::Pwrite | MemFSOp::Append => (&self.write_channel, self.profile.write_bandwidth),
+            _ => return,
+        };
+
+        let Some(bandwidth) = bandwidth else {
+            return;
+        };
+
+        if bytes == 0 {
+            return;
+        }
+
+        let done = {
+            let mut channel = channel.lock().unwrap_or_else(PoisonError::into_inner);
+            let start = (*channel).max(self.clock.ticks());
+
+            *channel = start + Duration::from_secs_f64(bytes as f64 / bandwidth as f64);
+            *channel
+        };
+
+        self.clock.sleep(done.saturating_sub(self.clock.ticks()));
+    }
+}
+
+/// Matches `path` against `pattern`, where `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (pattern, path) = (pattern.as_bytes(), path.as_bytes());
+    let (mut p, mut s) = (0, 0);
+
+    // Position of the last star in the pattern, and of the path when it was reached.
+    let mut backtrack = None;
+
+    while s < path.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, s));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == path[s] {
+            p += 1;
+            s += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            s = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
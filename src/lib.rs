@@ -1,3 +1,60 @@
+#[cfg(feature = "tokio")]
+pub mod aio;
+pub mod arena;
+pub mod changes;
+pub mod clock;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod contention;
+pub mod copy;
+pub mod crash;
+#[cfg(feature = "dedup")]
+pub mod dedup;
+pub mod dentry;
+pub mod device;
+pub mod durability;
+pub mod eviction;
+mod descriptor;
+pub mod exclusive;
+pub mod flock;
+pub mod freeze;
+pub mod hash;
+pub mod history;
+pub mod host;
+#[cfg(feature = "serde")]
+mod image;
+pub mod inode;
+#[cfg(feature = "tracing")]
+mod instrument;
+pub mod journal;
+pub mod latency;
+pub mod lock;
+pub mod maintenance;
 #[allow(unused_imports)]
 pub mod memfs;
+pub mod metrics;
+pub mod mmap;
+pub mod names;
+pub mod oplog;
+mod page;
+mod persist;
+pub mod permission;
+pub mod pipe;
+pub mod pool;
+pub mod process;
+pub mod quota;
+pub mod range_lock;
+pub mod readdir;
+pub mod removal;
+pub mod snapshot;
+mod timestamp;
+pub mod trace;
+mod trash;
+pub mod tuning;
 pub mod utils;
+#[cfg(feature = "vfs")]
+pub mod vfs;
+pub mod virtual_file;
+pub mod watch;
+pub mod workload;
+pub mod writer;
@@ -0,0 +1,307 @@
+use std::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::{PoisonError, TryLockError},
+};
+
+// Loom's, with `check-loom`, as the locks of the coarse-grained backend are held across operations loom
+// goes through.
+#[cfg(not(feature = "check-loom"))]
+use std::sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "check-loom")]
+use loom::sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Which side wins when readers and writers compete for a lock of the coarse-grained backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// `std::sync::RwLock`, whose behavior depends on the platform.
+    #[default]
+    Platform,
+
+    /// Readers enter whenever no writer holds the lock. Writers may starve under heavy reading.
+    ReadPreferring,
+
+    /// New readers wait while a writer is waiting. Readers may starve under heavy writing.
+    WritePreferring,
+
+    /// Readers and writers enter in arrival order, so neither side starves.
+    FairQueued,
+}
+
+/// Returned when the lock was poisoned by a panicking holder.
+#[derive(Debug)]
+pub struct PoisonedLock;
+
+struct QueueState {
+    readers: usize,
+    writer: bool,
+    waiting_writers: usize,
+    next_ticket: u64,
+    serving: u64,
+}
+
+enum Inner<T> {
+    Platform(RwLock<T>),
+    Policy {
+        state: Mutex<QueueState>,
+        changed: Condvar,
+        value: UnsafeCell<T>,
+    },
+}
+
+/// Reader-writer lock following a [LockPolicy].
+pub struct PolicyRwLock<T> {
+    policy: LockPolicy,
+    inner: Inner<T>,
+}
+
+unsafe impl<T: Send> Send for PolicyRwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for PolicyRwLock<T> {}
+
+impl<T: Default> Default for PolicyRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> PolicyRwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self::with_policy(value, LockPolicy::default())
+    }
+
+    pub fn with_policy(value: T, policy: LockPolicy) -> Self {
+        let inner = match policy {
+            LockPolicy::Platform => Inner::Platform(RwLock::new(value)),
+            _ => Inner::Policy {
+                state: Mutex::new(QueueState {
+                    readers: 0,
+                    writer: false,
+                    waiting_writers: 0,
+                    next_ticket: 0,
+                    serving: 0,
+                }),
+                changed: Condvar::new(),
+                value: UnsafeCell::new(value),
+            },
+        };
+
+        Self { policy, inner }
+    }
+
+    pub fn policy(&self) -> LockPolicy {
+        self.policy
+    }
+
+    pub fn read(&self) -> Result<PolicyReadGuard<'_, T>, PoisonedLock> {
+        match &self.inner {
+            Inner::Platform(lock) => lock
+                .read()
+                .map(PolicyReadGuard::Platform)
+                .map_err(|_| PoisonedLock),
+            Inner::Policy { state, changed, .. } => {
+                let mut state = lock_state(state);
+                let ticket = take_ticket(&mut state, self.policy);
+
+                loop {
+                    let can_enter = !state.writer
+                        && match self.policy {
+                            LockPolicy::WritePreferring => state.waiting_writers == 0,
+                            LockPolicy::FairQueued => ticket == state.serving,
+                            _ => true,
+                        };
+
+                    if can_enter {
+                        break;
+                    }
+
+                    state = changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+                }
+
+                state.readers += 1;
+
+                if self.policy == LockPolicy::FairQueued {
+                    // Let the next one in the queue try, so that consecutive readers share the lock.
+                    state.serving += 1;
+                    changed.notify_all();
+                }
+
+                Ok(PolicyReadGuard::Policy(self))
+            }
+        }
+    }
+
+    pub fn write(&self) -> Result<PolicyWriteGuard<'_, T>, PoisonedLock> {
+        match &self.inner {
+            Inner::Platform(lock) => lock
+                .write()
+                .map(PolicyWriteGuard::Platform)
+                .map_err(|_| PoisonedLock),
+            Inner::Policy { state, changed, .. } => {
+                let mut state = lock_state(state);
+                let ticket = take_ticket(&mut state, self.policy);
+
+                state.waiting_writers += 1;
+
+                while state.writer
+                    || state.readers > 0
+                    || (self.policy == LockPolicy::FairQueued && ticket != state.serving)
+                {
+                    state = changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+                }
+
+                state.waiting_writers -= 1;
+                state.writer = true;
+
+                if self.policy == LockPolicy::FairQueued {
+                    state.serving += 1;
+                }
+
+                Ok(PolicyWriteGuard::Policy(self))
+            }
+        }
+    }
+
+    /// Acquires shared access only if it is available without waiting.
+    pub fn try_read(&self) -> Result<Option<PolicyReadGuard<'_, T>>, PoisonedLock> {
+        match &self.inner {
+            Inner::Platform(lock) => match lock.try_read() {
+                Ok(guard) => Ok(Some(PolicyReadGuard::Platform(guard))),
+                Err(TryLockError::WouldBlock) => Ok(None),
+                Err(TryLockError::Poisoned(_)) => Err(PoisonedLock),
+            },
+            Inner::Policy { state, .. } => {
+                let mut state = lock_state(state);
+                let can_enter = !state.writer
+                    && match self.policy {
+                        LockPolicy::WritePreferring => state.waiting_writers == 0,
+                        LockPolicy::FairQueued => state.next_ticket == state.serving,
+                        _ => true,
+                    };
+
+                if !can_enter {
+                    return Ok(None);
+                }
+
+                state.readers += 1;
+
+                Ok(Some(PolicyReadGuard::Policy(self)))
+            }
+        }
+    }
+
+    /// Acquires exclusive access only if it is available without waiting.
+    pub fn try_write(&self) -> Result<Option<PolicyWriteGuard<'_, T>>, PoisonedLock> {
+        match &self.inner {
+            Inner::Platform(lock) => match lock.try_write() {
+                Ok(guard) => Ok(Some(PolicyWriteGuard::Platform(guard))),
+                Err(TryLockError::WouldBlock) => Ok(None),
+                Err(TryLockError::Poisoned(_)) => Err(PoisonedLock),
+            },
+            Inner::Policy { state, .. } => {
+                let mut state = lock_state(state);
+
+                // Nobody is queued when every ticket has been served.
+                if state.writer || state.readers > 0 || state.next_ticket != state.serving {
+                    return Ok(None);
+                }
+
+                state.writer = true;
+
+                Ok(Some(PolicyWriteGuard::Policy(self)))
+            }
+        }
+    }
+
+    fn release(&self, writer: bool) {
+        if let Inner::Policy { state, changed, .. } = &self.inner {
+            let mut state = lock_state(state);
+
+            if writer {
+                state.writer = false;
+            } else {
+                state.readers -= 1;
+            }
+
+            changed.notify_all();
+        }
+    }
+
+    fn value(&self) -> *mut T {
+        match &self.inner {
+            Inner::Policy { value, .. } => value.get(),
+            Inner::Platform(_) => unreachable!(),
+        }
+    }
+}
+
+fn lock_state(state: &Mutex<QueueState>) -> MutexGuard<'_, QueueState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn take_ticket(state: &mut QueueState, policy: LockPolicy) -> u64 {
+    if policy != LockPolicy::FairQueued {
+        return 0;
+    }
+
+    let ticket = state.next_ticket;
+    state.next_ticket += 1;
+
+    ticket
+}
+
+pub enum PolicyReadGuard<'a, T> {
+    Platform(RwLockReadGuard<'a, T>),
+    Policy(&'a PolicyRwLock<T>),
+}
+
+impl<T> Deref for PolicyReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            PolicyReadGuard::Platform(guard) => guard,
+            PolicyReadGuard::Policy(lock) => unsafe { &*lock.value() },
+        }
+    }
+}
+
+impl<T> Drop for PolicyReadGuard<'_, T> {
+    fn drop(&mut self) {
+        if let PolicyReadGuard::Policy(lock) = self {
+            lock.release(false);
+        }
+    }
+}
+
+pub enum PolicyWriteGuard<'a, T> {
+    Platform(RwLockWriteGuard<'a, T>),
+    Policy(&'a PolicyRwLock<T>),
+}
+
+impl<T> Deref for PolicyWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            PolicyWriteGuard::Platform(guard) => guard,
+            PolicyWriteGuard::Policy(lock) => unsafe { &*lock.value() },
+        }
+    }
+}
+
+impl<T> DerefMut for PolicyWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            PolicyWriteGuard::Platform(guard) => guard,
+            PolicyWriteGuard::Policy(lock) => unsafe { &mut *lock.value() },
+        }
+    }
+}
+
+impl<T> Drop for PolicyWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        if let PolicyWriteGuard::Policy(lock) = self {
+            lock.release(true);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::clock::Clock;
+
+/// Identifier of a periodic task registered on [MaintenanceScheduler].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaintenanceTaskId(u64);
+
+type TaskFn = Arc<Mutex<Box<dyn FnMut() + Send>>>;
+
+struct MaintenanceTask {
+    id: MaintenanceTaskId,
+    interval: Duration,
+
+    /// In ticks of the clock of the scheduler.
+    next_run: Duration,
+    run: TaskFn,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    tasks: Vec<MaintenanceTask>,
+    next_id: u64,
+    shutdown: bool,
+
+    /// Whether due tasks run when the virtual clock of the scheduler is advanced.
+    listening: bool,
+}
+
+#[derive(Default)]
+struct SchedulerShared {
+    state: Mutex<SchedulerState>,
+    wakeup: Condvar,
+    clock: Clock,
+}
+
+/// Runs periodic maintenance tasks of a MemFS instance on a single dedicated thread.
+///
+/// The thread is spawned lazily when the first task is registered,
+/// and is stopped and joined on [MaintenanceScheduler::shutdown] or on drop.
+/// On a [crate::clock::VirtualClock], there is no thread: due tasks run when the clock is advanced.
+#[derive(Default)]
+pub struct MaintenanceScheduler {
+    shared: Arc<SchedulerShared>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn with_clock(clock: Clock) -> Self {
+        Self {
+            shared: Arc::new(SchedulerShared {
+                clock,
+                ..Default::default()
+            }),
+            thread: Mutex::default(),
+        }
+    }
+
+    /// Registers `task` to be run every `interval`. The first run happens one interval from now.
+    pub fn schedule(&self, interval: Duration, task: impl FnMut() + Send + 'static) -> MaintenanceTaskId {
+        let id = {
+            let mut state = self.shared.state.lock().unwrap();
+            let id = MaintenanceTaskId(state.next_id);
+
+            state.next_id += 1;
+            state.shutdown = false;
+            state.tasks.push(MaintenanceTask {
+                id,
+                interval,
+                next_run: self.shared.clock.ticks() + interval,
+                run: Arc::new(Mutex::new(Box::new(task))),
+            });
+
+            id
+        };
+
+        self.ensure_worker();
+        self.shared.wakeup.notify_all();
+
+        id
+    }
+
+    /// Unregisters a task. Returns false if there is no task with the given id.
+    pub fn cancel(&self, id: MaintenanceTaskId) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let before = state.tasks.len();
+
+        state.tasks.retain(|t| t.id != id);
+
+        before != state.tasks.len()
+    }
+
+    /// Number of registered tasks.
+    pub fn task_count(&self) -> usize {
+        self.shared.state.lock().unwrap().tasks.len()
+    }
+
+    /// Drops every registered task and joins the worker thread.
+    /// A task which is running at the moment is allowed to finish.
+    pub fn shutdown(&self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            state.tasks.clear();
+        }
+
+        self.shared.wakeup.notify_all();
+
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn ensure_worker(&self) {
+        let mut thread = self.thread.lock().unwrap();
+
+        if thread.is_some() {
+            return;
+        }
+
+        if let Clock::Virtual(clock) = &self.shared.clock {
+            let mut state = self.shared.state.lock().unwrap();
+
+            // Stays registered after a shutdown, as the scheduler may get tasks again.
+            if !state.listening {
+                state.listening = true;
+                let shared = Arc::downgrade(&self.shared);
+
+                clock.on_advance(move || match shared.upgrade() {
+                    Some(shared) => {
+                        let due = Self::take_due(&mut shared.state.lock().unwrap(), shared.clock.ticks());
+                        due.into_iter().for_each(|task| (task.lock().unwrap())());
+                        true
+                    }
+                    None => false,
+                });
+            }
+
+            return;
+        }
+
+        let shared = self.shared.clone();
+        *thread = Some(thread::spawn(move || Self::run_worker(shared)));
+    }
+
+    /// Returns the tasks due at `now`, and schedules their next run.
+    fn take_due(state: &mut SchedulerState, now: Duration) -> Vec<TaskFn> {
+        let mut due = Vec::new();
+
+        for task in state.tasks.iter_mut().filter(|t| t.next_run <= now) {
+            task.next_run = now + task.interval;
+            due.push(task.run.clone());
+        }
+
+        due
+    }
+
+    fn run_worker(shared: Arc<SchedulerShared>) {
+        let mut state = shared.state.lock().unwrap();
+
+        loop {
+            if state.shutdown {
+                return;
+            }
+
+            let now = shared.clock.ticks();
+            let due = Self::take_due(&mut state, now);
+
+            if !due.is_empty() {
+                // Run tasks without holding the state lock, so that tasks can be (un)registered meanwhile.
+                drop(state);
+
+                for task in due {
+                    (task.lock().unwrap())();
+                }
+
+                state = shared.state.lock().unwrap();
+                continue;
+            }
+
+            state = match state.tasks.iter().map(|t| t.next_run).min() {
+                Some(next_run) => {
+                    shared
+                        .wakeup
+                        .wait_timeout(state, next_run.saturating_sub(now))
+                        .unwrap()
+                        .0
+                }
+                None => shared.wakeup.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+impl Drop for MaintenanceScheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -1,1365 +1,7369 @@
-use crossbeam::queue::ArrayQueue;
-
-use std::collections::HashMap;
-use dashmap::{DashMap, Entry};
-use papaya::{HashMap as LockFreeHashMap, HashMapRef, LocalGuard};
-use std::hash::RandomState;
-
+use std::collections::{HashMap, HashSet};
+use dashmap::{DashMap, Entry, mapref::one::Ref, try_result::TryResult};
+use papaya::{Compute, HashMap as LockFreeHashMap, HashMapRef, LocalGuard, Operation};
+
+
+use crate::arena::{Arena, ArenaAllocated, NodeArc, NodeWeak};
+use crate::changes::{Change, ChangeLog};
+use crate::clock::{Clock, ClockScope, VirtualClock};
+#[cfg(feature = "compression")]
+use crate::compression::CompressionStats;
+use crate::contention::{ContentionCounters, NodeContention};
+use crate::crash::{CrashModel, CrashReport, CrashTracker};
+#[cfg(feature = "dedup")]
+use crate::dedup::{DedupStats, DedupTable};
+use crate::dentry::{DentryCache, DentryCacheStats, Resolution};
+use crate::device::{DEFAULT_DEVICE_MODE, Device};
+use crate::durability::{DurabilityBackend, SyncedFile};
+use crate::eviction::{Eviction, UseOrder};
+use crate::descriptor::{DescriptorNumbers, DescriptorShards};
+use crate::exclusive::{ExclusiveGate, ExclusiveGuard, OperationGuard};
+use crate::flock::{FileLock, LockOp, next_owner};
+use crate::freeze::{FreezeGate, FreezeMode, MutationGuard};
+use crate::hash::{HashState, MemFSHasher};
+use crate::history::{FileVersion, VersionHistory, VersionPolicy};
+#[cfg(feature = "serde")]
+use crate::image::NodeImage;
+use crate::inode::{InodeTable, ROOT_INO};
+#[cfg(feature = "tracing")]
+use crate::instrument::OperationSpan;
+use crate::journal::{Journal, JournalEntry, JournalRecorder};
+use crate::latency::{LatencyInjector, LatencyProfile};
+use crate::lock::{LockPolicy, PolicyReadGuard, PolicyRwLock, PolicyWriteGuard};
+use crate::maintenance::{MaintenanceScheduler, MaintenanceTaskId};
+use crate::metrics::{MemFSMetrics, MemFSMetricsRecorder, MemFSOp};
+use crate::mmap::{MappedRegion, MappedSource, MappingMode};
+use crate::names::{Name, NameInterner, NameLimits, NameMatching};
+use crate::oplog::{OpLogger, OpRecord};
+use crate::page::{FileContents, IndexedPages};
+use crate::pool::{BlockStore, CompactionReport, MemoryPool, Page};
+use crate::persist::PersistentStore;
+use crate::permission::{DEFAULT_DIRECTORY_MODE, DEFAULT_FILE_MODE, MAY_EXECUTE, MAY_READ, MAY_WRITE, NodeAttributes};
+use crate::pipe::{PIPE_CAPACITY, Pipe, PipeEnd};
+use crate::process::{Credentials, DEFAULT_UMASK, MAIN_PID, MemFSProcess, ProcessState, active_process};
+use crate::quota::{Accounting, Charge, NODE_METADATA_BYTES, Usage};
+use crate::range_lock::{LockWaits, RangeLock, RangeLockConflict, RangeLockKind, RangeLocks};
+use crate::readdir::{ReadDir, ReadDirEntry};
+use crate::removal::{DetachMode, RemovalHandle, RemovalReport};
+use crate::snapshot::{MemFSView, SavedContents, Snapshot, SnapshotId};
+use crate::timestamp::Timestamps;
+use crate::trace::{SyscallArgs, TraceRecorder};
+use crate::trash::Trash;
+use crate::tuning::{MapConfig, MapTuning};
+use crate::utils::{
+    AT_FDCWD, AtFlag, DirEntry, FILE_MAX_SIZE, FallocateMode, FileType, MemFSErr, MemFSErrType, NUMBER_OF_MAXIMUM_FILES, OpenFlag,
+    PAGE_SIZE, RenameFlag, Result, SeekFlag, Stat, StatFs,
+};
+use crate::virtual_file::VirtualFile;
+use crate::watch::{DEFAULT_WATCH_BUFFER, WatchEventKind, WatchHandle, WatchMask, WatchRegistry, WatchStream};
+use crate::writer::{DEFAULT_WRITER_BUFFER, MemFSWriter};
+use std::{
+    borrow::Cow, cell::{Cell, RefCell, UnsafeCell}, sync::{atomic::Ordering, Arc, PoisonError, Weak},
+    thread::{self, ThreadId}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}
+};
+use std::io::{IoSlice, IoSliceMut};
+use std::ptr::NonNull;
 
-use crate::utils::{FILE_MAX_SIZE, MemFSErr, NUMBER_OF_MAXIMUM_FILES, OpenFlag, Result, SeekFlag};
+// With `check-loom`, locks and atomics of the file system are loom's, so that the loom tests go through
+// their interleavings. Arcs stay those of std, as they are downgraded and handed to other modules.
+#[cfg(not(feature = "check-loom"))]
 use std::{
-    cell::UnsafeCell, iter::Peekable, sync::{
-        atomic::{AtomicUsize, Ordering}, Arc, Mutex, RwLock, Weak, RwLockWriteGuard
-    }
+    sync::{Mutex, RwLock, atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize}},
+    thread::yield_now,
 };
+#[cfg(feature = "check-loom")]
+use loom::{
+    sync::{Mutex, RwLock, atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize}},
+    thread::yield_now,
+};
+
+/// Number of symbolic links a single path lookup follows before failing with ELOOP.
+const SYMLINK_FOLLOW_LIMIT: usize = 40;
 
 /// Implementation of In-Memory file system that supports the following system calls:
 /// [open], [close], [unlink], [read], [write], [lseek], [mkdir], [rmdir]
 #[cfg(feature = "coarse-grained")]
 pub struct MemFS {
-    root: Arc<RwLock<MemFSEntry>>,
-    cwd_node: Arc<RwLock<MemFSEntry>>,
-    file_descriptors: Arc<RwLock<HashMap<usize, MemFSFileDescriptor>>>,
-    file_descriptor_count: AtomicUsize,
-    file_memory: Arc<ArrayQueue<Vec<u8>>>,
+    root: NodeArc<PolicyRwLock<MemFSEntry>>,
+    cwd: WorkingDirectory,
+    file_descriptors: Arc<DescriptorShards<PolicyRwLock<HashMap<usize, MemFSFileDescriptor, HashState>>>>,
+    descriptor_numbers: DescriptorNumbers,
+    file_memory: Arc<dyn BlockStore>,
+    metrics: MemFSMetricsRecorder,
+    op_logger: Option<OpLogger>,
+    trace_recorder: Option<TraceRecorder>,
+    maintenance: MaintenanceScheduler,
+    clock: Clock,
+    watchers: WatchRegistry,
+    freeze_gate: FreezeGate,
+
+    /// Gate of [MemFS::lock_exclusive], which operations pass through only once it is enabled.
+    exclusive_gate: Option<ExclusiveGate>,
+    rename_lock: Mutex<()>,
+    crash_tracker: Option<CrashTracker<MemFSNode>>,
+
+    /// Whether nodes count their accesses, as set by [MemFSBuilder::contention_stats].
+    contention_stats: bool,
+    dentry_cache: Option<DentryCache<MemFSNode>>,
+    changes: Option<ChangeLog>,
+    latency: Option<LatencyInjector>,
+    read_only: bool,
+    permission_checks: bool,
+    accounting: Arc<Accounting>,
+    inodes: InodeTable<MemFSWeakNode>,
+    snapshots: Mutex<HashMap<SnapshotId, Snapshot>>,
+
+    /// Contents saved for the snapshot this read-only instance views, if any.
+    snapshot_contents: Option<Arc<SavedContents>>,
+    next_snapshot_id: AtomicUsize,
+    version_policy: VersionPolicy,
+    #[cfg(feature = "compression")]
+    compression: bool,
+    #[cfg(feature = "dedup")]
+    dedup: Option<DedupTable>,
+    journal: Option<JournalRecorder>,
+    trash: Option<Trash<MemFSNode>>,
+    eviction: Eviction,
+
+    /// Files in the order they were used, while eviction is enabled.
+    use_order: UseOrder<MemFSWeakNode>,
+    durability: Option<Arc<dyn DurabilityBackend>>,
+    persistence: Option<Arc<PersistentStore>>,
+    name_limits: NameLimits,
+    lock_waits: LockWaits,
 }
 
 #[cfg(feature = "fine-grained")]
 pub struct MemFS {
-    root: Arc<MemFSEntry>,
-    cwd_node: Arc<MemFSEntry>,
-    file_descriptors: Arc<DashMap<usize, MemFSFileDescriptor>>,
-    file_descriptor_count: AtomicUsize,
-    file_memory: Arc<ArrayQueue<Vec<u8>>>,
+    root: NodeArc<MemFSEntry>,
+    cwd: WorkingDirectory,
+    file_descriptors: Arc<DescriptorShards<DashMap<usize, MemFSFileDescriptor, HashState>>>,
+    descriptor_numbers: DescriptorNumbers,
+    file_memory: Arc<dyn BlockStore>,
+    metrics: MemFSMetricsRecorder,
+    op_logger: Option<OpLogger>,
+    trace_recorder: Option<TraceRecorder>,
+    maintenance: MaintenanceScheduler,
+    clock: Clock,
+    watchers: WatchRegistry,
+    freeze_gate: FreezeGate,
+
+    /// Gate of [MemFS::lock_exclusive], which operations pass through only once it is enabled.
+    exclusive_gate: Option<ExclusiveGate>,
+    rename_lock: Mutex<()>,
+    crash_tracker: Option<CrashTracker<MemFSNode>>,
+
+    /// Whether nodes count their accesses, as set by [MemFSBuilder::contention_stats].
+    contention_stats: bool,
+    dentry_cache: Option<DentryCache<MemFSNode>>,
+    changes: Option<ChangeLog>,
+    latency: Option<LatencyInjector>,
+    read_only: bool,
+    permission_checks: bool,
+    accounting: Arc<Accounting>,
+    inodes: InodeTable<MemFSWeakNode>,
+    snapshots: Mutex<HashMap<SnapshotId, Snapshot>>,
+
+    /// Contents saved for the snapshot this read-only instance views, if any.
+    snapshot_contents: Option<Arc<SavedContents>>,
+    next_snapshot_id: AtomicUsize,
+    version_policy: VersionPolicy,
+    #[cfg(feature = "compression")]
+    compression: bool,
+    #[cfg(feature = "dedup")]
+    dedup: Option<DedupTable>,
+    journal: Option<JournalRecorder>,
+    trash: Option<Trash<MemFSNode>>,
+    eviction: Eviction,
+
+    /// Files in the order they were used, while eviction is enabled.
+    use_order: UseOrder<MemFSWeakNode>,
+    durability: Option<Arc<dyn DurabilityBackend>>,
+    persistence: Option<Arc<PersistentStore>>,
+    name_limits: NameLimits,
+    lock_waits: LockWaits,
 }
 
 #[cfg(feature = "lock-free")]
 pub struct MemFS {
-    root: Arc<MemFSEntry>,
-    cwd_node: Arc<MemFSEntry>,
-    file_descriptors: Arc<LockFreeHashMap<usize, MemFSFileDescriptor>>,
-    file_descriptor_count: AtomicUsize,
-    file_memory: Arc<ArrayQueue<Vec<u8>>>,
+    root: NodeArc<MemFSEntry>,
+    cwd: WorkingDirectory,
+    file_descriptors: Arc<DescriptorShards<LockFreeHashMap<usize, MemFSFileDescriptor, HashState>>>,
+    descriptor_numbers: DescriptorNumbers,
+    file_memory: Arc<dyn BlockStore>,
+    metrics: MemFSMetricsRecorder,
+    op_logger: Option<OpLogger>,
+    trace_recorder: Option<TraceRecorder>,
+    maintenance: MaintenanceScheduler,
+    clock: Clock,
+    watchers: WatchRegistry,
+    freeze_gate: FreezeGate,
+
+    /// Gate of [MemFS::lock_exclusive], which operations pass through only once it is enabled.
+    exclusive_gate: Option<ExclusiveGate>,
+    rename_lock: Mutex<()>,
+    crash_tracker: Option<CrashTracker<MemFSNode>>,
+
+    /// Whether nodes count their accesses, as set by [MemFSBuilder::contention_stats].
+    contention_stats: bool,
+    dentry_cache: Option<DentryCache<MemFSNode>>,
+    changes: Option<ChangeLog>,
+    latency: Option<LatencyInjector>,
+    read_only: bool,
+    permission_checks: bool,
+    accounting: Arc<Accounting>,
+    inodes: InodeTable<MemFSWeakNode>,
+    snapshots: Mutex<HashMap<SnapshotId, Snapshot>>,
+
+    /// Contents saved for the snapshot this read-only instance views, if any.
+    snapshot_contents: Option<Arc<SavedContents>>,
+    next_snapshot_id: AtomicUsize,
+    version_policy: VersionPolicy,
+    #[cfg(feature = "compression")]
+    compression: bool,
+    #[cfg(feature = "dedup")]
+    dedup: Option<DedupTable>,
+    journal: Option<JournalRecorder>,
+    trash: Option<Trash<MemFSNode>>,
+    eviction: Eviction,
+
+    /// Files in the order they were used, while eviction is enabled.
+    use_order: UseOrder<MemFSWeakNode>,
+    durability: Option<Arc<dyn DurabilityBackend>>,
+    persistence: Option<Arc<PersistentStore>>,
+    name_limits: NameLimits,
+    lock_waits: LockWaits,
 }
 
 
-unsafe impl Sync for MemFS {}
-unsafe impl Send for MemFS {}
+#[cfg(feature = "coarse-grained")]
+pub(crate) type MemFSNode = NodeArc<PolicyRwLock<MemFSEntry>>;
 
-impl MemFS {
-    #[cfg(feature = "coarse-grained")]
-    pub fn new() -> Self {
-        let root = Arc::new(RwLock::new(MemFSEntry::Directory(MemFSDirNode::new())));
-        let seg_queue = ArrayQueue::new(NUMBER_OF_MAXIMUM_FILES);
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+pub(crate) type MemFSNode = NodeArc<MemFSEntry>;
 
-        for _ in 0..NUMBER_OF_MAXIMUM_FILES {
-            seg_queue.push(vec![0; FILE_MAX_SIZE]).unwrap();
-        }
+#[cfg(feature = "coarse-grained")]
+type MemFSWeakNode = NodeWeak<PolicyRwLock<MemFSEntry>>;
 
-        Self {
-            root: root.clone(),
-            cwd_node: root,
-            file_descriptors: Arc::new(RwLock::new(HashMap::new())),
-            file_descriptor_count: AtomicUsize::new(0),
-            file_memory: Arc::new(seg_queue),
-        }
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+type MemFSWeakNode = NodeWeak<MemFSEntry>;
+
+#[cfg(feature = "coarse-grained")]
+impl ArenaAllocated for PolicyRwLock<MemFSEntry> {
+    fn arena() -> &'static Arena<Self> {
+        static NODES: Arena<PolicyRwLock<MemFSEntry>> = Arena::new();
+        &NODES
     }
+}
 
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+impl ArenaAllocated for MemFSEntry {
+    fn arena() -> &'static Arena<Self> {
+        static NODES: Arena<MemFSEntry> = Arena::new();
+        &NODES
+    }
+}
 
-    #[cfg(feature = "fine-grained")]
-    pub fn new() -> Self {
-        let root = Arc::new(MemFSEntry::Directory(MemFSDirNode::new()));
-        let seg_queue = ArrayQueue::new(NUMBER_OF_MAXIMUM_FILES);
+#[cfg(feature = "coarse-grained")]
+fn new_node(entry: MemFSEntry) -> MemFSNode {
+    NodeArc::new(PolicyRwLock::new(entry))
+}
 
-        for _ in 0..NUMBER_OF_MAXIMUM_FILES {
-            seg_queue.push(vec![0; FILE_MAX_SIZE]).unwrap();
-        }
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+fn new_node(entry: MemFSEntry) -> MemFSNode {
+    NodeArc::new(entry)
+}
 
-        Self {
-            root: root.clone(),
-            cwd_node: root,
-            file_descriptors: Arc::new(DashMap::new()),
-            file_descriptor_count: AtomicUsize::new(0),
-            file_memory: Arc::new(seg_queue),
+/// Tells whether an access of the file would wait for, or race with, a write in progress.
+#[cfg(feature = "coarse-grained")]
+fn file_is_busy(node: &MemFSNode) -> bool {
+    match node.try_read() {
+        Ok(Some(entry)) => {
+            matches!(&*entry, MemFSEntry::File(file) if file.writers.load(Ordering::SeqCst) > 0)
         }
+        Ok(None) => true,
+        Err(_) => false,
     }
+}
 
-    #[cfg(feature = "lock-free")]
-    pub fn new() -> Self {
-        let root = Arc::new(MemFSEntry::Directory(MemFSDirNode::new()));
-        let seg_queue = ArrayQueue::new(NUMBER_OF_MAXIMUM_FILES);
+/// Tells whether an access of the file would race with a write in progress.
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+fn file_is_busy(node: &MemFSNode) -> bool {
+    matches!(&**node, MemFSEntry::File(file) if file.writers.load(Ordering::SeqCst) > 0)
+}
 
-        for _ in 0..NUMBER_OF_MAXIMUM_FILES {
-            seg_queue.push(vec![0; FILE_MAX_SIZE]).unwrap();
-        }
+#[cfg(feature = "coarse-grained")]
+fn new_root(options: &MemFSBuilder) -> MemFSNode {
+    let root = MemFSDirNode {
+        contention: options.contention_stats.then(Arc::default),
+        ..MemFSDirNode::configured(options.lock_policy, options.map_config())
+    };
+
+    NodeArc::new(PolicyRwLock::with_policy(
+        MemFSEntry::Directory(root),
+        options.lock_policy,
+    ))
+}
 
-        Self {
-            root: root.clone(),
-            cwd_node: root,
-            file_descriptors: Arc::new(LockFreeHashMap::new()),
-            file_descriptor_count: AtomicUsize::new(0),
-            file_memory: Arc::new(seg_queue),
-        }
-    }
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+fn new_root(options: &MemFSBuilder) -> MemFSNode {
+    let root = MemFSDirNode {
+        contention: options.contention_stats.then(Arc::default),
+        ..MemFSDirNode::configured(options.lock_policy, options.map_config())
+    };
 
-    #[cfg(feature = "coarse-grained")]
-        pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
-        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
-        if !flag.check_mode_exclusiveness() {
-            return Err(MemFSErr::invalid_value());
-        }
+    new_node(MemFSEntry::Directory(root))
+}
 
-        if flag.contains(OpenFlag::O_CREAT) {
-            self.create(path, OpenFlag::O_EXCL & (flag.clone()), self.allocate_file_memory()?)?;
-        }
+/// Map configuration of the root directory, which the file descriptor table uses as well.
+fn root_maps(root: &MemFSNode) -> MapConfig {
+    with_entry(root, |entry| match entry {
+        MemFSEntry::Directory(dir) => dir.maps.clone(),
+        _ => MapConfig::default(),
+    })
+    .unwrap_or_default()
+}
 
-        let item_node = self.get_node_of_given_path(path)?;
+/// Creates the file descriptor table, using the lock policy and map configuration of the root.
+#[cfg(feature = "coarse-grained")]
+fn new_descriptor_table(
+    root: &MemFSNode,
+) -> Arc<DescriptorShards<PolicyRwLock<HashMap<usize, MemFSFileDescriptor, HashState>>>> {
+    let maps = root_maps(root);
+    let shards = maps.tuning.descriptor_shards.max(1);
+
+    Arc::new(DescriptorShards::new(shards, || {
+        PolicyRwLock::with_policy(maps.new_map(maps.tuning.descriptor_capacity.div_ceil(shards)), root.policy())
+    }))
+}
 
-        let item_guard = item_node.read().map_err(|_| MemFSErr::poisoned_lock())?;
+/// Creates the file descriptor table, using the map configuration of the root.
+#[cfg(feature = "fine-grained")]
+fn new_descriptor_table(root: &MemFSNode) -> Arc<DescriptorShards<DashMap<usize, MemFSFileDescriptor, HashState>>> {
+    let maps = root_maps(root);
+    let shards = maps.tuning.descriptor_shards.max(1);
 
-        match &*item_guard {
+    Arc::new(DescriptorShards::new(shards, || {
+        maps.new_map(maps.tuning.descriptor_capacity.div_ceil(shards))
+    }))
+}
 
-            MemFSEntry::File(_) => {
-                let fd = self.allocate_file_descriptor()?;
-                let mut guard = self
-                    .file_descriptors
-                    .write()
-                    .map_err(|_| MemFSErr::poisoned_lock())?;
+/// Creates the file descriptor table, using the map configuration of the root.
+#[cfg(feature = "lock-free")]
+fn new_descriptor_table(
+    root: &MemFSNode,
+) -> Arc<DescriptorShards<LockFreeHashMap<usize, MemFSFileDescriptor, HashState>>> {
+    let maps = root_maps(root);
+    let shards = maps.tuning.descriptor_shards.max(1);
+
+    Arc::new(DescriptorShards::new(shards, || {
+        maps.new_map(maps.tuning.descriptor_capacity.div_ceil(shards))
+    }))
+}
 
-                guard.insert(
-                    fd,
-                    MemFSFileDescriptor::new(fd, flag & !(OpenFlag::O_CREAT), item_node.clone()),
-                );
+/// Identity of a node, stable while the node is alive.
+fn node_key(node: &MemFSNode) -> usize {
+    NodeArc::as_ptr(node) as *const () as usize
+}
 
-                Ok(fd)
-            }
-            _ => Err(MemFSErr::is_directory()),
+/// Runs `f` on the entry behind the node, holding the read lock of the node if there is one.
+#[cfg(feature = "coarse-grained")]
+fn with_entry<R>(node: &MemFSNode, f: impl FnOnce(&MemFSEntry) -> R) -> Result<R> {
+    let guard = node.read().map_err(|_| MemFSErr::poisoned_lock())?;
+    Ok(f(&guard))
+}
+
+/// Runs `f` on the entry behind the node, holding the read lock of the node if there is one.
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+fn with_entry<R>(node: &MemFSNode, f: impl FnOnce(&MemFSEntry) -> R) -> Result<R> {
+    Ok(f(node))
+}
+
+/// Splits a path into its components, skipping empty ones and `.`.
+fn path_components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|x| !x.is_empty() && *x != ".")
+}
+
+/// Returns the first component of `path` at or after the byte `from`, skipping empty ones and `.`,
+/// along with the byte where it ends.
+fn next_component(path: &str, from: usize) -> Option<(&str, usize)> {
+    let mut start = from;
+
+    while start < path.len() {
+        let end = path[start..].find('/').map_or(path.len(), |i| start + i);
+
+        match &path[start..end] {
+            "" | "." => start = end + 1,
+            name => return Some((name, end)),
         }
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
-        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
-        if !flag.check_mode_exclusiveness() {
-            return Err(MemFSErr::invalid_value());
-        }
+    None
+}
 
-        let parent_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+/// Returns the byte where the last component of `path` starts, if it has any.
+fn last_component_start(path: &str) -> Option<usize> {
+    let mut last = None;
+    let mut from = 0;
 
-        match self.resolve_dir_and_entry(last_elem, &*parent_node)? {
-            Entry::Vacant(v) => {
-                if flag.contains(OpenFlag::O_CREAT) {
-                    // If the entry is empty and O_CREAT is specified, add the file entry.
-                    let memory_block = self.allocate_file_memory()?;
-                    let file_node = Arc::new(MemFSEntry::File(MemFSFileNode::new(memory_block)));
+    while let Some((name, end)) = next_component(path, from) {
+        last = Some(end - name.len());
+        from = end;
+    }
 
-                    let fd = self.allocate_file_descriptor()?;
+    last
+}
 
-                    v.insert(file_node.clone());
+/// Node standing for the parent of the root, which `..` at the end of a path resolves to.
+#[cfg(feature = "coarse-grained")]
+fn resolved_as_root() -> MemFSNode {
+    NodeArc::new(PolicyRwLock::new(MemFSEntry::ResolvedAsRoot))
+}
 
-                    self.file_descriptors.insert(
-                        fd,
-                        MemFSFileDescriptor::new(fd, flag & !(OpenFlag::O_CREAT), file_node),
-                    );
+/// Node standing for the parent of the root, which `..` at the end of a path resolves to.
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+fn resolved_as_root() -> MemFSNode {
+    NodeArc::new(MemFSEntry::ResolvedAsRoot)
+}
 
-                    Ok(fd)
-                } else {
-                    Err(MemFSErr::no_such_file_or_directory())
-                }
-            }
-            Entry::Occupied(v) => {
-                if flag.contains(OpenFlag::O_CREAT | OpenFlag::O_EXCL) {
-                    Err(MemFSErr::already_exists())
-                } else {
-                    let file_node = v.get();
+/// State of a path lookup, resolving the symbolic links met on the way.
+///
+/// The same walk serves every backend: it goes down one component at a time, holding nothing but
+/// the node of the current directory between two steps, and borrows the components from the path.
+/// Only symbolic links make it allocate, as their target is joined with the rest of the path.
+struct PathLookup<'a> {
+    root: &'a MemFSNode,
 
-                    match &**file_node {
-                        MemFSEntry::File(_) => {
-                            let fd = self.allocate_file_descriptor()?;
+    /// Whether a symbolic link at the last component is followed, or returned as is.
+    follow_last: bool,
 
-                            self.file_descriptors.insert(
-                                fd,
-                                MemFSFileDescriptor::new(
-                                    fd,
-                                    flag & !(OpenFlag::O_CREAT),
-                                    file_node.clone(),
-                                ),
-                            );
+    /// Number of symbolic links followed so far.
+    followed: Cell<usize>,
+}
 
-                            Ok(fd)
-                        }
-                        _ => Err(MemFSErr::is_directory()),
-                    }
-                }
-            }
+impl<'a> PathLookup<'a> {
+    fn new(root: &'a MemFSNode, follow_last: bool) -> Self {
+        Self {
+            root,
+            follow_last,
+            followed: Cell::new(0),
         }
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
-        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
-        if !flag.check_mode_exclusiveness() {
-            return Err(MemFSErr::invalid_value());
-        }
+    /// Returns `found`, the last component of the path looked up in `dir`, following it if it is a symbolic link.
+    fn found(&self, dir: &MemFSNode, found: &MemFSNode) -> Result<MemFSNode> {
+        let target = with_entry(found, |entry| match entry {
+            MemFSEntry::Symlink(link) if self.follow_last => Some(link.target.clone()),
+            _ => None,
+        })?;
 
-        let parent_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+        match target {
+            Some(target) => {
+                self.count_follow()?;
 
-        let parent_pin = self.resolve_open_dir(&parent_node)?;
-        
-        // Check if there is already a file.
-        match parent_pin.get(last_elem) {
-            Some(f) => {
-                if flag.contains(OpenFlag::O_CREAT | OpenFlag::O_EXCL) {
-                    Err(MemFSErr::already_exists())
-                }
-                else {
-                    match &**f {
-                        MemFSEntry::File(_) => {
-                            let fd = self.allocate_file_descriptor()?;
-                            let descriptor = MemFSFileDescriptor::new(
-                                fd,
-                                flag & !(OpenFlag::O_CREAT),
-                                f.clone()
-                            );
+                let start = if target.starts_with('/') { self.root } else { dir };
+                self.walk(start, &target)
+            }
+            None => Ok(found.clone()),
+        }
+    }
 
-                            self.file_descriptors.pin().insert(fd, descriptor);
+    /// Walks `path` from the directory `start`, relative paths included, and returns the node it names.
+    /// `..` goes to the parent directory, and stays at the root, except as the last component where it
+    /// resolves to [MemFSEntry::ResolvedAsRoot].
+    fn walk(&self, start: &MemFSNode, path: &str) -> Result<MemFSNode> {
+        let mut dir = self.directory(start)?;
+        let mut rest = Cow::Borrowed(path);
+        let mut position = 0;
+
+        while let Some((name, end)) = next_component(&rest, position) {
+            let last = next_component(&rest, end).is_none();
+            let child = match name {
+                ".." => self.parent(&dir, last)?,
+                _ => with_entry(&dir, |entry| match entry {
+                    MemFSEntry::Directory(dir) => dir.child(name),
+                    _ => Err(MemFSErr::is_not_directory()),
+                })??
+                .ok_or(MemFSErr::no_such_file_or_directory())?,
+            };
 
-                            Ok(fd)
-                        },
-                        _ => Err(MemFSErr::is_directory()),
-                    }
+            let target = with_entry(&child, |entry| match entry {
+                MemFSEntry::Symlink(link) if !last || self.follow_last => Ok(Some(link.target.clone())),
+                MemFSEntry::File(_)
+                | MemFSEntry::Symlink(_)
+                | MemFSEntry::Device(_)
+                | MemFSEntry::Fifo(_)
+                | MemFSEntry::Virtual(_) if !last => {
+                    Err(MemFSErr::is_not_directory())
                 }
-            },
-            None => {
-                if flag.contains(OpenFlag::O_CREAT) {
-                    // If the entry is empty and O_CREAT is specified, add the file entry.
-                    let memory_block = self.allocate_file_memory()?;
-                    let file_node = Arc::new(MemFSEntry::File(MemFSFileNode::new(memory_block)));
+                _ => Ok(None),
+            })??;
 
-                    let fd = self.allocate_file_descriptor()?;
-                    let descriptor = MemFSFileDescriptor::new(fd, flag & !(OpenFlag::O_CREAT), file_node.clone());
+            match target {
+                Some(target) => {
+                    self.count_follow()?;
 
-                    parent_pin.insert(last_elem.to_string(), file_node);
-                    self.file_descriptors.pin().insert(fd, descriptor);
+                    if target.starts_with('/') {
+                        dir = self.root.clone();
+                    }
 
-                    Ok(fd)
+                    rest = Cow::Owned(format!("{}/{}", target, &rest[end..]));
+                    position = 0;
                 }
-                else {
-                    Err(MemFSErr::no_such_file_or_directory())
+                None if last => return Ok(child),
+                None => {
+                    dir = child;
+                    position = end;
                 }
             }
         }
-    }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn close(&self, fd: usize) -> Result<()> {
-        let mut guard = self
-            .file_descriptors
-            .write()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+        Ok(dir)
+    }
 
-        if guard.contains_key(&fd) {
-            guard.remove(&fd);
-            Ok(())
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
+    /// Returns the parent of `dir`, or `dir` itself if it is the root and `..` is not the last component.
+    fn parent(&self, dir: &MemFSNode, last: bool) -> Result<MemFSNode> {
+        let parent = with_entry(dir, |entry| match entry {
+            MemFSEntry::Directory(dir) => Ok(dir.parent()),
+            _ => Err(MemFSErr::is_not_directory()),
+        })??;
+
+        match parent {
+            // The root of a process confined with chroot has a parent, which `..` never goes up to.
+            Some(_) if node_key(dir) == node_key(self.root) => Ok(dir.clone()),
+            Some(parent) => parent.upgrade().ok_or(MemFSErr::no_such_file_or_directory()),
+            None if last => Ok(resolved_as_root()),
+            None => Ok(dir.clone()),
         }
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn close(&self, fd: usize) -> Result<()> {
-        let entry = self.file_descriptors.entry(fd);
-        match entry {
-            Entry::Occupied(e) => {
-                e.remove();
-                Ok(())
-            },
-            Entry::Vacant(_) => Err(MemFSErr::bad_file_descriptor())
+    /// Maps [MemFSEntry::ResolvedAsRoot] to the root, which is where a walk from it goes on.
+    fn directory(&self, node: &MemFSNode) -> Result<MemFSNode> {
+        match with_entry(node, |entry| matches!(entry, MemFSEntry::ResolvedAsRoot))? {
+            true => Ok(self.root.clone()),
+            false => Ok(node.clone()),
         }
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn close(&self, fd: usize) -> Result<()> {
-        // let entry = self.file_descriptors.pin().entry(fd);
+    fn count_follow(&self) -> Result<()> {
+        let followed = self.followed.get() + 1;
 
-        match self.file_descriptors.pin().remove(&fd) {
-            Some(_) => Ok(()),
-            None => Err(MemFSErr::bad_file_descriptor()),
+        if followed > SYMLINK_FOLLOW_LIMIT {
+            return Err(MemFSErr::too_many_links());
         }
-    }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn unlink(&self, path: &str) -> Result<()> {
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
-        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+        self.followed.set(followed);
 
-        match &*dir_guard {
-            MemFSEntry::Directory(dir) => dir.remove_file(last_elem),
+        Ok(())
+    }
+}
 
+/// Checks that a rename may replace `existing` with a directory if `is_dir`, or with a file otherwise.
+/// Without `replace`, nothing may be replaced.
+fn check_replacement(existing: &MemFSNode, is_dir: bool, replace: bool) -> Result<()> {
+    if !replace {
+        return Err(MemFSErr::already_exists());
+    }
 
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => {
-                let root_guard = self.root.write().map_err(|_| MemFSErr::poisoned_lock())?;
+    with_entry(existing, |entry| match entry {
+        MemFSEntry::Directory(_) if !is_dir => Err(MemFSErr::is_directory()),
+        MemFSEntry::Directory(dir) => match dir.child_count()? {
+            0 => Ok(()),
+            _ => Err(MemFSErr::is_not_empty()),
+        },
+        _ if is_dir => Err(MemFSErr::is_not_directory()),
+        _ => Ok(()),
+    })?
+}
 
-                if let MemFSEntry::Directory(dir) = &*root_guard {
-                    dir.remove_file(last_elem)
-                } else {
-                    Err(MemFSErr::no_such_file_or_directory())
-                }
-            }
+/// Takes a removed directory entry off the link count of a file.
+fn drop_link(node: &MemFSNode) {
+    let _ = with_entry(node, |entry| {
+        if let MemFSEntry::File(file) = entry {
+            file.links.fetch_sub(1, Ordering::AcqRel);
         }
+    });
+}
+
+/// Working directory, as a node to resolve relative paths from and its absolute path.
+#[derive(Clone)]
+pub(crate) struct CurrentDirectory {
+    node: MemFSNode,
+    path: String,
+}
+
+thread_local! {
+    /// Directory which relative paths resolve from during an *at call on this thread,
+    /// with the address of the file system it belongs to.
+    static AT_DIRECTORY: RefCell<Option<(usize, CurrentDirectory)>> = const { RefCell::new(None) };
+}
+
+/// Restores the directory which relative paths resolved from before an *at call.
+struct AtScope {
+    previous: Option<(usize, CurrentDirectory)>,
+}
+
+impl Drop for AtScope {
+    fn drop(&mut self) {
+        AT_DIRECTORY.with(|at| *at.borrow_mut() = self.previous.take());
     }
+}
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    pub fn unlink(&self, path: &str) -> Result<()> {
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+enum WorkingDirectory {
+    /// One working directory for every thread, like a single process.
+    Shared(RwLock<CurrentDirectory>),
 
-        match &*dir_node {
-            MemFSEntry::Directory(dir) => dir.remove_file(last_elem),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => {
-                if let MemFSEntry::Directory(dir) = &*self.root {
-                    dir.remove_file(last_elem)
-                } else {
-                    Err(MemFSErr::no_such_file_or_directory())
-                }
-            }
-        }
+    /// Working directory of each thread. Threads which never changed it are at the root.
+    PerThread(DashMap<ThreadId, CurrentDirectory>),
+}
+
+/// Builder of [MemFS] instances with non-default options.
+#[derive(Default)]
+pub struct MemFSBuilder {
+    thread_local_cwd: bool,
+    crash_simulation: bool,
+    exclusive_lock: bool,
+    lock_policy: LockPolicy,
+    contention_stats: bool,
+    dentry_cache: Option<usize>,
+    hasher: MemFSHasher,
+    map_tuning: MapTuning,
+    name_matching: NameMatching,
+    name_limits: NameLimits,
+    max_open_files: Option<usize>,
+    change_tracking: bool,
+    latency: Option<LatencyProfile>,
+    permission_checks: bool,
+    quota: Option<u64>,
+    versions: VersionPolicy,
+    journal: bool,
+    trash: bool,
+    clock: Option<VirtualClock>,
+    durability: Option<Arc<dyn DurabilityBackend>>,
+    eviction: Eviction,
+    block_store: Option<Arc<dyn BlockStore>>,
+    #[cfg(feature = "compression")]
+    compression: bool,
+    #[cfg(feature = "dedup")]
+    dedup: bool,
+}
+
+impl MemFSBuilder {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn read(&self, fd: usize, buffer: &mut Vec<u8>, size: usize) -> Result<usize> {
-        let fd_map = self
-            .file_descriptors
-            .read()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Gives every thread its own working directory, starting at the root, so that threads sharing
+    /// a single MemFS can chdir independently. Entries of threads which exited are not reclaimed.
+    pub fn thread_local_cwd(mut self, enabled: bool) -> Self {
+        self.thread_local_cwd = enabled;
+        self
+    }
 
-        if let Some(v) = fd_map.get(&fd) {
-            unsafe { v.read_file(buffer, size) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Tracks writes which are not fsynced yet, so that a power failure can be simulated with [MemFS::crash].
+    /// Writes are serialized while it is enabled.
+    pub fn crash_simulation(mut self, enabled: bool) -> Self {
+        self.crash_simulation = enabled;
+        self
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn read(&self, fd: usize, buffer: &mut Vec<u8>, size: usize) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.get(&fd) {
-            unsafe { v.read_file(buffer, size) }
-        }
-        else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Lets a thread hold every other operation off with [MemFS::lock_exclusive]. Every operation passes
+    /// through a gate while it is enabled, which takes a pair of atomic operations on a counter shared by
+    /// every thread. Without it, operations skip the gate and [MemFS::lock_exclusive] fails with EINVAL.
+    pub fn exclusive_lock(mut self, enabled: bool) -> Self {
+        self.exclusive_lock = enabled;
+        self
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn read(&self, fd: usize, buffer: &mut Vec<u8>, size: usize) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.pin().get(&fd) {
-            unsafe { v.read_file(buffer, size) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Sets the fairness policy of the node, directory and file descriptor table locks.
+    /// Only the coarse-grained backend has such locks; other backends ignore it.
+    pub fn lock_policy(mut self, policy: LockPolicy) -> Self {
+        self.lock_policy = policy;
+        self
     }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn write(&self, fd: usize, buffer: &Vec<u8>, size: usize) -> Result<usize> {
-        let fd_map = self
-            .file_descriptors
-            .read()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Counts accesses and contended accesses of every directory and file,
+    /// to be reported by [MemFS::hot_nodes].
+    pub fn contention_stats(mut self, enabled: bool) -> Self {
+        self.contention_stats = enabled;
+        self
+    }
 
-        if let Some(v) = fd_map.get(&fd) {
-            unsafe { v.write_file(buffer, size) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Caches the nodes found by lookups of up to `capacity` absolute paths, so that repeated calls on
+    /// the same path skip the walk of the tree. Removals, renames and replacements empty the cache.
+    /// Hits and misses are reported by [MemFS::dentry_cache_stats].
+    pub fn dentry_cache(mut self, capacity: usize) -> Self {
+        self.dentry_cache = Some(capacity);
+        self
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn write(&self, fd: usize, buffer: &Vec<u8>, size: usize) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.get(&fd) {
-            unsafe { v.write_file(buffer, size) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Sets the hash function of directories and the file descriptor table, for every backend.
+    pub fn hasher(mut self, hasher: MemFSHasher) -> Self {
+        self.hasher = hasher;
+        self
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn write(&self, fd: usize, buffer: &Vec<u8>, size: usize) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.pin().get(&fd) {
-            unsafe { v.write_file(buffer, size) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
-    }    
+    /// Sets shard counts, initial capacities and the resize mode of directories and the file descriptor table,
+    /// and how many shards the file descriptor table is split in.
+    pub fn map_tuning(mut self, tuning: MapTuning) -> Self {
+        self.map_tuning = tuning;
+        self
+    }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn lseek(&self, fd: usize, offset: usize, flag: SeekFlag) -> Result<usize> {
-        let fd_map = self
-            .file_descriptors
-            .read()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Sets which names of directory entries refer to the same entry, such as names differing only by case
+    /// to emulate the file systems of Windows or macOS. Names must match exactly by default.
+    pub fn name_matching(mut self, matching: NameMatching) -> Self {
+        self.name_matching = matching;
+        self
+    }
 
-        if let Some(v) = fd_map.get(&fd) {
-            unsafe { v.seek_file(offset, flag) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Sets the longest names and paths, and which characters names may hold. Paths breaking the limits fail
+    /// with ENAMETOOLONG or EINVAL, as on Linux whose limits are the default. See [NameLimits].
+    pub fn name_limits(mut self, limits: NameLimits) -> Self {
+        self.name_limits = limits;
+        self
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn lseek(&self, fd: usize, offset: usize, flag: SeekFlag) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.get(&fd) {
-            unsafe { v.seek_file(offset, flag) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Caps open file descriptors as RLIMIT_NOFILE does: descriptors are numbered below `limit`, and opening
+    /// a file or directory once they are all in use fails with EMFILE. Descriptors are unlimited by default.
+    pub fn max_open_files(mut self, limit: usize) -> Self {
+        self.max_open_files = Some(limit);
+        self
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn lseek(&self, fd: usize, offset: usize, flag: SeekFlag) -> Result<usize> {
-        if let Some(v) = self.file_descriptors.pin().get(&fd) {
-            unsafe { v.seek_file(offset, flag) }
-        } else {
-            Err(MemFSErr::bad_file_descriptor())
-        }
+    /// Makes [MemFS::unlink] move entries into a hidden trash instead of freeing them, so that
+    /// [MemFS::undelete] can put them back until [MemFS::purge_trash] frees them. Entries in the trash keep
+    /// their memory and count against the quota.
+    pub fn trash(mut self, enabled: bool) -> Self {
+        self.trash = enabled;
+        self
     }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn mkdir(&self, path: &str) -> Result<()> {
-        if path == "/" {
-            return Err(MemFSErr::already_exists());
-        }
+    /// Keeps the latest change of every path, to be queried with [MemFS::changes_since],
+    /// and stamps changed nodes with its sequence number, as reported by [MemFS::stat].
+    pub fn change_tracking(mut self, enabled: bool) -> Self {
+        self.change_tracking = enabled;
+        self
+    }
 
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+    /// Delays system calls and caps their bandwidth as set by `profile`, to emulate a slow device.
+    pub fn latency(mut self, profile: LatencyProfile) -> Self {
+        self.latency = Some(profile);
+        self
+    }
 
-        if last_elem == "." || last_elem == ".." {
-            return Err(MemFSErr::already_exists());
-        }
+    /// Checks permission bits on open, unlink, mkdir, rmdir, rename, link and symlink, failing with EACCES.
+    /// Calls made directly on the MemFS act as the superuser, which passes every check; calls made through
+    /// a [crate::process::MemFSProcess] act as its credentials.
+    pub fn permission_checks(mut self, enabled: bool) -> Self {
+        self.permission_checks = enabled;
+        self
+    }
 
-        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Caps the bytes used by file contents and metadata, as reported by [MemFS::usage].
+    /// Writes, truncations and creations which would exceed it fail with ENOSPC.
+    pub fn quota(mut self, bytes: u64) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
 
-        match &*dir_guard {
-            MemFSEntry::Directory(dir) => dir.create_new_directory(last_elem, dir_node.clone()),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::already_exists()),
-        }
+    /// Saves the contents of a file as a new version before every write or truncation of it,
+    /// as [MemFS::checkpoint] does. Each saved version is a full copy of the file.
+    pub fn version_on_write(mut self, enabled: bool) -> Self {
+        self.versions.on_write = enabled;
+        self
     }
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]    
-    pub fn mkdir(&self, path: &str) -> Result<()> {
-        if path == "/" {
-            return Err(MemFSErr::already_exists());
-        }
+    /// Keeps at most `count` versions of each file, dropping the oldest ones. Versions are unlimited by default.
+    pub fn max_versions(mut self, count: usize) -> Self {
+        self.versions.limit = Some(count);
+        self
+    }
 
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+    /// Records every change of the tree and of file contents, to be taken with [MemFS::journal] and
+    /// replayed onto another MemFS. Mutating operations are serialized while it is enabled.
+    pub fn journal(mut self, enabled: bool) -> Self {
+        self.journal = enabled;
+        self
+    }
 
-        if last_elem == "." || last_elem == ".." {
-            return Err(MemFSErr::already_exists());
-        }
+    /// Runs the file system on `clock` instead of the system clock: files are stamped with its time, maintenance
+    /// tasks run as it is advanced, and timeouts and injected latencies pass on it, so that scenarios do not
+    /// depend on how threads are scheduled. The test keeps a clone of the clock to advance it.
+    pub fn virtual_clock(mut self, clock: VirtualClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
 
-        match &*dir_node {
-            MemFSEntry::Directory(dir) => dir.create_new_directory(last_elem, dir_node.clone()),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::already_exists()),
-        }
+    /// Hands files to `backend` as they are synced by [MemFS::fsync], [MemFS::fdatasync] and [MemFS::sync],
+    /// so that it persists them, for instance in a directory of the host or in a log.
+    pub fn durability(mut self, backend: impl DurabilityBackend + 'static) -> Self {
+        self.durability = Some(Arc::new(backend));
+        self
     }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn rmdir(&self, path: &str) -> Result<()> {
-        if path == "/" {
-            return Err(MemFSErr::busy());
-        }
+    /// Makes writes which find the block store full evict files instead of failing with ENOMEM,
+    /// as described in [Eviction]. Evicted files do not go to the trash.
+    pub fn eviction(mut self, eviction: Eviction) -> Self {
+        self.eviction = eviction;
+        self
+    }
 
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+    /// Takes the pages of file contents from `store` instead of a pool of [NUMBER_OF_MAXIMUM_FILES] pages
+    /// in memory, for instance to put those beyond some memory on disk with [crate::pool::OverflowStore], so that
+    /// files can outgrow the memory. Trees detached with [DetachMode::Copy] still get a pool in memory.
+    pub fn block_store(mut self, store: impl BlockStore + 'static) -> Self {
+        self.block_store = Some(Arc::new(store));
+        self
+    }
 
-        if last_elem == "." {
-            return Err(MemFSErr::invalid_value());
-        } else if last_elem == ".." {
-            return Err(MemFSErr::is_not_empty());
-        }
+    /// Compresses the contents of files created afterwards, once they outgrow the inline space. See
+    /// [MemFS::set_compression] to switch a single file.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
 
-        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Hashes the contents of files when they are closed or fsynced, and makes files with identical
+    /// contents share a single copy of them, outside of the memory pool, until they are written again.
+    /// Bytes saved are reported by [MemFS::dedup_stats].
+    #[cfg(feature = "dedup")]
+    pub fn dedup(mut self, enabled: bool) -> Self {
+        self.dedup = enabled;
+        self
+    }
 
-        match &*dir_guard {
-            MemFSEntry::Directory(dir) => dir.remove_directory(last_elem),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::busy()),
+    fn map_config(&self) -> MapConfig {
+        MapConfig {
+            hasher: HashState::new(self.hasher),
+            tuning: self.map_tuning,
+            names: self.name_matching,
+            interner: NameInterner::default(),
         }
     }
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    pub fn rmdir(&self, path: &str) -> Result<()> {
-        if path == "/" {
-            return Err(MemFSErr::busy());
+    pub fn build(self) -> MemFS {
+        let clock = self.clock.clone().map_or_else(Clock::default, Clock::Virtual);
+        let _clock = clock.enter();
+
+        let mut fs = MemFS::with_root(
+            new_root(&self),
+            self.block_store
+                .clone()
+                .unwrap_or_else(|| Arc::new(MemoryPool::with_preallocated(NUMBER_OF_MAXIMUM_FILES))),
+            false,
+            clock.clone(),
+        );
+
+        fs.contention_stats = self.contention_stats;
+        fs.dentry_cache = self.dentry_cache.map(DentryCache::new);
+        fs.changes = self.change_tracking.then(ChangeLog::new);
+        fs.latency = self.latency.map(|profile| LatencyInjector::new(profile, clock));
+        fs.permission_checks = self.permission_checks;
+        fs.accounting = Arc::new(Accounting::new(self.quota));
+        fs.version_policy = self.versions;
+        fs.journal = self.journal.then(JournalRecorder::default);
+        fs.trash = self.trash.then(Trash::new);
+        fs.durability = self.durability;
+        fs.eviction = self.eviction;
+        fs.name_limits = self.name_limits;
+        fs.exclusive_gate = self.exclusive_lock.then(ExclusiveGate::new);
+
+        if let Some(limit) = self.max_open_files {
+            fs.descriptor_numbers = DescriptorNumbers::with_limit(self.map_tuning.descriptor_shards, limit);
         }
 
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
+        #[cfg(feature = "compression")]
+        {
+            fs.compression = self.compression;
+        }
 
-        if last_elem == "." {
-            return Err(MemFSErr::invalid_value());
-        } else if last_elem == ".." {
-            return Err(MemFSErr::is_not_empty());
+        #[cfg(feature = "dedup")]
+        {
+            fs.dedup = self.dedup.then(DedupTable::default);
         }
 
-        match &*dir_node {
-            MemFSEntry::Directory(dir) => dir.remove_directory(last_elem),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::busy()),
+        if self.crash_simulation {
+            fs.crash_tracker = Some(CrashTracker::new());
         }
-    }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn chdir(&mut self, path: &str) -> Result<()> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        } else if path == "/" {
-            self.cwd_node = self.root.clone();
-            return Ok(());
+        if self.thread_local_cwd {
+            fs.cwd = WorkingDirectory::PerThread(DashMap::new());
         }
 
-        let dir_node = self.get_node_of_given_path(path)?;
-        let dir_guard = dir_node.read().map_err(|_| MemFSErr::poisoned_lock())?;
+        fs
+    }
+}
 
-        match &*dir_guard {
-            MemFSEntry::Directory(_) => {
-                self.cwd_node = dir_node.clone();
-                Ok(())
-            }
-            MemFSEntry::ResolvedAsRoot => {
-                self.cwd_node = self.root.clone();
-                Ok(())
-            }
-            _ => Err(MemFSErr::is_not_directory()),
+unsafe impl Sync for MemFS {}
+unsafe impl Send for MemFS {}
+
+/// Writes the tree of a file system opened with [MemFS::open_persistent] to its backing file.
+/// Errors cannot be reported from there: call [MemFS::sync] first to see them.
+impl Drop for MemFS {
+    fn drop(&mut self) {
+        if let Some(store) = self.persistence.take() {
+            let _ = self.persist(&store);
         }
     }
+}
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    pub fn chdir(&mut self, path: &str) -> Result<()> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        } else if path == "/" {
-            self.cwd_node = self.root.clone();
-            return Ok(());
-        }
+impl MemFS {
+    pub fn new() -> Self {
+        MemFSBuilder::new().build()
+    }
 
-        let dir_node = self.get_node_of_given_path(path)?;
+    pub fn builder() -> MemFSBuilder {
+        MemFSBuilder::new()
+    }
 
-        match &*dir_node {
-            MemFSEntry::Directory(_) => {
-                self.cwd_node = dir_node.clone();
+    /// Builds an empty file system whose files take their pages from the backing file of `store`.
+    pub(crate) fn with_persistence(store: Arc<PersistentStore>) -> Self {
+        let mut fs = MemFSBuilder::new().block_store(store.clone()).exclusive_lock(true).build();
+        fs.persistence = Some(store);
 
-                Ok(())
+        fs
+    }
+
+    /// Builds a file system around an existing tree, whose inode numbers are kept.
+    fn with_root(root: MemFSNode, file_memory: Arc<dyn BlockStore>, read_only: bool, clock: Clock) -> Self {
+        let descriptor_shards = root_maps(&root).tuning.descriptor_shards;
+        let fs = Self {
+            file_descriptors: new_descriptor_table(&root),
+            root: root.clone(),
+            cwd: WorkingDirectory::Shared(RwLock::new(CurrentDirectory {
+                node: root,
+                path: "/".to_string(),
+            })),
+            descriptor_numbers: DescriptorNumbers::new(descriptor_shards),
+            file_memory,
+            metrics: MemFSMetricsRecorder::default(),
+            op_logger: None,
+            trace_recorder: None,
+            maintenance: MaintenanceScheduler::with_clock(clock.clone()),
+            clock,
+            watchers: WatchRegistry::default(),
+            freeze_gate: FreezeGate::new(),
+            exclusive_gate: None,
+            rename_lock: Mutex::new(()),
+            crash_tracker: None,
+            contention_stats: false,
+            dentry_cache: None,
+            changes: None,
+            latency: None,
+            read_only,
+            permission_checks: false,
+            accounting: Arc::new(Accounting::new(None)),
+            inodes: InodeTable::new(ROOT_INO + 1),
+            snapshots: Mutex::default(),
+            snapshot_contents: None,
+            next_snapshot_id: AtomicUsize::new(0),
+            version_policy: VersionPolicy::default(),
+            #[cfg(feature = "compression")]
+            compression: false,
+            #[cfg(feature = "dedup")]
+            dedup: None,
+            journal: None,
+            trash: None,
+            eviction: Eviction::Disabled,
+            use_order: UseOrder::new(),
+            durability: None,
+            persistence: None,
+            name_limits: NameLimits::default(),
+            lock_waits: LockWaits::default(),
+        };
+
+        fs.register_subtree(&fs.root, "");
+
+        fs
+    }
+
+    pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        self.syscall(SyscallArgs::Open { path, flag: flag.bits() }, || {
+            if self.read_only && !flag.contains(OpenFlag::O_RDONLY) {
+                return Err(MemFSErr::read_only_file_system());
             }
-            MemFSEntry::ResolvedAsRoot => {
-                self.cwd_node = self.root.clone();
 
-                Ok(())
+            if flag.contains(OpenFlag::O_SNAPSHOT) && !flag.contains(OpenFlag::O_RDONLY) {
+                return Err(MemFSErr::invalid_value());
             }
-            _ => Err(MemFSErr::is_not_directory()),
-        }
-    }
 
-    #[cfg(feature = "coarse-grained")]
-    fn create(&self, path: &str, flag: OpenFlag, space: Vec<u8>) -> Result<()> {
-        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
-        let last_elem = Self::get_last_component_of_path(path)?;
-        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+            if flag.contains(OpenFlag::O_DIRECTORY) {
+                return self.open_directory(path, flag);
+            }
 
-        match &*dir_guard {
-            MemFSEntry::Directory(dir) => dir.create_new_file(last_elem, flag, space),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::is_directory()),
-        }
+            if flag.contains(OpenFlag::O_TMPFILE) {
+                let _mutation = self.begin_mutation()?;
+
+                return self.journaled(
+                    || self.open_tmpfile(path, flag),
+                    |&fd| {
+                        Some(JournalEntry::Tmpfile {
+                            ino: self.descriptor_ino(fd)?,
+                            dir: self.absolute_path(path),
+                        })
+                    },
+                );
+            }
+
+            let mutation = if flag.contains(OpenFlag::O_CREAT) {
+                Some(self.begin_mutation()?)
+            } else {
+                None
+            };
+            self.check_open_access(path, &flag)?;
+            let opened = self.journaled(
+                || self.open_inner(path, flag.clone()),
+                |&(fd, created)| {
+                    Some(JournalEntry::Create {
+                        ino: self.descriptor_ino(fd).filter(|_| created)?,
+                        path: self.absolute_path(path),
+                    })
+                },
+            );
+            let (fd, created) = match opened {
+                // The backends open files only, and fail on anything else as if it were a directory.
+                Err(err) if matches!(err.err_type, MemFSErrType::EISDIR) => {
+                    drop(mutation);
+                    return self.open_special(path, flag).unwrap_or(Err(err));
+                }
+                opened => opened?,
+            };
+
+            if created {
+                if let Some(node) = self.descriptor_entry(fd) {
+                    self.register_inode(&node);
+                }
+
+                self.touch_parent(path);
+                self.notify(WatchEventKind::Create, path);
+            }
+
+            Ok(fd)
+        })
     }
 
-    fn path_str_to_iter(&self, path: &str) -> Result<Peekable<impl Iterator<Item = String>>> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        }
+    /// Creates a file and returns a writer streaming chunks into it, buffering [DEFAULT_WRITER_BUFFER] bytes.
+    /// Fails with EEXIST if the file already exists.
+    pub fn create_writer(&self, path: &str) -> Result<MemFSWriter<'_>> {
+        self.create_writer_with_capacity(path, DEFAULT_WRITER_BUFFER)
+    }
 
-        let vec: Vec<String> = path
-            .split("/")
-            .filter(|x| *x != "" && *x != ".")
-            .map(|x| x.to_string())
-            .collect();
+    /// Same as [MemFS::create_writer], buffering `capacity` bytes.
+    pub fn create_writer_with_capacity(&self, path: &str, capacity: usize) -> Result<MemFSWriter<'_>> {
+        let fd = self.open(path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)?;
 
-        Ok(vec.into_iter().peekable())
+        Ok(MemFSWriter::new(self, fd, capacity))
     }
 
-    fn path_str_to_iter_and_without_last_component(
-        &self,
-        path: &str,
-    ) -> Result<Peekable<impl Iterator<Item = String>>> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        }
+    /// Closes the descriptor. Byte-range locks the caller holds on the file are dropped,
+    /// even those taken through other descriptors.
+    pub fn close(&self, fd: usize) -> Result<()> {
+        self.syscall(SyscallArgs::Close { fd }, || {
+            let locks = self
+                .with_descriptor(fd, |descriptor| Ok(descriptor.file_locks()))
+                .ok()
+                .flatten();
+
+            #[cfg(feature = "dedup")]
+            if let Some(node) = self.descriptor_entry(fd) {
+                self.deduplicate(&node);
+            }
 
-        let mut vec: Vec<String> = path
-            .split("/")
-            .filter(|x| *x != "" && *x != ".")
-            .map(|x| x.to_string())
-            .collect();
+            self.close_inner(fd)?;
+            self.descriptor_numbers.release(fd);
 
-        vec.pop();
+            if let Some(locks) = locks {
+                locks.ranges.release_all(self.caller_pid());
+            }
 
-        Ok(vec.into_iter().peekable())
+            Ok(())
+        })
     }
 
-    fn is_absolute_path(path: &str) -> bool {
-        path.chars().nth(0).unwrap() == '/'
+    /// Removes the name of a file or symbolic link. The name is gone at once, but descriptors open on
+    /// the file keep reading and writing it, and [MemFS::fstat] reports it with no link left.
+    /// The file and its memory are reclaimed when the last link and the last descriptor are gone.
+    pub fn unlink(&self, path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Unlink { path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let node = self.journaled(
+                || self.unlink_inner(path),
+                |_| Some(JournalEntry::Unlink { path: self.absolute_path(path) }),
+            )?;
+
+            if let Some(trash) = &self.trash {
+                trash.push(self.absolute_path(path), node);
+            }
+
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Delete, path);
+
+            Ok(())
+        })
     }
 
-    fn get_last_component_of_path(path: &str) -> Result<&str> {
-        path.trim_end_matches('/')
-            .split("/")
-            .last()
-            .ok_or(MemFSErr::no_such_file_or_directory())
+    /// Puts back the entry last removed from `path` while [MemFSBuilder::trash] is enabled, as it was when
+    /// removed. Fails with ENOENT if the trash holds nothing removed from `path` or its directory is gone,
+    /// EEXIST if `path` exists again, and EINVAL if the trash is not enabled.
+    pub fn undelete(&self, path: &str) -> Result<()> {
+        self.operation("undelete", path, || {
+            let trash = self.trash.as_ref().ok_or(MemFSErr::invalid_value())?;
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let absolute_path = self.absolute_path(path);
+            self.journaled(
+                || {
+                    trash
+                        .restore(&absolute_path, |node| self.link_node(node, path))
+                        .unwrap_or(Err(MemFSErr::no_such_file_or_directory()))
+                },
+                |_| Some(JournalEntry::Undelete { path: absolute_path.clone() }),
+            )?;
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Create, path);
+
+            Ok(())
+        })
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn get_node_of_given_path(&self, path: &str) -> Result<Arc<RwLock<MemFSEntry>>> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        }
+    /// Frees the entries held by the trash, and returns how many there were. Files still open stay usable
+    /// until they are closed, as with [MemFS::unlink].
+    pub fn purge_trash(&self) -> usize {
+        self.trash.as_ref().map_or(0, Trash::clear)
+    }
 
-        let mut iter = self.path_str_to_iter(path)?;
+    /// Returns the paths the entries held by the trash were removed from, in the order they were removed.
+    pub fn trashed(&self) -> Vec<String> {
+        self.trash.as_ref().map_or_else(Vec::new, Trash::paths)
+    }
 
-        if iter.peek().is_none() {
-            return if Self::is_absolute_path(path) {
-                Ok(self.root.clone())
-            } else {
-                Ok(self.cwd_node.clone())
-            };
-        }
+    /// Creates `new_path` as another name of the file at `existing_path`.
+    /// Fails with EPERM if `existing_path` is a directory, and EEXIST if `new_path` exists.
+    pub fn link(&self, existing_path: &str, new_path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Link { existing: existing_path, new: new_path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(new_path)?;
+            self.journaled(
+                || self.link_inner(existing_path, new_path),
+                |_| {
+                    Some(JournalEntry::Link {
+                        existing: self.absolute_path(existing_path),
+                        new: self.absolute_path(new_path),
+                    })
+                },
+            )?;
+            self.touch_parent(new_path);
+            self.touch_status(new_path);
+            self.notify(WatchEventKind::Create, new_path);
 
-        let guard = if Self::is_absolute_path(path) {
-            // Absolute path
-            self.root.read().map_err(|_| MemFSErr::poisoned_lock())
-        } else {
+            Ok(())
+        })
+    }
 
-            // Relative path
-            self.cwd_node.read().map_err(|_| MemFSErr::poisoned_lock())
-        }?;
+    /// Gives the file open as `fd` a new name, as with linkat and AT_EMPTY_PATH. This is how a file opened
+    /// with [OpenFlag::O_TMPFILE] gets into the tree, after which closing it no longer reclaims it.
+    /// Fails with ENOENT if the file has no link left, unless it was opened with O_TMPFILE and without O_EXCL,
+    /// EPERM if the descriptor is not open on a file, and EEXIST if `new_path` exists.
+    pub fn linkat(&self, fd: usize, new_path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Linkat { fd, new: new_path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(new_path)?;
+            self.journaled(
+                || self.linkat_inner(fd, new_path),
+                |_| {
+                    Some(JournalEntry::Linkat {
+                        ino: self.descriptor_ino(fd)?,
+                        new: self.absolute_path(new_path),
+                    })
+                },
+            )?;
+            self.touch_parent(new_path);
+            self.touch_status(new_path);
+            self.notify(WatchEventKind::Create, new_path);
 
-        match &*guard {
-            MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
-        }
+            Ok(())
+        })
     }
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    fn get_node_of_given_path(&self, path: &str) -> Result<Arc<MemFSEntry>> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        }
+    /// Creates a file at `target_path` with the contents of the file at `source_path`, following symbolic
+    /// links. The new file shares the contents the source has now, and takes its own pages of the memory
+    /// pool only once it is written; later writes of the source do not show in it. It is charged against
+    /// [MemFSBuilder::quota] like a copy. Fails with EISDIR if the source is a directory, and EEXIST
+    /// if `target_path` exists.
+    pub fn reflink(&self, source_path: &str, target_path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Reflink { source: source_path, target: target_path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(target_path)?;
+            self.journaled(
+                || self.reflink_inner(source_path, target_path),
+                |node| {
+                    Some(JournalEntry::Reflink {
+                        ino: with_entry(node, Self::entry_ino).ok()??,
+                        source: self.absolute_path(source_path),
+                        target: self.absolute_path(target_path),
+                    })
+                },
+            )?;
+            self.touch_parent(target_path);
+            self.notify(WatchEventKind::Create, target_path);
 
-        let mut iter = self.path_str_to_iter(path)?;
+            Ok(())
+        })
+    }
 
-        if iter.peek().is_none() {
-            return if Self::is_absolute_path(path) {
-                Ok(self.root.clone())
-            } else {
-                Ok(self.cwd_node.clone())
-            };
-        }
+    /// Creates a symbolic link at `link_path` pointing to `target`, which does not need to exist.
+    /// Fails with EEXIST if `link_path` exists.
+    pub fn symlink(&self, target: &str, link_path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Symlink { target, link: link_path }, || {
+            self.name_limits.check(target)?;
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(link_path)?;
+            self.journaled(
+                || self.symlink_inner(target, link_path),
+                |_| {
+                    Some(JournalEntry::Symlink {
+                        target: target.to_string(),
+                        link: self.absolute_path(link_path),
+                    })
+                },
+            )?;
+            self.touch_parent(link_path);
+            self.notify(WatchEventKind::Create, link_path);
 
-        let starting_node = if Self::is_absolute_path(path) {
-            // Absolute path
-            self.root.clone()
-        } else {
-            // Relative path
-            self.cwd_node.clone()
-        };
+            Ok(())
+        })
+    }
 
-        match &*starting_node {
-            MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
-        }
+    /// Returns the target of the symbolic link at the path. Fails with EINVAL if the path is not a symbolic link.
+    pub fn readlink(&self, path: &str) -> Result<String> {
+        self.operation("readlink", path, || {
+            let node = self.get_link_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::Symlink(link) => Ok(link.target.clone()),
+                _ => Err(MemFSErr::invalid_value()),
+            })?
+        })
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn get_parent_directory_node_of_given_path(
+    /// Creates a character device at `path` behaving as `device`, with the permission bits of `mode` less
+    /// the file creation mask. Fails with EEXIST if `path` exists.
+    pub fn mknod(&self, path: &str, device: Device, mode: u32) -> Result<()> {
+        self.operation("mknod", path, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let attributes = self.new_attributes(mode & 0o7777)?;
+            self.journaled(
+                || {
+                    self.mknod_inner(
+                        path,
+                        MemFSEntry::Device(MemFSDeviceNode {
+                            device,
+                            attributes: Arc::new(attributes),
+                        }),
+                    )
+                },
+                |_| {
+                    Some(JournalEntry::Mknod {
+                        path: self.absolute_path(path),
+                        device,
+                        mode,
+                    })
+                },
+            )?;
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Create, path);
+
+            Ok(())
+        })
+    }
+
+    /// Creates a FIFO at `path`, with the permission bits of `mode` less the file creation mask.
+    /// Reads through its descriptors wait for data, and writes wait while the [PIPE_CAPACITY] bytes
+    /// it buffers are taken, unless the descriptor was opened with O_NONBLOCK. Opening it for reading
+    /// only waits for a writer, and for writing only waits for a reader. Fails with EEXIST if `path` exists.
+    pub fn mkfifo(&self, path: &str, mode: u32) -> Result<()> {
+        self.operation("mkfifo", path, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let attributes = self.new_attributes(mode & 0o7777)?;
+            self.journaled(
+                || {
+                    self.mknod_inner(
+                        path,
+                        MemFSEntry::Fifo(MemFSFifoNode {
+                            pipe: Arc::default(),
+                            attributes: Arc::new(attributes),
+                        }),
+                    )
+                },
+                |_| {
+                    Some(JournalEntry::Mkfifo {
+                        path: self.absolute_path(path),
+                        mode,
+                    })
+                },
+            )?;
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Create, path);
+
+            Ok(())
+        })
+    }
+
+    /// Creates `/dev`, if needed, with the devices of [Device::STANDARD] in it, readable and writable by everyone.
+    /// Devices which exist already are left as they are.
+    pub fn create_standard_devices(&self) -> Result<()> {
+        self.create_dir_all("/dev")?;
+
+        for (path, device) in Device::STANDARD {
+            match self.mknod(path, device, DEFAULT_DEVICE_MODE) {
+                Err(err) if matches!(err.err_type, MemFSErrType::EEXIST) => {}
+                created => {
+                    created?;
+                    self.chmod(path, DEFAULT_DEVICE_MODE)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a virtual file at `path`, whose contents come from the callbacks of `file`, readable by everyone
+    /// and writable by its owner if `file` takes writes. The journal does not record it, as its callbacks
+    /// cannot be replayed. Fails with EEXIST if `path` exists.
+    pub fn create_virtual_file(&self, path: &str, file: VirtualFile) -> Result<()> {
+        self.operation("create_virtual_file", path, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let attributes = self.new_attributes(file.mode())?;
+            self.mknod_inner(
+                path,
+                MemFSEntry::Virtual(MemFSVirtualNode {
+                    file,
+                    attributes: Arc::new(attributes),
+                }),
+            )?;
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Create, path);
+
+            Ok(())
+        })
+    }
+
+    /// Returns the virtual file at the path. Fails with EINVAL if the path is not a virtual file.
+    pub fn virtual_file(&self, path: &str) -> Result<VirtualFile> {
+        self.operation("virtual_file", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::Virtual(node) => Ok(node.file.clone()),
+                _ => Err(MemFSErr::invalid_value()),
+            })?
+        })
+    }
+
+    /// Returns the behavior of the device at the path. Fails with EINVAL if the path is not a device.
+    pub fn device(&self, path: &str) -> Result<Device> {
+        self.operation("device", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::Device(device) => Ok(device.device),
+                _ => Err(MemFSErr::invalid_value()),
+            })?
+        })
+    }
+
+    /// Returns the absolute path the path leads to, as realpath(3) does: `.` and `..` components are resolved
+    /// and symbolic links are followed, so that the result names the same entry through directories only.
+    /// Directories are named by their place in the tree, as with [MemFS::getcwd].
+    ///
+    /// Fails with ENOENT if an entry is missing, ENOTDIR if a file is used as a directory,
+    /// and ELOOP if too many symbolic links are followed.
+    pub fn canonicalize(&self, path: &str) -> Result<String> {
+        self.operation("canonicalize", path, || {
+            let mut path = path.to_string();
+
+            for _ in 0..=SYMLINK_FOLLOW_LIMIT {
+                // Only a directory may be named with a trailing slash or a last component of `.` or `..`.
+                let names_directory = path.ends_with('/') || matches!(path.rsplit('/').next(), Some("." | ".."));
+                let node = match names_directory {
+                    true => self.get_node_of_given_path(&path)?,
+                    false => self.get_link_node_of_given_path(&path)?,
+                };
+                let (is_file, target) = with_entry(&node, |entry| match entry {
+                    MemFSEntry::File(_) | MemFSEntry::Device(_) | MemFSEntry::Fifo(_) | MemFSEntry::Virtual(_) => {
+                        (true, None)
+                    }
+                    MemFSEntry::Symlink(link) => (false, Some(link.target.clone())),
+                    MemFSEntry::Directory(_) | MemFSEntry::ResolvedAsRoot => (false, None),
+                })?;
+
+                if is_file && names_directory {
+                    return Err(MemFSErr::is_not_directory());
+                }
+
+                let parent_path = || self.directory_path(self.parent_directory(&path)?);
+
+                match target {
+                    Some(target) if target.starts_with('/') => path = target,
+                    Some(target) => path = format!("{}/{}", parent_path()?, target),
+                    None if is_file => {
+                        let name = path_components(&path).last().ok_or(MemFSErr::no_such_file_or_directory())?;
+
+                        return Ok(format!("{}/{}", parent_path()?.trim_end_matches('/'), name));
+                    }
+                    None if with_entry(&node, |entry| matches!(entry, MemFSEntry::ResolvedAsRoot))? => {
+                        return Ok("/".to_string());
+                    }
+                    None => return self.directory_path(node),
+                }
+            }
+
+            Err(MemFSErr::too_many_links())
+        })
+    }
+
+    /// Reads up to `buffer.len()` bytes from the offset of the descriptor, and returns the number of bytes read.
+    /// A read of a FIFO waits for data, unless the descriptor was opened with O_NONBLOCK, and finds the end
+    /// of the file once it is empty and no descriptor has it open for writing.
+    pub fn read(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        let size = buffer.len();
+
+        self.syscall(SyscallArgs::Read { fd, size }, || {
+            self.record_file_access(fd);
+            self.retry_on_pipe(fd, || self.read_inner(fd, buffer), Pipe::wait_readable)
+        })
+    }
+
+    /// Writes all of `data` at the offset of the descriptor, or at the end of the file with O_APPEND,
+    /// and returns the number of bytes written. A write to a FIFO waits for room until all of `data` is in,
+    /// unless the descriptor was opened with O_NONBLOCK, and fails with EPIPE if no descriptor has it open
+    /// for reading.
+    pub fn write(&self, fd: usize, data: &[u8]) -> Result<usize> {
+        self.syscall(SyscallArgs::Write { fd, data }, || {
+            self.record_file_access(fd);
+
+            // Each attempt is a mutation of its own, so that a write waiting on a FIFO holds no freeze off.
+            let written = self.write_all_to_pipe(fd, data, |data| {
+                let _mutation = self.begin_mutation()?;
+
+                self.evicting(|| {
+                    self.journaled(
+                        || match &self.crash_tracker {
+                            Some(tracker) => self.write_tracked(tracker, fd, || self.write_inner(fd, data)),
+                            None => self.write_inner(fd, data),
+                        },
+                        |&written| self.journal_write(fd, data, written, None),
+                    )
+                })
+            })?;
+
+            if written > 0 {
+                self.notify_write(fd);
+            }
+
+            Ok(written)
+        })
+    }
+
+    /// Reads into `buffers` one after another, as a single read from the offset of the descriptor,
+    /// and returns the number of bytes read. The call is traced and counted as a read.
+    pub fn readv(&self, fd: usize, buffers: &mut [IoSliceMut]) -> Result<usize> {
+        let mut gathered = vec![0; buffers.iter().map(|buffer| buffer.len()).sum()];
+        let read = self.read(fd, &mut gathered)?;
+        let mut remaining = &gathered[..read];
+
+        for buffer in buffers {
+            let (head, tail) = remaining.split_at(buffer.len().min(remaining.len()));
+            buffer[..head.len()].copy_from_slice(head);
+            remaining = tail;
+        }
+
+        Ok(read)
+    }
+
+    /// Writes the concatenation of `buffers` as a single write, and returns the number of bytes written.
+    /// With O_APPEND, it lands at the end of the file in one piece like any other write.
+    /// The call is traced and counted as a write.
+    pub fn writev(&self, fd: usize, buffers: &[IoSlice]) -> Result<usize> {
+        self.write(fd, &buffers.iter().flat_map(|buffer| buffer.iter().copied()).collect::<Vec<_>>())
+    }
+
+    /// Writes the concatenation of `buffers` at the end of the file, whether or not the descriptor was opened
+    /// with O_APPEND, and returns the number of bytes written. The bytes land in one piece however many
+    /// threads append at once: appends of the file through any descriptor, including writes with O_APPEND,
+    /// take turns. The offset of the descriptor moves to the new end of the file.
+    pub fn append_all(&self, fd: usize, buffers: &[IoSlice]) -> Result<usize> {
+        let data: Vec<u8> = buffers.iter().flat_map(|buffer| buffer.iter().copied()).collect();
+
+        self.syscall(SyscallArgs::Append { fd, data: &data }, || {
+            let _mutation = self.begin_mutation()?;
+            self.record_file_access(fd);
+
+            let written = self.write_all_to_pipe(fd, &data, |data| {
+                let append = || self.with_descriptor(fd, |descriptor| descriptor.append_file(data));
+
+                self.evicting(|| {
+                    self.journaled(
+                        || match &self.crash_tracker {
+                            Some(tracker) => self.write_tracked(tracker, fd, append),
+                            None => append(),
+                        },
+                        |&written| self.journal_write(fd, data, written, None),
+                    )
+                })
+            })?;
+
+            if written > 0 {
+                self.notify_write(fd);
+            }
+
+            Ok(written)
+        })
+    }
+
+    /// Reads from `offset` of the file, without moving the offset of the descriptor.
+    pub fn pread(&self, fd: usize, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        let size = buffer.len();
+
+        self.syscall(SyscallArgs::Pread { fd, size, offset }, || {
+            self.record_file_access(fd);
+            self.with_descriptor(fd, |descriptor| descriptor.read_file_at(buffer, offset))
+        })
+    }
+
+    /// Writes at `offset` of the file, without moving the offset of the descriptor.
+    /// Writing past the end of the file leaves a gap which reads as zeroes. Unlike [MemFS::write],
+    /// O_APPEND is ignored, so that the data always goes to `offset`.
+    pub fn pwrite(&self, fd: usize, data: &[u8], offset: usize) -> Result<usize> {
+        self.syscall(SyscallArgs::Pwrite { fd, offset, data }, || {
+            let _mutation = self.begin_mutation()?;
+            self.record_file_access(fd);
+            self.pwrite_inner(fd, data, offset)
+        })
+    }
+
+    /// Copies up to `len` bytes from the file open as `fd_in` to the file open as `fd_out`, without
+    /// the caller moving them through a buffer, and returns the number of bytes copied, which is 0 at
+    /// the end of the input. An offset which is given is used instead of the offset of the descriptor,
+    /// which stays as is; otherwise the copy starts at the offset of the descriptor, which moves past it.
+    ///
+    /// The bytes go through a buffer of a single page, each page being written, and journaled, on its own.
+    /// If a write fails once some bytes were copied, the number of those is returned instead.
+    ///
+    /// Fails with EBADF if `fd_in` is not open for reading, or `fd_out` is not open for writing or is
+    /// open with O_APPEND, and with EINVAL if both are open on the same file and the ranges overlap.
+    pub fn copy_file_range(
         &self,
-        path: &str,
-    ) -> Result<Arc<RwLock<MemFSEntry>>> {
+        fd_in: usize,
+        off_in: Option<usize>,
+        fd_out: usize,
+        off_out: Option<usize>,
+        len: usize,
+    ) -> Result<usize> {
+        self.syscall(SyscallArgs::CopyFileRange { fd_in, off_in, fd_out, off_out, len }, || {
+            let _mutation = self.begin_mutation()?;
+
+            if self
+                .descriptor_flag(fd_out)
+                .is_none_or(|flag| flag.intersects(OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT | OpenFlag::O_APPEND))
+            {
+                return Err(MemFSErr::bad_file_descriptor());
+            }
+
+            let offset_of = |fd: usize, offset: Option<usize>| match offset {
+                Some(offset) => Ok(offset),
+                None => self.with_descriptor(fd, |descriptor| Ok(descriptor.file_offset.load(Ordering::Acquire))),
+            };
+            let (input_offset, output_offset) = (offset_of(fd_in, off_in)?, offset_of(fd_out, off_out)?);
+            let input = self.descriptor_entry(fd_in).ok_or(MemFSErr::bad_file_descriptor())?;
+            let output = self.descriptor_entry(fd_out).ok_or(MemFSErr::bad_file_descriptor())?;
+
+            if node_key(&input) == node_key(&output)
+                && input_offset < output_offset.saturating_add(len)
+                && output_offset < input_offset.saturating_add(len)
+            {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            self.record_file_access(fd_in);
+            self.record_file_access(fd_out);
+
+            let mut buffer = [0; PAGE_SIZE];
+            let mut copied = 0;
+
+            while copied < len {
+                let chunk = &mut buffer[..(len - copied).min(PAGE_SIZE)];
+                let read = self
+                    .with_descriptor(fd_in, |descriptor| descriptor.read_file_at(chunk, input_offset + copied))?;
+
+                if read == 0 {
+                    break;
+                }
+
+                let written = match self.pwrite_inner(fd_out, &buffer[..read], output_offset.saturating_add(copied)) {
+                    Ok(written) => written,
+                    Err(_) if copied > 0 => break,
+                    Err(err) => return Err(err),
+                };
+
+                copied += written;
+
+                if written < read {
+                    break;
+                }
+            }
+
+            for (fd, offset) in [(fd_in, off_in), (fd_out, off_out)] {
+                if offset.is_none() {
+                    self.with_descriptor(fd, |descriptor| {
+                        descriptor.file_offset.fetch_add(copied, Ordering::AcqRel);
+                        Ok(())
+                    })?;
+                }
+            }
+
+            Ok(copied)
+        })
+    }
+
+    /// Moves the offset of the descriptor, which never goes past the end of the file.
+    /// Fails with EINVAL if the offset would be negative.
+    pub fn lseek(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        self.syscall(SyscallArgs::Lseek { fd, offset, flag }, || {
+            self.lseek_inner(fd, offset, flag)
+        })
+    }
+
+    pub fn mkdir(&self, path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Mkdir { path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            let node = self.journaled(
+                || self.mkdir_inner(path),
+                |_| Some(JournalEntry::Mkdir { path: self.absolute_path(path) }),
+            )?;
+            self.register_inode(&node);
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Create, path);
+
+            Ok(())
+        })
+    }
+
+    /// Creates a directory along with its missing ancestors, as `mkdir -p` does. Each directory is created
+    /// by its own [MemFS::mkdir], so it is traced and journaled as one. A directory which another thread
+    /// creates meanwhile is taken as it is, and so is the path if it is a directory already.
+    ///
+    /// Fails with ENOTDIR if an ancestor is not a directory, and EEXIST if the path itself is not one.
+    pub fn create_dir_all(&self, path: &str) -> Result<()> {
+        self.create_dir_all_inner(path).map_err(|err| err.with_context("create_dir_all", Some(path)))
+    }
+
+    fn create_dir_all_inner(&self, path: &str) -> Result<()> {
         if path.is_empty() {
             return Err(MemFSErr::no_such_file_or_directory());
         }
 
-        let mut iter = self.path_str_to_iter_and_without_last_component(path)?;
+        let path = path.trim_end_matches('/');
+        let is_directory = |prefix: &str| self.stat(prefix).map(|stat| stat.file_type == FileType::Directory);
+        let prefixes = path
+            .match_indices('/')
+            .map(|(end, _)| &path[..end])
+            .chain(std::iter::once(path))
+            .filter(|prefix| !prefix.is_empty() && !prefix.ends_with('/'));
+
+        for prefix in prefixes {
+            let exists = match is_directory(prefix) {
+                Err(e) if matches!(e.err_type, MemFSErrType::ENOENT) => match self.mkdir(prefix) {
+                    Ok(()) => continue,
+                    Err(e) if matches!(e.err_type, MemFSErrType::EEXIST) => is_directory(prefix)?,
+                    Err(e) => return Err(e),
+                },
+                result => result?,
+            };
+
+            if !exists {
+                return Err(match prefix == path {
+                    true => MemFSErr::already_exists(),
+                    false => MemFSErr::is_not_directory(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn rmdir(&self, path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Rmdir { path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(path)?;
+            self.journaled(
+                || self.rmdir_inner(path),
+                |_| Some(JournalEntry::Rmdir { path: self.absolute_path(path) }),
+            )?;
+            self.touch_parent(path);
+            self.notify(WatchEventKind::Delete, path);
+
+            Ok(())
+        })
+    }
+
+    /// Same as [MemFS::open], resolving a relative path from the directory open as `dirfd` with
+    /// [OpenFlag::O_DIRECTORY] instead of the working directory. The directory is found by its node,
+    /// so it is the same whatever renames happened since it was opened. With [AT_FDCWD], relative paths
+    /// resolve from the working directory; absolute paths ignore `dirfd`.
+    ///
+    /// Fails with EBADF if `dirfd` is not open, and ENOTDIR if it is open on a file.
+    pub fn openat(&self, dirfd: usize, path: &str, flag: OpenFlag) -> Result<usize> {
+        self.at(dirfd, path, || self.open(path, flag))
+    }
+
+    /// Same as [MemFS::mkdir], resolving a relative path as [MemFS::openat] does.
+    pub fn mkdirat(&self, dirfd: usize, path: &str) -> Result<()> {
+        self.at(dirfd, path, || self.mkdir(path))
+    }
+
+    /// Same as [MemFS::unlink], or [MemFS::rmdir] with [AtFlag::AT_REMOVEDIR], resolving a relative path
+    /// as [MemFS::openat] does.
+    pub fn unlinkat(&self, dirfd: usize, path: &str, flag: AtFlag) -> Result<()> {
+        self.at(dirfd, path, || {
+            if flag.contains(AtFlag::AT_REMOVEDIR) {
+                self.rmdir(path)
+            } else {
+                self.unlink(path)
+            }
+        })
+    }
+
+    /// Runs `call` with relative paths resolving from the directory open as `dirfd`.
+    fn at<R>(&self, dirfd: usize, path: &str, call: impl FnOnce() -> Result<R>) -> Result<R> {
+        if dirfd == AT_FDCWD || path.starts_with('/') {
+            return call();
+        }
+
+        let node = self
+            .descriptor_entry(dirfd)
+            .ok_or(MemFSErr::bad_file_descriptor())?;
+
+        if !with_entry(&node, |entry| matches!(entry, MemFSEntry::Directory(_)))? {
+            return Err(MemFSErr::is_not_directory());
+        }
+
+        let directory = CurrentDirectory {
+            path: self.directory_path(node.clone())?,
+            node,
+        };
+        let key = self as *const MemFS as usize;
+        let previous = AT_DIRECTORY.with(|at| at.replace(Some((key, directory))));
+        let _scope = AtScope { previous };
+
+        call()
+    }
+
+    /// Moves a file or directory to `new_path`, replacing the file or empty directory found there.
+    /// Fails with EISDIR if a file would replace a directory, ENOTDIR if a directory would replace a file,
+    /// EINVAL if a directory would move into its own subtree, and ENOTEMPTY if it would replace a directory
+    /// holding it.
+    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Rename { old: old_path, new: new_path }, || {
+            let _mutation = self.begin_mutation()?;
+            self.check_parent_access(old_path)?;
+            self.check_parent_access(new_path)?;
+            self.journaled(
+                || self.rename_inner(old_path, new_path, RenameFlag::empty()),
+                |_| {
+                    Some(JournalEntry::Rename {
+                        old: self.absolute_path(old_path),
+                        new: self.absolute_path(new_path),
+                    })
+                },
+            )?;
+            self.touch_parent(old_path);
+            self.touch_parent(new_path);
+            self.touch_status(new_path);
+            self.notify_move(old_path, new_path);
+
+            Ok(())
+        })
+    }
+
+    /// Same as [MemFS::rename], resolving relative paths as [MemFS::openat] does, from `olddirfd` and `newdirfd`.
+    /// With [RenameFlag::RENAME_NOREPLACE], fails with EEXIST instead of replacing `new_path`, even with a link
+    /// of the same file. With [RenameFlag::RENAME_EXCHANGE], swaps the two entries, which must both exist
+    /// but may be of different types; it fails with ENOENT if one is missing, and EINVAL if a directory
+    /// would move into its own subtree. Both flags at once are EINVAL.
+    pub fn renameat2(
+        &self,
+        olddirfd: usize,
+        old_path: &str,
+        newdirfd: usize,
+        new_path: &str,
+        flag: RenameFlag,
+    ) -> Result<()> {
+        self.at(olddirfd, old_path, || {
+            let old_path = self.absolute_path(old_path);
+
+            self.at(newdirfd, new_path, || self.rename_with_flag(&old_path, new_path, flag))
+        })
+    }
+
+    fn rename_with_flag(&self, old_path: &str, new_path: &str, flag: RenameFlag) -> Result<()> {
+        self.syscall(SyscallArgs::Renameat2 { old: old_path, new: new_path, flag: flag.bits() }, || {
+            if flag.contains(RenameFlag::RENAME_NOREPLACE | RenameFlag::RENAME_EXCHANGE) {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            let _mutation = self.begin_mutation()?;
+            let exchange = flag.contains(RenameFlag::RENAME_EXCHANGE);
+            self.check_parent_access(old_path)?;
+            self.check_parent_access(new_path)?;
+            self.journaled(
+                || self.rename_inner(old_path, new_path, flag),
+                |_| {
+                    let (old, new) = (self.absolute_path(old_path), self.absolute_path(new_path));
+
+                    Some(match exchange {
+                        true => JournalEntry::Exchange { old, new },
+                        false => JournalEntry::Rename { old, new },
+                    })
+                },
+            )?;
+            self.touch_parent(old_path);
+            self.touch_parent(new_path);
+            self.touch_status(new_path);
+            self.notify_move(old_path, new_path);
+
+            if exchange {
+                self.touch_status(old_path);
+                self.notify_move(new_path, old_path);
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Removes a directory with everything under it.
+    /// Open file descriptors of removed files stay usable, as with [MemFS::unlink].
+    pub fn remove_dir_all(&self, path: &str) -> Result<()> {
+        self.operation("remove_dir_all", path, || {
+            let subtree = self.detach_directory(path)?;
+
+            Self::reclaim_subtree(subtree, true);
+
+            Ok(())
+        })
+    }
+
+    /// Same as [MemFS::remove_dir_all], except that the caller only detaches the directory from the tree.
+    /// Its nodes and file contents are reclaimed on a background thread, which the returned handle tracks.
+    /// The path is free to be created again as soon as this returns.
+    pub fn remove_dir_all_lazy(&self, path: &str) -> Result<RemovalHandle> {
+        self.operation("remove_dir_all_lazy", path, || {
+            let subtree = self.detach_directory(path)?;
+
+            Ok(RemovalHandle::new(thread::spawn(move || Self::reclaim_subtree(subtree, true))))
+        })
+    }
+
+    /// Removes a directory with everything under it, and returns it as a file system of its own rooted at it.
+    /// Files are shared as with [DetachMode::Share].
+    pub fn detach(&self, path: &str) -> Result<MemFS> {
+        self.detach_with(path, DetachMode::Share)
+    }
+
+    /// Same as [MemFS::detach], where `mode` decides whether files are shared or copied.
+    /// The detached file system keeps the map configuration of the directory, and default options otherwise.
+    pub fn detach_with(&self, path: &str, mode: DetachMode) -> Result<MemFS> {
+        self.operation("detach", path, || {
+            let subtree = self.detach_directory(path)?;
+            let root = with_entry(&subtree, |entry| match entry {
+                MemFSEntry::Directory(dir) => Self::deep_copy_directory(
+                    dir,
+                    MemFSDirNode::configured(dir.lock_policy(), dir.maps.clone()),
+                    mode == DetachMode::Share,
+                ),
+                _ => Err(MemFSErr::is_not_directory()),
+            })??;
+
+            let file_memory = match mode {
+                DetachMode::Share => self.file_memory.clone(),
+                DetachMode::Copy => Arc::new(MemoryPool::with_preallocated(NUMBER_OF_MAXIMUM_FILES)),
+            };
+
+            // Directories are rebuilt so that `..` of the new root stays in it; the old ones are not needed anymore.
+            Self::reclaim_subtree(subtree, false);
+
+            Ok(Self::with_root(root, file_memory, false, self.clock.clone()))
+        })
+    }
+
+    /// Changes the working directory. On file systems built with [MemFSBuilder::thread_local_cwd],
+    /// only the working directory of the calling thread is changed. Calls made through a
+    /// [crate::process::MemFSProcess] change the working directory of the process.
+    pub fn chdir(&self, path: &str) -> Result<()> {
+        self.syscall(SyscallArgs::Chdir { path }, || {
+            let node = self.chdir_inner(path)?;
+
+            self.set_current_directory(CurrentDirectory {
+                node,
+                path: self.absolute_path(path),
+            });
+
+            Ok(())
+        })
+    }
+
+    /// Starts a process confined to the directory `path`, which it sees as the root; see [MemFSProcess::chroot].
+    pub fn confined_view(self: &Arc<Self>, path: &str) -> Result<MemFSProcess> {
+        let process = MemFSProcess::new(self.clone());
+        process.chroot(path)?;
+
+        Ok(process)
+    }
+
+    /// Makes the directory `path` the root and the working directory of `process`, which runs the call.
+    pub(crate) fn chroot(&self, process: &ProcessState, path: &str) -> Result<()> {
+        self.operation("chroot", path, || {
+            if self.permission_checks && self.caller_credentials() != Credentials::ROOT {
+                return Err(MemFSErr::operation_not_permitted());
+            }
+
+            let node = self.chdir_inner(path)?;
+
+            process.set_root(node.clone());
+            process.set_cwd(CurrentDirectory {
+                node,
+                path: "/".to_string(),
+            });
+
+            Ok(())
+        })
+    }
+
+    /// Returns the absolute path of the working directory, rebuilt from the parents of its node so that
+    /// renames of the directory or of its ancestors are followed. Symbolic links are resolved.
+    /// Fails with ENOENT if the working directory was removed.
+    pub fn getcwd(&self) -> Result<String> {
+        let _operation = self.enter_operation();
+
+        self.directory_path(self.current_directory().node)
+            .map_err(|err| err.with_context("getcwd", None))
+    }
+
+    /// Returns the absolute path of the directory `node`, rebuilt from its parents up to the root of the process.
+    /// Fails with ENOENT if the directory was removed.
+    fn directory_path(&self, mut node: MemFSNode) -> Result<String> {
+        let root = self.lookup_root();
+        let mut names = Vec::new();
+
+        while node_key(&node) != node_key(&root) {
+            let parent = with_entry(&node, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.parent().and_then(|parent| parent.upgrade()),
+                _ => None,
+            })?
+            .ok_or(MemFSErr::no_such_file_or_directory())?;
+
+            let name = with_entry(&parent, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.list_children(),
+                _ => Ok(Vec::new()),
+            })??
+            .into_iter()
+            .find(|(_, child)| node_key(child) == node_key(&node))
+            .map(|(name, _)| name)
+            .ok_or(MemFSErr::no_such_file_or_directory())?;
+
+            names.push(name);
+            node = parent;
+        }
+
+        names.reverse();
+
+        Ok(format!("/{}", names.join("/")))
+    }
+
+    /// Checks that the path exists and, with [MemFSBuilder::permission_checks], that the caller is granted
+    /// every access in `mode`, a mask of [crate::permission::R_OK], [crate::permission::W_OK] and
+    /// [crate::permission::X_OK], or [crate::permission::F_OK] alone. Symbolic links are followed.
+    /// Fails with ENOENT or EACCES as [MemFS::open] would, EROFS when testing write access of a read-only
+    /// file system, and EINVAL on other bits.
+    pub fn access(&self, path: &str, mode: u32) -> Result<()> {
+        self.operation("access", path, || {
+            if mode & !(MAY_READ | MAY_WRITE | MAY_EXECUTE) != 0 {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            let node = self.get_node_of_given_path(path)?;
+
+            if mode & MAY_WRITE != 0 && self.read_only {
+                return Err(MemFSErr::read_only_file_system());
+            }
+
+            self.check_access(&node, mode)
+        })
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Stat> {
+        self.operation("stat", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| self.stat_entry(entry))?
+        })
+    }
+
+    /// Same as [MemFS::stat], on the file open as `fd`. Works on files which were unlinked since.
+    pub fn fstat(&self, fd: usize) -> Result<Stat> {
+        self.descriptor_operation("fstat", fd, || {
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+
+            with_entry(&node, |entry| self.stat_entry(entry))?
+        })
+    }
+
+    /// Takes, converts or drops an advisory lock on the whole file open as `fd`, waiting for conflicting
+    /// locks of other descriptors to go away. Locks belong to the descriptor, even when several are open
+    /// on the same file, and are dropped when it is closed. Reads and writes ignore them.
+    pub fn flock(&self, fd: usize, op: LockOp) -> Result<()> {
+        self.flock_inner(fd, op, true).map_err(|err| self.descriptor_context(err, "flock", fd))
+    }
+
+    /// Same as [MemFS::flock], failing with EAGAIN instead of waiting.
+    pub fn try_flock(&self, fd: usize, op: LockOp) -> Result<()> {
+        self.flock_inner(fd, op, false).map_err(|err| self.descriptor_context(err, "try_flock", fd))
+    }
+
+    fn flock_inner(&self, fd: usize, op: LockOp, wait: bool) -> Result<()> {
+        // The gate is left before waiting, so that waiting for a lock never holds off lock_exclusive.
+        let (locks, owner) = {
+            let _operation = self.enter_operation();
+
+            self.with_descriptor(fd, |descriptor| {
+                let locks = descriptor.file_locks().ok_or(MemFSErr::bad_file_descriptor())?;
+
+                Ok((locks, descriptor.lock_owner))
+            })?
+        };
+
+        locks.flock.apply(owner, op, wait)?;
+
+        // Closing the descriptor while this waited dropped its locks already, and must not leave one behind.
+        if self
+            .with_descriptor(fd, |descriptor| Ok(descriptor.lock_owner == owner))
+            .is_ok_and(|same| same)
+        {
+            Ok(())
+        } else {
+            locks.flock.release(owner);
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    /// Takes or drops a byte-range lock on the file open as `fd`, as with F_SETLK. Locks belong to the
+    /// calling process, as told by [crate::process::MemFSProcess]; calls made directly on the MemFS act as
+    /// a single process. Locks of a process never conflict with each other: a new lock replaces those
+    /// the process holds on the range. Closing any descriptor of the file drops every lock of the process
+    /// on it. Reads and writes ignore them.
+    ///
+    /// Fails with EAGAIN on a conflicting lock of another process, and EBADF if the descriptor was not
+    /// opened for reading to take a read lock, or for writing to take a write lock.
+    pub fn setlk(&self, fd: usize, lock: RangeLock) -> Result<()> {
+        self.setlk_inner(fd, lock, false).map_err(|err| self.descriptor_context(err, "setlk", fd))
+    }
+
+    /// Same as [MemFS::setlk], waiting for conflicting locks to go away, as with F_SETLKW.
+    /// Fails with EDEADLK if a process holding one of them waits for the caller, directly or not.
+    pub fn setlkw(&self, fd: usize, lock: RangeLock) -> Result<()> {
+        self.setlk_inner(fd, lock, true).map_err(|err| self.descriptor_context(err, "setlkw", fd))
+    }
+
+    /// Returns a lock of another process which would prevent `lock` from being taken, as with F_GETLK.
+    pub fn getlk(&self, fd: usize, lock: RangeLock) -> Result<Option<RangeLockConflict>> {
+        self.descriptor_operation("getlk", fd, || {
+            let locks = self.descriptor_range_locks(fd, lock.kind)?;
+
+            locks.ranges.conflict(self.caller_pid(), &lock)
+        })
+    }
+
+    fn setlk_inner(&self, fd: usize, lock: RangeLock, wait: bool) -> Result<()> {
+        // The gate is left before waiting, as in flock.
+        let (locks, lock_owner) = {
+            let _operation = self.enter_operation();
+
+            (
+                self.descriptor_range_locks(fd, lock.kind)?,
+                self.with_descriptor(fd, |descriptor| Ok(descriptor.lock_owner))?,
+            )
+        };
+        let pid = self.caller_pid();
+
+        locks.ranges.apply(pid, &lock, wait.then_some(&self.lock_waits))?;
+
+        // Closing a descriptor of the file while this waited dropped the locks of the process already.
+        if self
+            .with_descriptor(fd, |descriptor| Ok(descriptor.lock_owner == lock_owner))
+            .is_ok_and(|same| same)
+        {
+            Ok(())
+        } else {
+            locks.ranges.release_all(pid);
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    /// Returns the locks of the file open as `fd`, checking that the descriptor allows a range lock of `kind`.
+    fn descriptor_range_locks(&self, fd: usize, kind: RangeLockKind) -> Result<Arc<FileLocks>> {
+        self.with_descriptor(fd, |descriptor| {
+            let denied = match kind {
+                RangeLockKind::Read => descriptor.flag.contains(OpenFlag::O_WRONLY),
+                RangeLockKind::Write => descriptor.flag.contains(OpenFlag::O_RDONLY),
+                RangeLockKind::Unlock => false,
+            };
+
+            if denied {
+                return Err(MemFSErr::bad_file_descriptor());
+            }
+
+            descriptor.file_locks().ok_or(MemFSErr::bad_file_descriptor())
+        })
+    }
+
+    /// Maps `len` bytes of the file open as `fd` from `offset`, as with mmap. The region may go past the end
+    /// of the file; it reads as far as the file goes. Mapping a descriptor opened with O_SNAPSHOT shows
+    /// the contents it pinned.
+    ///
+    /// Fails with EINVAL if `len` is 0 or the range overflows, or if the descriptor is not open on a file,
+    /// and EACCES if it was not opened for reading.
+    pub fn mmap(&self, fd: usize, offset: usize, len: usize, mode: MappingMode) -> Result<MappedRegion> {
+        self.descriptor_operation("mmap", fd, || {
+            if len == 0 || offset.checked_add(len).is_none() {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            let source = self.with_descriptor(fd, |descriptor| {
+                if descriptor.flag.contains(OpenFlag::O_WRONLY) {
+                    return Err(MemFSErr::permission_denied());
+                }
+
+                if let Some(version) = &descriptor.pinned {
+                    return Ok(MappedSource::Pinned(version.clone()));
+                }
+
+                if with_entry(&descriptor.entry, |entry| matches!(entry, MemFSEntry::File(_)))? {
+                    Ok(MappedSource::File(descriptor.entry.clone()))
+                } else {
+                    Err(MemFSErr::invalid_value())
+                }
+            })?;
+
+            Ok(MappedRegion::new(source, offset, len, mode))
+        })
+    }
+
+    /// Reads what a mapping shows from `offset` of the file, as with pread.
+    pub(crate) fn read_mapped(source: &MappedSource, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        match source {
+            MappedSource::Pinned(version) => Ok(MemFSFileDescriptor::copy_range(version, buffer, offset)),
+            MappedSource::File(node) => with_entry(node, |entry| match entry {
+                MemFSEntry::File(file) => {
+                    Ok(file.read_at(buffer, offset))
+                }
+                _ => Err(MemFSErr::no_such_file_or_directory()),
+            })?,
+        }
+    }
+
+    /// Returns metadata of the node numbered `ino`, as reported in [Stat::ino].
+    /// Nodes which were removed stay reachable while a descriptor or a working directory holds them.
+    /// Fails with ENOENT once the node is gone. The lock-free backend frees removed nodes lazily,
+    /// so they may be found for a while after the last holder let go.
+    pub fn stat_inode(&self, ino: u64) -> Result<Stat> {
+        let _operation = self.enter_operation();
+        let node = self
+            .inodes
+            .get(ino)
+            .and_then(|node| node.upgrade())
+            .ok_or(MemFSErr::no_such_file_or_directory())?;
+
+        with_entry(&node, |entry| self.stat_entry(entry))?
+    }
+
+    /// Returns the bytes used by file contents and metadata, counted against [MemFSBuilder::quota].
+    /// Contents saved for snapshots are not counted, but files removed since a snapshot was taken
+    /// stay counted as long as the snapshot holds them.
+    pub fn usage(&self) -> Usage {
+        self.accounting.usage()
+    }
+
+    /// Returns statistics of the whole file system, counting pages of file contents as blocks, to tell how many
+    /// more pages can be written before writes fail with ENOMEM.
+    pub fn statfs(&self) -> StatFs {
+        let _operation = self.enter_operation();
+        let blocks = self.file_memory.capacity();
+        let blocks_free = self.file_memory.available();
+
+        StatFs {
+            block_size: PAGE_SIZE,
+            blocks,
+            blocks_used: blocks - blocks_free,
+            blocks_free,
+            entries: self.inodes.count(|node| node.upgrade().is_some()),
+            read_only: self.read_only,
+        }
+    }
+
+    /// Returns metadata of every path in the given order.
+    /// Paths under the same directory share a single resolution of that directory.
+    pub fn stat_many(&self, paths: &[&str]) -> Vec<Result<Stat>> {
+        let _operation = self.enter_operation();
+        let mut results: Vec<Option<Result<Stat>>> = vec![None; paths.len()];
+        let mut groups: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+
+        for (index, path) in paths.iter().enumerate() {
+            if path.is_empty() {
+                results[index] = Some(Err(MemFSErr::no_such_file_or_directory()));
+                continue;
+            }
+
+            let absolute = self.absolute_path(path);
+
+            match absolute.rsplit_once('/') {
+                Some((_, "")) => results[index] = Some(self.stat("/")),
+                Some((parent, name)) => groups
+                    .entry(parent.to_string())
+                    .or_default()
+                    .push((index, name.to_string())),
+                None => unreachable!(),
+            }
+        }
+
+        for (parent, entries) in groups {
+            let parent = if parent.is_empty() { "/" } else { &parent };
+            let stats = self.stat_children(parent, entries.iter().map(|(_, name)| name.as_str()));
+
+            for (position, (index, _)) in entries.iter().enumerate() {
+                results[*index] = Some(match &stats {
+                    Ok(stats) => stats[position].clone(),
+                    Err(e) => Err(e.clone()),
+                });
+            }
+        }
+
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Returns every entry of the directory with its metadata, sorted by name.
+    pub fn stat_dir_entries(&self, path: &str) -> Result<Vec<DirEntry>> {
+        self.operation("stat_dir_entries", path, || {
+            let mut entries = self
+                .list_directory(path)?
+                .into_iter()
+                .map(|(name, child)| {
+                    let stat = with_entry(&child, |entry| self.stat_entry(entry))??;
+                    Ok(DirEntry { name, stat })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+            Ok(entries)
+        })
+    }
+
+    /// Returns the name and type of every entry of the directory, sorted by name.
+    /// Cheaper than [MemFS::stat_dir_entries], which also takes the metadata of every entry.
+    pub fn readdir(&self, path: &str) -> Result<Vec<ReadDirEntry>> {
+        self.operation("readdir", path, || {
+            let mut entries = self
+                .list_directory(path)?
+                .into_iter()
+                .map(|(name, child)| {
+                    let file_type = with_entry(&child, |entry| match entry {
+                        MemFSEntry::File(_) => FileType::File,
+                        MemFSEntry::Symlink(_) => FileType::Symlink,
+                        MemFSEntry::Device(_) => FileType::CharDevice,
+                        MemFSEntry::Fifo(_) => FileType::Fifo,
+                        MemFSEntry::Virtual(_) => FileType::File,
+                        _ => FileType::Directory,
+                    })?;
+
+                    Ok(ReadDirEntry { name, file_type })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+            Ok(entries)
+        })
+    }
+
+    /// Same as [MemFS::readdir], as an iterator.
+    pub fn readdir_iter(&self, path: &str) -> Result<ReadDir> {
+        self.readdir(path).map(ReadDir::new)
+    }
+
+    /// Makes every write made through the file descriptor durable. It does nothing more than checking
+    /// the descriptor, unless crash simulation is enabled or a [DurabilityBackend] is set.
+    pub fn fsync(&self, fd: usize) -> Result<()> {
+        self.syscall(SyscallArgs::Fsync { fd }, || self.sync_descriptor(fd, false))
+    }
+
+    /// Same as [MemFS::fsync], telling the [DurabilityBackend] that the metadata of the file need not be durable.
+    pub fn fdatasync(&self, fd: usize) -> Result<()> {
+        self.descriptor_operation("fdatasync", fd, || self.sync_descriptor(fd, true))
+    }
+
+    /// Makes every file of the tree durable, then lets the [DurabilityBackend] flush what it buffered.
+    /// A file with several links is synced once per name. Files are synced one after another while others
+    /// may be written meanwhile, and the first error of the backend stops the sync.
+    /// A file system opened with [MemFS::open_persistent] then writes its tree to the backing file.
+    pub fn sync(&self) -> Result<()> {
+        self.operation("sync", "/", || {
+            let mut pending = vec![(self.root.clone(), String::new())];
+
+            while let Some((node, path)) = pending.pop() {
+                let children = with_entry(&node, |entry| match entry {
+                    MemFSEntry::Directory(dir) => dir.list_children().map(Some),
+                    _ => Ok(None),
+                })??;
+
+                match children {
+                    Some(children) => {
+                        pending.extend(children.into_iter().map(|(name, child)| (child, format!("{path}/{name}"))));
+                    }
+                    None => self.sync_node(&node, &path, false)?,
+                }
+            }
+
+            if let Some(backend) = &self.durability {
+                backend.sync_all()?;
+            }
+
+            Ok(())
+        })?;
+
+        match &self.persistence {
+            Some(store) => self.persist(store),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the size of a file to `len`, dropping the bytes past it or zero-filling up to it. The pages past
+    /// the new size, including those [MemFS::fallocate] took beyond the old one, go back to the block store.
+    /// Fails with EISDIR on a directory, and EFBIG if `len` exceeds [FILE_MAX_SIZE].
+    pub fn truncate(&self, path: &str, len: usize) -> Result<()> {
+        self.syscall(SyscallArgs::Truncate { path, len }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self.get_node_of_given_path(path)?;
+
+            self.check_access(&node, MAY_WRITE)?;
+            self.journaled(
+                || self.truncate_node(&node, len),
+                |_| Some(JournalEntry::Truncate { ino: with_entry(&node, Self::entry_ino).ok()??, len }),
+            )?;
+            self.notify(WatchEventKind::Modify, path);
+
+            Ok(())
+        })
+    }
+
+    /// Same as [MemFS::truncate] for the file a descriptor was opened on.
+    /// Fails with EBADF if the descriptor was not opened for writing.
+    pub fn ftruncate(&self, fd: usize, len: usize) -> Result<()> {
+        self.syscall(SyscallArgs::Ftruncate { fd, len }, || {
+            let _mutation = self.begin_mutation()?;
+
+            if self
+                .descriptor_flag(fd)
+                .is_none_or(|flag| flag.intersects(OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT))
+            {
+                return Err(MemFSErr::bad_file_descriptor());
+            }
+
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+
+            self.journaled(
+                || self.truncate_node(&node, len),
+                |_| Some(JournalEntry::Truncate { ino: with_entry(&node, Self::entry_ino).ok()??, len }),
+            )?;
+            self.notify_write(fd);
+
+            Ok(())
+        })
+    }
+
+    /// Reserves `len` bytes from `offset` of the file a descriptor was opened on, so that later writes
+    /// within the range do not fail with ENOSPC or ENOMEM. The space is charged against [MemFSBuilder::quota]
+    /// and the pages of the range are taken from [MemFSBuilder::block_store] right away, until the file is
+    /// truncated, which gives back both the space and the pages past its new size. Fails with ENOMEM if the
+    /// store runs out of pages meanwhile; the pages taken before stay.
+    ///
+    /// With [FallocateMode::Allocate], the file grows to cover the range, and reads of the new bytes
+    /// return zeroes. With [FallocateMode::KeepSize], its size is left as is.
+    ///
+    /// [FallocateMode::PunchHole] zeroes the bytes of the range instead, up to the end of the file,
+    /// and leaves its size as is. Pages the range covers as a whole go back to the block store, while
+    /// the bytes of the pages at its edges are zeroed in place. The quota charged for the hole is kept.
+    /// Fails with EBADF if the descriptor was not opened for writing, EINVAL if `len` is 0,
+    /// and EFBIG if the range ends past [FILE_MAX_SIZE].
+    pub fn fallocate(&self, fd: usize, mode: FallocateMode, offset: usize, len: usize) -> Result<()> {
+        self.syscall(SyscallArgs::Fallocate { fd, mode, offset, len }, || {
+            let _mutation = self.begin_mutation()?;
+
+            if self
+                .descriptor_flag(fd)
+                .is_none_or(|flag| flag.intersects(OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT))
+            {
+                return Err(MemFSErr::bad_file_descriptor());
+            }
+
+            if len == 0 {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            let end = offset.checked_add(len).ok_or(MemFSErr::file_too_large())?;
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+            let changed = self.journaled(
+                || {
+                    self.resize_node(&node, |file| match mode {
+                        FallocateMode::Allocate => file.allocate(offset, end, false),
+                        FallocateMode::KeepSize => file.allocate(offset, end, true),
+                        FallocateMode::PunchHole => file.punch_hole(offset, end),
+                    })
+                },
+                |&changed| {
+                    let ino = with_entry(&node, Self::entry_ino).ok()??;
+
+                    match mode {
+                        _ if !changed => None,
+                        FallocateMode::PunchHole => Some(JournalEntry::PunchHole { ino, offset, len }),
+                        _ => Some(JournalEntry::Truncate { ino, len: end }),
+                    }
+                },
+            )?;
+
+            if changed {
+                self.notify_write(fd);
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Sets the permission bits of the file or directory at the path, following symbolic links.
+    /// Bits outside of [crate::permission::MODE_MASK] are ignored. With [MemFSBuilder::permission_checks],
+    /// fails with EPERM unless the caller owns the file or is the superuser.
+    pub fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.syscall(SyscallArgs::Chmod { path, mode }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self.get_node_of_given_path(path)?;
+
+            self.journaled(
+                || self.chmod_node(&node, mode),
+                |_| Some(JournalEntry::Chmod { path: self.absolute_path(path), mode }),
+            )
+        })
+    }
+
+    /// Same as [MemFS::chmod] for the file a descriptor was opened on.
+    pub fn fchmod(&self, fd: usize, mode: u32) -> Result<()> {
+        self.syscall(SyscallArgs::Fchmod { fd, mode }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+
+            self.journaled(
+                || self.chmod_node(&node, mode),
+                |_| {
+                    self.journal_descriptor_change(
+                        fd,
+                        |ino| JournalEntry::Fchmod { ino, mode },
+                        |path| JournalEntry::Chmod { path, mode },
+                    )
+                },
+            )
+        })
+    }
+
+    /// Sets the owner and the group of the file or directory at the path, following symbolic links.
+    /// `None` leaves the id unchanged. With [MemFSBuilder::permission_checks], fails with EPERM unless
+    /// the caller is the superuser, or owns the file and only changes its group to its own group.
+    pub fn chown(&self, path: &str, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.syscall(SyscallArgs::Chown { path, uid, gid }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self.get_node_of_given_path(path)?;
+
+            self.journaled(
+                || self.chown_node(&node, uid, gid),
+                |_| Some(JournalEntry::Chown { path: self.absolute_path(path), uid, gid }),
+            )
+        })
+    }
+
+    /// Same as [MemFS::chown] for the file a descriptor was opened on.
+    pub fn fchown(&self, fd: usize, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.syscall(SyscallArgs::Fchown { fd, uid, gid }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+
+            self.journaled(
+                || self.chown_node(&node, uid, gid),
+                |_| {
+                    self.journal_descriptor_change(
+                        fd,
+                        |ino| JournalEntry::Fchown { ino, uid, gid },
+                        |path| JournalEntry::Chown { path, uid, gid },
+                    )
+                },
+            )
+        })
+    }
+
+    /// Sets the access and modification times of the file or directory at the path, following symbolic links.
+    /// `None` leaves the time unchanged. The status change time is set to the current time.
+    /// Fails with EINVAL on a time before the Unix epoch, and with [MemFSBuilder::permission_checks],
+    /// with EPERM unless the caller owns the file or is the superuser.
+    pub fn utimens(&self, path: &str, atime: Option<SystemTime>, mtime: Option<SystemTime>) -> Result<()> {
+        self.syscall(SyscallArgs::Utimens { path, atime, mtime }, || {
+            let _mutation = self.begin_mutation()?;
+            let node = self.get_node_of_given_path(path)?;
+
+            self.journaled(
+                || self.utimens_node(&node, atime, mtime),
+                |_| Some(JournalEntry::Utimens { path: self.absolute_path(path), atime, mtime }),
+            )
+        })
+    }
+
+    /// Simulates a power failure followed by a restart, on a file system built with
+    /// [MemFSBuilder::crash_simulation]. Writes which were not fsynced are lost as decided by `model`,
+    /// every file descriptor is closed along with the locks of open files, and working directories go back
+    /// to the root.
+    pub fn crash(&self, model: CrashModel) -> Result<CrashReport> {
+        let _operation = self.enter_operation();
+        let tracker = self
+            .crash_tracker
+            .as_ref()
+            .ok_or(MemFSErr::invalid_value())?;
+
+        let froze = self.freeze_gate.freeze(FreezeMode::Block).is_ok();
+        let mut report = CrashReport::default();
+
+        for (node, contents) in tracker.crash(model, &mut report) {
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => file.restore(&contents),
+                _ => Ok(()),
+            })??;
+        }
+
+        report.descriptors_closed = self.count_open_file_descriptors();
+        self.clear_file_descriptors();
+        self.descriptor_numbers.reset();
+        self.reset_current_directories();
+
+        if froze {
+            self.freeze_gate.thaw()?;
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the `n` nodes with the most contended accesses, then with the most accesses.
+    /// Nodes which are no longer in the tree are left out, and the walk of the tree for the report
+    /// counts as an access of every directory. Fails with EINVAL unless [MemFSBuilder::contention_stats] is enabled.
+    pub fn hot_nodes(&self, n: usize) -> Result<Vec<NodeContention>> {
+        let _operation = self.enter_operation();
+        if !self.contention_stats {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let mut nodes = Vec::new();
+
+        Self::collect_contention(&self.root, "/".to_string(), &mut nodes)?;
+
+        nodes.sort_by(|a, b| {
+            (b.contended, b.acquisitions)
+                .cmp(&(a.contended, a.acquisitions))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        nodes.truncate(n);
+
+        Ok(nodes)
+    }
+
+    /// Returns the hits, misses and invalidations of the dentry cache so far.
+    /// Fails with EINVAL unless [MemFSBuilder::dentry_cache] is enabled.
+    pub fn dentry_cache_stats(&self) -> Result<DentryCacheStats> {
+        self.dentry_cache
+            .as_ref()
+            .map(DentryCache::stats)
+            .ok_or(MemFSErr::invalid_value())
+    }
+
+    /// Compresses the contents of the file at `path`, following symbolic links, or decompresses them when
+    /// `enabled` is false. Compressed pages are kept outside of the memory pool, and decompressed by every
+    /// read and write of them. Files which never outgrew [crate::utils::INLINE_FILE_SIZE] are compressed
+    /// once they do.
+    /// Fails with EISDIR on a directory, and ENOMEM if the memory pool cannot take the decompressed pages.
+    #[cfg(feature = "compression")]
+    pub fn set_compression(&self, path: &str, enabled: bool) -> Result<()> {
+        self.operation("set_compression", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => file.data.set_compression(enabled, file.pool.as_deref()),
+                _ => Err(MemFSErr::is_directory()),
+            })?
+        })
+    }
+
+    /// Returns how many bytes the compressed files take, against the pages they would take uncompressed.
+    #[cfg(feature = "compression")]
+    pub fn compression_stats(&self) -> CompressionStats {
+        let _operation = self.enter_operation();
+        let mut stats = CompressionStats::default();
+
+        self.inodes.for_each(|node| {
+            if let Some(node) = node.upgrade() {
+                let _ = with_entry(&node, |entry| {
+                    if let MemFSEntry::File(file) = entry {
+                        file.data.add_to_stats(&mut stats);
+                    }
+                });
+            }
+        });
+
+        stats
+    }
+
+    /// Returns how many files share their contents, and how many bytes sharing them saved.
+    /// Fails with EINVAL unless [MemFSBuilder::dedup] is enabled.
+    #[cfg(feature = "dedup")]
+    pub fn dedup_stats(&self) -> Result<DedupStats> {
+        if self.dedup.is_none() {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let _operation = self.enter_operation();
+        let mut contents = Vec::new();
+
+        self.inodes.for_each(|node| {
+            if let Some(node) = node.upgrade() {
+                let _ = with_entry(&node, |entry| {
+                    if let MemFSEntry::File(file) = entry {
+                        contents.extend(file.data.deduplicated());
+                    }
+                });
+            }
+        });
+
+        Ok(DedupStats::of(contents))
+    }
+
+    /// Returns the latest change of every path changed after the change `seq`, oldest first.
+    /// Removing a directory with everything under it is a single change of the directory.
+    /// Fails with EINVAL unless the file system was built with [MemFSBuilder::change_tracking].
+    pub fn changes_since(&self, seq: u64) -> Result<Vec<Change>> {
+        let changes = self.changes.as_ref().ok_or(MemFSErr::invalid_value())?;
+
+        Ok(changes.since(seq))
+    }
+
+    /// Sequence number of the latest change, to be passed to [MemFS::changes_since] later.
+    /// Fails with EINVAL unless change tracking is enabled.
+    pub fn last_change_seq(&self) -> Result<u64> {
+        let changes = self.changes.as_ref().ok_or(MemFSErr::invalid_value())?;
+
+        Ok(changes.last_seq())
+    }
+
+    /// Blocks every mutating operation (creation, write, unlink, mkdir, rmdir) until [MemFS::thaw],
+    /// after waiting for mutations already in progress. While frozen, the file system is a stable image.
+    /// A thread must not mutate the file system it froze itself, as it would wait forever.
+    pub fn freeze(&self) -> Result<()> {
+        self.freeze_gate.freeze(FreezeMode::Block)
+    }
+
+    /// Same as [MemFS::freeze], where `mode` decides whether mutations wait or fail with EAGAIN.
+    /// Fails with EBUSY if the file system is already frozen.
+    pub fn freeze_with(&self, mode: FreezeMode) -> Result<()> {
+        self.freeze_gate.freeze(mode)
+    }
+
+    /// Resumes mutating operations. Fails with EINVAL if the file system is not frozen.
+    pub fn thaw(&self) -> Result<()> {
+        self.freeze_gate.thaw()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.freeze_gate.is_frozen()
+    }
+
+    /// Waits until every operation in progress finishes, and holds off operations of every other thread
+    /// until the guard is dropped, so that maintenance such as exporting, checking or compacting the file
+    /// system sees it quiescent. The calling thread keeps using the file system meanwhile.
+    /// Mutations waiting for [MemFS::thaw] count as in progress.
+    /// Fails with EBUSY if the calling thread holds the guard already, and with EINVAL unless the file
+    /// system was built with [MemFSBuilder::exclusive_lock].
+    pub fn lock_exclusive(&self) -> Result<ExclusiveGuard<'_>> {
+        self.exclusive_gate()?.acquire(None, &self.clock)
+    }
+
+    /// Same as [MemFS::lock_exclusive], but fails with EAGAIN if the file system cannot be held within
+    /// `timeout`, such as when an operation in progress waits for the caller. Operations held off
+    /// meanwhile resume then.
+    pub fn try_lock_exclusive_for(&self, timeout: Duration) -> Result<ExclusiveGuard<'_>> {
+        self.exclusive_gate()?.acquire(Some(timeout), &self.clock)
+    }
+
+    pub fn is_locked_exclusive(&self) -> bool {
+        self.exclusive_gate.as_ref().is_some_and(ExclusiveGate::is_locked)
+    }
+
+    fn exclusive_gate(&self) -> Result<&ExclusiveGate> {
+        self.exclusive_gate.as_ref().ok_or(MemFSErr::invalid_value())
+    }
+
+    /// Takes a point-in-time copy of the whole tree, as described on [Snapshot].
+    /// It takes time in the number of nodes but copies no file contents. Mutating operations are blocked
+    /// while the tree is being copied, and the first write to each file afterwards copies all of its contents.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let _operation = self.enter_operation();
+
+        // If the file system is frozen by the user already, it is a stable image anyway.
+        let froze = self.freeze_gate.freeze(FreezeMode::Block).is_ok();
+        let saved = Arc::new(SavedContents::default());
+        let copy = with_entry(&self.root, |entry| match entry {
+            MemFSEntry::Directory(dir) => Self::deep_copy_directory(dir, MemFSDirNode::new(), true),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })
+        .and_then(|copy| {
+            let copy = copy?;
+            Self::share_files_with_snapshot(&copy, &saved)?;
+
+            Ok(copy)
+        });
+
+        if froze {
+            self.freeze_gate.thaw()?;
+        }
+
+        Ok(Snapshot { root: copy?, saved })
+    }
+
+    /// Replaces the whole tree with the one of `snapshot`, whose nodes get back their inode numbers.
+    /// Descriptors open on replaced files keep them, as with [MemFS::unlink].
+    ///
+    /// The restored tree is built before the current one is dropped, so both count against the quota
+    /// and the memory pool meanwhile. Fails without changing anything if they do not fit.
+    /// Mutating operations are blocked while the tree is replaced.
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+        if self.read_only {
+            return Err(MemFSErr::read_only_file_system());
+        }
+
+        let _operation = self.enter_operation();
+        let froze = self.freeze_gate.freeze(FreezeMode::Block).is_ok();
+        let restored = self.restore_tree(snapshot);
+
+        if froze {
+            self.freeze_gate.thaw()?;
+        }
+
+        restored?;
+        self.notify(WatchEventKind::Modify, "/");
+
+        Ok(())
+    }
+
+    /// Same as [MemFS::snapshot], keeping the snapshot under an identifier to be opened later with [MemFS::open_snapshot].
+    pub fn take_snapshot(&self) -> Result<SnapshotId> {
+        let snapshot = self.snapshot()?;
+        let mut snapshots = self
+            .snapshots
+            .lock()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        let id = SnapshotId(self.next_snapshot_id.fetch_add(1, Ordering::Relaxed));
+        snapshots.insert(id, snapshot);
+
+        Ok(id)
+    }
+
+    /// Opens a snapshot as an independent read-only file system.
+    /// The view stays valid even if the snapshot is dropped afterwards.
+    pub fn open_snapshot(&self, id: SnapshotId) -> Result<MemFSView> {
+        let snapshots = self
+            .snapshots
+            .lock()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        snapshots
+            .get(&id)
+            .map(Snapshot::view)
+            .ok_or(MemFSErr::no_such_file_or_directory())
+    }
+
+    /// Builds the read-only file system behind [Snapshot::view].
+    pub(crate) fn snapshot_view(snapshot: &Snapshot) -> Self {
+        let mut fs = Self::with_root(
+            snapshot.root.clone(),
+            Arc::new(MemoryPool::with_preallocated(0)),
+            true,
+            Clock::default(),
+        );
+        fs.snapshot_contents = Some(snapshot.saved.clone());
+
+        fs
+    }
+
+    pub fn drop_snapshot(&self, id: SnapshotId) -> Result<()> {
+        let mut snapshots = self
+            .snapshots
+            .lock()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        snapshots
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(MemFSErr::no_such_file_or_directory())
+    }
+
+    /// Returns the mutations made so far, if [MemFSBuilder::journal] is enabled.
+    pub fn journal(&self) -> Option<Journal> {
+        self.journal.as_ref().map(JournalRecorder::journal)
+    }
+
+    /// Saves the current contents of the file open as `fd` as a new version, and returns its number.
+    /// Returns the number of the latest version instead if the file was not written since it was saved.
+    /// Versions are kept in memory as long as the file, outside of the memory pool and the quota.
+    pub fn checkpoint(&self, fd: usize) -> Result<u64> {
+        self.descriptor_operation("checkpoint", fd, || {
+            if self.read_only {
+                return Err(MemFSErr::read_only_file_system());
+            }
+
+            let node = self
+                .descriptor_entry(fd)
+                .ok_or(MemFSErr::bad_file_descriptor())?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => Ok(file.save_version()),
+                _ => Err(MemFSErr::bad_file_descriptor()),
+            })?
+        })
+    }
+
+    /// Lists the versions saved of the file at the path, oldest first, following symbolic links.
+    /// Versions are saved by [MemFS::checkpoint], and on every write with [MemFSBuilder::version_on_write].
+    pub fn versions(&self, path: &str) -> Result<Vec<FileVersion>> {
+        self.operation("versions", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => Ok(file.history.list()),
+                _ => Err(MemFSErr::is_directory()),
+            })?
+        })
+    }
+
+    /// Opens version `number` of the file at the path, as listed by [MemFS::versions], for reading only.
+    /// The descriptor reads the contents of that version, while [MemFS::fstat] still reports the live file.
+    /// Fails with ENOENT if the version was never saved or was dropped since.
+    pub fn open_version(&self, path: &str, number: u64) -> Result<usize> {
+        self.operation("open_version", path, || {
+            let node = self.get_node_of_given_path(path)?;
+
+            self.check_access(&node, MAY_READ)?;
+
+            let version = with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => file
+                    .history
+                    .get(number)
+                    .ok_or(MemFSErr::no_such_file_or_directory()),
+                _ => Err(MemFSErr::is_directory()),
+            })??;
+
+            let fd = self.allocate_file_descriptor()?;
+            let mut descriptor = self.new_descriptor(fd, OpenFlag::O_RDONLY, node, self.absolute_path(path));
+            descriptor.pinned = Some(version);
+            self.insert_descriptor(fd, descriptor)?;
+
+            Ok(fd)
+        })
+    }
+
+    /// Returns a stream of changes made on `path` and everything under it.
+    /// At most [DEFAULT_WATCH_BUFFER] events are buffered; older ones are dropped and reported as lag.
+    pub fn watch_stream(&self, path: &str) -> Result<WatchStream> {
+        self.watch_stream_with_capacity(path, DEFAULT_WATCH_BUFFER)
+    }
+
+    /// Same as [MemFS::watch_stream], buffering at most `capacity` events.
+    pub fn watch_stream_with_capacity(&self, path: &str, capacity: usize) -> Result<WatchStream> {
+        self.operation("watch_stream_with_capacity", path, || {
+            self.get_node_of_given_path(path)?;
+
+            Ok(self.watchers.subscribe(self.absolute_path(path), capacity))
+        })
+    }
+
+    /// Watches `path` and everything under it, delivering the events selected by `mask` over a channel,
+    /// as described on [WatchHandle]. Fails with ENOENT if the path does not exist.
+    pub fn watch(&self, path: &str, mask: WatchMask) -> Result<WatchHandle> {
+        self.operation("watch", path, || {
+            self.get_node_of_given_path(path)?;
+
+            Ok(self.watchers.watch(self.absolute_path(path), mask))
+        })
+    }
+
+    /// Enables structured logging: every following system call emits one JSON record to `logger`.
+    /// Records every following system call into `recorder`, which can be replayed later.
+    pub fn set_trace_recorder(&mut self, recorder: TraceRecorder) {
+        self.trace_recorder = Some(recorder);
+    }
+
+    pub fn set_op_logger(&mut self, logger: OpLogger) {
+        self.op_logger = Some(logger);
+    }
+
+    /// Returns the operation counters along with the current usage of file descriptors and file memory.
+    pub fn metrics(&self) -> MemFSMetrics {
+        let mut metrics = self.metrics.snapshot();
+
+        metrics.open_file_descriptors = self.count_open_file_descriptors();
+        metrics.memory_blocks_total = self.file_memory.capacity();
+        metrics.memory_blocks_free = self.file_memory.available();
+        metrics.memory_bytes_reclaimed = self.file_memory.reclaimed_bytes();
+
+        metrics
+    }
+
+    /// Releases idle pages of file memory to the global allocator.
+    /// Released pages are allocated again on demand, so the amount of contents which can be written does not change.
+    pub fn compact(&self) -> CompactionReport {
+        CompactionReport {
+            reclaimed_bytes: self.file_memory.compact(),
+        }
+    }
+
+    /// Runs [MemFS::compact] every `interval` on the maintenance thread.
+    pub fn enable_background_compaction(&self, interval: Duration) -> MaintenanceTaskId {
+        let pool = self.file_memory.clone();
+
+        self.maintenance.schedule(interval, move || {
+            pool.compact();
+        })
+    }
+
+    /// Scheduler running periodic maintenance tasks of this instance.
+    /// Every task is stopped when the MemFS is dropped.
+    pub fn maintenance(&self) -> &MaintenanceScheduler {
+        &self.maintenance
+    }
+
+    /// Encodes [MemFS::metrics] in Prometheus text exposition format, ready to be served on a scrape endpoint.
+    #[cfg(feature = "prometheus")]
+    pub fn encode_metrics(&self) -> String {
+        self.metrics().encode()
+    }
+
+    /// Takes an image of the whole tree, walking every directory in order of names.
+    #[cfg(feature = "serde")]
+    pub(crate) fn image(&self) -> Result<NodeImage> {
+        let _operation = self.enter_operation();
+
+        Self::image_node(&self.root, "", &mut HashMap::new())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn open_inner(&self, path: &str, flag: OpenFlag) -> Result<(usize, bool)> {
+        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
+        if !flag.check_mode_exclusiveness() {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let created = if flag.contains(OpenFlag::O_CREAT) {
+            self.create(path, OpenFlag::O_EXCL & (flag.clone()), || self.new_file_node())?
+        } else {
+            false
+        };
+
+        let item_node = self.get_node_of_given_path(path)?;
+
+        let item_guard = item_node.read().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*item_guard {
+
+            MemFSEntry::File(_) => {
+                let fd = self.allocate_file_descriptor()?;
+                let mut guard = self
+                    .file_descriptors
+                    .shard(fd)
+                    .write()
+                    .map_err(|_| MemFSErr::poisoned_lock())?;
+
+                guard.insert(
+                    fd,
+                    self.new_descriptor(
+                        fd,
+                        flag & !(OpenFlag::O_CREAT),
+                        item_node.clone(),
+                        self.absolute_path(path),
+                    ),
+                );
+
+                Ok((fd, created))
+            }
+            _ => Err(MemFSErr::is_directory()),
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn open_inner(&self, path: &str, flag: OpenFlag) -> Result<(usize, bool)> {
+        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
+        if !flag.check_mode_exclusiveness() {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let parent_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        let (parent_dir, entry) = self.resolve_dir_and_entry(last_elem, &*parent_node)?;
+
+        match entry {
+            Entry::Vacant(v) => {
+                if flag.contains(OpenFlag::O_CREAT) {
+                    // If the entry is empty and O_CREAT is specified, add the file entry.
+                    let file_node = NodeArc::new(MemFSEntry::File(Box::new(self.new_file_node()?)));
+
+                    let fd = self.allocate_file_descriptor()?;
+
+                    parent_dir.spell(last_elem);
+                    v.insert(file_node.clone());
+
+                    self.file_descriptors.shard(fd).insert(
+                        fd,
+                        self.new_descriptor(
+                            fd,
+                            flag & !(OpenFlag::O_CREAT),
+                            file_node,
+                            self.absolute_path(path),
+                        ),
+                    );
+
+                    Ok((fd, true))
+                } else {
+                    Err(MemFSErr::no_such_file_or_directory())
+                }
+            }
+            Entry::Occupied(v) => {
+                if flag.contains(OpenFlag::O_CREAT | OpenFlag::O_EXCL) {
+                    Err(MemFSErr::already_exists())
+                } else {
+                    let file_node = v.get();
+
+                    match &**file_node {
+                        MemFSEntry::File(_) => {
+                            let fd = self.allocate_file_descriptor()?;
+
+                            self.file_descriptors.shard(fd).insert(
+                                fd,
+                                self.new_descriptor(
+                                    fd,
+                                    flag & !(OpenFlag::O_CREAT),
+                                    file_node.clone(),
+                                    self.absolute_path(path),
+                                ),
+                            );
+
+                            Ok((fd, false))
+                        }
+                        MemFSEntry::Symlink(_) => {
+                            let link = file_node.clone();
+
+                            // Releases the shard before following the link, which may lead back into it.
+                            drop(v);
+                            self.open_through_link(&parent_node, &link, path, flag)
+                        }
+                        _ => Err(MemFSErr::is_directory()),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Opens the file which `link`, the symbolic link at the last component of the path, resolves to.
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn open_through_link(
+        &self,
+        parent_node: &MemFSNode,
+        link: &MemFSNode,
+        path: &str,
+        flag: OpenFlag,
+    ) -> Result<(usize, bool)> {
+        let parent_node = match &**parent_node {
+            MemFSEntry::ResolvedAsRoot => &self.root,
+            _ => parent_node,
+        };
+        let file_node = PathLookup::new(&self.lookup_root(), true).found(parent_node, link)?;
+
+        if !matches!(&*file_node, MemFSEntry::File(_)) {
+            return Err(MemFSErr::is_directory());
+        }
+
+        let fd = self.allocate_file_descriptor()?;
+        let descriptor = self.new_descriptor(fd, flag & !(OpenFlag::O_CREAT), file_node, self.absolute_path(path));
+
+        #[cfg(feature = "fine-grained")]
+        self.file_descriptors.shard(fd).insert(fd, descriptor);
+
+        #[cfg(feature = "lock-free")]
+        self.file_descriptors.shard(fd).pin().insert(fd, descriptor);
+
+        Ok((fd, false))
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn open_inner(&self, path: &str, flag: OpenFlag) -> Result<(usize, bool)> {
+        // Check flag. O_RDONLY, O_WRONLY, O_RDWR are the mutually exclusive ones.
+        if !flag.check_mode_exclusiveness() {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let parent_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        let parent_dir = self.resolve_open_dir(&parent_node)?;
+        let parent_pin = parent_dir.pin_children();
+
+        // Check if there is already a file.
+        match parent_pin.get(&*parent_dir.key(last_elem)) {
+            Some(f) => {
+                if flag.contains(OpenFlag::O_CREAT | OpenFlag::O_EXCL) {
+                    Err(MemFSErr::already_exists())
+                }
+                else {
+                    match &**f {
+                        MemFSEntry::File(_) => {
+                            let fd = self.allocate_file_descriptor()?;
+                            let descriptor = self.new_descriptor(
+                                fd,
+                                flag & !(OpenFlag::O_CREAT),
+                                f.clone(),
+                                self.absolute_path(path),
+                            );
+
+                            self.file_descriptors.shard(fd).pin().insert(fd, descriptor);
+
+                            Ok((fd, false))
+                        },
+                        MemFSEntry::Symlink(_) => self.open_through_link(&parent_node, f, path, flag),
+                        _ => Err(MemFSErr::is_directory()),
+                    }
+                }
+            },
+            None => {
+                if flag.contains(OpenFlag::O_CREAT) {
+                    // If the entry is empty and O_CREAT is specified, add the file entry.
+                    let file_node = NodeArc::new(MemFSEntry::File(Box::new(self.new_file_node()?)));
+
+                    let fd = self.allocate_file_descriptor()?;
+                    let descriptor = self.new_descriptor(
+                        fd,
+                        flag & !(OpenFlag::O_CREAT),
+                        file_node.clone(),
+                        self.absolute_path(path),
+                    );
+
+                    parent_pin.insert(parent_dir.interned_key(last_elem), file_node);
+                    parent_dir.spell(last_elem);
+                    self.file_descriptors.shard(fd).pin().insert(fd, descriptor);
+
+                    Ok((fd, true))
+                }
+                else {
+                    Err(MemFSErr::no_such_file_or_directory())
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn close_inner(&self, fd: usize) -> Result<()> {
+        let mut guard = self
+            .file_descriptors
+            .shard(fd)
+            .write()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match guard.remove(&fd) {
+            Some(descriptor) => {
+                descriptor.release();
+                Ok(())
+            }
+            None => Err(MemFSErr::bad_file_descriptor()),
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn close_inner(&self, fd: usize) -> Result<()> {
+        let entry = self.file_descriptors.shard(fd).entry(fd);
+        match entry {
+            Entry::Occupied(e) => {
+                e.remove().release();
+                Ok(())
+            },
+            Entry::Vacant(_) => Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn close_inner(&self, fd: usize) -> Result<()> {
+        // let entry = self.file_descriptors.pin().entry(fd);
+
+        match self.file_descriptors.shard(fd).pin().remove(&fd) {
+            Some(descriptor) => {
+                descriptor.release();
+                Ok(())
+            }
+            None => Err(MemFSErr::bad_file_descriptor()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn unlink_inner(&self, path: &str) -> Result<MemFSNode> {
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        let file_node = match &*dir_guard {
+            MemFSEntry::Directory(dir) => dir.remove_file(last_elem),
+
+
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => {
+                let root_guard = self.root.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+                if let MemFSEntry::Directory(dir) = &*root_guard {
+                    dir.remove_file(last_elem)
+                } else {
+                    Err(MemFSErr::no_such_file_or_directory())
+                }
+            }
+        }?;
+
+        drop(dir_guard);
+        self.invalidate_dentries();
+        drop_link(&file_node);
+
+        Ok(file_node)
+    }
+
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn unlink_inner(&self, path: &str) -> Result<MemFSNode> {
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        let file_node = match &*dir_node {
+            MemFSEntry::Directory(dir) => dir.remove_file(last_elem),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => {
+                if let MemFSEntry::Directory(dir) = &*self.root {
+                    dir.remove_file(last_elem)
+                } else {
+                    Err(MemFSErr::no_such_file_or_directory())
+                }
+            }
+        }?;
+
+        self.invalidate_dentries();
+        drop_link(&file_node);
+
+        Ok(file_node)
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn read_inner(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        let fd_map = self
+            .file_descriptors
+            .shard(fd)
+            .read()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if let Some(v) = fd_map.get(&fd) {
+            unsafe { v.read_file(buffer) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn read_inner(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).get(&fd) {
+            unsafe { v.read_file(buffer) }
+        }
+        else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn read_inner(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).pin().get(&fd) {
+            unsafe { v.read_file(buffer) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn write_inner(&self, fd: usize, buffer: &[u8]) -> Result<usize> {
+        let fd_map = self
+            .file_descriptors
+            .shard(fd)
+            .read()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if let Some(v) = fd_map.get(&fd) {
+            unsafe { v.write_file(buffer) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn write_inner(&self, fd: usize, buffer: &[u8]) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).get(&fd) {
+            unsafe { v.write_file(buffer) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn write_inner(&self, fd: usize, buffer: &[u8]) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).pin().get(&fd) {
+            unsafe { v.write_file(buffer) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }    
+
+    #[cfg(feature = "coarse-grained")]
+    fn lseek_inner(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        let fd_map = self
+            .file_descriptors
+            .shard(fd)
+            .read()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if let Some(v) = fd_map.get(&fd) {
+            unsafe { v.seek_file(offset, flag) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn lseek_inner(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).get(&fd) {
+            unsafe { v.seek_file(offset, flag) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn lseek_inner(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        if let Some(v) = self.file_descriptors.shard(fd).pin().get(&fd) {
+            unsafe { v.seek_file(offset, flag) }
+        } else {
+            Err(MemFSErr::bad_file_descriptor())
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn mkdir_inner(&self, path: &str) -> Result<MemFSNode> {
+        if path == "/" {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        if last_elem == "." || last_elem == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*dir_guard {
+            MemFSEntry::Directory(dir) => {
+                dir.create_new_directory(last_elem, dir_node.clone(), self.new_attributes(DEFAULT_DIRECTORY_MODE)?)
+            }
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::already_exists()),
+        }
+    }
+
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]    
+    fn mkdir_inner(&self, path: &str) -> Result<MemFSNode> {
+        if path == "/" {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        if last_elem == "." || last_elem == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        match &*dir_node {
+            MemFSEntry::Directory(dir) => {
+                dir.create_new_directory(last_elem, dir_node.clone(), self.new_attributes(DEFAULT_DIRECTORY_MODE)?)
+            }
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::already_exists()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn rmdir_inner(&self, path: &str) -> Result<()> {
+        if path == "/" {
+            return Err(MemFSErr::busy());
+        }
+
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        if last_elem == "." {
+            return Err(MemFSErr::invalid_value());
+        } else if last_elem == ".." {
+            return Err(MemFSErr::is_not_empty());
+        }
+
+        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*dir_guard {
+            MemFSEntry::Directory(dir) => dir.remove_directory(last_elem),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::busy()),
+        }?;
+
+        drop(dir_guard);
+        self.invalidate_dentries();
+
+        Ok(())
+    }
+
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn rmdir_inner(&self, path: &str) -> Result<()> {
+        if path == "/" {
+            return Err(MemFSErr::busy());
+        }
+
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        if last_elem == "." {
+            return Err(MemFSErr::invalid_value());
+        } else if last_elem == ".." {
+            return Err(MemFSErr::is_not_empty());
+        }
+
+        match &*dir_node {
+            MemFSEntry::Directory(dir) => dir.remove_directory(last_elem),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::busy()),
+        }?;
+
+        self.invalidate_dentries();
+
+        Ok(())
+    }
+
+    /// Renames are serialized with each other, so that no two of them can move directories into each other.
+    fn rename_inner(&self, old_path: &str, new_path: &str, flag: RenameFlag) -> Result<()> {
+        if old_path.is_empty() || new_path.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        } else if old_path == "/" || new_path == "/" {
+            return Err(MemFSErr::busy());
+        }
+
+        let old_name = Self::get_last_component_of_path(old_path)?;
+        let new_name = Self::get_last_component_of_path(new_path)?;
+
+        if [old_name, new_name].iter().any(|name| *name == "." || *name == "..") {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let _rename = self.rename_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let old_parent = self.parent_directory(old_path)?;
+        let new_parent = self.parent_directory(new_path)?;
+
+        let node = with_entry(&old_parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.lookup_children(std::iter::once(old_name)),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??
+        .pop()
+        .flatten()
+        .ok_or(MemFSErr::no_such_file_or_directory())?;
+        let is_dir = with_entry(&node, |entry| matches!(entry, MemFSEntry::Directory(_)))?;
+        let same_parent = node_key(&old_parent) == node_key(&new_parent);
+
+        // As on Linux, a path with a trailing slash names a directory only.
+        if !is_dir && (old_path.ends_with('/') || new_path.ends_with('/')) {
+            return Err(MemFSErr::is_not_directory());
+        }
+
+        let no_replace = flag.contains(RenameFlag::RENAME_NOREPLACE);
+
+        if same_parent && old_name == new_name {
+            return if no_replace { Err(MemFSErr::already_exists()) } else { Ok(()) };
+        }
+
+        if is_dir {
+            self.check_not_ancestor(&node, &new_parent)?;
+        }
+
+        // Both names may be links of the same file, in which case there is nothing to do.
+        let target = with_entry(&new_parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.lookup_children(std::iter::once(new_name)),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??
+        .pop()
+        .flatten();
+
+        if flag.contains(RenameFlag::RENAME_EXCHANGE) {
+            let target = target.ok_or(MemFSErr::no_such_file_or_directory())?;
+
+            if new_path.ends_with('/') && !with_entry(&target, |entry| matches!(entry, MemFSEntry::Directory(_)))? {
+                return Err(MemFSErr::is_not_directory());
+            }
+
+            return self.exchange_nodes((&old_parent, old_name, &node), (&new_parent, new_name, &target));
+        }
+
+        if no_replace && target.is_some() {
+            return Err(MemFSErr::already_exists());
+        }
+
+        // A directory holding the entry would have to be empty to be replaced by it.
+        if let Some(target) = &target
+            && self.check_not_ancestor(target, &old_parent).is_err()
+        {
+            return Err(MemFSErr::is_not_empty());
+        }
+
+        if target.is_some_and(|target| node_key(&target) == node_key(&node)) {
+            if same_parent {
+                with_entry(&new_parent, |entry| {
+                    if let MemFSEntry::Directory(dir) = entry {
+                        dir.respell(old_name, new_name);
+                    }
+                })?;
+            }
+
+            return Ok(());
+        }
+
+        let replace = !no_replace;
+        let replaced = if same_parent {
+            with_entry(&old_parent, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.move_child(old_name, dir, new_name, &node, is_dir, replace),
+                _ => Err(MemFSErr::no_such_file_or_directory()),
+            })??
+        } else {
+            with_entry(&old_parent, |old_entry| {
+                with_entry(&new_parent, |new_entry| match (old_entry, new_entry) {
+                    (MemFSEntry::Directory(source), MemFSEntry::Directory(target)) => {
+                        source.move_child(old_name, target, new_name, &node, is_dir, replace)
+                    }
+                    _ => Err(MemFSErr::no_such_file_or_directory()),
+                })?
+            })??
+        };
+
+        self.invalidate_dentries();
+
+        if let Some(replaced) = replaced {
+            drop_link(&replaced);
+        }
+
+        if is_dir {
+            with_entry(&node, |entry| {
+                if let MemFSEntry::Directory(dir) = entry {
+                    dir.set_parent(&new_parent);
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps two entries, each given as its parent, its name and its node, for [RenameFlag::RENAME_EXCHANGE].
+    fn exchange_nodes(&self, old: (&MemFSNode, &str, &MemFSNode), new: (&MemFSNode, &str, &MemFSNode)) -> Result<()> {
+        let ((old_parent, old_name, node), (new_parent, new_name, other)) = (old, new);
+
+        if node_key(node) == node_key(other) {
+            return Ok(());
+        }
+
+        let is_dir = |node: &MemFSNode| with_entry(node, |entry| matches!(entry, MemFSEntry::Directory(_)));
+
+        if is_dir(other)? {
+            self.check_not_ancestor(other, old_parent)?;
+        }
+
+        let complete = if node_key(old_parent) == node_key(new_parent) {
+            with_entry(old_parent, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.exchange_child(old_name, dir, new_name, node, other),
+                _ => Err(MemFSErr::no_such_file_or_directory()),
+            })??
+        } else {
+            with_entry(old_parent, |old_entry| {
+                with_entry(new_parent, |new_entry| match (old_entry, new_entry) {
+                    (MemFSEntry::Directory(source), MemFSEntry::Directory(target)) => {
+                        source.exchange_child(old_name, target, new_name, node, other)
+                    }
+                    _ => Err(MemFSErr::no_such_file_or_directory()),
+                })?
+            })??
+        };
+
+        self.invalidate_dentries();
+
+        // The old name went away while the entries were swapped: as if it was removed right after the swap,
+        // which unlinks the other entry instead of the node.
+        if !complete {
+            with_entry(node, |entry| {
+                if let MemFSEntry::File(file) = entry {
+                    file.links.fetch_add(1, Ordering::AcqRel);
+                }
+            })?;
+            drop_link(other);
+        }
+
+        for (moved, parent) in [(node, new_parent), (other, old_parent)] {
+            with_entry(moved, |entry| {
+                if let MemFSEntry::Directory(dir) = entry {
+                    dir.set_parent(parent);
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn link_inner(&self, existing_path: &str, new_path: &str) -> Result<()> {
+        let node = self.get_node_of_given_path(existing_path)?;
+
+        if !with_entry(&node, |entry| matches!(entry, MemFSEntry::File(_)))? {
+            return Err(MemFSErr::operation_not_permitted());
+        }
+
+        self.link_node(&node, new_path)
+    }
+
+    fn linkat_inner(&self, fd: usize, new_path: &str) -> Result<()> {
+        let (node, flag) =
+            self.with_descriptor(fd, |descriptor| Ok((descriptor.entry.clone(), descriptor.flag.clone())))?;
+        let unlinked = with_entry(&node, |entry| match entry {
+            MemFSEntry::File(file) => Ok(file.links.load(Ordering::Acquire) == 0),
+            _ => Err(MemFSErr::operation_not_permitted()),
+        })??;
+
+        if unlinked && (!flag.contains(OpenFlag::O_TMPFILE) || flag.contains(OpenFlag::O_EXCL)) {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        self.link_node(&node, new_path)
+    }
+
+    /// Adds a name for the file `node` at `new_path`.
+    fn link_node(&self, node: &MemFSNode, new_path: &str) -> Result<()> {
+        let new_name = Self::get_last_component_of_path(new_path)?;
+
+        if new_path == "/" || new_name == "." || new_name == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let parent = self.parent_directory(new_path)?;
+
+        // Counted before the entry shows up, so that an unlink of the new name never finds the file unlinked.
+        with_entry(node, |entry| {
+            if let MemFSEntry::File(file) = entry {
+                file.links.fetch_add(1, Ordering::AcqRel);
+            }
+        })?;
+
+        let linked = with_entry(&parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.insert_child(new_name, node.clone()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })?;
+
+        if linked.is_err() {
+            drop_link(node);
+        }
+
+        linked
+    }
+
+    fn reflink_inner(&self, source_path: &str, target_path: &str) -> Result<MemFSNode> {
+        let source = self.get_node_of_given_path(source_path)?;
+        self.check_access(&source, MAY_READ)?;
+
+        let contents = with_entry(&source, |entry| match entry {
+            MemFSEntry::File(file) => Ok(file.pin_version()),
+            MemFSEntry::Directory(_) => Err(MemFSErr::is_directory()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??;
+        let name = Self::get_last_component_of_path(target_path)?;
+
+        if target_path == "/" || name == "." || name == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let parent = self.parent_directory(target_path)?;
+        let attributes = self.new_attributes(DEFAULT_FILE_MODE)?;
+
+        if let Some(charge) = attributes.charge() {
+            charge.set_data(contents.len() as u64)?;
+        }
+
+        let mut file = MemFSFileNode::with_attributes(attributes);
+        file.pool = Some(self.file_memory.clone());
+        file.history = VersionHistory::new(self.version_policy);
+        file.share_contents(contents);
+
+        #[cfg(feature = "compression")]
+        file.data.set_compression(self.compression, None)?;
+
+        let node = new_node(MemFSEntry::File(Box::new(file)));
+
+        with_entry(&parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.insert_child(name, node.clone()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??;
+
+        Ok(node)
+    }
+
+    /// Creates a device or a FIFO at `path`.
+    fn mknod_inner(&self, path: &str, entry: MemFSEntry) -> Result<()> {
+        let name = Self::get_last_component_of_path(path)?;
+
+        if path == "/" || name == "." || name == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let parent = self.parent_directory(path)?;
+        let node = new_node(entry);
+
+        with_entry(&parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.insert_child(name, node.clone()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??;
+
+        self.register_inode(&node);
+
+        Ok(())
+    }
+
+    fn symlink_inner(&self, target: &str, link_path: &str) -> Result<()> {
+        if target.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        let name = Self::get_last_component_of_path(link_path)?;
+
+        if link_path == "/" || name == "." || name == ".." {
+            return Err(MemFSErr::already_exists());
+        }
+
+        let parent = self.parent_directory(link_path)?;
+        let charge = self
+            .accounting
+            .charge_metadata(NODE_METADATA_BYTES + target.len() as u64)?;
+        let link = new_node(MemFSEntry::Symlink(MemFSSymlinkNode {
+            target: target.to_string(),
+            ino: self.inodes.allocate(),
+            _charge: Some(charge),
+        }));
+
+        with_entry(&parent, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.insert_child(name, link.clone()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })??;
+
+        self.register_inode(&link);
+
+        Ok(())
+    }
+
+    /// Returns the directory holding the last component of the path, resolving it to the root if needed.
+    fn parent_directory(&self, path: &str) -> Result<MemFSNode> {
+        let parent = self.get_parent_directory_node_of_given_path(path)?;
+
+        match with_entry(&parent, |entry| match entry {
+            MemFSEntry::Directory(_) => Ok(false),
+            MemFSEntry::ResolvedAsRoot => Ok(true),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::is_not_directory())
+            }
+        })?? {
+            true => Ok(self.root.clone()),
+            false => Ok(parent),
+        }
+    }
+
+    /// Fails with EINVAL if `dir` is `target` or one of its ancestors.
+    fn check_not_ancestor(&self, dir: &MemFSNode, target: &MemFSNode) -> Result<()> {
+        let mut current = Some(target.clone());
+
+        while let Some(node) = current {
+            if node_key(&node) == node_key(dir) {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            current = with_entry(&node, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.parent().and_then(|parent| parent.upgrade()),
+                _ => None,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the directory node to change the working directory into.
+    #[cfg(feature = "coarse-grained")]
+    fn chdir_inner(&self, path: &str) -> Result<MemFSNode> {
+        if path.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        } else if path == "/" {
+            return Ok(self.lookup_root());
+        }
+
+        let dir_node = self.get_node_of_given_path(path)?;
+        let dir_guard = dir_node.read().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*dir_guard {
+            MemFSEntry::Directory(_) => Ok(dir_node.clone()),
+            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
+            _ => Err(MemFSErr::is_not_directory()),
+        }
+    }
+
+    /// Returns the directory node to change the working directory into.
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn chdir_inner(&self, path: &str) -> Result<MemFSNode> {
+        if path.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        } else if path == "/" {
+            return Ok(self.lookup_root());
+        }
+
+        let dir_node = self.get_node_of_given_path(path)?;
+
+        match &*dir_node {
+            MemFSEntry::Directory(_) => Ok(dir_node.clone()),
+            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
+            _ => Err(MemFSErr::is_not_directory()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn create(&self, path: &str, flag: OpenFlag, new_file: impl FnOnce() -> Result<MemFSFileNode>) -> Result<bool> {
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+        let dir_guard = dir_node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*dir_guard {
+            MemFSEntry::Directory(dir) => dir.create_new_file(last_elem, flag, new_file),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::no_such_file_or_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::is_directory()),
+        }
+    }
+
+    fn is_absolute_path(path: &str) -> bool {
+        path.starts_with('/')
+    }
+
+    fn get_last_component_of_path(path: &str) -> Result<&str> {
+        path.trim_end_matches('/')
+            .split("/")
+            .last()
+            .ok_or(MemFSErr::no_such_file_or_directory())
+    }
+
+    /// Returns the node at the path, following a symbolic link at the last component.
+    fn get_node_of_given_path(&self, path: &str) -> Result<MemFSNode> {
+        self.resolve_path(path, true)
+    }
+
+    /// Returns the node at the path, without following a symbolic link at the last component.
+    fn get_link_node_of_given_path(&self, path: &str) -> Result<MemFSNode> {
+        self.resolve_path(path, false)
+    }
+
+    fn resolve_path(&self, path: &str, follow_last: bool) -> Result<MemFSNode> {
+        let resolution = if follow_last { Resolution::Follow } else { Resolution::NoFollow };
+
+        self.cached_lookup(path, resolution, || self.walk_path(path, follow_last))
+    }
+
+    fn get_parent_directory_node_of_given_path(&self, path: &str) -> Result<MemFSNode> {
+        self.cached_lookup(path, Resolution::Parent, || self.walk_to_parent_directory(path))
+    }
+
+    /// Looks `path` up through the dentry cache, if there is one. Relative paths depend on the working
+    /// directory, and absolute ones on the root of a confined process, so they are always walked.
+    fn cached_lookup(
+        &self,
+        path: &str,
+        resolution: Resolution,
+        walk: impl FnOnce() -> Result<MemFSNode>,
+    ) -> Result<MemFSNode> {
+        match &self.dentry_cache {
+            Some(cache) if Self::is_absolute_path(path) && !self.is_confined() => cache.lookup(path, resolution, walk),
+            _ => walk(),
+        }
+    }
+
+    /// Forgets the paths cached so far, once an entry was removed, renamed or replaced.
+    fn invalidate_dentries(&self) {
+        if let Some(cache) = &self.dentry_cache {
+            cache.invalidate();
+        }
+    }
+
+    fn walk_path(&self, path: &str, follow_last: bool) -> Result<MemFSNode> {
+        if path.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        self.name_limits.check(path)?;
+
+        PathLookup::new(&self.lookup_root(), follow_last).walk(&self.lookup_start(path), path)
+    }
+
+    /// Walks every component of the path but the last one, following symbolic links.
+    fn walk_to_parent_directory(&self, path: &str) -> Result<MemFSNode> {
+        if path.is_empty() {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        self.name_limits.check(path)?;
+
+        let parent = &path[..last_component_start(path).unwrap_or(path.len())];
+
+        PathLookup::new(&self.lookup_root(), true).walk(&self.lookup_start(path), parent)
+    }
+
+    /// Returns the directory a lookup of `path` starts from: the root for absolute paths,
+    /// and the working directory otherwise.
+    fn lookup_start(&self, path: &str) -> MemFSNode {
+        if Self::is_absolute_path(path) {
+            self.lookup_root()
+        } else {
+            self.current_directory().node
+        }
+    }
+
+    /// Root directory of the process running the call, which is that of the file system unless it was confined
+    /// with [MemFSProcess::chroot].
+    fn lookup_root(&self) -> MemFSNode {
+        active_process(self)
+            .and_then(|process| process.root())
+            .unwrap_or_else(|| self.root.clone())
+    }
+
+    fn is_confined(&self) -> bool {
+        active_process(self).is_some_and(|process| process.root().is_some())
+    }
+
+    /// Enters the exclusive gate, and the clock of the file system, for the time of an operation.
+    fn enter_operation(&self) -> (Option<OperationGuard<'_>>, ClockScope) {
+        (self.exclusive_gate.as_ref().map(ExclusiveGate::enter), self.clock.enter())
+    }
+
+    /// Runs `f`, which waits on another thread, outside the gate of [MemFS::lock_exclusive].
+    fn outside_gate<T>(&self, f: impl FnOnce() -> T) -> T {
+        match &self.exclusive_gate {
+            Some(gate) => gate.outside(f),
+            None => f(),
+        }
+    }
+
+    /// Runs a system call, recording its outcome in metrics and in the operation log.
+    fn syscall<T: SyscallOutput>(&self, args: SyscallArgs, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        #[cfg(feature = "tracing")]
+        let span = OperationSpan::enter(args.op().name(), args.fd(), || {
+            args.path().map(str::to_string).or_else(|| args.fd().and_then(|fd| self.descriptor_path(fd)))
+        });
+        let _operation = self.enter_operation();
+        let started = Instant::now();
+        let ticket = self.trace_recorder.as_ref().map(|r| r.begin());
+
+        if let Some(latency) = &self.latency {
+            self.inject_latency(latency, args);
+        }
+
+        let result = f().map_err(|err| {
+            let path = args.path().map(str::to_string).or_else(|| args.fd().and_then(|fd| self.descriptor_path(fd)));
+
+            err.with_context(args.op().name(), path.as_deref())
+        });
+
+        // Read data comes back through the read channel once the call is done.
+        if let Some(latency) = &self.latency
+            && let SyscallArgs::Read { .. } | SyscallArgs::Pread { .. } = args
+            && let Ok(read) = &result
+        {
+            latency.transfer(MemFSOp::Read, read.value().unwrap_or(0));
+        }
+
+        self.finish_syscall(args, started, &result);
+
+        if let (Some(recorder), Some(ticket)) = (&self.trace_recorder, ticket) {
+            recorder.finish(ticket, args, result.as_ref().map(|v| v.value()));
+        }
+
+        #[cfg(feature = "tracing")]
+        span.finish(result.as_ref().map(|v| v.value()));
+
+        result
+    }
+
+    /// Runs `f` as the method `name` on `path`, naming them in the error it returns as [MemFS::syscall] does.
+    fn operation<T>(&self, name: &'static str, path: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        #[cfg(feature = "tracing")]
+        let span = OperationSpan::enter(name, None, || Some(path.to_string()));
+        let _operation = self.enter_operation();
+        let result = f().map_err(|err| err.with_context(name, Some(path)));
+
+        #[cfg(feature = "tracing")]
+        span.finish(result.as_ref().map(|_| None));
+
+        result
+    }
+
+    /// Same as [MemFS::operation], for a method on the descriptor `fd`, named by the path it was opened with.
+    fn descriptor_operation<T>(&self, name: &'static str, fd: usize, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        #[cfg(feature = "tracing")]
+        let span = OperationSpan::enter(name, Some(fd), || self.descriptor_path(fd));
+        let _operation = self.enter_operation();
+        let result = f().map_err(|err| self.descriptor_context(err, name, fd));
+
+        #[cfg(feature = "tracing")]
+        span.finish(result.as_ref().map(|_| None));
+
+        result
+    }
+
+    /// Names the method `name` in `err`, with the path the descriptor `fd` was opened with.
+    fn descriptor_context(&self, err: MemFSErr, name: &'static str, fd: usize) -> MemFSErr {
+        err.with_context(name, self.descriptor_path(fd).as_deref())
+    }
+
+    /// Sleeps for the latency of the call. Written data goes through the write channel before the call is done.
+    fn inject_latency(&self, latency: &LatencyInjector, args: SyscallArgs) {
+        let path = if !latency.has_path_rules() {
+            None
+        } else if let Some(path) = args.path() {
+            Some(self.absolute_path(path))
+        } else {
+            args.fd().and_then(|fd| self.descriptor_path(fd))
+        };
+
+        latency.delay(args.op(), path.as_deref());
+
+        if let SyscallArgs::Write { data, .. } | SyscallArgs::Pwrite { data, .. } | SyscallArgs::Append { data, .. } =
+            args
+        {
+            latency.transfer(MemFSOp::Write, data.len());
+        }
+    }
+
+    fn finish_syscall<T: SyscallOutput>(&self, args: SyscallArgs, started: Instant, result: &Result<T>) {
+        let (op, path, fd) = (args.op(), args.path(), args.fd());
+        let outcome = result.as_ref().map(|v| v.value());
+        let duration = started.elapsed();
+        self.metrics.record(op, outcome, duration);
+
+        if let Some(logger) = &self.op_logger {
+            let value = outcome.ok().flatten();
+            let (fd, bytes) = match op {
+                MemFSOp::Open => (value, None),
+                MemFSOp::Read | MemFSOp::Write | MemFSOp::Append => (fd, value),
+                _ => (fd, None),
+            };
+
+            logger.log(&OpRecord {
+                op,
+                path,
+                fd,
+                bytes,
+                error: outcome.err(),
+                duration,
+            });
+        }
+    }
+
+    fn begin_mutation(&self) -> Result<MutationGuard<'_>> {
+        if self.read_only {
+            return Err(MemFSErr::read_only_file_system());
+        }
+
+        self.freeze_gate.enter()
+    }
+
+    /// Takes an image of `node` found at `path`. A file already met under another name in `files`
+    /// becomes a link to that name.
+    #[cfg(feature = "serde")]
+    fn image_node(node: &MemFSNode, path: &str, files: &mut HashMap<usize, String>) -> Result<NodeImage> {
+        let children = with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.list_children().map(Some),
+            _ => Ok(None),
+        })??;
+
+        let Some(mut children) = children else {
+            if let Some(first) = files.get(&node_key(node)) {
+                return Ok(NodeImage::Link(first.clone()));
+            }
+
+            return with_entry(node, |entry| match entry {
+                MemFSEntry::File(file) => {
+                    files.insert(node_key(node), path.to_string());
+                    Ok(NodeImage::File(file.contents()))
+                }
+                MemFSEntry::Symlink(link) => Ok(NodeImage::Symlink(link.target.clone())),
+                MemFSEntry::Device(device) => Ok(NodeImage::Device(device.device)),
+                MemFSEntry::Fifo(_) => Ok(NodeImage::Fifo),
+                _ => Err(MemFSErr::no_such_file_or_directory()),
+            })?;
+        };
+
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut entries = std::collections::BTreeMap::new();
+
+        for (name, child) in children {
+            // Virtual files are left out, as their callbacks cannot be written down.
+            if with_entry(&child, |entry| matches!(entry, MemFSEntry::Virtual(_)))? {
+                continue;
+            }
+
+            let child_image = Self::image_node(&child, &format!("{path}/{name}"), files)?;
+            entries.insert(name, child_image);
+        }
+
+        Ok(NodeImage::Directory(entries))
+    }
+
+    /// Appends the statistics of `node` and everything under it which was ever accessed.
+    fn collect_contention(node: &MemFSNode, path: String, out: &mut Vec<NodeContention>) -> Result<()> {
+        let (counters, file_type) = with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => (dir.contention.as_ref().and_then(|c| c.counters()), FileType::Directory),
+            MemFSEntry::File(file) => (file.contention.counters(), FileType::File),
+            _ => (None, FileType::File),
+        })?;
+
+        if let Some((acquisitions, contended)) = counters {
+            out.push(NodeContention {
+                path: path.clone(),
+                file_type,
+                acquisitions,
+                contended,
+            });
+        }
+
+        let children = with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.list_children(),
+            _ => Ok(Vec::new()),
+        })??;
+
+        for (name, child) in children {
+            let child_path = match path.as_str() {
+                "/" => format!("/{name}"),
+                _ => format!("{path}/{name}"),
+            };
+
+            Self::collect_contention(&child, child_path, out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts an access of the file behind `fd`, as contended if a write of the file is in progress,
+    /// and marks the file used.
+    fn record_file_access(&self, fd: usize) {
+        if !self.contention_stats && !self.eviction.is_enabled() {
+            return;
+        }
+
+        let _ = self.with_descriptor(fd, |descriptor| {
+            let node = &descriptor.entry;
+
+            if self.contention_stats {
+                let contended = file_is_busy(node);
+
+                with_entry(node, |entry| {
+                    if let MemFSEntry::File(file) = entry {
+                        file.contention.record_acquisition(contended);
+                    }
+                })?;
+            }
+
+            self.mark_used(node, &descriptor.path);
+
+            Ok(())
+        });
+    }
+
+    /// Moves `node`, used through `path`, to the most recently used end of the use order, while eviction is enabled.
+    fn mark_used(&self, node: &MemFSNode, path: &str) {
+        if !self.eviction.is_enabled() {
+            return;
+        }
+
+        let sweep = with_entry(node, |entry| match entry {
+            MemFSEntry::File(file) => self.use_order.touch(
+                file.attributes.ino(),
+                file.last_used.load(Ordering::Acquire),
+                NodeArc::downgrade(node),
+                path,
+                |stamp| file.last_used.store(stamp, Ordering::Release),
+            ),
+            _ => false,
+        });
+
+        if sweep.unwrap_or(false) {
+            self.use_order.sweep(|file| file.upgrade().is_some());
+        }
+    }
+
+    /// Runs `write`, and runs it again each time it fails with ENOMEM after evicting a file, while
+    /// [MemFSBuilder::eviction] is enabled. Fails with ENOMEM once no file is left to evict. A write which
+    /// failed with ENOMEM left its file as it was, apart from pages it allocated, so that it can run again.
+    fn evicting<T>(&self, mut write: impl FnMut() -> Result<T>) -> Result<T> {
+        loop {
+            match write() {
+                Err(err) if matches!(err.err_type, MemFSErrType::ENOMEM) && self.eviction.is_enabled() => {
+                    if !self.evict_next()? {
+                        return Err(err);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Evicts the least recently used file which holds pages of the block store and which no descriptor has
+    /// open, as in [Eviction], and returns whether one was. Files are taken from the use order, and entries
+    /// of files which are gone or were used again since are dropped on the way.
+    fn evict_next(&self) -> Result<bool> {
+        let open = self.open_node_keys();
+        let mut after = None;
+
+        while let Some((key, file, path)) = self.use_order.next_after(after) {
+            after = Some(key);
+
+            let Some(node) = file.upgrade() else {
+                self.use_order.remove(key);
+                continue;
+            };
+
+            let (current, evictable, links) = with_entry(&node, |entry| match entry {
+                MemFSEntry::File(file) => (
+                    file.last_used.load(Ordering::Acquire) == key.0,
+                    file.data.is_paged() && !file.has_shared_contents.load(Ordering::Acquire),
+                    file.links.load(Ordering::Acquire),
+                ),
+                _ => (false, false, 0),
+            })?;
+
+            if !current {
+                self.use_order.remove(key);
+                continue;
+            }
+
+            if !evictable || open.contains(&node_key(&node)) {
+                continue;
+            }
+
+            // The path of the entry is enough unless the file has other names or was moved since.
+            let paths = if links == 1
+                && self.get_node_of_given_path(&path).is_ok_and(|found| node_key(&found) == node_key(&node))
+            {
+                vec![path]
+            } else {
+                self.paths_of(&node)?
+            };
+
+            if paths.is_empty() {
+                // Not in the tree anymore. A later use enters the file again.
+                self.use_order.remove(key);
+                continue;
+            }
+
+            // As with sync, a file with several links is spilled once per name.
+            if self.eviction == Eviction::Spill && paths.iter().any(|path| self.sync_node(&node, path, false).is_err()) {
+                continue;
+            }
+
+            let mut evicted = false;
+
+            for path in paths {
+                // A name which now leads elsewhere was renamed or replaced since the file was found.
+                if self.get_node_of_given_path(&path).is_ok_and(|found| node_key(&found) == node_key(&node)) {
+                    self.journaled(
+                        || self.unlink_inner(&path),
+                        |_| Some(JournalEntry::Unlink { path: path.clone() }),
+                    )?;
+                    self.reclaim_removed(&path);
+                    self.touch_parent(&path);
+                    self.notify(WatchEventKind::Delete, &path);
+                    evicted = true;
+                }
+            }
+
+            if evicted {
+                self.use_order.remove(key);
+
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Returns every path `node` is found at in the tree, walking the whole tree.
+    fn paths_of(&self, node: &MemFSNode) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        let mut pending = vec![(self.root.clone(), String::new())];
+
+        while let Some((dir, path)) = pending.pop() {
+            let children = with_entry(&dir, |entry| match entry {
+                MemFSEntry::Directory(dir) => dir.list_children(),
+                _ => Ok(Vec::new()),
+            })??;
+
+            for (name, child) in children {
+                let child_path = format!("{path}/{name}");
+
+                if node_key(&child) == node_key(node) {
+                    paths.push(child_path);
+                } else if with_entry(&child, |entry| matches!(entry, MemFSEntry::Directory(_)))? {
+                    pending.push((child, child_path));
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Frees the entries removed from the directory of `path`, and the descriptors closed, by this thread which
+    /// no thread can reach anymore, rather than once enough of them were removed, so that the memory of
+    /// an evicted file is given back right away. Descriptors closed by other threads are freed as usual.
+    #[cfg(feature = "lock-free")]
+    fn reclaim_removed(&self, path: &str) {
+        for shard in self.file_descriptors.iter() {
+            papaya::Guard::flush(&shard.guard());
+        }
+
+        if let Ok(parent) = self.get_parent_directory_node_of_given_path(path) {
+            let parent = match &*parent {
+                MemFSEntry::ResolvedAsRoot => self.root.clone(),
+                _ => parent,
+            };
+
+            if let MemFSEntry::Directory(dir) = &*parent {
+                papaya::Guard::flush(&dir.children.guard());
+            }
+        }
+    }
+
+    /// Removed entries are freed once the last reference to them is dropped.
+    #[cfg(any(feature = "coarse-grained", feature = "fine-grained"))]
+    fn reclaim_removed(&self, _path: &str) {}
+
+    /// Copies `dir` and everything under it into `copy`, and returns the node of the copy.
+    /// Subdirectories of the copy are configured like `copy`, and keep the permission bits and owners of the originals.
+    /// With `share_files`, files are not copied but shared between both trees.
+    fn deep_copy_directory(dir: &MemFSDirNode, copy: MemFSDirNode, share_files: bool) -> Result<MemFSNode> {
+        copy.changed
+            .store(dir.changed.load(Ordering::Acquire), Ordering::Release);
+
+        let copy = MemFSDirNode {
+            attributes: Arc::new((*dir.attributes).clone()),
+            ..copy
+        };
+
+        let template = copy.clone();
+        let copy_node = new_node(MemFSEntry::Directory(copy));
+
+        for (name, child) in dir.list_children()? {
+            let child_copy = with_entry(&child, |entry| match entry {
+                MemFSEntry::Directory(child_dir) => Self::deep_copy_directory(
+                    child_dir,
+                    template.child_directory(&copy_node),
+                    share_files,
+                ),
+                MemFSEntry::File(_) if share_files => Ok(child.clone()),
+                MemFSEntry::File(file) => Ok(new_node(MemFSEntry::File(Box::new(file.duplicate())))),
+                MemFSEntry::Symlink(link) => Ok(new_node(MemFSEntry::Symlink(MemFSSymlinkNode {
+                    target: link.target.clone(),
+                    ino: link.ino,
+                    _charge: None,
+                }))),
+                MemFSEntry::Device(device) => Ok(new_node(MemFSEntry::Device(MemFSDeviceNode {
+                    device: device.device,
+                    attributes: Arc::new((*device.attributes).clone()),
+                }))),
+                // Data buffered in a FIFO belongs to the descriptors open on it, so copies start empty.
+                MemFSEntry::Fifo(fifo) => Ok(new_node(MemFSEntry::Fifo(MemFSFifoNode {
+                    pipe: Arc::default(),
+                    attributes: Arc::new((*fifo.attributes).clone()),
+                }))),
+                MemFSEntry::Virtual(node) => Ok(new_node(MemFSEntry::Virtual(MemFSVirtualNode {
+                    file: node.file.clone(),
+                    attributes: Arc::new((*node.attributes).clone()),
+                }))),
+                MemFSEntry::ResolvedAsRoot => Err(MemFSErr::no_such_file_or_directory()),
+            })??;
+
+            with_entry(&copy_node, |entry| match entry {
+                MemFSEntry::Directory(copy_dir) => copy_dir.insert_child(&name, child_copy),
+                _ => Err(MemFSErr::is_not_directory()),
+            })??;
+        }
+
+        Ok(copy_node)
+    }
+
+    /// Makes every file of a snapshot tree save its contents into `saved` before it is written next.
+    fn share_files_with_snapshot(root: &MemFSNode, saved: &Arc<SavedContents>) -> Result<()> {
+        let mut pending = vec![root.clone()];
+
+        while let Some(node) = pending.pop() {
+            with_entry(&node, |entry| -> Result<()> {
+                match entry {
+                    MemFSEntry::Directory(dir) => {
+                        pending.extend(dir.list_children()?.into_iter().map(|(_, child)| child));
+                    }
+                    MemFSEntry::File(file) => file.share_with_snapshot(saved),
+                    MemFSEntry::Symlink(_)
+                    | MemFSEntry::Device(_)
+                    | MemFSEntry::Fifo(_)
+                    | MemFSEntry::Virtual(_)
+                    | MemFSEntry::ResolvedAsRoot => {}
+                }
+
+                Ok(())
+            })??;
+        }
+
+        Ok(())
+    }
+
+    /// Builds a copy of the tree of `snapshot` for this file system, then swaps it for the current tree.
+    fn restore_tree(&self, snapshot: &Snapshot) -> Result<()> {
+        with_entry(&self.root, |entry| -> Result<()> {
+            let MemFSEntry::Directory(root) = entry else {
+                return Err(MemFSErr::no_such_file_or_directory());
+            };
+
+            let mut files = HashMap::new();
+            let mut restored = Vec::new();
+
+            let (mode, owner) = with_entry(&snapshot.root, |entry| match entry {
+                MemFSEntry::Directory(dir) => {
+                    for (name, child) in dir.list_children()? {
+                        restored.push((name, self.restore_node(&child, &self.root, root, snapshot, &mut files)?));
+                    }
+
+                    Ok((dir.attributes.mode(), dir.attributes.owner()))
+                }
+                _ => Err(MemFSErr::no_such_file_or_directory()),
+            })??;
+
+            for child in root.drain_children()? {
+                Self::reclaim_subtree(child, true);
+            }
+
+            for (name, node) in restored {
+                root.insert_child(&name, node)?;
+            }
+
+            root.attributes.set_mode(mode);
+            root.attributes.set_owner(Some(owner.uid), Some(owner.gid));
+            root.attributes.times().touch_modify();
+
+            Ok(())
+        })??;
+
+        self.invalidate_dentries();
+
+        self.register_subtree(&self.root, "");
+
+        Ok(())
+    }
+
+    /// Copies a node of a snapshot tree under `parent`, charging the copy against the quota.
+    /// `files` maps files of the snapshot to their copy, so that hard links stay links of one file.
+    fn restore_node(
+        &self,
+        node: &MemFSNode,
+        parent: &MemFSNode,
+        root: &MemFSDirNode,
+        snapshot: &Snapshot,
+        files: &mut HashMap<usize, MemFSNode>,
+    ) -> Result<MemFSNode> {
+        if let Some(copy) = files.get(&node_key(node)) {
+            with_entry(copy, |entry| {
+                if let MemFSEntry::File(file) = entry {
+                    file.links.fetch_add(1, Ordering::AcqRel);
+                }
+            })?;
+
+            return Ok(copy.clone());
+        }
+
+        with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => {
+                let copy = root
+                    .child_directory(parent)
+                    .with_attributes(self.charged_attributes(&dir.attributes)?);
+                let copy_node = new_node(MemFSEntry::Directory(copy.clone()));
+
+                for (name, child) in dir.list_children()? {
+                    let child_copy = self.restore_node(&child, &copy_node, root, snapshot, files)?;
+                    copy.insert_child(&name, child_copy)?;
+                }
+
+                Ok(copy_node)
+            }
+            MemFSEntry::File(file) => {
+                let contents = file.snapshot_version(&snapshot.saved);
+                let saved = file.snapshot_attributes(&snapshot.saved);
+                let attributes = self.charged_attributes(&saved)?;
+
+                if let Some(charge) = attributes.charge() {
+                    charge.set_data(contents.len() as u64)?;
+                }
+
+                let mut copy = MemFSFileNode::with_attributes(attributes);
+                copy.pool = Some(self.file_memory.clone());
+                copy.restore(&contents)?;
+
+                // Restoring the contents stamps the file as written, so its times are set back afterwards.
+                copy.attributes
+                    .times()
+                    .set(Some(saved.times().accessed()), Some(saved.times().modified()))?;
+
+                let copy_node = new_node(MemFSEntry::File(Box::new(copy)));
+                files.insert(node_key(node), copy_node.clone());
+
+                Ok(copy_node)
+            }
+            MemFSEntry::Symlink(link) => {
+                let charge = self
+                    .accounting
+                    .charge_metadata(NODE_METADATA_BYTES + link.target.len() as u64)?;
+
+                Ok(new_node(MemFSEntry::Symlink(MemFSSymlinkNode {
+                    target: link.target.clone(),
+                    ino: link.ino,
+                    _charge: Some(charge),
+                })))
+            }
+            MemFSEntry::Device(device) => Ok(new_node(MemFSEntry::Device(MemFSDeviceNode {
+                device: device.device,
+                attributes: Arc::new(self.charged_attributes(&device.attributes)?),
+            }))),
+            MemFSEntry::Fifo(fifo) => Ok(new_node(MemFSEntry::Fifo(MemFSFifoNode {
+                pipe: Arc::default(),
+                attributes: Arc::new(self.charged_attributes(&fifo.attributes)?),
+            }))),
+            MemFSEntry::Virtual(node) => Ok(new_node(MemFSEntry::Virtual(MemFSVirtualNode {
+                file: node.file.clone(),
+                attributes: Arc::new(self.charged_attributes(&node.attributes)?),
+            }))),
+            MemFSEntry::ResolvedAsRoot => Err(MemFSErr::no_such_file_or_directory()),
+        })?
+    }
+
+    /// Copies attributes of a node, keeping its inode number, and charges the copy for its metadata.
+    fn charged_attributes(&self, attributes: &NodeAttributes) -> Result<NodeAttributes> {
+        let charge = self.accounting.charge_metadata(NODE_METADATA_BYTES)?;
+
+        Ok(attributes.clone().with_charge(charge))
+    }
+
+    /// Runs a mutation, journaling what `entry` describes of it once it succeeded, if journaling is enabled.
+    fn journaled<T>(
+        &self,
+        mutate: impl FnOnce() -> Result<T>,
+        entry: impl FnOnce(&T) -> Option<JournalEntry>,
+    ) -> Result<T> {
+        match &self.journal {
+            Some(journal) => journal.record(mutate, entry),
+            None => mutate(),
+        }
+    }
+
+    /// Describes a write of `written` bytes of `data` through `fd`. Without `offset`, the write ended
+    /// at the offset of the descriptor, which no other write moved since, as mutations are serialized.
+    fn journal_write(&self, fd: usize, data: &[u8], written: usize, offset: Option<usize>) -> Option<JournalEntry> {
+        if written == 0 {
+            return None;
+        }
+
+        // Devices, FIFOs and virtual files store nothing, and their descriptors keep no offset.
+        if with_entry(&self.descriptor_entry(fd)?, |entry| {
+            matches!(entry, MemFSEntry::Device(_) | MemFSEntry::Fifo(_) | MemFSEntry::Virtual(_))
+        })
+        .ok()?
+        {
+            return None;
+        }
+
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                self.with_descriptor(fd, |descriptor| Ok(descriptor.file_offset.load(Ordering::Acquire)))
+                    .ok()?
+                    - written
+            }
+        };
+
+        Some(JournalEntry::Write {
+            ino: self.descriptor_ino(fd)?,
+            offset,
+            data: data[..written].to_vec(),
+        })
+    }
+
+    /// Describes a change of metadata through `fd`: by inode number for a regular file, as the replay keeps
+    /// a descriptor of every file it created, and by the path the descriptor was opened with otherwise.
+    fn journal_descriptor_change(
+        &self,
+        fd: usize,
+        by_ino: impl FnOnce(u64) -> JournalEntry,
+        by_path: impl FnOnce(String) -> JournalEntry,
+    ) -> Option<JournalEntry> {
+        let node = self.descriptor_entry(fd)?;
+
+        match with_entry(&node, |entry| matches!(entry, MemFSEntry::File(_))).ok()? {
+            true => self.descriptor_ino(fd).map(by_ino),
+            false => self.descriptor_path(fd).map(by_path),
+        }
+    }
+
+    fn descriptor_ino(&self, fd: usize) -> Option<u64> {
+        let node = self.descriptor_entry(fd)?;
+
+        with_entry(&node, Self::entry_ino).ok().flatten()
+    }
+
+    /// Creates the descriptor of a file opened with `flag`.
+    /// Files of a snapshot view are always read as they were when the snapshot was taken.
+    fn new_descriptor(&self, number: usize, flag: OpenFlag, entry: MemFSNode, path: String) -> MemFSFileDescriptor {
+        self.mark_used(&entry, &path);
+        let mut descriptor = MemFSFileDescriptor::new(number, flag, entry, path);
+
+        if let Some(saved) = &self.snapshot_contents {
+            descriptor.pinned = with_entry(&descriptor.entry, |entry| match entry {
+                MemFSEntry::File(file) => Some(file.snapshot_version(saved)),
+                _ => None,
+            })
+            .ok()
+            .flatten();
+        }
+
+        descriptor
+    }
+
+    /// Returns the pipe of the FIFO the descriptor is open on, unless it was opened with O_NONBLOCK.
+    fn blocking_pipe(&self, fd: usize) -> Option<Arc<Pipe>> {
+        self.with_descriptor(fd, |descriptor| {
+            Ok(descriptor
+                .pipe_end
+                .as_ref()
+                .filter(|_| !descriptor.flag.contains(OpenFlag::O_NONBLOCK))
+                .map(|end| end.pipe().clone()))
+        })
+        .ok()
+        .flatten()
+    }
+
+    /// Runs `io` through `fd` until it does not fail with EAGAIN, which only FIFOs do, calling `wait`
+    /// on the pipe in between. `wait` runs without any lock of the file system held and outside the gate,
+    /// so that neither the other end nor [MemFS::lock_exclusive] waits for it. Descriptors opened with
+    /// O_NONBLOCK fail with EAGAIN instead.
+    fn retry_on_pipe(&self, fd: usize, mut io: impl FnMut() -> Result<usize>, wait: impl Fn(&Pipe)) -> Result<usize> {
+        loop {
+            match io() {
+                Err(err) if matches!(err.err_type, MemFSErrType::EAGAIN) => match self.blocking_pipe(fd) {
+                    Some(pipe) => self.outside_gate(|| wait(&pipe)),
+                    None => return Err(err),
+                },
+                done => return done,
+            }
+        }
+    }
+
+    /// Writes `data` with `write`, which a FIFO may take only part of, until all of it is in. Once part of it
+    /// is, a failure, such as EAGAIN with O_NONBLOCK or EPIPE, ends the write with the number of bytes written.
+    fn write_all_to_pipe(&self, fd: usize, data: &[u8], write: impl Fn(&[u8]) -> Result<usize>) -> Result<usize> {
+        let mut written = 0;
+
+        loop {
+            let rest = &data[written..];
+            let count = match self.retry_on_pipe(fd, || write(rest), |pipe| pipe.wait_writable(rest.len())) {
+                Ok(count) => count,
+                Err(_) if written > 0 => return Ok(written),
+                Err(err) => return Err(err),
+            };
+            written += count;
+
+            if count == 0 || written == data.len() {
+                return Ok(written);
+            }
+        }
+    }
+
+    fn write_tracked(
+        &self,
+        tracker: &CrashTracker<MemFSNode>,
+        fd: usize,
+        write: impl FnOnce() -> Result<usize>,
+    ) -> Result<usize> {
+        let node = self
+            .descriptor_entry(fd)
+            .ok_or(MemFSErr::bad_file_descriptor())?;
+        let contents = |node: &MemFSNode| {
+            with_entry(node, |entry| match entry {
+                MemFSEntry::File(file) => file.contents(),
+                _ => Vec::new(),
+            })
+            .unwrap_or_default()
+        };
+
+        tracker.track_write(node_key(&node), &node, || {
+            let before = contents(&node);
+            let result = write();
+
+            (before, result, contents(&node))
+        })
+    }
+
+    /// Writes at `offset` through `fd`, as a write of the file when crash simulation is enabled.
+    fn pwrite_inner(&self, fd: usize, data: &[u8], offset: usize) -> Result<usize> {
+        let pwrite = || self.with_descriptor(fd, |descriptor| descriptor.write_file_at(data, offset));
+        let written = self.evicting(|| {
+            self.journaled(
+                || match &self.crash_tracker {
+                    Some(tracker) => self.write_tracked(tracker, fd, pwrite),
+                    None => pwrite(),
+                },
+                |&written| self.journal_write(fd, data, written, Some(offset)),
+            )
+        })?;
+
+        if written > 0 {
+            self.notify_write(fd);
+        }
+
+        Ok(written)
+    }
+
+    fn truncate_node(&self, node: &MemFSNode, len: usize) -> Result<()> {
+        self.resize_node(node, |file| file.truncate(len))
+    }
+
+    /// Truncates or grows a file node with `resize`, as a write of it when crash simulation is enabled.
+    fn resize_node<T>(&self, node: &MemFSNode, resize: impl FnOnce(&MemFSFileNode) -> Result<T>) -> Result<T> {
+        let Some(tracker) = &self.crash_tracker else {
+            return Self::resize_file(node, resize);
+        };
+        let contents = || {
+            with_entry(node, |entry| match entry {
+                MemFSEntry::File(file) => file.contents(),
+                _ => Vec::new(),
+            })
+            .unwrap_or_default()
+        };
+
+        tracker.track_write(node_key(node), node, || {
+            let before = contents();
+            let result = Self::resize_file(node, resize);
+
+            (before, result, contents())
+        })
+    }
+
+    /// Holds the write lock of the node, so that no read or write of the file overlaps the resize.
+    #[cfg(feature = "coarse-grained")]
+    fn resize_file<T>(node: &MemFSNode, resize: impl FnOnce(&MemFSFileNode) -> Result<T>) -> Result<T> {
+        let guard = node.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        match &*guard {
+            MemFSEntry::File(file) => resize(file),
+            _ => Err(MemFSErr::is_directory()),
+        }
+    }
+
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn resize_file<T>(node: &MemFSNode, resize: impl FnOnce(&MemFSFileNode) -> Result<T>) -> Result<T> {
+        match &**node {
+            MemFSEntry::File(file) => resize(file),
+            _ => Err(MemFSErr::is_directory()),
+        }
+    }
+
+    fn reset_current_directories(&self) {
+        match &self.cwd {
+            WorkingDirectory::Shared(_) => self.set_current_directory(self.root_directory()),
+            WorkingDirectory::PerThread(cwds) => cwds.clear(),
+        }
+    }
+
+    pub(crate) fn root_directory(&self) -> CurrentDirectory {
+        CurrentDirectory {
+            node: self.root.clone(),
+            path: "/".to_string(),
+        }
+    }
+
+    /// Working directory of the process running the call, or else of the calling thread.
+    fn current_directory(&self) -> CurrentDirectory {
+        let key = self as *const MemFS as usize;
+        let at = AT_DIRECTORY.with(|at| match &*at.borrow() {
+            Some((at_key, directory)) if *at_key == key => Some(directory.clone()),
+            _ => None,
+        });
+
+        if let Some(directory) = at {
+            return directory;
+        }
+
+        if let Some(process) = active_process(self) {
+            return process.cwd();
+        }
+
+        match &self.cwd {
+            WorkingDirectory::Shared(cwd) => cwd.read().unwrap_or_else(PoisonError::into_inner).clone(),
+            WorkingDirectory::PerThread(cwds) => match cwds.get(&thread::current().id()) {
+                Some(cwd) => cwd.clone(),
+                None => self.root_directory(),
+            },
+        }
+    }
+
+    fn set_current_directory(&self, new_cwd: CurrentDirectory) {
+        if let Some(process) = active_process(self) {
+            return process.set_cwd(new_cwd);
+        }
+
+        match &self.cwd {
+            WorkingDirectory::Shared(cwd) => {
+                *cwd.write().unwrap_or_else(PoisonError::into_inner) = new_cwd;
+            }
+            WorkingDirectory::PerThread(cwds) => {
+                cwds.insert(thread::current().id(), new_cwd);
+            }
+        }
+    }
+
+    fn entry_ino(entry: &MemFSEntry) -> Option<u64> {
+        match entry {
+            MemFSEntry::Directory(dir) => Some(dir.attributes.ino()),
+            MemFSEntry::File(file) => Some(file.attributes.ino()),
+            MemFSEntry::Symlink(link) => Some(link.ino),
+            MemFSEntry::Device(device) => Some(device.attributes.ino()),
+            MemFSEntry::Fifo(fifo) => Some(fifo.attributes.ino()),
+            MemFSEntry::Virtual(node) => Some(node.attributes.ino()),
+            MemFSEntry::ResolvedAsRoot => None,
+        }
+    }
+
+    fn sync_descriptor(&self, fd: usize, data_only: bool) -> Result<()> {
+        let node = self
+            .descriptor_entry(fd)
+            .ok_or(MemFSErr::bad_file_descriptor())?;
+
+        self.sync_node(&node, &self.descriptor_path(fd).unwrap_or_default(), data_only)
+    }
+
+    /// Marks the writes of a file as durable, and hands it to the durability backend if any.
+    /// Directories and symbolic links have nothing to sync.
+    fn sync_node(&self, node: &MemFSNode, path: &str, data_only: bool) -> Result<()> {
+        if let Some(tracker) = &self.crash_tracker {
+            tracker.sync(node_key(node));
+        }
+
+        #[cfg(feature = "dedup")]
+        self.deduplicate(node);
+
+        let Some(backend) = &self.durability else {
+            return Ok(());
+        };
+        let file = with_entry(node, |entry| match entry {
+            MemFSEntry::File(file) => self.stat_entry(entry).map(|stat| Some((stat, file.contents()))),
+            _ => Ok(None),
+        })??;
+
+        if let Some((stat, contents)) = file {
+            backend.sync_file(&SyncedFile {
+                path,
+                stat: &stat,
+                contents: &contents,
+                data_only,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Pages holding the contents of the file at `path` along with their index, or None if the contents are not
+    /// in pages of their own, such as inline or shared ones.
+    pub(crate) fn file_pages(&self, path: &str) -> Result<Option<IndexedPages>> {
+        let node = self.get_node_of_given_path(path)?;
+
+        with_entry(&node, |entry| match entry {
+            MemFSEntry::File(file) if !file.has_shared_contents.load(Ordering::Acquire) => Ok(file.data.pages()),
+            MemFSEntry::File(_) => Ok(None),
+            _ => Err(MemFSErr::is_directory()),
+        })?
+    }
+
+    /// Makes the file at `path`, which was never written, hold `pages` as they are.
+    pub(crate) fn adopt_pages(&self, path: &str, pages: &[(usize, NonNull<Page>)]) -> Result<()> {
+        let node = self.get_node_of_given_path(path)?;
+
+        with_entry(&node, |entry| match entry {
+            MemFSEntry::File(file) => {
+                file.data.adopt_pages(pages);
+                Ok(())
+            }
+            _ => Err(MemFSErr::is_directory()),
+        })?
+    }
+
+    /// Makes the file at `node` share its contents with the files holding the same ones, if deduplication is
+    /// enabled.
+    #[cfg(feature = "dedup")]
+    fn deduplicate(&self, node: &MemFSNode) {
+        let Some(table) = &self.dedup else {
+            return;
+        };
+
+        let _ = with_entry(node, |entry| {
+            if let MemFSEntry::File(file) = entry {
+                file.deduplicate(table);
+            }
+        });
+    }
+
+    /// Maps the inode number of a new node to it, sweeping entries of dropped nodes once the table grew enough.
+    fn register_inode(&self, node: &MemFSNode) {
+        let Ok(Some(ino)) = with_entry(node, Self::entry_ino) else {
+            return;
+        };
+
+        if self.inodes.register(ino, NodeArc::downgrade(node)) {
+            self.inodes.sweep(|node| node.upgrade().is_some());
+        }
+    }
+
+    /// Registers every node of a tree, and numbers new nodes after the largest inode number found.
+    /// Files are entered as never used, while eviction is enabled. `path` is the path of `node`, empty for the root.
+    fn register_subtree(&self, node: &MemFSNode, path: &str) {
+        if let Ok(Some(ino)) = with_entry(node, Self::entry_ino) {
+            self.inodes.reserve(ino);
+        }
+
+        self.register_inode(node);
+
+        if self.eviction.is_enabled() {
+            let _ = with_entry(node, |entry| {
+                if let MemFSEntry::File(file) = entry
+                    && file.last_used.load(Ordering::Acquire) == 0
+                {
+                    self.use_order
+                        .enter_unused(file.attributes.ino(), NodeArc::downgrade(node), path.to_string());
+                }
+            });
+        }
+
+        let children = with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.list_children().unwrap_or_default(),
+            _ => Vec::new(),
+        })
+        .unwrap_or_default();
+
+        for (name, child) in children {
+            self.register_subtree(&child, &format!("{path}/{name}"));
+        }
+    }
+
+    /// Credentials of the process running the call, or of the superuser for calls made directly.
+    /// Process the caller runs as, which owns the byte-range locks it takes.
+    fn caller_pid(&self) -> u64 {
+        active_process(self).map_or(MAIN_PID, |process| process.pid())
+    }
+
+    fn caller_credentials(&self) -> Credentials {
+        active_process(self).map_or(Credentials::ROOT, |process| process.credentials())
+    }
+
+    /// Attributes of a node created by the caller with `mode`: the caller owns it, and the file creation mask
+    /// of the caller is applied to the mode.
+    /// Fails with ENOSPC if the metadata of the node would exceed the quota.
+    fn new_attributes(&self, mode: u32) -> Result<NodeAttributes> {
+        let umask = active_process(self).map_or(DEFAULT_UMASK, |process| process.umask());
+        let charge = self.accounting.charge_metadata(NODE_METADATA_BYTES)?;
+
+        Ok(NodeAttributes::new(self.inodes.allocate(), mode & !umask, self.caller_credentials()).with_charge(charge))
+    }
+
+    /// Fails with EACCES if permission checks are enabled and the caller is not granted `access` to the node.
+    fn check_access(&self, node: &MemFSNode, access: u32) -> Result<()> {
+        if !self.permission_checks {
+            return Ok(());
+        }
+
+        self.node_attributes(node, |attributes| attributes.check(self.caller_credentials(), access))
+    }
+
+    /// Checks that the caller may add and remove entries of the directory holding the last component of the path.
+    fn check_parent_access(&self, path: &str) -> Result<()> {
+        if !self.permission_checks {
+            return Ok(());
+        }
+
+        self.check_access(&self.parent_directory(path)?, MAY_WRITE | MAY_EXECUTE)
+    }
+
+    /// Checks the accesses requested by the flags of [MemFS::open]: reading and writing an existing file,
+    /// or creating a missing one. Lookup errors are left for the open itself to report.
+    fn check_open_access(&self, path: &str, flag: &OpenFlag) -> Result<()> {
+        if !self.permission_checks {
+            return Ok(());
+        }
+
+        match self.get_node_of_given_path(path) {
+            Ok(_) if flag.contains(OpenFlag::O_CREAT | OpenFlag::O_EXCL) => Ok(()),
+            Ok(node) => {
+                let access = if flag.contains(OpenFlag::O_RDWR) {
+                    MAY_READ | MAY_WRITE
+                } else if flag.contains(OpenFlag::O_WRONLY) {
+                    MAY_WRITE
+                } else {
+                    MAY_READ
+                };
+
+                self.check_access(&node, access)
+            }
+            Err(e) if matches!(e.err_type, MemFSErrType::ENOENT) && flag.contains(OpenFlag::O_CREAT) => {
+                self.check_parent_access(path)
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Runs `f` on the attributes of a file, directory, device, FIFO or virtual file.
+    /// Fails with ENOENT on a symbolic link, which has none.
+    fn node_attributes<R>(&self, node: &MemFSNode, f: impl FnOnce(&NodeAttributes) -> Result<R>) -> Result<R> {
+        with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => f(&dir.attributes),
+            MemFSEntry::File(file) => f(&file.attributes),
+            MemFSEntry::Device(device) => f(&device.attributes),
+            MemFSEntry::Fifo(fifo) => f(&fifo.attributes),
+            MemFSEntry::Virtual(node) => f(&node.attributes),
+            MemFSEntry::Symlink(_) => Err(MemFSErr::no_such_file_or_directory()),
+            MemFSEntry::ResolvedAsRoot => self.node_attributes(&self.root, f),
+        })?
+    }
+
+    fn chmod_node(&self, node: &MemFSNode, mode: u32) -> Result<()> {
+        self.node_attributes(node, |attributes| {
+            if self.permission_checks {
+                attributes.check_chmod(self.caller_credentials())?;
+            }
+
+            attributes.set_mode(mode);
+            attributes.times().touch_status();
+
+            Ok(())
+        })
+    }
+
+    fn utimens_node(&self, node: &MemFSNode, atime: Option<SystemTime>, mtime: Option<SystemTime>) -> Result<()> {
+        self.node_attributes(node, |attributes| {
+            if self.permission_checks {
+                attributes.check_chmod(self.caller_credentials())?;
+            }
+
+            attributes.times().set(atime, mtime)
+        })
+    }
+
+    /// Stamps the modification time of the directory holding the last component of the path,
+    /// after an entry was added to or removed from it.
+    fn touch_parent(&self, path: &str) {
+        if let Ok(parent) = self.parent_directory(path) {
+            let _ = self.node_attributes(&parent, |attributes| {
+                attributes.times().touch_modify();
+                Ok(())
+            });
+        }
+    }
+
+    /// Stamps the status change time of the node at the path, without following a final symbolic link.
+    fn touch_status(&self, path: &str) {
+        if let Ok(node) = self.get_link_node_of_given_path(path) {
+            let _ = self.node_attributes(&node, |attributes| {
+                attributes.times().touch_status();
+                Ok(())
+            });
+        }
+    }
+
+    fn chown_node(&self, node: &MemFSNode, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.node_attributes(node, |attributes| {
+            if self.permission_checks {
+                attributes.check_chown(self.caller_credentials(), uid, gid)?;
+            }
+
+            attributes.set_owner(uid, gid);
+            attributes.times().touch_status();
+
+            Ok(())
+        })
+    }
+
+    fn stat_entry(&self, entry: &MemFSEntry) -> Result<Stat> {
+        let (file_type, size, change_seq, nlink, attributes) = match entry {
+            MemFSEntry::Directory(dir) => (
+                FileType::Directory,
+                dir.child_count()?,
+                dir.changed.load(Ordering::Acquire),
+                1,
+                Some(&*dir.attributes),
+            ),
+            MemFSEntry::File(file) => (
+                FileType::File,
+                file.size.load(Ordering::Acquire),
+                file.changed.load(Ordering::Acquire),
+                file.links.load(Ordering::Acquire),
+                Some(&file.attributes),
+            ),
+            // Symbolic links have no attributes of their own.
+            MemFSEntry::Symlink(link) => (FileType::Symlink, link.target.len(), 0, 1, None),
+            MemFSEntry::Device(device) => (FileType::CharDevice, 0, 0, 1, Some(&*device.attributes)),
+            MemFSEntry::Fifo(fifo) => (FileType::Fifo, 0, 0, 1, Some(&*fifo.attributes)),
+            MemFSEntry::Virtual(node) => (FileType::File, 0, 0, 1, Some(&*node.attributes)),
+            MemFSEntry::ResolvedAsRoot => return with_entry(&self.root, |root| self.stat_entry(root))?,
+        };
+        let owner = attributes.map_or(Credentials::ROOT, NodeAttributes::owner);
+        let time = |get: fn(&Timestamps) -> SystemTime| attributes.map_or(UNIX_EPOCH, |a| get(a.times()));
+
+        Ok(Stat {
+            ino: Self::entry_ino(entry).unwrap_or(ROOT_INO),
+            file_type,
+            size,
+            change_seq,
+            nlink,
+            mode: attributes.map_or(0o777, NodeAttributes::mode),
+            uid: owner.uid,
+            gid: owner.gid,
+            atime: time(Timestamps::accessed),
+            mtime: time(Timestamps::modified),
+            ctime: time(Timestamps::status_changed),
+            btime: time(Timestamps::created),
+        })
+    }
+
+    /// Resolves the directory once and returns metadata of the given children of it, in the given order.
+    fn stat_children<'a>(
+        &self,
+        dir_path: &str,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Result<Vec<Result<Stat>>> {
+        let dir_node = self.get_node_of_given_path(dir_path)?;
+        let children = with_entry(&dir_node, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.lookup_children(names),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::is_not_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => with_entry(&self.root, |root| match root {
+                MemFSEntry::Directory(dir) => dir.lookup_children(names),
+                _ => unreachable!(),
+            })?,
+        })??;
+
+        Ok(children
+            .into_iter()
+            .map(|child| match child {
+                Some(node) => {
+                    let node = PathLookup::new(&self.lookup_root(), true).found(&dir_node, &node)?;
+                    with_entry(&node, |entry| self.stat_entry(entry))?
+                }
+                None => Err(MemFSErr::no_such_file_or_directory()),
+            })
+            .collect())
+    }
+
+    /// Returns the children of the directory at the moment of the call.
+    fn list_directory(&self, path: &str) -> Result<Vec<(String, MemFSNode)>> {
+        let node = self.get_node_of_given_path(path)?;
+
+        with_entry(&node, |entry| match entry {
+            MemFSEntry::Directory(dir) => dir.list_children(),
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::is_not_directory())
+            }
+            MemFSEntry::ResolvedAsRoot => with_entry(&self.root, |root| match root {
+                MemFSEntry::Directory(dir) => dir.list_children(),
+                _ => unreachable!(),
+            })?,
+        })?
+    }
+
+    /// Unlinks a directory from its parent regardless of its contents, and returns its node.
+    fn detach_directory(&self, path: &str) -> Result<MemFSNode> {
+        let _mutation = self.begin_mutation()?;
+
+        if path == "/" {
+            return Err(MemFSErr::busy());
+        }
+
+        self.check_parent_access(path)?;
+        let dir_node = self.get_parent_directory_node_of_given_path(path)?;
+        let last_elem = Self::get_last_component_of_path(path)?;
+
+        if last_elem == "." {
+            return Err(MemFSErr::invalid_value());
+        } else if last_elem == ".." {
+            return Err(MemFSErr::is_not_empty());
+        }
+
+        let subtree = self.journaled(
+            || {
+                with_entry(&dir_node, |entry| match entry {
+                    MemFSEntry::Directory(dir) => dir.detach_directory(last_elem),
+                    MemFSEntry::File(_)
+                    | MemFSEntry::Symlink(_)
+                    | MemFSEntry::Device(_)
+                    | MemFSEntry::Fifo(_)
+                    | MemFSEntry::Virtual(_) => {
+                        Err(MemFSErr::no_such_file_or_directory())
+                    }
+                    MemFSEntry::ResolvedAsRoot => Err(MemFSErr::busy()),
+                })?
+            },
+            |_| Some(JournalEntry::RemoveAll { path: self.absolute_path(path) }),
+        )?;
+
+        self.invalidate_dentries();
+        self.notify(WatchEventKind::Delete, path);
+
+        Ok(subtree)
+    }
+
+    /// Takes a detached subtree apart one node at a time, so that deep trees are not dropped recursively.
+    /// With `unlink_files`, files of the subtree lose their link to it.
+    fn reclaim_subtree(subtree: MemFSNode, unlink_files: bool) -> RemovalReport {
+        let mut report = RemovalReport::default();
+        let mut pending = vec![subtree];
+
+        while let Some(node) = pending.pop() {
+            let children = with_entry(&node, |entry| match entry {
+                MemFSEntry::Directory(dir) => {
+                    report.directories += 1;
+                    dir.drain_children()
+                }
+                MemFSEntry::File(file) => {
+                    if unlink_files {
+                        file.links.fetch_sub(1, Ordering::AcqRel);
+                    }
+
+                    report.files += 1;
+                    Ok(Vec::new())
+                }
+                MemFSEntry::Symlink(_) | MemFSEntry::Device(_) | MemFSEntry::Fifo(_) | MemFSEntry::Virtual(_) => {
+                    report.files += 1;
+                    Ok(Vec::new())
+                }
+                MemFSEntry::ResolvedAsRoot => Ok(Vec::new()),
+            });
+
+            // Children behind a poisoned lock are left to the drop of their directory.
+            if let Ok(Ok(children)) = children {
+                pending.extend(children);
+            }
+        }
+
+        report
+    }
+
+    fn notify(&self, kind: WatchEventKind, path: &str) {
+        self.track_change(kind, path);
+
+        if self.watchers.is_watched() {
+            self.watchers.publish(kind, &self.absolute_path(path));
+        }
+    }
+
+    /// Same as [MemFS::notify] for a rename, which watch handles get as a single move.
+    fn notify_move(&self, old_path: &str, new_path: &str) {
+        self.track_change(WatchEventKind::Delete, old_path);
+        self.track_change(WatchEventKind::Create, new_path);
+
+        if self.watchers.is_watched() {
+            self.watchers
+                .publish_move(&self.absolute_path(old_path), &self.absolute_path(new_path));
+        }
+    }
+
+    /// Same as [MemFS::notify] for a write through the descriptor, which stamps the file it was opened on.
+    fn notify_write(&self, fd: usize) {
+        if !self.watchers.is_watched() && self.changes.is_none() {
+            return;
+        }
+
+        let Some(path) = self.descriptor_path(fd) else {
+            return;
+        };
+
+        if let Some(seq) = self.track_change(WatchEventKind::Modify, &path)
+            && let Some(node) = self.descriptor_entry(fd)
+        {
+            self.stamp_change(&node, seq);
+        }
+
+        if self.watchers.is_watched() {
+            self.watchers.publish(WatchEventKind::Modify, &path);
+        }
+    }
+
+    /// Records a change if change tracking is enabled, and returns its sequence number.
+    /// A created node and the directory whose entries changed are stamped with it.
+    fn track_change(&self, kind: WatchEventKind, path: &str) -> Option<u64> {
+        let changes = self.changes.as_ref()?;
+        let path = self.absolute_path(path);
+        let seq = changes.record(kind, &path);
+
+        if kind == WatchEventKind::Create
+            && let Ok(node) = self.get_node_of_given_path(&path)
+        {
+            self.stamp_change(&node, seq);
+        }
+
+        if kind != WatchEventKind::Modify
+            && let Ok(parent) = self.get_parent_directory_node_of_given_path(&path)
+        {
+            self.stamp_change(&parent, seq);
+        }
+
+        Some(seq)
+    }
+
+    fn stamp_change(&self, node: &MemFSNode, seq: u64) {
+        let _ = with_entry(node, |entry| match entry {
+            MemFSEntry::Directory(dir) => {
+                dir.changed.fetch_max(seq, Ordering::AcqRel);
+            }
+            MemFSEntry::File(file) => {
+                file.changed.fetch_max(seq, Ordering::AcqRel);
+            }
+            MemFSEntry::Symlink(_) | MemFSEntry::Device(_) | MemFSEntry::Fifo(_) | MemFSEntry::Virtual(_) => {}
+            MemFSEntry::ResolvedAsRoot => self.stamp_change(&self.root, seq),
+        });
+    }
+
+    /// Lexically normalizes the path into an absolute one, resolving relative paths against the working directory.
+    fn absolute_path(&self, path: &str) -> String {
+        let cwd = if path.starts_with('/') {
+            String::new()
+        } else {
+            self.current_directory().path
+        };
+        let mut components: Vec<&str> = if path.starts_with('/') {
+            Vec::new()
+        } else {
+            cwd.split('/').filter(|c| !c.is_empty()).collect()
+        };
+
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    components.pop();
+                }
+                c => components.push(c),
+            }
+        }
+
+        format!("/{}", components.join("/"))
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn descriptor_path(&self, fd: usize) -> Option<String> {
+        let guard = self.file_descriptors.shard(fd).read().ok()?;
+        guard.get(&fd).map(|v| v.path.clone())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn descriptor_path(&self, fd: usize) -> Option<String> {
+        self.file_descriptors.shard(fd).get(&fd).map(|v| v.path.clone())
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn descriptor_path(&self, fd: usize) -> Option<String> {
+        self.file_descriptors.shard(fd).pin().get(&fd).map(|v| v.path.clone())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn descriptor_entry(&self, fd: usize) -> Option<MemFSNode> {
+        let guard = self.file_descriptors.shard(fd).read().ok()?;
+        guard.get(&fd).map(|v| v.entry.clone())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn descriptor_entry(&self, fd: usize) -> Option<MemFSNode> {
+        self.file_descriptors.shard(fd).get(&fd).map(|v| v.entry.clone())
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn descriptor_entry(&self, fd: usize) -> Option<MemFSNode> {
+        self.file_descriptors.shard(fd).pin().get(&fd).map(|v| v.entry.clone())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn with_descriptor<R>(&self, fd: usize, f: impl FnOnce(&MemFSFileDescriptor) -> Result<R>) -> Result<R> {
+        let fd_map = self
+            .file_descriptors
+            .shard(fd)
+            .read()
+            .map_err(|_| MemFSErr::poisoned_lock())?;
+
+        f(fd_map.get(&fd).ok_or(MemFSErr::bad_file_descriptor())?)
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn with_descriptor<R>(&self, fd: usize, f: impl FnOnce(&MemFSFileDescriptor) -> Result<R>) -> Result<R> {
+        let descriptor = self
+            .file_descriptors
+            .shard(fd)
+            .get(&fd)
+            .ok_or(MemFSErr::bad_file_descriptor())?;
+
+        f(&descriptor)
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn with_descriptor<R>(&self, fd: usize, f: impl FnOnce(&MemFSFileDescriptor) -> Result<R>) -> Result<R> {
+        f(self
+            .file_descriptors
+            .shard(fd)
+            .pin()
+            .get(&fd)
+            .ok_or(MemFSErr::bad_file_descriptor())?)
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn insert_descriptor(&self, fd: usize, descriptor: MemFSFileDescriptor) -> Result<()> {
+        self.file_descriptors
+            .shard(fd)
+            .write()
+            .map_err(|_| MemFSErr::poisoned_lock())?
+            .insert(fd, descriptor);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn insert_descriptor(&self, fd: usize, descriptor: MemFSFileDescriptor) -> Result<()> {
+        self.file_descriptors.shard(fd).insert(fd, descriptor);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn insert_descriptor(&self, fd: usize, descriptor: MemFSFileDescriptor) -> Result<()> {
+        self.file_descriptors.shard(fd).pin().insert(fd, descriptor);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn descriptor_flag(&self, fd: usize) -> Option<OpenFlag> {
+        let guard = self.file_descriptors.shard(fd).read().ok()?;
+        guard.get(&fd).map(|v| v.flag.clone())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn descriptor_flag(&self, fd: usize) -> Option<OpenFlag> {
+        self.file_descriptors.shard(fd).get(&fd).map(|v| v.flag.clone())
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn descriptor_flag(&self, fd: usize) -> Option<OpenFlag> {
+        self.file_descriptors.shard(fd).pin().get(&fd).map(|v| v.flag.clone())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn clear_file_descriptors(&self) {
+        for shard in self.file_descriptors.iter() {
+            if let Ok(mut guard) = shard.write() {
+                guard.values().for_each(MemFSFileDescriptor::release_all);
+                guard.clear();
+            }
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn clear_file_descriptors(&self) {
+        for shard in self.file_descriptors.iter() {
+            shard.iter().for_each(|descriptor| descriptor.release_all());
+            shard.clear();
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn clear_file_descriptors(&self) {
+        for shard in self.file_descriptors.iter() {
+            let descriptors = shard.pin();
+
+            descriptors
+                .values()
+                .for_each(MemFSFileDescriptor::release_all);
+            descriptors.clear();
+        }
+    }
+
+    /// Keys of the nodes which descriptors are open on.
+    #[cfg(feature = "coarse-grained")]
+    fn open_node_keys(&self) -> HashSet<usize> {
+        let mut keys = HashSet::new();
+
+        for shard in self.file_descriptors.iter() {
+            if let Ok(guard) = shard.read() {
+                keys.extend(guard.values().map(|descriptor| node_key(&descriptor.entry)));
+            }
+        }
+
+        keys
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn open_node_keys(&self) -> HashSet<usize> {
+        self.file_descriptors
+            .iter()
+            .flat_map(|shard| shard.iter().map(|descriptor| node_key(&descriptor.entry)).collect::<Vec<_>>())
+            .collect()
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn open_node_keys(&self) -> HashSet<usize> {
+        self.file_descriptors
+            .iter()
+            .flat_map(|shard| shard.pin().values().map(|descriptor| node_key(&descriptor.entry)).collect::<Vec<_>>())
+            .collect()
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn count_open_file_descriptors(&self) -> usize {
+        self.file_descriptors
+            .iter()
+            .map(|shard| shard.read().map(|guard| guard.len()).unwrap_or_default())
+            .sum()
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn count_open_file_descriptors(&self) -> usize {
+        self.file_descriptors.iter().map(DashMap::len).sum()
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn count_open_file_descriptors(&self) -> usize {
+        self.file_descriptors.iter().map(LockFreeHashMap::len).sum()
+    }
+
+    /// Returns the lowest file descriptor which is not in use, of the shard of the calling thread if
+    /// [MapTuning::descriptor_shards] is more than one. Fails with EMFILE at [MemFSBuilder::max_open_files].
+    fn allocate_file_descriptor(&self) -> Result<usize> {
+        self.descriptor_numbers.allocate().ok_or_else(MemFSErr::too_many_open_files)
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn resolve_dir_and_entry<'a>(
+        &'a self,
+        last_elem: &str,
+        parent_node: &'a MemFSEntry,
+    ) -> Result<(&'a MemFSDirNode, Entry<'a, Name, NodeArc<MemFSEntry>>)> {
+        match parent_node {
+            MemFSEntry::Directory(dir) => Ok((dir, dir.child_entry(last_elem))),
+            MemFSEntry::ResolvedAsRoot => match &*self.root {
+                MemFSEntry::Directory(rootdir) => Ok((rootdir, rootdir.child_entry(last_elem))),
+                _ => return Err(MemFSErr::no_such_file_or_directory()),
+            },
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::is_not_directory())
+            }
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn resolve_open_dir<'a>(&'a self, parent_node: &'a MemFSEntry) -> Result<&'a MemFSDirNode> {
+        match parent_node {
+            MemFSEntry::Directory(dir) => Ok(dir),
+            MemFSEntry::ResolvedAsRoot => match &*self.root {
+                MemFSEntry::Directory(rootdir) => Ok(rootdir),
+                _ => Err(MemFSErr::no_such_file_or_directory())
+            },
+            MemFSEntry::File(_)
+            | MemFSEntry::Symlink(_)
+            | MemFSEntry::Device(_)
+            | MemFSEntry::Fifo(_)
+            | MemFSEntry::Virtual(_) => {
+                Err(MemFSErr::is_not_directory())
+            }
+        }
+    }
+
+    /// Creates an empty file owned by the caller, whose memory goes back to the pool once the file is dropped.
+    /// Opens the directory at `path`, for [OpenFlag::O_DIRECTORY].
+    fn open_directory(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        if flag.intersects(OpenFlag::O_CREAT | OpenFlag::O_TMPFILE) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let node = self.get_node_of_given_path(path)?;
+        let node = match with_entry(&node, |entry| match entry {
+            MemFSEntry::Directory(_) => Ok(false),
+            MemFSEntry::ResolvedAsRoot => Ok(true),
+            _ => Err(MemFSErr::is_not_directory()),
+        })?? {
+            true => self.root.clone(),
+            false => node,
+        };
+
+        if !flag.contains(OpenFlag::O_RDONLY) || !flag.check_mode_exclusiveness() {
+            return Err(MemFSErr::is_directory());
+        }
+
+        self.check_access(&node, MAY_READ)?;
+
+        let fd = self.allocate_file_descriptor()?;
+        let descriptor = self.new_descriptor(fd, flag, node, self.absolute_path(path));
+        self.insert_descriptor(fd, descriptor)?;
+
+        Ok(fd)
+    }
+
+    /// Opens the device, FIFO or virtual file at `path`, or returns None if there is none. Opening a FIFO
+    /// may wait for its other end, as described at [MemFS::mkfifo], which it does before taking a descriptor
+    /// number. Opening a virtual file for reading generates its contents, with no lock held.
+    fn open_special(&self, path: &str, flag: OpenFlag) -> Option<Result<usize>> {
+        let node = self.get_node_of_given_path(path).ok()?;
+        let (special, pipe, virtual_file) = with_entry(&node, |entry| match entry {
+            MemFSEntry::Device(_) => (true, None, None),
+            MemFSEntry::Fifo(fifo) => (true, Some(fifo.pipe.clone()), None),
+            MemFSEntry::Virtual(node) => (true, None, Some(node.file.clone())),
+            _ => (false, None, None),
+        })
+        .ok()?;
+
+        if !special {
+            return None;
+        }
+
+        let reads = flag.intersects(OpenFlag::O_RDONLY | OpenFlag::O_RDWR);
+        let writes = flag.intersects(OpenFlag::O_WRONLY | OpenFlag::O_RDWR);
+        let nonblock = flag.contains(OpenFlag::O_NONBLOCK);
+        let pipe_end = match pipe
+            .map(|pipe| self.outside_gate(|| pipe.open(reads, writes, nonblock)))
+            .transpose()
+        {
+            Ok(end) => end,
+            Err(err) => return Some(Err(err)),
+        };
+        let contents = match virtual_file {
+            Some(file) if writes && !file.is_writable() => return Some(Err(MemFSErr::permission_denied())),
+            Some(file) if reads => Some(Arc::new(file.contents())),
+            _ => None,
+        };
+
+        Some(self.allocate_file_descriptor().and_then(|fd| {
+            let mut descriptor = self.new_descriptor(fd, flag & !OpenFlag::O_CREAT, node, self.absolute_path(path));
+            descriptor.pipe_end = pipe_end;
+            descriptor.pinned = contents;
+            self.insert_descriptor(fd, descriptor)?;
+
+            Ok(fd)
+        }))
+    }
+
+    /// Opens an unnamed file in the directory at `path`, for [OpenFlag::O_TMPFILE].
+    fn open_tmpfile(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        if !flag.check_mode_exclusiveness() || flag.contains(OpenFlag::O_RDONLY) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let dir = self.get_node_of_given_path(path)?;
+
+        if !with_entry(&dir, |entry| matches!(entry, MemFSEntry::Directory(_) | MemFSEntry::ResolvedAsRoot))? {
+            return Err(MemFSErr::is_not_directory());
+        }
+
+        self.check_access(&dir, MAY_WRITE | MAY_EXECUTE)?;
+
+        let file = self.new_file_node()?;
+        file.links.store(0, Ordering::Release);
+
+        let node = new_node(MemFSEntry::File(Box::new(file)));
+        self.register_inode(&node);
+
+        let fd = self.allocate_file_descriptor()?;
+        let descriptor = self.new_descriptor(fd, flag & !OpenFlag::O_CREAT, node, self.absolute_path(path));
+        self.insert_descriptor(fd, descriptor)?;
+
+        Ok(fd)
+    }
+
+    fn new_file_node(&self) -> Result<MemFSFileNode> {
+        let mut file = MemFSFileNode::with_attributes(self.new_attributes(DEFAULT_FILE_MODE)?);
+        file.pool = Some(self.file_memory.clone());
+        file.history = VersionHistory::new(self.version_policy);
+
+        #[cfg(feature = "compression")]
+        file.data.set_compression(self.compression, None)?;
+
+        Ok(file)
+    }
+}
+
+unsafe impl Sync for MemFSDirNode {}
+unsafe impl Send for MemFSDirNode {}
+
+#[cfg(feature = "coarse-grained")]
+type ChildMap = HashMap<Name, NodeArc<PolicyRwLock<MemFSEntry>>, HashState>;
+
+#[cfg(feature = "coarse-grained")]
+#[derive(Clone)]
+pub struct MemFSDirNode {
+    parent: Arc<RwLock<Option<NodeWeak<PolicyRwLock<MemFSEntry>>>>>,
+    children: Arc<PolicyRwLock<ChildMap>>,
+    maps: MapConfig,
+    /// Counters of the accesses of the children, while [MemFSBuilder::contention_stats] is enabled.
+    contention: Option<Arc<ContentionCounters>>,
+
+    /// Sequence number of the latest change of the entries, while change tracking is enabled.
+    changed: Arc<AtomicU64>,
+
+    attributes: Arc<NodeAttributes>,
+
+    /// Names of the entries keyed by a different form, unless names must match exactly. See [NameMatching].
+    spellings: Option<Arc<DashMap<String, String>>>,
+}
+
+#[cfg(feature = "fine-grained")]
+#[derive(Clone)]
+pub struct MemFSDirNode {
+    parent: Arc<RwLock<Option<NodeWeak<MemFSEntry>>>>,
+    children: Arc<DashMap<Name, NodeArc<MemFSEntry>, HashState>>,
+    maps: MapConfig,
+    /// Counters of the accesses of the children, while [MemFSBuilder::contention_stats] is enabled.
+    contention: Option<Arc<ContentionCounters>>,
+
+    /// Sequence number of the latest change of the entries, while change tracking is enabled.
+    changed: Arc<AtomicU64>,
+
+    attributes: Arc<NodeAttributes>,
+
+    /// Names of the entries keyed by a different form, unless names must match exactly. See [NameMatching].
+    spellings: Option<Arc<DashMap<String, String>>>,
+}
+
+#[cfg(feature = "lock-free")]
+#[derive(Clone)]
+pub struct MemFSDirNode {
+    parent: Arc<RwLock<Option<NodeWeak<MemFSEntry>>>>,
+    children: Arc<LockFreeHashMap<Name, NodeArc<MemFSEntry>, HashState>>,
+    maps: MapConfig,
+    /// Counters of the accesses of the children, while [MemFSBuilder::contention_stats] is enabled.
+    contention: Option<Arc<ContentionCounters>>,
+
+    /// Sequence number of the latest change of the entries, while change tracking is enabled.
+    changed: Arc<AtomicU64>,
+
+    attributes: Arc<NodeAttributes>,
+
+    /// Names of the entries keyed by a different form, unless names must match exactly. See [NameMatching].
+    spellings: Option<Arc<DashMap<String, String>>>,
+}
+
+impl MemFSDirNode {
+    pub fn new() -> Self {
+        Self::configured(LockPolicy::default(), MapConfig::default())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn configured(policy: LockPolicy, maps: MapConfig) -> Self {
+        Self {
+            parent: Arc::default(),
+            children: Arc::new(PolicyRwLock::with_policy(
+                maps.new_map(maps.tuning.directory_capacity),
+                policy,
+            )),
+            spellings: (!maps.names.is_exact()).then(Arc::default),
+            maps,
+            contention: None,
+            changed: Arc::default(),
+            attributes: Arc::new(NodeAttributes::new(ROOT_INO, DEFAULT_DIRECTORY_MODE & !DEFAULT_UMASK, Credentials::ROOT)),
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn configured(_policy: LockPolicy, maps: MapConfig) -> Self {
+        Self {
+            parent: Arc::default(),
+            children: Arc::new(maps.new_map(maps.tuning.directory_capacity)),
+            spellings: (!maps.names.is_exact()).then(Arc::default),
+            maps,
+            contention: None,
+            changed: Arc::default(),
+            attributes: Arc::new(NodeAttributes::new(ROOT_INO, DEFAULT_DIRECTORY_MODE & !DEFAULT_UMASK, Credentials::ROOT)),
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn configured(_policy: LockPolicy, maps: MapConfig) -> Self {
+        Self {
+            parent: Arc::default(),
+            children: Arc::new(maps.new_map(maps.tuning.directory_capacity)),
+            spellings: (!maps.names.is_exact()).then(Arc::default),
+            maps,
+            contention: None,
+            changed: Arc::default(),
+            attributes: Arc::new(NodeAttributes::new(ROOT_INO, DEFAULT_DIRECTORY_MODE & !DEFAULT_UMASK, Credentials::ROOT)),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn lock_policy(&self) -> LockPolicy {
+        self.children.policy()
+    }
+
+    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+    fn lock_policy(&self) -> LockPolicy {
+        LockPolicy::default()
+    }
+
+    /// Creates a subdirectory node with the same lock policy and map configuration, which counts
+    /// its accesses if this one does.
+    fn child_directory(&self, parent_ptr: &MemFSNode) -> Self {
+        Self {
+            parent: Arc::new(RwLock::new(Some(NodeArc::downgrade(parent_ptr)))),
+            contention: self.contention.as_ref().map(|_| Arc::default()),
+            ..Self::configured(self.lock_policy(), self.maps.clone())
+        }
+    }
+
+    fn with_attributes(self, attributes: NodeAttributes) -> Self {
+        Self {
+            attributes: Arc::new(attributes),
+            ..self
+        }
+    }
+
+    fn parent(&self) -> Option<MemFSWeakNode> {
+        self.parent
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Points `..` to the new parent of a moved directory.
+    fn set_parent(&self, parent: &MemFSNode) {
+        *self.parent.write().unwrap_or_else(PoisonError::into_inner) = Some(NodeArc::downgrade(parent));
+    }
+
+    /// Identifies the directory, to lock directories in a fixed order. Clones of the node share it.
+    #[cfg(feature = "coarse-grained")]
+    fn order_key(&self) -> usize {
+        Arc::as_ptr(&self.children) as *const () as usize
+    }
+
+    /// Key of the entry named `name` in the children, which every name matching it shares.
+    fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        self.maps.names.key(name)
+    }
+
+    /// Same as [MemFSDirNode::key], interned along with the names of every other directory.
+    fn interned_key(&self, name: &str) -> Name {
+        self.maps.interner.intern(&self.key(name))
+    }
+
+    /// Remembers `name` as the name of the entry at its key, to be listed as such.
+    fn spell(&self, name: &str) {
+        if let Some(spellings) = &self.spellings {
+            match self.key(name) {
+                Cow::Borrowed(_) => {
+                    spellings.remove(name);
+                }
+                Cow::Owned(key) => {
+                    spellings.insert(key, name.to_string());
+                }
+            }
+        }
+    }
+
+    /// Renames the entry at `name` to `new_name` if both names match, which only changes how it is listed.
+    fn respell(&self, name: &str, new_name: &str) {
+        if self.key(name) == self.key(new_name) {
+            self.spell(new_name);
+        }
+    }
+
+    /// Forgets the name of the entry at `key`, before the entry is removed so that it cannot forget the name
+    /// of an entry created again in between.
+    fn forget_spelling(&self, key: &str) {
+        if let Some(spellings) = &self.spellings {
+            spellings.remove(key);
+        }
+    }
+
+    fn forget_spellings(&self) {
+        if let Some(spellings) = &self.spellings {
+            spellings.clear();
+        }
+    }
+
+    /// Name of the entry at `key`.
+    fn spelling(&self, key: &str) -> String {
+        match &self.spellings {
+            Some(spellings) => spellings.get(key).map_or_else(|| key.to_string(), |name| name.clone()),
+            None => key.to_string(),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn read_children(&self) -> Result<PolicyReadGuard<'_, ChildMap>> {
+        if let Some(tracker) = &self.contention
+            && let Some(guard) = self.children.try_read().map_err(|_| MemFSErr::poisoned_lock())?
+        {
+            tracker.record_acquisition(false);
+            return Ok(guard);
+        }
+
+        let guard = self.children.read().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if let Some(tracker) = &self.contention {
+            tracker.record_acquisition(true);
+        }
+
+        Ok(guard)
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn write_children(&self) -> Result<PolicyWriteGuard<'_, ChildMap>> {
+        if let Some(tracker) = &self.contention
+            && let Some(guard) = self.children.try_write().map_err(|_| MemFSErr::poisoned_lock())?
+        {
+            tracker.record_acquisition(false);
+            return Ok(guard);
+        }
+
+        let guard = self.children.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if let Some(tracker) = &self.contention {
+            tracker.record_acquisition(true);
+        }
+
+        Ok(guard)
+    }
+
+    /// Pins the children for an access which does not need validation, such as an insertion or removal.
+    #[cfg(feature = "lock-free")]
+    fn pin_children(&self) -> HashMapRef<'_, Name, MemFSNode, HashState, LocalGuard<'_>> {
+        if let Some(tracker) = &self.contention {
+            tracker.record_acquisition(false);
+        }
+
+        self.children.pin()
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn get_child(&self, name: &str) -> Option<Ref<'_, Name, MemFSNode>> {
+        let name = &*self.key(name);
+        let Some(tracker) = &self.contention else {
+            return self.children.get(name);
+        };
+
+        let (child, contended) = match self.children.try_get(name) {
+            TryResult::Present(child) => (Some(child), false),
+            TryResult::Absent => (None, false),
+            TryResult::Locked => (self.children.get(name), true),
+        };
+
+        tracker.record_acquisition(contended);
+
+        child
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn child_entry(&self, name: &str) -> Entry<'_, Name, MemFSNode> {
+        let key = self.interned_key(name);
+        let Some(tracker) = &self.contention else {
+            return self.children.entry(key);
+        };
+
+        let (entry, contended) = match self.children.try_entry(key.clone()) {
+            Some(entry) => (entry, false),
+            None => (self.children.entry(key), true),
+        };
+
+        tracker.record_acquisition(contended);
+
+        entry
+    }
+
+    /// Returns the children at the moment of the call.
+    #[cfg(feature = "coarse-grained")]
+    fn list_children(&self) -> Result<Vec<(String, MemFSNode)>> {
+        let guard = self.read_children()?;
+
+        Ok(guard.iter().map(|(k, v)| (self.spelling(k), v.clone())).collect())
+    }
+
+    /// Returns the children at the moment of the call.
+    #[cfg(feature = "fine-grained")]
+    fn list_children(&self) -> Result<Vec<(String, MemFSNode)>> {
+        Ok(self
+            .children
+            .iter()
+            .map(|e| (self.spelling(e.key()), e.value().clone()))
+            .collect())
+    }
+
+    /// Returns the children at the moment of the call.
+    #[cfg(feature = "lock-free")]
+    fn list_children(&self) -> Result<Vec<(String, MemFSNode)>> {
+        Ok(self
+            .pin_children()
+            .iter()
+            .map(|(k, v)| (self.spelling(k), v.clone()))
+            .collect())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn child_count(&self) -> Result<usize> {
+        let guard = self.read_children()?;
+
+        Ok(guard.len())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn child_count(&self) -> Result<usize> {
+        Ok(self.children.len())
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn child_count(&self) -> Result<usize> {
+        Ok(self.children.len())
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn child(&self, name: &str) -> Result<Option<MemFSNode>> {
+        Ok(self.read_children()?.get(&*self.key(name)).cloned())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn child(&self, name: &str) -> Result<Option<MemFSNode>> {
+        Ok(self.get_child(name).map(|v| v.value().clone()))
+    }
+
+    /// Looks the child up holding only the epoch guard of the map. The lookup of the map is atomic, so
+    /// the child found was in the directory at some point of the call.
+    #[cfg(feature = "lock-free")]
+    fn child(&self, name: &str) -> Result<Option<MemFSNode>> {
+        Ok(self.pin_children().get(&*self.key(name)).cloned())
+    }
+
+    /// Looks up several children at once, holding the lock of the directory only once.
+    #[cfg(feature = "coarse-grained")]
+    fn lookup_children<'a>(&self, names: impl Iterator<Item = &'a str>) -> Result<Vec<Option<MemFSNode>>> {
+        let guard = self.read_children()?;
+
+        Ok(names.map(|name| guard.get(&*self.key(name)).cloned()).collect())
+    }
+
+    /// Looks up several children at once.
+    #[cfg(feature = "fine-grained")]
+    fn lookup_children<'a>(&self, names: impl Iterator<Item = &'a str>) -> Result<Vec<Option<MemFSNode>>> {
+        Ok(names
+            .map(|name| self.get_child(name).map(|v| v.value().clone()))
+            .collect())
+    }
+
+    /// Looks up several children at once, pinning the map only once.
+    #[cfg(feature = "lock-free")]
+    fn lookup_children<'a>(&self, names: impl Iterator<Item = &'a str>) -> Result<Vec<Option<MemFSNode>>> {
+        let children = self.pin_children();
+
+        Ok(names.map(|name| children.get(&*self.key(name)).cloned()).collect())
+    }
+
+    /// Adds a child node, failing if there is already an entry with the name.
+    #[cfg(feature = "coarse-grained")]
+    fn insert_child(&self, name: &str, node: MemFSNode) -> Result<()> {
+        let mut guard = self.write_children()?;
+
+        match guard.entry(self.interned_key(name)) {
+            std::collections::hash_map::Entry::Occupied(_) => Err(MemFSErr::already_exists()),
+            std::collections::hash_map::Entry::Vacant(v) => {
+                v.insert(node);
+                self.spell(name);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a child node, failing if there is already an entry with the name.
+    #[cfg(feature = "fine-grained")]
+    fn insert_child(&self, name: &str, node: MemFSNode) -> Result<()> {
+        match self.child_entry(name) {
+            Entry::Occupied(_) => Err(MemFSErr::already_exists()),
+            Entry::Vacant(v) => {
+                self.spell(name);
+                v.insert(node);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a child node, failing if there is already an entry with the name.
+    #[cfg(feature = "lock-free")]
+    fn insert_child(&self, name: &str, node: MemFSNode) -> Result<()> {
+        match self.pin_children().try_insert(self.interned_key(name), node) {
+            Ok(_) => {
+                self.spell(name);
+                Ok(())
+            }
+            Err(_) => Err(MemFSErr::already_exists()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    /// Inserts the file made by `new_file` unless the name exists, in which case `new_file` is not called.
+    fn create_new_file(
+        &self,
+        file_name: &str,
+        flag: OpenFlag,
+        new_file: impl FnOnce() -> Result<MemFSFileNode>,
+    ) -> Result<bool> {
+        let mut guard = self.write_children()?;
+
+        match guard.entry(self.interned_key(file_name)) {
+            std::collections::hash_map::Entry::Vacant(v) => {
+                v.insert(NodeArc::new(PolicyRwLock::with_policy(
+                    MemFSEntry::File(Box::new(new_file()?)),
+                    self.children.policy(),
+                )));
+                self.spell(file_name);
+
+                Ok(true)
+            }
+            std::collections::hash_map::Entry::Occupied(_) => {
+                if flag.contains(OpenFlag::O_EXCL) {
+                    return Err(MemFSErr::already_exists());
+                }
+
+                Ok(false)
+            }
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn create_new_directory(
+        &self,
+        dir_name: &str,
+        parent_ptr: NodeArc<PolicyRwLock<MemFSEntry>>,
+        attributes: NodeAttributes,
+    ) -> Result<MemFSNode> {
+        let mut guard = self.write_children()?;
+
+        match guard.entry(self.interned_key(dir_name)) {
+            std::collections::hash_map::Entry::Occupied(_) => Err(MemFSErr::already_exists()),
+            std::collections::hash_map::Entry::Vacant(v) => {
+                let node = NodeArc::new(PolicyRwLock::with_policy(
+                    MemFSEntry::Directory(self.child_directory(&parent_ptr).with_attributes(attributes)),
+                    self.children.policy(),
+                ));
+                v.insert(node.clone());
+                self.spell(dir_name);
+                Ok(node)
+            }
+        }
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn create_new_directory(
+        &self,
+        dir_name: &str,
+        parent_ptr: NodeArc<MemFSEntry>,
+        attributes: NodeAttributes,
+    ) -> Result<MemFSNode> {
+        // Fine-grained
+        match self.child_entry(dir_name) {
+            Entry::Occupied(_) => Err(MemFSErr::already_exists()),
+            Entry::Vacant(v) => {
+                let node = NodeArc::new(MemFSEntry::Directory(
+                    self.child_directory(&parent_ptr).with_attributes(attributes),
+                ));
+                self.spell(dir_name);
+                v.insert(node.clone());
+                Ok(node)
+            }
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn create_new_directory(
+        &self,
+        dir_name: &str,
+        parent_ptr: NodeArc<MemFSEntry>,
+        attributes: NodeAttributes,
+    ) -> Result<MemFSNode> {
+        match self.pin_children().try_insert_with(self.interned_key(dir_name), || {
+            NodeArc::new(MemFSEntry::Directory(self.child_directory(&parent_ptr).with_attributes(attributes)))
+        }) {
+            Ok(node) => {
+                self.spell(dir_name);
+                Ok(node.clone())
+            }
+            Err(_) => Err(MemFSErr::already_exists()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn remove_file(&self, file_name: &str) -> Result<MemFSNode> {
+        let file_name = &*self.key(file_name);
+        let mut guard = self.write_children()?;
+
+        if guard.contains_key(file_name) {
+            let entry = guard.get(file_name).unwrap();
+            let entry_guard = entry.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+            if let MemFSEntry::Directory(_) = *entry_guard {
+                return Err(MemFSErr::is_directory());
+            }
+        } else {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        self.forget_spelling(file_name);
+
+        Ok(guard.remove(file_name).unwrap())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn remove_file(&self, file_name: &str) -> Result<MemFSNode> {
+        match self.child_entry(file_name) {
+            Entry::Occupied(v) => {
+                let inner = v.get();
+
+                if !matches!(&**inner, MemFSEntry::Directory(_) | MemFSEntry::ResolvedAsRoot) {
+                    self.forget_spelling(v.key());
+                    Ok(v.remove())
+                } else {
+                    Err(MemFSErr::is_directory())
+                }
+            }
+            Entry::Vacant(_) => Err(MemFSErr::no_such_file_or_directory()),
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn remove_file(&self, file_name: &str) -> Result<MemFSNode> {
+        // lockfree
+        match self.pin_children().remove_if(&*self.key(file_name), |key, v| {
+            if !matches!(&**v, MemFSEntry::Directory(_) | MemFSEntry::ResolvedAsRoot) {
+                self.forget_spelling(key);
+                true
+            }
+            else {
+                false
+            }
+        }) {
+            Ok(v) => match v {
+                Some((_, node)) => {
+                    Ok(node.clone())
+                }
+                None => Err(MemFSErr::no_such_file_or_directory()),
+            },
+            Err(_) => Err(MemFSErr::is_directory()),
+        }
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    fn remove_directory(&self, dir_name: &str) -> Result<()> {
+        let dir_name = &*self.key(dir_name);
+        let mut guard = self.write_children()?;
+
+        if guard.contains_key(dir_name) {
+            let entry = guard.get(dir_name).unwrap();
+            let entry_guard = entry.write().map_err(|_| MemFSErr::poisoned_lock())?;
+
+            if let MemFSEntry::Directory(dir_node) = &*entry_guard {
+                let children_guard = dir_node.read_children()?;
+
+                if !children_guard.is_empty() {
+                    return Err(MemFSErr::is_not_empty());
+                }
+            } else {
+                return Err(MemFSErr::is_not_directory());
+            }
+        } else {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        self.forget_spelling(dir_name);
+        guard.remove_entry(dir_name);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    fn remove_directory(&self, dir_name: &str) -> Result<()> {
+        match self.child_entry(dir_name) {
+            Entry::Occupied(v) => {
+                let inner = v.get();
+
+                if let MemFSEntry::Directory(dir_node) = &**inner {
+                    if dir_node.children.is_empty() {
+                        self.forget_spelling(v.key());
+                        v.remove();
+                        Ok(())
+                    } else {
+                        Err(MemFSErr::is_not_empty())
+                    }
+                } else {
+                    Err(MemFSErr::is_not_directory())
+                }
+            }
+            Entry::Vacant(_) => Err(MemFSErr::no_such_file_or_directory()),
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    fn remove_directory(&self, dir_name: &str) -> Result<()> {
+        // lockfree
+        match self.pin_children().remove_if(&*self.key(dir_name), |key, v| {
+            if let MemFSEntry::Directory(dir_node) = &**v {
+                if dir_node.children.is_empty() {
+                    self.forget_spelling(key);
+                    true
+                }
+                else {
+                    false
+                }
+            }
+            else {
+                false
+            }
+        }) {
+            Ok(v) => match v {
+                Some(_) => {
+                    Ok(())
+                }
+                None => Err(MemFSErr::no_such_file_or_directory()),
+            },
+            Err(entry) => {
+                if let MemFSEntry::Directory(_) = &**(entry.1) {
+                    Err(MemFSErr::is_not_empty())
+                }
+                else {
+                    Err(MemFSErr::is_not_directory())
+                }
+            },
+        }
+    }
+
+    /// Removes a child directory with everything under it, and returns its node.
+    #[cfg(feature = "coarse-grained")]
+    fn detach_directory(&self, dir_name: &str) -> Result<MemFSNode> {
+        let dir_name = &*self.key(dir_name);
+        let mut guard = self.write_children()?;
+        let entry = guard
+            .get(dir_name)
+            .ok_or(MemFSErr::no_such_file_or_directory())?;
+        let entry_guard = entry.read().map_err(|_| MemFSErr::poisoned_lock())?;
+
+        if !matches!(&*entry_guard, MemFSEntry::Directory(_)) {
+            return Err(MemFSErr::is_not_directory());
+        }
+
+        drop(entry_guard);
+        self.forget_spelling(dir_name);
+
+        Ok(guard.remove(dir_name).unwrap())
+    }
+
+    /// Removes a child directory with everything under it, and returns its node.
+    #[cfg(feature = "fine-grained")]
+    fn detach_directory(&self, dir_name: &str) -> Result<MemFSNode> {
+        match self.child_entry(dir_name) {
+            Entry::Occupied(v) => {
+                if let MemFSEntry::Directory(_) = &**v.get() {
+                    self.forget_spelling(v.key());
+                    Ok(v.remove())
+                } else {
+                    Err(MemFSErr::is_not_directory())
+                }
+            }
+            Entry::Vacant(_) => Err(MemFSErr::no_such_file_or_directory()),
+        }
+    }
+
+    /// Removes a child directory with everything under it, and returns its node.
+    #[cfg(feature = "lock-free")]
+    fn detach_directory(&self, dir_name: &str) -> Result<MemFSNode> {
+        let children = self.pin_children();
+
+        let removed = children.remove_if(&*self.key(dir_name), |key, v| {
+            let is_dir = matches!(&**v, MemFSEntry::Directory(_));
+
+            if is_dir {
+                self.forget_spelling(key);
+            }
+
+            is_dir
+        });
+
+        match removed {
+            Ok(Some((_, node))) => {
+                Ok(node.clone())
+            }
+            Ok(None) => Err(MemFSErr::no_such_file_or_directory()),
+            Err(_) => Err(MemFSErr::is_not_directory()),
+        }
+    }
+
+    /// Moves the child `node` from `name` to `new_name` of `target`, and returns the entry it replaced.
+    /// Without `replace`, fails with EEXIST if `new_name` exists.
+    /// Both directories are locked for the move, so that it is atomic.
+    #[cfg(feature = "coarse-grained")]
+    fn move_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        is_dir: bool,
+        replace: bool,
+    ) -> Result<Option<MemFSNode>> {
+        let (name, new_key) = (&*self.key(name), target.key(new_name));
+        let is_child = |children: &ChildMap| children.get(name).is_some_and(|child| node_key(child) == node_key(node));
+
+        if self.order_key() == target.order_key() {
+            let mut guard = self.write_children()?;
+
+            if !is_child(&guard) {
+                return Err(MemFSErr::no_such_file_or_directory());
+            }
+
+            if let Some(existing) = guard.get(&*new_key) {
+                check_replacement(existing, is_dir, replace)?;
+            }
+
+            self.forget_spelling(name);
+            guard.remove(name);
+            self.spell(new_name);
+
+            return Ok(guard.insert(target.maps.interner.intern(&new_key), node.clone()));
+        }
+
+        // Directories are locked in a fixed order, so that two moves in opposite directions do not deadlock.
+        let (mut source, mut destination) = if self.order_key() < target.order_key() {
+            let source = self.write_children()?;
+            (source, target.write_children()?)
+        } else {
+            let destination = target.write_children()?;
+            (self.write_children()?, destination)
+        };
+
+        if !is_child(&source) {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        if let Some(existing) = destination.get(&*new_key) {
+            check_replacement(existing, is_dir, replace)?;
+        }
+
+        self.forget_spelling(name);
+        source.remove(name);
+        target.spell(new_name);
+
+        Ok(destination.insert(target.maps.interner.intern(&new_key), node.clone()))
+    }
+
+    /// Swaps the child `node` at `name` with the child `other` at `new_name` of `target`.
+    /// Both directories are locked for the swap, so that it is atomic and always complete.
+    #[cfg(feature = "coarse-grained")]
+    fn exchange_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        other: &MemFSNode,
+    ) -> Result<bool> {
+        // Entries swap their nodes and keep their names.
+        let (name, new_name) = (&*self.key(name), &*target.key(new_name));
+        let is_child = |children: &ChildMap, name: &str, node: &MemFSNode| {
+            children.get(name).is_some_and(|child| node_key(child) == node_key(node))
+        };
+
+        if self.order_key() == target.order_key() {
+            let mut guard = self.write_children()?;
+
+            if !is_child(&guard, name, node) || !is_child(&guard, new_name, other) {
+                return Err(MemFSErr::no_such_file_or_directory());
+            }
+
+            guard.insert(self.maps.interner.intern(name), other.clone());
+            guard.insert(target.maps.interner.intern(new_name), node.clone());
+
+            return Ok(true);
+        }
+
+        let (mut source, mut destination) = if self.order_key() < target.order_key() {
+            let source = self.write_children()?;
+            (source, target.write_children()?)
+        } else {
+            let destination = target.write_children()?;
+            (self.write_children()?, destination)
+        };
+
+        if !is_child(&source, name, node) || !is_child(&destination, new_name, other) {
+            return Err(MemFSErr::no_such_file_or_directory());
+        }
+
+        source.insert(self.maps.interner.intern(name), other.clone());
+        destination.insert(target.maps.interner.intern(new_name), node.clone());
+
+        Ok(true)
+    }
+
+    /// Moves the child `node` from `name` to `new_name` of `target`, and returns the entry it replaced.
+    /// Without `replace`, fails with EEXIST if `new_name` exists. Fails with ENOENT if `node` is not
+    /// at `name` anymore. The node is unlinked from the old name before it is linked under the new one,
+    /// so that a concurrent removal of the old name either fails the move or finds nothing to remove.
+    #[cfg(feature = "fine-grained")]
+    fn move_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        is_dir: bool,
+        replace: bool,
+    ) -> Result<Option<MemFSNode>> {
+        match self.child_entry(name) {
+            Entry::Occupied(v) if node_key(v.get()) == node_key(node) => {
+                self.forget_spelling(v.key());
+                v.remove();
+            }
+            _ => return Err(MemFSErr::no_such_file_or_directory()),
+        }
+
+        let linked = match target.child_entry(new_name) {
+            Entry::Occupied(mut v) => check_replacement(v.get(), is_dir, replace).map(|()| {
+                target.spell(new_name);
+                Some(v.insert(node.clone()))
+            }),
+            Entry::Vacant(v) => {
+                target.spell(new_name);
+                v.insert(node.clone());
+                Ok(None)
+            }
+        };
+
+        if linked.is_err() {
+            self.restore_child(name, node);
+        }
+
+        linked
+    }
+
+    /// Puts `node` back at `name`, after a move which unlinked it failed. An entry created at `name`
+    /// meanwhile gives way to it, as if it were removed right after it was created.
+    #[cfg(feature = "fine-grained")]
+    fn restore_child(&self, name: &str, node: &MemFSNode) {
+        self.spell(name);
+
+        let displaced = match self.child_entry(name) {
+            Entry::Occupied(mut v) => Some(v.insert(node.clone())),
+            Entry::Vacant(v) => {
+                v.insert(node.clone());
+                None
+            }
+        };
+
+        if let Some(displaced) = displaced {
+            drop_link(&displaced);
+        }
+    }
+
+    /// Swaps the child `node` at `name` with the child `other` at `new_name` of `target`. The node takes
+    /// the new name before `other` takes the old one, so that a concurrent lookup may find the node under
+    /// both names for a moment. Returns false if the old name was removed in between, leaving `other` out.
+    #[cfg(feature = "fine-grained")]
+    fn exchange_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        other: &MemFSNode,
+    ) -> Result<bool> {
+        match target.child_entry(new_name) {
+            Entry::Occupied(mut v) if node_key(v.get()) == node_key(other) => {
+                v.insert(node.clone());
+            }
+            _ => return Err(MemFSErr::no_such_file_or_directory()),
+        }
+
+        match self.child_entry(name) {
+            Entry::Occupied(mut v) if node_key(v.get()) == node_key(node) => {
+                v.insert(other.clone());
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Moves the child `node` from `name` to `new_name` of `target`, and returns the entry it replaced.
+    /// Without `replace`, fails with EEXIST if `new_name` exists. Fails with ENOENT if `node` is not
+    /// at `name` anymore. The node is unlinked from the old name before it is linked under the new one,
+    /// so that a concurrent removal of the old name either fails the move or finds nothing to remove.
+    #[cfg(feature = "lock-free")]
+    fn move_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        is_dir: bool,
+        replace: bool,
+    ) -> Result<Option<MemFSNode>> {
+        let source = self.pin_children();
+        let unlinked = source.remove_if(&*self.key(name), |key, child| {
+            let same = node_key(child) == node_key(node);
+
+            if same {
+                self.forget_spelling(key);
+            }
+
+            same
+        });
 
-        if iter.peek().is_none() {
-            return if Self::is_absolute_path(path) {
-                Ok(self.root.clone())
-            } else {
-                Ok(self.cwd_node.clone())
-            };
+        if !matches!(unlinked, Ok(Some(_))) {
+            return Err(MemFSErr::no_such_file_or_directory());
         }
 
-        let guard = if Self::is_absolute_path(path) {
-            // Absolute path
-            self.root.read().map_err(|_| MemFSErr::poisoned_lock())
-        } else {
+        let children = target.pin_children();
+        let linked = children.compute(target.interned_key(new_name), |existing| match existing {
+            Some((_, child)) => match check_replacement(child, is_dir, replace) {
+                Ok(()) => Operation::Insert(node.clone()),
+                Err(e) => Operation::Abort(e),
+            },
+            None => Operation::Insert(node.clone()),
+        });
 
-            // Relative path
-            self.cwd_node.read().map_err(|_| MemFSErr::poisoned_lock())
-        }?;
+        let replaced = match linked {
+            Compute::Aborted(e) => {
+                self.restore_child(name, node);
+                return Err(e);
+            }
+            Compute::Updated { old: (_, old), .. } => Some(old.clone()),
+            _ => None,
+        };
 
-        match &*guard {
-            MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
-        }
-    }
+        target.spell(new_name);
 
-    #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    fn get_parent_directory_node_of_given_path(&self, path: &str) -> Result<Arc<MemFSEntry>> {
-        if path.is_empty() {
-            return Err(MemFSErr::no_such_file_or_directory());
-        }
+        Ok(replaced)
+    }
 
-        let mut iter = self.path_str_to_iter_and_without_last_component(path)?;
+    /// Puts `node` back at `name`, after a move which unlinked it failed. An entry created at `name`
+    /// meanwhile gives way to it, as if it were removed right after it was created.
+    #[cfg(feature = "lock-free")]
+    fn restore_child(&self, name: &str, node: &MemFSNode) {
+        self.spell(name);
 
-        if iter.peek().is_none() {
-            return if Self::is_absolute_path(path) {
-                Ok(self.root.clone())
-            } else {
-                Ok(self.cwd_node.clone())
-            };
+        if let Some(displaced) = self.pin_children().insert(self.interned_key(name), node.clone()) {
+            drop_link(displaced);
         }
+    }
 
-        let starting_node = if Self::is_absolute_path(path) {
-            // Absolute path
-            self.root.clone()
-        } else {
-            // Relative path
-            self.cwd_node.clone()
+    /// Swaps the child `node` at `name` with the child `other` at `new_name` of `target`. The node takes
+    /// the new name before `other` takes the old one, so that a concurrent lookup may find the node under
+    /// both names for a moment. Returns false if the old name was removed in between, leaving `other` out.
+    #[cfg(feature = "lock-free")]
+    fn exchange_child(
+        &self,
+        name: &str,
+        target: &MemFSDirNode,
+        new_name: &str,
+        node: &MemFSNode,
+        other: &MemFSNode,
+    ) -> Result<bool> {
+        let swap = |children: HashMapRef<'_, Name, MemFSNode, HashState, LocalGuard<'_>>,
+                    name: &str,
+                    expected: &MemFSNode,
+                    replacement: &MemFSNode| {
+            let swapped = children.compute(self.maps.interner.intern(name), |existing| match existing {
+                Some((_, child)) if node_key(child) == node_key(expected) => Operation::Insert(replacement.clone()),
+                _ => Operation::Abort(()),
+            });
+
+            !matches!(swapped, Compute::Aborted(()))
         };
 
-        match &*starting_node {
-            MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-            MemFSEntry::File(_) => Err(MemFSErr::no_such_file_or_directory()),
-            MemFSEntry::ResolvedAsRoot => Ok(self.root.clone()),
+        // Entries swap their nodes and keep their names.
+        if !swap(target.pin_children(), &target.key(new_name), other, node) {
+            return Err(MemFSErr::no_such_file_or_directory());
         }
+
+        let complete = swap(self.pin_children(), &self.key(name), node, other);
+
+        Ok(complete)
     }
 
-    fn allocate_file_descriptor(&self) -> Result<usize> {
-        let fd = self.file_descriptor_count.fetch_add(1, Ordering::AcqRel);
-        Ok(fd)
+    /// Removes every child and returns them.
+    #[cfg(feature = "coarse-grained")]
+    fn drain_children(&self) -> Result<Vec<MemFSNode>> {
+        let mut guard = self.write_children()?;
+        self.forget_spellings();
+
+        Ok(guard.drain().map(|(_, v)| v).collect())
     }
 
+    /// Removes every child and returns them.
     #[cfg(feature = "fine-grained")]
-    fn resolve_dir_and_entry<'a>(
-        &'a self,
-        last_elem: &str,
-        parent_node: &'a MemFSEntry,
-    ) -> Result<Entry<'a, String, Arc<MemFSEntry>>> {
-        match parent_node {
-            MemFSEntry::Directory(dir) => Ok(dir.children.entry(last_elem.to_string())),
-            MemFSEntry::ResolvedAsRoot => match &*self.root {
-                MemFSEntry::Directory(rootdir) => Ok(rootdir.children.entry(last_elem.to_string())),
-                _ => return Err(MemFSErr::no_such_file_or_directory()),
-            },
-            MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-        }
+    fn drain_children(&self) -> Result<Vec<MemFSNode>> {
+        let mut drained = Vec::new();
+
+        self.children.retain(|_, v| {
+            drained.push(v.clone());
+            false
+        });
+        self.forget_spellings();
+
+        Ok(drained)
     }
 
+    /// Removes every child and returns them.
     #[cfg(feature = "lock-free")]
-    fn resolve_open_dir<'a>(&'a self, parent_node: &'a MemFSEntry) -> Result<HashMapRef<'a, String, Arc<MemFSEntry>, RandomState, LocalGuard<'a>>> {
-        match parent_node {
-            MemFSEntry::Directory(dir) => Ok(dir.children.pin()),
-            MemFSEntry::ResolvedAsRoot => match &*self.root {
-                MemFSEntry::Directory(rootdir) => Ok(rootdir.children.pin()),
-                _ => Err(MemFSErr::no_such_file_or_directory())
-            },
-            MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-        }
-    }
+    fn drain_children(&self) -> Result<Vec<MemFSNode>> {
+        let mut drained = Vec::new();
 
-    /// Allocates file memory.
-    /// The implementation is very bad, but it can handle tests.
-    fn allocate_file_memory(&self) -> Result<Vec<u8>> {
-        if let Some(block) = self.file_memory.pop() {
-            Ok(block)
-        } else {
-            Err(MemFSErr::out_of_memory())
-        }
+        self.pin_children().retain(|_, v| {
+            drained.push(v.clone());
+            false
+        });
+        self.forget_spellings();
+
+        Ok(drained)
     }
 }
 
-unsafe impl Sync for MemFSDirNode {}
-unsafe impl Send for MemFSDirNode {}
+unsafe impl Sync for MemFSFileNode {}
+unsafe impl Send for MemFSFileNode {}
 
-#[cfg(feature = "coarse-grained")]
-#[derive(Clone)]
-pub struct MemFSDirNode {
-    parent: Option<Weak<RwLock<MemFSEntry>>>,
-    children: Arc<RwLock<HashMap<String, Arc<RwLock<MemFSEntry>>>>>,
+pub struct MemFSFileNode {
+    size: AtomicUsize,
+    data: FileContents,
+
+    /// Number of writes in progress.
+    writers: AtomicU32,
+
+    /// Incremented on every finished write.
+    generation: AtomicU64,
+
+    /// Sequence number of the latest change, while change tracking is enabled.
+    changed: AtomicU64,
+
+    /// Stamp of the latest use of the file while eviction is enabled, or 0 if it was not used since it was created.
+    last_used: AtomicU64,
+
+    /// Accesses of the contents, counted while [MemFSBuilder::contention_stats] is enabled.
+    contention: ContentionCounters,
+
+    /// Latest version pinned by O_SNAPSHOT readers, shared until the file is written again.
+    pinned: Mutex<Option<(u64, Arc<Vec<u8>>)>>,
+
+    /// Number of directory entries linking the file. The contents go back to the memory pool once
+    /// the last link and the last descriptor of the file are gone.
+    links: AtomicUsize,
+
+    attributes: NodeAttributes,
+
+    /// Pool the pages of the contents are taken from, which gets them back when the file is dropped.
+    pool: Option<Arc<dyn BlockStore>>,
+
+    /// Contents shared with the file this one was cloned from by [MemFS::reflink]. The file takes
+    /// its own pages of the pool, and stops sharing them, once it is written.
+    shared_contents: Mutex<Option<Arc<Vec<u8>>>>,
+    has_shared_contents: AtomicBool,
+
+    /// Snapshots sharing the file which did not get its contents yet. The next write saves them first.
+    unsaved_snapshots: Mutex<Vec<Weak<SavedContents>>>,
+    has_unsaved_snapshots: AtomicBool,
+
+    /// Previous versions of the contents, as listed by [MemFS::versions].
+    history: VersionHistory,
+
+    locks: Arc<FileLocks>,
 }
 
-#[cfg(feature = "fine-grained")]
-#[derive(Clone)]
-pub struct MemFSDirNode {
-    parent: Option<Weak<MemFSEntry>>,
-    children: Arc<DashMap<String, Arc<MemFSEntry>>>,
+/// Locks of a file. Descriptors hold on to them while they wait, even if the file goes away.
+#[derive(Default)]
+struct FileLocks {
+    /// Advisory lock taken with [MemFS::flock].
+    flock: FileLock,
+
+    /// Byte-range locks taken with [MemFS::setlk].
+    ranges: RangeLocks,
+
+    /// Serializes appends through every descriptor of the file, so that each lands at its end in one piece.
+    append: Mutex<()>,
 }
 
-#[cfg(feature = "lock-free")]
-#[derive(Clone)]
-pub struct MemFSDirNode {
-    parent: Option<Weak<MemFSEntry>>,
-    children: Arc<LockFreeHashMap<String, Arc<MemFSEntry>>>,
+/// Marks a write of a file in progress. Publishes a new generation and stamps the modification time on drop.
+struct FileWriteGuard<'a> {
+    file: &'a MemFSFileNode,
 }
 
-impl MemFSDirNode {
-    #[cfg(feature = "coarse-grained")]
-    pub fn new() -> Self {
-        Self {
-            parent: None,
-            children: Arc::new(RwLock::new(HashMap::new()))
-        }
+impl Drop for FileWriteGuard<'_> {
+    fn drop(&mut self) {
+        self.file.attributes.times().touch_modify();
+        self.file.generation.fetch_add(1, Ordering::SeqCst);
+        self.file.writers.fetch_sub(1, Ordering::SeqCst);
     }
+}
 
-    #[cfg(feature = "fine-grained")]
-    pub fn new() -> Self {
-        Self {
-            parent: None,
-            children: Arc::new(DashMap::new()),
-        }
+impl Default for MemFSFileNode {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    #[cfg(feature = "lock-free")]
+impl MemFSFileNode {
+    /// Creates an empty file whose pages are not taken from a memory pool.
     pub fn new() -> Self {
-        Self {
-            parent: None,
-            children: Arc::new(LockFreeHashMap::new()),
-        }
+        Self::with_attributes(NodeAttributes::new(0, DEFAULT_FILE_MODE & !DEFAULT_UMASK, Credentials::ROOT))
     }
 
-    #[cfg(feature = "coarse-grained")]
-    pub fn with_parent(parent: Weak<RwLock<MemFSEntry>>) -> Self {
+    fn with_attributes(attributes: NodeAttributes) -> Self {
         Self {
-            parent: Some(parent),
-            children: Arc::new(RwLock::new(HashMap::new())),
+            size: AtomicUsize::new(0),
+            data: FileContents::new(),
+            writers: AtomicU32::new(0),
+            generation: AtomicU64::new(0),
+            changed: AtomicU64::new(0),
+            last_used: AtomicU64::new(0),
+            contention: ContentionCounters::default(),
+            pinned: Mutex::new(None),
+            links: AtomicUsize::new(1),
+            attributes,
+            pool: None,
+            shared_contents: Mutex::default(),
+            has_shared_contents: AtomicBool::new(false),
+            unsaved_snapshots: Mutex::default(),
+            has_unsaved_snapshots: AtomicBool::new(false),
+            history: VersionHistory::new(VersionPolicy::default()),
+            locks: Arc::default(),
         }
     }
 
-    #[cfg(feature = "fine-grained")]
-    pub fn with_parent(parent: Weak<MemFSEntry>) -> Self {
-        Self {
-            parent: Some(parent),
-            children: Arc::new(DashMap::new()),
-        }
+    /// Charges the quota for contents growing to `size` bytes, before they are written.
+    fn reserve_size(&self, size: usize) -> Result<()> {
+        self.attributes
+            .charge()
+            .map_or(Ok(()), |charge| charge.grow_data(size as u64))
     }
 
-    #[cfg(feature = "lock-free")]
-    pub fn with_parent(parent: Weak<MemFSEntry>) -> Self {
-        Self {
-            parent: Some(parent),
-            children: Arc::new(LockFreeHashMap::new()),
+    /// Writes `data` at `offset`, allocating the pages it lands on first, and grows the size to its end
+    /// once it is copied. The caller unshares the contents and marks the write in progress first.
+    fn write_at(&self, data: &[u8], offset: usize) -> Result<usize> {
+        let end = offset.saturating_add(data.len());
+
+        if end > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
         }
+
+        self.reserve_size(end)?;
+        self.data.write(data, offset, self.pool.as_deref())?;
+        self.size.fetch_max(end, Ordering::AcqRel);
+
+        Ok(end)
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn create_new_file(&self, file_name: &str, flag: OpenFlag, space: Vec<u8>) -> Result<()> {
-        let mut guard = self
-            .children
-            .write()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Writes `buffer` at the end of the file in one piece, and returns the new end.
+    /// The caller unshares the contents and marks the write in progress first.
+    fn append(&self, buffer: &[u8]) -> Result<usize> {
+        let _lock = self.locks.append.lock().unwrap_or_else(PoisonError::into_inner);
 
-        match guard.entry(file_name.to_string()) {
-            std::collections::hash_map::Entry::Vacant(v) => {
-                v.insert(Arc::new(RwLock::new(
-                    MemFSEntry::File(MemFSFileNode::new(space)),
-                )));
-            }
-            std::collections::hash_map::Entry::Occupied(_) => {
-                if flag.contains(OpenFlag::O_EXCL) {
-                    return Err(MemFSErr::already_exists());
-                }
-            }
-        }
+        self.write_at(buffer, self.size.load(Ordering::Acquire))
+    }
 
-        Ok(())
+    /// Saves the current attributes into `snapshot`, and makes the next write save the current contents
+    /// into it first.
+    fn share_with_snapshot(&self, snapshot: &Arc<SavedContents>) {
+        snapshot.save_attributes(self.snapshot_key(), self.attributes.clone());
+
+        let mut unsaved = self.unsaved_snapshots.lock().unwrap_or_else(PoisonError::into_inner);
+
+        unsaved.retain(|snapshot| snapshot.strong_count() > 0);
+        unsaved.push(Arc::downgrade(snapshot));
+        self.has_unsaved_snapshots.store(true, Ordering::SeqCst);
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn create_new_directory(&self, dir_name: &str, parent_ptr: Arc<RwLock<MemFSEntry>>) -> Result<()> {
-        let mut guard = self
-            .children
-            .write()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Saves the current contents into the snapshots sharing the file which did not get them yet.
+    /// Runs before every write, while no write is in progress since the snapshots were taken.
+    fn save_for_snapshots(&self) {
+        if !self.has_unsaved_snapshots.load(Ordering::SeqCst) {
+            return;
+        }
 
-        match guard.entry(dir_name.to_string()) {
-            std::collections::hash_map::Entry::Occupied(_) => Err(MemFSErr::already_exists()),
-            std::collections::hash_map::Entry::Vacant(v) => {
-                v.insert(Arc::new(RwLock::new(MemFSEntry::Directory(
-                    MemFSDirNode::with_parent(Arc::downgrade(&parent_ptr)),
-                ))));
-                Ok(())
+        let mut unsaved = self.unsaved_snapshots.lock().unwrap_or_else(PoisonError::into_inner);
+        let snapshots: Vec<_> = unsaved.drain(..).filter_map(|snapshot| snapshot.upgrade()).collect();
+
+        if !snapshots.is_empty() {
+            let contents = self.pin_version();
+
+            for snapshot in snapshots {
+                snapshot.save(self.snapshot_key(), contents.clone());
             }
         }
+
+        self.has_unsaved_snapshots.store(false, Ordering::SeqCst);
     }
 
-    #[cfg(feature = "fine-grained")]
-    fn create_new_directory(&self, dir_name: &str, parent_ptr: Arc<MemFSEntry>) -> Result<()> {
-        // Fine-grained
-        match self.children.entry(dir_name.to_string()) {
-            Entry::Occupied(_) => Err(MemFSErr::already_exists()),
-            Entry::Vacant(v) => {
-                v.insert(Arc::new(MemFSEntry::Directory(MemFSDirNode::with_parent(
-                    Arc::downgrade(&parent_ptr),
-                ))));
-                Ok(())
-            }
-        }
+    /// Returns the contents of the file as of the time `snapshot` was taken.
+    /// Unless they were saved already, no write started since, and none starts until this returns.
+    fn snapshot_version(&self, snapshot: &SavedContents) -> Arc<Vec<u8>> {
+        let _unsaved = self.unsaved_snapshots.lock().unwrap_or_else(PoisonError::into_inner);
+
+        snapshot
+            .get(self.snapshot_key())
+            .unwrap_or_else(|| self.pin_version())
     }
 
-    #[cfg(feature = "lock-free")]
-    fn create_new_directory(&self, dir_name: &str, parent_ptr: Arc<MemFSEntry>) -> Result<()> {
-        match self.children.pin().try_insert_with(dir_name.to_string(), || {
-            Arc::new(MemFSEntry::Directory(MemFSDirNode::with_parent(Arc::downgrade(&parent_ptr))))
-        }) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(MemFSErr::already_exists()),
-        }
+    /// Returns the attributes of the file as of the time `snapshot` was taken.
+    fn snapshot_attributes(&self, snapshot: &SavedContents) -> NodeAttributes {
+        snapshot
+            .attributes(self.snapshot_key())
+            .unwrap_or_else(|| self.attributes.clone())
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn remove_file(&self, file_name: &str) -> Result<()> {
-        let mut guard = self
-            .children
-            .write()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    fn snapshot_key(&self) -> usize {
+        self as *const Self as usize
+    }
 
-        if guard.contains_key(file_name) {
-            let entry = guard.get(file_name).unwrap();
-            let entry_guard = entry.write().map_err(|_| MemFSErr::poisoned_lock())?;
+    fn begin_write(&self) -> FileWriteGuard<'_> {
+        self.save_for_snapshots();
 
-            if let MemFSEntry::Directory(_) = *entry_guard {
-                return Err(MemFSErr::is_directory());
-            }
-        } else {
-            return Err(MemFSErr::no_such_file_or_directory());
+        if self.history.saves_on_write() {
+            self.save_version();
         }
 
-        guard.remove_entry(file_name);
-
-        Ok(())
+        self.writers.fetch_add(1, Ordering::SeqCst);
+        FileWriteGuard { file: self }
     }
 
-    #[cfg(feature = "fine-grained")]
-    fn remove_file(&self, file_name: &str) -> Result<()> {
-        match self.children.entry(file_name.to_string()) {
-            Entry::Occupied(v) => {
-                let inner = v.get();
+    /// Saves the current contents into the history of the file, and returns the number of the version.
+    fn save_version(&self) -> u64 {
+        let (generation, contents) = self.pinned_version();
 
-                if let MemFSEntry::File(_) = &**inner {
-                    v.remove();
-                    Ok(())
-                } else {
-                    Err(MemFSErr::is_directory())
-                }
-            }
-            Entry::Vacant(_) => Err(MemFSErr::no_such_file_or_directory()),
-        }
+        self.history.save(generation, contents)
     }
 
-    #[cfg(feature = "lock-free")]
-    fn remove_file(&self, file_name: &str) -> Result<()> {
-        // lockfree
-        match self.children.pin().remove_if(file_name, |_, v| {
-            if let MemFSEntry::File(_) = &**v {
-                true
-            }
-            else {
-                false
-            }
-        }) {
-            Ok(v) => match v {
-                Some(_) => Ok(()),
-                None => Err(MemFSErr::no_such_file_or_directory()),
-            },
-            Err(_) => Err(MemFSErr::is_directory()),
-        }
+    /// Contents pinned for an O_SNAPSHOT descriptor. Takes a copy of the whole file unless the latest one
+    /// is still current.
+    fn pin_version(&self) -> Arc<Vec<u8>> {
+        self.pinned_version().1
     }
 
-    #[cfg(feature = "coarse-grained")]
-    fn remove_directory(&self, dir_name: &str) -> Result<()> {
-        let mut guard = self
-            .children
-            .write()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Returns an immutable copy of the contents, which no write observed in part, along with its generation.
+    /// Writers never wait for it; instead, the copy is retried while a write is in progress.
+    fn pinned_version(&self) -> (u64, Arc<Vec<u8>>) {
+        let mut pinned = self.pinned.lock().unwrap_or_else(PoisonError::into_inner);
 
-        if guard.contains_key(dir_name) {
-            let entry = guard.get(dir_name).unwrap();
-            let entry_guard = entry.write().map_err(|_| MemFSErr::poisoned_lock())?;
+        loop {
+            let generation = self.generation.load(Ordering::SeqCst);
 
-            if let MemFSEntry::Directory(dir_node) = &*entry_guard {
-                let children_guard = dir_node
-                    .children
-                    .read()
-                    .map_err(|_| MemFSErr::poisoned_lock())?;
+            if self.writers.load(Ordering::SeqCst) == 0 {
+                if let Some((pinned_generation, version)) = &*pinned
+                    && *pinned_generation == generation
+                {
+                    return (generation, version.clone());
+                }
 
-                if !children_guard.is_empty() {
-                    return Err(MemFSErr::is_not_empty());
+                let version = Arc::new(self.contents());
+
+                if self.writers.load(Ordering::SeqCst) == 0
+                    && self.generation.load(Ordering::SeqCst) == generation
+                {
+                    *pinned = Some((generation, version.clone()));
+                    return (generation, version);
                 }
-            } else {
-                return Err(MemFSErr::is_not_directory());
             }
-        } else {
-            return Err(MemFSErr::no_such_file_or_directory());
+
+            yield_now();
         }
+    }
 
-        guard.remove_entry(dir_name);
+    fn contents(&self) -> Vec<u8> {
+        let mut contents = vec![0; self.size.load(Ordering::Acquire)];
+        let read = self.read_range(&mut contents, 0);
 
-        Ok(())
+        contents.truncate(read);
+        contents
     }
 
-    #[cfg(feature = "fine-grained")]
-    fn remove_directory(&self, dir_name: &str) -> Result<()> {
-        match self.children.entry(dir_name.to_string()) {
-            Entry::Occupied(v) => {
-                let inner = v.get();
+    /// Copies the contents of the file from `offset` up to its size into `buffer`, whether they are
+    /// shared or not, and returns the number of bytes copied.
+    fn read_range(&self, buffer: &mut [u8], offset: usize) -> usize {
+        if self.has_shared_contents.load(Ordering::Acquire) {
+            let shared = self
+                .shared_contents
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
 
-                if let MemFSEntry::Directory(dir_node) = &**inner {
-                    if dir_node.children.is_empty() {
-                        v.remove();
-                        Ok(())
-                    } else {
-                        Err(MemFSErr::is_not_empty())
-                    }
-                } else {
-                    Err(MemFSErr::is_not_directory())
-                }
+            if let Some(contents) = shared {
+                let size = self.size.load(Ordering::Acquire).min(contents.len());
+
+                return MemFSFileDescriptor::copy_range(&contents[..size], buffer, offset);
             }
-            Entry::Vacant(_) => Err(MemFSErr::no_such_file_or_directory()),
         }
+
+        let reading_length = offset
+            .saturating_add(buffer.len())
+            .min(self.size.load(Ordering::Acquire))
+            .saturating_sub(offset);
+
+        self.data.read(&mut buffer[..reading_length], offset);
+
+        reading_length
+    }
+
+    /// Copies as much of the contents from `offset` as fits into `buffer`, and returns the number of bytes copied.
+    #[cfg(not(feature = "lock-free"))]
+    fn read_at(&self, buffer: &mut [u8], offset: usize) -> usize {
+        self.read_range(buffer, offset)
     }
 
+    /// Copies as much of the contents from `offset` as fits into `buffer`, and returns the number of bytes copied.
+    ///
+    /// Reads are optimistic, as with a seqlock: the copy is retried if a write was in progress or finished
+    /// meanwhile, so that it never shows a write in part. Writers never wait for readers, which retry only
+    /// while the file is being written.
     #[cfg(feature = "lock-free")]
-    fn remove_directory(&self, dir_name: &str) -> Result<()> {
-        // lockfree
-        match self.children.pin().remove_if(dir_name, |_, v| {
-            if let MemFSEntry::Directory(dir_node) = &**v {
-                if dir_node.children.is_empty() {
-                    true
-                }
-                else {
-                    false
-                }
-            }
-            else {
-                false
-            }
-        }) {
-            Ok(v) => match v {
-                Some(_) => Ok(()),
-                None => Err(MemFSErr::no_such_file_or_directory()),
-            },
-            Err(entry) => {
-                if let MemFSEntry::Directory(_) = &**(entry.1) {
-                    Err(MemFSErr::is_not_empty())
-                }
-                else {
-                    Err(MemFSErr::is_not_directory())
+    fn read_at(&self, buffer: &mut [u8], offset: usize) -> usize {
+        loop {
+            let generation = self.generation.load(Ordering::SeqCst);
+
+            if self.writers.load(Ordering::SeqCst) == 0 {
+                let read = self.read_range(buffer, offset);
+
+                if self.writers.load(Ordering::SeqCst) == 0 && self.generation.load(Ordering::SeqCst) == generation {
+                    return read;
                 }
-            },
+            }
+
+            yield_now();
         }
     }
 
-    #[cfg(feature = "coarse-grained")]
-     fn search_entry_with_path(
-        &self,
-        mut iter: Peekable<impl Iterator<Item = String>>,
-    ) -> Result<Arc<RwLock<MemFSEntry>>> {
-        let current_elem = iter.next();
+    /// Makes a file created without pages share `contents`.
+    fn share_contents(&mut self, contents: Arc<Vec<u8>>) {
+        self.size = AtomicUsize::new(contents.len());
+        *self.shared_contents.get_mut().unwrap_or_else(PoisonError::into_inner) = Some(contents);
+        self.has_shared_contents.store(true, Ordering::Release);
+    }
 
-        let cv = current_elem.unwrap();
-        let current_path = cv.as_str();
+    /// Copies shared contents into pages of the pool before the file is written.
+    /// Readers find the pages filled in once the contents are no longer shared.
+    fn unshare(&self) -> Result<()> {
+        if !self.has_shared_contents.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let mut shared = self.shared_contents.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(contents) = shared.as_ref() else {
+            return Ok(());
+        };
 
-        let next_elem = iter.peek();
+        self.data.write(contents, 0, self.pool.as_deref())?;
+        *shared = None;
+        self.has_shared_contents.store(false, Ordering::Release);
 
-        let guard = self
-            .children
-            .read()
-            .map_err(|_| MemFSErr::poisoned_lock())?;
+        Ok(())
+    }
 
-        match next_elem {
-            Some(_) => match guard.get(current_path) {
-                Some(v) => {
-                    let inner_guard = v.read().map_err(|_| MemFSErr::poisoned_lock())?;
+    /// Replaces the contents of the file. Bytes past the new size are zeroed, and the pages holding only
+    /// such bytes go back to the pool. Shared contents are replaced as a whole, so that the file still takes no page of the pool.
+    fn restore(&self, contents: &[u8]) -> Result<()> {
+        let _write = self.begin_write();
 
-                    match &*inner_guard {
-                        MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-                        MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-                        _ => unreachable!(),
-                    }
-                }
-                None => {
-                    match current_path {
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    let inner_guard =
-                                        inner.read().map_err(|_| MemFSErr::poisoned_lock())?;
-
-                                    if let MemFSEntry::Directory(dir) = &*inner_guard {
-                                        dir.search_entry_with_path(iter)
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => self.search_entry_with_path(iter),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    }
-                }
+        if let Some(charge) = self.attributes.charge() {
+            charge.force_data(contents.len() as u64);
+        }
 
-            },
-            None => {
-                // Now at the end of path string. current_elem should be the one you looking for.
-                match guard.get(current_path) {
-                    Some(v) => Ok(v.clone()),
-                    None => match current_path {
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    let inner_guard =
-                                        inner.read().map_err(|_| MemFSErr::poisoned_lock())?;
-
-                                    if let MemFSEntry::Directory(_) = &*inner_guard {
-                                        Ok(inner.clone())
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => Ok(Arc::new(RwLock::new(MemFSEntry::ResolvedAsRoot))),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    },
-                }
-            }
+        if let Some(shared) = self
+            .shared_contents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            *shared = Arc::new(contents.to_vec());
+            self.size.store(contents.len(), Ordering::Release);
+            return Ok(());
         }
+
+        self.data.write(contents, 0, self.pool.as_deref())?;
+
+        let old_size = self.size.swap(contents.len(), Ordering::AcqRel);
+        self.data.discard(contents.len(), old_size, self.pool.as_deref())
     }
 
-    #[cfg(feature = "fine-grained")]
-    fn search_entry_with_path(
-        &self,
-        mut iter: Peekable<impl Iterator<Item = String>>,
-    ) -> Result<Arc<MemFSEntry>> {
-        let current_elem = iter.next();
+    /// Sets the size of the file. The bytes between the old and the new size are zeroed before a
+    /// larger size is published, and after a smaller one is, so that a concurrent reader finds zeroes
+    /// past the new size rather than dropped contents. Unless the file grows, every page past the new
+    /// size goes back to the pool, once no read can reach it.
+    fn truncate(&self, len: usize) -> Result<()> {
+        if len > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
+        }
 
-        let cv = current_elem.unwrap();
-        let current_path = cv.as_str();
+        self.unshare()?;
+        let _write = self.begin_write();
 
-        let next_elem = iter.peek();
+        if let Some(charge) = self.attributes.charge() {
+            charge.set_data(len as u64)?;
+        }
 
-        match next_elem {
-            Some(_) => match self.children.get(current_path) {
-                Some(v) => match &**v {
-                    MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-                    MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-                    _ => unreachable!(),
-                },
-                None => {
-                    match current_path {
-                        // "." => self.search_entry_with_path(iter),
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(dir) = &*inner {
-                                        dir.search_entry_with_path(iter)
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => self.search_entry_with_path(iter),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    }
-                }
-            },
-            None => {
-                // Now at the end of path string. current_elem should be the one you looking for.
-                match self.children.get(current_path) {
-                    Some(v) => Ok(v.clone()),
-                    None => match current_path {
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(_) = &*inner {
-                                        Ok(inner.clone())
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => Ok(Arc::new(MemFSEntry::ResolvedAsRoot)),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    },
-                }
-            }
+        let old_size = self.size.load(Ordering::Acquire);
+
+        if len > old_size {
+            self.data.zero(old_size, len, self.pool.as_deref())?;
+            self.size.store(len, Ordering::Release);
+        } else {
+            self.size.store(len, Ordering::Release);
+            self.data.discard(len, FILE_MAX_SIZE, self.pool.as_deref())?;
         }
 
-        #[cfg(feature = "lock-free")]
-        match next_elem {
-            Some(_) => match self.children.pin().get(current_path) {
-                Some(v) => match &**v {
-                    MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-                    MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-                    _ => unreachable!(),
-                },
-                None => {
-                    match current_path {
-                        // "." => self.search_entry_with_path(iter),
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(dir) = &*inner {
-                                        dir.search_entry_with_path(iter)
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => self.search_entry_with_path(iter),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    }
-                }
-            },
-            None => {
-                // Now at the end of path string. current_elem should be the one you looking for.
-                match self.children.pin().get(current_path) {
-                    Some(v) => Ok(v.clone()),
-                    None => match current_path {
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(_) = &*inner {
-                                        Ok(inner.clone())
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => Ok(Arc::new(MemFSEntry::ResolvedAsRoot)),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    },
-                }
-            }
+        Ok(())
+    }
+
+    /// Charges the quota for contents of `end` bytes and takes the pages of `start..end` from the pool, then
+    /// grows the file to `end` unless `keep_size` is set. Bytes past the size of a file are always zeroes, so
+    /// growing it needs no write of the contents. Returns whether the file grew.
+    fn allocate(&self, start: usize, end: usize, keep_size: bool) -> Result<bool> {
+        if end > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
+        }
+
+        self.reserve_size(end)?;
+        self.unshare()?;
+        self.data.allocate(start, end, self.pool.as_deref())?;
+
+        if keep_size || end <= self.size.load(Ordering::Acquire) {
+            return Ok(false);
         }
+
+        let _write = self.begin_write();
+
+        Ok(self.size.fetch_max(end, Ordering::AcqRel) < end)
     }
 
-    #[cfg(feature = "lock-free")]
-    fn search_entry_with_path(
-        &self,
-        mut iter: Peekable<impl Iterator<Item = String>>,
-    ) -> Result<Arc<MemFSEntry>> {
-        let current_elem = iter.next();
+    /// Returns the first offset of `offset..end` which holds data, or lies in a hole if `data` is false,
+    /// or `end` if there is none. Contents shared with another file are data as a whole.
+    fn find_extent(&self, offset: usize, end: usize, data: bool) -> usize {
+        if self.has_shared_contents.load(Ordering::Acquire) {
+            return contiguous_extent(offset, end, data);
+        }
 
-        let cv = current_elem.unwrap();
-        let current_path = cv.as_str();
+        self.data.find_extent(offset, end, data)
+    }
 
-        let next_elem = iter.peek();
+    /// Zeroes `start..end` of the contents, up to the size of the file, and gives the pages it covers as a whole
+    /// back to the pool. Returns whether any byte was zeroed.
+    fn punch_hole(&self, start: usize, end: usize) -> Result<bool> {
+        let end = end.min(self.size.load(Ordering::Acquire));
 
-        match next_elem {
-            Some(_) => match self.children.pin().get(current_path) {
-                Some(v) => match &**v {
-                    MemFSEntry::Directory(dir) => dir.search_entry_with_path(iter),
-                    MemFSEntry::File(_) => Err(MemFSErr::is_not_directory()),
-                    _ => unreachable!(),
-                },
-                None => {
-                    match current_path {
-                        // "." => self.search_entry_with_path(iter),
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(dir) = &*inner {
-                                        dir.search_entry_with_path(iter)
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => self.search_entry_with_path(iter),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    }
-                }
-            },
-            None => {
-                // Now at the end of path string. current_elem should be the one you looking for.
-                match self.children.pin().get(current_path) {
-                    Some(v) => Ok(v.clone()),
-                    None => match current_path {
-                        ".." => match &self.parent {
-                            Some(parent) => {
-                                if let Some(inner) = parent.upgrade() {
-                                    if let MemFSEntry::Directory(_) = &*inner {
-                                        Ok(inner.clone())
-                                    } else {
-                                        Err(MemFSErr::is_not_directory())
-                                    }
-                                } else {
-                                    Err(MemFSErr::no_such_file_or_directory())
-                                }
-                            }
-                            None => Ok(Arc::new(MemFSEntry::ResolvedAsRoot)),
-                        },
-                        _ => Err(MemFSErr::no_such_file_or_directory()),
-                    },
-                }
-            }
+        if start >= end {
+            return Ok(false);
         }
+
+        self.unshare()?;
+        let _write = self.begin_write();
+
+        self.data.discard(start, end, self.pool.as_deref())?;
+
+        Ok(true)
     }
 
-}
+    /// Replaces the pages of the file with shared contents from `table` equal to them, or makes them the
+    /// shared contents. Files written meanwhile keep their pages.
+    #[cfg(feature = "dedup")]
+    fn deduplicate(&self, table: &DedupTable) {
+        if self.has_shared_contents.load(Ordering::Acquire) || !self.data.is_paged() {
+            return;
+        }
 
-unsafe impl Sync for MemFSFileNode {}
-unsafe impl Send for MemFSFileNode {}
+        let (generation, contents) = self.pinned_version();
+        let contents = table.intern(contents);
+        let unchanged =
+            || self.writers.load(Ordering::SeqCst) == 0 && self.generation.load(Ordering::SeqCst) == generation;
 
-pub struct MemFSFileNode {
-    size: AtomicUsize,
-    data: UnsafeCell<Vec<u8>>,
-}
+        if self.data.share(contents.clone(), self.pool.as_deref(), unchanged) {
+            // The pinned copy would keep the contents in memory twice.
+            let mut pinned = self.pinned.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if let Some((pinned_generation, _)) = &*pinned
+                && *pinned_generation == generation
+            {
+                *pinned = Some((generation, contents));
+            }
+        }
+    }
+
+    /// Copies size, contents, permission bits and owner of the file, but not its previous versions.
+    /// The copy is not taken from the memory pool.
+    fn duplicate(&self) -> Self {
+        let shared = self
+            .shared_contents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let content = match shared {
+            Some(_) => FileContents::new(),
+            None => self.data.duplicate(),
+        };
 
-impl MemFSFileNode {
-    pub fn new(space: Vec<u8>) -> Self {
         Self {
-            size: AtomicUsize::new(0),
-            data: UnsafeCell::new(space),
+            size: AtomicUsize::new(self.size.load(Ordering::Acquire)),
+            data: content,
+            writers: AtomicU32::new(0),
+            generation: AtomicU64::new(0),
+            changed: AtomicU64::new(self.changed.load(Ordering::Acquire)),
+            last_used: AtomicU64::new(0),
+            contention: ContentionCounters::default(),
+            pinned: Mutex::new(None),
+            links: AtomicUsize::new(1),
+            attributes: self.attributes.clone(),
+            pool: None,
+            has_shared_contents: AtomicBool::new(shared.is_some()),
+            shared_contents: Mutex::new(shared),
+            unsaved_snapshots: Mutex::default(),
+            has_unsaved_snapshots: AtomicBool::new(false),
+            history: VersionHistory::new(self.history.policy()),
+            locks: Arc::default(),
+        }
+    }
+}
+
+impl Drop for MemFSFileNode {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            self.data.release(Some(&*pool));
         }
     }
 }
@@ -1369,17 +7373,61 @@ unsafe impl Send for MemFSEntry {}
 
 pub enum MemFSEntry {
     Directory(MemFSDirNode),
-    File(MemFSFileNode),
+
+    /// File, boxed as its inline contents make it much larger than the other entries.
+    File(Box<MemFSFileNode>),
+
+    /// Symbolic link holding its target path, which is resolved on lookup.
+    Symlink(MemFSSymlinkNode),
+
+    /// Character device created with [MemFS::mknod].
+    Device(MemFSDeviceNode),
+
+    /// FIFO created with [MemFS::mkfifo].
+    Fifo(MemFSFifoNode),
+
+    /// File whose contents come from callbacks, created with [MemFS::create_virtual_file].
+    Virtual(MemFSVirtualNode),
     ResolvedAsRoot,
 }
 
+pub struct MemFSSymlinkNode {
+    target: String,
+    ino: u64,
+    _charge: Option<Charge>,
+}
+
+pub struct MemFSDeviceNode {
+    device: Device,
+    attributes: Arc<NodeAttributes>,
+}
+
+pub struct MemFSFifoNode {
+    pipe: Arc<Pipe>,
+    attributes: Arc<NodeAttributes>,
+}
+
+pub struct MemFSVirtualNode {
+    file: VirtualFile,
+    attributes: Arc<NodeAttributes>,
+}
+
 #[cfg(feature = "coarse-grained")]
 struct MemFSFileDescriptor {
     _number: usize,
     flag: OpenFlag,
     file_offset: AtomicUsize,
-    entry: Arc<RwLock<MemFSEntry>>,
-    append_mutex: Arc<Mutex<()>>,
+    entry: NodeArc<PolicyRwLock<MemFSEntry>>,
+    path: String,
+
+    /// Contents pinned at open time with O_SNAPSHOT.
+    pinned: Option<Arc<Vec<u8>>>,
+
+    /// Holder of the locks taken with [MemFS::flock] through the descriptor.
+    lock_owner: u64,
+
+    /// End of the pipe held open when the descriptor is open on a FIFO.
+    pipe_end: Option<PipeEnd>,
 }
 
 #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
@@ -1387,258 +7435,439 @@ struct MemFSFileDescriptor {
     _number: usize,
     flag: OpenFlag,
     file_offset: AtomicUsize,
-    entry: Arc<MemFSEntry>,
-    append_mutex: Arc<Mutex<()>>,
+    entry: NodeArc<MemFSEntry>,
+    path: String,
+
+    /// Contents pinned at open time with O_SNAPSHOT.
+    pinned: Option<Arc<Vec<u8>>>,
+
+    /// Holder of the locks taken with [MemFS::flock] through the descriptor.
+    lock_owner: u64,
+
+    /// End of the pipe held open when the descriptor is open on a FIFO.
+    pipe_end: Option<PipeEnd>,
 }
 
 impl MemFSFileDescriptor {
     #[cfg(feature = "coarse-grained")]
-    pub fn new(number: usize, flag: OpenFlag, entry: Arc<RwLock<MemFSEntry>>) -> Self {
+    pub fn new(number: usize, flag: OpenFlag, entry: NodeArc<PolicyRwLock<MemFSEntry>>, path: String) -> Self {
         Self {
             _number: number,
+            pinned: Self::pin_if_requested(&flag, &entry),
             flag,
             file_offset: AtomicUsize::new(0),
             entry,
-            append_mutex: Arc::new(Mutex::new(())),
+            path,
+            lock_owner: next_owner(),
+            pipe_end: None,
         }
     }
 
     #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    pub fn new(number: usize, flag: OpenFlag, entry: Arc<MemFSEntry>) -> Self {
+    pub fn new(number: usize, flag: OpenFlag, entry: NodeArc<MemFSEntry>, path: String) -> Self {
         Self {
             _number: number,
+            pinned: Self::pin_if_requested(&flag, &entry),
             flag,
             file_offset: AtomicUsize::new(0),
             entry,
-            append_mutex: Arc::new(Mutex::new(())),
+            path,
+            lock_owner: next_owner(),
+            pipe_end: None,
         }
     }
 
-    #[cfg(feature = "coarse-grained")]
-    unsafe fn read_file(&self, buffer: &mut Vec<u8>, size: usize) -> Result<usize> {
+    /// Returns the locks of the file, or None if the descriptor is open on something else.
+    fn file_locks(&self) -> Option<Arc<FileLocks>> {
+        with_entry(&self.entry, |entry| match entry {
+            MemFSEntry::File(file) => Some(file.locks.clone()),
+            _ => None,
+        })
+        .ok()
+        .flatten()
+    }
+
+    /// Drops the lock taken through the descriptor, if any, and its end of a FIFO, as it is being closed.
+    /// The lock-free backend drops removed descriptors late, so this cannot be left to [Drop].
+    fn release(&self) {
+        if let Some(locks) = self.file_locks() {
+            locks.flock.release(self.lock_owner);
+        }
+
+        if let Some(end) = &self.pipe_end {
+            end.close();
+        }
+    }
+
+    /// Drops the lock taken through the descriptor, every range lock of the file and the end of a FIFO,
+    /// as processes are gone after a crash.
+    fn release_all(&self) {
+        if let Some(locks) = self.file_locks() {
+            locks.flock.release(self.lock_owner);
+            locks.ranges.clear();
+        }
+
+        if let Some(end) = &self.pipe_end {
+            end.close();
+        }
+    }
+
+    fn pin_if_requested(flag: &OpenFlag, entry: &MemFSNode) -> Option<Arc<Vec<u8>>> {
+        if !flag.contains(OpenFlag::O_SNAPSHOT) {
+            return None;
+        }
+
+        with_entry(entry, |entry| match entry {
+            MemFSEntry::File(file) => Some(file.pin_version()),
+            _ => None,
+        })
+        .ok()
+        .flatten()
+    }
+
+    fn read_pinned(&self, version: &[u8], buffer: &mut [u8]) -> usize {
+        let reading_length = Self::copy_range(version, buffer, self.file_offset.load(Ordering::Acquire));
+        self.file_offset.fetch_add(reading_length, Ordering::AcqRel);
+
+        reading_length
+    }
+
+    /// Copies as much of `contents` from `offset` as fits into `buffer`.
+    fn copy_range(contents: &[u8], buffer: &mut [u8], offset: usize) -> usize {
+        let reading_length = offset
+            .saturating_add(buffer.len())
+            .min(contents.len())
+            .saturating_sub(offset);
+
+        if reading_length == 0 {
+            return 0;
+        }
+
+        buffer[..reading_length].copy_from_slice(&contents[offset..offset + reading_length]);
+
+        reading_length
+    }
+
+    fn read_file_at(&self, buffer: &mut [u8], offset: usize) -> Result<usize> {
         if self.flag.contains(OpenFlag::O_WRONLY) {
             return Err(MemFSErr::bad_file_descriptor());
         }
 
-        let fg = self.entry.read().map_err(|_| MemFSErr::poisoned_lock())?;
-        if let MemFSEntry::File(file) = &*fg {
-            let file_guard = file.data.get();
-            let current_offset = self.file_offset.load(Ordering::Acquire);
-            let file_size = file.size.load(Ordering::Relaxed);
+        if let Some(version) = &self.pinned {
+            return Ok(Self::copy_range(version, buffer, offset));
+        }
+
+        with_entry(&self.entry, |entry| match entry {
+            MemFSEntry::File(file) => {
+                file.attributes.times().touch_access();
+
+                Ok(file.read_at(buffer, offset))
+            }
+            MemFSEntry::Device(device) => Ok(device.device.read(buffer)),
+            // FIFOs have no offsets to read at.
+            MemFSEntry::Fifo(_) => Err(MemFSErr::invalid_value()),
+            _ => Err(MemFSErr::is_directory()),
+        })?
+    }
+
+    /// The data is copied before the size of the file grows, so that a concurrent reader never finds
+    /// the gap or the new range before it is written.
+    fn write_file_at(&self, data: &[u8], offset: usize) -> Result<usize> {
+        if self.flag.contains(OpenFlag::O_RDONLY) {
+            return Err(MemFSErr::bad_file_descriptor());
+        }
+
+        with_entry(&self.entry, |entry| match entry {
+            MemFSEntry::File(file) => {
+                let end = offset.saturating_add(data.len());
+
+                if end > FILE_MAX_SIZE {
+                    return Err(MemFSErr::file_too_large());
+                } else if data.is_empty() {
+                    return Ok(0);
+                }
+
+                file.unshare()?;
+                let _write = file.begin_write();
+                file.write_at(data, offset)?;
+
+                Ok(data.len())
+            }
+            MemFSEntry::Device(device) => Ok(device.device.write(data)),
+            MemFSEntry::Virtual(node) => node.file.write(data),
+            MemFSEntry::Fifo(_) => Err(MemFSErr::invalid_value()),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })?
+    }
+
+    /// Writes `data` at the end of the file in one piece, and moves the offset to the new end.
+    /// A FIFO takes what it has room for, as with [MemFSFileDescriptor::write_file].
+    fn append_file(&self, data: &[u8]) -> Result<usize> {
+        if self.flag.contains(OpenFlag::O_RDONLY) {
+            return Err(MemFSErr::bad_file_descriptor());
+        }
+
+        if let Some(end) = &self.pipe_end {
+            return end.write(data);
+        }
 
-            let content = unsafe { &*file_guard };
-            let reading_length = ((current_offset).saturating_add(size))
-                .min(file_size)
-                .saturating_sub(current_offset);
+        with_entry(&self.entry, |entry| match entry {
+            MemFSEntry::File(file) => {
+                file.unshare()?;
+                let _write = file.begin_write();
+                let end = file.append(data)?;
 
-            let slice_from_file =
-                content[current_offset..(current_offset).saturating_add(reading_length)].to_vec();
+                self.file_offset.store(end, Ordering::Release);
 
-            if buffer.len() < reading_length {
-                return Err(MemFSErr::bad_memory_access());
+                Ok(data.len())
             }
+            MemFSEntry::Device(device) => Ok(device.device.write(data)),
+            MemFSEntry::Virtual(node) => node.file.write(data),
+            _ => Err(MemFSErr::no_such_file_or_directory()),
+        })?
+    }
+
+    #[cfg(feature = "coarse-grained")]
+    unsafe fn read_file(&self, buffer: &mut [u8]) -> Result<usize> {
+        if self.flag.contains(OpenFlag::O_WRONLY) {
+            return Err(MemFSErr::bad_file_descriptor());
+        }
+
+        if let Some(end) = &self.pipe_end {
+            return end.read(buffer);
+        }
 
-            buffer[0..reading_length].copy_from_slice(&slice_from_file);
+        if let Some(version) = &self.pinned {
+            return Ok(self.read_pinned(version, buffer));
+        }
+
+        let fg = self.entry.read().map_err(|_| MemFSErr::poisoned_lock())?;
+        if let MemFSEntry::File(file) = &*fg {
+            let current_offset = self.file_offset.load(Ordering::Acquire);
+            let reading_length = file.read_at(buffer, current_offset);
 
             self.file_offset.fetch_add(reading_length, Ordering::AcqRel);
+            file.attributes.times().touch_access();
 
-            Ok(slice_from_file.len())
+            Ok(reading_length)
+        } else if let MemFSEntry::Device(device) = &*fg {
+            Ok(device.device.read(buffer))
         } else {
-            Err(MemFSErr::no_such_file_or_directory())
+            Err(MemFSErr::is_directory())
         }
     }
 
     #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    unsafe fn read_file(&self, buffer: &mut Vec<u8>, size: usize) -> Result<usize> {
+    unsafe fn read_file(&self, buffer: &mut [u8]) -> Result<usize> {
         if self.flag.contains(OpenFlag::O_WRONLY) {
             return Err(MemFSErr::bad_file_descriptor());
         }
 
-        if let MemFSEntry::File(file) = &*self.entry {
-            let file_guard = file.data.get();
-            let current_offset = self.file_offset.load(Ordering::Acquire);
-            let file_size = file.size.load(Ordering::Relaxed);
-
-            let content = unsafe { &*file_guard };
-            let reading_length = ((current_offset).saturating_add(size))
-                .min(file_size)
-                .saturating_sub(current_offset);
-
-            let slice_from_file =
-                content[current_offset..(current_offset).saturating_add(reading_length)].to_vec();
+        if let Some(end) = &self.pipe_end {
+            return end.read(buffer);
+        }
 
-            if buffer.len() < reading_length {
-                return Err(MemFSErr::bad_memory_access());
-            }
+        if let Some(version) = &self.pinned {
+            return Ok(self.read_pinned(version, buffer));
+        }
 
-            buffer[0..reading_length].copy_from_slice(&slice_from_file);
+        if let MemFSEntry::File(file) = &*self.entry {
+            let current_offset = self.file_offset.load(Ordering::Acquire);
+            let reading_length = file.read_at(buffer, current_offset);
 
             self.file_offset.fetch_add(reading_length, Ordering::AcqRel);
+            file.attributes.times().touch_access();
 
-            Ok(slice_from_file.len())
+            Ok(reading_length)
+        } else if let MemFSEntry::Device(device) = &*self.entry {
+            Ok(device.device.read(buffer))
         } else {
-            Err(MemFSErr::no_such_file_or_directory())
+            Err(MemFSErr::is_directory())
         }
     }
 
     #[cfg(feature = "coarse-grained")]
-    unsafe fn write_file(&self, buffer: &Vec<u8>, size: usize) -> Result<usize> {
+    unsafe fn write_file(&self, buffer: &[u8]) -> Result<usize> {
         if self.flag.contains(OpenFlag::O_RDONLY) {
             return Err(MemFSErr::bad_file_descriptor());
         }
 
+        if let Some(end) = &self.pipe_end {
+            return end.write(buffer);
+        }
+
         let fg = self.entry.read().map_err(|_| MemFSErr::poisoned_lock())?;
         if let MemFSEntry::File(file) = &*fg {
-            let file_guard = file.data.get();
-            let file_content = unsafe { &mut *file_guard };
+            file.unshare()?;
+            let _write = file.begin_write();
 
             if self.flag.contains(OpenFlag::O_APPEND) {
-                let _lock = self
-                    .append_mutex
-                    .lock()
-                    .map_err(|_| MemFSErr::poisoned_lock());
-
-                let current_offset = file.size.load(Ordering::Acquire);
-
-                let writing_content_size = size.min(buffer.len());
-                let expected_offset = current_offset.saturating_add(writing_content_size);
-
-                if expected_offset > FILE_MAX_SIZE {
-                    return Err(MemFSErr::file_too_large());
-                }
-
-                // self.file_offset.store(current_offset, Ordering::Release);
-
-                file.size.store(expected_offset, Ordering::Release);
-
-                file_content[current_offset..expected_offset]
-                    .copy_from_slice(&buffer[0..writing_content_size]);
+                let expected_offset = file.append(buffer)?;
 
                 self.file_offset.store(expected_offset, Ordering::Release);
 
-                Ok(writing_content_size)
+                Ok(buffer.len())
             } else {
                 let current_offset = self.file_offset.load(Ordering::Acquire);
-                let writing_content_size = size.min(buffer.len());
-                let expected_offset = current_offset.saturating_add(writing_content_size);
-
-                if expected_offset > FILE_MAX_SIZE {
-                    return Err(MemFSErr::file_too_large());
-                }
-
-                file.size.fetch_max(expected_offset, Ordering::Relaxed);
-
-                file_content[current_offset..expected_offset]
-                    .copy_from_slice(&buffer[0..writing_content_size]);
+                let expected_offset = file.write_at(buffer, current_offset)?;
 
                 self.file_offset.store(expected_offset, Ordering::Release);
 
-                Ok(writing_content_size)
+                Ok(buffer.len())
             }
+        } else if let MemFSEntry::Device(device) = &*fg {
+            Ok(device.device.write(buffer))
+        } else if let MemFSEntry::Virtual(node) = &*fg {
+            node.file.write(buffer)
         } else {
             Err(MemFSErr::no_such_file_or_directory())
         }
     }
 
     #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    unsafe fn write_file(&self, buffer: &Vec<u8>, size: usize) -> Result<usize> {
+    unsafe fn write_file(&self, buffer: &[u8]) -> Result<usize> {
         if self.flag.contains(OpenFlag::O_RDONLY) {
             return Err(MemFSErr::bad_file_descriptor());
         }
 
+        if let Some(end) = &self.pipe_end {
+            return end.write(buffer);
+        }
+
         if let MemFSEntry::File(file) = &*self.entry {
-            let file_guard = file.data.get();
-            let file_content = unsafe { &mut *file_guard };
+            file.unshare()?;
+            let _write = file.begin_write();
 
             if self.flag.contains(OpenFlag::O_APPEND) {
-                let _lock = self
-                    .append_mutex
-                    .lock()
-                    .map_err(|_| MemFSErr::poisoned_lock());
-
-                let current_offset = file.size.load(Ordering::Acquire);
-
-                let writing_content_size = size.min(buffer.len());
-                let expected_offset = current_offset.saturating_add(writing_content_size);
-
-                if expected_offset > FILE_MAX_SIZE {
-                    return Err(MemFSErr::file_too_large());
-                }
-
-                // self.file_offset.store(current_offset, Ordering::Release);
-
-                file.size.store(expected_offset, Ordering::Release);
-
-                file_content[current_offset..expected_offset]
-                    .copy_from_slice(&buffer[0..writing_content_size]);
+                let expected_offset = file.append(buffer)?;
 
                 self.file_offset.store(expected_offset, Ordering::Release);
 
-                Ok(writing_content_size)
+                Ok(buffer.len())
             } else {
                 let current_offset = self.file_offset.load(Ordering::Acquire);
-                let writing_content_size = size.min(buffer.len());
-                let expected_offset = current_offset.saturating_add(writing_content_size);
-
-                if expected_offset > FILE_MAX_SIZE {
-                    return Err(MemFSErr::file_too_large());
-                }
-
-                file.size.fetch_max(expected_offset, Ordering::Relaxed);
-
-                file_content[current_offset..expected_offset]
-                    .copy_from_slice(&buffer[0..writing_content_size]);
+                let expected_offset = file.write_at(buffer, current_offset)?;
 
                 self.file_offset.store(expected_offset, Ordering::Release);
 
-                Ok(writing_content_size)
+                Ok(buffer.len())
             }
+        } else if let MemFSEntry::Device(device) = &*self.entry {
+            Ok(device.device.write(buffer))
+        } else if let MemFSEntry::Virtual(node) = &*self.entry {
+            node.file.write(buffer)
         } else {
             Err(MemFSErr::no_such_file_or_directory())
         }
     }
 
     #[cfg(feature = "coarse-grained")]
-    unsafe fn seek_file(&self, seek_position: usize, flag: SeekFlag) -> Result<usize> {
+    unsafe fn seek_file(&self, seek_position: i64, flag: SeekFlag) -> Result<usize> {
         let fg = self.entry.read().map_err(|_| MemFSErr::poisoned_lock())?;
         let current_offset = self.file_offset.load(Ordering::Acquire);
-        let maximum_offset = if let MemFSEntry::File(file) = &*fg {
-            file.size.load(Ordering::Acquire)
+        let final_offset = if let Some(version) = &self.pinned {
+            seek_offset(current_offset, version.len(), seek_position, flag, contiguous_extent)?
+        } else if let MemFSEntry::File(file) = &*fg {
+            let size = file.size.load(Ordering::Acquire);
+
+            seek_offset(current_offset, size, seek_position, flag, |offset, end, data| {
+                file.find_extent(offset, end, data)
+            })?
+        } else if let MemFSEntry::Device(_) | MemFSEntry::Virtual(_) = &*fg {
+            // Devices have no position, as on Linux, and virtual files open for writing only
+            // have no contents to seek in.
+            return Ok(0);
+        } else if let MemFSEntry::Fifo(_) = &*fg {
+            return Err(MemFSErr::invalid_value());
         } else {
             return Err(MemFSErr::no_such_file_or_directory());
         };
 
-        let additional_offset = match flag {
-            SeekFlag::SEEK_CUR => current_offset,
-            SeekFlag::SEEK_END => maximum_offset,
-            SeekFlag::SEEK_SET => 0,
-        };
-
-        let final_offset = maximum_offset.min(additional_offset.saturating_add(seek_position));
         self.file_offset.store(final_offset, Ordering::Release);
 
         Ok(final_offset)
     }
 
     #[cfg(any(feature = "fine-grained", feature = "lock-free"))]
-    unsafe fn seek_file(&self, seek_position: usize, flag: SeekFlag) -> Result<usize> {
+    unsafe fn seek_file(&self, seek_position: i64, flag: SeekFlag) -> Result<usize> {
         let current_offset = self.file_offset.load(Ordering::Acquire);
 
-        let maximum_offset = if let MemFSEntry::File(file) = &*self.entry {
-            file.size.load(Ordering::Acquire)
+        let final_offset = if let Some(version) = &self.pinned {
+            seek_offset(current_offset, version.len(), seek_position, flag, contiguous_extent)?
+        } else if let MemFSEntry::File(file) = &*self.entry {
+            let size = file.size.load(Ordering::Acquire);
+
+            seek_offset(current_offset, size, seek_position, flag, |offset, end, data| {
+                file.find_extent(offset, end, data)
+            })?
+        } else if let MemFSEntry::Device(_) | MemFSEntry::Virtual(_) = &*self.entry {
+            // Devices have no position, as on Linux, and virtual files open for writing only
+            // have no contents to seek in.
+            return Ok(0);
+        } else if let MemFSEntry::Fifo(_) = &*self.entry {
+            return Err(MemFSErr::invalid_value());
         } else {
             return Err(MemFSErr::is_directory());
         };
 
-        let additional_offset = match flag {
-            SeekFlag::SEEK_CUR => current_offset,
-            SeekFlag::SEEK_END => maximum_offset,
-            SeekFlag::SEEK_SET => 0,
-        };
-
-        let final_offset = maximum_offset.min(additional_offset.saturating_add(seek_position));
         self.file_offset.store(final_offset, Ordering::Release);
 
         Ok(final_offset)
     }
 }
+
+/// Returns the offset a seek from `current_offset` by `seek_position` lands on, in a file of `size` bytes.
+/// Fails with EINVAL if the offset would be negative.
+/// SEEK_DATA and SEEK_HOLE look for data or a hole with `find_extent`, given the offset to start from, the size
+/// and whether to look for data, which returns the size if there is none. The end of the file is a hole.
+fn seek_offset(
+    current_offset: usize,
+    size: usize,
+    seek_position: i64,
+    flag: SeekFlag,
+    find_extent: impl FnOnce(usize, usize, bool) -> usize,
+) -> Result<usize> {
+    let additional_offset = match flag {
+        SeekFlag::SEEK_DATA | SeekFlag::SEEK_HOLE => {
+            let data = flag == SeekFlag::SEEK_DATA;
+
+            return match usize::try_from(seek_position) {
+                Ok(position) if position < size => match find_extent(position, size, data) {
+                    found if found < size || !data => Ok(found.min(size)),
+                    _ => Err(MemFSErr::no_such_device_or_address()),
+                },
+                _ => Err(MemFSErr::no_such_device_or_address()),
+            };
+        }
+        SeekFlag::SEEK_CUR => current_offset,
+        SeekFlag::SEEK_END => size,
+        SeekFlag::SEEK_SET => 0,
+    };
+
+    match usize::try_from((additional_offset as i64).saturating_add(seek_position)) {
+        Ok(final_offset) => Ok(size.min(final_offset)),
+        Err(_) => Err(MemFSErr::invalid_value()),
+    }
+}
+
+/// Finds data or a hole in contents which are data as a whole, such as a saved version of a file.
+fn contiguous_extent(offset: usize, end: usize, data: bool) -> usize {
+    if data { offset } else { end }
+}
+
+/// Value returned by a system call, as seen by metrics and the operation log.
+trait SyscallOutput {
+    fn value(&self) -> Option<usize>;
+}
+
+impl SyscallOutput for () {
+    fn value(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl SyscallOutput for usize {
+    fn value(&self) -> Option<usize> {
+        Some(*self)
+    }
+}
@@ -0,0 +1,360 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::utils::{MemFSErr, MemFSErrType};
+
+/// Number of buckets of a [LatencyHistogram]. Bucket `i` holds latencies shorter than `2^i` microseconds
+/// and at least as long as the bound of the previous bucket. The last bucket holds every longer latency.
+pub const LATENCY_BUCKETS: usize = 22;
+
+/// System calls whose usage is recorded by MemFS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemFSOp {
+    Open,
+    Close,
+    Unlink,
+    Read,
+    Write,
+    Lseek,
+    Mkdir,
+    Rmdir,
+    Chdir,
+    Fsync,
+    Rename,
+    Truncate,
+    Ftruncate,
+    Pread,
+    Pwrite,
+    Link,
+    Symlink,
+    Chmod,
+    Fchmod,
+    Chown,
+    Fchown,
+    Utimens,
+    Fallocate,
+    CopyFileRange,
+    Reflink,
+    Append,
+    Linkat,
+    Renameat2,
+}
+
+impl MemFSOp {
+    pub const ALL: [MemFSOp; 28] = [
+        MemFSOp::Open,
+        MemFSOp::Close,
+        MemFSOp::Unlink,
+        MemFSOp::Read,
+        MemFSOp::Write,
+        MemFSOp::Lseek,
+        MemFSOp::Mkdir,
+        MemFSOp::Rmdir,
+        MemFSOp::Chdir,
+        MemFSOp::Fsync,
+        MemFSOp::Rename,
+        MemFSOp::Truncate,
+        MemFSOp::Ftruncate,
+        MemFSOp::Pread,
+        MemFSOp::Pwrite,
+        MemFSOp::Link,
+        MemFSOp::Symlink,
+        MemFSOp::Chmod,
+        MemFSOp::Fchmod,
+        MemFSOp::Chown,
+        MemFSOp::Fchown,
+        MemFSOp::Utimens,
+        MemFSOp::Fallocate,
+        MemFSOp::CopyFileRange,
+        MemFSOp::Reflink,
+        MemFSOp::Append,
+        MemFSOp::Linkat,
+        MemFSOp::Renameat2,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemFSOp::Open => "open",
+            MemFSOp::Close => "close",
+            MemFSOp::Unlink => "unlink",
+            MemFSOp::Read => "read",
+            MemFSOp::Write => "write",
+            MemFSOp::Lseek => "lseek",
+            MemFSOp::Mkdir => "mkdir",
+            MemFSOp::Rmdir => "rmdir",
+            MemFSOp::Chdir => "chdir",
+            MemFSOp::Fsync => "fsync",
+            MemFSOp::Rename => "rename",
+            MemFSOp::Truncate => "truncate",
+            MemFSOp::Ftruncate => "ftruncate",
+            MemFSOp::Pread => "pread",
+            MemFSOp::Pwrite => "pwrite",
+            MemFSOp::Link => "link",
+            MemFSOp::Symlink => "symlink",
+            MemFSOp::Chmod => "chmod",
+            MemFSOp::Fchmod => "fchmod",
+            MemFSOp::Chown => "chown",
+            MemFSOp::Fchown => "fchown",
+            MemFSOp::Utimens => "utimens",
+            MemFSOp::Fallocate => "fallocate",
+            MemFSOp::CopyFileRange => "copy_file_range",
+            MemFSOp::Reflink => "reflink",
+            MemFSOp::Append => "append",
+            MemFSOp::Linkat => "linkat",
+            MemFSOp::Renameat2 => "renameat2",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+fn error_index(err_type: &MemFSErrType) -> usize {
+    err_type.clone() as usize
+}
+
+fn latency_bucket(latency: Duration) -> usize {
+    ((u128::BITS - latency.as_micros().leading_zeros()) as usize).min(LATENCY_BUCKETS - 1)
+}
+
+fn bucket_bound(bucket: usize) -> Option<Duration> {
+    (bucket < LATENCY_BUCKETS - 1).then(|| Duration::from_micros(1 << bucket))
+}
+
+/// Counters updated by every system call of MemFS.
+/// Every counter is a relaxed atomic, so recording never blocks the caller.
+#[derive(Default)]
+pub(crate) struct MemFSMetricsRecorder {
+    calls: [AtomicU64; MemFSOp::ALL.len()],
+    errors: [AtomicU64; MemFSOp::ALL.len()],
+    errors_by_type: [AtomicU64; MemFSErrType::ALL.len()],
+    latencies: [[AtomicU64; LATENCY_BUCKETS]; MemFSOp::ALL.len()],
+    latency_nanos: [AtomicU64; MemFSOp::ALL.len()],
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl MemFSMetricsRecorder {
+    /// Records a finished system call, which took `latency`. `outcome` holds the value returned on success, if any.
+    pub fn record(&self, op: MemFSOp, outcome: std::result::Result<Option<usize>, &MemFSErr>, latency: Duration) {
+        self.calls[op.index()].fetch_add(1, Ordering::Relaxed);
+        self.latencies[op.index()][latency_bucket(latency)].fetch_add(1, Ordering::Relaxed);
+        self.latency_nanos[op.index()].fetch_add(latency.as_nanos() as u64, Ordering::Relaxed);
+
+        match (op, outcome) {
+            (_, Err(err)) => {
+                self.errors[op.index()].fetch_add(1, Ordering::Relaxed);
+                self.errors_by_type[error_index(&err.err_type)].fetch_add(1, Ordering::Relaxed);
+            }
+            (MemFSOp::Read | MemFSOp::Pread, Ok(Some(bytes))) => {
+                self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+            }
+            (MemFSOp::Write | MemFSOp::Pwrite | MemFSOp::Append, Ok(Some(bytes))) => {
+                self.bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn snapshot(&self) -> MemFSMetrics {
+        MemFSMetrics {
+            calls: self.calls.each_ref().map(|c| c.load(Ordering::Relaxed)),
+            errors: self.errors.each_ref().map(|c| c.load(Ordering::Relaxed)),
+            errors_by_type: self.errors_by_type.each_ref().map(|c| c.load(Ordering::Relaxed)),
+            latencies: MemFSOp::ALL.map(|op| LatencyHistogram {
+                buckets: self.latencies[op.index()].each_ref().map(|c| c.load(Ordering::Relaxed)),
+                sum: Duration::from_nanos(self.latency_nanos[op.index()].load(Ordering::Relaxed)),
+            }),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Point-in-time copy of the statistics of a MemFS instance.
+#[derive(Clone, Debug, Default)]
+pub struct MemFSMetrics {
+    calls: [u64; MemFSOp::ALL.len()],
+    errors: [u64; MemFSOp::ALL.len()],
+    errors_by_type: [u64; MemFSErrType::ALL.len()],
+    latencies: [LatencyHistogram; MemFSOp::ALL.len()],
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub open_file_descriptors: usize,
+    pub memory_blocks_total: usize,
+    pub memory_blocks_free: usize,
+    pub memory_bytes_reclaimed: u64,
+}
+
+impl MemFSMetrics {
+    /// Number of calls of the given system call, including failed ones.
+    pub fn calls(&self, op: MemFSOp) -> u64 {
+        self.calls[op.index()]
+    }
+
+    /// Number of calls of the given system call which returned an error.
+    pub fn errors(&self, op: MemFSOp) -> u64 {
+        self.errors[op.index()]
+    }
+
+    /// Number of calls of any system call which returned an error of the given type.
+    pub fn errors_of_type(&self, err_type: MemFSErrType) -> u64 {
+        self.errors_by_type[error_index(&err_type)]
+    }
+
+    /// Latencies of the calls of the given system call.
+    pub fn latency(&self, op: MemFSOp) -> &LatencyHistogram {
+        &self.latencies[op.index()]
+    }
+
+    /// Number of calls of every system call, including failed ones.
+    pub fn total_calls(&self) -> u64 {
+        self.calls.iter().sum()
+    }
+
+    /// Returns what was counted between `earlier` and these statistics, taken later on the same instance,
+    /// such as the calls made while a benchmark ran. Gauges, such as the open file descriptors, are kept as they are.
+    pub fn since(&self, earlier: &MemFSMetrics) -> MemFSMetrics {
+        let sub = |later: &[u64], earlier: &[u64], i: usize| later[i].saturating_sub(earlier[i]);
+
+        MemFSMetrics {
+            calls: std::array::from_fn(|i| sub(&self.calls, &earlier.calls, i)),
+            errors: std::array::from_fn(|i| sub(&self.errors, &earlier.errors, i)),
+            errors_by_type: std::array::from_fn(|i| sub(&self.errors_by_type, &earlier.errors_by_type, i)),
+            latencies: std::array::from_fn(|i| LatencyHistogram {
+                buckets: std::array::from_fn(|b| sub(&self.latencies[i].buckets, &earlier.latencies[i].buckets, b)),
+                sum: self.latencies[i].sum.saturating_sub(earlier.latencies[i].sum),
+            }),
+            bytes_read: self.bytes_read.saturating_sub(earlier.bytes_read),
+            bytes_written: self.bytes_written.saturating_sub(earlier.bytes_written),
+            memory_bytes_reclaimed: self.memory_bytes_reclaimed.saturating_sub(earlier.memory_bytes_reclaimed),
+            ..self.clone()
+        }
+    }
+
+    /// Encodes the statistics in Prometheus text exposition format.
+    #[cfg(feature = "prometheus")]
+    pub fn encode(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+
+        writeln!(out, "# HELP memfs_operations_total Number of system calls handled by MemFS.").unwrap();
+        writeln!(out, "# TYPE memfs_operations_total counter").unwrap();
+        for op in MemFSOp::ALL {
+            writeln!(out, "memfs_operations_total{{op=\"{}\"}} {}", op.name(), self.calls(op)).unwrap();
+        }
+
+        writeln!(out, "# HELP memfs_operation_errors_total Number of system calls which returned an error.").unwrap();
+        writeln!(out, "# TYPE memfs_operation_errors_total counter").unwrap();
+        for op in MemFSOp::ALL {
+            writeln!(out, "memfs_operation_errors_total{{op=\"{}\"}} {}", op.name(), self.errors(op)).unwrap();
+        }
+
+        writeln!(out, "# HELP memfs_errors_total Number of system calls which returned an error, by type.").unwrap();
+        writeln!(out, "# TYPE memfs_errors_total counter").unwrap();
+        for err_type in MemFSErrType::ALL {
+            writeln!(out, "memfs_errors_total{{type=\"{:?}\"}} {}", err_type, self.errors_of_type(err_type.clone()))
+                .unwrap();
+        }
+
+        writeln!(out, "# HELP memfs_operation_duration_seconds Latency of system calls.").unwrap();
+        writeln!(out, "# TYPE memfs_operation_duration_seconds histogram").unwrap();
+        for op in MemFSOp::ALL.into_iter().filter(|op| self.calls(*op) > 0) {
+            let (name, histogram) = (op.name(), self.latency(op));
+            let mut cumulative = 0;
+
+            for (bound, count) in histogram.buckets() {
+                cumulative += count;
+                let le = bound.map_or("+Inf".to_string(), |bound| bound.as_secs_f64().to_string());
+                writeln!(out, "memfs_operation_duration_seconds_bucket{{op=\"{name}\",le=\"{le}\"}} {cumulative}")
+                    .unwrap();
+            }
+
+            let sum = histogram.sum().as_secs_f64();
+            writeln!(out, "memfs_operation_duration_seconds_sum{{op=\"{name}\"}} {sum}").unwrap();
+            writeln!(out, "memfs_operation_duration_seconds_count{{op=\"{name}\"}} {}", histogram.count()).unwrap();
+        }
+
+        writeln!(out, "# HELP memfs_read_bytes_total Bytes returned by read calls.").unwrap();
+        writeln!(out, "# TYPE memfs_read_bytes_total counter").unwrap();
+        writeln!(out, "memfs_read_bytes_total {}", self.bytes_read).unwrap();
+
+        writeln!(out, "# HELP memfs_written_bytes_total Bytes stored by write calls.").unwrap();
+        writeln!(out, "# TYPE memfs_written_bytes_total counter").unwrap();
+        writeln!(out, "memfs_written_bytes_total {}", self.bytes_written).unwrap();
+
+        writeln!(out, "# HELP memfs_open_file_descriptors Number of currently opened file descriptors.").unwrap();
+        writeln!(out, "# TYPE memfs_open_file_descriptors gauge").unwrap();
+        writeln!(out, "memfs_open_file_descriptors {}", self.open_file_descriptors).unwrap();
+
+        writeln!(out, "# HELP memfs_memory_blocks Number of file memory blocks in the pool.").unwrap();
+        writeln!(out, "# TYPE memfs_memory_blocks gauge").unwrap();
+        writeln!(out, "memfs_memory_blocks{{state=\"free\"}} {}", self.memory_blocks_free).unwrap();
+        writeln!(
+            out,
+            "memfs_memory_blocks{{state=\"used\"}} {}",
+            self.memory_blocks_total - self.memory_blocks_free
+        )
+        .unwrap();
+
+        writeln!(out, "# HELP memfs_memory_reclaimed_bytes_total Bytes of idle file memory released by compaction.").unwrap();
+        writeln!(out, "# TYPE memfs_memory_reclaimed_bytes_total counter").unwrap();
+        writeln!(out, "memfs_memory_reclaimed_bytes_total {}", self.memory_bytes_reclaimed).unwrap();
+
+        out
+    }
+}
+
+/// Latencies of the calls of a system call, counted in buckets of exponentially growing bounds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS],
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Number of calls counted.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Sum of the latencies of every call counted.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Mean latency, if any call was counted.
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.count();
+
+        (count > 0).then(|| Duration::from_nanos((self.sum.as_nanos() / u128::from(count)) as u64))
+    }
+
+    /// Upper bound of every bucket along with the number of calls in it, in order of bounds.
+    /// The last bucket has no bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        self.buckets.iter().enumerate().map(|(bucket, count)| (bucket_bound(bucket), *count))
+    }
+
+    /// Upper bound of the bucket which the given fraction of the calls, such as `0.99`, fall in or below,
+    /// if any call was counted. The bound is [Duration::MAX] when the fraction reaches the last bucket.
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        let count = self.count();
+
+        if count == 0 {
+            return None;
+        }
+
+        let target = ((fraction.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+
+        self.buckets().find_map(|(bound, bucket_count)| {
+            cumulative += bucket_count;
+
+            (cumulative >= target).then(|| bound.unwrap_or(Duration::MAX))
+        })
+    }
+}
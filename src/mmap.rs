@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use crate::memfs::{MemFS, MemFSNode};
+use crate::utils::{MemFSErr, Result};
+
+/// Mode of a [MappedRegion] returned by [MemFS::mmap].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MappingMode {
+    /// Shared read-only mapping. Reads see the file as it is, including writes made after the mapping.
+    ReadOnly,
+
+    /// Private mapping, as with MAP_PRIVATE. Reads see the file until the region is first written;
+    /// writes go to a private copy of the region and never reach the file.
+    CopyOnWrite,
+}
+
+/// What a mapping reads from: the file itself, or the contents an O_SNAPSHOT descriptor pinned.
+pub(crate) enum MappedSource {
+    File(MemFSNode),
+    Pinned(Arc<Vec<u8>>),
+}
+
+/// View of a range of a file, returned by [MemFS::mmap].
+///
+/// The region keeps the file alive, so it stays readable after the descriptor is closed or the file
+/// is removed. Offsets given to its methods are relative to the start of the region.
+pub struct MappedRegion {
+    source: MappedSource,
+    offset: usize,
+    len: usize,
+    mode: MappingMode,
+
+    /// Copy of the region taken by the first write of a copy-on-write mapping.
+    private: Option<Vec<u8>>,
+}
+
+impl MappedRegion {
+    pub(crate) fn new(source: MappedSource, offset: usize, len: usize, mode: MappingMode) -> Self {
+        Self {
+            source,
+            offset,
+            len,
+            mode,
+            private: None,
+        }
+    }
+
+    /// Offset of the region in the file.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn mode(&self) -> MappingMode {
+        self.mode
+    }
+
+    /// Reads from `at` into `buffer`, and returns the number of bytes read. Reads stop at the end of the region,
+    /// and at the end of the file unless the region was written.
+    pub fn read(&self, at: usize, buffer: &mut [u8]) -> Result<usize> {
+        let length = buffer.len().min(self.len.saturating_sub(at));
+
+        if length == 0 {
+            return Ok(0);
+        }
+
+        let buffer = &mut buffer[..length];
+
+        match &self.private {
+            Some(private) => {
+                buffer.copy_from_slice(&private[at..at + length]);
+
+                Ok(length)
+            }
+            None => MemFS::read_mapped(&self.source, buffer, self.offset + at),
+        }
+    }
+
+    /// Returns the bytes of the region, as far as [MappedRegion::read] goes.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut contents = vec![0; self.len];
+        let read = self.read(0, &mut contents)?;
+
+        contents.truncate(read);
+
+        Ok(contents)
+    }
+
+    /// Writes `data` at `at` of a copy-on-write region. The first write copies the region aside,
+    /// with the bytes past the end of the file as zeroes. The file is never changed.
+    ///
+    /// Fails with EACCES on a read-only region, and EFAULT if `data` goes past the end of the region.
+    pub fn write(&mut self, at: usize, data: &[u8]) -> Result<()> {
+        if self.mode == MappingMode::ReadOnly {
+            return Err(MemFSErr::permission_denied());
+        }
+
+        let end = at
+            .checked_add(data.len())
+            .filter(|&end| end <= self.len)
+            .ok_or(MemFSErr::bad_memory_access())?;
+
+        let private = match self.private.take() {
+            Some(private) => private,
+            None => {
+                let mut private = vec![0; self.len];
+                self.read(0, &mut private)?;
+
+                private
+            }
+        };
+
+        self.private.insert(private)[at..end].copy_from_slice(data);
+
+        Ok(())
+    }
+}
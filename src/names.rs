@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+use crate::utils::{MemFSErr, Result};
+
+/// How names of directory entries are compared, set with [crate::memfs::MemFSBuilder::name_matching].
+///
+/// Names matching each other refer to the same entry: creating one while the other exists fails with EEXIST,
+/// and opening one opens the other. Entries keep the name they were created or last renamed with,
+/// which is the one listed by [crate::memfs::MemFS::readdir].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NameMatching {
+    /// Names differing only by case match, as on Windows and macOS. Names are compared by their lowercase,
+    /// as [str::to_lowercase] gives it, so that locale-dependent foldings such as the Turkish dotless i are not.
+    pub case_insensitive: bool,
+
+    /// Names which are canonically equivalent match, such as `é` written as one code point or as `e` followed
+    /// by a combining acute accent, as on macOS. Names are compared in Normalization Form C.
+    pub unicode_normalization: bool,
+}
+
+impl NameMatching {
+    /// Names match only if they are the same bytes, as on Linux. The default.
+    pub const EXACT: Self = Self {
+        case_insensitive: false,
+        unicode_normalization: false,
+    };
+
+    /// Names match regardless of case, as on Windows.
+    pub const CASE_INSENSITIVE: Self = Self {
+        case_insensitive: true,
+        unicode_normalization: false,
+    };
+
+    /// Names match regardless of case and Unicode normalization, as on macOS.
+    pub const CASE_INSENSITIVE_NORMALIZED: Self = Self {
+        case_insensitive: true,
+        unicode_normalization: true,
+    };
+
+    pub fn is_exact(&self) -> bool {
+        *self == Self::EXACT
+    }
+
+    /// Form of `name` which every name matching it shares, borrowed when `name` is in that form already.
+    pub fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut key = Cow::Borrowed(name);
+
+        if self.unicode_normalization && is_nfc_quick(key.chars()) != IsNormalized::Yes {
+            key = Cow::Owned(key.nfc().collect());
+        }
+
+        if self.case_insensitive && key.chars().any(|c| !c.to_lowercase().eq([c])) {
+            key = Cow::Owned(key.to_lowercase());
+
+            // Lowercase letters are not always composed the way their uppercase was.
+            if self.unicode_normalization && is_nfc_quick(key.chars()) != IsNormalized::Yes {
+                key = Cow::Owned(key.nfc().collect());
+            }
+        }
+
+        key
+    }
+}
+
+/// Name of a directory entry as the maps of directories hold it, shared through [NameInterner].
+pub(crate) type Name = Arc<str>;
+
+/// Fewest names interned between two times an interner drops those no entry holds anymore.
+const MIN_PRUNED_NAMES: usize = 1024;
+
+/// Names of directory entries of a file system, each allocated once however many directories hold it,
+/// so that names repeated across the tree, such as `index.html` or `mod.rs`, share their memory.
+///
+/// Names stay interned after the last entry holding them is gone, until as many names were interned
+/// since the interner last dropped such names as it kept then.
+#[derive(Clone, Default)]
+pub(crate) struct NameInterner {
+    interned: Arc<InternedNames>,
+}
+
+#[derive(Default)]
+struct InternedNames {
+    names: DashMap<Name, ()>,
+
+    /// Names interned since those no entry holds were last dropped.
+    added: AtomicUsize,
+
+    /// Names kept when those no entry holds were last dropped.
+    kept: AtomicUsize,
+}
+
+impl NameInterner {
+    /// Returns the interned copy of `name`, interning it first if no entry holds it.
+    pub fn intern(&self, name: &str) -> Name {
+        let interned = &*self.interned;
+
+        if let Some(name) = interned.names.get(name) {
+            return name.key().clone();
+        }
+
+        let added = interned.added.fetch_add(1, Ordering::Relaxed);
+
+        if added >= interned.kept.load(Ordering::Relaxed).max(MIN_PRUNED_NAMES) {
+            interned.names.retain(|name, _| Arc::strong_count(name) > 1);
+            interned.kept.store(interned.names.len(), Ordering::Relaxed);
+            interned.added.store(0, Ordering::Relaxed);
+        }
+
+        interned.names.entry(Name::from(name)).or_default().key().clone()
+    }
+}
+
+/// Limits and checks of the paths given to a file system, set with [crate::memfs::MemFSBuilder::name_limits].
+///
+/// Paths longer than [NameLimits::path_max] allows, or with a component longer than [NameLimits::name_max],
+/// fail with ENAMETOOLONG, and paths with a NUL byte, which no C string can hold, fail with EINVAL.
+/// Paths are [str], so they are valid UTF-8 in any case; [crate::memfs::MemFS::load_from_disk] fails with EINVAL
+/// on host names which are not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NameLimits {
+    /// Longest component, in bytes. 255 by default, as NAME_MAX of Linux.
+    pub name_max: usize,
+
+    /// Bytes of a path, which must be fewer than it to leave room for the terminating NUL byte, as PATH_MAX
+    /// of Linux counts it. 4096 by default.
+    pub path_max: usize,
+
+    /// Fails with EINVAL on paths with control characters, such as newlines, which break tools listing names
+    /// one per line. Off by default.
+    pub reject_control_characters: bool,
+}
+
+impl Default for NameLimits {
+    fn default() -> Self {
+        Self {
+            name_max: 255,
+            path_max: 4096,
+            reject_control_characters: false,
+        }
+    }
+}
+
+impl NameLimits {
+    /// No limit on lengths, and only the NUL byte rejected.
+    pub const UNLIMITED: Self = Self {
+        name_max: usize::MAX,
+        path_max: usize::MAX,
+        reject_control_characters: false,
+    };
+
+    /// Fails with ENAMETOOLONG or EINVAL if `path` breaks the limits.
+    pub fn check(&self, path: &str) -> Result<()> {
+        if path.len() >= self.path_max {
+            return Err(MemFSErr::name_too_long());
+        }
+
+        for name in path.split('/') {
+            if name.len() > self.name_max {
+                return Err(MemFSErr::name_too_long());
+            }
+
+            if name.contains('\0') || (self.reject_control_characters && name.contains(char::is_control)) {
+                return Err(MemFSErr::invalid_value());
+            }
+        }
+
+        Ok(())
+    }
+}
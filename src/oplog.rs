@@ -0,0 +1,108 @@
+use std::{
+    fmt::Write as _,
+    io::Write,
+    sync::{Mutex, mpsc::Sender},
+    time::Duration,
+};
+
+use crate::metrics::MemFSOp;
+use crate::utils::MemFSErr;
+
+/// Description of a single finished system call.
+pub struct OpRecord<'a> {
+    pub op: MemFSOp,
+    pub path: Option<&'a str>,
+    pub fd: Option<usize>,
+    pub bytes: Option<usize>,
+    pub error: Option<&'a MemFSErr>,
+    pub duration: Duration,
+}
+
+impl OpRecord<'_> {
+    /// Encodes the record as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let mut out = String::with_capacity(128);
+
+        write!(out, "{{\"op\":\"{}\",\"path\":", self.op.name()).unwrap();
+        match self.path {
+            Some(path) => write_json_string(&mut out, path),
+            None => out.push_str("null"),
+        }
+        write!(out, ",\"fd\":{},\"bytes\":{}", json_number(self.fd), json_number(self.bytes)).unwrap();
+        match self.error {
+            Some(e) => write!(out, ",\"result\":\"{:?}\"", e.err_type).unwrap(),
+            None => out.push_str(",\"result\":\"ok\""),
+        }
+        write!(out, ",\"duration_ns\":{}}}", self.duration.as_nanos()).unwrap();
+
+        out
+    }
+}
+
+enum OpLogSink {
+    Writer(Mutex<Box<dyn Write + Send>>),
+    Channel(Sender<String>),
+}
+
+/// Structured logger emitting one JSON object per system call.
+/// It is opt-in, see [crate::memfs::MemFS::set_op_logger].
+pub struct OpLogger {
+    sink: OpLogSink,
+}
+
+impl OpLogger {
+    /// Writes each record as a line to the given writer.
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            sink: OpLogSink::Writer(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Sends each record to the given channel. Records are dropped once the receiver is gone.
+    pub fn to_channel(sender: Sender<String>) -> Self {
+        Self {
+            sink: OpLogSink::Channel(sender),
+        }
+    }
+
+    pub(crate) fn log(&self, record: &OpRecord) {
+        let line = record.to_json();
+
+        // Logging must never make a system call fail, so sink errors are ignored.
+        match &self.sink {
+            OpLogSink::Writer(writer) => {
+                if let Ok(mut w) = writer.lock() {
+                    let _ = writeln!(w, "{line}");
+                }
+            }
+            OpLogSink::Channel(sender) => {
+                let _ = sender.send(line);
+            }
+        }
+    }
+}
+
+fn json_number(value: Option<usize>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "null".to_string(),
+    }
+}
+
+fn write_json_string(out: &mut String, value: &str) {
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
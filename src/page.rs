@@ -0,0 +1,787 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::ptr::{self, NonNull};
+use std::thread;
+
+#[cfg(feature = "dedup")]
+use std::sync::Arc;
+
+#[cfg(feature = "compression")]
+use crate::compression::{CompressedPages, CompressionStats};
+use crate::pool::BlockStore;
+use crate::utils::{FILE_MAX_SIZE, INLINE_FILE_SIZE, MemFSErr, PAGE_SIZE, Result};
+
+/// Unit of memory holding file contents, handed out by a [BlockStore].
+pub type Page = [u8; PAGE_SIZE];
+
+/// Pages of file contents along with their index in the contents.
+pub type IndexedPages = Vec<(usize, NonNull<Page>)>;
+
+/// Number of pages a page table points to.
+const PAGES_PER_TABLE: usize = 256;
+
+/// Number of page tables a file needs to reach [FILE_MAX_SIZE].
+const TABLES: usize = FILE_MAX_SIZE / PAGE_SIZE / PAGES_PER_TABLE;
+
+type PageTable = [AtomicPtr<Page>; PAGES_PER_TABLE];
+
+/// States of [FileContents]. Contents never go back to [INLINE] once they left it.
+const INLINE: u8 = 0;
+const MOVING: u8 = 1;
+const PAGED: u8 = 2;
+
+/// Contents being moved between pages and compressed or shared contents. Nothing reads or writes them meanwhile.
+#[cfg(any(feature = "compression", feature = "dedup"))]
+const SWITCHING: u8 = 3;
+#[cfg(feature = "compression")]
+const COMPRESSED: u8 = 4;
+#[cfg(feature = "dedup")]
+const DEDUPED: u8 = 5;
+
+/// Contents of a file, kept inline until they outgrow [INLINE_FILE_SIZE], then in pages which are allocated
+/// on the first write to them.
+///
+/// Pages are found through a two-level table, whose second level is allocated along with the first page
+/// it points to, so that an empty or tiny file takes no page and a small one takes a single page. A page
+/// which was never written reads as zeroes.
+///
+/// Pages past the end of a file which shrinks, and those a hole punched in it covers, are taken out of the
+/// contents. Every access is counted, so that they are only given back to the pool once no access can reach
+/// them; until then they are kept aside as retired. Contents which moved to pages never move back inline.
+///
+/// With the `compression` feature, the pages can be kept compressed instead, outside of the memory pool.
+/// With the `dedup` feature, they can be replaced by contents shared with other files, which are copied
+/// back into pages on the next write.
+pub(crate) struct FileContents {
+    inline: UnsafeCell<[u8; INLINE_FILE_SIZE]>,
+    state: AtomicU8,
+    tables: [AtomicPtr<PageTable>; TABLES],
+
+    /// Whether contents which outgrow the inline space are compressed.
+    #[cfg(feature = "compression")]
+    compress: AtomicBool,
+    #[cfg(feature = "compression")]
+    compressed: Mutex<CompressedPages>,
+
+    /// Contents shared with other files, set only while no access is in progress.
+    #[cfg(feature = "dedup")]
+    shared: UnsafeCell<Option<Arc<Vec<u8>>>>,
+
+    /// Number of reads and writes in progress.
+    accesses: AtomicUsize,
+
+    /// Pages taken out of the contents which an access in progress may still be reading or writing.
+    retired: Mutex<Vec<NonNull<Page>>>,
+    has_retired: AtomicBool,
+}
+
+/// Marks an access of [FileContents] in progress, during which the contents are not switched and the pages
+/// it finds are not given back.
+struct Access<'a> {
+    contents: &'a FileContents,
+}
+
+impl Drop for Access<'_> {
+    fn drop(&mut self) {
+        self.contents.accesses.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl FileContents {
+    pub fn new() -> Self {
+        Self {
+            inline: UnsafeCell::new([0; INLINE_FILE_SIZE]),
+            state: AtomicU8::new(INLINE),
+            tables: [const { AtomicPtr::new(ptr::null_mut()) }; TABLES],
+            #[cfg(feature = "compression")]
+            compress: AtomicBool::new(false),
+            #[cfg(feature = "compression")]
+            compressed: Mutex::default(),
+            #[cfg(feature = "dedup")]
+            shared: UnsafeCell::new(None),
+            accesses: AtomicUsize::new(0),
+            retired: Mutex::default(),
+            has_retired: AtomicBool::new(false),
+        }
+    }
+
+    /// Copies `data` into the contents at `offset`. Contents which would outgrow [INLINE_FILE_SIZE] move
+    /// to pages first. Every missing page of the range is allocated before anything is copied, from `pool`
+    /// if given, so that the write cannot fail halfway; pages allocated before a failure stay, as zeroes.
+    /// Pages discarded meanwhile are allocated again.
+    pub fn write(&self, data: &[u8], offset: usize, pool: Option<&dyn BlockStore>) -> Result<()> {
+        let end = offset.saturating_add(data.len());
+
+        if end > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
+        }
+
+        let access = self.writable_access(pool)?;
+
+        if self.state.load(Ordering::Acquire) == INLINE && end <= INLINE_FILE_SIZE {
+            let inline = unsafe { &mut *self.inline.get() };
+            inline[offset..end].copy_from_slice(data);
+
+            return Ok(());
+        }
+
+        self.move_out_of_inline(pool)?;
+
+        #[cfg(feature = "compression")]
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            self.lock_compressed().write(data, offset);
+
+            return Ok(());
+        }
+
+        loop {
+            self.allocate_pages(offset, end, pool)?;
+
+            if self.write_pages(data, offset) {
+                break;
+            }
+        }
+
+        drop(access);
+        self.reclaim(pool);
+
+        Ok(())
+    }
+
+    /// Takes every missing page of `start..end` from `pool` if given, so that writing the range later
+    /// needs no more memory. Contents which would outgrow [INLINE_FILE_SIZE] move to pages first, and
+    /// compressed ones take no page.
+    pub fn allocate(&self, start: usize, end: usize, pool: Option<&dyn BlockStore>) -> Result<()> {
+        if end > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
+        }
+
+        let _access = self.writable_access(pool)?;
+
+        if self.state.load(Ordering::Acquire) == INLINE && end <= INLINE_FILE_SIZE {
+            return Ok(());
+        }
+
+        self.move_out_of_inline(pool)?;
+
+        #[cfg(feature = "compression")]
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            return Ok(());
+        }
+
+        self.allocate_pages(start, end, pool)
+    }
+
+    /// Copies the inline contents into the first page, once, unless they are all zeroes. Writers racing with
+    /// the move wait for it to end, while readers keep reading the inline contents, which do not change, until
+    /// the pages are published.
+    fn move_out_of_inline(&self, pool: Option<&dyn BlockStore>) -> Result<()> {
+        if !self.is_inline() {
+            return Ok(());
+        }
+
+        let inline = unsafe { &*self.inline.get() };
+        let written = inline.iter().any(|byte| *byte != 0);
+
+        #[cfg(feature = "compression")]
+        if self.compress.load(Ordering::Acquire) {
+            if self
+                .state
+                .compare_exchange(INLINE, MOVING, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                self.lock_compressed().write(inline, 0);
+                self.state.store(COMPRESSED, Ordering::Release);
+            }
+
+            return self.wait_out_of_inline();
+        }
+
+        if written {
+            self.allocate_pages(0, INLINE_FILE_SIZE, pool)?;
+        }
+
+        if self
+            .state
+            .compare_exchange(INLINE, MOVING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            if written {
+                self.write_pages(inline, 0);
+            }
+
+            self.state.store(PAGED, Ordering::Release);
+        }
+
+        self.wait_out_of_inline()
+    }
+
+    fn wait_out_of_inline(&self) -> Result<()> {
+        while self.is_inline() {
+            thread::yield_now();
+        }
+
+        Ok(())
+    }
+
+    fn is_inline(&self) -> bool {
+        matches!(self.state.load(Ordering::Acquire), INLINE | MOVING)
+    }
+
+    /// Whether the contents are in pages, which [FileContents::share] can replace.
+    pub fn is_paged(&self) -> bool {
+        self.state.load(Ordering::Acquire) == PAGED
+    }
+
+    fn allocate_pages(&self, start: usize, end: usize, pool: Option<&dyn BlockStore>) -> Result<()> {
+        for index in start / PAGE_SIZE..end.div_ceil(PAGE_SIZE) {
+            let slot = &self.table(index / PAGES_PER_TABLE)[index % PAGES_PER_TABLE];
+
+            if !slot.load(Ordering::Acquire).is_null() {
+                continue;
+            }
+
+            let page = match pool {
+                Some(pool) => pool.allocate()?.as_ptr(),
+                None => Box::into_raw(Box::new([0; PAGE_SIZE])),
+            };
+
+            // Another writer may have allocated the page meanwhile, in which case it is kept.
+            if slot
+                .compare_exchange(ptr::null_mut(), page, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                unsafe { Self::release_page(page, pool) };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the contents from `offset` into the whole of `buffer`.
+    pub fn read(&self, buffer: &mut [u8], offset: usize) {
+        let _access = self.access();
+
+        if self.is_inline() {
+            let inline = unsafe { &*self.inline.get() };
+            let len = INLINE_FILE_SIZE.saturating_sub(offset).min(buffer.len());
+
+            buffer[..len].copy_from_slice(&inline[offset.min(INLINE_FILE_SIZE)..][..len]);
+            buffer[len..].fill(0);
+            return;
+        }
+
+        #[cfg(feature = "compression")]
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            return self.lock_compressed().read(buffer, offset);
+        }
+
+        #[cfg(feature = "dedup")]
+        if let Some(shared) = self.shared_contents() {
+            let len = shared.len().saturating_sub(offset).min(buffer.len());
+
+            buffer[..len].copy_from_slice(&shared[offset.min(shared.len())..][..len]);
+            buffer[len..].fill(0);
+            return;
+        }
+
+        self.for_each_page(offset, buffer.len(), |page, within, copied, len| {
+            let target = &mut buffer[copied..copied + len];
+
+            match page {
+                Some(page) => unsafe {
+                    ptr::copy_nonoverlapping((*page).as_ptr().add(within), target.as_mut_ptr(), len)
+                },
+                None => target.fill(0),
+            }
+        });
+    }
+
+    /// Copies `data` into the pages at `offset`, which are allocated first. Returns false if some of them
+    /// were discarded meanwhile, in which case the bytes landing on them are not copied.
+    fn write_pages(&self, data: &[u8], offset: usize) -> bool {
+        let mut complete = true;
+
+        self.for_each_page(offset, data.len(), |page, within, copied, len| match page {
+            Some(page) => unsafe {
+                ptr::copy_nonoverlapping(data[copied..].as_ptr(), (*page).as_mut_ptr().add(within), len)
+            },
+            None => complete = false,
+        });
+
+        complete
+    }
+
+    /// Zeroes `start..end` of the contents which exist. Missing pages read as zeroes already.
+    /// Shared contents are copied into pages taken from `pool` first.
+    pub fn zero(&self, start: usize, end: usize, pool: Option<&dyn BlockStore>) -> Result<()> {
+        self.clear(start, end, false, pool)
+    }
+
+    /// Same as [FileContents::zero], but the pages `start..end` covers as a whole are taken out of the contents
+    /// instead, and given back to `pool` once no access can reach them.
+    pub fn discard(&self, start: usize, end: usize, pool: Option<&dyn BlockStore>) -> Result<()> {
+        self.clear(start, end, true, pool)
+    }
+
+    fn clear(&self, start: usize, end: usize, discard: bool, pool: Option<&dyn BlockStore>) -> Result<()> {
+        let access = self.writable_access(pool)?;
+
+        if self.is_inline() {
+            let end = end.min(INLINE_FILE_SIZE);
+
+            if start < end {
+                let inline = unsafe { &mut *self.inline.get() };
+                inline[start..end].fill(0);
+            }
+            return Ok(());
+        }
+
+        #[cfg(feature = "compression")]
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            self.lock_compressed().zero(start, end);
+
+            return Ok(());
+        }
+
+        self.for_each_page(start, end.saturating_sub(start), |page, within, cleared, len| {
+            match page {
+                Some(_) if discard && len == PAGE_SIZE => self.retire((start + cleared) / PAGE_SIZE),
+                Some(page) => unsafe { ptr::write_bytes((*page).as_mut_ptr().add(within), 0, len) },
+                None => {}
+            }
+        });
+
+        drop(access);
+        self.reclaim(pool);
+
+        Ok(())
+    }
+
+    /// Takes page `index` out of the contents. It is kept aside until [FileContents::reclaim] finds no access
+    /// which may still reach it.
+    fn retire(&self, index: usize) {
+        let table = self.tables[index / PAGES_PER_TABLE].load(Ordering::SeqCst);
+
+        if table.is_null() {
+            return;
+        }
+
+        let page = unsafe { &*table }[index % PAGES_PER_TABLE].swap(ptr::null_mut(), Ordering::SeqCst);
+
+        if let Some(page) = NonNull::new(page) {
+            let mut retired = self.retired.lock().unwrap_or_else(PoisonError::into_inner);
+
+            retired.push(page);
+            self.has_retired.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Gives the retired pages back to `pool` if no access is in progress. An access which starts afterwards
+    /// cannot find them anymore, as they were taken out of the contents before.
+    fn reclaim(&self, pool: Option<&dyn BlockStore>) {
+        if !self.has_retired.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut retired = self.retired.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if self.accesses.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+
+        for page in retired.drain(..) {
+            unsafe { Self::release_page(page.as_ptr(), pool) };
+        }
+
+        self.has_retired.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns the first offset of `offset..end` which lies in a written page, or in a hole if `data` is false,
+    /// or `end` if there is none. Pages which were never written are holes. Contents which are not in pages
+    /// are data as a whole.
+    pub fn find_extent(&self, offset: usize, end: usize, data: bool) -> usize {
+        let _access = self.access();
+
+        if self.state.load(Ordering::Acquire) != PAGED {
+            return if data { offset.min(end) } else { end };
+        }
+
+        let mut found = end;
+
+        self.for_each_page(offset, end.saturating_sub(offset), |page, _, visited, _| {
+            if found == end && page.is_some() == data {
+                found = offset + visited;
+            }
+        });
+
+        found
+    }
+
+    /// Copies the contents into new ones, whose pages are not taken from a memory pool.
+    pub fn duplicate(&self) -> Self {
+        let _access = self.access();
+        let copy = Self::new();
+
+        #[cfg(feature = "compression")]
+        copy.compress.store(self.compress.load(Ordering::Acquire), Ordering::Release);
+
+        if self.is_inline() {
+            let inline = unsafe { &mut *copy.inline.get() };
+            inline.copy_from_slice(unsafe { &*self.inline.get() });
+
+            return copy;
+        }
+
+        #[cfg(feature = "compression")]
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            *copy.lock_compressed() = self.lock_compressed().clone();
+            copy.state.store(COMPRESSED, Ordering::Release);
+
+            return copy;
+        }
+
+        #[cfg(feature = "dedup")]
+        if let Some(shared) = self.shared_contents() {
+            unsafe { *copy.shared.get() = Some(shared.clone()) };
+            copy.state.store(DEDUPED, Ordering::Release);
+
+            return copy;
+        }
+
+        copy.state.store(PAGED, Ordering::Release);
+
+        for index in 0..TABLES * PAGES_PER_TABLE {
+            if let Some(page) = self.page(index) {
+                let start = index * PAGE_SIZE;
+
+                copy.allocate_pages(start, start + PAGE_SIZE, None)
+                    .expect("pages outside of a pool are always allocated");
+                copy.write_pages(unsafe { &*page }, start);
+            }
+        }
+
+        copy
+    }
+
+    /// Pages of the contents along with their index, if the contents are in pages.
+    pub fn pages(&self) -> Option<IndexedPages> {
+        let _access = self.access();
+
+        if self.state.load(Ordering::Acquire) != PAGED {
+            return None;
+        }
+
+        let pages = (0..TABLES * PAGES_PER_TABLE).filter_map(|index| Some((index, NonNull::new(self.page(index)?)?)));
+
+        Some(pages.collect())
+    }
+
+    /// Makes contents which were never written hold `pages`, given along with their index, as they are.
+    pub fn adopt_pages(&self, pages: &[(usize, NonNull<Page>)]) {
+        self.state.store(PAGED, Ordering::Release);
+
+        for (index, page) in pages {
+            self.table(index / PAGES_PER_TABLE)[index % PAGES_PER_TABLE].store(page.as_ptr(), Ordering::Release);
+        }
+    }
+
+    /// Takes every page out of the contents, giving them back to `pool` if given, and leaves them without pages.
+    pub fn release(&mut self, pool: Option<&dyn BlockStore>) {
+        self.take_pages(pool);
+
+        #[cfg(feature = "dedup")]
+        {
+            *self.shared.get_mut() = None;
+        }
+    }
+
+    /// Same as [FileContents::release], while no access can reach the pages.
+    fn take_pages(&self, pool: Option<&dyn BlockStore>) {
+        self.reclaim(pool);
+
+        for table in &self.tables {
+            let table = table.swap(ptr::null_mut(), Ordering::AcqRel);
+
+            if table.is_null() {
+                continue;
+            }
+
+            let table = unsafe { Box::from_raw(table) };
+
+            for slot in table.iter() {
+                let page = slot.swap(ptr::null_mut(), Ordering::AcqRel);
+
+                if !page.is_null() {
+                    unsafe { Self::release_page(page, pool) };
+                }
+            }
+        }
+    }
+
+    /// Gives `page` back to `pool`, or frees it if it was allocated outside of a pool.
+    unsafe fn release_page(page: *mut Page, pool: Option<&dyn BlockStore>) {
+        match pool {
+            Some(pool) => unsafe { pool.release(NonNull::new_unchecked(page)) },
+            None => drop(unsafe { Box::from_raw(page) }),
+        }
+    }
+
+    /// Returns the table of pages `table * PAGES_PER_TABLE..`, allocating it if missing.
+    fn table(&self, table: usize) -> &PageTable {
+        let slot = &self.tables[table];
+        let mut current = slot.load(Ordering::Acquire);
+
+        if current.is_null() {
+            let new = Box::into_raw(Box::new([const { AtomicPtr::new(ptr::null_mut()) }; PAGES_PER_TABLE]));
+
+            current = match slot.compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => new,
+                Err(existing) => {
+                    drop(unsafe { Box::from_raw(new) });
+                    existing
+                }
+            };
+        }
+
+        unsafe { &*current }
+    }
+
+    /// Returns page `index` if it exists. Loads are sequentially consistent with the accesses counted by
+    /// [FileContents::access], so that a page is never found once it is retired and reclaimed.
+    fn page(&self, index: usize) -> Option<*mut Page> {
+        let table = self.tables.get(index / PAGES_PER_TABLE)?.load(Ordering::SeqCst);
+
+        if table.is_null() {
+            return None;
+        }
+
+        let page = unsafe { &*table }[index % PAGES_PER_TABLE].load(Ordering::SeqCst);
+
+        (!page.is_null()).then_some(page)
+    }
+
+    /// Runs `visit` on every page `offset..offset + len` spans, with the page if it exists, the offset of the
+    /// range within the page, the number of bytes of the range visited before, and the length within the page.
+    fn for_each_page(
+        &self,
+        offset: usize,
+        len: usize,
+        mut visit: impl FnMut(Option<*mut Page>, usize, usize, usize),
+    ) {
+        let mut done = 0;
+
+        while done < len {
+            let position = offset + done;
+            let within = position % PAGE_SIZE;
+            let chunk = (PAGE_SIZE - within).min(len - done);
+
+            visit(self.page(position / PAGE_SIZE), within, done, chunk);
+            done += chunk;
+        }
+    }
+
+    /// Enters an access of the contents, waiting while they are switched.
+    fn access(&self) -> Access<'_> {
+        self.accesses.fetch_add(1, Ordering::SeqCst);
+
+        #[cfg(any(feature = "compression", feature = "dedup"))]
+        while self.state.load(Ordering::SeqCst) == SWITCHING {
+            self.accesses.fetch_sub(1, Ordering::SeqCst);
+            thread::yield_now();
+            self.accesses.fetch_add(1, Ordering::SeqCst);
+        }
+
+        Access { contents: self }
+    }
+
+    /// Enters an access of the contents which writes them, once shared contents are copied into pages.
+    fn writable_access(&self, pool: Option<&dyn BlockStore>) -> Result<Access<'_>> {
+        #[cfg(feature = "dedup")]
+        loop {
+            let access = self.access();
+
+            if self.state.load(Ordering::Acquire) != DEDUPED {
+                return Ok(access);
+            }
+
+            drop(access);
+            self.unshare(pool)?;
+        }
+
+        #[cfg(not(feature = "dedup"))]
+        {
+            let _ = pool;
+            Ok(self.access())
+        }
+    }
+}
+
+#[cfg(any(feature = "compression", feature = "dedup"))]
+impl FileContents {
+    /// Moves the contents from state `from` to [SWITCHING], once no access is in progress.
+    /// Returns false if they are in another state.
+    fn begin_switch(&self, from: u8) -> bool {
+        loop {
+            match self
+                .state
+                .compare_exchange(from, SWITCHING, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(SWITCHING) => thread::yield_now(),
+                Err(_) => return false,
+            }
+        }
+
+        while self.accesses.load(Ordering::SeqCst) > 0 {
+            thread::yield_now();
+        }
+
+        true
+    }
+}
+
+#[cfg(feature = "compression")]
+impl FileContents {
+
+    fn lock_compressed(&self) -> std::sync::MutexGuard<'_, CompressedPages> {
+        self.compressed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Compresses the pages, giving them back to `pool`, or decompresses them into pages taken from `pool`.
+    /// Inline contents stay inline, and are compressed or not once they outgrow it. Fails with ENOMEM,
+    /// leaving the contents compressed, if the pool runs out of pages.
+    pub fn set_compression(&self, enabled: bool, pool: Option<&dyn BlockStore>) -> Result<()> {
+        self.compress.store(enabled, Ordering::SeqCst);
+
+        let (from, to) = if enabled { (PAGED, COMPRESSED) } else { (COMPRESSED, PAGED) };
+
+        if !self.begin_switch(from) {
+            return Ok(());
+        }
+
+        let switched = if enabled { self.compress_pages(pool) } else { self.decompress_pages(pool) };
+        let state = if switched.is_ok() { to } else { from };
+
+        self.state.store(state, Ordering::SeqCst);
+
+        switched
+    }
+
+    fn compress_pages(&self, pool: Option<&dyn BlockStore>) -> Result<()> {
+        let mut compressed = self.lock_compressed();
+
+        for index in 0..TABLES * PAGES_PER_TABLE {
+            if let Some(page) = self.page(index) {
+                compressed.set_page(index, unsafe { &*page });
+            }
+        }
+
+        self.take_pages(pool);
+
+        Ok(())
+    }
+
+    fn decompress_pages(&self, pool: Option<&dyn BlockStore>) -> Result<()> {
+        let mut compressed = self.lock_compressed();
+
+        for index in compressed.indices() {
+            let start = index * PAGE_SIZE;
+
+            if let Err(err) = self.allocate_pages(start, start + PAGE_SIZE, pool) {
+                self.take_pages(pool);
+                self.compress.store(true, Ordering::SeqCst);
+
+                return Err(err);
+            }
+
+            self.write_pages(&compressed.page(index), start);
+        }
+
+        *compressed = CompressedPages::default();
+
+        Ok(())
+    }
+
+    /// Adds the compressed pages to `stats`, unless the contents are inline or in pages.
+    pub fn add_to_stats(&self, stats: &mut CompressionStats) {
+        let _access = self.access();
+
+        if self.state.load(Ordering::Acquire) == COMPRESSED {
+            stats.add(&self.lock_compressed());
+        }
+    }
+}
+
+#[cfg(feature = "dedup")]
+impl FileContents {
+    fn shared_contents(&self) -> Option<&Arc<Vec<u8>>> {
+        if self.state.load(Ordering::Acquire) != DEDUPED {
+            return None;
+        }
+
+        unsafe { (*self.shared.get()).as_ref() }
+    }
+
+    /// Replaces the pages with `contents`, giving them back to `pool`, if the contents are in pages and
+    /// `unchanged` holds once no access is in progress. Returns whether the pages were replaced.
+    pub fn share(
+        &self,
+        contents: Arc<Vec<u8>>,
+        pool: Option<&dyn BlockStore>,
+        unchanged: impl FnOnce() -> bool,
+    ) -> bool {
+        if !self.begin_switch(PAGED) {
+            return false;
+        }
+
+        if !unchanged() {
+            self.state.store(PAGED, Ordering::SeqCst);
+            return false;
+        }
+
+        self.take_pages(pool);
+        unsafe { *self.shared.get() = Some(contents) };
+        self.state.store(DEDUPED, Ordering::SeqCst);
+
+        true
+    }
+
+    /// Copies shared contents into pages taken from `pool`. Fails with ENOMEM, leaving them shared,
+    /// if the pool runs out of pages.
+    fn unshare(&self, pool: Option<&dyn BlockStore>) -> Result<()> {
+        if !self.begin_switch(DEDUPED) {
+            return Ok(());
+        }
+
+        let shared = unsafe { &mut *self.shared.get() };
+        let contents = shared.as_deref().map_or(&[][..], Vec::as_slice);
+
+        if let Err(err) = self.allocate_pages(0, contents.len(), pool) {
+            self.take_pages(pool);
+            self.state.store(DEDUPED, Ordering::SeqCst);
+
+            return Err(err);
+        }
+
+        self.write_pages(contents, 0);
+        *shared = None;
+        self.state.store(PAGED, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Returns the contents shared with other files, unless the contents are inline or in pages.
+    pub fn deduplicated(&self) -> Option<Arc<Vec<u8>>> {
+        let _access = self.access();
+
+        self.shared_contents().cloned()
+    }
+}
+
+impl Drop for FileContents {
+    fn drop(&mut self) {
+        self.release(None);
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::process::Credentials;
+use crate::quota::Charge;
+use crate::timestamp::Timestamps;
+use crate::utils::{MemFSErr, Result};
+
+/// Permission bits of a file created by [crate::memfs::MemFS::open], before the file creation mask is applied.
+pub const DEFAULT_FILE_MODE: u32 = 0o666;
+
+/// Permission bits of a directory created by [crate::memfs::MemFS::mkdir], before the file creation mask is applied.
+pub const DEFAULT_DIRECTORY_MODE: u32 = 0o777;
+
+/// Bits kept by [crate::memfs::MemFS::chmod]: permissions, set-user-ID, set-group-ID and sticky.
+pub const MODE_MASK: u32 = 0o7777;
+
+pub(crate) const MAY_READ: u32 = 0o4;
+pub(crate) const MAY_WRITE: u32 = 0o2;
+pub(crate) const MAY_EXECUTE: u32 = 0o1;
+
+/// Mode of [crate::memfs::MemFS::access] testing that the path exists.
+pub const F_OK: u32 = 0;
+
+/// Mode of [crate::memfs::MemFS::access] testing read permission. May be combined with [W_OK] and [X_OK].
+pub const R_OK: u32 = MAY_READ;
+
+/// Mode of [crate::memfs::MemFS::access] testing write permission.
+pub const W_OK: u32 = MAY_WRITE;
+
+/// Mode of [crate::memfs::MemFS::access] testing execute permission, or search permission of a directory.
+pub const X_OK: u32 = MAY_EXECUTE;
+
+/// Metadata of a file or directory: its inode number, who may access it, its times, and what it is charged against the quota.
+/// Clones keep the inode number, so that copies of a tree keep the identity of their nodes, but are charged nothing.
+#[derive(Debug)]
+pub(crate) struct NodeAttributes {
+    ino: u64,
+    mode: AtomicU32,
+    uid: AtomicU32,
+    gid: AtomicU32,
+    times: Timestamps,
+    charge: Option<Charge>,
+}
+
+impl NodeAttributes {
+    pub fn new(ino: u64, mode: u32, owner: Credentials) -> Self {
+        Self {
+            ino,
+            mode: AtomicU32::new(mode & MODE_MASK),
+            uid: AtomicU32::new(owner.uid),
+            gid: AtomicU32::new(owner.gid),
+            times: Timestamps::new(),
+            charge: None,
+        }
+    }
+
+    pub fn with_charge(mut self, charge: Charge) -> Self {
+        self.charge = Some(charge);
+        self
+    }
+
+    pub fn charge(&self) -> Option<&Charge> {
+        self.charge.as_ref()
+    }
+
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    pub fn times(&self) -> &Timestamps {
+        &self.times
+    }
+
+    pub fn mode(&self) -> u32 {
+        self.mode.load(Ordering::Acquire)
+    }
+
+    pub fn set_mode(&self, mode: u32) {
+        self.mode.store(mode & MODE_MASK, Ordering::Release);
+    }
+
+    pub fn owner(&self) -> Credentials {
+        Credentials::new(self.uid.load(Ordering::Acquire), self.gid.load(Ordering::Acquire))
+    }
+
+    pub fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) {
+        if let Some(uid) = uid {
+            self.uid.store(uid, Ordering::Release);
+        }
+
+        if let Some(gid) = gid {
+            self.gid.store(gid, Ordering::Release);
+        }
+    }
+
+    /// Fails with EPERM unless `credentials` may change the mode of the node: the superuser and the owner may.
+    pub fn check_chmod(&self, credentials: Credentials) -> Result<()> {
+        if credentials == Credentials::ROOT || credentials.uid == self.owner().uid {
+            Ok(())
+        } else {
+            Err(MemFSErr::operation_not_permitted())
+        }
+    }
+
+    /// Fails with EPERM unless `credentials` may change the owner to `uid` and the group to `gid`.
+    /// The superuser may change both; the owner may only hand the group over to its own group.
+    pub fn check_chown(&self, credentials: Credentials, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        let owner = self.owner();
+
+        if credentials == Credentials::ROOT
+            || (credentials.uid == owner.uid
+                && uid.is_none_or(|uid| uid == owner.uid)
+                && gid.is_none_or(|gid| gid == owner.gid || gid == credentials.gid))
+        {
+            Ok(())
+        } else {
+            Err(MemFSErr::operation_not_permitted())
+        }
+    }
+
+    /// Fails with EACCES unless `credentials` are granted every access in `access`, a mask of
+    /// [MAY_READ], [MAY_WRITE] and [MAY_EXECUTE]. The superuser is granted everything.
+    ///
+    /// As on Unix, only the bits of the first class matching the credentials apply: owner, group, then others.
+    pub fn check(&self, credentials: Credentials, access: u32) -> Result<()> {
+        if credentials == Credentials::ROOT {
+            return Ok(());
+        }
+
+        let owner = self.owner();
+        let shift = if credentials.uid == owner.uid {
+            6
+        } else if credentials.gid == owner.gid {
+            3
+        } else {
+            0
+        };
+        let granted = (self.mode() >> shift) & 0o7;
+
+        if granted & access == access {
+            Ok(())
+        } else {
+            Err(MemFSErr::permission_denied())
+        }
+    }
+}
+
+impl Clone for NodeAttributes {
+    fn clone(&self) -> Self {
+        Self {
+            times: self.times.clone(),
+            ..Self::new(self.ino, self.mode(), self.owner())
+        }
+    }
+}
@@ -0,0 +1,619 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io,
+    os::unix::fs::FileExt,
+    path::Path,
+    ptr::NonNull,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::device::Device;
+use crate::memfs::MemFS;
+use crate::pool::{BlockStore, MappedPages, Page};
+use crate::utils::{FileType, MemFSErr, NUMBER_OF_MAXIMUM_FILES, OpenFlag, PAGE_SIZE, Result, Stat};
+
+const MAGIC: &[u8; 8] = b"MEMFSPF2";
+
+/// Length of the header: the magic, the number of pages, and the offset, length and checksum of the tree.
+const HEADER_LEN: usize = 40;
+
+/// Pages of a backing file held by a file system opened with [MemFS::open_persistent].
+///
+/// The file starts with a header page, followed by the pages of file contents, mapped into memory so that
+/// files read and write them in place, followed by the record of the tree, which is rewritten on every
+/// [MemFS::sync] and when the file system is dropped. A new tree is written where it does not overlap
+/// the one the header points to, and the header is moved to it only once it is written, so that a crash
+/// leaves one of them whole.
+pub(crate) struct PersistentStore {
+    file: File,
+    pages: MappedPages,
+
+    /// Offset and length of the tree the header points to. Serializes writes of the tree.
+    tree: Mutex<(u64, u64)>,
+}
+
+impl PersistentStore {
+    /// Opens the backing file at `path`, or creates it for `capacity` pages, along with the records of the tree
+    /// it holds. The capacity of an existing file is kept.
+    fn open(path: &Path, capacity: usize) -> Result<(Self, Vec<Record>)> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let mut header = [0u8; HEADER_LEN];
+
+        if file.metadata()?.len() == 0 {
+            header[..8].copy_from_slice(MAGIC);
+            header[8..16].copy_from_slice(&(capacity as u64).to_le_bytes());
+            header[16..24].copy_from_slice(&Self::tree_offset(capacity).to_le_bytes());
+            header[32..].copy_from_slice(&checksum(&[]).to_le_bytes());
+            file.set_len(((capacity + 1) * PAGE_SIZE) as u64)?;
+            file.write_all_at(&header, 0)?;
+            file.sync_all()?;
+        } else {
+            file.read_exact_at(&mut header, 0)?;
+        }
+
+        if &header[..8] != MAGIC {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let field = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        let capacity = field(8) as usize;
+        let offset = field(16);
+        let mut tree = vec![0u8; field(24) as usize];
+
+        if offset < Self::tree_offset(capacity) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        file.read_exact_at(&mut tree, offset)?;
+
+        if checksum(&tree) != field(32) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupted tree in backing file").into());
+        }
+
+        let records = Decoder { bytes: &tree }.records()?;
+        let mut pages = MappedPages::map(&file, PAGE_SIZE as u64, capacity)?;
+        let used = records.iter().flat_map(Record::slots).collect::<Vec<_>>();
+
+        if used.iter().any(|&slot| slot >= capacity) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        pages.reserve(&used);
+
+        Ok((
+            Self {
+                file,
+                pages,
+                tree: Mutex::new((offset, tree.len() as u64)),
+            },
+            records,
+        ))
+    }
+
+    fn tree_offset(capacity: usize) -> u64 {
+        ((capacity + 1) * PAGE_SIZE) as u64
+    }
+
+    /// Writes the pages back, then the tree, and only then the header pointing to it. The tree goes right
+    /// after the pages if it ends before the current one starts, and after the current one otherwise.
+    /// Whatever follows it is cut off once the header points to it.
+    fn write_tree(&self, records: &[Record]) -> Result<()> {
+        let mut current = self.tree.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut tree = Vec::new();
+        records.iter().for_each(|record| record.encode(&mut tree));
+
+        let (current_offset, current_len) = *current;
+        let len = tree.len() as u64;
+        let offset = match Self::tree_offset(self.pages.capacity) {
+            start if start + len <= current_offset => start,
+            _ => current_offset + current_len,
+        };
+        let mut header = [0u8; HEADER_LEN - 8];
+        header[..8].copy_from_slice(&(self.pages.capacity as u64).to_le_bytes());
+        header[8..16].copy_from_slice(&offset.to_le_bytes());
+        header[16..24].copy_from_slice(&len.to_le_bytes());
+        header[24..].copy_from_slice(&checksum(&tree).to_le_bytes());
+
+        self.pages.flush()?;
+        self.file.write_all_at(&tree, offset)?;
+        self.file.sync_data()?;
+        self.file.write_all_at(&header, 8)?;
+        self.file.sync_data()?;
+        *current = (offset, len);
+        self.file.set_len(offset + len)?;
+
+        Ok(())
+    }
+}
+
+impl BlockStore for PersistentStore {
+    fn allocate(&self) -> Result<NonNull<Page>> {
+        self.pages.allocate().ok_or_else(MemFSErr::out_of_memory)
+    }
+
+    unsafe fn release(&self, page: NonNull<Page>) {
+        match self.pages.index_of(page) {
+            Some(index) => self.pages.release(index),
+            None => drop(unsafe { Box::from_raw(page.as_ptr()) }),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.pages.capacity
+    }
+
+    fn available(&self) -> usize {
+        self.pages.available()
+    }
+}
+
+/// FNV-1a, to tell a tree torn by a crash from a complete one.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Metadata of a directory or a file, restored on open.
+struct Attributes {
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    atime: SystemTime,
+    mtime: SystemTime,
+}
+
+impl From<&Stat> for Attributes {
+    fn from(stat: &Stat) -> Self {
+        Self {
+            mode: stat.mode,
+            uid: stat.uid,
+            gid: stat.gid,
+            atime: stat.atime,
+            mtime: stat.mtime,
+        }
+    }
+}
+
+/// Where a part of the contents of a file is.
+enum Chunk {
+    /// The page at `index` of the file is the page at `slot` of the backing file.
+    Page { index: usize, slot: usize },
+
+    /// Contents which are not in a page of the backing file, such as inline or compressed ones.
+    Data { offset: usize, bytes: Vec<u8> },
+}
+
+/// Entry of the tree, under a path whose parent comes earlier in the record.
+enum Record {
+    Directory { path: String, attributes: Attributes },
+    File { path: String, attributes: Attributes, size: usize, chunks: Vec<Chunk> },
+
+    /// Another name of the file at `first`.
+    Link { path: String, first: String },
+
+    Symlink { path: String, target: String },
+    Device { path: String, attributes: Attributes, device: Device },
+    Fifo { path: String, attributes: Attributes },
+}
+
+impl Record {
+    fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        let chunks = match self {
+            Record::File { chunks, .. } => chunks.as_slice(),
+            _ => &[],
+        };
+
+        chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::Page { slot, .. } => Some(*slot),
+            Chunk::Data { .. } => None,
+        })
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Record::Directory { path, attributes } => {
+                out.push(0);
+                put_bytes(out, path.as_bytes());
+                attributes.encode(out);
+            }
+            Record::File { path, attributes, size, chunks } => {
+                out.push(1);
+                put_bytes(out, path.as_bytes());
+                attributes.encode(out);
+                put_u64(out, *size as u64);
+                put_u64(out, chunks.len() as u64);
+
+                for chunk in chunks {
+                    match chunk {
+                        Chunk::Page { index, slot } => {
+                            out.push(0);
+                            put_u64(out, *index as u64);
+                            put_u64(out, *slot as u64);
+                        }
+                        Chunk::Data { offset, bytes } => {
+                            out.push(1);
+                            put_u64(out, *offset as u64);
+                            put_bytes(out, bytes);
+                        }
+                    }
+                }
+            }
+            Record::Link { path, first } => {
+                out.push(2);
+                put_bytes(out, path.as_bytes());
+                put_bytes(out, first.as_bytes());
+            }
+            Record::Symlink { path, target } => {
+                out.push(3);
+                put_bytes(out, path.as_bytes());
+                put_bytes(out, target.as_bytes());
+            }
+            Record::Device { path, attributes, device } => {
+                out.push(4);
+                put_bytes(out, path.as_bytes());
+                attributes.encode(out);
+                out.push(match device {
+                    Device::Null => 0,
+                    Device::Zero => 1,
+                    Device::Random => 2,
+                });
+            }
+            Record::Fifo { path, attributes } => {
+                out.push(5);
+                put_bytes(out, path.as_bytes());
+                attributes.encode(out);
+            }
+        }
+    }
+}
+
+impl Attributes {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let nanos = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64);
+
+        out.extend_from_slice(&self.mode.to_le_bytes());
+        out.extend_from_slice(&self.uid.to_le_bytes());
+        out.extend_from_slice(&self.gid.to_le_bytes());
+        put_u64(out, nanos(self.atime));
+        put_u64(out, nanos(self.mtime));
+    }
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_u64(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Reads records written by [Record::encode], failing with EINVAL on anything else.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn records(&mut self) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+
+        while !self.bytes.is_empty() {
+            records.push(self.record()?);
+        }
+
+        Ok(records)
+    }
+
+    fn record(&mut self) -> Result<Record> {
+        Ok(match self.take(1)?[0] {
+            0 => Record::Directory {
+                path: self.string()?,
+                attributes: self.attributes()?,
+            },
+            1 => {
+                let path = self.string()?;
+                let attributes = self.attributes()?;
+                let size = self.u64()? as usize;
+                let count = self.u64()?;
+                let mut chunks = Vec::new();
+
+                for _ in 0..count {
+                    chunks.push(match self.take(1)?[0] {
+                        0 => Chunk::Page {
+                            index: self.u64()? as usize,
+                            slot: self.u64()? as usize,
+                        },
+                        1 => Chunk::Data {
+                            offset: self.u64()? as usize,
+                            bytes: self.bytes()?.to_vec(),
+                        },
+                        _ => return Err(MemFSErr::invalid_value()),
+                    });
+                }
+
+                Record::File {
+                    path,
+                    attributes,
+                    size,
+                    chunks,
+                }
+            }
+            2 => Record::Link {
+                path: self.string()?,
+                first: self.string()?,
+            },
+            3 => Record::Symlink {
+                path: self.string()?,
+                target: self.string()?,
+            },
+            4 => Record::Device {
+                path: self.string()?,
+                attributes: self.attributes()?,
+                device: match self.take(1)?[0] {
+                    0 => Device::Null,
+                    1 => Device::Zero,
+                    2 => Device::Random,
+                    _ => return Err(MemFSErr::invalid_value()),
+                },
+            },
+            5 => Record::Fifo {
+                path: self.string()?,
+                attributes: self.attributes()?,
+            },
+            _ => return Err(MemFSErr::invalid_value()),
+        })
+    }
+
+    fn attributes(&mut self) -> Result<Attributes> {
+        let u32 = |decoder: &mut Self| Ok::<_, MemFSErr>(u32::from_le_bytes(decoder.take(4)?.try_into().unwrap()));
+        let time = |nanos: u64| UNIX_EPOCH + Duration::from_nanos(nanos);
+
+        Ok(Attributes {
+            mode: u32(self)?,
+            uid: u32(self)?,
+            gid: u32(self)?,
+            atime: time(self.u64()?),
+            mtime: time(self.u64()?),
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.bytes.len() < len {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&[u8]> {
+        let len = self.u64()? as usize;
+
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| MemFSErr::invalid_value())
+    }
+}
+
+impl MemFS {
+    /// Opens the file system kept in the backing file at `path`, creating it with room for
+    /// [NUMBER_OF_MAXIMUM_FILES] pages if it does not exist. See [MemFS::open_persistent_with_capacity].
+    pub fn open_persistent(path: impl AsRef<Path>) -> Result<MemFS> {
+        Self::open_persistent_with_capacity(path, NUMBER_OF_MAXIMUM_FILES)
+    }
+
+    /// Opens the file system kept in the backing file at `path`, or creates the file with room for `pages` pages
+    /// of file contents. The pages live in the file, mapped into memory, so that they do not take space on
+    /// the heap and the kernel writes them back and evicts the cold ones.
+    ///
+    /// The tree, along with the mode, owners and times of directories and files, is written to the file by
+    /// [MemFS::sync] and when the file system is dropped, and restored on the next open. Inode numbers are not
+    /// kept, nor are files which are only open. Pages are written in place, so after a crash files may hold
+    /// writes made since the last sync. Fails with EINVAL if the file is not a backing file.
+    pub fn open_persistent_with_capacity(path: impl AsRef<Path>, pages: usize) -> Result<MemFS> {
+        let (store, records) = PersistentStore::open(path.as_ref(), pages)?;
+        let store = Arc::new(store);
+        let fs = MemFS::with_persistence(store.clone());
+
+        fs.restore_records(&store, &records)?;
+
+        Ok(fs)
+    }
+
+    /// Writes the tree into the backing file of a file system opened with [MemFS::open_persistent], holding
+    /// every other operation off meanwhile.
+    pub(crate) fn persist(&self, store: &PersistentStore) -> Result<()> {
+        let _exclusive = self.lock_exclusive()?;
+        let root = self.stat("/")?;
+        let mut records = vec![Record::Directory {
+            path: String::new(),
+            attributes: Attributes::from(&root),
+        }];
+        let mut files = HashMap::new();
+        let mut claimed = HashSet::new();
+        let mut pending = vec![String::new()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = self.readdir(if dir.is_empty() { "/" } else { &dir })?;
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+            for entry in entries {
+                let path = format!("{dir}/{}", entry.name);
+
+                if entry.file_type == FileType::Symlink {
+                    let target = self.readlink(&path)?;
+                    records.push(Record::Symlink { path, target });
+                    continue;
+                }
+
+                // Virtual files are left out, as their callbacks cannot be written down.
+                if entry.file_type == FileType::File && self.virtual_file(&path).is_ok() {
+                    continue;
+                }
+
+                let stat = self.stat(&path)?;
+
+                if entry.file_type == FileType::CharDevice {
+                    records.push(Record::Device {
+                        device: self.device(&path)?,
+                        path,
+                        attributes: Attributes::from(&stat),
+                    });
+                } else if entry.file_type == FileType::Fifo {
+                    records.push(Record::Fifo {
+                        path,
+                        attributes: Attributes::from(&stat),
+                    });
+                } else if entry.file_type == FileType::Directory {
+                    records.push(Record::Directory {
+                        path: path.clone(),
+                        attributes: Attributes::from(&stat),
+                    });
+                    pending.push(path);
+                } else if let Some(first) = files.get(&stat.ino) {
+                    records.push(Record::Link {
+                        path,
+                        first: String::clone(first),
+                    });
+                } else {
+                    let chunks = self.persisted_chunks(store, &mut claimed, &path, stat.size)?;
+                    files.insert(stat.ino, path.clone());
+                    records.push(Record::File {
+                        path,
+                        attributes: Attributes::from(&stat),
+                        size: stat.size,
+                        chunks,
+                    });
+                }
+            }
+        }
+
+        store.write_tree(&records)
+    }
+
+    /// Tells where the contents of the file at `path` are: pages of the backing file are referred to,
+    /// unless another file in `claimed` refers to them already, and anything else is copied.
+    fn persisted_chunks(
+        &self,
+        store: &PersistentStore,
+        claimed: &mut HashSet<usize>,
+        path: &str,
+        size: usize,
+    ) -> Result<Vec<Chunk>> {
+        let Some(pages) = self.file_pages(path)? else {
+            let mut bytes = vec![0u8; size];
+            let fd = self.open(path, OpenFlag::O_RDONLY)?;
+            let read = self.pread(fd, &mut bytes, 0);
+            self.close(fd)?;
+            bytes.truncate(read?);
+
+            return Ok(vec![Chunk::Data { offset: 0, bytes }]);
+        };
+
+        Ok(pages
+            .into_iter()
+            .filter(|(index, _)| index * PAGE_SIZE < size)
+            .map(|(index, page)| match store.pages.index_of(page) {
+                Some(slot) if claimed.insert(slot) => Chunk::Page { index, slot },
+                _ => Chunk::Data {
+                    offset: index * PAGE_SIZE,
+                    bytes: unsafe { page.as_ref() }[..PAGE_SIZE.min(size - index * PAGE_SIZE)].to_vec(),
+                },
+            })
+            .collect())
+    }
+
+    fn restore_records(&self, store: &PersistentStore, records: &[Record]) -> Result<()> {
+        let mut attributes = Vec::new();
+
+        for record in records {
+            match record {
+                Record::Directory { path, attributes: attrs } => {
+                    if !path.is_empty() {
+                        self.mkdir(path)?;
+                    }
+
+                    attributes.push((if path.is_empty() { "/" } else { path.as_str() }, attrs));
+                }
+                Record::File {
+                    path,
+                    attributes: attrs,
+                    size,
+                    chunks,
+                } => {
+                    let fd = self.open(path, OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)?;
+                    let restored = self.restore_contents(store, fd, path, *size, chunks);
+                    self.close(fd)?;
+                    restored?;
+
+                    attributes.push((path, attrs));
+                }
+                Record::Link { path, first } => self.link(first, path)?,
+                Record::Symlink { path, target } => self.symlink(target, path)?,
+                Record::Device {
+                    path,
+                    attributes: attrs,
+                    device,
+                } => {
+                    self.mknod(path, *device, attrs.mode)?;
+                    attributes.push((path, attrs));
+                }
+                Record::Fifo { path, attributes: attrs } => {
+                    self.mkfifo(path, attrs.mode)?;
+                    attributes.push((path, attrs));
+                }
+            }
+        }
+
+        // Children are restored after their parents, so they get their times last.
+        for (path, attrs) in attributes.into_iter().rev() {
+            self.chmod(path, attrs.mode)?;
+            self.chown(path, Some(attrs.uid), Some(attrs.gid))?;
+            self.utimens(path, Some(attrs.atime), Some(attrs.mtime))?;
+        }
+
+        Ok(())
+    }
+
+    fn restore_contents(
+        &self,
+        store: &PersistentStore,
+        fd: usize,
+        path: &str,
+        size: usize,
+        chunks: &[Chunk],
+    ) -> Result<()> {
+        self.ftruncate(fd, size)?;
+
+        let pages = chunks
+            .iter()
+            .filter_map(|chunk| match chunk {
+                Chunk::Page { index, slot } => Some((*index, store.pages.page(*slot))),
+                Chunk::Data { .. } => None,
+            })
+            .collect::<Vec<_>>();
+
+        if !pages.is_empty() {
+            self.adopt_pages(path, &pages)?;
+        }
+
+        for chunk in chunks {
+            if let Chunk::Data { offset, bytes } = chunk {
+                self.pwrite(fd, bytes, *offset)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::utils::{MemFSErr, Result};
+
+/// Number of bytes a FIFO holds before writes wait for a reader to make room, as the default on Linux.
+pub const PIPE_CAPACITY: usize = 1 << 16;
+
+/// Writes of at most this many bytes land in a FIFO in one piece, never interleaved with other writes.
+pub const PIPE_BUF: usize = 1 << 12;
+
+#[derive(Default)]
+struct PipeState {
+    buffer: VecDeque<u8>,
+    readers: usize,
+    writers: usize,
+
+    /// Number of opens for reading and for writing so far, so that an open waiting for the other end
+    /// notices one which was opened and closed again before it woke up.
+    reader_opens: u64,
+    writer_opens: u64,
+}
+
+impl PipeState {
+    /// Returns whether a write of `len` more bytes has to wait: there is no room at all, or a write
+    /// of at most [PIPE_BUF] bytes does not fit in one piece.
+    fn is_full_for(&self, len: usize) -> bool {
+        let room = PIPE_CAPACITY - self.buffer.len();
+
+        room == 0 || (len <= PIPE_BUF && room < len)
+    }
+}
+
+/// Buffer of a FIFO created with [crate::memfs::MemFS::mkfifo], shared by every descriptor open on it.
+/// Data written through one descriptor is read once, in order, through any descriptor open for reading.
+#[derive(Default)]
+pub(crate) struct Pipe {
+    state: Mutex<PipeState>,
+    changed: Condvar,
+}
+
+impl Pipe {
+    fn lock(&self) -> MutexGuard<'_, PipeState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Opens an end of the pipe for reading, writing or both. Without `nonblock`, an end which only reads
+    /// waits for a writer, and an end which only writes waits for a reader. With `nonblock`, an end which
+    /// only writes fails with ENXIO if there is no reader.
+    pub fn open(self: &Arc<Self>, reads: bool, writes: bool, nonblock: bool) -> Result<PipeEnd> {
+        let mut state = self.lock();
+
+        if writes && !reads && nonblock && state.readers == 0 {
+            return Err(MemFSErr::no_such_device_or_address());
+        }
+
+        if reads {
+            state.readers += 1;
+            state.reader_opens += 1;
+        }
+
+        if writes {
+            state.writers += 1;
+            state.writer_opens += 1;
+        }
+
+        self.changed.notify_all();
+
+        if !nonblock && reads != writes {
+            let (reader_opens, writer_opens) = (state.reader_opens, state.writer_opens);
+
+            while (reads && state.writers == 0 && state.writer_opens == writer_opens)
+                || (writes && state.readers == 0 && state.reader_opens == reader_opens)
+            {
+                state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        Ok(PipeEnd {
+            pipe: self.clone(),
+            reads,
+            writes,
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Waits until a read would not fail with EAGAIN: there is data, or no writer is left.
+    pub fn wait_readable(&self) {
+        let mut state = self.lock();
+
+        while state.buffer.is_empty() && state.writers > 0 {
+            state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Waits until a write of `len` bytes would not fail with EAGAIN: there is room for it, or no reader is left.
+    pub fn wait_writable(&self, len: usize) {
+        let mut state = self.lock();
+
+        while state.is_full_for(len) && state.readers > 0 {
+            state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// End of a [Pipe] held by a descriptor. Closing it, or dropping it, wakes up the other end.
+pub(crate) struct PipeEnd {
+    pipe: Arc<Pipe>,
+    reads: bool,
+    writes: bool,
+    closed: AtomicBool,
+}
+
+impl PipeEnd {
+    pub fn pipe(&self) -> &Arc<Pipe> {
+        &self.pipe
+    }
+
+    /// Reads what is buffered into `buffer`, and returns the number of bytes read, which is 0 once
+    /// the pipe is empty and no writer is left. Fails with EAGAIN if it is empty and writers are left.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        let mut state = self.pipe.lock();
+
+        if state.buffer.is_empty() {
+            return match state.writers == 0 || buffer.is_empty() {
+                true => Ok(0),
+                false => Err(MemFSErr::try_again()),
+            };
+        }
+
+        let read = buffer.len().min(state.buffer.len());
+
+        for (byte, buffered) in buffer.iter_mut().zip(state.buffer.drain(..read)) {
+            *byte = buffered;
+        }
+
+        self.pipe.changed.notify_all();
+
+        Ok(read)
+    }
+
+    /// Buffers as much of `data` as there is room for, all of it if it is at most [PIPE_BUF] bytes,
+    /// and returns the number of bytes written. Fails with EPIPE if no reader is left, and with EAGAIN
+    /// if no byte could be written.
+    pub fn write(&self, data: &[u8]) -> Result<usize> {
+        let mut state = self.pipe.lock();
+
+        if state.readers == 0 {
+            return Err(MemFSErr::broken_pipe());
+        } else if data.is_empty() {
+            return Ok(0);
+        } else if state.is_full_for(data.len()) {
+            return Err(MemFSErr::try_again());
+        }
+
+        let written = data.len().min(PIPE_CAPACITY - state.buffer.len());
+        state.buffer.extend(&data[..written]);
+        self.pipe.changed.notify_all();
+
+        Ok(written)
+    }
+
+    /// Lets the other end know this one is gone. Only the first call does anything.
+    pub fn close(&self) {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mut state = self.pipe.lock();
+
+        if self.reads {
+            state.readers -= 1;
+        }
+
+        if self.writes {
+            state.writers -= 1;
+        }
+
+        // As on Linux, what is left unread is gone once no descriptor has the pipe open.
+        if state.readers == 0 && state.writers == 0 {
+            state.buffer.clear();
+        }
+
+        self.pipe.changed.notify_all();
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
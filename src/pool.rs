@@ -0,0 +1,365 @@
+use crossbeam::queue::ArrayQueue;
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+pub use crate::page::Page;
+use crate::utils::{MemFSErr, PAGE_SIZE, Result};
+
+/// Storage of the pages holding file contents, set with [crate::memfs::MemFSBuilder::block_store].
+///
+/// Files read and write their pages in place through the pointers handed out, without going through
+/// the store, so a page must stay valid and in place until it is released.
+pub trait BlockStore: Send + Sync {
+    /// Hands out a zeroed page, or fails with ENOMEM once the store is full.
+    fn allocate(&self) -> Result<NonNull<Page>>;
+
+    /// Takes back a page which no file uses anymore.
+    ///
+    /// # Safety
+    /// `page` was handed out by [BlockStore::allocate] of this store, or is a page MemFS allocated as a [Box],
+    /// as the pages of copied files are, which the store owns from then on.
+    unsafe fn release(&self, page: NonNull<Page>);
+
+    /// Number of pages the store can hold at the same time.
+    fn capacity(&self) -> usize;
+
+    /// Number of pages which can still be handed out.
+    fn available(&self) -> usize;
+
+    /// Gives idle memory back to the system, and returns the number of reclaimed bytes.
+    fn compact(&self) -> u64 {
+        0
+    }
+
+    /// Bytes reclaimed by every [BlockStore::compact] so far.
+    fn reclaimed_bytes(&self) -> u64 {
+        0
+    }
+}
+
+/// Shares a store between several file systems.
+impl<S: BlockStore + ?Sized> BlockStore for Arc<S> {
+    fn allocate(&self) -> Result<NonNull<Page>> {
+        (**self).allocate()
+    }
+
+    unsafe fn release(&self, page: NonNull<Page>) {
+        unsafe { (**self).release(page) }
+    }
+
+    fn capacity(&self) -> usize {
+        (**self).capacity()
+    }
+
+    fn available(&self) -> usize {
+        (**self).available()
+    }
+
+    fn compact(&self) -> u64 {
+        (**self).compact()
+    }
+
+    fn reclaimed_bytes(&self) -> u64 {
+        (**self).reclaimed_bytes()
+    }
+}
+
+/// Pool of the pages holding file contents in memory, which is the default [BlockStore].
+///
+/// At most `capacity` pages exist at the same time. Pages are preallocated on creation,
+/// but idle ones can be released by [MemoryPool::compact]; they are allocated again lazily on demand.
+pub struct MemoryPool {
+    idle: ArrayQueue<Box<Page>>,
+    capacity: usize,
+    allocated: AtomicUsize,
+    reclaimed_bytes: AtomicU64,
+}
+
+impl MemoryPool {
+    pub fn with_preallocated(capacity: usize) -> Self {
+        // ArrayQueue cannot be empty, even for a pool which never hands out a page.
+        let idle = ArrayQueue::new(capacity.max(1));
+
+        for _ in 0..capacity {
+            idle.push(Box::new([0; PAGE_SIZE])).unwrap();
+        }
+
+        Self {
+            idle,
+            capacity,
+            allocated: AtomicUsize::new(capacity),
+            reclaimed_bytes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl BlockStore for MemoryPool {
+    fn allocate(&self) -> Result<NonNull<Page>> {
+        if let Some(page) = self.idle.pop() {
+            return Ok(NonNull::from(Box::leak(page)));
+        }
+
+        // Pool ran dry. Allocate a new page if the capacity allows it.
+        self.allocated
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.capacity()).then_some(n + 1)
+            })
+            .map(|_| NonNull::from(Box::leak(Box::new([0; PAGE_SIZE]))))
+            .map_err(|_| MemFSErr::out_of_memory())
+    }
+
+    /// Takes back a page, zeroing it for the next file.
+    unsafe fn release(&self, page: NonNull<Page>) {
+        let mut page = unsafe { Box::from_raw(page.as_ptr()) };
+        page.fill(0);
+
+        if self.idle.push(page).is_err() {
+            self.allocated.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn available(&self) -> usize {
+        self.capacity() - self.allocated.load(Ordering::Acquire) + self.idle.len()
+    }
+
+    /// Releases every idle page to the global allocator and returns the number of reclaimed bytes.
+    fn compact(&self) -> u64 {
+        let mut released = 0;
+
+        while self.idle.pop().is_some() {
+            self.allocated.fetch_sub(1, Ordering::AcqRel);
+            released += 1;
+        }
+
+        let bytes = (released * PAGE_SIZE) as u64;
+        self.reclaimed_bytes.fetch_add(bytes, Ordering::Relaxed);
+
+        bytes
+    }
+
+    fn reclaimed_bytes(&self) -> u64 {
+        self.reclaimed_bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Keeps pages in a host file mapped into memory, so that the kernel writes them back to the file and evicts
+/// the cold ones under memory pressure, instead of MemFS holding every page in memory.
+///
+/// The file is sized for `capacity` pages up front, as a sparse file which takes disk space only for the pages
+/// written. It is scratch space: its contents cannot be loaded again.
+pub struct MmapStore {
+    pages: MappedPages,
+}
+
+impl MmapStore {
+    /// Creates the file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((capacity * PAGE_SIZE) as u64)?;
+
+        Ok(Self {
+            pages: MappedPages::map(&file, 0, capacity)?,
+        })
+    }
+}
+
+impl BlockStore for MmapStore {
+    fn allocate(&self) -> Result<NonNull<Page>> {
+        self.pages.allocate().ok_or_else(MemFSErr::out_of_memory)
+    }
+
+    unsafe fn release(&self, page: NonNull<Page>) {
+        match self.pages.index_of(page) {
+            Some(index) => self.pages.release(index),
+            None => drop(unsafe { Box::from_raw(page.as_ptr()) }),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.pages.capacity
+    }
+
+    fn available(&self) -> usize {
+        self.pages.available()
+    }
+}
+
+/// Hands out pages of a [MemoryPool] of `memory_pages` first, and the pages needed beyond them from
+/// a file mapping of `overflow_pages`, as [MmapStore] does, so that files can outgrow the memory given to them.
+///
+/// Only new pages overflow to the file. Pages are never moved between memory and the file once handed out,
+/// since files address them in place, so pages in memory stay there however cold they get, and pages in the
+/// file stay there once memory frees up. For the kernel to page cold contents out, use [MmapStore] instead.
+///
+/// The overflow file is created under `overflow_dir` and unlinked right away, so that nothing is left behind
+/// once the store is dropped, even if the process does not exit cleanly.
+pub struct OverflowStore {
+    memory: MemoryPool,
+    overflow: MappedPages,
+}
+
+impl OverflowStore {
+    pub fn new(memory_pages: usize, overflow_dir: impl AsRef<Path>, overflow_pages: usize) -> io::Result<Self> {
+        static OVERFLOW_FILES: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!(
+            ".memfs-overflow-{}-{}",
+            std::process::id(),
+            OVERFLOW_FILES.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = overflow_dir.as_ref().join(name);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        let overflow = file
+            .set_len((overflow_pages * PAGE_SIZE) as u64)
+            .and_then(|_| MappedPages::map(&file, 0, overflow_pages));
+
+        fs::remove_file(&path)?;
+
+        Ok(Self {
+            memory: MemoryPool::with_preallocated(memory_pages),
+            overflow: overflow?,
+        })
+    }
+
+    /// Number of pages in use in the overflow file.
+    pub fn overflowed(&self) -> usize {
+        self.overflow.capacity - self.overflow.available()
+    }
+}
+
+impl BlockStore for OverflowStore {
+    fn allocate(&self) -> Result<NonNull<Page>> {
+        self.memory
+            .allocate()
+            .or_else(|err| self.overflow.allocate().ok_or(err))
+    }
+
+    unsafe fn release(&self, page: NonNull<Page>) {
+        match self.overflow.index_of(page) {
+            Some(index) => self.overflow.release(index),
+            None => unsafe { self.memory.release(page) },
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.memory.capacity() + self.overflow.capacity
+    }
+
+    fn available(&self) -> usize {
+        self.memory.available() + self.overflow.available()
+    }
+
+    fn compact(&self) -> u64 {
+        self.memory.compact()
+    }
+
+    fn reclaimed_bytes(&self) -> u64 {
+        self.memory.reclaimed_bytes()
+    }
+}
+
+/// Pages in a shared mapping of a host file, handed out in place.
+pub(crate) struct MappedPages {
+    map: MmapMut,
+    base: NonNull<Page>,
+    pub capacity: usize,
+
+    /// Indices of released pages, which are handed out again before the pages never handed out.
+    free: ArrayQueue<usize>,
+
+    /// Number of pages ever handed out.
+    next: AtomicUsize,
+}
+
+// Pages are only reached through the pointers handed out, whose accesses files synchronize themselves.
+unsafe impl Send for MappedPages {}
+unsafe impl Sync for MappedPages {}
+
+impl MappedPages {
+    /// Maps `capacity` pages of `file` from `offset`, which the file must already cover.
+    pub fn map(file: &File, offset: u64, capacity: usize) -> io::Result<Self> {
+        let mut map = unsafe { MmapOptions::new().offset(offset).len(capacity * PAGE_SIZE).map_mut(file)? };
+        let base = NonNull::new(map.as_mut_ptr().cast()).unwrap_or(NonNull::dangling());
+
+        Ok(Self {
+            map,
+            base,
+            capacity,
+            free: ArrayQueue::new(capacity.max(1)),
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Hands out a page, zeroed first, as the file may hold anything where no page is in use.
+    pub fn allocate(&self) -> Option<NonNull<Page>> {
+        let index = self.free.pop().or_else(|| {
+            self.next
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < self.capacity).then_some(n + 1))
+                .ok()
+        })?;
+        let page = self.page(index);
+
+        unsafe { ptr::write_bytes(page.as_ptr(), 0, 1) };
+
+        Some(page)
+    }
+
+    pub fn page(&self, index: usize) -> NonNull<Page> {
+        unsafe { self.base.add(index) }
+    }
+
+    /// Index of `page` in the mapping, if it is one of its pages.
+    pub fn index_of(&self, page: NonNull<Page>) -> Option<usize> {
+        let offset = (page.as_ptr() as usize).wrapping_sub(self.base.as_ptr() as usize);
+
+        (offset < self.capacity * PAGE_SIZE).then_some(offset / PAGE_SIZE)
+    }
+
+    /// Hands out the page at `index` again.
+    pub fn release(&self, index: usize) {
+        // At most every page handed out is free, which the queue has room for.
+        let _ = self.free.push(index);
+    }
+
+    /// Takes the pages at the indices in `used` out of the ones handed out, as if they were already.
+    pub fn reserve(&mut self, used: &[usize]) {
+        let mut in_use = vec![false; self.capacity];
+        used.iter().for_each(|&index| in_use[index] = true);
+
+        self.free = ArrayQueue::new(self.capacity.max(1));
+        self.next = AtomicUsize::new(self.capacity);
+
+        for index in (0..self.capacity).filter(|&index| !in_use[index]) {
+            let _ = self.free.push(index);
+        }
+    }
+
+    pub fn available(&self) -> usize {
+        self.capacity - self.next.load(Ordering::Acquire) + self.free.len()
+    }
+
+    /// Writes the pages back to the file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+}
+
+/// Result of a single compaction run.
+#[derive(Clone, Debug, Default)]
+pub struct CompactionReport {
+    pub reclaimed_bytes: u64,
+}
@@ -0,0 +1,389 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+};
+
+use crate::memfs::{CurrentDirectory, MemFS, MemFSNode};
+use crate::readdir::ReadDirEntry;
+use crate::utils::{AT_FDCWD, AtFlag, MemFSErr, OpenFlag, Result, SeekFlag, Stat};
+
+/// File creation mask of a new process.
+pub const DEFAULT_UMASK: u32 = 0o022;
+
+/// User and group a process acts as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Credentials {
+    /// The superuser, as which calls made directly on a [MemFS] act.
+    pub const ROOT: Self = Self { uid: 0, gid: 0 };
+
+    pub fn new(uid: u32, gid: u32) -> Self {
+        Self { uid, gid }
+    }
+}
+
+/// Identifier of calls made directly on a [MemFS], which act as a single process.
+pub const MAIN_PID: u64 = 0;
+
+fn next_pid() -> u64 {
+    static NEXT_PID: AtomicU64 = AtomicU64::new(MAIN_PID + 1);
+
+    NEXT_PID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Open file description: a descriptor of the [MemFS], with its offset and flags, shared by the descriptors
+/// of processes referring to it, and closed once the last of them is.
+struct OpenFileDescription {
+    fs: Arc<MemFS>,
+    fd: usize,
+}
+
+impl Drop for OpenFileDescription {
+    fn drop(&mut self) {
+        let _ = self.fs.close(self.fd);
+    }
+}
+
+/// State of a process, seen by the calls made through its handle instead of the state of the [MemFS].
+pub(crate) struct ProcessState {
+    pid: u64,
+    cwd: Mutex<CurrentDirectory>,
+
+    /// Directory set with [MemFSProcess::chroot], if any, which absolute paths resolve from instead of the root.
+    root: Mutex<Option<MemFSNode>>,
+    umask: AtomicU32,
+    credentials: Mutex<Credentials>,
+
+    /// Descriptor table of the process, by descriptor number.
+    descriptors: Mutex<BTreeMap<usize, Arc<OpenFileDescription>>>,
+}
+
+impl ProcessState {
+    pub fn pid(&self) -> u64 {
+        self.pid
+    }
+
+    pub fn cwd(&self) -> CurrentDirectory {
+        self.cwd.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn set_cwd(&self, cwd: CurrentDirectory) {
+        *self.cwd.lock().unwrap_or_else(PoisonError::into_inner) = cwd;
+    }
+
+    pub fn root(&self) -> Option<MemFSNode> {
+        self.root.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub fn set_root(&self, root: MemFSNode) {
+        *self.root.lock().unwrap_or_else(PoisonError::into_inner) = Some(root);
+    }
+
+    pub fn umask(&self) -> u32 {
+        self.umask.load(Ordering::Acquire)
+    }
+
+    pub fn credentials(&self) -> Credentials {
+        *self.credentials.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn descriptors(&self) -> BTreeMap<usize, Arc<OpenFileDescription>> {
+        self.descriptors.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn description(&self, fd: usize) -> Result<Arc<OpenFileDescription>> {
+        let descriptors = self.descriptors.lock().unwrap_or_else(PoisonError::into_inner);
+
+        descriptors.get(&fd).cloned().ok_or(MemFSErr::bad_file_descriptor())
+    }
+
+    /// Refers to `description` with the lowest number not in use in the table, and returns it.
+    fn install(&self, description: Arc<OpenFileDescription>) -> usize {
+        let mut descriptors = self.descriptors.lock().unwrap_or_else(PoisonError::into_inner);
+        let fd = descriptors
+            .keys()
+            .enumerate()
+            .find(|(number, fd)| number != *fd)
+            .map_or(descriptors.len(), |(number, _)| number);
+        descriptors.insert(fd, description);
+
+        fd
+    }
+}
+
+thread_local! {
+    /// Process running a call on this thread, with the address of the file system it runs on.
+    static ACTIVE_PROCESS: RefCell<Option<(usize, Arc<ProcessState>)>> = const { RefCell::new(None) };
+}
+
+/// Returns the process running a call of `fs` on this thread, if any.
+pub(crate) fn active_process(fs: &MemFS) -> Option<Arc<ProcessState>> {
+    ACTIVE_PROCESS.with(|active| match &*active.borrow() {
+        Some((key, state)) if *key == fs as *const MemFS as usize => Some(state.clone()),
+        _ => None,
+    })
+}
+
+/// Restores the process which was active before [MemFSProcess::run].
+struct ActiveScope {
+    previous: Option<(usize, Arc<ProcessState>)>,
+}
+
+impl Drop for ActiveScope {
+    fn drop(&mut self) {
+        ACTIVE_PROCESS.with(|active| *active.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Simulated process operating on a shared [MemFS], with a working directory, a file creation mask,
+/// credentials and a file descriptor table of its own.
+///
+/// Calls made through the handle resolve relative paths from the working directory of the process,
+/// and absolute ones from its root directory, see [MemFSProcess::chroot]. Descriptors they take and return
+/// are numbers of the table of the process, each referring to an open file description, which descriptors
+/// of several processes can share, see [MemFSProcess::pass_fd]. Everything else is shared with the other
+/// processes and with calls made directly on the MemFS, which take descriptors of the MemFS, as do calls
+/// made within [MemFSProcess::run]; see [MemFSProcess::raw_fd]. Clones of a handle are the same process.
+#[derive(Clone)]
+pub struct MemFSProcess {
+    fs: Arc<MemFS>,
+    state: Arc<ProcessState>,
+}
+
+impl MemFSProcess {
+    /// Starts a process at the root directory, acting as [Credentials::ROOT] with [DEFAULT_UMASK].
+    pub fn new(fs: Arc<MemFS>) -> Self {
+        Self::with_credentials(fs, Credentials::ROOT)
+    }
+
+    /// Same as [MemFSProcess::new], acting as `credentials`.
+    pub fn with_credentials(fs: Arc<MemFS>, credentials: Credentials) -> Self {
+        let state = ProcessState {
+            pid: next_pid(),
+            cwd: Mutex::new(fs.root_directory()),
+            root: Mutex::new(None),
+            umask: AtomicU32::new(DEFAULT_UMASK),
+            credentials: Mutex::new(credentials),
+            descriptors: Mutex::default(),
+        };
+
+        Self {
+            fs,
+            state: Arc::new(state),
+        }
+    }
+
+    /// Starts another process with a copy of the working directory, root directory, file creation mask, credentials
+    /// and descriptor table of this one, whose descriptors share their open file descriptions with those of this one.
+    pub fn fork(&self) -> Self {
+        let state = ProcessState {
+            pid: next_pid(),
+            cwd: Mutex::new(self.state.cwd()),
+            root: Mutex::new(self.state.root()),
+            umask: AtomicU32::new(self.state.umask()),
+            credentials: Mutex::new(self.credentials()),
+            descriptors: Mutex::new(self.state.descriptors()),
+        };
+
+        Self {
+            fs: self.fs.clone(),
+            state: Arc::new(state),
+        }
+    }
+
+    pub fn fs(&self) -> &Arc<MemFS> {
+        &self.fs
+    }
+
+    /// Identifier of the process, unique among the processes of every file system, and never [MAIN_PID].
+    pub fn pid(&self) -> u64 {
+        self.state.pid
+    }
+
+    pub fn credentials(&self) -> Credentials {
+        self.state.credentials()
+    }
+
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.state.credentials.lock().unwrap_or_else(PoisonError::into_inner) = credentials;
+    }
+
+    /// Sets the file creation mask to the permission bits of `mask`, and returns the previous one.
+    pub fn umask(&self, mask: u32) -> u32 {
+        self.state.umask.swap(mask & 0o777, Ordering::AcqRel)
+    }
+
+    /// Runs `f` as this process, so that calls it makes on the file system see the state of the process.
+    pub fn run<R>(&self, f: impl FnOnce(&MemFS) -> R) -> R {
+        let key = Arc::as_ptr(&self.fs) as usize;
+        let previous = ACTIVE_PROCESS.with(|active| active.replace(Some((key, self.state.clone()))));
+        let _scope = ActiveScope { previous };
+
+        f(&self.fs)
+    }
+
+    pub fn chdir(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.chdir(path))
+    }
+
+    pub fn getcwd(&self) -> Result<String> {
+        self.run(|fs| fs.getcwd())
+    }
+
+    /// Confines the process to the directory `path`, as `chroot` followed by `chdir("/")`: absolute paths, symbolic
+    /// links to them included, resolve from it, and `..` never goes above it, so that code run as the process cannot
+    /// reach other files but through descriptors opened before. Paths reported by [MemFSProcess::getcwd] and
+    /// [MemFSProcess::canonicalize] are relative to it.
+    ///
+    /// Fails as [MemFS::chdir] does, and with EPERM on file systems built with
+    /// [crate::memfs::MemFSBuilder::permission_checks] unless the process acts as [Credentials::ROOT].
+    pub fn chroot(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.chroot(&self.state, path))
+    }
+
+    /// Descriptor of the MemFS which the descriptor `fd` of the process refers to, to be passed to calls
+    /// made directly on the MemFS or within [MemFSProcess::run]. It stays open while `fd` is.
+    ///
+    /// Fails with EBADF if `fd` is not open in the process.
+    pub fn raw_fd(&self, fd: usize) -> Result<usize> {
+        self.state.description(fd).map(|description| description.fd)
+    }
+
+    /// Opens a descriptor of the process referring to the same open file description as `fd`, as `dup` does:
+    /// both share the offset and the flags, and the description is closed with the last of them.
+    pub fn dup(&self, fd: usize) -> Result<usize> {
+        Ok(self.state.install(self.state.description(fd)?))
+    }
+
+    /// Hands the open file description `fd` refers to over to the process `to`, as a descriptor sent with
+    /// SCM_RIGHTS over a Unix socket: `to` gets a descriptor of its own, which is returned, sharing the offset
+    /// and the flags with `fd`. Both stay open until closed by their own process.
+    ///
+    /// Fails with EBADF if `fd` is not open in this process, and EINVAL if `to` runs on another file system.
+    pub fn pass_fd(&self, fd: usize, to: &MemFSProcess) -> Result<usize> {
+        if !Arc::ptr_eq(&self.fs, &to.fs) {
+            return Err(MemFSErr::invalid_value());
+        }
+
+        Ok(to.state.install(self.state.description(fd)?))
+    }
+
+    pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        let fd = self.run(|fs| fs.open(path, flag))?;
+
+        Ok(self.install(fd))
+    }
+
+    /// Closes the descriptor `fd` of the process, and its open file description with the last descriptor
+    /// referring to it.
+    pub fn close(&self, fd: usize) -> Result<()> {
+        let removed = self
+            .state
+            .descriptors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&fd);
+        let description = removed.ok_or(MemFSErr::bad_file_descriptor())?;
+        self.run(|_| drop(description));
+
+        Ok(())
+    }
+
+    pub fn read(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        self.run(|fs| fs.read(self.raw_fd(fd)?, buffer))
+    }
+
+    pub fn write(&self, fd: usize, data: &[u8]) -> Result<usize> {
+        self.run(|fs| fs.write(self.raw_fd(fd)?, data))
+    }
+
+    pub fn pread(&self, fd: usize, buffer: &mut [u8], offset: usize) -> Result<usize> {
+        self.run(|fs| fs.pread(self.raw_fd(fd)?, buffer, offset))
+    }
+
+    pub fn pwrite(&self, fd: usize, data: &[u8], offset: usize) -> Result<usize> {
+        self.run(|fs| fs.pwrite(self.raw_fd(fd)?, data, offset))
+    }
+
+    pub fn lseek(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        self.run(|fs| fs.lseek(self.raw_fd(fd)?, offset, flag))
+    }
+
+    pub fn mkdir(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.mkdir(path))
+    }
+
+    pub fn create_dir_all(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.create_dir_all(path))
+    }
+
+    pub fn rmdir(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.rmdir(path))
+    }
+
+    pub fn unlink(&self, path: &str) -> Result<()> {
+        self.run(|fs| fs.unlink(path))
+    }
+
+    pub fn openat(&self, dirfd: usize, path: &str, flag: OpenFlag) -> Result<usize> {
+        let dirfd = self.raw_dirfd(dirfd, path)?;
+        let fd = self.run(|fs| fs.openat(dirfd, path, flag))?;
+
+        Ok(self.install(fd))
+    }
+
+    pub fn mkdirat(&self, dirfd: usize, path: &str) -> Result<()> {
+        let dirfd = self.raw_dirfd(dirfd, path)?;
+        self.run(|fs| fs.mkdirat(dirfd, path))
+    }
+
+    pub fn unlinkat(&self, dirfd: usize, path: &str, flag: AtFlag) -> Result<()> {
+        let dirfd = self.raw_dirfd(dirfd, path)?;
+        self.run(|fs| fs.unlinkat(dirfd, path, flag))
+    }
+
+    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.run(|fs| fs.rename(old_path, new_path))
+    }
+
+    pub fn canonicalize(&self, path: &str) -> Result<String> {
+        self.run(|fs| fs.canonicalize(path))
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Stat> {
+        self.run(|fs| fs.stat(path))
+    }
+
+    pub fn access(&self, path: &str, mode: u32) -> Result<()> {
+        self.run(|fs| fs.access(path, mode))
+    }
+
+    pub fn readdir(&self, path: &str) -> Result<Vec<ReadDirEntry>> {
+        self.run(|fs| fs.readdir(path))
+    }
+
+    /// Installs the descriptor `fd` of the MemFS, just opened, in the table of the process.
+    fn install(&self, fd: usize) -> usize {
+        self.state.install(Arc::new(OpenFileDescription {
+            fs: self.fs.clone(),
+            fd,
+        }))
+    }
+
+    /// Descriptor of the MemFS to resolve `path` from in the `*at` calls, which ignore `dirfd` as [MemFS::openat] does
+    /// for absolute paths and [AT_FDCWD].
+    fn raw_dirfd(&self, dirfd: usize, path: &str) -> Result<usize> {
+        match dirfd == AT_FDCWD || path.starts_with('/') {
+            true => Ok(dirfd),
+            false => self.raw_fd(dirfd),
+        }
+    }
+}
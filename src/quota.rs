@@ -0,0 +1,158 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::utils::{MemFSErr, Result};
+
+/// Bytes each file, directory and symbolic link is charged for its metadata.
+/// A symbolic link is charged for its target on top of it.
+pub const NODE_METADATA_BYTES: u64 = 256;
+
+/// Bytes in use by a file system, as returned by [crate::memfs::MemFS::usage].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Sum of the sizes of the files, including unlinked files which are still open,
+    /// and of the space reserved past their end with [crate::memfs::MemFS::fallocate].
+    pub data_bytes: u64,
+
+    /// Bytes charged for the metadata of every file, directory and symbolic link but the root.
+    pub metadata_bytes: u64,
+
+    /// Budget set with [crate::memfs::MemFSBuilder::quota], if any.
+    pub quota: Option<u64>,
+}
+
+impl Usage {
+    pub fn total_bytes(&self) -> u64 {
+        self.data_bytes + self.metadata_bytes
+    }
+}
+
+/// Bytes in use by a file system, checked against its quota.
+#[derive(Debug)]
+pub(crate) struct Accounting {
+    quota: Option<u64>,
+    used: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Accounting {
+    pub fn new(quota: Option<u64>) -> Self {
+        Self {
+            quota,
+            used: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        }
+    }
+
+    pub fn usage(&self) -> Usage {
+        let used = self.used.load(Ordering::Acquire);
+        let data = self.data.load(Ordering::Acquire).min(used);
+
+        Usage {
+            data_bytes: data,
+            metadata_bytes: used - data,
+            quota: self.quota,
+        }
+    }
+
+    /// Charges `bytes` of metadata for a new node. The charge lasts as long as the returned [Charge].
+    pub fn charge_metadata(self: &Arc<Self>, bytes: u64) -> Result<Charge> {
+        self.reserve(bytes, true)?;
+
+        Ok(Charge {
+            accounting: self.clone(),
+            metadata: bytes,
+            data: AtomicU64::new(0),
+        })
+    }
+
+    /// Fails with ENOSPC, charging nothing, if `enforce` is set and `bytes` more would exceed the quota.
+    fn reserve(&self, bytes: u64, enforce: bool) -> Result<()> {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                let total = used.checked_add(bytes)?;
+
+                (!enforce || self.quota.is_none_or(|quota| total <= quota)).then_some(total)
+            })
+            .map(|_| ())
+            .map_err(|_| MemFSErr::no_space_left())
+    }
+
+    fn release(&self, bytes: u64) {
+        self.used.fetch_sub(bytes, Ordering::AcqRel);
+    }
+}
+
+/// Bytes charged for a node, released when the node is dropped.
+#[derive(Debug)]
+pub(crate) struct Charge {
+    accounting: Arc<Accounting>,
+    metadata: u64,
+    data: AtomicU64,
+}
+
+impl Charge {
+    /// Charges for contents growing to `bytes`. Does nothing if as many are charged already.
+    /// Fails with ENOSPC if the quota would be exceeded, charging nothing.
+    pub fn grow_data(&self, bytes: u64) -> Result<()> {
+        self.resize_data(bytes, true, true)
+    }
+
+    /// Charges for contents of exactly `bytes`, releasing bytes of contents which were cut off.
+    /// Fails with ENOSPC if the quota would be exceeded, charging nothing.
+    pub fn set_data(&self, bytes: u64) -> Result<()> {
+        self.resize_data(bytes, false, true)
+    }
+
+    /// Same as [Charge::set_data], regardless of the quota, for contents which were accepted before.
+    pub fn force_data(&self, bytes: u64) {
+        let _ = self.resize_data(bytes, false, false);
+    }
+
+    fn resize_data(&self, bytes: u64, grow_only: bool, enforce: bool) -> Result<()> {
+        let accounting = &self.accounting;
+
+        loop {
+            let charged = self.data.load(Ordering::Acquire);
+
+            if bytes == charged || (grow_only && bytes < charged) {
+                return Ok(());
+            }
+
+            if bytes > charged {
+                accounting.reserve(bytes - charged, enforce)?;
+
+                if self
+                    .data
+                    .compare_exchange(charged, bytes, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    accounting.data.fetch_add(bytes - charged, Ordering::AcqRel);
+                    return Ok(());
+                }
+
+                // Another write moved the charge first. Take the reservation back and start over.
+                accounting.release(bytes - charged);
+            } else if self
+                .data
+                .compare_exchange(charged, bytes, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                accounting.data.fetch_sub(charged - bytes, Ordering::AcqRel);
+                accounting.release(charged - bytes);
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        let data = *self.data.get_mut();
+
+        self.accounting.data.fetch_sub(data, Ordering::AcqRel);
+        self.accounting.release(self.metadata + data);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, PoisonError};
+
+use crate::utils::{MemFSErr, Result};
+
+/// Kind of a byte-range lock, as with F_RDLCK, F_WRLCK and F_UNLCK.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeLockKind {
+    /// Lets other owners take read locks on the range too, but no write lock.
+    Read,
+
+    /// Excludes every lock of other owners on the range.
+    Write,
+
+    /// Drops the locks of the owner on the range.
+    Unlock,
+}
+
+/// Byte range to lock or unlock with [crate::memfs::MemFS::setlk].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeLock {
+    pub kind: RangeLockKind,
+    pub start: u64,
+
+    /// Number of bytes, where 0 stands for every byte from `start` on, however large the file grows.
+    pub len: u64,
+}
+
+impl RangeLock {
+    pub fn new(kind: RangeLockKind, start: u64, len: u64) -> Self {
+        Self { kind, start, len }
+    }
+
+    /// Returns the end of the range, exclusive. Fails with EINVAL if it overflows.
+    fn end(&self) -> Result<u64> {
+        match self.len {
+            0 => Ok(u64::MAX),
+            len => self.start.checked_add(len).ok_or(MemFSErr::invalid_value()),
+        }
+    }
+}
+
+/// Lock which prevents another one from being taken, as reported by [crate::memfs::MemFS::getlk].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeLockConflict {
+    pub lock: RangeLock,
+
+    /// Identifier of the process holding the lock, as returned by [crate::process::MemFSProcess::pid].
+    pub pid: u64,
+}
+
+#[derive(Clone, Copy)]
+struct HeldRange {
+    owner: u64,
+    write: bool,
+    start: u64,
+    end: u64,
+}
+
+impl HeldRange {
+    fn conflicts(&self, owner: u64, write: bool, start: u64, end: u64) -> bool {
+        self.owner != owner && (self.write || write) && self.start < end && start < self.end
+    }
+
+    fn to_conflict(self) -> RangeLockConflict {
+        let kind = if self.write { RangeLockKind::Write } else { RangeLockKind::Read };
+        let len = if self.end == u64::MAX { 0 } else { self.end - self.start };
+
+        RangeLockConflict {
+            lock: RangeLock::new(kind, self.start, len),
+            pid: self.owner,
+        }
+    }
+}
+
+/// Byte-range locks of a file, held by processes.
+#[derive(Default)]
+pub(crate) struct RangeLocks {
+    held: Mutex<Vec<HeldRange>>,
+    released: Condvar,
+}
+
+impl RangeLocks {
+    /// Returns a lock of another owner which conflicts with `lock`, if any.
+    pub fn conflict(&self, owner: u64, lock: &RangeLock) -> Result<Option<RangeLockConflict>> {
+        let (start, end) = (lock.start, lock.end()?);
+        let write = match lock.kind {
+            RangeLockKind::Read => false,
+            RangeLockKind::Write => true,
+            RangeLockKind::Unlock => return Ok(None),
+        };
+        let held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+
+        Ok(held
+            .iter()
+            .find(|range| range.conflicts(owner, write, start, end))
+            .map(|range| range.to_conflict()))
+    }
+
+    /// Applies `lock` for `owner`, replacing the locks the owner holds on the range.
+    /// Without `waits`, fails with EAGAIN on a conflicting lock. Otherwise waits for conflicting locks
+    /// to go away, failing with EDEADLK if their owners wait for `owner` themselves.
+    pub fn apply(&self, owner: u64, lock: &RangeLock, waits: Option<&LockWaits>) -> Result<()> {
+        let (start, end) = (lock.start, lock.end()?);
+        let write = lock.kind == RangeLockKind::Write;
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if lock.kind != RangeLockKind::Unlock {
+            loop {
+                let blockers: HashSet<u64> = held
+                    .iter()
+                    .filter(|range| range.conflicts(owner, write, start, end))
+                    .map(|range| range.owner)
+                    .collect();
+
+                if blockers.is_empty() {
+                    break;
+                }
+
+                let Some(waits) = waits else {
+                    return Err(MemFSErr::try_again());
+                };
+
+                waits.wait_for(owner, blockers)?;
+                held = self.released.wait(held).unwrap_or_else(PoisonError::into_inner);
+                waits.done(owner);
+            }
+        }
+
+        Self::carve(&mut held, owner, start, end);
+
+        if lock.kind != RangeLockKind::Unlock {
+            held.push(HeldRange { owner, write, start, end });
+        }
+
+        self.released.notify_all();
+
+        Ok(())
+    }
+
+    /// Drops every lock of `owner`, as when the owner closes a descriptor of the file.
+    pub fn release_all(&self, owner: u64) {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = held.len();
+
+        held.retain(|range| range.owner != owner);
+
+        if held.len() != count {
+            self.released.notify_all();
+        }
+    }
+
+    pub fn clear(&self) {
+        self.held.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        self.released.notify_all();
+    }
+
+    /// Removes `start..end` from the ranges held by `owner`, splitting those which stick out of it.
+    fn carve(held: &mut Vec<HeldRange>, owner: u64, start: u64, end: u64) {
+        let mut kept = Vec::with_capacity(held.len() + 1);
+
+        for range in held.drain(..) {
+            if range.owner != owner || range.end <= start || end <= range.start {
+                kept.push(range);
+                continue;
+            }
+
+            if range.start < start {
+                kept.push(HeldRange { end: start, ..range });
+            }
+
+            if end < range.end {
+                kept.push(HeldRange { start: end, ..range });
+            }
+        }
+
+        *held = kept;
+    }
+}
+
+/// Owners waiting for range locks of a file system, with the owners they wait for, to detect deadlocks.
+#[derive(Default)]
+pub(crate) struct LockWaits {
+    waiting: Mutex<HashMap<u64, HashSet<u64>>>,
+}
+
+impl LockWaits {
+    /// Records that `owner` waits for `blockers`. Fails with EDEADLK, recording nothing,
+    /// if one of them waits for `owner`, directly or through other owners.
+    fn wait_for(&self, owner: u64, blockers: HashSet<u64>) -> Result<()> {
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut visited = HashSet::new();
+        let mut pending: Vec<u64> = blockers.iter().copied().collect();
+
+        while let Some(next) = pending.pop() {
+            if next == owner {
+                return Err(MemFSErr::deadlock());
+            }
+
+            if visited.insert(next)
+                && let Some(waited) = waiting.get(&next)
+            {
+                pending.extend(waited.iter().copied());
+            }
+        }
+
+        waiting.insert(owner, blockers);
+
+        Ok(())
+    }
+
+    fn done(&self, owner: u64) {
+        self.waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&owner);
+    }
+}
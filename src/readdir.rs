@@ -0,0 +1,40 @@
+use std::vec;
+
+use crate::utils::FileType;
+
+/// Name and type of an entry of a directory, returned by [crate::memfs::MemFS::readdir].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadDirEntry {
+    pub name: String,
+    pub file_type: FileType,
+}
+
+/// Iterator over the entries of a directory, returned by [crate::memfs::MemFS::readdir_iter].
+///
+/// The entries are taken when the iterator is created, so that no lock of the directory is held
+/// while iterating. Entries created afterwards are not returned, and removed ones still are.
+pub struct ReadDir {
+    entries: vec::IntoIter<ReadDirEntry>,
+}
+
+impl ReadDir {
+    pub(crate) fn new(entries: Vec<ReadDirEntry>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+        }
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = ReadDirEntry;
+
+    fn next(&mut self) -> Option<ReadDirEntry> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ReadDir {}
@@ -0,0 +1,48 @@
+use std::{panic, thread::JoinHandle};
+
+/// Number of nodes reclaimed by a recursive removal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RemovalReport {
+    /// Removed directories, including the removed directory itself.
+    pub directories: usize,
+    pub files: usize,
+}
+
+/// Completion handle of [crate::memfs::MemFS::remove_dir_all_lazy].
+///
+/// The subtree is no longer reachable when the handle is returned; the handle only tracks
+/// the reclamation of its nodes. Dropping the handle lets the reclamation finish on its own.
+pub struct RemovalHandle {
+    thread: JoinHandle<RemovalReport>,
+}
+
+impl RemovalHandle {
+    pub(crate) fn new(thread: JoinHandle<RemovalReport>) -> Self {
+        Self { thread }
+    }
+
+    /// Returns true once every node of the subtree was reclaimed.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Blocks until every node of the subtree was reclaimed.
+    pub fn wait(self) -> RemovalReport {
+        match self.thread.join() {
+            Ok(report) => report,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+/// How [crate::memfs::MemFS::detach_with] hands files over to the detached file system.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DetachMode {
+    /// Files are moved without copying their contents, and both file systems share one memory pool.
+    /// Descriptors opened on the files before stay open and see the changes made through the detached file system.
+    #[default]
+    Share,
+
+    /// Files are copied into a file system with a memory pool of its own.
+    Copy,
+}
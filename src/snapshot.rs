@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::memfs::{MemFS, MemFSNode};
+use crate::permission::NodeAttributes;
+use crate::utils::{OpenFlag, Result, SeekFlag};
+
+/// Identifier of a snapshot taken by [MemFS::take_snapshot].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SnapshotId(pub(crate) usize);
+
+/// Point-in-time copy of a whole [MemFS], taken by [MemFS::snapshot] and brought back by [MemFS::restore].
+///
+/// Only the directories are copied when it is taken, while mutations are blocked. Files are shared with
+/// the live file system: their permission bits, owners and times are copied aside when it is taken, and the
+/// contents a file had at the time of the snapshot are copied aside as a whole by its first write after it,
+/// however small that write is. Clones share everything.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub(crate) root: MemFSNode,
+    pub(crate) saved: Arc<SavedContents>,
+}
+
+impl Snapshot {
+    /// Opens the snapshot as an independent read-only file system.
+    pub fn view(&self) -> MemFSView {
+        MemFSView::new(MemFS::snapshot_view(self))
+    }
+}
+
+/// Contents of files of a snapshot which were written after it was taken, and attributes of every file
+/// of it as of when it was taken, by address of the file.
+#[derive(Default)]
+pub(crate) struct SavedContents {
+    files: Mutex<HashMap<usize, Arc<Vec<u8>>>>,
+    attributes: Mutex<HashMap<usize, NodeAttributes>>,
+}
+
+impl SavedContents {
+    pub fn save(&self, file: usize, contents: Arc<Vec<u8>>) {
+        self.files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(file, contents);
+    }
+
+    pub fn get(&self, file: usize) -> Option<Arc<Vec<u8>>> {
+        self.files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&file)
+            .cloned()
+    }
+
+    pub fn save_attributes(&self, file: usize, attributes: NodeAttributes) {
+        self.attributes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(file, attributes);
+    }
+
+    pub fn attributes(&self, file: usize) -> Option<NodeAttributes> {
+        self.attributes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&file)
+            .cloned()
+    }
+}
+
+/// Read-only file system opened from a snapshot.
+///
+/// It has its own working directory and file descriptors, so it can be used side by side
+/// with the live file system the snapshot was taken from.
+pub struct MemFSView {
+    fs: MemFS,
+}
+
+impl MemFSView {
+    pub(crate) fn new(fs: MemFS) -> Self {
+        Self { fs }
+    }
+
+    /// Opens a file of the snapshot. Fails with EROFS unless `flag` is O_RDONLY.
+    pub fn open(&self, path: &str, flag: OpenFlag) -> Result<usize> {
+        self.fs.open(path, flag)
+    }
+
+    pub fn close(&self, fd: usize) -> Result<()> {
+        self.fs.close(fd)
+    }
+
+    pub fn read(&self, fd: usize, buffer: &mut [u8]) -> Result<usize> {
+        self.fs.read(fd, buffer)
+    }
+
+    pub fn lseek(&self, fd: usize, offset: i64, flag: SeekFlag) -> Result<usize> {
+        self.fs.lseek(fd, offset, flag)
+    }
+
+    pub fn chdir(&self, path: &str) -> Result<()> {
+        self.fs.chdir(path)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::clock;
+use crate::utils::{MemFSErr, Result};
+
+/// Times of a file or directory, kept as nanoseconds since the Unix epoch so that they are updated without locking.
+#[derive(Debug)]
+pub(crate) struct Timestamps {
+    accessed: AtomicU64,
+    modified: AtomicU64,
+    status_changed: AtomicU64,
+    created: AtomicU64,
+}
+
+impl Timestamps {
+    /// Stamps every time with the current time.
+    pub fn new() -> Self {
+        let now = now_nanos();
+
+        Self {
+            accessed: AtomicU64::new(now),
+            modified: AtomicU64::new(now),
+            status_changed: AtomicU64::new(now),
+            created: AtomicU64::new(now),
+        }
+    }
+
+    pub fn accessed(&self) -> SystemTime {
+        from_nanos(self.accessed.load(Ordering::Acquire))
+    }
+
+    pub fn modified(&self) -> SystemTime {
+        from_nanos(self.modified.load(Ordering::Acquire))
+    }
+
+    pub fn status_changed(&self) -> SystemTime {
+        from_nanos(self.status_changed.load(Ordering::Acquire))
+    }
+
+    pub fn created(&self) -> SystemTime {
+        from_nanos(self.created.load(Ordering::Acquire))
+    }
+
+    /// Contents were read.
+    pub fn touch_access(&self) {
+        self.accessed.store(now_nanos(), Ordering::Release);
+    }
+
+    /// Contents of a file, or entries of a directory, were modified, which changes the status as well.
+    pub fn touch_modify(&self) {
+        let now = now_nanos();
+
+        self.modified.store(now, Ordering::Release);
+        self.status_changed.store(now, Ordering::Release);
+    }
+
+    /// Metadata, such as the mode or the owner, changed.
+    pub fn touch_status(&self) {
+        self.status_changed.store(now_nanos(), Ordering::Release);
+    }
+
+    /// Sets the access and modification times, leaving those which are `None` unchanged.
+    /// Fails with EINVAL on a time before the Unix epoch, leaving both unchanged.
+    pub fn set(&self, accessed: Option<SystemTime>, modified: Option<SystemTime>) -> Result<()> {
+        let accessed = accessed.map(to_nanos).transpose()?;
+        let modified = modified.map(to_nanos).transpose()?;
+
+        if let Some(accessed) = accessed {
+            self.accessed.store(accessed, Ordering::Release);
+        }
+
+        if let Some(modified) = modified {
+            self.modified.store(modified, Ordering::Release);
+        }
+
+        self.touch_status();
+
+        Ok(())
+    }
+}
+
+impl Clone for Timestamps {
+    fn clone(&self) -> Self {
+        Self {
+            accessed: AtomicU64::new(self.accessed.load(Ordering::Acquire)),
+            modified: AtomicU64::new(self.modified.load(Ordering::Acquire)),
+            status_changed: AtomicU64::new(self.status_changed.load(Ordering::Acquire)),
+            created: AtomicU64::new(self.created.load(Ordering::Acquire)),
+        }
+    }
+}
+
+/// Current time, as stamped on files.
+pub(crate) fn now() -> SystemTime {
+    from_nanos(now_nanos())
+}
+
+fn now_nanos() -> u64 {
+    to_nanos(clock::now()).unwrap_or(0)
+}
+
+fn to_nanos(time: SystemTime) -> Result<u64> {
+    let since_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| MemFSErr::invalid_value())?;
+
+    u64::try_from(since_epoch.as_nanos()).map_err(|_| MemFSErr::invalid_value())
+}
+
+fn from_nanos(nanos: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos)
+}
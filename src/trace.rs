@@ -0,0 +1,1058 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::IoSlice,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::memfs::MemFS;
+use crate::metrics::MemFSOp;
+use crate::utils::{AT_FDCWD, FallocateMode, MemFSErr, OpenFlag, RenameFlag, Result, SeekFlag};
+
+/// Arguments of a system call, borrowed from the caller.
+#[derive(Clone, Copy)]
+pub(crate) enum SyscallArgs<'a> {
+    Open { path: &'a str, flag: u32 },
+    Close { fd: usize },
+    Unlink { path: &'a str },
+    Read { fd: usize, size: usize },
+    Write { fd: usize, data: &'a [u8] },
+    Lseek { fd: usize, offset: i64, flag: SeekFlag },
+    Mkdir { path: &'a str },
+    Rmdir { path: &'a str },
+    Chdir { path: &'a str },
+    Fsync { fd: usize },
+    Rename { old: &'a str, new: &'a str },
+    Truncate { path: &'a str, len: usize },
+    Ftruncate { fd: usize, len: usize },
+    Pread { fd: usize, size: usize, offset: usize },
+    Pwrite { fd: usize, offset: usize, data: &'a [u8] },
+    Link { existing: &'a str, new: &'a str },
+    Symlink { target: &'a str, link: &'a str },
+    Chmod { path: &'a str, mode: u32 },
+    Fchmod { fd: usize, mode: u32 },
+    Chown { path: &'a str, uid: Option<u32>, gid: Option<u32> },
+    Fchown { fd: usize, uid: Option<u32>, gid: Option<u32> },
+    Utimens { path: &'a str, atime: Option<SystemTime>, mtime: Option<SystemTime> },
+    Fallocate { fd: usize, mode: FallocateMode, offset: usize, len: usize },
+    CopyFileRange { fd_in: usize, off_in: Option<usize>, fd_out: usize, off_out: Option<usize>, len: usize },
+    Reflink { source: &'a str, target: &'a str },
+    Append { fd: usize, data: &'a [u8] },
+    Linkat { fd: usize, new: &'a str },
+    Renameat2 { old: &'a str, new: &'a str, flag: u32 },
+}
+
+impl SyscallArgs<'_> {
+    pub fn op(&self) -> MemFSOp {
+        match self {
+            SyscallArgs::Open { .. } => MemFSOp::Open,
+            SyscallArgs::Close { .. } => MemFSOp::Close,
+            SyscallArgs::Unlink { .. } => MemFSOp::Unlink,
+            SyscallArgs::Read { .. } => MemFSOp::Read,
+            SyscallArgs::Write { .. } => MemFSOp::Write,
+            SyscallArgs::Lseek { .. } => MemFSOp::Lseek,
+            SyscallArgs::Mkdir { .. } => MemFSOp::Mkdir,
+            SyscallArgs::Rmdir { .. } => MemFSOp::Rmdir,
+            SyscallArgs::Chdir { .. } => MemFSOp::Chdir,
+            SyscallArgs::Fsync { .. } => MemFSOp::Fsync,
+            SyscallArgs::Rename { .. } => MemFSOp::Rename,
+            SyscallArgs::Truncate { .. } => MemFSOp::Truncate,
+            SyscallArgs::Ftruncate { .. } => MemFSOp::Ftruncate,
+            SyscallArgs::Pread { .. } => MemFSOp::Pread,
+            SyscallArgs::Pwrite { .. } => MemFSOp::Pwrite,
+            SyscallArgs::Link { .. } => MemFSOp::Link,
+            SyscallArgs::Symlink { .. } => MemFSOp::Symlink,
+            SyscallArgs::Chmod { .. } => MemFSOp::Chmod,
+            SyscallArgs::Fchmod { .. } => MemFSOp::Fchmod,
+            SyscallArgs::Chown { .. } => MemFSOp::Chown,
+            SyscallArgs::Fchown { .. } => MemFSOp::Fchown,
+            SyscallArgs::Utimens { .. } => MemFSOp::Utimens,
+            SyscallArgs::Fallocate { .. } => MemFSOp::Fallocate,
+            SyscallArgs::CopyFileRange { .. } => MemFSOp::CopyFileRange,
+            SyscallArgs::Reflink { .. } => MemFSOp::Reflink,
+            SyscallArgs::Append { .. } => MemFSOp::Append,
+            SyscallArgs::Linkat { .. } => MemFSOp::Linkat,
+            SyscallArgs::Renameat2 { .. } => MemFSOp::Renameat2,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            SyscallArgs::Open { path, .. }
+            | SyscallArgs::Unlink { path }
+            | SyscallArgs::Mkdir { path }
+            | SyscallArgs::Rmdir { path }
+            | SyscallArgs::Chdir { path }
+            | SyscallArgs::Rename { old: path, .. }
+            | SyscallArgs::Renameat2 { old: path, .. }
+            | SyscallArgs::Truncate { path, .. }
+            | SyscallArgs::Chmod { path, .. }
+            | SyscallArgs::Chown { path, .. }
+            | SyscallArgs::Utimens { path, .. }
+            | SyscallArgs::Link { existing: path, .. }
+            | SyscallArgs::Linkat { new: path, .. }
+            | SyscallArgs::Reflink { source: path, .. }
+            | SyscallArgs::Symlink { link: path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn fd(&self) -> Option<usize> {
+        match self {
+            SyscallArgs::Close { fd }
+            | SyscallArgs::Fsync { fd }
+            | SyscallArgs::Read { fd, .. }
+            | SyscallArgs::Write { fd, .. }
+            | SyscallArgs::Append { fd, .. }
+            | SyscallArgs::Linkat { fd, .. }
+            | SyscallArgs::Lseek { fd, .. }
+            | SyscallArgs::Ftruncate { fd, .. }
+            | SyscallArgs::Pread { fd, .. }
+            | SyscallArgs::Pwrite { fd, .. }
+            | SyscallArgs::Fchmod { fd, .. }
+            | SyscallArgs::Fchown { fd, .. }
+            | SyscallArgs::Fallocate { fd, .. }
+            | SyscallArgs::CopyFileRange { fd_out: fd, .. } => Some(*fd),
+            _ => None,
+        }
+    }
+
+    fn to_call(self) -> TraceCall {
+        match self {
+            SyscallArgs::Open { path, flag } => TraceCall::Open {
+                path: path.to_string(),
+                flag,
+            },
+            SyscallArgs::Close { fd } => TraceCall::Close { fd },
+            SyscallArgs::Unlink { path } => TraceCall::Unlink {
+                path: path.to_string(),
+            },
+            SyscallArgs::Read { fd, size } => TraceCall::Read { fd, size },
+            SyscallArgs::Write { fd, data } => TraceCall::Write {
+                fd,
+                data: data.to_vec(),
+            },
+            SyscallArgs::Lseek { fd, offset, flag } => TraceCall::Lseek { fd, offset, flag },
+            SyscallArgs::Mkdir { path } => TraceCall::Mkdir {
+                path: path.to_string(),
+            },
+            SyscallArgs::Rmdir { path } => TraceCall::Rmdir {
+                path: path.to_string(),
+            },
+            SyscallArgs::Chdir { path } => TraceCall::Chdir {
+                path: path.to_string(),
+            },
+            SyscallArgs::Fsync { fd } => TraceCall::Fsync { fd },
+            SyscallArgs::Rename { old, new } => TraceCall::Rename {
+                old: old.to_string(),
+                new: new.to_string(),
+            },
+            SyscallArgs::Truncate { path, len } => TraceCall::Truncate {
+                path: path.to_string(),
+                len,
+            },
+            SyscallArgs::Ftruncate { fd, len } => TraceCall::Ftruncate { fd, len },
+            SyscallArgs::Pread { fd, size, offset } => TraceCall::Pread { fd, size, offset },
+            SyscallArgs::Pwrite { fd, offset, data } => TraceCall::Pwrite {
+                fd,
+                offset,
+                data: data.to_vec(),
+            },
+            SyscallArgs::Link { existing, new } => TraceCall::Link {
+                existing: existing.to_string(),
+                new: new.to_string(),
+            },
+            SyscallArgs::Symlink { target, link } => TraceCall::Symlink {
+                target: target.to_string(),
+                link: link.to_string(),
+            },
+            SyscallArgs::Chmod { path, mode } => TraceCall::Chmod {
+                path: path.to_string(),
+                mode,
+            },
+            SyscallArgs::Fchmod { fd, mode } => TraceCall::Fchmod { fd, mode },
+            SyscallArgs::Chown { path, uid, gid } => TraceCall::Chown {
+                path: path.to_string(),
+                uid,
+                gid,
+            },
+            SyscallArgs::Fchown { fd, uid, gid } => TraceCall::Fchown { fd, uid, gid },
+            SyscallArgs::Utimens { path, atime, mtime } => TraceCall::Utimens {
+                path: path.to_string(),
+                atime,
+                mtime,
+            },
+            SyscallArgs::Fallocate { fd, mode, offset, len } => TraceCall::Fallocate { fd, mode, offset, len },
+            SyscallArgs::CopyFileRange { fd_in, off_in, fd_out, off_out, len } => TraceCall::CopyFileRange {
+                fd_in,
+                off_in,
+                fd_out,
+                off_out,
+                len,
+            },
+            SyscallArgs::Reflink { source, target } => TraceCall::Reflink {
+                source: source.to_string(),
+                target: target.to_string(),
+            },
+            SyscallArgs::Append { fd, data } => TraceCall::Append {
+                fd,
+                data: data.to_vec(),
+            },
+            SyscallArgs::Linkat { fd, new } => TraceCall::Linkat {
+                fd,
+                new: new.to_string(),
+            },
+            SyscallArgs::Renameat2 { old, new, flag } => TraceCall::Renameat2 {
+                old: old.to_string(),
+                new: new.to_string(),
+                flag,
+            },
+        }
+    }
+}
+
+/// Recorded system call with every argument needed to run it again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceCall {
+    Open { path: String, flag: u32 },
+    Close { fd: usize },
+    Unlink { path: String },
+    Read { fd: usize, size: usize },
+    Write { fd: usize, data: Vec<u8> },
+    Lseek { fd: usize, offset: i64, flag: SeekFlag },
+    Mkdir { path: String },
+    Rmdir { path: String },
+    Chdir { path: String },
+    Fsync { fd: usize },
+    Rename { old: String, new: String },
+    Truncate { path: String, len: usize },
+    Ftruncate { fd: usize, len: usize },
+    Pread { fd: usize, size: usize, offset: usize },
+    Pwrite { fd: usize, offset: usize, data: Vec<u8> },
+    Link { existing: String, new: String },
+    Symlink { target: String, link: String },
+    Chmod { path: String, mode: u32 },
+    Fchmod { fd: usize, mode: u32 },
+    Chown { path: String, uid: Option<u32>, gid: Option<u32> },
+    Fchown { fd: usize, uid: Option<u32>, gid: Option<u32> },
+    Utimens { path: String, atime: Option<SystemTime>, mtime: Option<SystemTime> },
+    Fallocate { fd: usize, mode: FallocateMode, offset: usize, len: usize },
+    CopyFileRange { fd_in: usize, off_in: Option<usize>, fd_out: usize, off_out: Option<usize>, len: usize },
+    Reflink { source: String, target: String },
+    Append { fd: usize, data: Vec<u8> },
+    Linkat { fd: usize, new: String },
+    Renameat2 { old: String, new: String, flag: u32 },
+}
+
+impl TraceCall {
+    pub fn op(&self) -> MemFSOp {
+        match self {
+            TraceCall::Open { .. } => MemFSOp::Open,
+            TraceCall::Close { .. } => MemFSOp::Close,
+            TraceCall::Unlink { .. } => MemFSOp::Unlink,
+            TraceCall::Read { .. } => MemFSOp::Read,
+            TraceCall::Write { .. } => MemFSOp::Write,
+            TraceCall::Lseek { .. } => MemFSOp::Lseek,
+            TraceCall::Mkdir { .. } => MemFSOp::Mkdir,
+            TraceCall::Rmdir { .. } => MemFSOp::Rmdir,
+            TraceCall::Chdir { .. } => MemFSOp::Chdir,
+            TraceCall::Fsync { .. } => MemFSOp::Fsync,
+            TraceCall::Rename { .. } => MemFSOp::Rename,
+            TraceCall::Truncate { .. } => MemFSOp::Truncate,
+            TraceCall::Ftruncate { .. } => MemFSOp::Ftruncate,
+            TraceCall::Pread { .. } => MemFSOp::Pread,
+            TraceCall::Pwrite { .. } => MemFSOp::Pwrite,
+            TraceCall::Link { .. } => MemFSOp::Link,
+            TraceCall::Symlink { .. } => MemFSOp::Symlink,
+            TraceCall::Chmod { .. } => MemFSOp::Chmod,
+            TraceCall::Fchmod { .. } => MemFSOp::Fchmod,
+            TraceCall::Chown { .. } => MemFSOp::Chown,
+            TraceCall::Fchown { .. } => MemFSOp::Fchown,
+            TraceCall::Utimens { .. } => MemFSOp::Utimens,
+            TraceCall::Fallocate { .. } => MemFSOp::Fallocate,
+            TraceCall::CopyFileRange { .. } => MemFSOp::CopyFileRange,
+            TraceCall::Reflink { .. } => MemFSOp::Reflink,
+            TraceCall::Append { .. } => MemFSOp::Append,
+            TraceCall::Linkat { .. } => MemFSOp::Linkat,
+            TraceCall::Renameat2 { .. } => MemFSOp::Renameat2,
+        }
+    }
+}
+
+/// Outcome of a recorded system call. Errors are kept as the name of their [crate::utils::MemFSErrType].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceResult {
+    Ok(Option<usize>),
+    Err(String),
+}
+
+impl TraceResult {
+    fn from_outcome(outcome: std::result::Result<Option<usize>, &MemFSErr>) -> Self {
+        match outcome {
+            Ok(value) => TraceResult::Ok(value),
+            Err(e) => TraceResult::Err(format!("{:?}", e.err_type)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// Position of the call in the order calls were started.
+    pub seq: u64,
+
+    /// Identifier of the calling thread, numbered in the order threads made their first call.
+    pub thread: u64,
+
+    /// Start of the call, measured from the creation of the recorder.
+    pub start: Duration,
+    pub duration: Duration,
+    pub call: TraceCall,
+    pub result: TraceResult,
+}
+
+pub(crate) struct TraceTicket {
+    seq: u64,
+    thread: u64,
+    started: Instant,
+}
+
+struct RecorderState {
+    origin: Instant,
+    next_seq: AtomicU64,
+    threads: Mutex<HashMap<ThreadId, u64>>,
+    events: Mutex<Vec<TraceEvent>>,
+}
+
+/// Records every system call made on a MemFS, see [MemFS::set_trace_recorder].
+/// Clones share the same trace.
+#[derive(Clone)]
+pub struct TraceRecorder {
+    state: Arc<RecorderState>,
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RecorderState {
+                origin: Instant::now(),
+                next_seq: AtomicU64::new(0),
+                threads: Mutex::default(),
+                events: Mutex::default(),
+            }),
+        }
+    }
+
+    pub(crate) fn begin(&self) -> TraceTicket {
+        let thread = {
+            let mut threads = self.state.threads.lock().unwrap();
+            let next = threads.len() as u64;
+
+            *threads.entry(thread::current().id()).or_insert(next)
+        };
+
+        TraceTicket {
+            seq: self.state.next_seq.fetch_add(1, Ordering::SeqCst),
+            thread,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(
+        &self,
+        ticket: TraceTicket,
+        args: SyscallArgs,
+        outcome: std::result::Result<Option<usize>, &MemFSErr>,
+    ) {
+        let event = TraceEvent {
+            seq: ticket.seq,
+            thread: ticket.thread,
+            start: ticket.started.duration_since(self.state.origin),
+            duration: ticket.started.elapsed(),
+            call: args.to_call(),
+            result: TraceResult::from_outcome(outcome),
+        };
+
+        self.state.events.lock().unwrap().push(event);
+    }
+
+    /// Returns the calls recorded so far, ordered by start.
+    pub fn trace(&self) -> Trace {
+        let mut events = self.state.events.lock().unwrap().clone();
+        events.sort_by_key(|e| e.seq);
+
+        Trace { events }
+    }
+}
+
+/// How [Trace::replay] schedules recorded calls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Every call runs on the calling thread, one after another in the order they were started.
+    Sequential,
+
+    /// Each recorded thread gets its own thread, and calls are started in the recorded order.
+    /// Calls which overlapped in the recording can overlap again.
+    Interleaved,
+}
+
+/// Recorded call whose replayed result differs from the recorded one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayMismatch {
+    pub seq: u64,
+    pub expected: TraceResult,
+    pub actual: TraceResult,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+impl ReplayReport {
+    /// Returns true if every call had the same result as in the recording.
+    pub fn is_faithful(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Complete trace of system calls, which can be saved as text and replayed later.
+///
+/// The text format has one call per line, with tab-separated fields:
+/// `seq thread start_ns duration_ns op arguments... result`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+
+        for e in &self.events {
+            write!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                e.seq,
+                e.thread,
+                e.start.as_nanos(),
+                e.duration.as_nanos(),
+                e.call.op().name()
+            )
+            .unwrap();
+
+            match &e.call {
+                TraceCall::Open { path, flag } => write!(out, "\t{}\t{}", escape(path), flag),
+                TraceCall::Close { fd } | TraceCall::Fsync { fd } => write!(out, "\t{}", fd),
+                TraceCall::Read { fd, size } => write!(out, "\t{}\t{}", fd, size),
+                TraceCall::Write { fd, data } | TraceCall::Append { fd, data } => write!(out, "\t{}\t{}", fd, to_hex(data)),
+                TraceCall::Lseek { fd, offset, flag } => {
+                    write!(out, "\t{}\t{}\t{}", fd, offset, seek_flag_name(*flag))
+                }
+                TraceCall::Unlink { path }
+                | TraceCall::Mkdir { path }
+                | TraceCall::Rmdir { path }
+                | TraceCall::Chdir { path } => write!(out, "\t{}", escape(path)),
+                TraceCall::Rename { old, new }
+                | TraceCall::Link { existing: old, new }
+                | TraceCall::Symlink { target: old, link: new }
+                | TraceCall::Reflink { source: old, target: new } => {
+                    write!(out, "\t{}\t{}", escape(old), escape(new))
+                }
+                TraceCall::Truncate { path, len } => write!(out, "\t{}\t{}", escape(path), len),
+                TraceCall::Linkat { fd, new } => write!(out, "\t{}\t{}", fd, escape(new)),
+                TraceCall::Renameat2 { old, new, flag } => {
+                    write!(out, "\t{}\t{}\t{}", escape(old), escape(new), flag)
+                }
+                TraceCall::Ftruncate { fd, len } => write!(out, "\t{}\t{}", fd, len),
+                TraceCall::Pread { fd, size, offset } => write!(out, "\t{}\t{}\t{}", fd, size, offset),
+                TraceCall::Pwrite { fd, offset, data } => write!(out, "\t{}\t{}\t{}", fd, offset, to_hex(data)),
+                TraceCall::Chmod { path, mode } => write!(out, "\t{}\t{}", escape(path), mode),
+                TraceCall::Fchmod { fd, mode } => write!(out, "\t{}\t{}", fd, mode),
+                TraceCall::Chown { path, uid, gid } => {
+                    write!(out, "\t{}\t{}\t{}", escape(path), id_text(*uid), id_text(*gid))
+                }
+                TraceCall::Fchown { fd, uid, gid } => write!(out, "\t{}\t{}\t{}", fd, id_text(*uid), id_text(*gid)),
+                TraceCall::Utimens { path, atime, mtime } => {
+                    write!(out, "\t{}\t{}\t{}", escape(path), time_text(*atime), time_text(*mtime))
+                }
+                TraceCall::Fallocate { fd, mode, offset, len } => {
+                    write!(out, "\t{}\t{}\t{}\t{}", fd, fallocate_mode_name(*mode), offset, len)
+                }
+                TraceCall::CopyFileRange { fd_in, off_in, fd_out, off_out, len } => write!(
+                    out,
+                    "\t{}\t{}\t{}\t{}\t{}",
+                    fd_in,
+                    offset_text(*off_in),
+                    fd_out,
+                    offset_text(*off_out),
+                    len
+                ),
+            }
+            .unwrap();
+
+            match &e.result {
+                TraceResult::Ok(Some(value)) => writeln!(out, "\tok:{}", value),
+                TraceResult::Ok(None) => writeln!(out, "\tok"),
+                TraceResult::Err(err) => writeln!(out, "\terr:{}", err),
+            }
+            .unwrap();
+        }
+
+        out
+    }
+
+    /// Parses a trace written by [Trace::to_text]. Fails with EINVAL on malformed lines.
+    pub fn from_text(text: &str) -> Result<Self> {
+        let events = text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(parse_event)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { events })
+    }
+
+    /// Runs the recorded calls against `fs`, which is usually a fresh MemFS.
+    /// File descriptors of the recording are mapped to the ones returned during the replay.
+    pub fn replay(&self, fs: &MemFS, mode: ReplayMode) -> ReplayReport {
+        let replayer = Replayer {
+            fs,
+            fds: Mutex::default(),
+            mismatches: Mutex::default(),
+            turn: Mutex::new(0),
+            turn_changed: Condvar::new(),
+        };
+
+        match mode {
+            ReplayMode::Sequential => {
+                for event in &self.events {
+                    replayer.run(event);
+                }
+            }
+            ReplayMode::Interleaved => {
+                let mut threads: HashMap<u64, Vec<(usize, &TraceEvent)>> = HashMap::new();
+
+                for (position, event) in self.events.iter().enumerate() {
+                    threads.entry(event.thread).or_default().push((position, event));
+                }
+
+                thread::scope(|scope| {
+                    for events in threads.into_values() {
+                        let replayer = &replayer;
+
+                        scope.spawn(move || {
+                            for (position, event) in events {
+                                replayer.wait_for_turn(position);
+                                replayer.run(event);
+                            }
+                        });
+                    }
+                });
+            }
+        }
+
+        let mut mismatches = replayer.mismatches.into_inner().unwrap();
+        mismatches.sort_by_key(|m| m.seq);
+
+        ReplayReport {
+            replayed: self.events.len(),
+            mismatches,
+        }
+    }
+}
+
+struct Replayer<'a> {
+    fs: &'a MemFS,
+    fds: Mutex<DescriptorMap>,
+    mismatches: Mutex<Vec<ReplayMismatch>>,
+    turn: Mutex<usize>,
+    turn_changed: Condvar,
+}
+
+impl Replayer<'_> {
+    /// Waits until every call recorded before `position` has been started.
+    fn wait_for_turn(&self, position: usize) {
+        let mut turn = self.turn.lock().unwrap();
+
+        while *turn != position {
+            turn = self.turn_changed.wait(turn).unwrap();
+        }
+
+        *turn += 1;
+        self.turn_changed.notify_all();
+    }
+
+    fn run(&self, event: &TraceEvent) {
+        let fs = self.fs;
+        let replayed = |recorded: usize| self.fds.lock().unwrap().get(event.thread, recorded);
+        let outcome: Result<Option<usize>> = match &event.call {
+            TraceCall::Open { path, flag } => fs.open(path, OpenFlag::from_bits_retain(*flag)).map(Some),
+            TraceCall::Close { fd } => fs.close(replayed(*fd)).map(|_| None),
+            TraceCall::Unlink { path } => fs.unlink(path).map(|_| None),
+            TraceCall::Read { fd, size } => fs.read(replayed(*fd), &mut vec![0; *size]).map(Some),
+            TraceCall::Write { fd, data } => fs.write(replayed(*fd), data).map(Some),
+            TraceCall::Append { fd, data } => fs.append_all(replayed(*fd), &[IoSlice::new(data)]).map(Some),
+            TraceCall::Lseek { fd, offset, flag } => fs.lseek(replayed(*fd), *offset, *flag).map(Some),
+            TraceCall::Mkdir { path } => fs.mkdir(path).map(|_| None),
+            TraceCall::Rmdir { path } => fs.rmdir(path).map(|_| None),
+            TraceCall::Chdir { path } => fs.chdir(path).map(|_| None),
+            TraceCall::Fsync { fd } => fs.fsync(replayed(*fd)).map(|_| None),
+            TraceCall::Rename { old, new } => fs.rename(old, new).map(|_| None),
+            TraceCall::Truncate { path, len } => fs.truncate(path, *len).map(|_| None),
+            TraceCall::Ftruncate { fd, len } => fs.ftruncate(replayed(*fd), *len).map(|_| None),
+            TraceCall::Pread { fd, size, offset } => fs.pread(replayed(*fd), &mut vec![0; *size], *offset).map(Some),
+            TraceCall::Pwrite { fd, offset, data } => fs.pwrite(replayed(*fd), data, *offset).map(Some),
+            TraceCall::Link { existing, new } => fs.link(existing, new).map(|_| None),
+            TraceCall::Linkat { fd, new } => fs.linkat(replayed(*fd), new).map(|_| None),
+            TraceCall::Renameat2 { old, new, flag } => fs
+                .renameat2(AT_FDCWD, old, AT_FDCWD, new, RenameFlag::from_bits_retain(*flag))
+                .map(|_| None),
+            TraceCall::Symlink { target, link } => fs.symlink(target, link).map(|_| None),
+            TraceCall::Reflink { source, target } => fs.reflink(source, target).map(|_| None),
+            TraceCall::Chmod { path, mode } => fs.chmod(path, *mode).map(|_| None),
+            TraceCall::Fchmod { fd, mode } => fs.fchmod(replayed(*fd), *mode).map(|_| None),
+            TraceCall::Chown { path, uid, gid } => fs.chown(path, *uid, *gid).map(|_| None),
+            TraceCall::Fchown { fd, uid, gid } => fs.fchown(replayed(*fd), *uid, *gid).map(|_| None),
+            TraceCall::Utimens { path, atime, mtime } => fs.utimens(path, *atime, *mtime).map(|_| None),
+            TraceCall::Fallocate { fd, mode, offset, len } => {
+                fs.fallocate(replayed(*fd), *mode, *offset, *len).map(|_| None)
+            }
+            TraceCall::CopyFileRange { fd_in, off_in, fd_out, off_out, len } => fs
+                .copy_file_range(replayed(*fd_in), *off_in, replayed(*fd_out), *off_out, *len)
+                .map(Some),
+        };
+
+        let actual = TraceResult::from_outcome(outcome.as_ref().map(|v| *v));
+
+        if let TraceCall::Close { fd } = &event.call {
+            self.fds.lock().unwrap().forget(event.thread, *fd);
+        }
+
+        // Descriptor numbers depend on the order of allocation, so only the success of open is compared.
+        let matches = match (&event.call, &event.result, &actual) {
+            (TraceCall::Open { .. }, TraceResult::Ok(Some(recorded)), TraceResult::Ok(Some(replayed))) => {
+                self.fds.lock().unwrap().insert(event.thread, *recorded, *replayed);
+                true
+            }
+            (_, expected, actual) => expected == actual,
+        };
+
+        if !matches {
+            self.mismatches.lock().unwrap().push(ReplayMismatch {
+                seq: event.seq,
+                expected: event.result.clone(),
+                actual,
+            });
+        }
+    }
+}
+
+/// Descriptors returned during a replay, by the number returned during the recording.
+///
+/// Numbers of closed descriptors are reused, so a number may be returned to another thread while
+/// the call closing it is still being replayed. Threads look up the descriptors they opened
+/// themselves first, which is what they use in practice.
+#[derive(Default)]
+struct DescriptorMap {
+    by_thread: HashMap<(u64, usize), usize>,
+    latest: HashMap<usize, usize>,
+}
+
+impl DescriptorMap {
+    fn insert(&mut self, thread: u64, recorded: usize, replayed: usize) {
+        self.by_thread.insert((thread, recorded), replayed);
+        self.latest.insert(recorded, replayed);
+    }
+
+    /// Stops preferring a descriptor closed by `thread`, whose number may go to another thread next.
+    fn forget(&mut self, thread: u64, recorded: usize) {
+        self.by_thread.remove(&(thread, recorded));
+    }
+
+    fn get(&self, thread: u64, recorded: usize) -> usize {
+        self.by_thread
+            .get(&(thread, recorded))
+            .or_else(|| self.latest.get(&recorded))
+            .copied()
+            .unwrap_or(recorded)
+    }
+}
+
+fn parse_event(line: &str) -> Result<TraceEvent> {
+    let fields: Vec<&str> = line.split('\t').collect();
+
+    if fields.len() < 7 {
+        return Err(malformed(line));
+    }
+
+    let number = |i: usize| fields[i].parse::<u64>().map_err(|_| malformed(line));
+    let size = |i: usize| fields[i].parse::<usize>().map_err(|_| malformed(line));
+    let op = MemFSOp::ALL
+        .into_iter()
+        .find(|op| op.name() == fields[4])
+        .ok_or_else(|| malformed(line))?;
+
+    let (call, argument_count) = match op {
+        MemFSOp::Open => (
+            TraceCall::Open {
+                path: unescape(fields[5]),
+                flag: fields[6].parse().map_err(|_| malformed(line))?,
+            },
+            2,
+        ),
+        MemFSOp::Close => (TraceCall::Close { fd: size(5)? }, 1),
+        MemFSOp::Read => (
+            TraceCall::Read {
+                fd: size(5)?,
+                size: size(6)?,
+            },
+            2,
+        ),
+        MemFSOp::Write => (
+            TraceCall::Write {
+                fd: size(5)?,
+                data: from_hex(fields[6]).ok_or_else(|| malformed(line))?,
+            },
+            2,
+        ),
+        MemFSOp::Lseek => (
+            TraceCall::Lseek {
+                fd: size(5)?,
+                offset: fields[6].parse().map_err(|_| malformed(line))?,
+                flag: parse_seek_flag(fields[7]).ok_or_else(|| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Unlink => (TraceCall::Unlink { path: unescape(fields[5]) }, 1),
+        MemFSOp::Mkdir => (TraceCall::Mkdir { path: unescape(fields[5]) }, 1),
+        MemFSOp::Rmdir => (TraceCall::Rmdir { path: unescape(fields[5]) }, 1),
+        MemFSOp::Chdir => (TraceCall::Chdir { path: unescape(fields[5]) }, 1),
+        MemFSOp::Fsync => (TraceCall::Fsync { fd: size(5)? }, 1),
+        MemFSOp::Rename => (
+            TraceCall::Rename {
+                old: unescape(fields[5]),
+                new: unescape(fields[6]),
+            },
+            2,
+        ),
+        MemFSOp::Truncate => (
+            TraceCall::Truncate {
+                path: unescape(fields[5]),
+                len: size(6)?,
+            },
+            2,
+        ),
+        MemFSOp::Ftruncate => (
+            TraceCall::Ftruncate {
+                fd: size(5)?,
+                len: size(6)?,
+            },
+            2,
+        ),
+        MemFSOp::Pread => (
+            TraceCall::Pread {
+                fd: size(5)?,
+                size: size(6)?,
+                offset: size(7)?,
+            },
+            3,
+        ),
+        MemFSOp::Pwrite => (
+            TraceCall::Pwrite {
+                fd: size(5)?,
+                offset: size(6)?,
+                data: from_hex(fields[7]).ok_or_else(|| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Link => (
+            TraceCall::Link {
+                existing: unescape(fields[5]),
+                new: unescape(fields[6]),
+            },
+            2,
+        ),
+        MemFSOp::Symlink => (
+            TraceCall::Symlink {
+                target: unescape(fields[5]),
+                link: unescape(fields[6]),
+            },
+            2,
+        ),
+        MemFSOp::Chmod => (
+            TraceCall::Chmod {
+                path: unescape(fields[5]),
+                mode: fields[6].parse().map_err(|_| malformed(line))?,
+            },
+            2,
+        ),
+        MemFSOp::Fchmod => (
+            TraceCall::Fchmod {
+                fd: size(5)?,
+                mode: fields[6].parse().map_err(|_| malformed(line))?,
+            },
+            2,
+        ),
+        MemFSOp::Chown => (
+            TraceCall::Chown {
+                path: unescape(fields[5]),
+                uid: parse_id(fields[6]).ok_or_else(|| malformed(line))?,
+                gid: parse_id(fields[7]).ok_or_else(|| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Fchown => (
+            TraceCall::Fchown {
+                fd: size(5)?,
+                uid: parse_id(fields[6]).ok_or_else(|| malformed(line))?,
+                gid: parse_id(fields[7]).ok_or_else(|| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Utimens => (
+            TraceCall::Utimens {
+                path: unescape(fields[5]),
+                atime: parse_time(fields[6]).ok_or_else(|| malformed(line))?,
+                mtime: parse_time(fields[7]).ok_or_else(|| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Fallocate => (
+            TraceCall::Fallocate {
+                fd: size(5)?,
+                mode: parse_fallocate_mode(fields[6]).ok_or_else(|| malformed(line))?,
+                offset: size(7)?,
+                len: size(8)?,
+            },
+            4,
+        ),
+        MemFSOp::CopyFileRange => (
+            TraceCall::CopyFileRange {
+                fd_in: size(5)?,
+                off_in: parse_offset(fields[6]).ok_or_else(|| malformed(line))?,
+                fd_out: size(7)?,
+                off_out: parse_offset(fields[8]).ok_or_else(|| malformed(line))?,
+                len: size(9)?,
+            },
+            5,
+        ),
+        MemFSOp::Reflink => (
+            TraceCall::Reflink {
+                source: unescape(fields[5]),
+                target: unescape(fields[6]),
+            },
+            2,
+        ),
+        MemFSOp::Linkat => (
+            TraceCall::Linkat {
+                fd: size(5)?,
+                new: unescape(fields[6]),
+            },
+            2,
+        ),
+        MemFSOp::Renameat2 => (
+            TraceCall::Renameat2 {
+                old: unescape(fields[5]),
+                new: unescape(fields[6]),
+                flag: fields[7].parse().map_err(|_| malformed(line))?,
+            },
+            3,
+        ),
+        MemFSOp::Append => (
+            TraceCall::Append {
+                fd: size(5)?,
+                data: from_hex(fields[6]).ok_or_else(|| malformed(line))?,
+            },
+            2,
+        ),
+    };
+
+    if fields.len() != 6 + argument_count {
+        return Err(malformed(line));
+    }
+
+    let result = match fields[5 + argument_count] {
+        "ok" => TraceResult::Ok(None),
+        r => match (r.strip_prefix("ok:"), r.strip_prefix("err:")) {
+            (Some(value), _) => TraceResult::Ok(Some(value.parse().map_err(|_| malformed(line))?)),
+            (_, Some(err)) => TraceResult::Err(err.to_string()),
+            _ => return Err(malformed(line)),
+        },
+    };
+
+    Ok(TraceEvent {
+        seq: number(0)?,
+        thread: number(1)?,
+        start: Duration::from_nanos(number(2)?),
+        duration: Duration::from_nanos(number(3)?),
+        call,
+        result,
+    })
+}
+
+fn malformed(line: &str) -> MemFSErr {
+    MemFSErr {
+        message: format!("Malformed trace line: {line}"),
+        ..MemFSErr::invalid_value()
+    }
+}
+
+fn seek_flag_name(flag: SeekFlag) -> &'static str {
+    match flag {
+        SeekFlag::SEEK_CUR => "cur",
+        SeekFlag::SEEK_END => "end",
+        SeekFlag::SEEK_SET => "set",
+        SeekFlag::SEEK_DATA => "data",
+        SeekFlag::SEEK_HOLE => "hole",
+    }
+}
+
+fn parse_seek_flag(name: &str) -> Option<SeekFlag> {
+    match name {
+        "cur" => Some(SeekFlag::SEEK_CUR),
+        "end" => Some(SeekFlag::SEEK_END),
+        "set" => Some(SeekFlag::SEEK_SET),
+        "data" => Some(SeekFlag::SEEK_DATA),
+        "hole" => Some(SeekFlag::SEEK_HOLE),
+        _ => None,
+    }
+}
+
+fn fallocate_mode_name(mode: FallocateMode) -> &'static str {
+    match mode {
+        FallocateMode::Allocate => "allocate",
+        FallocateMode::KeepSize => "keep_size",
+        FallocateMode::PunchHole => "punch_hole",
+    }
+}
+
+fn parse_fallocate_mode(name: &str) -> Option<FallocateMode> {
+    match name {
+        "allocate" => Some(FallocateMode::Allocate),
+        "keep_size" => Some(FallocateMode::KeepSize),
+        "punch_hole" => Some(FallocateMode::PunchHole),
+        _ => None,
+    }
+}
+
+/// Writes an offset of copy_file_range, or `cur` for the offset of the descriptor.
+fn offset_text(offset: Option<usize>) -> String {
+    offset.map_or_else(|| "cur".to_string(), |offset| offset.to_string())
+}
+
+fn parse_offset(value: &str) -> Option<Option<usize>> {
+    match value {
+        "cur" => Some(None),
+        _ => value.parse().ok().map(Some),
+    }
+}
+
+/// Writes an id of chown, where -1 leaves the id unchanged as in the system call.
+fn id_text(id: Option<u32>) -> String {
+    id.map_or_else(|| "-1".to_string(), |id| id.to_string())
+}
+
+fn parse_id(value: &str) -> Option<Option<u32>> {
+    match value {
+        "-1" => Some(None),
+        _ => value.parse().ok().map(Some),
+    }
+}
+
+/// Writes a time of utimens as signed nanoseconds since the Unix epoch, or `omit` for a time left unchanged.
+fn time_text(time: Option<SystemTime>) -> String {
+    match time.map(|time| time.duration_since(UNIX_EPOCH)) {
+        None => "omit".to_string(),
+        Some(Ok(after)) => after.as_nanos().to_string(),
+        Some(Err(before)) => format!("-{}", before.duration().as_nanos()),
+    }
+}
+
+fn parse_time(value: &str) -> Option<Option<SystemTime>> {
+    if value == "omit" {
+        return Some(None);
+    }
+
+    let nanos: i128 = value.parse().ok()?;
+    let offset = Duration::from_nanos(u64::try_from(nanos.unsigned_abs()).ok()?);
+
+    match nanos < 0 {
+        true => UNIX_EPOCH.checked_sub(offset).map(Some),
+        false => UNIX_EPOCH.checked_add(offset).map(Some),
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+fn to_hex(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 2);
+
+    for byte in data {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+
+    out
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
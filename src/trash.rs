@@ -0,0 +1,48 @@
+use std::sync::{Mutex, PoisonError};
+
+use crate::utils::Result;
+
+/// Entries removed by [crate::memfs::MemFS::unlink] while [crate::memfs::MemFSBuilder::trash] is enabled,
+/// in the order they were removed, each with the absolute path it was removed from.
+pub(crate) struct Trash<N> {
+    entries: Mutex<Vec<(String, N)>>,
+}
+
+impl<N> Trash<N> {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::default(),
+        }
+    }
+
+    pub fn push(&self, path: String, node: N) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((path, node));
+    }
+
+    /// Runs `restore` on the entry last removed from `path`, and takes it out of the trash if it succeeds.
+    /// Returns None if no entry was removed from `path`.
+    pub fn restore(&self, path: &str, restore: impl FnOnce(&N) -> Result<()>) -> Option<Result<()>> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = entries.iter().rposition(|(removed_from, _)| removed_from == path)?;
+
+        Some(restore(&entries[index].1).map(|()| {
+            entries.remove(index);
+        }))
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    /// Empties the trash, and returns the number of entries it held. They are dropped once the lock is released.
+    pub fn clear(&self) -> usize {
+        let purged = std::mem::take(&mut *self.entries.lock().unwrap_or_else(PoisonError::into_inner));
+
+        purged.len()
+    }
+}
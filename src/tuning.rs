@@ -0,0 +1,109 @@
+#[cfg(feature = "coarse-grained")]
+use std::collections::HashMap;
+#[cfg(any(feature = "fine-grained", feature = "lock-free"))]
+use std::hash::Hash;
+
+#[cfg(feature = "fine-grained")]
+use dashmap::DashMap;
+#[cfg(feature = "lock-free")]
+use papaya::{HashMap as LockFreeHashMap, ResizeMode};
+
+use crate::hash::HashState;
+use crate::names::{NameInterner, NameMatching};
+
+/// How a lock-free map grows once it is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapResize {
+    /// Every writer copies `chunk` entries to the new table before its own write,
+    /// which keeps insertion latency flat at the cost of throughput during the resize.
+    Incremental { chunk: usize },
+
+    /// Writers wait until the new table is complete. Better throughput, but latency spikes.
+    Blocking,
+}
+
+impl Default for MapResize {
+    fn default() -> Self {
+        Self::Incremental { chunk: 64 }
+    }
+}
+
+/// Sizing of directory maps and the file descriptor table.
+///
+/// Shards only exist on the fine-grained backend and the resize mode only applies to the lock-free backend.
+///
+/// More shards make it less likely that threads working on the same directory wait for the same lock,
+/// but every shard is a separate allocation in every directory, so trees of many small directories
+/// and single-threaded use prefer few shards. Preallocated capacity avoids resizes of large directories
+/// and wastes memory on small ones.
+///
+/// Creating 2^14 files in one directory from a single thread (release build, single core, average of 16 runs,
+/// three repetitions) took 11-18ms on the fine-grained backend with 2, 4 (the default on one core) or 64 shards,
+/// with no order between them beyond noise. On the lock-free backend the same workload took 19-23ms with
+/// incremental resizes, 20-24ms with blocking ones and 14-21ms with the capacity preallocated.
+/// Multi-threaded runs are needed to see the benefit of more shards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapTuning {
+    /// Number of DashMap shards, rounded up to a power of two of at least 2.
+    /// DashMap picks four times the number of cores when it is not set.
+    pub shards: Option<usize>,
+
+    /// Entries preallocated in every directory, including empty ones.
+    pub directory_capacity: usize,
+
+    /// Entries preallocated in the file descriptor table, split evenly between its shards.
+    pub descriptor_capacity: usize,
+
+    /// Number of shards of the file descriptor table, which descriptors are spread over by their number.
+    /// With more than one, each thread takes descriptor numbers from a shard of its own, so that threads
+    /// opening and closing files at once contend only when they share a shard. A descriptor is then the lowest
+    /// free number of that shard, not the lowest free one overall as POSIX requires.
+    /// Zero and one both keep a single shard, which hands out the lowest free number.
+    pub descriptor_shards: usize,
+
+    pub resize: MapResize,
+}
+
+/// Hasher and sizing of every map of a file system.
+#[derive(Clone, Default)]
+pub(crate) struct MapConfig {
+    pub hasher: HashState,
+    pub tuning: MapTuning,
+    pub names: NameMatching,
+
+    /// Names of the entries of every directory, which clones of the configuration share.
+    pub interner: NameInterner,
+}
+
+impl MapConfig {
+    #[cfg(feature = "coarse-grained")]
+    pub fn new_map<K, V>(&self, capacity: usize) -> HashMap<K, V, HashState> {
+        HashMap::with_capacity_and_hasher(capacity, self.hasher.clone())
+    }
+
+    #[cfg(feature = "fine-grained")]
+    pub fn new_map<K: Eq + Hash, V>(&self, capacity: usize) -> DashMap<K, V, HashState> {
+        match self.tuning.shards {
+            Some(shards) => DashMap::with_capacity_and_hasher_and_shard_amount(
+                capacity,
+                self.hasher.clone(),
+                shards.max(2).next_power_of_two(),
+            ),
+            None => DashMap::with_capacity_and_hasher(capacity, self.hasher.clone()),
+        }
+    }
+
+    #[cfg(feature = "lock-free")]
+    pub fn new_map<K: Eq + Hash, V>(&self, capacity: usize) -> LockFreeHashMap<K, V, HashState> {
+        let resize = match self.tuning.resize {
+            MapResize::Incremental { chunk } => ResizeMode::Incremental(chunk),
+            MapResize::Blocking => ResizeMode::Blocking,
+        };
+
+        LockFreeHashMap::builder()
+            .hasher(self.hasher.clone())
+            .capacity(capacity)
+            .resize_mode(resize)
+            .build()
+    }
+}
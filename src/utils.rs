@@ -1,8 +1,12 @@
 use bitflags::bitflags;
 use rand::Rng;
-use std::fmt::Display;
+use std::{fmt::Display, io, time::SystemTime};
 
-pub const FILE_MAX_SIZE: usize = 1 << 12;
+/// Size of the pages holding file contents, which are allocated from the memory pool as files grow.
+pub const PAGE_SIZE: usize = 1 << 12;
+/// Files which never grew past this size keep their contents inline in their node, taking no page.
+pub const INLINE_FILE_SIZE: usize = 128;
+pub const FILE_MAX_SIZE: usize = 1 << 22;
 pub const THREAD_MAX_ID: usize = 1 << 8;
 pub const NUMBER_OF_MAXIMUM_FILES: usize = 1 << 14;
 
@@ -15,6 +19,50 @@ bitflags! {
         const O_CREAT  = 0b1000;
         const O_EXCL = 0b10000;
         const O_APPEND = 0b100000;
+
+        /// Pins the contents of the file at open time. Only valid with O_RDONLY.
+        ///
+        /// The contents are copied whole, not shared page by page. Opens share the copy until the file is
+        /// written again, so the first open after each write takes time and memory in the size of the file.
+        const O_SNAPSHOT = 0b1000000;
+
+        /// Creates an unnamed file in the directory given as the path, reclaimed once its last descriptor
+        /// is closed unless [crate::memfs::MemFS::linkat] gives it a name. Needs O_WRONLY or O_RDWR.
+        /// With O_EXCL, the file can never be given a name.
+        const O_TMPFILE = 0b10000000;
+
+        /// Opens a directory, to serve as the base of relative paths given to the *at calls such as
+        /// [crate::memfs::MemFS::openat]. Needs O_RDONLY. Fails with ENOTDIR on anything else.
+        const O_DIRECTORY = 0b100000000;
+
+        /// Makes reads and writes of a FIFO fail with EAGAIN instead of waiting, and its opens return at once.
+        /// Opening a FIFO for writing only then fails with ENXIO if no descriptor has it open for reading.
+        const O_NONBLOCK = 0b1000000000;
+    }
+}
+
+/// Directory descriptor standing for the working directory in the *at calls, such as
+/// [crate::memfs::MemFS::openat].
+pub const AT_FDCWD: usize = usize::MAX;
+
+bitflags! {
+    /// Flags of [crate::memfs::MemFS::renameat2].
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct RenameFlag: u32 {
+        /// Fails with EEXIST instead of replacing the destination.
+        const RENAME_NOREPLACE = 0b1;
+
+        /// Swaps the source and the destination, which must both exist.
+        const RENAME_EXCHANGE = 0b10;
+    }
+}
+
+bitflags! {
+    /// Flags of [crate::memfs::MemFS::unlinkat].
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct AtFlag: u32 {
+        /// Removes an empty directory instead of a file, as with rmdir.
+        const AT_REMOVEDIR = 0b1;
     }
 }
 
@@ -28,19 +76,125 @@ impl OpenFlag {
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SeekFlag {
     SEEK_CUR,
     SEEK_END,
     SEEK_SET,
+
+    /// Moves to the next offset at or after the given one which holds data.
+    SEEK_DATA,
+
+    /// Moves to the next hole at or after the given offset. The end of the file counts as a hole.
+    SEEK_HOLE,
+}
+
+/// Mode of [crate::memfs::MemFS::fallocate].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FallocateMode {
+    /// Reserves the range and grows the file to cover it, as with a mode of 0.
+    Allocate,
+
+    /// Reserves the range without changing the size of the file, as with FALLOC_FL_KEEP_SIZE.
+    KeepSize,
+
+    /// Zeroes the range without changing the size of the file, as with FALLOC_FL_PUNCH_HOLE.
+    PunchHole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+    Symlink,
+    CharDevice,
+    Fifo,
 }
 
-#[derive(Debug)]
+/// Metadata of a file or directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stat {
+    /// Inode number, unique among the nodes of the file system and kept for the life of the node.
+    /// Hard links of a file share it.
+    pub ino: u64,
+
+    pub file_type: FileType,
+
+    /// Size of a file in bytes, or number of entries of a directory.
+    pub size: usize,
+
+    /// Sequence number of the latest change of the file or of the entries of the directory,
+    /// or 0 without [crate::memfs::MemFSBuilder::change_tracking].
+    pub change_seq: u64,
+
+    /// Number of directory entries linking a file, or 1 for a directory.
+    pub nlink: usize,
+
+    /// Permission bits, with the set-user-ID, set-group-ID and sticky bits. Always 0o777 for a symbolic link.
+    pub mode: u32,
+
+    /// Owner of the file. Symbolic links have no owner, and report the superuser.
+    pub uid: u32,
+
+    /// Group of the file. Symbolic links have no group, and report the superuser's.
+    pub gid: u32,
+
+    /// Time of the latest read of a file. Symbolic links have no times, and report the Unix epoch for each.
+    pub atime: SystemTime,
+
+    /// Time of the latest write of a file, or of the latest change of the entries of a directory.
+    pub mtime: SystemTime,
+
+    /// Time of the latest change of the contents or of the metadata, such as the mode or the owner.
+    pub ctime: SystemTime,
+
+    /// Creation time.
+    pub btime: SystemTime,
+}
+
+/// Statistics of a whole file system, like those of statvfs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatFs {
+    /// Size of a block of file memory in bytes, which is [PAGE_SIZE]. A file takes a block for every page of
+    /// its contents which was written or allocated, and none while they fit inline.
+    pub block_size: usize,
+
+    /// Number of blocks the file system can hold.
+    pub blocks: usize,
+
+    /// Number of blocks held by files, including unlinked files which are still open, and blocks a file gave
+    /// up while a read of it was in progress, until they are reclaimed.
+    pub blocks_used: usize,
+
+    /// Number of blocks which can still be handed out to the contents of files.
+    pub blocks_free: usize,
+
+    /// Number of files, directories and symbolic links, counting hard links of a file once.
+    pub entries: usize,
+
+    pub read_only: bool,
+}
+
+/// Entry of a directory, returned with its metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub stat: Stat,
+}
+
+#[derive(Clone, Debug)]
 pub struct MemFSErr {
     pub message: String,
     pub err_type: MemFSErrType,
+
+    /// Name of the MemFS method which failed, such as `open`, when the error came out of one.
+    pub operation: Option<&'static str>,
+
+    /// Path given to the method which failed, or the path a file descriptor given to it was opened with.
+    pub path: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum MemFSErrType {
     /// Used on poisoned lock error.
     PoisonedLock,
@@ -80,107 +234,305 @@ pub enum MemFSErrType {
     /// Used when memory ran out.
     ENOMEM,
 
+    /// Used when a write or a creation would exceed the quota of the file system.
+    ENOSPC,
+
+    /// Used when the operation would block, such as mutating a frozen file system.
+    EAGAIN,
+
+    /// Used when mutating a read-only file system.
+    EROFS,
+
+    /// Used when the operation is not allowed on the target, such as a hard link to a directory.
+    EPERM,
+
+    /// Used when the permission bits of the target or of its directory deny the access.
+    EACCES,
+
+    /// Used when path lookup follows too many symbolic links, usually because of a loop.
+    ELOOP,
+
+    /// Used when SEEK_DATA or SEEK_HOLE starts at or past the end of the file.
+    ENXIO,
+
+    /// Used when waiting for a lock would never end, because its holder waits for the caller.
+    EDEADLK,
+
+    /// Used when a path or one of its components is longer than the limits of the file system allow.
+    ENAMETOOLONG,
+
+    /// Used when opening a file would exceed the limit of open file descriptors.
+    EMFILE,
+
+    /// Used when writing to a FIFO which no descriptor has open for reading.
+    EPIPE,
+
     /// Miscellaneous
     Misc,
 }
 
+impl MemFSErrType {
+    /// Every error type, in order of declaration.
+    pub const ALL: [MemFSErrType; 24] = [
+        MemFSErrType::PoisonedLock,
+        MemFSErrType::ENOENT,
+        MemFSErrType::EEXIST,
+        MemFSErrType::EBADF,
+        MemFSErrType::EISDIR,
+        MemFSErrType::ENOTDIR,
+        MemFSErrType::EFAULT,
+        MemFSErrType::EINVAL,
+        MemFSErrType::ENOTEMPTY,
+        MemFSErrType::EBUSY,
+        MemFSErrType::EFBIG,
+        MemFSErrType::ENOMEM,
+        MemFSErrType::ENOSPC,
+        MemFSErrType::EAGAIN,
+        MemFSErrType::EROFS,
+        MemFSErrType::EPERM,
+        MemFSErrType::EACCES,
+        MemFSErrType::ELOOP,
+        MemFSErrType::ENXIO,
+        MemFSErrType::EDEADLK,
+        MemFSErrType::ENAMETOOLONG,
+        MemFSErrType::EMFILE,
+        MemFSErrType::EPIPE,
+        MemFSErrType::Misc,
+    ];
+
+    /// Returns the errno value of the error type, as defined by the C library of the platform.
+    /// Error types with no errno counterpart are EIO.
+    pub fn to_errno(&self) -> i32 {
+        match self {
+            MemFSErrType::ENOENT => libc::ENOENT,
+            MemFSErrType::EEXIST => libc::EEXIST,
+            MemFSErrType::EBADF => libc::EBADF,
+            MemFSErrType::EISDIR => libc::EISDIR,
+            MemFSErrType::ENOTDIR => libc::ENOTDIR,
+            MemFSErrType::EFAULT => libc::EFAULT,
+            MemFSErrType::EINVAL => libc::EINVAL,
+            MemFSErrType::ENOTEMPTY => libc::ENOTEMPTY,
+            MemFSErrType::EBUSY => libc::EBUSY,
+            MemFSErrType::EFBIG => libc::EFBIG,
+            MemFSErrType::ENOMEM => libc::ENOMEM,
+            MemFSErrType::ENOSPC => libc::ENOSPC,
+            MemFSErrType::EAGAIN => libc::EAGAIN,
+            MemFSErrType::EROFS => libc::EROFS,
+            MemFSErrType::EPERM => libc::EPERM,
+            MemFSErrType::EACCES => libc::EACCES,
+            MemFSErrType::ELOOP => libc::ELOOP,
+            MemFSErrType::ENXIO => libc::ENXIO,
+            MemFSErrType::EDEADLK => libc::EDEADLK,
+            MemFSErrType::ENAMETOOLONG => libc::ENAMETOOLONG,
+            MemFSErrType::EMFILE => libc::EMFILE,
+            MemFSErrType::EPIPE => libc::EPIPE,
+            MemFSErrType::PoisonedLock | MemFSErrType::Misc => libc::EIO,
+        }
+    }
+}
+
+/// Error type of an errno value, as defined by the C library of the platform.
+/// Values with no matching error type are [MemFSErrType::Misc].
+impl From<i32> for MemFSErrType {
+    fn from(errno: i32) -> Self {
+        match errno {
+            libc::ENOENT => MemFSErrType::ENOENT,
+            libc::EEXIST => MemFSErrType::EEXIST,
+            libc::EBADF => MemFSErrType::EBADF,
+            libc::EISDIR => MemFSErrType::EISDIR,
+            libc::ENOTDIR => MemFSErrType::ENOTDIR,
+            libc::EFAULT => MemFSErrType::EFAULT,
+            libc::EINVAL => MemFSErrType::EINVAL,
+            libc::ENOTEMPTY => MemFSErrType::ENOTEMPTY,
+            libc::EBUSY => MemFSErrType::EBUSY,
+            libc::EFBIG => MemFSErrType::EFBIG,
+            libc::ENOMEM => MemFSErrType::ENOMEM,
+            libc::ENOSPC => MemFSErrType::ENOSPC,
+            libc::EAGAIN => MemFSErrType::EAGAIN,
+            libc::EROFS => MemFSErrType::EROFS,
+            libc::EPERM => MemFSErrType::EPERM,
+            libc::EACCES => MemFSErrType::EACCES,
+            libc::ELOOP => MemFSErrType::ELOOP,
+            libc::ENXIO => MemFSErrType::ENXIO,
+            libc::EDEADLK => MemFSErrType::EDEADLK,
+            libc::ENAMETOOLONG => MemFSErrType::ENAMETOOLONG,
+            libc::EMFILE => MemFSErrType::EMFILE,
+            libc::EPIPE => MemFSErrType::EPIPE,
+            _ => MemFSErrType::Misc,
+        }
+    }
+}
+
 impl Display for MemFSErr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let message = &self.message;
-        write!(f, "{message}")
+
+        match (self.operation, &self.path) {
+            (Some(operation), Some(path)) => write!(f, "{operation} {path}: {message}"),
+            (Some(operation), None) => write!(f, "{operation}: {message}"),
+            _ => write!(f, "{message}"),
+        }
     }
 }
 
 impl MemFSErr {
-    pub fn with_message(message: &str) -> Self {
+    fn new(err_type: MemFSErrType, message: &str) -> Self {
         Self {
             message: message.to_string(),
-            err_type: MemFSErrType::Misc,
+            err_type,
+            operation: None,
+            path: None,
         }
     }
 
-    pub fn no_such_file_or_directory() -> Self {
-        Self {
-            message: "No such file or directory".to_string(),
-            err_type: MemFSErrType::ENOENT,
+    /// Names the operation which failed and its path, unless the error names one already,
+    /// so that an operation made of others reports the innermost one which failed.
+    pub fn with_context(mut self, operation: &'static str, path: Option<&str>) -> Self {
+        if self.operation.is_none() {
+            self.operation = Some(operation);
+            self.path = path.map(str::to_string);
         }
+
+        self
+    }
+
+    pub fn with_message(message: &str) -> Self {
+        Self::new(MemFSErrType::Misc, message)
+    }
+
+    pub fn no_such_file_or_directory() -> Self {
+        Self::new(MemFSErrType::ENOENT, "No such file or directory")
     }
 
     pub fn bad_file_descriptor() -> Self {
-        Self {
-            message: "Not a file descriptor".to_string(),
-            err_type: MemFSErrType::EBADF,
-        }
+        Self::new(MemFSErrType::EBADF, "Not a file descriptor")
     }
 
     pub fn is_directory() -> Self {
-        Self {
-            message: "Is a directory".to_string(),
-            err_type: MemFSErrType::EISDIR,
-        }
+        Self::new(MemFSErrType::EISDIR, "Is a directory")
     }
 
     pub fn is_not_directory() -> Self {
-        Self {
-            message: "Is not a directory".to_string(),
-            err_type: MemFSErrType::ENOTDIR,
-        }
+        Self::new(MemFSErrType::ENOTDIR, "Is not a directory")
     }
 
     pub fn bad_memory_access() -> Self {
-        Self {
-            message: "Bad memory access".to_string(),
-            err_type: MemFSErrType::EFAULT,
-        }
+        Self::new(MemFSErrType::EFAULT, "Bad memory access")
     }
 
     pub fn already_exists() -> Self {
-        Self {
-            message: "An entry with name already exists".to_string(),
-            err_type: MemFSErrType::EEXIST,
-        }
+        Self::new(MemFSErrType::EEXIST, "An entry with name already exists")
     }
 
     pub fn invalid_value() -> Self {
-        Self {
-            message: "Invalid value".to_string(),
-            err_type: MemFSErrType::EINVAL,
-        }
+        Self::new(MemFSErrType::EINVAL, "Invalid value")
     }
 
     pub fn is_not_empty() -> Self {
-        Self {
-            message: "Directory is not empty".to_string(),
-            err_type: MemFSErrType::ENOTEMPTY,
-        }
+        Self::new(MemFSErrType::ENOTEMPTY, "Directory is not empty")
     }
 
     pub fn poisoned_lock() -> Self {
-        Self {
-            message: "Lock poison error".to_string(),
-            err_type: MemFSErrType::PoisonedLock,
-        }
+        Self::new(MemFSErrType::PoisonedLock, "Lock poison error")
     }
 
     pub fn busy() -> Self {
-        Self {
-            message: "Is used by other process".to_string(),
-            err_type: MemFSErrType::EBUSY,
-        }
+        Self::new(MemFSErrType::EBUSY, "Is used by other process")
     }
 
     pub fn file_too_large() -> Self {
-        Self {
-            message: "File too large".to_string(),
-            err_type: MemFSErrType::EFBIG,
-        }
+        Self::new(MemFSErrType::EFBIG, "File too large")
     }
 
     pub fn out_of_memory() -> Self {
-        Self {
-            message: "Cannot allocate memory".to_string(),
-            err_type: MemFSErrType::ENOMEM,
-        }
+        Self::new(MemFSErrType::ENOMEM, "Cannot allocate memory")
+    }
+
+    pub fn no_space_left() -> Self {
+        Self::new(MemFSErrType::ENOSPC, "No space left on device")
+    }
+
+    pub fn try_again() -> Self {
+        Self::new(MemFSErrType::EAGAIN, "Resource temporarily unavailable")
+    }
+
+    pub fn read_only_file_system() -> Self {
+        Self::new(MemFSErrType::EROFS, "Read-only file system")
+    }
+
+    pub fn operation_not_permitted() -> Self {
+        Self::new(MemFSErrType::EPERM, "Operation not permitted")
+    }
+
+    pub fn permission_denied() -> Self {
+        Self::new(MemFSErrType::EACCES, "Permission denied")
+    }
+
+    pub fn too_many_links() -> Self {
+        Self::new(MemFSErrType::ELOOP, "Too many levels of symbolic links")
+    }
+
+    pub fn no_such_device_or_address() -> Self {
+        Self::new(MemFSErrType::ENXIO, "No such device or address")
+    }
+
+    pub fn deadlock() -> Self {
+        Self::new(MemFSErrType::EDEADLK, "Resource deadlock avoided")
+    }
+
+    pub fn name_too_long() -> Self {
+        Self::new(MemFSErrType::ENAMETOOLONG, "File name too long")
+    }
+
+    pub fn too_many_open_files() -> Self {
+        Self::new(MemFSErrType::EMFILE, "Too many open files")
+    }
+
+    pub fn broken_pipe() -> Self {
+        Self::new(MemFSErrType::EPIPE, "Broken pipe")
+    }
+}
+
+/// Error of the host file system, mapped by its errno value if it has one, or to the closest error type.
+impl From<io::Error> for MemFSErr {
+    fn from(err: io::Error) -> Self {
+        let err_type = err.raw_os_error().map(MemFSErrType::from).unwrap_or_else(|| match err.kind() {
+            io::ErrorKind::NotFound => MemFSErrType::ENOENT,
+            io::ErrorKind::AlreadyExists => MemFSErrType::EEXIST,
+            io::ErrorKind::PermissionDenied => MemFSErrType::EACCES,
+            io::ErrorKind::NotADirectory => MemFSErrType::ENOTDIR,
+            io::ErrorKind::IsADirectory => MemFSErrType::EISDIR,
+            io::ErrorKind::InvalidInput => MemFSErrType::EINVAL,
+            io::ErrorKind::StorageFull => MemFSErrType::ENOSPC,
+            io::ErrorKind::Deadlock => MemFSErrType::EDEADLK,
+            io::ErrorKind::InvalidFilename => MemFSErrType::ENAMETOOLONG,
+            io::ErrorKind::BrokenPipe => MemFSErrType::EPIPE,
+            _ => MemFSErrType::Misc,
+        });
+
+        Self::new(err_type, &err.to_string())
+    }
+}
+
+/// Error returned through [std::io] traits implemented on top of MemFS.
+impl From<MemFSErr> for io::Error {
+    fn from(err: MemFSErr) -> Self {
+        let kind = match err.err_type {
+            MemFSErrType::ENOENT => io::ErrorKind::NotFound,
+            MemFSErrType::EEXIST => io::ErrorKind::AlreadyExists,
+            MemFSErrType::EPERM | MemFSErrType::EACCES => io::ErrorKind::PermissionDenied,
+            MemFSErrType::EINVAL => io::ErrorKind::InvalidInput,
+            MemFSErrType::EFBIG => io::ErrorKind::FileTooLarge,
+            MemFSErrType::EROFS => io::ErrorKind::ReadOnlyFilesystem,
+            MemFSErrType::EAGAIN => io::ErrorKind::WouldBlock,
+            MemFSErrType::ENOSPC => io::ErrorKind::StorageFull,
+            MemFSErrType::EDEADLK => io::ErrorKind::Deadlock,
+            MemFSErrType::ENAMETOOLONG => io::ErrorKind::InvalidFilename,
+            MemFSErrType::EPIPE => io::ErrorKind::BrokenPipe,
+            _ => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, err.to_string())
     }
 }
 
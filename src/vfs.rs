@@ -0,0 +1,185 @@
+use std::{
+    fmt::Debug,
+    io::{self, Read, Seek, SeekFrom, Write},
+    sync::Arc,
+};
+
+use ::vfs::{FileSystem, SeekAndRead, VfsError, VfsFileType, VfsMetadata, VfsResult, error::VfsErrorKind};
+
+use crate::memfs::MemFS;
+use crate::utils::{FileType, MemFSErr, MemFSErrType, OpenFlag, SeekFlag};
+
+/// Adapter implementing [FileSystem] of the `vfs` crate on top of a [MemFS].
+///
+/// Paths given by `vfs` are absolute, with the empty string standing for the root directory.
+/// Opened files are closed when the returned reader or writer is dropped.
+#[derive(Clone)]
+pub struct MemFSVfs {
+    fs: Arc<MemFS>,
+}
+
+impl MemFSVfs {
+    pub fn new(fs: MemFS) -> Self {
+        Self::from_shared(Arc::new(fs))
+    }
+
+    /// Wraps a file system which is also used directly elsewhere.
+    pub fn from_shared(fs: Arc<MemFS>) -> Self {
+        Self { fs }
+    }
+
+    pub fn inner(&self) -> &Arc<MemFS> {
+        &self.fs
+    }
+
+    fn open(&self, path: &str, flag: OpenFlag) -> VfsResult<VfsFile> {
+        let fd = self.fs.open(memfs_path(path), flag).map_err(vfs_error)?;
+
+        Ok(VfsFile {
+            fs: self.fs.clone(),
+            fd,
+        })
+    }
+}
+
+impl Debug for MemFSVfs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemFSVfs").finish_non_exhaustive()
+    }
+}
+
+impl FileSystem for MemFSVfs {
+    fn read_dir(&self, path: &str) -> VfsResult<Box<dyn Iterator<Item = String> + Send>> {
+        let entries = self.fs.readdir(memfs_path(path)).map_err(vfs_error)?;
+
+        Ok(Box::new(entries.into_iter().map(|entry| entry.name)))
+    }
+
+    fn create_dir(&self, path: &str) -> VfsResult<()> {
+        self.fs.mkdir(memfs_path(path)).map_err(|err| match err.err_type {
+            MemFSErrType::EEXIST => VfsErrorKind::DirectoryExists.into(),
+            _ => vfs_error(err),
+        })
+    }
+
+    fn open_file(&self, path: &str) -> VfsResult<Box<dyn SeekAndRead + Send>> {
+        Ok(Box::new(self.open(path, OpenFlag::O_RDONLY)?))
+    }
+
+    fn create_file(&self, path: &str) -> VfsResult<Box<dyn Write + Send>> {
+        let file = self.open(path, OpenFlag::O_CREAT | OpenFlag::O_WRONLY)?;
+        self.fs.ftruncate(file.fd, 0).map_err(vfs_error)?;
+
+        Ok(Box::new(file))
+    }
+
+    fn append_file(&self, path: &str) -> VfsResult<Box<dyn Write + Send>> {
+        Ok(Box::new(self.open(path, OpenFlag::O_WRONLY | OpenFlag::O_APPEND)?))
+    }
+
+    fn metadata(&self, path: &str) -> VfsResult<VfsMetadata> {
+        let stat = self.fs.stat(memfs_path(path)).map_err(vfs_error)?;
+
+        Ok(match stat.file_type {
+            FileType::Directory => VfsMetadata {
+                file_type: VfsFileType::Directory,
+                len: 0,
+            },
+            _ => VfsMetadata {
+                file_type: VfsFileType::File,
+                len: stat.size as u64,
+            },
+        })
+    }
+
+    fn exists(&self, path: &str) -> VfsResult<bool> {
+        match self.fs.stat(memfs_path(path)) {
+            Ok(_) => Ok(true),
+            Err(err) if matches!(err.err_type, MemFSErrType::ENOENT | MemFSErrType::ENOTDIR) => Ok(false),
+            Err(err) => Err(vfs_error(err)),
+        }
+    }
+
+    fn remove_file(&self, path: &str) -> VfsResult<()> {
+        self.fs.unlink(memfs_path(path)).map_err(vfs_error)
+    }
+
+    fn remove_dir(&self, path: &str) -> VfsResult<()> {
+        self.fs.rmdir(memfs_path(path)).map_err(vfs_error)
+    }
+
+    fn copy_file(&self, src: &str, dest: &str) -> VfsResult<()> {
+        let mut source = self.open(src, OpenFlag::O_RDONLY)?;
+        let mut contents = Vec::new();
+        source.read_to_end(&mut contents)?;
+
+        let mut target = self.create_file(dest)?;
+        target.write_all(&contents)?;
+
+        Ok(())
+    }
+
+    fn move_file(&self, src: &str, dest: &str) -> VfsResult<()> {
+        self.fs.rename(memfs_path(src), memfs_path(dest)).map_err(vfs_error)
+    }
+
+    fn move_dir(&self, src: &str, dest: &str) -> VfsResult<()> {
+        self.fs.rename(memfs_path(src), memfs_path(dest)).map_err(vfs_error)
+    }
+}
+
+/// File opened through [MemFSVfs], closing its descriptor on drop.
+struct VfsFile {
+    fs: Arc<MemFS>,
+    fd: usize,
+}
+
+impl Read for VfsFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fs.read(self.fd, buf).map_err(io::Error::from)
+    }
+}
+
+impl Write for VfsFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fs.write(self.fd, buf).map_err(io::Error::from)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.fs.fsync(self.fd).map_err(io::Error::from)
+    }
+}
+
+impl Seek for VfsFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, flag) = match pos {
+            SeekFrom::Start(offset) => (
+                i64::try_from(offset).map_err(|_| io::Error::from(MemFSErr::invalid_value()))?,
+                SeekFlag::SEEK_SET,
+            ),
+            SeekFrom::Current(offset) => (offset, SeekFlag::SEEK_CUR),
+            SeekFrom::End(offset) => (offset, SeekFlag::SEEK_END),
+        };
+
+        self.fs.lseek(self.fd, offset, flag).map(|offset| offset as u64).map_err(io::Error::from)
+    }
+}
+
+impl Drop for VfsFile {
+    fn drop(&mut self) {
+        let _ = self.fs.close(self.fd);
+    }
+}
+
+/// `vfs` names the root directory with the empty string.
+fn memfs_path(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+fn vfs_error(err: MemFSErr) -> VfsError {
+    match err.err_type {
+        MemFSErrType::ENOENT => VfsErrorKind::FileNotFound.into(),
+        MemFSErrType::EEXIST => VfsErrorKind::FileExists.into(),
+        _ => VfsErrorKind::Other(err.message).into(),
+    }
+}
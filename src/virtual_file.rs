@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::utils::{MemFSErr, Result};
+
+/// Permission bits of a virtual file which takes no writes, as most files of procfs have.
+pub const READ_ONLY_VIRTUAL_MODE: u32 = 0o444;
+
+/// Permission bits of a virtual file which takes writes.
+pub const WRITABLE_VIRTUAL_MODE: u32 = 0o644;
+
+type Generate = dyn Fn() -> Vec<u8> + Send + Sync;
+type Accept = dyn Fn(&[u8]) -> Result<()> + Send + Sync;
+
+/// File created with [crate::memfs::MemFS::create_virtual_file], whose contents come from callbacks instead
+/// of being stored, as the files of procfs do. Its size is always zero, as there is nothing stored to measure.
+///
+/// The contents are generated each time the file is opened for reading, outside of the locks of the file
+/// system, so the callback may look the file system up. Reads and seeks through the descriptor then see
+/// what was generated at open, until it is closed.
+#[derive(Clone)]
+pub struct VirtualFile {
+    generate: Arc<Generate>,
+    accept: Option<Arc<Accept>>,
+}
+
+impl VirtualFile {
+    /// Creates a virtual file whose contents are what `generate` returns. It takes no writes: opening it
+    /// for writing fails with EACCES.
+    pub fn new(generate: impl Fn() -> Vec<u8> + Send + Sync + 'static) -> Self {
+        Self {
+            generate: Arc::new(generate),
+            accept: None,
+        }
+    }
+
+    /// Makes the file take writes, each of them passed to `accept` as a whole. The write fails with
+    /// the error `accept` returns, if any. `accept` runs as part of the write, so it must not use
+    /// the file system.
+    pub fn on_write(mut self, accept: impl Fn(&[u8]) -> Result<()> + Send + Sync + 'static) -> Self {
+        self.accept = Some(Arc::new(accept));
+        self
+    }
+
+    /// Returns whether the file takes writes.
+    pub fn is_writable(&self) -> bool {
+        self.accept.is_some()
+    }
+
+    /// Generates the contents of the file.
+    pub fn contents(&self) -> Vec<u8> {
+        (self.generate)()
+    }
+
+    /// Passes `data` to the write callback, and returns the number of bytes written, which is all of them.
+    pub(crate) fn write(&self, data: &[u8]) -> Result<usize> {
+        let accept = self.accept.as_ref().ok_or(MemFSErr::permission_denied())?;
+        accept(data)?;
+
+        Ok(data.len())
+    }
+
+    /// Permission bits the file gets when created.
+    pub(crate) fn mode(&self) -> u32 {
+        match self.is_writable() {
+            true => WRITABLE_VIRTUAL_MODE,
+            false => READ_ONLY_VIRTUAL_MODE,
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex, RwLock, Weak,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    task::Waker,
+    time::Duration,
+};
+
+use bitflags::bitflags;
+
+/// Default number of events buffered by a single watch stream.
+pub const DEFAULT_WATCH_BUFFER: usize = 1 << 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchEventKind {
+    /// A file or directory was created.
+    Create,
+
+    /// A file or directory was removed.
+    Delete,
+
+    /// Contents of a file were modified.
+    Modify,
+}
+
+/// Change made on the file system. `path` is always an absolute, normalized path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub kind: WatchEventKind,
+    pub path: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchItem {
+    Event(WatchEvent),
+
+    /// The given number of events was dropped because the consumer could not keep up with the buffer.
+    Lagged(u64),
+}
+
+bitflags! {
+    /// Kinds of events a [WatchHandle] delivers.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct WatchMask: u32 {
+        const CREATE = 0b1;
+        const DELETE = 0b10;
+        const MODIFY = 0b100;
+        const MOVE = 0b1000;
+    }
+}
+
+/// Event delivered by a [WatchHandle]. Paths are absolute and normalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchNotification {
+    Create { path: String },
+    Delete { path: String },
+    Modify { path: String },
+
+    /// A file or directory was renamed. Delivered if either path is watched.
+    Move { from: String, to: String },
+}
+
+impl WatchNotification {
+    pub fn mask(&self) -> WatchMask {
+        match self {
+            WatchNotification::Create { .. } => WatchMask::CREATE,
+            WatchNotification::Delete { .. } => WatchMask::DELETE,
+            WatchNotification::Modify { .. } => WatchMask::MODIFY,
+            WatchNotification::Move { .. } => WatchMask::MOVE,
+        }
+    }
+
+    fn from_event(kind: WatchEventKind, path: &str) -> Self {
+        let path = path.to_string();
+
+        match kind {
+            WatchEventKind::Create => WatchNotification::Create { path },
+            WatchEventKind::Delete => WatchNotification::Delete { path },
+            WatchEventKind::Modify => WatchNotification::Modify { path },
+        }
+    }
+}
+
+struct StreamState {
+    buffer: VecDeque<WatchEvent>,
+    capacity: usize,
+    lagged: u64,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+struct Subscriber {
+    path: String,
+    state: Mutex<StreamState>,
+}
+
+/// Whether `path` is `watched` or lies under it.
+fn is_under(watched: &str, path: &str) -> bool {
+    watched == "/"
+        || path == watched
+        || (path.starts_with(watched) && path.as_bytes()[watched.len()] == b'/')
+}
+
+impl Subscriber {
+    fn is_interested_in(&self, path: &str) -> bool {
+        is_under(&self.path, path)
+    }
+
+    fn push(&self, event: WatchEvent) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.buffer.len() == state.capacity {
+            state.buffer.pop_front();
+            state.lagged += 1;
+        }
+
+        state.buffer.push_back(event);
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Receiving end of a [WatchHandle], held by the registry. The sender is dropped along with the MemFS.
+struct HandleSubscriber {
+    path: String,
+    mask: WatchMask,
+    sender: Mutex<Option<Sender<WatchNotification>>>,
+}
+
+impl HandleSubscriber {
+    fn send(&self, notification: WatchNotification) {
+        if !self.mask.contains(notification.mask()) {
+            return;
+        }
+
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            let _ = sender.send(notification);
+        }
+    }
+}
+
+/// Subscribers of change events of a MemFS instance.
+#[derive(Default)]
+pub(crate) struct WatchRegistry {
+    subscribers: RwLock<Vec<Weak<Subscriber>>>,
+    handles: RwLock<Vec<Weak<HandleSubscriber>>>,
+    count: AtomicUsize,
+}
+
+impl WatchRegistry {
+    pub fn subscribe(&self, path: String, capacity: usize) -> WatchStream {
+        let subscriber = Arc::new(Subscriber {
+            path,
+            state: Mutex::new(StreamState {
+                buffer: VecDeque::with_capacity(capacity.min(DEFAULT_WATCH_BUFFER)),
+                capacity: capacity.max(1),
+                lagged: 0,
+                waker: None,
+                closed: false,
+            }),
+        });
+
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.retain(|s| s.strong_count() > 0);
+        subscribers.push(Arc::downgrade(&subscriber));
+        drop(subscribers);
+        self.update_count();
+
+        WatchStream { subscriber }
+    }
+
+    pub fn watch(&self, path: String, mask: WatchMask) -> WatchHandle {
+        let (sender, receiver) = mpsc::channel();
+        let subscriber = Arc::new(HandleSubscriber {
+            path,
+            mask,
+            sender: Mutex::new(Some(sender)),
+        });
+
+        let mut handles = self.handles.write().unwrap();
+        handles.retain(|h| h.strong_count() > 0);
+        handles.push(Arc::downgrade(&subscriber));
+        drop(handles);
+        self.update_count();
+
+        WatchHandle {
+            _subscriber: subscriber,
+            receiver,
+        }
+    }
+
+    fn update_count(&self) {
+        let streams = self.subscribers.read().unwrap().len();
+        let handles = self.handles.read().unwrap().len();
+
+        self.count.store(streams + handles, Ordering::Release);
+    }
+
+    /// Cheap check used to skip building events when nobody listens.
+    pub fn is_watched(&self) -> bool {
+        self.count.load(Ordering::Acquire) > 0
+    }
+
+    pub fn publish(&self, kind: WatchEventKind, path: &str) {
+        let subscribers = self.subscribers.read().unwrap();
+
+        for subscriber in subscribers.iter().filter_map(|s| s.upgrade()) {
+            if subscriber.is_interested_in(path) {
+                subscriber.push(WatchEvent {
+                    kind,
+                    path: path.to_string(),
+                });
+            }
+        }
+
+        drop(subscribers);
+        self.send_to_handles(
+            |handle| is_under(&handle.path, path),
+            || WatchNotification::from_event(kind, path),
+        );
+    }
+
+    /// Publishes a rename. Streams see it as the removal of `from` followed by the creation of `to`.
+    pub fn publish_move(&self, from: &str, to: &str) {
+        let subscribers = self.subscribers.read().unwrap();
+
+        for subscriber in subscribers.iter().filter_map(|s| s.upgrade()) {
+            for (kind, path) in [(WatchEventKind::Delete, from), (WatchEventKind::Create, to)] {
+                if subscriber.is_interested_in(path) {
+                    subscriber.push(WatchEvent {
+                        kind,
+                        path: path.to_string(),
+                    });
+                }
+            }
+        }
+
+        drop(subscribers);
+        self.send_to_handles(
+            |handle| is_under(&handle.path, from) || is_under(&handle.path, to),
+            || WatchNotification::Move {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+        );
+    }
+
+    fn send_to_handles(
+        &self,
+        is_interested: impl Fn(&HandleSubscriber) -> bool,
+        notification: impl Fn() -> WatchNotification,
+    ) {
+        let handles = self.handles.read().unwrap();
+
+        for handle in handles.iter().filter_map(|h| h.upgrade()) {
+            if is_interested(&handle) {
+                handle.send(notification());
+            }
+        }
+    }
+}
+
+impl Drop for WatchRegistry {
+    fn drop(&mut self) {
+        if let Ok(subscribers) = self.subscribers.read() {
+            for subscriber in subscribers.iter().filter_map(|s| s.upgrade()) {
+                subscriber.close();
+            }
+        }
+
+        if let Ok(handles) = self.handles.read() {
+            for handle in handles.iter().filter_map(|h| h.upgrade()) {
+                handle.sender.lock().unwrap().take();
+            }
+        }
+    }
+}
+
+/// Watch of a path and everything under it, returned by [crate::memfs::MemFS::watch].
+///
+/// Events selected by its mask are sent over a channel as they happen, from the thread which made
+/// the change. The channel is unbounded, so nothing is dropped. Once the MemFS is dropped and every
+/// sent event is received, receiving returns None. Dropping the handle stops the watch.
+pub struct WatchHandle {
+    _subscriber: Arc<HandleSubscriber>,
+    receiver: Receiver<WatchNotification>,
+}
+
+impl WatchHandle {
+    /// Waits for the next event. Returns None once the MemFS is gone.
+    pub fn recv(&self) -> Option<WatchNotification> {
+        self.receiver.recv().ok()
+    }
+
+    /// Returns the next event without blocking, or None if nothing was sent.
+    pub fn try_recv(&self) -> Option<WatchNotification> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Same as [WatchHandle::recv], waiting at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<WatchNotification> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+/// Stream of changes made under a watched path, with bounded buffering.
+///
+/// When more than the buffer capacity of events pile up, the oldest ones are dropped
+/// and [WatchItem::Lagged] is reported before the remaining events.
+/// The stream ends once the MemFS is dropped and every buffered event is consumed.
+pub struct WatchStream {
+    subscriber: Arc<Subscriber>,
+}
+
+impl WatchStream {
+    /// Returns the next item without blocking, or None if nothing is buffered.
+    pub fn try_next(&self) -> Option<WatchItem> {
+        let mut state = self.subscriber.state.lock().unwrap();
+        Self::take_item(&mut state)
+    }
+
+    /// Returns true if the file system is gone and no events are buffered.
+    pub fn is_terminated(&self) -> bool {
+        let state = self.subscriber.state.lock().unwrap();
+        state.closed && state.lagged == 0 && state.buffer.is_empty()
+    }
+
+    fn take_item(state: &mut StreamState) -> Option<WatchItem> {
+        if state.lagged > 0 {
+            let lagged = state.lagged;
+            state.lagged = 0;
+
+            return Some(WatchItem::Lagged(lagged));
+        }
+
+        state.buffer.pop_front().map(WatchItem::Event)
+    }
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for WatchStream {
+    type Item = WatchItem;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<WatchItem>> {
+        use std::task::Poll;
+
+        let mut state = self.subscriber.state.lock().unwrap();
+
+        match Self::take_item(&mut state) {
+            Some(item) => Poll::Ready(Some(item)),
+            None if state.closed => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
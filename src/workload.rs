@@ -0,0 +1,270 @@
+use std::thread;
+
+use rand::Rng;
+
+use crate::memfs::MemFS;
+use crate::utils::{OpenFlag, PAGE_SIZE, SeekFlag, generate_random_vector};
+
+/// Name of the concurrency backend MemFS is built with, to tell measurements of backends apart.
+#[cfg(feature = "coarse-grained")]
+pub const BACKEND: &str = "coarse-grained";
+#[cfg(feature = "fine-grained")]
+pub const BACKEND: &str = "fine-grained";
+#[cfg(feature = "lock-free")]
+pub const BACKEND: &str = "lock-free";
+
+/// Concurrent workloads measuring the throughput of MemFS, shared by the benchmarks and the tests.
+///
+/// A workload spreads a total number of operations over threads, each thread working on its own
+/// files or directories, or all of them on the same ones, as the name tells. On a rewritten file,
+/// the first thread overwrites the file while the others read it, through plain descriptors or
+/// through O_SNAPSHOT ones pinning its contents at open time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Workload {
+    CreatesOnSameDirectory,
+    CreatesOnDifferentDirectories,
+    ExclusiveCreatesOnDifferentDirectories,
+    RemovesOnDifferentDirectories,
+    WritesOnSingleDescriptor,
+    AppendsOnSingleDescriptor,
+    WritesOnSingleFileThroughMultipleDescriptors,
+    WritesOnMultipleFiles,
+    ReadsOnSingleFile,
+    ReadsAndWritesOnSingleFile,
+    ReadsOnRewrittenFile,
+    SnapshotReadsOnRewrittenFile,
+    LseekOnSingleDescriptor,
+    MkdirOnSameDirectory,
+    MkdirOnDifferentDirectories,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 15] = [
+        Workload::CreatesOnSameDirectory,
+        Workload::CreatesOnDifferentDirectories,
+        Workload::ExclusiveCreatesOnDifferentDirectories,
+        Workload::RemovesOnDifferentDirectories,
+        Workload::WritesOnSingleDescriptor,
+        Workload::AppendsOnSingleDescriptor,
+        Workload::WritesOnSingleFileThroughMultipleDescriptors,
+        Workload::WritesOnMultipleFiles,
+        Workload::ReadsOnSingleFile,
+        Workload::ReadsAndWritesOnSingleFile,
+        Workload::ReadsOnRewrittenFile,
+        Workload::SnapshotReadsOnRewrittenFile,
+        Workload::LseekOnSingleDescriptor,
+        Workload::MkdirOnSameDirectory,
+        Workload::MkdirOnDifferentDirectories,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Workload::CreatesOnSameDirectory => "creates_on_same_directory",
+            Workload::CreatesOnDifferentDirectories => "creates_on_different_directories",
+            Workload::ExclusiveCreatesOnDifferentDirectories => "exclusive_creates_on_different_directories",
+            Workload::RemovesOnDifferentDirectories => "removes_on_different_directories",
+            Workload::WritesOnSingleDescriptor => "writes_on_single_descriptor",
+            Workload::AppendsOnSingleDescriptor => "appends_on_single_descriptor",
+            Workload::WritesOnSingleFileThroughMultipleDescriptors => {
+                "writes_on_single_file_through_multiple_descriptors"
+            }
+            Workload::WritesOnMultipleFiles => "writes_on_multiple_files",
+            Workload::ReadsOnSingleFile => "reads_on_single_file",
+            Workload::ReadsAndWritesOnSingleFile => "reads_and_writes_on_single_file",
+            Workload::ReadsOnRewrittenFile => "reads_on_rewritten_file",
+            Workload::SnapshotReadsOnRewrittenFile => "snapshot_reads_on_rewritten_file",
+            Workload::LseekOnSingleDescriptor => "lseek_on_single_descriptor",
+            Workload::MkdirOnSameDirectory => "mkdir_on_same_directory",
+            Workload::MkdirOnDifferentDirectories => "mkdir_on_different_directories",
+        }
+    }
+
+    /// Creates a new MemFS holding what the workload works on, such as the directories of the threads,
+    /// the files to remove or the file to read, so that only the operations themselves are measured.
+    pub fn prepare(self, thread_count: usize, total_works: usize) -> PreparedWorkload {
+        assert!(thread_count > 0);
+
+        let fs = MemFS::new();
+        let mut fds = Vec::new();
+
+        match self {
+            Workload::CreatesOnDifferentDirectories
+            | Workload::ExclusiveCreatesOnDifferentDirectories
+            | Workload::MkdirOnDifferentDirectories => {
+                for i in 0..thread_count {
+                    fs.mkdir(&format!("/dir{}", i)).unwrap();
+                }
+            }
+            Workload::RemovesOnDifferentDirectories => {
+                for i in 0..thread_count {
+                    fs.mkdir(&format!("/dir{}", i)).unwrap();
+
+                    for j in 0..works_of_thread(i, thread_count, total_works) {
+                        let fd = fs.open(&format!("/dir{}/{}.rs", i, j), OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+                        fs.close(fd.unwrap()).unwrap();
+                    }
+                }
+            }
+            Workload::WritesOnSingleDescriptor => {
+                fds.push(fs.open("/single", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap());
+            }
+            Workload::AppendsOnSingleDescriptor => {
+                let flags = OpenFlag::O_CREAT | OpenFlag::O_RDWR | OpenFlag::O_APPEND;
+                fds.push(fs.open("/single", flags).unwrap());
+            }
+            Workload::WritesOnSingleFileThroughMultipleDescriptors => {
+                for _ in 0..thread_count {
+                    fds.push(fs.open("/single", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap());
+                }
+            }
+            Workload::WritesOnMultipleFiles => {
+                for i in 0..thread_count {
+                    fds.push(fs.open(&format!("/file{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap());
+                }
+            }
+            Workload::ReadsOnSingleFile | Workload::ReadsAndWritesOnSingleFile => {
+                let fd = fs.open("/single", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+                fs.write(fd, &generate_random_vector(PAGE_SIZE)).unwrap();
+                fs.close(fd).unwrap();
+
+                let flags = match self {
+                    Workload::ReadsOnSingleFile => OpenFlag::O_RDONLY,
+                    _ => OpenFlag::O_RDWR,
+                };
+
+                for _ in 0..thread_count {
+                    fds.push(fs.open("/single", flags.clone()).unwrap());
+                }
+            }
+            Workload::ReadsOnRewrittenFile | Workload::SnapshotReadsOnRewrittenFile => {
+                let fd = fs.open("/single", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+                fs.write(fd, &generate_random_vector(PAGE_SIZE)).unwrap();
+                fds.push(fd);
+
+                let flags = match self {
+                    Workload::ReadsOnRewrittenFile => OpenFlag::O_RDONLY,
+                    _ => OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT,
+                };
+
+                for _ in 1..thread_count {
+                    fds.push(fs.open("/single", flags.clone()).unwrap());
+                }
+            }
+            Workload::LseekOnSingleDescriptor => {
+                let fd = fs.open("/single", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+                fs.write(fd, &generate_random_vector(PAGE_SIZE)).unwrap();
+                fds.push(fd);
+            }
+            Workload::CreatesOnSameDirectory | Workload::MkdirOnSameDirectory => {}
+        }
+
+        PreparedWorkload {
+            workload: self,
+            fs,
+            fds,
+            thread_count,
+            total_works,
+        }
+    }
+
+    /// Number of operations expected to succeed once a prepared workload runs.
+    ///
+    /// Each read and each write counts as an operation, so does each create racing with O_EXCL,
+    /// where only the first one of every thread succeeds.
+    pub fn expected_successes(&self, thread_count: usize, total_works: usize) -> usize {
+        match self {
+            Workload::ExclusiveCreatesOnDifferentDirectories => thread_count.min(total_works),
+            Workload::ReadsAndWritesOnSingleFile => 2 * total_works,
+            _ => total_works,
+        }
+    }
+}
+
+/// Number of operations the thread at the given index makes, out of the total spread over all threads.
+pub fn works_of_thread(index: usize, thread_count: usize, total_works: usize) -> usize {
+    total_works / thread_count + usize::from(index < total_works % thread_count)
+}
+
+/// A workload ready to run on its own MemFS, returned by [Workload::prepare].
+pub struct PreparedWorkload {
+    workload: Workload,
+    fs: MemFS,
+    fds: Vec<usize>,
+    thread_count: usize,
+    total_works: usize,
+}
+
+impl PreparedWorkload {
+    pub fn fs(&self) -> &MemFS {
+        &self.fs
+    }
+
+    /// Descriptors opened by [Workload::prepare], one for every thread if they do not share one.
+    pub fn fds(&self) -> &[usize] {
+        &self.fds
+    }
+
+    /// Runs the operations over the threads, and returns how many of them succeeded.
+    pub fn run(&self) -> usize {
+        thread::scope(|scope| {
+            let handles = (0..self.thread_count)
+                .map(|i| scope.spawn(move || self.run_thread(i)))
+                .collect::<Vec<_>>();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).sum()
+        })
+    }
+
+    fn run_thread(&self, index: usize) -> usize {
+        let fs = &self.fs;
+        let works = works_of_thread(index, self.thread_count, self.total_works);
+        let fd = || self.fds[index % self.fds.len()];
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        let mut successes = 0;
+
+        for j in 0..works {
+            successes += match self.workload {
+                Workload::CreatesOnSameDirectory => {
+                    let result = fs.open(&format!("/file{}-{}", index, j), OpenFlag::O_CREAT | OpenFlag::O_RDONLY);
+                    result.and_then(|fd| fs.close(fd)).is_ok() as usize
+                }
+                Workload::CreatesOnDifferentDirectories => {
+                    let result = fs.open(&format!("/dir{}/{}", index, j), OpenFlag::O_CREAT | OpenFlag::O_RDONLY);
+                    result.and_then(|fd| fs.close(fd)).is_ok() as usize
+                }
+                Workload::ExclusiveCreatesOnDifferentDirectories => {
+                    let flags = OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_RDWR;
+                    let result = fs.open(&format!("/dir{}/exclusive", index), flags);
+                    result.and_then(|fd| fs.close(fd)).is_ok() as usize
+                }
+                Workload::RemovesOnDifferentDirectories => {
+                    fs.unlink(&format!("/dir{}/{}.rs", index, j)).is_ok() as usize
+                }
+                Workload::WritesOnSingleDescriptor => fs.write(fd(), &[index as u8]).is_ok() as usize,
+                Workload::AppendsOnSingleDescriptor => fs.write(fd(), &[(index % 256) as u8]).is_ok() as usize,
+                Workload::WritesOnSingleFileThroughMultipleDescriptors => {
+                    fs.pwrite(fd(), &[index as u8], 0).is_ok() as usize
+                }
+                Workload::WritesOnMultipleFiles => fs.pwrite(fd(), &buffer, 0).is_ok() as usize,
+                Workload::ReadsOnSingleFile => fs.pread(fd(), &mut buffer, 0).is_ok() as usize,
+                Workload::ReadsAndWritesOnSingleFile => {
+                    fs.pread(fd(), &mut buffer, 0).is_ok() as usize + fs.pwrite(fd(), &buffer, 0).is_ok() as usize
+                }
+                Workload::ReadsOnRewrittenFile | Workload::SnapshotReadsOnRewrittenFile if index == 0 => {
+                    fs.pwrite(fd(), &buffer, 0).is_ok() as usize
+                }
+                Workload::ReadsOnRewrittenFile | Workload::SnapshotReadsOnRewrittenFile => {
+                    fs.pread(fd(), &mut buffer, 0).is_ok() as usize
+                }
+                Workload::LseekOnSingleDescriptor => {
+                    let offset = rand::rng().random_range(0..PAGE_SIZE) as i64;
+                    fs.lseek(fd(), offset, SeekFlag::SEEK_SET).is_ok() as usize
+                }
+                Workload::MkdirOnSameDirectory => fs.mkdir(&format!("/dir{}-{}", index, j)).is_ok() as usize,
+                Workload::MkdirOnDifferentDirectories => fs.mkdir(&format!("/dir{}/{}", index, j)).is_ok() as usize,
+            };
+        }
+
+        successes
+    }
+}
@@ -0,0 +1,94 @@
+use crate::memfs::MemFS;
+use crate::utils::{FILE_MAX_SIZE, MemFSErr, Result};
+
+/// Number of bytes a [MemFSWriter] buffers before writing them to the file.
+pub const DEFAULT_WRITER_BUFFER: usize = 1 << 10;
+
+/// Buffered writer of a new file, returned by [MemFS::create_writer].
+///
+/// Chunks are appended to the file whenever the buffer fills up, so the contents never have to be
+/// assembled in a single vector. [MemFSWriter::finish] writes the rest, syncs and closes the file.
+/// A writer dropped without finishing still writes what it buffered, but errors are lost.
+pub struct MemFSWriter<'a> {
+    fs: &'a MemFS,
+    fd: usize,
+    buffer: Vec<u8>,
+    capacity: usize,
+    written: usize,
+    closed: bool,
+}
+
+impl<'a> MemFSWriter<'a> {
+    pub(crate) fn new(fs: &'a MemFS, fd: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            fs,
+            fd,
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+            written: 0,
+            closed: false,
+        }
+    }
+
+    /// Appends a chunk. Fails with EFBIG, leaving the file as it was, if the file would outgrow [FILE_MAX_SIZE].
+    pub fn write_chunk(&mut self, mut chunk: &[u8]) -> Result<()> {
+        if self.len() + chunk.len() > FILE_MAX_SIZE {
+            return Err(MemFSErr::file_too_large());
+        }
+
+        while !chunk.is_empty() {
+            let taken = chunk.len().min(self.capacity - self.buffer.len());
+
+            self.buffer.extend_from_slice(&chunk[..taken]);
+            chunk = &chunk[taken..];
+
+            if self.buffer.len() == self.capacity {
+                self.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of bytes written so far, including buffered ones.
+    pub fn len(&self) -> usize {
+        self.written + self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes buffered bytes, syncs and closes the file. Returns the size of the file.
+    pub fn finish(mut self) -> Result<usize> {
+        self.flush()?;
+        self.fs.fsync(self.fd)?;
+
+        self.closed = true;
+        self.fs.close(self.fd)?;
+
+        Ok(self.written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.written += self.fs.write(self.fd, &self.buffer)?;
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+impl Drop for MemFSWriter<'_> {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.flush();
+            let _ = self.fs.close(self.fd);
+        }
+    }
+}
@@ -8,7 +8,7 @@ use std::{
     time::Instant,
 };
 
-use memfs::utils::{FILE_MAX_SIZE, generate_random_vector};
+use memfs::utils::{PAGE_SIZE, generate_random_vector};
 
 const TOTAL_WORKS: usize = 1usize << 16;
 
@@ -146,7 +146,7 @@ fn helper_fs_reads_from_single_file_through_multiple_file_descriptors(thread_cou
 
     let work_per_thread = TOTAL_WORKS / thread_count;
     let true_total_work = work_per_thread * thread_count;
-    let buffer_size = FILE_MAX_SIZE;
+    let buffer_size = PAGE_SIZE;
     let file_name = "ex/readers.txt";
     let mut handles = Vec::new();
     let mut fds = Vec::new();
@@ -174,7 +174,7 @@ fn helper_fs_reads_from_single_file_through_multiple_file_descriptors(thread_cou
     for mut fd in fds.into_iter() {
         handles.push(thread::spawn(move || {
             let mut read_success = 0;
-            let mut read_buffer = vec![0; FILE_MAX_SIZE];
+            let mut read_buffer = vec![0; PAGE_SIZE];
 
             for _ in 0..work_per_thread {
                 if fd.read(read_buffer.as_mut_slice()).is_ok() {
@@ -0,0 +1,61 @@
+#![cfg(feature = "tokio")]
+
+use std::io::SeekFrom;
+
+use memfs::aio::AsyncMemFS;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_async_calls_should_read_back_written_data() {
+    /* Arrange */
+
+    let fs = AsyncMemFS::new(MemFS::new());
+    let mut buffer = [0u8; 5];
+
+    /* Action */
+
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).await.unwrap();
+    let written = fs.write(fd, b"hello").await.unwrap();
+    fs.close(fd).await.unwrap();
+
+    let fd = fs.open("/file", OpenFlag::O_RDONLY).await.unwrap();
+    let read = fs.read(fd, &mut buffer).await.unwrap();
+    fs.close(fd).await.unwrap();
+
+    let missing = fs.open("/missing", OpenFlag::O_RDONLY).await;
+
+    /* Assert */
+
+    assert_eq!(written, 5);
+    assert_eq!(read, 5);
+    assert_eq!(&buffer, b"hello");
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[tokio::test]
+async fn test_async_file_should_implement_tokio_io_traits() {
+    /* Arrange */
+
+    let fs = AsyncMemFS::new(MemFS::new());
+    let mut contents = String::new();
+
+    /* Action */
+
+    let mut file = fs.open_file("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).await.unwrap();
+    file.write_all(b"0123456789").await.unwrap();
+    file.flush().await.unwrap();
+
+    let position = file.seek(SeekFrom::End(-4)).await.unwrap();
+    file.read_to_string(&mut contents).await.unwrap();
+    let before_start = file.seek(SeekFrom::Current(-100)).await;
+    drop(file);
+
+    /* Assert */
+
+    assert_eq!(position, 6);
+    assert_eq!(contents, "6789");
+    assert!(before_start.is_err_and(|e| e.kind() == std::io::ErrorKind::InvalidInput));
+    assert_eq!(fs.inner().metrics().open_file_descriptors, 0);
+}
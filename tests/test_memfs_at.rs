@@ -0,0 +1,93 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{AT_FDCWD, AtFlag, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_openat_should_resolve_from_directory_through_renames() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkdir("/old").unwrap();
+    fs.mkdir("/cwd").unwrap();
+    fs.chdir("/cwd").unwrap();
+    let dirfd = fs.open("/old", OpenFlag::O_DIRECTORY | OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    fs.rename("/old", "/new").unwrap();
+    fs.mkdirat(dirfd, "sub").unwrap();
+    let fd = fs
+        .openat(dirfd, "sub/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
+        .unwrap();
+    fs.write(fd, b"data").unwrap();
+    fs.close(fd).unwrap();
+
+    fs.openat(dirfd, "/top", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.openat(AT_FDCWD, "here", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    let cwd_after = fs.getcwd().unwrap();
+
+    let replayed = MemFS::new();
+    fs.journal().unwrap().replay(&replayed).unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/new/sub/file").unwrap().size, 4);
+    assert!(fs.stat("/top").is_ok());
+    assert!(fs.stat("/cwd/here").is_ok());
+    assert_eq!(cwd_after, "/cwd");
+    assert_eq!(replayed.stat("/new/sub/file").unwrap().size, 4);
+}
+
+#[test]
+fn test_unlinkat_should_remove_files_and_directories() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/empty").unwrap();
+    fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    let dirfd = fs.open("/dir", OpenFlag::O_DIRECTORY | OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    let file_as_directory = fs.unlinkat(dirfd, "file", AtFlag::AT_REMOVEDIR);
+    let directory_as_file = fs.unlinkat(dirfd, "empty", AtFlag::empty());
+    fs.unlinkat(dirfd, "file", AtFlag::empty()).unwrap();
+    fs.unlinkat(dirfd, "empty", AtFlag::AT_REMOVEDIR).unwrap();
+
+    /* Assert */
+
+    assert!(file_as_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(directory_as_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(fs.readdir("/dir").unwrap().is_empty());
+}
+
+#[test]
+fn test_directory_descriptor_should_check_flags_and_usage() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let file = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let dirfd = fs.open("/dir", OpenFlag::O_DIRECTORY | OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    let on_file = fs.open("/file", OpenFlag::O_DIRECTORY | OpenFlag::O_RDONLY);
+    let writable = fs.open("/dir", OpenFlag::O_DIRECTORY | OpenFlag::O_RDWR);
+    let read = fs.read(dirfd, &mut [0u8; 4]);
+    let write = fs.write(dirfd, b"x");
+    let from_file = fs.openat(file, "name", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+    let from_closed = fs.mkdirat(99, "name");
+    let stat = fs.fstat(dirfd).unwrap();
+    fs.close(dirfd).unwrap();
+
+    /* Assert */
+
+    assert!(on_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(writable.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(read.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(write.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(from_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(from_closed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert_eq!(stat.ino, fs.stat("/dir").unwrap().ino);
+}
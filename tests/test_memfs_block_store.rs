@@ -0,0 +1,154 @@
+use std::{fs, path::PathBuf};
+
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::pool::{BlockStore, MemoryPool, MmapStore, OverflowStore};
+use memfs::utils::{FallocateMode, MemFSErrType, OpenFlag, PAGE_SIZE, generate_random_vector};
+
+fn host_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("memfs_store_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; fs.stat(path).unwrap().size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_memory_pool_should_bound_pages_of_files() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().block_store(MemoryPool::with_preallocated(2)).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let fitting = fs.pwrite(fd, &generate_random_vector(2 * PAGE_SIZE), 0);
+    let exceeding = fs.pwrite(fd, &[1], 2 * PAGE_SIZE);
+
+    /* Assert */
+
+    assert!(fitting.is_ok());
+    assert!(exceeding.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOMEM)));
+    assert_eq!(fs.statfs().blocks, 2);
+    assert_eq!(fs.statfs().blocks_free, 0);
+}
+
+#[test]
+fn test_truncate_should_give_pages_past_new_size_back_to_pool() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().block_store(MemoryPool::with_preallocated(4)).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let data = generate_random_vector(3 * PAGE_SIZE);
+    fs.write(fd, &data).unwrap();
+    fs.fallocate(fd, FallocateMode::KeepSize, 0, 4 * PAGE_SIZE).unwrap();
+    let before = fs.statfs().blocks_free;
+
+    /* Action */
+
+    fs.ftruncate(fd, PAGE_SIZE + 10).unwrap();
+    let after = fs.statfs().blocks_free;
+
+    fs.ftruncate(fd, 2 * PAGE_SIZE).unwrap();
+    let mut contents = vec![0xffu8; 2 * PAGE_SIZE];
+    fs.pread(fd, &mut contents, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(before, 0);
+    assert_eq!(after, 2);
+    assert_eq!(fs.statfs().blocks_free, 2);
+    assert_eq!(&contents[..PAGE_SIZE + 10], &data[..PAGE_SIZE + 10]);
+    assert!(contents[PAGE_SIZE + 10..].iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn test_mmap_store_should_hold_contents_in_host_file() {
+    /* Arrange */
+
+    let dir = host_dir("mmap");
+    let fs = MemFSBuilder::new().block_store(MmapStore::create(dir.join("pages"), 8).unwrap()).build();
+    let data = generate_random_vector(3 * PAGE_SIZE + 17);
+
+    /* Action */
+
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.close(fd).unwrap();
+
+    let read = read_file(&fs, "/file");
+    let used = fs.statfs().blocks_used;
+
+    /* Assert */
+
+    assert_eq!(read, data);
+    assert_eq!(used, 4);
+    assert_eq!(fs::metadata(dir.join("pages")).unwrap().len(), (8 * PAGE_SIZE) as u64);
+
+    drop(fs);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_released_mapped_pages_should_be_reused_zeroed() {
+    /* Arrange */
+
+    let dir = host_dir("reuse");
+    let store = MmapStore::create(dir.join("pages"), 1).unwrap();
+    let page = store.allocate().unwrap();
+    unsafe { (*page.as_ptr()).fill(0xff) };
+
+    /* Action */
+
+    unsafe { store.release(page) };
+    let again = store.allocate().unwrap();
+    let exhausted = store.allocate();
+
+    /* Assert */
+
+    assert_eq!(again, page);
+    assert!(unsafe { (*again.as_ptr()).iter().all(|byte| *byte == 0) });
+    assert!(exhausted.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOMEM)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_overflow_store_should_put_pages_beyond_memory_on_disk() {
+    /* Arrange */
+
+    let dir = host_dir("overflow");
+    let store = OverflowStore::new(2, &dir, 16).unwrap();
+    let data = generate_random_vector(6 * PAGE_SIZE);
+
+    /* Action */
+
+    let overflowed_before = store.overflowed();
+    let fs = MemFSBuilder::new().block_store(store).build();
+    let fd = fs.open("/big", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &data).unwrap();
+    let copy = fs.open("/copy", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(copy, &data[..PAGE_SIZE]).unwrap();
+
+    let statfs = fs.statfs();
+
+    /* Assert */
+
+    assert_eq!(overflowed_before, 0);
+    assert_eq!(read_file(&fs, "/big"), data);
+    assert_eq!(read_file(&fs, "/copy"), &data[..PAGE_SIZE]);
+    assert_eq!(statfs.blocks, 18);
+    assert_eq!(statfs.blocks_used, 7);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    drop(fs);
+    fs::remove_dir_all(&dir).unwrap();
+}
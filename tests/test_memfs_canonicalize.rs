@@ -0,0 +1,87 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_canonicalize_should_resolve_dots_and_relative_paths() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.create_dir_all("/a/b/c").unwrap();
+    fs.open("/a/b/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.chdir("/a/b").unwrap();
+
+    /* Action */
+
+    let dotted = fs.canonicalize("/a/./b//c/../file").unwrap();
+    let relative = fs.canonicalize("c/..").unwrap();
+    let current = fs.canonicalize(".").unwrap();
+    let above_root = fs.canonicalize("/../../a").unwrap();
+    let root = fs.canonicalize("/..").unwrap();
+
+    /* Assert */
+
+    assert_eq!(dotted, "/a/b/file");
+    assert_eq!(relative, "/a/b");
+    assert_eq!(current, "/a/b");
+    assert_eq!(above_root, "/a");
+    assert_eq!(root, "/");
+}
+
+#[test]
+fn test_canonicalize_should_follow_symbolic_links() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.create_dir_all("/real/dir").unwrap();
+    fs.open("/real/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.symlink("/real", "/abs").unwrap();
+    fs.symlink("dir/file", "/real/rel").unwrap();
+    fs.symlink("../rel", "/real/dir/up").unwrap();
+
+    /* Action */
+
+    let through_directory = fs.canonicalize("/abs/dir/file").unwrap();
+    let relative_target = fs.canonicalize("/abs/rel").unwrap();
+    let chained = fs.canonicalize("/abs/dir/up").unwrap();
+    let link_to_directory = fs.canonicalize("/abs").unwrap();
+
+    fs.rename("/real", "/moved").unwrap();
+    let after_rename = fs.canonicalize("/moved/dir/up").unwrap();
+
+    /* Assert */
+
+    assert_eq!(through_directory, "/real/dir/file");
+    assert_eq!(relative_target, "/real/dir/file");
+    assert_eq!(chained, "/real/dir/file");
+    assert_eq!(link_to_directory, "/real");
+    assert_eq!(after_rename, "/moved/dir/file");
+}
+
+#[test]
+fn test_canonicalize_should_fail_on_missing_entries_and_loops() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.symlink("/loop_b", "/loop_a").unwrap();
+    fs.symlink("/loop_a", "/loop_b").unwrap();
+    fs.symlink("/missing", "/dangling").unwrap();
+
+    /* Action */
+
+    let missing = fs.canonicalize("/missing");
+    let dangling = fs.canonicalize("/dangling");
+    let looping = fs.canonicalize("/loop_a");
+    let file_as_directory = fs.canonicalize("/file/");
+    let dot_of_file = fs.canonicalize("/file/.");
+    let empty = fs.canonicalize("");
+
+    /* Assert */
+
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(dangling.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(looping.is_err_and(|e| matches!(e.err_type, MemFSErrType::ELOOP)));
+    assert!(file_as_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(dot_of_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(empty.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
@@ -0,0 +1,87 @@
+use memfs::changes::Change;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+use memfs::watch::WatchEventKind;
+
+#[test]
+fn test_changes_since_should_fail_without_change_tracking() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    let result = fs.changes_since(0);
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(fs.stat("/").unwrap().change_seq, 0);
+}
+
+#[test]
+fn test_changes_since_should_return_latest_change_of_each_path_after_sequence() {
+    /* Arrange */
+
+    let fs = MemFS::builder().change_tracking(true).build();
+    let data = vec![b'c'; 8];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs
+        .open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    let bookmark = fs.last_change_seq().unwrap();
+
+    /* Action */
+
+    fs.write(fd, &data).unwrap();
+    fs.mkdir("/other").unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.rmdir("/other").unwrap();
+
+    let changes = fs.changes_since(bookmark).unwrap();
+
+    /* Assert */
+
+    assert_eq!(bookmark, 2);
+    assert_eq!(
+        changes,
+        vec![
+            Change {
+                path: "/dir/file".to_string(),
+                kind: WatchEventKind::Modify,
+                seq: 5,
+            },
+            Change {
+                path: "/other".to_string(),
+                kind: WatchEventKind::Delete,
+                seq: 6,
+            },
+        ]
+    );
+    assert_eq!(fs.changes_since(0).unwrap().len(), 3);
+    assert!(fs.changes_since(6).unwrap().is_empty());
+}
+
+#[test]
+fn test_stat_should_report_change_sequence_of_nodes() {
+    /* Arrange */
+
+    let fs = MemFS::builder().change_tracking(true).build();
+    let data = vec![b's'; 4];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs
+        .open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+
+    /* Action */
+
+    fs.write(fd, &data).unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/").unwrap().change_seq, 1);
+    assert_eq!(fs.stat("/dir").unwrap().change_seq, 2);
+    assert_eq!(fs.stat("/dir/file").unwrap().change_seq, 3);
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::process::{Credentials, MemFSProcess};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_confined_view_should_clamp_paths_at_its_root() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.create_dir_all("/sandbox/inner").unwrap();
+    fs.mkdir("/secret").unwrap();
+    let view = fs.confined_view("/sandbox").unwrap();
+
+    /* Action */
+
+    let fd = view.open("/../../created", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    view.close(fd).unwrap();
+    view.chdir("inner/../..").unwrap();
+    let cwd = view.getcwd();
+    let escaped = view.stat("/secret");
+    let relative_escape = view.stat("../../secret");
+    view.run(|fs| fs.symlink("/inner", "/link")).unwrap();
+    let canonical = view.canonicalize("/link/../link");
+
+    /* Assert */
+
+    assert!(fs.stat("/sandbox/created").is_ok());
+    assert!(fs.stat("/created").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(cwd.unwrap(), "/");
+    assert!(escaped.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(relative_escape.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(canonical.unwrap(), "/inner");
+    assert_eq!(view.readdir("/").unwrap().len(), 3);
+    assert_eq!(fs.readdir("/").unwrap().len(), 2);
+}
+
+#[test]
+fn test_chroot_should_bypass_dentry_cache_and_survive_fork() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFSBuilder::new().dentry_cache(64).build());
+    fs.create_dir_all("/jail/etc").unwrap();
+    fs.create_dir_all("/etc/host").unwrap();
+    fs.stat("/etc/host").unwrap();
+    let process = MemFSProcess::new(fs.clone());
+
+    /* Action */
+
+    process.chroot("/jail").unwrap();
+    let child = process.fork();
+    let confined = child.stat("/etc/host");
+    let nested = child.chroot("etc");
+    let unconfined = fs.stat("/etc/host");
+
+    /* Assert */
+
+    assert!(confined.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(nested.is_ok());
+    assert_eq!(child.readdir("/..").unwrap().len(), 0);
+    assert_eq!(process.canonicalize("/etc").unwrap(), "/etc");
+    assert!(unconfined.is_ok());
+}
+
+#[test]
+fn test_chroot_should_require_superuser_with_permission_checks() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFSBuilder::new().permission_checks(true).build());
+    fs.mkdir("/jail").unwrap();
+    fs.open("/jail/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 1000));
+    let root = MemFSProcess::new(fs.clone());
+
+    /* Action */
+
+    let denied = user.chroot("/jail");
+    let missing = root.chroot("/missing");
+    let file = root.chroot("/jail/file");
+    let allowed = root.chroot("/jail");
+
+    /* Assert */
+
+    assert!(denied.is_err_and(|e| matches!(e.err_type, MemFSErrType::EPERM)));
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(allowed.is_ok());
+    assert!(root.stat("/file").is_ok());
+}
@@ -0,0 +1,143 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use memfs::clock::VirtualClock;
+use memfs::latency::{LatencyDistribution, LatencyProfile};
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::metrics::MemFSOp;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn start() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_000_000)
+}
+
+fn simulated() -> (MemFS, VirtualClock) {
+    let clock = VirtualClock::new(start());
+
+    (MemFSBuilder::new().virtual_clock(clock.clone()).build(), clock)
+}
+
+#[test]
+fn test_files_should_be_stamped_with_virtual_time() {
+    /* Arrange */
+
+    let (fs, clock) = simulated();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let mut buffer = [0u8; 4];
+
+    /* Action */
+
+    clock.advance(Duration::from_secs(5));
+    fs.write(fd, b"data").unwrap();
+
+    clock.advance(Duration::from_secs(5));
+    fs.pread(fd, &mut buffer, 0).unwrap();
+
+    let stat = fs.stat("/file").unwrap();
+    let root = fs.stat("/").unwrap();
+
+    /* Assert */
+
+    assert_eq!(stat.btime, start());
+    assert_eq!(stat.mtime, start() + Duration::from_secs(5));
+    assert_eq!(stat.atime, start() + Duration::from_secs(10));
+    assert_eq!(root.btime, start());
+    assert_eq!(root.mtime, start());
+}
+
+#[test]
+fn test_maintenance_tasks_should_run_as_virtual_clock_advances() {
+    /* Arrange */
+
+    let (fs, clock) = simulated();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counted = runs.clone();
+
+    fs.maintenance().schedule(Duration::from_secs(10), move || {
+        counted.fetch_add(1, Ordering::SeqCst);
+    });
+
+    /* Action */
+
+    clock.advance(Duration::from_secs(9));
+    let before_due = runs.load(Ordering::SeqCst);
+
+    clock.advance(Duration::from_secs(1));
+    let once_due = runs.load(Ordering::SeqCst);
+
+    clock.advance(Duration::from_secs(25));
+    let late = runs.load(Ordering::SeqCst);
+
+    /* Assert */
+
+    assert_eq!(before_due, 0);
+    assert_eq!(once_due, 1);
+    assert_eq!(late, 2);
+}
+
+#[test]
+fn test_timeouts_should_pass_on_virtual_clock() {
+    /* Arrange */
+
+    let clock = VirtualClock::new(start());
+    let fs = Arc::new(MemFSBuilder::new().virtual_clock(clock.clone()).exclusive_lock(true).build());
+    let holder = fs.clone();
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+
+    let holding = thread::spawn(move || {
+        let _guard = holder.lock_exclusive().unwrap();
+        locked_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+
+    locked_rx.recv().unwrap();
+
+    /* Action */
+
+    let advancing = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        clock.advance(HOUR);
+    });
+
+    let started = Instant::now();
+    let result = fs.try_lock_exclusive_for(HOUR).map(|_| ());
+    let waited = started.elapsed();
+
+    release_tx.send(()).unwrap();
+    advancing.join().unwrap();
+    holding.join().unwrap();
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(waited < Duration::from_secs(60));
+}
+
+#[test]
+fn test_injected_latency_should_advance_virtual_clock() {
+    /* Arrange */
+
+    let clock = VirtualClock::new(start());
+    let profile = LatencyProfile::new().op(MemFSOp::Mkdir, LatencyDistribution::Fixed(HOUR));
+    let fs = MemFSBuilder::new().virtual_clock(clock.clone()).latency(profile).build();
+    let started = Instant::now();
+
+    /* Action */
+
+    fs.mkdir("/slow").unwrap();
+
+    /* Assert */
+
+    assert!(started.elapsed() < Duration::from_secs(60));
+    assert_eq!(clock.now(), start() + HOUR);
+    assert_eq!(fs.metrics().latency(MemFSOp::Mkdir).count(), 1);
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "compression")]
+
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::utils::{MemFSErrType, OpenFlag, PAGE_SIZE};
+
+fn fixture(len: usize) -> Vec<u8> {
+    (0..len).map(|i| b"fixture tree "[i % 13]).collect()
+}
+
+#[test]
+fn test_compressed_files_should_read_back_without_taking_pages() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().compression(true).build();
+    let data = fixture(4 * PAGE_SIZE + 7);
+    let mut read_back = vec![0u8; data.len()];
+    let mut middle = vec![0u8; 10];
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    let fd = fs.open("/fixture", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.pwrite(fd, b"0123456789", PAGE_SIZE - 5).unwrap();
+    let read = fs.pread(fd, &mut read_back, 0).unwrap();
+    fs.pread(fd, &mut middle, PAGE_SIZE - 5).unwrap();
+    let stats = fs.compression_stats();
+
+    /* Assert */
+
+    assert_eq!(read, data.len());
+    assert_eq!(&read_back[..PAGE_SIZE - 5], &data[..PAGE_SIZE - 5]);
+    assert_eq!(&read_back[PAGE_SIZE + 5..], &data[PAGE_SIZE + 5..]);
+    assert_eq!(&middle, b"0123456789");
+    assert_eq!(fs.statfs().blocks_free, blocks_free);
+    assert_eq!(stats.files, 1);
+    assert_eq!(stats.page_bytes, (5 * PAGE_SIZE) as u64);
+    assert!(stats.ratio() > 1.0);
+}
+
+#[test]
+fn test_set_compression_should_move_pages_out_of_and_back_into_the_pool() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = fixture(3 * PAGE_SIZE);
+    let mut read_back = vec![0u8; data.len()];
+    let fd = fs.open("/fixture", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &data).unwrap();
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    fs.set_compression("/fixture", true).unwrap();
+    let free_compressed = fs.statfs().blocks_free;
+    let stats_compressed = fs.compression_stats();
+
+    fs.set_compression("/fixture", false).unwrap();
+    let free_decompressed = fs.statfs().blocks_free;
+    let stats_decompressed = fs.compression_stats();
+    fs.pread(fd, &mut read_back, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(free_compressed, blocks_free + 3);
+    assert_eq!(stats_compressed.files, 1);
+    assert_eq!(free_decompressed, blocks_free);
+    assert_eq!(stats_decompressed.files, 0);
+    assert_eq!(stats_decompressed.ratio(), 1.0);
+    assert_eq!(read_back, data);
+}
+
+#[test]
+fn test_set_compression_should_fail_on_directories() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+
+    /* Action */
+
+    let result = fs.set_compression("/dir", true);
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+}
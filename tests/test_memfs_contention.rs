@@ -0,0 +1,154 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_hot_nodes_should_fail_without_contention_stats() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    let result = fs.hot_nodes(10);
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
+
+#[test]
+fn test_hot_nodes_should_rank_busier_directory_first() {
+    /* Arrange */
+
+    let fs = MemFS::builder().contention_stats(true).build();
+
+    fs.mkdir("/hot").unwrap();
+    fs.mkdir("/cold").unwrap();
+
+    /* Action */
+
+    for i in 0..50 {
+        let path = format!("/hot/file_{}", i);
+        let fd = fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+        fs.close(fd).unwrap();
+        fs.unlink(&path).unwrap();
+    }
+
+    let fd = fs
+        .open("/cold/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.close(fd).unwrap();
+
+    let nodes = fs.hot_nodes(usize::MAX).unwrap();
+    let position = |path: &str| nodes.iter().position(|n| n.path == path).unwrap();
+
+    /* Assert */
+
+    assert!(position("/hot") < position("/cold"));
+    assert!(nodes.iter().all(|n| n.file_type == FileType::Directory || n.path == "/cold/file"));
+    assert_eq!(fs.hot_nodes(1).unwrap().len(), 1);
+}
+
+#[test]
+fn test_hot_nodes_should_count_file_accesses() {
+    /* Arrange */
+
+    let fs = MemFS::builder().contention_stats(true).build();
+    let data = vec![b'c'; 16];
+    let mut buffer = vec![0u8; 16];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs
+        .open("/dir/data.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+
+    /* Action */
+
+    for _ in 0..3 {
+        fs.write(fd, &data).unwrap();
+    }
+
+    for _ in 0..2 {
+        fs.read(fd, &mut buffer).unwrap();
+    }
+
+    fs.close(fd).unwrap();
+
+    let nodes = fs.hot_nodes(usize::MAX).unwrap();
+    let file = nodes.iter().find(|n| n.path == "/dir/data.txt").unwrap();
+
+    /* Assert */
+
+    assert_eq!(file.file_type, FileType::File);
+    assert_eq!(file.acquisitions, 5);
+    assert_eq!(file.contended, 0);
+}
+
+#[test]
+fn test_hot_nodes_should_sum_accesses_of_threads() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().contention_stats(true).build());
+
+    fs.mkdir("/shared").unwrap();
+
+    /* Action */
+
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                for i in 0..100 {
+                    let path = format!("/shared/file_{}_{}", t, i);
+                    let fd = fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+                    fs.close(fd).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let nodes = fs.hot_nodes(usize::MAX).unwrap();
+    let shared = nodes.iter().find(|n| n.path == "/shared").unwrap();
+
+    /* Assert */
+
+    assert!(shared.acquisitions >= 800);
+    assert!(shared.contended <= shared.acquisitions);
+}
+
+#[test]
+fn test_hot_nodes_should_not_count_accesses_of_removed_nodes() {
+    /* Arrange */
+
+    let fs = MemFS::builder().contention_stats(true).build();
+    let mut buffer = vec![0u8; 16];
+    let mut counted = Vec::new();
+
+    /* Action */
+
+    for _ in 0..32 {
+        fs.mkdir("/dir").unwrap();
+        let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+        fs.read(fd, &mut buffer).unwrap();
+        fs.close(fd).unwrap();
+
+        let nodes = fs.hot_nodes(usize::MAX).unwrap();
+        let acquisitions = |path: &str| nodes.iter().find(|n| n.path == path).unwrap().acquisitions;
+        counted.push((acquisitions("/dir"), acquisitions("/dir/file")));
+
+        fs.unlink("/dir/file").unwrap();
+        fs.rmdir("/dir").unwrap();
+    }
+
+    /* Assert */
+
+    assert!(counted.iter().all(|&counts| counts == counted[0]));
+    assert_eq!(counted[0].1, 1);
+}
@@ -0,0 +1,120 @@
+use memfs::copy::CopyMode;
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; fs.stat(path).unwrap().size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer[..read].to_vec()
+}
+
+#[test]
+fn test_copy_should_duplicate_file_contents() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let contents: Vec<u8> = (0..4000u32).map(|i| i as u8).collect();
+    write_file(&fs, "/source", &contents);
+
+    /* Action */
+
+    fs.copy("/source", "/deep").unwrap();
+    fs.copy_with("/source", "/shared", CopyMode::Reflink).unwrap();
+    write_file(&fs, "/source", b"changed");
+
+    /* Assert */
+
+    assert_eq!(read_file(&fs, "/deep"), contents);
+    assert_eq!(read_file(&fs, "/shared"), contents);
+    assert_ne!(fs.stat("/deep").unwrap().ino, fs.stat("/source").unwrap().ino);
+    assert_eq!(fs.stat("/source").unwrap().nlink, 1);
+}
+
+#[test]
+fn test_copy_should_fail_on_directories_and_existing_targets() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/file", b"file");
+    write_file(&fs, "/taken", b"taken");
+
+    /* Action */
+
+    let of_directory = fs.copy("/dir", "/copy");
+    let onto_existing = fs.copy("/file", "/taken");
+    let of_missing = fs.copy("/missing", "/copy");
+
+    /* Assert */
+
+    assert!(of_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(onto_existing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(of_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.stat("/copy").is_err());
+    assert_eq!(read_file(&fs, "/taken"), b"taken");
+}
+
+#[test]
+fn test_copy_recursive_should_duplicate_subtree() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/src").unwrap();
+    fs.mkdir("/src/sub").unwrap();
+    write_file(&fs, "/src/a", b"a");
+    write_file(&fs, "/src/sub/b", b"b");
+    fs.link("/src/a", "/src/sub/alias").unwrap();
+    fs.symlink("../a", "/src/sub/link").unwrap();
+
+    /* Action */
+
+    fs.copy_recursive("/src", "/deep").unwrap();
+    fs.copy_recursive_with("/src/", "/shared/", CopyMode::Reflink).unwrap();
+    write_file(&fs, "/src/sub/b", b"B");
+
+    /* Assert */
+
+    for root in ["/deep", "/shared"] {
+        assert_eq!(read_file(&fs, &format!("{root}/a")), b"a");
+        assert_eq!(read_file(&fs, &format!("{root}/sub/b")), b"b");
+        assert_eq!(read_file(&fs, &format!("{root}/sub/alias")), b"a");
+        assert_eq!(fs.stat(&format!("{root}/sub/alias")).unwrap().nlink, 1);
+        assert_eq!(fs.readlink(&format!("{root}/sub/link")).unwrap(), "../a");
+        assert_eq!(fs.stat(&format!("{root}/sub")).unwrap().file_type, FileType::Directory);
+    }
+}
+
+#[test]
+fn test_copy_recursive_should_refuse_copying_into_itself() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/src").unwrap();
+    fs.mkdir("/src/sub").unwrap();
+    write_file(&fs, "/file", b"file");
+    fs.chdir("/src/sub").unwrap();
+
+    /* Action */
+
+    let into_child = fs.copy_recursive("/src", "/src/sub/copy");
+    let into_relative = fs.copy_recursive("/src", "copy");
+    let of_file = fs.copy_recursive("/file", "/copy");
+    let onto_existing = fs.copy_recursive("/src/sub", "/src");
+
+    /* Assert */
+
+    assert!(into_child.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(into_relative.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(of_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(onto_existing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(fs.readdir("/src/sub").unwrap().is_empty());
+}
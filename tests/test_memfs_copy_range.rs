@@ -0,0 +1,115 @@
+use memfs::memfs::MemFS;
+use memfs::trace::{ReplayMode, Trace, TraceRecorder};
+use memfs::utils::{FILE_MAX_SIZE, MemFSErrType, OpenFlag, PAGE_SIZE, SeekFlag, generate_random_vector};
+
+fn read_all(fs: &MemFS, path: &str) -> Vec<u8> {
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    let mut contents = vec![0u8; fs.stat(path).unwrap().size];
+    fs.pread(fd, &mut contents, 0).unwrap();
+    fs.close(fd).unwrap();
+
+    contents
+}
+
+#[test]
+fn test_copy_file_range_should_copy_between_offsets() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let source = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let target = fs.open("/target", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(source, b"hello world").unwrap();
+    fs.lseek(source, 6, SeekFlag::SEEK_SET).unwrap();
+
+    /* Action */
+
+    let from_descriptor = fs.copy_file_range(source, None, target, None, 100).unwrap();
+    let at_offsets = fs.copy_file_range(source, Some(0), target, Some(8), 5).unwrap();
+    let at_end = fs.copy_file_range(source, None, target, None, 5).unwrap();
+
+    /* Assert */
+
+    assert_eq!(from_descriptor, 5);
+    assert_eq!(at_offsets, 5);
+    assert_eq!(at_end, 0);
+    assert_eq!(fs.lseek(source, 0, SeekFlag::SEEK_CUR).unwrap(), 11);
+    assert_eq!(fs.lseek(target, 0, SeekFlag::SEEK_CUR).unwrap(), 5);
+    assert_eq!(read_all(&fs, "/target"), b"world\0\0\0hello");
+}
+
+#[test]
+fn test_copy_file_range_should_copy_ranges_spanning_pages() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let source = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let target = fs.open("/target", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    let data = generate_random_vector(3 * PAGE_SIZE + 100);
+    fs.write(source, &data).unwrap();
+
+    /* Action */
+
+    let copied = fs.copy_file_range(source, Some(10), target, Some(PAGE_SIZE / 2), FILE_MAX_SIZE).unwrap();
+
+    /* Assert */
+
+    assert_eq!(copied, data.len() - 10);
+    assert_eq!(&read_all(&fs, "/target")[PAGE_SIZE / 2..], &data[10..]);
+}
+
+#[test]
+fn test_copy_file_range_should_check_descriptors_and_overlap() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let file = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let read_only = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let append = fs.open("/other", OpenFlag::O_CREAT | OpenFlag::O_WRONLY | OpenFlag::O_APPEND).unwrap();
+    fs.write(file, b"0123456789").unwrap();
+
+    /* Action */
+
+    let to_read_only = fs.copy_file_range(file, Some(0), read_only, Some(0), 1);
+    let from_write_only = fs.copy_file_range(append, Some(0), file, Some(0), 1);
+    let to_append = fs.copy_file_range(file, Some(0), append, Some(0), 1);
+    let overlapping = fs.copy_file_range(read_only, Some(0), file, Some(4), 5);
+    let within_file = fs.copy_file_range(read_only, Some(0), file, Some(5), 5).unwrap();
+
+    /* Assert */
+
+    assert!(to_read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(from_write_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(to_append.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(overlapping.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(within_file, 5);
+    assert_eq!(read_all(&fs, "/file"), b"0123401234");
+}
+
+#[test]
+fn test_copy_file_range_should_be_traced_and_journaled() {
+    /* Arrange */
+
+    let mut fs = MemFS::builder().journal(true).build();
+    let recorder = TraceRecorder::new();
+    fs.set_trace_recorder(recorder.clone());
+
+    let source = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let target = fs.open("/target", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(source, b"abcdef").unwrap();
+    fs.copy_file_range(source, Some(2), target, None, 3).unwrap();
+    fs.copy_file_range(source, Some(0), target, None, 1).unwrap();
+
+    let trace = Trace::from_text(&recorder.trace().to_text()).unwrap();
+    let (traced, journaled) = (MemFS::new(), MemFS::new());
+
+    /* Action */
+
+    let report = trace.replay(&traced, ReplayMode::Sequential);
+    fs.journal().unwrap().replay(&journaled).unwrap();
+
+    /* Assert */
+
+    assert!(report.is_faithful());
+    assert_eq!(read_all(&traced, "/target"), b"cdea");
+    assert_eq!(read_all(&journaled, "/target"), b"cdea");
+}
@@ -0,0 +1,112 @@
+use memfs::crash::CrashModel;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+fn read_all(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_crash_should_drop_writes_after_last_fsync() {
+    /* Arrange */
+
+    let fs = MemFS::builder().crash_simulation(true).build();
+    let synced = "synced".as_bytes().to_vec();
+    let lost = "-lost".as_bytes().to_vec();
+
+    let fd = fs
+        .open("/data.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &synced).unwrap();
+    fs.fsync(fd).unwrap();
+    fs.write(fd, &lost).unwrap();
+
+    let never_synced = fs
+        .open("/temp.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(never_synced, &synced).unwrap();
+
+    /* Action */
+
+    let report = fs.crash(CrashModel::DropUnsynced).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_all(&fs, "/data.txt"), synced);
+    assert_eq!(read_all(&fs, "/temp.txt"), Vec::<u8>::new());
+    assert_eq!(report.writes_dropped, 2);
+    assert_eq!(report.files_rolled_back, 2);
+    assert_eq!(report.descriptors_closed, 2);
+    assert!(fs.write(fd, &lost).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_crash_with_keep_prefix_should_keep_writes_in_order() {
+    /* Arrange */
+
+    let fs = MemFS::builder().crash_simulation(true).build();
+    let fd = fs
+        .open("/log.txt", OpenFlag::O_CREAT | OpenFlag::O_WRONLY | OpenFlag::O_APPEND)
+        .unwrap();
+
+    for i in 0..20u8 {
+        fs.write(fd, &[b'a' + i]).unwrap();
+    }
+
+    /* Action */
+
+    let report = fs.crash(CrashModel::KeepPrefix { seed: 7 }).unwrap();
+    let contents = read_all(&fs, "/log.txt");
+
+    /* Assert */
+
+    let expected: Vec<u8> = (0..contents.len() as u8).map(|i| b'a' + i).collect();
+    assert_eq!(contents, expected);
+    assert_eq!(report.writes_kept, contents.len());
+    assert_eq!(report.writes_kept + report.writes_dropped, 20);
+}
+
+#[test]
+fn test_crash_with_reorder_should_be_deterministic_for_seed() {
+    let run = || {
+        let fs = MemFS::builder().crash_simulation(true).build();
+        let fd = fs
+            .open("/blocks.bin", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+            .unwrap();
+
+        fs.write(fd, &[0u8; 64]).unwrap();
+        fs.fsync(fd).unwrap();
+
+        for i in 0..16 {
+            fs.lseek(fd, i * 4, SeekFlag::SEEK_SET).unwrap();
+            fs.write(fd, &[i as u8 + 1; 4]).unwrap();
+        }
+
+        fs.crash(CrashModel::Reorder { seed: 42 }).unwrap();
+        read_all(&fs, "/blocks.bin")
+    };
+
+    let first = run();
+
+    assert_eq!(first, run());
+    assert_eq!(first.len(), 64);
+    for (i, block) in first.chunks(4).enumerate() {
+        assert!(block == [0; 4] || block == [i as u8 + 1; 4]);
+    }
+}
+
+#[test]
+fn test_crash_should_fail_without_crash_simulation() {
+    let fs = MemFS::new();
+
+    let result = fs.crash(CrashModel::DropUnsynced);
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
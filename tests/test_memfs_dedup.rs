@@ -0,0 +1,93 @@
+#![cfg(feature = "dedup")]
+
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::utils::{MemFSErrType, OpenFlag, PAGE_SIZE};
+
+fn fixture(fs: &MemFS, path: &str, data: &[u8]) -> usize {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+
+    fd
+}
+
+#[test]
+fn test_identical_files_should_share_contents_once_closed() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().dedup(true).build();
+    let data = (0..3 * PAGE_SIZE).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let mut read_back = vec![0u8; data.len()];
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    for path in ["/a", "/b", "/c"] {
+        let fd = fixture(&fs, path, &data);
+        fs.close(fd).unwrap();
+    }
+
+    let unique = fixture(&fs, "/unique", &[b'u'; 2 * PAGE_SIZE]);
+    fs.fsync(unique).unwrap();
+
+    let stats = fs.dedup_stats().unwrap();
+    let fd = fs.open("/b", OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut read_back).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_back, data);
+    assert_eq!(fs.statfs().blocks_free, blocks_free);
+    assert_eq!(stats.files, 4);
+    assert_eq!(stats.contents, 2);
+    assert_eq!(stats.shared_bytes, (5 * PAGE_SIZE) as u64);
+    assert_eq!(stats.saved_bytes, (6 * PAGE_SIZE) as u64);
+}
+
+#[test]
+fn test_writes_should_copy_shared_contents_first() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().dedup(true).build();
+    let data = vec![b'd'; 2 * PAGE_SIZE];
+    let mut first = vec![0u8; data.len()];
+    let mut second = vec![0u8; data.len()];
+
+    for path in ["/first", "/second"] {
+        let fd = fixture(&fs, path, &data);
+        fs.close(fd).unwrap();
+    }
+
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    let fd = fs.open("/first", OpenFlag::O_RDWR).unwrap();
+    fs.pwrite(fd, b"changed", PAGE_SIZE).unwrap();
+    fs.truncate("/second", PAGE_SIZE).unwrap();
+    fs.pread(fd, &mut first, 0).unwrap();
+    let read = fs.pread(fs.open("/second", OpenFlag::O_RDONLY).unwrap(), &mut second, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(&first[..PAGE_SIZE], &data[..PAGE_SIZE]);
+    assert_eq!(&first[PAGE_SIZE..PAGE_SIZE + 7], b"changed");
+    assert_eq!(read, PAGE_SIZE);
+    assert_eq!(&second[..PAGE_SIZE], &data[..PAGE_SIZE]);
+    assert_eq!(fs.statfs().blocks_free, blocks_free - 3);
+    assert_eq!(fs.dedup_stats().unwrap().files, 0);
+}
+
+#[test]
+fn test_dedup_stats_should_fail_when_disabled() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    let result = fs.dedup_stats();
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
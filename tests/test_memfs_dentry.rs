@@ -0,0 +1,95 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{AT_FDCWD, MemFSErrType, OpenFlag, RenameFlag};
+
+#[test]
+fn test_repeated_lookups_should_hit_dentry_cache() {
+    /* Arrange */
+
+    let fs = MemFS::builder().dentry_cache(64).build();
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/a/b").unwrap();
+    let fd = fs.open("/a/b/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.close(fd).unwrap();
+    let before = fs.dentry_cache_stats().unwrap();
+
+    /* Action */
+
+    for _ in 0..10 {
+        let fd = fs.open("/a/b/file", OpenFlag::O_RDONLY).unwrap();
+        fs.close(fd).unwrap();
+    }
+
+    let relative = fs.chdir("/a").and_then(|_| fs.stat("b/file"));
+    let after = fs.dentry_cache_stats().unwrap();
+
+    /* Assert */
+
+    assert!(relative.is_ok());
+    assert!(after.hits >= before.hits + 9);
+    assert!(after.misses <= before.misses + 1);
+    assert_eq!(after.invalidations, 0);
+}
+
+#[test]
+fn test_dentry_cache_should_forget_removed_and_renamed_paths() {
+    /* Arrange */
+
+    let fs = MemFS::builder().dentry_cache(64).build();
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/other").unwrap();
+    fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.open("/other/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+    let dir_ino = fs.stat("/dir/file").unwrap().ino;
+    let other_ino = fs.stat("/other/file").unwrap().ino;
+    fs.stat("/link").unwrap();
+
+    /* Action */
+
+    fs.renameat2(AT_FDCWD, "/dir", AT_FDCWD, "/other", RenameFlag::RENAME_EXCHANGE).unwrap();
+    let exchanged = fs.stat("/dir/file").unwrap().ino;
+    let through_link = fs.stat("/link").unwrap().ino;
+
+    fs.rename("/dir", "/moved").unwrap();
+    let renamed_away = fs.stat("/dir/file");
+
+    fs.unlink("/moved/file").unwrap();
+    let unlinked = fs.open("/moved/file", OpenFlag::O_RDONLY);
+
+    fs.rmdir("/moved").unwrap();
+    let removed = fs.stat("/moved");
+    let stats = fs.dentry_cache_stats().unwrap();
+
+    /* Assert */
+
+    assert_eq!(exchanged, other_ino);
+    assert_eq!(through_link, other_ino);
+    assert_eq!(fs.stat("/other/file").unwrap().ino, dir_ino);
+    assert!(renamed_away.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(unlinked.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(removed.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(stats.invalidations >= 4);
+}
+
+#[test]
+fn test_dentry_cache_should_stay_within_capacity() {
+    /* Arrange */
+
+    let fs = MemFS::builder().dentry_cache(2).build();
+    let without_cache = MemFS::new().dentry_cache_stats();
+
+    /* Action */
+
+    for i in 0..8 {
+        fs.mkdir(&format!("/dir_{}", i)).unwrap();
+        fs.stat(&format!("/dir_{}", i)).unwrap();
+    }
+
+    let stats = fs.dentry_cache_stats().unwrap();
+
+    /* Assert */
+
+    assert!(stats.entries <= 2);
+    assert!(without_cache.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!((0..8).all(|i| fs.stat(&format!("/dir_{}", i)).is_ok()));
+}
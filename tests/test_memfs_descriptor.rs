@@ -0,0 +1,71 @@
+use std::{collections::HashSet, sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::utils::OpenFlag;
+
+#[test]
+fn test_open_should_reuse_lowest_closed_descriptor() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let fds = (0..4)
+        .map(|_| fs.open("/file", OpenFlag::O_RDONLY).unwrap())
+        .collect::<Vec<_>>();
+
+    /* Action */
+
+    fs.close(fds[2]).unwrap();
+    fs.close(fds[0]).unwrap();
+    let first_reused = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let second_reused = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let fresh = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+
+    /* Assert */
+
+    assert_eq!(fd, 0);
+    assert_eq!(fds, vec![1, 2, 3, 4]);
+    assert_eq!(first_reused, 1);
+    assert_eq!(second_reused, 3);
+    assert_eq!(fresh, 5);
+}
+
+#[test]
+fn test_descriptor_numbers_should_stay_bounded_by_open_descriptors() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let handles = (0..8)
+        .map(|_| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                let mut highest = 0;
+
+                for _ in 0..1000 {
+                    let fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+                    highest = highest.max(fd);
+                    fs.close(fd).unwrap();
+                }
+
+                highest
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let highest = handles.into_iter().map(|handle| handle.join().unwrap()).max().unwrap();
+
+    let held = (0..8)
+        .map(|_| fs.open("/file", OpenFlag::O_RDONLY).unwrap())
+        .collect::<HashSet<_>>();
+
+    /* Assert */
+
+    assert!(highest < 8);
+    assert_eq!(held, (0..8).collect());
+}
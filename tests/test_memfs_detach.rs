@@ -0,0 +1,109 @@
+use memfs::memfs::MemFS;
+use memfs::removal::DetachMode;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag, SeekFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_detach_should_move_subtree_into_new_file_system() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/workspace").unwrap();
+    fs.mkdir("/workspace/src").unwrap();
+    write_file(&fs, "/workspace/src/main.rs", b"fn main() {}");
+    write_file(&fs, "/workspace/README", b"readme");
+
+    /* Action */
+
+    let detached = fs.detach("/workspace").unwrap();
+
+    /* Assert */
+
+    assert!(fs.stat("/workspace").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(detached.stat("/src").unwrap().file_type, FileType::Directory);
+    assert_eq!(read_file(&detached, "/src/main.rs"), b"fn main() {}");
+    assert_eq!(read_file(&detached, "/src/../README"), b"readme");
+    assert_eq!(detached.stat("/..").unwrap().file_type, FileType::Directory);
+    assert!(detached.stat("/../workspace").is_err());
+}
+
+#[test]
+fn test_detach_should_share_files_with_open_descriptors() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = vec![b'n'; 4];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let detached = fs.detach("/dir").unwrap();
+    fs.write(fd, &data).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_file(&detached, "/file"), data);
+}
+
+#[test]
+fn test_detach_with_copy_should_not_share_files() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = vec![b'c'; 4];
+    let mut buffer = vec![0u8; 4];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let detached = fs.detach_with("/dir", DetachMode::Copy).unwrap();
+    fs.write(fd, &data).unwrap();
+    write_file(&detached, "/new", b"new");
+
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(detached.stat("/file").unwrap().size, 0);
+    assert_eq!(buffer, data);
+    assert_eq!(read_file(&detached, "/new"), b"new");
+}
+
+#[test]
+fn test_detach_should_fail_on_file_and_root() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    write_file(&fs, "/file", b"f");
+
+    /* Action */
+
+    let file_result = fs.detach("/file");
+    let root_result = fs.detach("/");
+
+    /* Assert */
+
+    assert!(file_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(root_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBUSY)));
+}
@@ -0,0 +1,99 @@
+use memfs::device::Device;
+use memfs::journal::JournalEntry;
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_standard_devices_should_behave_as_on_linux() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.create_standard_devices().unwrap();
+    let null = fs.open("/dev/null", OpenFlag::O_RDWR).unwrap();
+    let zero = fs.open("/dev/zero", OpenFlag::O_RDONLY).unwrap();
+    let urandom = fs.open("/dev/urandom", OpenFlag::O_RDONLY).unwrap();
+    let mut zeroes = [0xffu8; 4096];
+    let mut random = [0u8; 64];
+    let mut nothing = [0u8; 16];
+
+    /* Action */
+
+    let discarded = fs.write(null, b"discarded output");
+    let null_read = fs.read(null, &mut nothing);
+    let zero_read = fs.read(zero, &mut zeroes);
+    let random_read = fs.pread(urandom, &mut random, 0);
+
+    /* Assert */
+
+    assert_eq!(discarded.unwrap(), 16);
+    assert_eq!(null_read.unwrap(), 0);
+    assert_eq!(zero_read.unwrap(), 4096);
+    assert!(zeroes.iter().all(|&byte| byte == 0));
+    assert_eq!(random_read.unwrap(), 64);
+    assert!(random.iter().any(|&byte| byte != 0));
+
+    let stat = fs.stat("/dev/null").unwrap();
+    assert_eq!(stat.file_type, FileType::CharDevice);
+    assert_eq!(stat.size, 0);
+    assert_eq!(stat.mode, 0o666);
+    assert_eq!(fs.readdir("/dev").unwrap().len(), 4);
+    assert!(fs.readdir("/dev").unwrap().iter().all(|entry| entry.file_type == FileType::CharDevice));
+}
+
+#[test]
+fn test_mknod_should_create_devices_at_any_path() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/sink").unwrap();
+    fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    fs.mknod("/sink/null", Device::Null, 0o600).unwrap();
+    let duplicate = fs.mknod("/sink/null", Device::Zero, 0o600);
+    let as_directory = fs.open("/sink/null", OpenFlag::O_RDONLY | OpenFlag::O_DIRECTORY);
+    let through_file = fs.open("/sink/null/child", OpenFlag::O_RDONLY);
+    let not_device = fs.device("/file");
+    fs.symlink("/sink/null", "/link").unwrap();
+    let fd = fs.open("/link", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.device("/sink/null").unwrap(), Device::Null);
+    assert_eq!(fs.stat("/sink/null").unwrap().mode, 0o600);
+    assert!(duplicate.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(as_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(through_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(not_device.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(fs.write(fd, b"data").unwrap(), 4);
+    assert_eq!(fs.fstat(fd).unwrap().size, 0);
+
+    fs.close(fd).unwrap();
+    fs.unlink("/sink/null").unwrap();
+    fs.rmdir("/sink").unwrap();
+}
+
+#[test]
+fn test_journal_should_record_devices_but_not_their_writes() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mknod("/null", Device::Null, 0o666).unwrap();
+    let fd = fs.open("/null", OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, b"discarded").unwrap();
+    fs.pwrite(fd, b"discarded", 10).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let journal = fs.journal().unwrap();
+    let replayed = MemFS::new();
+    journal.replay(&replayed).unwrap();
+
+    /* Assert */
+
+    assert_eq!(journal.entries().len(), 1);
+    assert!(matches!(&journal.entries()[0], JournalEntry::Mknod { device: Device::Null, .. }));
+    assert_eq!(replayed.device("/null").unwrap(), Device::Null);
+}
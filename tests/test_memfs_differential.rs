@@ -0,0 +1,323 @@
+#![cfg(unix)]
+
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, OpenFlag, SeekFlag};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Paths the operations pick from, including ones whose parent is a file or missing.
+const PATHS: [&str; 8] = ["/a", "/b", "/d", "/d/a", "/d/e", "/d/e/f", "/a/x", "/missing/x"];
+
+const SEEDS: u64 = 64;
+const STEPS: usize = 200;
+
+#[derive(Debug)]
+enum Op {
+    Open(usize, u32),
+    Close(usize),
+    Read(usize, usize),
+    Write(usize, usize),
+    Pread(usize, usize, usize),
+    Lseek(usize, i64, u8),
+    Ftruncate(usize, usize),
+    Mkdir(usize),
+    Rmdir(usize),
+    Unlink(usize),
+    Rename(usize, usize),
+    Stat(usize),
+}
+
+/// Outcome of an operation, compared between MemFS and the host.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Done,
+    Opened,
+    Count(usize),
+    Data(Vec<u8>),
+    File(usize),
+    Directory,
+    Errno(i32),
+}
+
+fn random_op(rng: &mut StdRng, descriptors: usize) -> Op {
+    let path = rng.random_range(0..PATHS.len());
+    let slot = rng.random_range(0..descriptors.max(1));
+
+    match rng.random_range(0..12) {
+        0 => Op::Open(path, rng.random_range(0..64)),
+        1 => Op::Close(slot),
+        2 => Op::Read(slot, rng.random_range(0..64)),
+        3 => Op::Write(slot, rng.random_range(0..64)),
+        4 => Op::Pread(slot, rng.random_range(0..64), rng.random_range(0..96)),
+        5 => Op::Lseek(slot, rng.random_range(-16..96), rng.random_range(0..3)),
+        6 => Op::Ftruncate(slot, rng.random_range(0..96)),
+        7 => Op::Mkdir(path),
+        8 => Op::Rmdir(path),
+        9 => Op::Unlink(path),
+        10 => Op::Rename(path, rng.random_range(0..PATHS.len())),
+        _ => Op::Stat(path),
+    }
+}
+
+/// Access mode and flags of an open, as chosen by the bits of `bits`.
+fn open_flags(bits: u32) -> (OpenFlag, i32) {
+    let (mut flag, mut host) = match bits % 3 {
+        0 => (OpenFlag::O_RDONLY, libc::O_RDONLY),
+        1 => (OpenFlag::O_WRONLY, libc::O_WRONLY),
+        _ => (OpenFlag::O_RDWR, libc::O_RDWR),
+    };
+
+    if bits & 4 != 0 {
+        flag |= OpenFlag::O_CREAT;
+        host |= libc::O_CREAT;
+    }
+
+    if bits & 8 != 0 {
+        flag |= OpenFlag::O_EXCL;
+        host |= libc::O_EXCL;
+    }
+
+    if bits & 16 != 0 {
+        flag |= OpenFlag::O_APPEND;
+        host |= libc::O_APPEND;
+    }
+
+    (flag, host)
+}
+
+fn seek_flags(whence: u8) -> (SeekFlag, i32) {
+    match whence {
+        0 => (SeekFlag::SEEK_SET, libc::SEEK_SET),
+        1 => (SeekFlag::SEEK_CUR, libc::SEEK_CUR),
+        _ => (SeekFlag::SEEK_END, libc::SEEK_END),
+    }
+}
+
+fn payload(len: usize, step: usize) -> Vec<u8> {
+    (0..len).map(|i| (step * 31 + i) as u8).collect()
+}
+
+/// Runs operations against a directory of the host through libc, keeping the errno of failures.
+struct Host {
+    root: PathBuf,
+}
+
+impl Host {
+    fn path(&self, path: &str) -> CString {
+        CString::new(self.root.join(path.trim_start_matches('/')).into_os_string().into_encoded_bytes()).unwrap()
+    }
+
+    fn check(result: libc::c_long, outcome: impl FnOnce(usize) -> Outcome) -> Outcome {
+        if result < 0 {
+            return Outcome::Errno(std::io::Error::last_os_error().raw_os_error().unwrap());
+        }
+
+        outcome(result as usize)
+    }
+
+    fn open(&self, path: &str, flags: i32) -> (Outcome, Option<i32>) {
+        let fd = unsafe { libc::open(self.path(path).as_ptr(), flags, 0o644) };
+        let outcome = Self::check(fd as libc::c_long, |_| Outcome::Opened);
+
+        (outcome, (fd >= 0).then_some(fd))
+    }
+
+    fn run(&self, op: &Op, fd: Option<i32>, step: usize) -> Outcome {
+        let fd = fd.unwrap_or(-1);
+
+        unsafe {
+            match *op {
+                Op::Open(..) => unreachable!("opens keep their descriptor"),
+                Op::Close(_) => Self::check(libc::close(fd) as libc::c_long, |_| Outcome::Done),
+                Op::Read(_, len) => {
+                    let mut buffer = vec![0u8; len];
+                    let read = libc::read(fd, buffer.as_mut_ptr().cast(), len);
+
+                    Self::check(read as libc::c_long, |read| Outcome::Data(buffer[..read].to_vec()))
+                }
+                Op::Write(_, len) => {
+                    let data = payload(len, step);
+
+                    Self::check(libc::write(fd, data.as_ptr().cast(), len) as libc::c_long, Outcome::Count)
+                }
+                Op::Pread(_, len, offset) => {
+                    let mut buffer = vec![0u8; len];
+                    let read = libc::pread(fd, buffer.as_mut_ptr().cast(), len, offset as libc::off_t);
+
+                    Self::check(read as libc::c_long, |read| Outcome::Data(buffer[..read].to_vec()))
+                }
+                Op::Lseek(_, offset, whence) => {
+                    let whence = seek_flags(whence).1;
+
+                    Self::check(libc::lseek(fd, offset as libc::off_t, whence) as libc::c_long, Outcome::Count)
+                }
+                Op::Ftruncate(_, len) => {
+                    Self::check(libc::ftruncate(fd, len as libc::off_t) as libc::c_long, |_| Outcome::Done)
+                }
+                Op::Mkdir(path) => {
+                    Self::check(libc::mkdir(self.path(PATHS[path]).as_ptr(), 0o755) as libc::c_long, |_| Outcome::Done)
+                }
+                Op::Rmdir(path) => {
+                    Self::check(libc::rmdir(self.path(PATHS[path]).as_ptr()) as libc::c_long, |_| Outcome::Done)
+                }
+                Op::Unlink(path) => {
+                    Self::check(libc::unlink(self.path(PATHS[path]).as_ptr()) as libc::c_long, |_| Outcome::Done)
+                }
+                Op::Rename(from, to) => {
+                    let renamed = libc::rename(self.path(PATHS[from]).as_ptr(), self.path(PATHS[to]).as_ptr());
+
+                    Self::check(renamed as libc::c_long, |_| Outcome::Done)
+                }
+                Op::Stat(path) => {
+                    let mut stat: libc::stat = std::mem::zeroed();
+                    let result = libc::stat(self.path(PATHS[path]).as_ptr(), &mut stat);
+
+                    Self::check(result as libc::c_long, |_| match stat.st_mode & libc::S_IFMT {
+                        libc::S_IFDIR => Outcome::Directory,
+                        _ => Outcome::File(stat.st_size as usize),
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn run_memfs(fs: &MemFS, op: &Op, fd: Option<usize>, step: usize) -> Outcome {
+    let fd = fd.unwrap_or(usize::MAX);
+    let outcome = match *op {
+        Op::Open(..) => unreachable!("opens keep their descriptor"),
+        Op::Close(_) => fs.close(fd).map(|_| Outcome::Done),
+        Op::Read(_, len) => {
+            let mut buffer = vec![0u8; len];
+            fs.read(fd, &mut buffer).map(|read| Outcome::Data(buffer[..read].to_vec()))
+        }
+        Op::Write(_, len) => fs.write(fd, &payload(len, step)).map(Outcome::Count),
+        Op::Pread(_, len, offset) => {
+            let mut buffer = vec![0u8; len];
+            fs.pread(fd, &mut buffer, offset).map(|read| Outcome::Data(buffer[..read].to_vec()))
+        }
+        Op::Lseek(_, offset, whence) => fs.lseek(fd, offset, seek_flags(whence).0).map(Outcome::Count),
+        Op::Ftruncate(_, len) => fs.ftruncate(fd, len).map(|_| Outcome::Done),
+        Op::Mkdir(path) => fs.mkdir(PATHS[path]).map(|_| Outcome::Done),
+        Op::Rmdir(path) => fs.rmdir(PATHS[path]).map(|_| Outcome::Done),
+        Op::Unlink(path) => fs.unlink(PATHS[path]).map(|_| Outcome::Done),
+        Op::Rename(from, to) => fs.rename(PATHS[from], PATHS[to]).map(|_| Outcome::Done),
+        Op::Stat(path) => fs.stat(PATHS[path]).map(|stat| match stat.file_type {
+            FileType::Directory => Outcome::Directory,
+            _ => Outcome::File(stat.size),
+        }),
+    };
+
+    outcome.unwrap_or_else(|e| Outcome::Errno(e.err_type.to_errno()))
+}
+
+/// Whether MemFS departs from the host on purpose, or in a way that is known already:
+/// - a component which is a file fails with ENOENT, rather than ENOTDIR, in most operations;
+/// - directories are opened with O_DIRECTORY only, and fail with EISDIR otherwise;
+/// - offsets never go past the end of the file;
+/// - ftruncate fails with EBADF, rather than EINVAL, on a descriptor not open for writing.
+fn is_known_divergence(op: &Op, memfs: &Outcome, host: &Outcome) -> bool {
+    match (op, memfs, host) {
+        (_, Outcome::Errno(libc::ENOENT), Outcome::Errno(libc::ENOTDIR)) => true,
+        (Op::Open(..), Outcome::Errno(libc::EISDIR), Outcome::Opened) => true,
+        (Op::Lseek(..), Outcome::Count(memfs), Outcome::Count(host)) => memfs < host,
+        (Op::Ftruncate(..), Outcome::Errno(libc::EBADF), Outcome::Errno(libc::EINVAL)) => true,
+        _ => false,
+    }
+}
+
+fn host_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("memfs_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// Applies the operations generated from `seed` to both file systems, and returns the first divergence.
+fn diverges(seed: u64, root: &Path) -> Option<String> {
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let memfs = MemFS::new();
+    let host = Host { root: root.to_path_buf() };
+    let mut descriptors: Vec<(usize, i32)> = Vec::new();
+    let mut divergence = None;
+
+    for step in 0..STEPS {
+        let op = random_op(&mut rng, descriptors.len());
+
+        let (expected, actual) = match op {
+            Op::Open(path, bits) => {
+                let (flag, host_flags) = open_flags(bits);
+                let (expected, host_fd) = host.open(PATHS[path], host_flags);
+                let memfs_fd = memfs.open(PATHS[path], flag);
+                let actual = match &memfs_fd {
+                    Ok(_) => Outcome::Opened,
+                    Err(e) => Outcome::Errno(e.err_type.to_errno()),
+                };
+
+                match (memfs_fd, host_fd) {
+                    (Ok(memfs_fd), Some(host_fd)) => descriptors.push((memfs_fd, host_fd)),
+                    (Ok(memfs_fd), None) => drop(memfs.close(memfs_fd)),
+                    (Err(_), Some(host_fd)) => drop(unsafe { libc::close(host_fd) }),
+                    (Err(_), None) => (),
+                }
+
+                (expected, actual)
+            }
+            Op::Close(slot) | Op::Read(slot, _) | Op::Write(slot, _) | Op::Pread(slot, ..) | Op::Lseek(slot, ..)
+            | Op::Ftruncate(slot, _) => {
+                let (memfs_fd, host_fd) = descriptors.get(slot).copied().unzip();
+                let expected = host.run(&op, host_fd, step);
+                let actual = run_memfs(&memfs, &op, memfs_fd, step);
+
+                // Keeps both offsets in step once MemFS stopped at the end of the file.
+                if let (Op::Lseek(..), Outcome::Count(offset), Some(host_fd)) = (&op, &actual, host_fd) {
+                    unsafe { libc::lseek(host_fd, *offset as libc::off_t, libc::SEEK_SET) };
+                }
+
+                if matches!(op, Op::Close(_)) && slot < descriptors.len() {
+                    descriptors.remove(slot);
+                }
+
+                (expected, actual)
+            }
+            _ => (host.run(&op, None, step), run_memfs(&memfs, &op, None, step)),
+        };
+
+        if expected != actual && !is_known_divergence(&op, &actual, &expected) {
+            divergence = Some(format!(
+                "seed {seed}, step {step}: {op:?} gave {actual:?} on MemFS but {expected:?} on the host"
+            ));
+            break;
+        }
+    }
+
+    for (_, host_fd) in descriptors {
+        unsafe { libc::close(host_fd) };
+    }
+
+    divergence
+}
+
+#[test]
+fn test_random_operations_should_match_the_host_file_system() {
+    /* Arrange */
+
+    let root = host_dir("differential");
+
+    /* Action */
+
+    let divergences: Vec<_> = (0..SEEDS).filter_map(|seed| diverges(seed, &root)).collect();
+
+    /* Assert */
+
+    let _ = fs::remove_dir_all(&root);
+    assert!(divergences.is_empty(), "{}", divergences.join("\n"));
+}
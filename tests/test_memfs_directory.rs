@@ -1,3 +1,5 @@
+use std::{sync::Arc, thread};
+
 use memfs::memfs::MemFS;
 use memfs::utils::{MemFSErrType, OpenFlag, generate_random_vector};
 
@@ -223,7 +225,7 @@ fn test_should_succeed_on_repeated_mkdir_and_rmdir_with_tremendous_levels() {
 fn test_should_succeed_on_basic_chdir() {
     /* Arrange */
 
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
     let dir1 = "/river";
     let dir2 = "/river/ocean";
     let file_name = "/river/ocean/sky.sk";
@@ -254,7 +256,7 @@ fn test_should_succeed_on_basic_chdir() {
 fn test_should_fail_when_chdir_to_nonexistent_directory() {
     /* Arrange */
 
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
     let dir1 = "/kaist";
     let dir2 = "postech";
     let ghost_dir = "snu";
@@ -283,7 +285,7 @@ fn test_should_fail_when_chdir_to_nonexistent_directory() {
 fn test_should_succeed_when_chdir_to_self_and_parent() {
     /* Arrange */
 
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
     let parent_name = "parent_folder";
     let file_name = "place.holder";
     fs.mkdir(parent_name).unwrap();
@@ -319,7 +321,7 @@ fn test_should_succeed_when_chdir_to_self_and_parent() {
 fn test_should_fail_when_chdir_to_file() {
     /* Arrange */
 
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
     let dir = "dir";
     let dir_file = "dir/flie";
     let file = "flie";
@@ -351,7 +353,7 @@ fn test_should_fail_when_chdir_to_file() {
 
 #[test]
 fn test_should_succeed_on_parsing_paths_with_strange_slashes() {
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
 
     let r1 = fs.mkdir("////one");
     let r2 = fs.mkdir("///one//two");
@@ -391,7 +393,7 @@ fn test_should_succeed_on_parsing_paths_with_strange_slashes() {
 fn test_should_succeed_on_mkdir_and_chdir_with_tremendous_levels() {
     /* Arrange */
 
-    let mut fs = MemFS::new();
+    let fs = MemFS::new();
     let loops = 256;
     let numbers = generate_random_vector(loops);
 
@@ -435,3 +437,305 @@ fn test_should_succeed_on_mkdir_and_chdir_with_tremendous_levels() {
     assert!(chdir_deepest.is_ok());
     assert!(remove_first_path.is_ok());
 }
+
+#[test]
+fn test_should_keep_cwd_per_thread_on_thread_local_cwd() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().thread_local_cwd(true).build());
+    let dirs = ["/alpha", "/beta", "/gamma", "/delta"];
+
+    for dir in dirs {
+        fs.mkdir(dir).unwrap();
+    }
+
+    /* Action */
+
+    let handles: Vec<_> = dirs
+        .iter()
+        .map(|dir| {
+            let fs = fs.clone();
+            let dir = dir.to_string();
+
+            thread::spawn(move || {
+                fs.chdir(&dir).unwrap();
+
+                for i in 0..100 {
+                    let fd = fs
+                        .open(&format!("file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                        .unwrap();
+                    fs.close(fd).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let root_file = fs.open("file_0", OpenFlag::O_RDONLY);
+
+    /* Assert */
+
+    for dir in dirs {
+        for i in 0..100 {
+            assert!(fs.unlink(&format!("{}/file_{}", dir, i)).is_ok());
+        }
+    }
+
+    assert!(root_file.is_err_and(|e| { matches!(e.err_type, MemFSErrType::ENOENT) }));
+}
+
+#[test]
+fn test_getcwd_should_follow_chdir_and_renames() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/a/b").unwrap();
+    fs.symlink("/a/b", "/link").unwrap();
+
+    /* Action */
+
+    let at_root = fs.getcwd().unwrap();
+    fs.chdir("/link").unwrap();
+    let through_link = fs.getcwd().unwrap();
+    fs.rename("/a", "/renamed").unwrap();
+    let after_rename = fs.getcwd().unwrap();
+    fs.chdir("..").unwrap();
+    let after_parent = fs.getcwd().unwrap();
+    fs.chdir("b").unwrap();
+    fs.rmdir("/renamed/b").unwrap();
+    let after_removal = fs.getcwd();
+
+    /* Assert */
+
+    assert_eq!(at_root, "/");
+    assert_eq!(through_link, "/a/b");
+    assert_eq!(after_rename, "/renamed/b");
+    assert_eq!(after_parent, "/renamed");
+    assert!(after_removal.is_err_and(|e| { matches!(e.err_type, MemFSErrType::ENOENT) }));
+}
+
+#[test]
+fn test_getcwd_should_be_per_thread_on_thread_local_cwd() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().thread_local_cwd(true).build());
+    fs.mkdir("/first").unwrap();
+    fs.mkdir("/second").unwrap();
+
+    /* Action */
+
+    let handles: Vec<_> = ["/first", "/second"]
+        .into_iter()
+        .map(|dir| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                fs.chdir(dir).unwrap();
+                fs.getcwd().unwrap()
+            })
+        })
+        .collect();
+
+    let cwds: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    /* Assert */
+
+    assert_eq!(cwds, vec!["/first", "/second"]);
+    assert_eq!(fs.getcwd().unwrap(), "/");
+}
+
+#[test]
+fn test_deep_lookup_should_succeed_while_siblings_change() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let mut deep_path = String::new();
+
+    for i in 0..16 {
+        deep_path.push_str(&format!("/level{}", i));
+        fs.mkdir(&deep_path).unwrap();
+    }
+
+    let file_path = format!("{}/file.txt", deep_path);
+    let fd = fs
+        .open(&file_path, OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let churners: Vec<_> = (0..4)
+        .map(|t| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                let mut dir = String::new();
+
+                for i in 0..16 {
+                    dir.push_str(&format!("/level{}", i));
+                    let sibling = format!("{}/sibling_{}", dir, t);
+
+                    for _ in 0..20 {
+                        fs.mkdir(&sibling).unwrap();
+                        fs.rmdir(&sibling).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let lookups: Vec<_> = (0..4)
+        .map(|_| {
+            let fs = fs.clone();
+            let file_path = file_path.clone();
+
+            thread::spawn(move || {
+                (0..500).all(|_| {
+                    fs.open(&file_path, OpenFlag::O_RDONLY)
+                        .and_then(|fd| fs.close(fd))
+                        .is_ok()
+                })
+            })
+        })
+        .collect();
+
+    for churner in churners {
+        churner.join().unwrap();
+    }
+
+    let all_found = lookups.into_iter().all(|lookup| lookup.join().unwrap());
+
+    /* Assert */
+
+    assert!(all_found);
+}
+
+#[test]
+fn test_names_shared_between_directories_should_survive_removals_elsewhere() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    for t in 0..4 {
+        fs.mkdir(&format!("/dir_{}", t)).unwrap();
+        fs.mkdir(&format!("/dir_{}/shared", t)).unwrap();
+    }
+
+    /* Action */
+
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                for i in 0..2000 {
+                    let path = format!("/dir_{}/name_{}", t, i);
+                    fs.mkdir(&path).unwrap();
+
+                    if i % 100 != 0 {
+                        fs.rmdir(&path).unwrap();
+                    }
+                }
+
+                fs.rmdir(&format!("/dir_{}/shared", t)).unwrap();
+                fs.mkdir(&format!("/dir_{}/shared", t)).unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    /* Assert */
+
+    for t in 0..4 {
+        let mut names: Vec<_> = fs
+            .readdir(&format!("/dir_{}", t))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+
+        let mut expected: Vec<_> = (0..20).map(|i| format!("name_{}", i * 100)).collect();
+        expected.push("shared".to_string());
+        expected.sort();
+
+        assert_eq!(names, expected);
+        assert!(fs.stat(&format!("/dir_{}/name_1900", t)).is_ok());
+    }
+}
+
+#[test]
+fn test_create_dir_all_should_create_missing_ancestors() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkdir("/a").unwrap();
+    fs.chdir("/a").unwrap();
+
+    /* Action */
+
+    fs.create_dir_all("/a/b//c/").unwrap();
+    fs.create_dir_all("/a/b/c").unwrap();
+    fs.create_dir_all("x/y").unwrap();
+    fs.create_dir_all("/").unwrap();
+
+    let replayed = MemFS::new();
+    fs.journal().unwrap().replay(&replayed).unwrap();
+
+    /* Assert */
+
+    assert!(fs.stat("/a/b/c").is_ok());
+    assert!(fs.stat("/a/x/y").is_ok());
+    assert!(replayed.stat("/a/b/c").is_ok());
+    assert!(replayed.stat("/a/x/y").is_ok());
+}
+
+#[test]
+fn test_create_dir_all_should_fail_on_files_in_the_way() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let through_file = fs.create_dir_all("/dir/file/sub");
+    let onto_file = fs.create_dir_all("/dir/file");
+    let empty = fs.create_dir_all("");
+
+    /* Assert */
+
+    assert!(through_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(onto_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(empty.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_create_dir_all_should_tolerate_concurrent_creators() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    /* Action */
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let fs = fs.clone();
+            thread::spawn(move || fs.create_dir_all(&format!("/shared/deep/tree/{}", i % 2)))
+        })
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    /* Assert */
+
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(fs.readdir("/shared/deep/tree").unwrap().len(), 2);
+}
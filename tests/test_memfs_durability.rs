@@ -0,0 +1,209 @@
+use std::{
+    fs, io,
+    sync::{Arc, Mutex},
+};
+
+use memfs::durability::{DurabilityBackend, HostMirror, SyncLog, SyncedFile};
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+/// Path, contents and data-only flag of a synced file.
+type Synced = (String, Vec<u8>, bool);
+
+/// Every synced file, and the number of calls to sync_all.
+#[derive(Clone, Default)]
+struct Recorder {
+    files: Arc<Mutex<Vec<Synced>>>,
+    flushes: Arc<Mutex<usize>>,
+}
+
+impl DurabilityBackend for Recorder {
+    fn sync_file(&self, file: &SyncedFile) -> io::Result<()> {
+        assert_eq!(file.stat.size, file.contents.len());
+
+        let synced = (file.path.to_string(), file.contents.to_vec(), file.data_only);
+        self.files.lock().unwrap().push(synced);
+
+        Ok(())
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        *self.flushes.lock().unwrap() += 1;
+
+        Ok(())
+    }
+}
+
+struct FailingDisk;
+
+impl DurabilityBackend for FailingDisk {
+    fn sync_file(&self, _: &SyncedFile) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::StorageFull))
+    }
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+#[test]
+fn test_fsync_and_fdatasync_should_hand_file_to_backend() {
+    /* Arrange */
+
+    let recorder = Recorder::default();
+    let fs = MemFSBuilder::new().durability(recorder.clone()).build();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let dir_fd = fs.open("/dir", OpenFlag::O_RDONLY | OpenFlag::O_DIRECTORY).unwrap();
+
+    /* Action */
+
+    fs.write(fd, b"first").unwrap();
+    fs.fsync(fd).unwrap();
+    fs.write(fd, b" second").unwrap();
+    fs.fdatasync(fd).unwrap();
+    fs.fsync(dir_fd).unwrap();
+
+    /* Assert */
+
+    let files = recorder.files.lock().unwrap().clone();
+
+    assert_eq!(
+        files,
+        vec![
+            ("/dir/file".to_string(), b"first".to_vec(), false),
+            ("/dir/file".to_string(), b"first second".to_vec(), true),
+        ]
+    );
+    assert_eq!(*recorder.flushes.lock().unwrap(), 0);
+}
+
+#[test]
+fn test_sync_should_hand_every_file_to_backend_then_flush() {
+    /* Arrange */
+
+    let recorder = Recorder::default();
+    let fs = MemFSBuilder::new().durability(recorder.clone()).build();
+    fs.create_dir_all("/a/b").unwrap();
+    write_file(&fs, "/top", b"top");
+    write_file(&fs, "/a/b/deep", b"deep");
+    fs.symlink("/top", "/a/link").unwrap();
+
+    /* Action */
+
+    fs.sync().unwrap();
+
+    /* Assert */
+
+    let mut files = recorder.files.lock().unwrap().clone();
+    files.sort();
+
+    assert_eq!(
+        files,
+        vec![
+            ("/a/b/deep".to_string(), b"deep".to_vec(), false),
+            ("/top".to_string(), b"top".to_vec(), false),
+        ]
+    );
+    assert_eq!(*recorder.flushes.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_syncs_should_do_nothing_without_backend() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"data").unwrap();
+
+    /* Action */
+
+    let fsync = fs.fsync(fd);
+    let fdatasync = fs.fdatasync(fd);
+    let sync = fs.sync();
+    let closed = fs.close(fd).and_then(|_| fs.fdatasync(fd));
+
+    /* Assert */
+
+    assert!(fsync.is_ok());
+    assert!(fdatasync.is_ok());
+    assert!(sync.is_ok());
+    assert!(closed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_backend_errors_should_fail_sync_calls() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().durability(FailingDisk).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let fsync = fs.fsync(fd);
+    let sync = fs.sync();
+
+    /* Assert */
+
+    assert!(fsync.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC)));
+    assert!(sync.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC)));
+}
+
+#[test]
+fn test_host_mirror_should_write_synced_files_under_directory() {
+    /* Arrange */
+
+    let dir = std::env::temp_dir().join(format!("memfs_mirror_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let memfs = MemFSBuilder::new().durability(HostMirror::new(&dir)).build();
+    memfs.create_dir_all("/sub/deeper").unwrap();
+    write_file(&memfs, "/sub/deeper/file", b"mirrored");
+    write_file(&memfs, "/unsynced", b"kept in memory");
+    let fd = memfs.open("/sub/deeper/file", OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    memfs.fsync(fd).unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs::read(dir.join("sub/deeper/file")).unwrap(), b"mirrored");
+    assert!(!dir.join("unsynced").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sync_log_should_append_synced_files() {
+    /* Arrange */
+
+    let buffer = SharedBuffer::default();
+    let fs = MemFSBuilder::new().durability(SyncLog::new(buffer.clone())).build();
+    let fd = fs.open("/log", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    fs.write(fd, b"one").unwrap();
+    fs.fsync(fd).unwrap();
+    fs.write(fd, b"two").unwrap();
+    fs.fdatasync(fd).unwrap();
+
+    /* Assert */
+
+    assert_eq!(buffer.0.lock().unwrap().as_slice(), b"/log\t3\none/log\t6\nonetwo");
+}
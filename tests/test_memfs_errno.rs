@@ -0,0 +1,78 @@
+use std::io;
+
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErr, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_errno_should_match_the_c_library() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let errors = [
+        fs.open("/missing", OpenFlag::O_RDONLY).unwrap_err(),
+        fs.mkdir("/dir").unwrap_err(),
+        fs.close(99).unwrap_err(),
+        fs.rmdir("/dir").unwrap_err(),
+        fs.open("/dir", OpenFlag::O_DIRECTORY | OpenFlag::O_RDWR).unwrap_err(),
+    ];
+
+    /* Assert */
+
+    let errnos: Vec<i32> = errors.iter().map(|e| e.err_type.to_errno()).collect();
+
+    assert_eq!(errnos, [libc::ENOENT, libc::EEXIST, libc::EBADF, libc::ENOTEMPTY, libc::EISDIR]);
+    assert_eq!(MemFSErrType::Misc.to_errno(), libc::EIO);
+    assert_eq!(MemFSErrType::PoisonedLock.to_errno(), libc::EIO);
+}
+
+#[test]
+fn test_errno_should_convert_back_to_error_types() {
+    /* Arrange */
+
+    let types = [
+        MemFSErrType::ENOENT,
+        MemFSErrType::EEXIST,
+        MemFSErrType::EBADF,
+        MemFSErrType::EISDIR,
+        MemFSErrType::ENOTDIR,
+        MemFSErrType::EFAULT,
+        MemFSErrType::EINVAL,
+        MemFSErrType::ENOTEMPTY,
+        MemFSErrType::EBUSY,
+        MemFSErrType::EFBIG,
+        MemFSErrType::ENOMEM,
+        MemFSErrType::ENOSPC,
+        MemFSErrType::EAGAIN,
+        MemFSErrType::EROFS,
+        MemFSErrType::EPERM,
+        MemFSErrType::EACCES,
+        MemFSErrType::ELOOP,
+        MemFSErrType::ENXIO,
+        MemFSErrType::EDEADLK,
+        MemFSErrType::ENAMETOOLONG,
+        MemFSErrType::EMFILE,
+        MemFSErrType::EPIPE,
+    ];
+
+    /* Action */
+
+    let round_trips: Vec<i32> = types
+        .iter()
+        .map(|err_type| MemFSErrType::from(err_type.to_errno()).to_errno())
+        .collect();
+    let unknown = MemFSErrType::from(libc::ESPIPE);
+    let host = MemFSErr::from(io::Error::from_raw_os_error(libc::ENOTEMPTY));
+
+    /* Assert */
+
+    let errnos: Vec<i32> = types.iter().map(MemFSErrType::to_errno).collect();
+
+    assert_eq!(round_trips, errnos);
+    assert!(matches!(unknown, MemFSErrType::Misc));
+    assert!(matches!(host.err_type, MemFSErrType::ENOTEMPTY));
+}
@@ -0,0 +1,49 @@
+use std::io;
+
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_error_should_name_operation_and_path() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let open = fs.open("/dir/missing", OpenFlag::O_RDONLY).unwrap_err();
+    let read = fs.read(fd, &mut [0u8; 4]).unwrap_err();
+    let stat = fs.stat("/nowhere").unwrap_err();
+    let fstat = fs.fstat(99).unwrap_err();
+    let nested = fs.create_dir_all("/dir/file/sub").unwrap_err();
+
+    /* Assert */
+
+    assert_eq!((open.operation, open.path.as_deref()), (Some("open"), Some("/dir/missing")));
+    assert_eq!((read.operation, read.path.as_deref()), (Some("read"), Some("/dir/file")));
+    assert_eq!((stat.operation, stat.path.as_deref()), (Some("stat"), Some("/nowhere")));
+    assert_eq!((fstat.operation, fstat.path.as_deref()), (Some("fstat"), None));
+    assert_eq!((nested.operation, nested.path.as_deref()), (Some("create_dir_all"), Some("/dir/file/sub")));
+    assert!(matches!(nested.err_type, MemFSErrType::ENOTDIR));
+}
+
+#[test]
+fn test_error_context_should_show_in_messages() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    let err = fs.unlink("/missing").unwrap_err();
+    let io_err = io::Error::from(err.clone());
+
+    /* Assert */
+
+    assert_eq!(err.message, "No such file or directory");
+    assert_eq!(err.to_string(), "unlink /missing: No such file or directory");
+    assert_eq!(io_err.kind(), io::ErrorKind::NotFound);
+    assert_eq!(io_err.to_string(), "unlink /missing: No such file or directory");
+}
@@ -0,0 +1,152 @@
+use std::fs;
+
+use memfs::durability::HostMirror;
+use memfs::eviction::Eviction;
+use memfs::journal::JournalEntry;
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::pool::MemoryPool;
+use memfs::utils::{MemFSErrType, OpenFlag, PAGE_SIZE, generate_random_vector};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; fs.stat(path).unwrap().size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_full_pool_should_evict_least_recently_used_file() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new()
+        .block_store(MemoryPool::with_preallocated(6))
+        .eviction(Eviction::Drop)
+        .build();
+    let (hot, cold, warm) = (
+        generate_random_vector(2 * PAGE_SIZE),
+        generate_random_vector(2 * PAGE_SIZE),
+        generate_random_vector(2 * PAGE_SIZE),
+    );
+    fs.mkdir("/cache").unwrap();
+    write_file(&fs, "/cache/hot", &hot);
+    write_file(&fs, "/cache/cold", &cold);
+    write_file(&fs, "/cache/warm", &warm);
+    read_file(&fs, "/cache/hot");
+
+    /* Action */
+
+    let written = fs.open("/cache/new", OpenFlag::O_CREAT | OpenFlag::O_RDWR).and_then(|fd| {
+        let written = fs.write(fd, &generate_random_vector(PAGE_SIZE + 1));
+        fs.close(fd)?;
+
+        written
+    });
+
+    /* Assert */
+
+    assert_eq!(written.unwrap(), PAGE_SIZE + 1);
+    assert!(fs.stat("/cache/cold").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read_file(&fs, "/cache/hot"), hot);
+    assert_eq!(read_file(&fs, "/cache/warm"), warm);
+    assert_eq!(fs.statfs().blocks_free, 0);
+}
+
+#[test]
+fn test_file_of_moved_directory_should_be_evicted_at_its_new_path() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new()
+        .block_store(MemoryPool::with_preallocated(4))
+        .eviction(Eviction::Drop)
+        .build();
+    let recent = generate_random_vector(2 * PAGE_SIZE);
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/dir/old", &generate_random_vector(2 * PAGE_SIZE));
+    write_file(&fs, "/recent", &recent);
+    fs.rename("/dir", "/moved").unwrap();
+
+    /* Action */
+
+    write_file(&fs, "/new", &generate_random_vector(PAGE_SIZE));
+
+    /* Assert */
+
+    assert!(fs.stat("/moved/old").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read_file(&fs, "/recent"), recent);
+    assert_eq!(fs.stat("/new").unwrap().size, PAGE_SIZE);
+}
+
+#[test]
+fn test_open_files_should_never_be_evicted() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new()
+        .block_store(MemoryPool::with_preallocated(4))
+        .eviction(Eviction::Drop)
+        .build();
+    let disabled = MemFSBuilder::new().block_store(MemoryPool::with_preallocated(2)).build();
+    write_file(&fs, "/closed", &generate_random_vector(2 * PAGE_SIZE));
+    write_file(&disabled, "/closed", &generate_random_vector(2 * PAGE_SIZE));
+    let held = fs.open("/held", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(held, &generate_random_vector(2 * PAGE_SIZE)).unwrap();
+
+    /* Action */
+
+    let grown = fs.pwrite(held, &generate_random_vector(2 * PAGE_SIZE), 2 * PAGE_SIZE);
+    let exhausted = fs.pwrite(held, &[1], 4 * PAGE_SIZE);
+    let not_enabled = disabled
+        .open("/other", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .and_then(|fd| disabled.write(fd, &[1; 2 * PAGE_SIZE]));
+
+    /* Assert */
+
+    assert_eq!(grown.unwrap(), 2 * PAGE_SIZE);
+    assert!(exhausted.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOMEM)));
+    assert!(not_enabled.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOMEM)));
+    assert!(fs.stat("/closed").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(fs.stat("/held").unwrap().size, 4 * PAGE_SIZE);
+    assert_eq!(disabled.stat("/closed").unwrap().size, 2 * PAGE_SIZE);
+}
+
+#[test]
+fn test_spill_should_hand_evicted_file_to_durability_backend() {
+    /* Arrange */
+
+    let dir = std::env::temp_dir().join(format!("memfs_eviction_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let fs = MemFSBuilder::new()
+        .block_store(MemoryPool::with_preallocated(2))
+        .durability(HostMirror::new(&dir))
+        .eviction(Eviction::Spill)
+        .journal(true)
+        .build();
+    let spilled = generate_random_vector(2 * PAGE_SIZE);
+    fs.create_dir_all("/a/b").unwrap();
+    write_file(&fs, "/a/b/spilled", &spilled);
+    fs.link("/a/b/spilled", "/a/other_name").unwrap();
+
+    /* Action */
+
+    write_file(&fs, "/a/b/next", &generate_random_vector(PAGE_SIZE));
+
+    /* Assert */
+
+    assert_eq!(fs::read(dir.join("a/b/spilled")).unwrap(), spilled);
+    assert_eq!(fs::read(dir.join("a/other_name")).unwrap(), spilled);
+    assert!(fs.stat("/a/b/spilled").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.stat("/a/other_name").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+
+    let journal = fs.journal().unwrap();
+    assert!(journal.entries().contains(&JournalEntry::Unlink { path: "/a/b/spilled".to_string() }));
+    assert!(journal.entries().contains(&JournalEntry::Unlink { path: "/a/other_name".to_string() }));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,154 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use memfs::memfs::MemFSBuilder;
+use memfs::utils::MemFSErrType;
+
+#[test]
+fn test_lock_exclusive_should_hold_off_operations_of_other_threads() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFSBuilder::new().exclusive_lock(true).build());
+    let done = Arc::new(AtomicBool::new(false));
+
+    fs.mkdir("/dir").unwrap();
+
+    /* Action */
+
+    let guard = fs.lock_exclusive().unwrap();
+
+    let handle = {
+        let fs = fs.clone();
+        let done = done.clone();
+
+        thread::spawn(move || {
+            fs.stat("/dir").unwrap();
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+
+    thread::sleep(Duration::from_millis(50));
+    let done_while_locked = done.load(Ordering::SeqCst);
+
+    drop(guard);
+    handle.join().unwrap();
+
+    /* Assert */
+
+    assert!(!done_while_locked);
+    assert!(done.load(Ordering::SeqCst));
+    assert!(!fs.is_locked_exclusive());
+}
+
+#[test]
+fn test_lock_exclusive_should_let_holder_use_file_system() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().exclusive_lock(true).build();
+
+    /* Action */
+
+    let guard = fs.lock_exclusive().unwrap();
+    let mkdir_result = fs.mkdir("/maintenance");
+    let stat_result = fs.stat("/maintenance");
+    let relock_busy = fs
+        .lock_exclusive()
+        .is_err_and(|e| matches!(e.err_type, MemFSErrType::EBUSY));
+    drop(guard);
+
+    /* Assert */
+
+    assert!(mkdir_result.is_ok());
+    assert!(stat_result.is_ok());
+    assert!(relock_busy);
+    assert!(fs.lock_exclusive().is_ok());
+}
+
+#[test]
+fn test_try_lock_exclusive_should_time_out_while_held_by_other_thread() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFSBuilder::new().exclusive_lock(true).build());
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+
+    let holder = {
+        let fs = fs.clone();
+
+        thread::spawn(move || {
+            let _guard = fs.lock_exclusive().unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        })
+    };
+
+    locked_rx.recv().unwrap();
+
+    /* Action */
+
+    let result = fs.try_lock_exclusive_for(Duration::from_millis(20));
+
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(fs.try_lock_exclusive_for(Duration::from_millis(20)).is_ok());
+}
+
+#[test]
+fn test_try_lock_exclusive_should_give_up_on_operation_waiting_for_thaw() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFSBuilder::new().exclusive_lock(true).build());
+
+    fs.freeze().unwrap();
+
+    let mutation = {
+        let fs = fs.clone();
+        thread::spawn(move || fs.mkdir("/blocked"))
+    };
+
+    thread::sleep(Duration::from_millis(20));
+
+    /* Action */
+
+    let result = fs.try_lock_exclusive_for(Duration::from_millis(30));
+    let gave_up = result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN));
+
+    fs.thaw().unwrap();
+    let mutation_result = mutation.join().unwrap();
+
+    /* Assert */
+
+    assert!(gave_up);
+    assert!(mutation_result.is_ok());
+    assert!(fs.lock_exclusive().is_ok());
+}
+
+#[test]
+fn test_lock_exclusive_should_fail_unless_enabled() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().build();
+
+    /* Action */
+
+    let result = fs.lock_exclusive().map(|_| ());
+    let timed_result = fs.try_lock_exclusive_for(Duration::from_millis(20)).map(|_| ());
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(timed_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(!fs.is_locked_exclusive());
+    assert!(fs.mkdir("/still_usable").is_ok());
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "coarse-grained")]
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use memfs::lock::LockPolicy;
+use memfs::memfs::MemFS;
+use memfs::utils::OpenFlag;
+
+const POLICIES: [LockPolicy; 4] = [
+    LockPolicy::Platform,
+    LockPolicy::ReadPreferring,
+    LockPolicy::WritePreferring,
+    LockPolicy::FairQueued,
+];
+
+#[test]
+fn test_every_lock_policy_should_behave_the_same() {
+    for policy in POLICIES {
+        /* Arrange */
+
+        let fs = MemFS::builder().lock_policy(policy).build();
+        let data = vec![b'p'; 64];
+        let mut buffer = vec![0u8; 64];
+
+        /* Action */
+
+        fs.mkdir("/dir").unwrap();
+        let fd = fs
+            .open("/dir/file.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+            .unwrap();
+        fs.write(fd, &data).unwrap();
+        fs.close(fd).unwrap();
+
+        let fd = fs.open("/dir/file.txt", OpenFlag::O_RDONLY).unwrap();
+        let read = fs.read(fd, &mut buffer).unwrap();
+        fs.close(fd).unwrap();
+
+        fs.unlink("/dir/file.txt").unwrap();
+        let rmdir = fs.rmdir("/dir");
+
+        /* Assert */
+
+        assert_eq!(read, 64, "policy {policy:?}");
+        assert_eq!(buffer, data, "policy {policy:?}");
+        assert!(rmdir.is_ok(), "policy {policy:?}");
+    }
+}
+
+/// Keeps the root directory read-locked by many readers, and measures how long a writer
+/// creating and removing entries of the root directory waits.
+#[cfg(feature = "coarse-grained")]
+fn max_writer_latency(policy: LockPolicy) -> Duration {
+    let fs = Arc::new(MemFS::builder().lock_policy(policy).build());
+    let stop = Arc::new(AtomicBool::new(false));
+
+    for i in 0..8 {
+        fs.mkdir(&format!("/read{i}")).unwrap();
+    }
+
+    let readers: Vec<_> = (0..8)
+        .map(|i| {
+            let fs = fs.clone();
+            let stop = stop.clone();
+
+            thread::spawn(move || {
+                let path = format!("/read{i}");
+
+                while !stop.load(Ordering::Relaxed) {
+                    fs.stat(&path).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    let mut max_latency = Duration::ZERO;
+
+    for i in 0..200 {
+        let path = format!("/write{i}");
+
+        let started = Instant::now();
+        fs.mkdir(&path).unwrap();
+        fs.rmdir(&path).unwrap();
+        max_latency = max_latency.max(started.elapsed());
+    }
+
+    stop.store(true, Ordering::Relaxed);
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    max_latency
+}
+
+#[cfg(feature = "coarse-grained")]
+#[test]
+fn test_writer_latency_should_be_bounded_under_read_pressure() {
+    for policy in [LockPolicy::WritePreferring, LockPolicy::FairQueued] {
+        /* Action */
+
+        let latency = max_writer_latency(policy);
+
+        /* Assert */
+
+        assert!(
+            latency < Duration::from_secs(1),
+            "policy {policy:?} let a writer wait {latency:?}"
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use memfs::memfs::MemFS;
+use memfs::pool::MemoryPool;
+use memfs::quota::NODE_METADATA_BYTES;
+use memfs::trace::{ReplayMode, Trace, TraceCall, TraceRecorder};
+use memfs::utils::{FILE_MAX_SIZE, FallocateMode, MemFSErrType, OpenFlag, PAGE_SIZE, generate_random_vector};
+
+#[test]
+fn test_fallocate_should_grow_file_with_zeroes_unless_keeping_size() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"data").unwrap();
+
+    /* Action */
+
+    fs.fallocate(fd, FallocateMode::Allocate, 2, 8).unwrap();
+    let allocated = fs.fstat(fd).unwrap().size;
+
+    fs.fallocate(fd, FallocateMode::KeepSize, 0, 100).unwrap();
+    let kept = fs.fstat(fd).unwrap().size;
+    let usage = fs.usage();
+
+    fs.fallocate(fd, FallocateMode::Allocate, 0, 4).unwrap();
+    let mut contents = [0xffu8; 16];
+    let read = fs.pread(fd, &mut contents, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(allocated, 10);
+    assert_eq!(kept, 10);
+    assert_eq!(usage.data_bytes, 100);
+    assert_eq!(&contents[..read], b"data\0\0\0\0\0\0");
+}
+
+#[test]
+fn test_fallocate_should_reserve_space_against_quota() {
+    /* Arrange */
+
+    let quota = 2 * NODE_METADATA_BYTES + 100;
+    let fs = MemFS::builder().quota(quota).build();
+    let reserved = fs.open("/reserved", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    let other = fs.open("/other", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let too_large = fs.fallocate(reserved, FallocateMode::KeepSize, 0, 101);
+    fs.fallocate(reserved, FallocateMode::KeepSize, 0, 80).unwrap();
+    let other_write = fs.write(other, &[1u8; 30]);
+    let reserved_write = fs.write(reserved, &[1u8; 80]);
+
+    fs.ftruncate(reserved, 0).unwrap();
+    let after_truncate = fs.write(other, &[1u8; 30]);
+
+    /* Assert */
+
+    assert!(too_large.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC)));
+    assert!(other_write.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC)));
+    assert_eq!(reserved_write.unwrap(), 80);
+    assert_eq!(after_truncate.unwrap(), 30);
+}
+
+#[test]
+fn test_fallocate_should_take_pages_of_range_from_block_store() {
+    /* Arrange */
+
+    let fs = MemFS::builder().block_store(MemoryPool::with_preallocated(4)).build();
+    let allocated = fs.open("/allocated", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let kept = fs.open("/kept", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let other = fs.open("/other", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let data = generate_random_vector(2 * PAGE_SIZE);
+
+    /* Action */
+
+    fs.fallocate(allocated, FallocateMode::Allocate, 0, 2 * PAGE_SIZE).unwrap();
+    fs.fallocate(kept, FallocateMode::KeepSize, PAGE_SIZE, PAGE_SIZE).unwrap();
+    let blocks_free = fs.statfs().blocks_free;
+
+    let exhausting = fs.fallocate(other, FallocateMode::Allocate, 0, 2 * PAGE_SIZE);
+    let allocated_write = fs.pwrite(allocated, &data, 0);
+    let kept_write = fs.pwrite(kept, &[1u8; 10], PAGE_SIZE + 5);
+
+    /* Assert */
+
+    assert_eq!(blocks_free, 1);
+    assert!(exhausting.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOMEM)));
+    assert_eq!(fs.statfs().blocks_free, 0);
+    assert_eq!(allocated_write.unwrap(), 2 * PAGE_SIZE);
+    assert_eq!(kept_write.unwrap(), 10);
+    assert_eq!(fs.fstat(kept).unwrap().size, PAGE_SIZE + 15);
+}
+
+#[test]
+fn test_fallocate_should_fail_on_invalid_descriptor_or_range() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let read_only = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDONLY).unwrap();
+    let writable = fs.open("/file", OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let on_read_only = fs.fallocate(read_only, FallocateMode::Allocate, 0, 1);
+    let empty = fs.fallocate(writable, FallocateMode::Allocate, 0, 0);
+    let past_max = fs.fallocate(writable, FallocateMode::Allocate, FILE_MAX_SIZE, 1);
+    let overflowing = fs.fallocate(writable, FallocateMode::KeepSize, usize::MAX, 1);
+
+    /* Assert */
+
+    assert!(on_read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(empty.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(past_max.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert!(overflowing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert_eq!(fs.fstat(writable).unwrap().size, 0);
+}
+
+#[test]
+fn test_fallocate_should_be_traced_and_replayed() {
+    /* Arrange */
+
+    let mut fs = MemFS::new();
+    let recorder = TraceRecorder::new();
+    fs.set_trace_recorder(recorder.clone());
+
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.fallocate(fd, FallocateMode::Allocate, 0, 16).unwrap();
+    fs.fallocate(fd, FallocateMode::KeepSize, 0, 64).unwrap();
+
+    let trace = Trace::from_text(&recorder.trace().to_text()).unwrap();
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    let report = trace.replay(&fresh, ReplayMode::Sequential);
+
+    /* Assert */
+
+    assert!(matches!(
+        trace.events()[2].call,
+        TraceCall::Fallocate { mode: FallocateMode::KeepSize, offset: 0, len: 64, .. }
+    ));
+    assert!(report.is_faithful());
+    assert_eq!(fresh.stat("/file").unwrap().size, 16);
+}
+
+#[test]
+fn test_punch_hole_should_zero_range_and_keep_size() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"0123456789").unwrap();
+
+    /* Action */
+
+    fs.fallocate(fd, FallocateMode::PunchHole, 2, 3).unwrap();
+    fs.fallocate(fd, FallocateMode::PunchHole, 8, 100).unwrap();
+    fs.fallocate(fd, FallocateMode::PunchHole, 50, 10).unwrap();
+
+    let mut contents = [0xffu8; 16];
+    let read = fs.pread(fd, &mut contents, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(&contents[..read], b"01\x00\x00\x00567\x00\x00");
+    assert_eq!(fs.fstat(fd).unwrap().size, 10);
+}
+
+#[test]
+fn test_punch_hole_should_give_covered_pages_back_to_block_store() {
+    /* Arrange */
+
+    let fs = MemFS::builder().block_store(MemoryPool::with_preallocated(4)).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let data = generate_random_vector(3 * PAGE_SIZE);
+    fs.write(fd, &data).unwrap();
+    let before = fs.statfs().blocks_free;
+
+    /* Action */
+
+    fs.fallocate(fd, FallocateMode::PunchHole, PAGE_SIZE / 2, 2 * PAGE_SIZE).unwrap();
+    let after = fs.statfs().blocks_free;
+
+    let mut contents = vec![0xffu8; 3 * PAGE_SIZE];
+    fs.pread(fd, &mut contents, 0).unwrap();
+
+    fs.pwrite(fd, b"refill", PAGE_SIZE).unwrap();
+    let refilled = fs.statfs().blocks_free;
+
+    /* Assert */
+
+    let hole = PAGE_SIZE / 2..5 * PAGE_SIZE / 2;
+
+    assert_eq!(before, 1);
+    assert_eq!(after, 2);
+    assert_eq!(refilled, 1);
+    assert_eq!(&contents[..hole.start], &data[..hole.start]);
+    assert!(contents[hole.clone()].iter().all(|byte| *byte == 0));
+    assert_eq!(&contents[hole.end..], &data[hole.end..]);
+    assert_eq!(fs.fstat(fd).unwrap().size, 3 * PAGE_SIZE);
+}
+
+#[test]
+fn test_punch_hole_should_be_journaled() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"abcdef").unwrap();
+    fs.fallocate(fd, FallocateMode::PunchHole, 1, 2).unwrap();
+    fs.fallocate(fd, FallocateMode::PunchHole, 6, 2).unwrap();
+    fs.fallocate(fd, FallocateMode::Allocate, 0, 8).unwrap();
+    fs.close(fd).unwrap();
+
+    let journal = fs.journal().unwrap();
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    journal.replay(&fresh).unwrap();
+
+    let fd = fresh.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let mut contents = [0xffu8; 16];
+    let read = fresh.pread(fd, &mut contents, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(journal.entries().len(), 4);
+    assert_eq!(&contents[..read], b"a\0\0def\0\0");
+}
@@ -0,0 +1,60 @@
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_opening_past_limit_should_fail_with_emfile() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().max_open_files(3).build();
+    fs.mkdir("/dir").unwrap();
+    let file = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let again = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let dir = fs.open("/dir", OpenFlag::O_RDONLY | OpenFlag::O_DIRECTORY).unwrap();
+
+    /* Action */
+
+    let exhausted = fs.open("/file", OpenFlag::O_RDONLY);
+    let created = fs.open("/new", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    assert_eq!([file, again, dir], [0, 1, 2]);
+    assert!(exhausted.is_err_and(|e| matches!(e.err_type, MemFSErrType::EMFILE)));
+    assert!(created.is_err_and(|e| matches!(e.err_type, MemFSErrType::EMFILE)));
+}
+
+#[test]
+fn test_closing_descriptor_should_make_room_under_limit() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().max_open_files(2).build();
+    let first = fs.open("/a", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.open("/b", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    fs.close(first).unwrap();
+    let reopened = fs.open("/c", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+    let exhausted = fs.open("/a", OpenFlag::O_RDONLY);
+
+    /* Assert */
+
+    assert_eq!(reopened.unwrap(), first);
+    assert!(exhausted.is_err_and(|e| matches!(e.err_type, MemFSErrType::EMFILE)));
+}
+
+#[test]
+fn test_descriptors_should_be_unlimited_by_default() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let opened = (0..4096).map(|_| fs.open("/file", OpenFlag::O_RDONLY)).collect::<Result<Vec<_>, _>>();
+
+    /* Assert */
+
+    assert_eq!(opened.unwrap().len(), 4096);
+}
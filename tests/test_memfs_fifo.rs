@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use memfs::journal::JournalEntry;
+use memfs::memfs::MemFS;
+use memfs::pipe::PIPE_CAPACITY;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag, SeekFlag};
+
+#[test]
+fn test_fifo_should_carry_data_from_producer_to_consumer() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.mkfifo("/queue", 0o600).unwrap();
+    let sent: Vec<u8> = (0..PIPE_CAPACITY * 3).map(|i| (i % 251) as u8).collect();
+
+    /* Action */
+
+    let producer = {
+        let fs = fs.clone();
+        let sent = sent.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/queue", OpenFlag::O_WRONLY).unwrap();
+            let written = sent.chunks(10_000).map(|chunk| fs.write(fd, chunk).unwrap()).sum::<usize>();
+            fs.close(fd).unwrap();
+
+            written
+        })
+    };
+    let fd = fs.open("/queue", OpenFlag::O_RDONLY).unwrap();
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 7000];
+
+    loop {
+        match fs.read(fd, &mut buffer).unwrap() {
+            0 => break,
+            read => received.extend_from_slice(&buffer[..read]),
+        }
+    }
+
+    /* Assert */
+
+    assert_eq!(producer.join().unwrap(), sent.len());
+    assert_eq!(received, sent);
+    assert_eq!(fs.stat("/queue").unwrap().file_type, FileType::Fifo);
+    assert_eq!(fs.stat("/queue").unwrap().size, 0);
+}
+
+#[test]
+fn test_nonblocking_descriptors_should_fail_instead_of_waiting() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkfifo("/fifo", 0o644).unwrap();
+    let lonely_writer = fs.open("/fifo", OpenFlag::O_WRONLY | OpenFlag::O_NONBLOCK);
+    let reader = fs.open("/fifo", OpenFlag::O_RDONLY | OpenFlag::O_NONBLOCK).unwrap();
+    let mut buffer = [0u8; 16];
+    let no_writer = fs.read(reader, &mut buffer);
+    let writer = fs.open("/fifo", OpenFlag::O_WRONLY | OpenFlag::O_NONBLOCK).unwrap();
+
+    /* Action */
+
+    let empty = fs.read(reader, &mut buffer);
+    let filled = fs.write(writer, &vec![1u8; PIPE_CAPACITY + 100]);
+    let full = fs.write(writer, b"x");
+    let seek = fs.lseek(reader, 0, SeekFlag::SEEK_SET);
+    let positioned = fs.pread(reader, &mut buffer, 0);
+
+    /* Assert */
+
+    assert!(lonely_writer.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENXIO)));
+    assert_eq!(no_writer.unwrap(), 0);
+    assert!(empty.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert_eq!(filled.unwrap(), PIPE_CAPACITY);
+    assert!(full.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(seek.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(positioned.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(fs.read(reader, &mut buffer).unwrap(), 16);
+}
+
+#[test]
+fn test_closing_ends_should_give_end_of_file_and_broken_pipe() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkfifo("/fifo", 0o600).unwrap();
+    let reader = fs.open("/fifo", OpenFlag::O_RDONLY | OpenFlag::O_NONBLOCK).unwrap();
+    let writer = fs.open("/fifo", OpenFlag::O_WRONLY).unwrap();
+    fs.write(writer, b"last words").unwrap();
+    let mut buffer = [0u8; 32];
+
+    /* Action */
+
+    fs.close(writer).unwrap();
+    let read = fs.read(reader, &mut buffer).unwrap();
+    let end = fs.read(reader, &mut buffer);
+    let writer = fs.open("/fifo", OpenFlag::O_RDWR).unwrap();
+    fs.close(reader).unwrap();
+    let unread = fs.write(writer, b"kept");
+    let other_reader = fs.open("/fifo", OpenFlag::O_RDONLY | OpenFlag::O_NONBLOCK).unwrap();
+    fs.close(writer).unwrap();
+    let broken_writer = fs.open("/fifo", OpenFlag::O_WRONLY | OpenFlag::O_NONBLOCK).unwrap();
+    fs.close(other_reader).unwrap();
+    let broken = fs.write(broken_writer, b"nobody listens");
+
+    /* Assert */
+
+    assert_eq!(&buffer[..read], b"last words");
+    assert_eq!(end.unwrap(), 0);
+    assert_eq!(unread.unwrap(), 4);
+    assert!(broken.is_err_and(|e| matches!(e.err_type, MemFSErrType::EPIPE)));
+
+    let journal = fs.journal().unwrap();
+    let replayed = MemFS::new();
+    journal.replay(&replayed).unwrap();
+
+    assert_eq!(journal.entries().len(), 1);
+    assert!(matches!(&journal.entries()[0], JournalEntry::Mkfifo { mode: 0o600, .. }));
+    assert_eq!(replayed.stat("/fifo").unwrap().file_type, FileType::Fifo);
+}
+
+#[test]
+fn test_lock_exclusive_should_not_wait_for_blocked_fifo_reads() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().exclusive_lock(true).build());
+    fs.mkfifo("/fifo", 0o600).unwrap();
+
+    let reader = {
+        let fs = fs.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/fifo", OpenFlag::O_RDONLY).unwrap();
+            let mut buffer = [0u8; 16];
+            let read = fs.read(fd, &mut buffer).unwrap();
+
+            buffer[..read].to_vec()
+        })
+    };
+    let writer = fs.open("/fifo", OpenFlag::O_WRONLY).unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    /* Action */
+
+    let guard = fs.try_lock_exclusive_for(Duration::from_secs(5));
+    let locked = guard.is_ok();
+    let written = fs.write(writer, b"wake up");
+    drop(guard);
+
+    /* Assert */
+
+    assert!(locked);
+    assert!(written.is_ok());
+    assert_eq!(reader.join().unwrap(), b"wake up");
+}
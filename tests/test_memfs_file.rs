@@ -1,5 +1,5 @@
 use memfs::memfs::MemFS;
-use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag, generate_random_vector};
+use memfs::utils::{MemFSErrType, OpenFlag, PAGE_SIZE, SeekFlag, generate_random_vector};
 use rand::Rng;
 
 #[test]
@@ -221,7 +221,7 @@ fn test_should_fail_when_reading_from_closed_file_descriptor() {
         .unwrap();
     fs.close(fd).unwrap();
 
-    let read_after_close = fs.read(fd, &mut buffer, buffer_size);
+    let read_after_close = fs.read(fd, &mut buffer);
 
     assert!(read_after_close.is_err_and(|e| { matches!(e.err_type, MemFSErrType::EBADF) }))
 }
@@ -239,13 +239,13 @@ fn test_check_lseek_offset_values_with_different_seekflag() {
     let fd = fs
         .open("/kaist.cp", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(fd, &random_buffer, file_size).unwrap();
+    fs.write(fd, &random_buffer).unwrap();
 
     /* Action */
 
-    let offset1 = fs.lseek(fd, random_offset, SeekFlag::SEEK_SET).unwrap();
-    let offset2 = fs.lseek(fd, random_offset, SeekFlag::SEEK_CUR).unwrap();
-    let offset3 = fs.lseek(fd, random_offset, SeekFlag::SEEK_END).unwrap();
+    let offset1 = fs.lseek(fd, random_offset as i64, SeekFlag::SEEK_SET).unwrap();
+    let offset2 = fs.lseek(fd, random_offset as i64, SeekFlag::SEEK_CUR).unwrap();
+    let offset3 = fs.lseek(fd, random_offset as i64, SeekFlag::SEEK_END).unwrap();
 
     /* Assert */
 
@@ -269,10 +269,10 @@ fn test_should_succeed_on_basic_reading_and_writing_on_file() {
 
     /* Action */
 
-    let write_result = fs.write(fd, &random_buffer, buffer_size);
-    let read_result_without_seek = fs.read(fd, &mut reading_buffer, buffer_size);
+    let write_result = fs.write(fd, &random_buffer);
+    let read_result_without_seek = fs.read(fd, &mut reading_buffer);
     fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
-    let read_result_after_seek = fs.read(fd, &mut reading_buffer, buffer_size);
+    let read_result_after_seek = fs.read(fd, &mut reading_buffer);
 
     /* Assert */
 
@@ -296,7 +296,7 @@ fn test_should_succeed_when_writing_and_reading_file_with_seek() {
         .unwrap();
     let mut comparison_buffer = generate_random_vector(buffer_size);
 
-    fs.write(fd, &comparison_buffer, buffer_size).unwrap();
+    fs.write(fd, &comparison_buffer).unwrap();
     fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
 
     /* Action */
@@ -308,9 +308,9 @@ fn test_should_succeed_when_writing_and_reading_file_with_seek() {
         let write_random_buffer = generate_random_vector(write_size);
 
         // Write random data, on random position.
-        fs.lseek(fd, random_seek_offset, SeekFlag::SEEK_SET)
+        fs.lseek(fd, random_seek_offset as i64, SeekFlag::SEEK_SET)
             .unwrap();
-        fs.write(fd, &write_random_buffer, write_size).unwrap();
+        fs.write(fd, &write_random_buffer).unwrap();
 
         // Modify original buffer too, for comparison
         comparison_buffer[random_seek_offset..(random_seek_offset + write_size)]
@@ -320,7 +320,7 @@ fn test_should_succeed_when_writing_and_reading_file_with_seek() {
 
         // Read whole file, and check content.
         fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
-        fs.read(fd, &mut reading_buffer, buffer_size).unwrap();
+        fs.read(fd, &mut reading_buffer).unwrap();
 
         if reading_buffer == comparison_buffer {
             equal_count += 1;
@@ -354,7 +354,7 @@ fn test_should_succeed_when_writing_on_single_file_through_multiple_file_descrip
     let init_fd = fs
         .open(file_name, OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(init_fd, &comparison_buffer, buffer_size).unwrap();
+    fs.write(init_fd, &comparison_buffer).unwrap();
     fs.close(init_fd).unwrap();
 
     /* Action */
@@ -368,8 +368,8 @@ fn test_should_succeed_when_writing_on_single_file_through_multiple_file_descrip
         let random_write_buffer = generate_random_vector(batch_size);
         let random_offset = rand::rng().random_range(0..(buffer_size - batch_size));
 
-        fs.lseek(fd, random_offset, SeekFlag::SEEK_SET).unwrap();
-        fs.write(fd, &random_write_buffer, batch_size).unwrap();
+        fs.lseek(fd, random_offset as i64, SeekFlag::SEEK_SET).unwrap();
+        fs.write(fd, &random_write_buffer).unwrap();
 
         comparison_buffer[random_offset..(random_offset + batch_size)]
             .copy_from_slice(random_write_buffer.as_slice());
@@ -384,7 +384,7 @@ fn test_should_succeed_when_writing_on_single_file_through_multiple_file_descrip
     let final_fd = fs.open(file_name, OpenFlag::O_RDONLY).unwrap();
     let mut final_buffer = vec![0; buffer_size];
 
-    fs.read(final_fd, &mut final_buffer, buffer_size).unwrap();
+    fs.read(final_fd, &mut final_buffer).unwrap();
     fs.close(final_fd).unwrap();
 
     /* Assert */
@@ -405,12 +405,12 @@ fn test_should_fail_when_reading_from_write_only_file() {
     let write_only_fd = fs
         .open("/write.f2", OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
         .unwrap();
-    fs.write(write_only_fd, &random_buffer, buffer_size)
+    fs.write(write_only_fd, &random_buffer)
         .unwrap();
 
     /* Action */
 
-    let read_result = fs.read(write_only_fd, &mut placeholder_buffer, buffer_size);
+    let read_result = fs.read(write_only_fd, &mut placeholder_buffer);
 
     /* Assert */
 
@@ -430,7 +430,7 @@ fn test_should_succeed_when_reading_and_fail_when_writing_on_read_only_file() {
     let initial_fd = fs
         .open("/victim.vic", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(initial_fd, &random_buffer, buffer_size).unwrap();
+    fs.write(initial_fd, &random_buffer).unwrap();
     fs.close(initial_fd).unwrap();
 
     // Open file again, now on read-only mode.
@@ -439,8 +439,8 @@ fn test_should_succeed_when_reading_and_fail_when_writing_on_read_only_file() {
     /* Action */
 
     // Try reading, and writing on file.
-    let read_result = fs.read(read_only_fd, &mut buffer, buffer_size);
-    let write_on_read_only = fs.write(read_only_fd, &random_buffer, buffer_size);
+    let read_result = fs.read(read_only_fd, &mut buffer);
+    let write_on_read_only = fs.write(read_only_fd, &random_buffer);
 
     /* Assert */
 
@@ -453,7 +453,7 @@ fn test_should_succeed_when_reading_and_fail_when_writing_on_read_only_file() {
 }
 
 #[test]
-fn test_should_fail_when_reading_from_file_by_more_than_buffer_size() {
+fn test_should_read_no_more_than_buffer_size_from_larger_file() {
     /* Arrange */
 
     let fs = MemFS::new();
@@ -466,7 +466,7 @@ fn test_should_fail_when_reading_from_file_by_more_than_buffer_size() {
     let initial_fd = fs
         .open(file_name, OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(initial_fd, &random_buffer, file_size).unwrap();
+    fs.write(initial_fd, &random_buffer).unwrap();
     fs.close(initial_fd).unwrap();
 
     let mut read_buffer = vec![0; buffer_size];
@@ -475,23 +475,24 @@ fn test_should_fail_when_reading_from_file_by_more_than_buffer_size() {
 
     /* Action */
 
-    // Try to read more than buffer size; it should fail.
-    let read_result = fs.read(fd, &mut read_buffer, file_size);
+    // The file is larger than the buffer; only the buffer gets filled.
+    let read_result = fs.read(fd, &mut read_buffer);
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
 
     /* Assert */
 
-    assert!(read_result.is_err_and(|e| { matches!(e.err_type, MemFSErrType::EFAULT) }));
+    assert!(read_result.is_ok_and(|result| { result == buffer_size }));
+    assert_eq!(read_buffer, random_buffer[..buffer_size]);
+    assert_eq!(offset, buffer_size);
 }
 
 #[test]
-fn test_should_succeed_when_reading_from_file_by_more_than_buffer_size_but_actual_content_size_is_not_larger_than_buffer_size()
- {
+fn test_should_succeed_when_reading_into_buffer_larger_than_file() {
     /* Arrange */
 
     let fs = MemFS::new();
     let file_size = 64;
-    let pseudo_read_size = 256;
-    let buffer_size = file_size;
+    let buffer_size = 256;
     let file_name = "/donald.trump";
     let random_buffer = generate_random_vector(file_size);
 
@@ -499,7 +500,7 @@ fn test_should_succeed_when_reading_from_file_by_more_than_buffer_size_but_actua
     let initial_fd = fs
         .open(file_name, OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(initial_fd, &random_buffer, file_size).unwrap();
+    fs.write(initial_fd, &random_buffer).unwrap();
     fs.close(initial_fd).unwrap();
 
     let mut read_buffer = vec![0; buffer_size];
@@ -507,8 +508,8 @@ fn test_should_succeed_when_reading_from_file_by_more_than_buffer_size_but_actua
 
     /* Action */
 
-    // Try to read more than buffer size, but the actual content size is not larger than buffer size.
-    let read_result = fs.read(fd, &mut read_buffer, pseudo_read_size);
+    // Try to read more than the file holds; only the content of the file is read.
+    let read_result = fs.read(fd, &mut read_buffer);
 
     /* Assert */
 
@@ -530,7 +531,7 @@ fn test_should_succeed_when_writing_over_the_file_size() {
     let initial_fd = fs
         .open(file_name, OpenFlag::O_CREAT | OpenFlag::O_RDWR)
         .unwrap();
-    fs.write(initial_fd, &random_buffer, small_buffer_size)
+    fs.write(initial_fd, &random_buffer)
         .unwrap();
     fs.close(initial_fd).unwrap();
 
@@ -538,7 +539,7 @@ fn test_should_succeed_when_writing_over_the_file_size() {
 
     /* Action */
 
-    let large_write_result = fs.write(write_fd, &large_random_buffer, large_buffer_size);
+    let large_write_result = fs.write(write_fd, &large_random_buffer);
 
     /* Assert */
 
@@ -576,10 +577,10 @@ fn test_check_whether_writes_on_file_descriptor_with_o_append_are_done_regardles
         comparison_buffer.extend(random_buffer.clone().iter());
 
         // Seek random offset, to check whether write is performed at the end of the file.
-        fs.lseek(fd, rng.random_range(0..((i + 1) * 8)), SeekFlag::SEEK_SET)
+        fs.lseek(fd, rng.random_range(0..((i + 1) * 8)) as i64, SeekFlag::SEEK_SET)
             .unwrap();
 
-        fs.write(fd, &random_buffer, buffer_size).unwrap();
+        fs.write(fd, &random_buffer).unwrap();
 
         // Get current offset.
         let write_offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
@@ -587,7 +588,7 @@ fn test_check_whether_writes_on_file_descriptor_with_o_append_are_done_regardles
     }
 
     fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
-    fs.read(fd, &mut result_buffer, loops * buffer_size)
+    fs.read(fd, &mut result_buffer)
         .unwrap();
     fs.close(fd).unwrap();
 
@@ -600,3 +601,91 @@ fn test_check_whether_writes_on_file_descriptor_with_o_append_are_done_regardles
     assert_eq!(result_buffer, comparison_buffer);
     assert_eq!(offsets_after_writes, expected_offsets);
 }
+
+#[test]
+fn test_lseek_with_seek_data_and_seek_hole_should_report_hole_at_end() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let file_size = 64;
+    let random_buffer = generate_random_vector(file_size);
+
+    let fd = fs
+        .open("/sparse.dat", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &random_buffer).unwrap();
+
+    /* Action */
+
+    let data = fs.lseek(fd, 10, SeekFlag::SEEK_DATA).unwrap();
+    let hole = fs.lseek(fd, 10, SeekFlag::SEEK_HOLE).unwrap();
+    let data_past_end = fs.lseek(fd, file_size as i64, SeekFlag::SEEK_DATA);
+    let hole_past_end = fs.lseek(fd, file_size as i64 + 1, SeekFlag::SEEK_HOLE);
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+
+    /* Assert */
+
+    assert_eq!(data, 10);
+    assert_eq!(hole, file_size);
+    assert!(data_past_end.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENXIO)));
+    assert!(hole_past_end.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENXIO)));
+    assert_eq!(offset, file_size);
+}
+
+#[test]
+fn test_lseek_with_seek_data_and_seek_hole_should_skip_unwritten_pages() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs
+        .open("/sparse.dat", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &generate_random_vector(PAGE_SIZE / 2)).unwrap();
+    fs.pwrite(fd, b"x", 10 * PAGE_SIZE).unwrap();
+
+    /* Action */
+
+    let hole_after_first_page = fs.lseek(fd, 0, SeekFlag::SEEK_HOLE).unwrap();
+    let data_within_first_page = fs.lseek(fd, 5, SeekFlag::SEEK_DATA).unwrap();
+    let data_after_hole = fs.lseek(fd, PAGE_SIZE as i64, SeekFlag::SEEK_DATA).unwrap();
+    let hole_within_hole = fs.lseek(fd, 3 * PAGE_SIZE as i64 + 7, SeekFlag::SEEK_HOLE).unwrap();
+    let hole_at_end = fs.lseek(fd, 10 * PAGE_SIZE as i64, SeekFlag::SEEK_HOLE).unwrap();
+
+    /* Assert */
+
+    assert_eq!(hole_after_first_page, PAGE_SIZE);
+    assert_eq!(data_within_first_page, 5);
+    assert_eq!(data_after_hole, 10 * PAGE_SIZE);
+    assert_eq!(hole_within_hole, 3 * PAGE_SIZE + 7);
+    assert_eq!(hole_at_end, 10 * PAGE_SIZE + 1);
+}
+
+#[test]
+fn test_lseek_with_negative_offset_should_move_backwards_or_fail_before_start() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let file_size = 64;
+    let random_buffer = generate_random_vector(file_size);
+
+    let fd = fs
+        .open("/backwards.dat", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &random_buffer).unwrap();
+
+    /* Action */
+
+    let from_end = fs.lseek(fd, -16, SeekFlag::SEEK_END).unwrap();
+    let from_current = fs.lseek(fd, -8, SeekFlag::SEEK_CUR).unwrap();
+    let before_start = fs.lseek(fd, -41, SeekFlag::SEEK_CUR);
+    let negative_set = fs.lseek(fd, -1, SeekFlag::SEEK_SET);
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+
+    /* Assert */
+
+    assert_eq!(from_end, file_size - 16);
+    assert_eq!(from_current, file_size - 24);
+    assert!(before_start.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(negative_set.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(offset, file_size - 24);
+}
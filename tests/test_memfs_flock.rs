@@ -0,0 +1,98 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use memfs::flock::LockOp;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_flock_should_share_or_exclude_between_descriptors() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let first = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let second = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let third = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    fs.try_flock(first, LockOp::Shared).unwrap();
+    let both_shared = fs.try_flock(second, LockOp::Shared);
+    let exclusive_while_shared = fs.try_flock(third, LockOp::Exclusive);
+
+    fs.try_flock(first, LockOp::Unlock).unwrap();
+    fs.close(second).unwrap();
+    let exclusive_after_release = fs.try_flock(third, LockOp::Exclusive);
+    let shared_while_exclusive = fs.try_flock(first, LockOp::Shared);
+
+    /* Assert */
+
+    assert!(both_shared.is_ok());
+    assert!(exclusive_while_shared.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(exclusive_after_release.is_ok());
+    assert!(shared_while_exclusive.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(fs.try_flock(third, LockOp::Shared).is_ok());
+    assert!(fs.try_flock(42, LockOp::Shared).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_flock_should_wait_until_conflicting_descriptor_is_closed() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let holder = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let waiter = fs.open("/file", OpenFlag::O_RDWR).unwrap();
+    fs.flock(holder, LockOp::Exclusive).unwrap();
+    let acquired = Arc::new(AtomicBool::new(false));
+
+    /* Action */
+
+    let handle = {
+        let fs = fs.clone();
+        let acquired = acquired.clone();
+
+        thread::spawn(move || {
+            fs.flock(waiter, LockOp::Exclusive).unwrap();
+            acquired.store(true, Ordering::SeqCst);
+        })
+    };
+
+    thread::sleep(Duration::from_millis(50));
+    let acquired_while_held = acquired.load(Ordering::SeqCst);
+    fs.close(holder).unwrap();
+    handle.join().unwrap();
+
+    /* Assert */
+
+    assert!(!acquired_while_held);
+    assert!(acquired.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_flock_should_convert_lock_of_same_descriptor() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let other = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    fs.flock(fd, LockOp::Shared).unwrap();
+    let upgraded = fs.try_flock(fd, LockOp::Exclusive);
+    let other_while_upgraded = fs.try_flock(other, LockOp::Shared);
+    fs.flock(fd, LockOp::Shared).unwrap();
+    let other_after_downgrade = fs.try_flock(other, LockOp::Shared);
+
+    /* Assert */
+
+    assert!(upgraded.is_ok());
+    assert!(other_while_upgraded.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(other_after_downgrade.is_ok());
+}
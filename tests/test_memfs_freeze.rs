@@ -0,0 +1,90 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use memfs::freeze::FreezeMode;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag, generate_random_vector};
+
+#[test]
+fn test_mutations_should_fail_with_eagain_on_fs_frozen_in_fail_mode() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = generate_random_vector(8);
+    let mut buffer = vec![0u8; 8];
+    let fd = fs
+        .open("/frozen.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+
+    /* Action */
+
+    fs.freeze_with(FreezeMode::Fail).unwrap();
+
+    let write_result = fs.write(fd, &data);
+    let mkdir_result = fs.mkdir("/dir");
+    let create_result = fs.open("/other.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+    let read_result = fs.read(fd, &mut buffer);
+    let open_result = fs.open("/frozen.txt", OpenFlag::O_RDONLY);
+
+    fs.thaw().unwrap();
+    let mkdir_after_thaw = fs.mkdir("/dir");
+
+    /* Assert */
+
+    assert!(write_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(mkdir_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(create_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert_eq!(read_result.unwrap(), 8);
+    assert_eq!(buffer, data);
+    assert!(open_result.is_ok());
+    assert!(mkdir_after_thaw.is_ok());
+}
+
+#[test]
+fn test_mutations_should_wait_until_thaw_on_fs_frozen_in_block_mode() {
+    let fs = Arc::new(MemFS::new());
+    let done = Arc::new(AtomicBool::new(false));
+
+    fs.freeze().unwrap();
+
+    let handle = {
+        let fs = fs.clone();
+        let done = done.clone();
+
+        thread::spawn(move || {
+            fs.mkdir("/late").unwrap();
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+
+    thread::sleep(Duration::from_millis(50));
+    let done_while_frozen = done.load(Ordering::SeqCst);
+
+    fs.thaw().unwrap();
+    handle.join().unwrap();
+
+    assert!(!done_while_frozen);
+    assert!(done.load(Ordering::SeqCst));
+    assert!(fs.mkdir("/late").is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+}
+
+#[test]
+fn test_should_fail_when_freezing_twice_or_thawing_unfrozen_fs() {
+    let fs = MemFS::new();
+
+    let thaw_unfrozen = fs.thaw();
+    fs.freeze().unwrap();
+    let freeze_again = fs.freeze();
+
+    assert!(thaw_unfrozen.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(freeze_again.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBUSY)));
+    assert!(fs.is_frozen());
+}
@@ -0,0 +1,73 @@
+use memfs::hash::MemFSHasher;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+const HASHERS: [MemFSHasher; 3] = [
+    MemFSHasher::Random,
+    MemFSHasher::Deterministic,
+    MemFSHasher::Fx,
+];
+
+#[test]
+fn test_every_hasher_should_find_entries() {
+    for hasher in HASHERS {
+        /* Arrange */
+
+        let fs = MemFS::builder().hasher(hasher).build();
+
+        fs.mkdir("/dir").unwrap();
+
+        /* Action */
+
+        let fds: Vec<usize> = (0..500)
+            .map(|i| {
+                fs.open(&format!("/dir/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                    .unwrap()
+            })
+            .collect();
+
+        for fd in &fds {
+            fs.close(*fd).unwrap();
+        }
+
+        let entries = fs.stat_dir_entries("/dir").unwrap();
+        let missing = fs.open("/dir/file_500", OpenFlag::O_RDONLY);
+
+        /* Assert */
+
+        assert_eq!(entries.len(), 500, "hasher {hasher:?}");
+        assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+
+        for i in 0..500 {
+            assert!(fs.unlink(&format!("/dir/file_{}", i)).is_ok(), "hasher {hasher:?}");
+        }
+
+        assert!(fs.rmdir("/dir").is_ok(), "hasher {hasher:?}");
+    }
+}
+
+#[test]
+fn test_hasher_should_apply_to_nested_directories() {
+    /* Arrange */
+
+    let fs = MemFS::builder().hasher(MemFSHasher::Fx).build();
+    let mut path = String::new();
+
+    /* Action */
+
+    for i in 0..10 {
+        path.push_str(&format!("/nested_{}", i));
+        fs.mkdir(&path).unwrap();
+    }
+
+    let fd = fs
+        .open(&format!("{}/leaf", path), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    let closed = fs.close(fd);
+    let stat = fs.stat(&format!("{}/leaf", path));
+
+    /* Assert */
+
+    assert!(closed.is_ok());
+    assert!(stat.is_ok());
+}
@@ -0,0 +1,91 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn read_all(fs: &MemFS, fd: usize) -> Vec<u8> {
+    let mut buffer = [0u8; 64];
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    buffer[..read].to_vec()
+}
+
+#[test]
+fn test_checkpoint_should_keep_contents_readable_after_writes() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"first").unwrap();
+
+    /* Action */
+
+    let first = fs.checkpoint(fd).unwrap();
+    let unchanged = fs.checkpoint(fd).unwrap();
+    fs.pwrite(fd, b"second draft", 0).unwrap();
+    let second = fs.checkpoint(fd).unwrap();
+    fs.ftruncate(fd, 0).unwrap();
+
+    let versions = fs.versions("/file").unwrap();
+    let old_fd = fs.open_version("/file", first).unwrap();
+    let old_contents = read_all(&fs, old_fd);
+    let write_to_version = fs.write(old_fd, b"x");
+
+    /* Assert */
+
+    assert_eq!(unchanged, first);
+    assert!(second > first);
+    assert_eq!(
+        versions.iter().map(|version| (version.number, version.size)).collect::<Vec<_>>(),
+        vec![(first, 5), (second, 12)]
+    );
+    assert_eq!(old_contents, b"first");
+    assert!(write_to_version.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert_eq!(fs.fstat(fd).unwrap().size, 0);
+}
+
+#[test]
+fn test_version_on_write_should_save_previous_contents_up_to_the_limit() {
+    /* Arrange */
+
+    let fs = MemFS::builder().version_on_write(true).max_versions(2).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    for data in [b"a", b"b", b"c", b"d"] {
+        fs.pwrite(fd, data, 0).unwrap();
+    }
+
+    let versions = fs.versions("/file").unwrap();
+    let oldest = fs.open_version("/file", versions[0].number).unwrap();
+    let dropped = fs.open_version("/file", versions[0].number - 1);
+
+    /* Assert */
+
+    assert_eq!(versions.len(), 2);
+    assert_eq!(read_all(&fs, oldest), b"b");
+    assert!(dropped.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_versions_should_fail_on_directories_and_missing_files() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let of_directory = fs.versions("/dir");
+    let of_missing = fs.versions("/missing");
+    let never_saved = fs.versions("/file").unwrap();
+    fs.close(fd).unwrap();
+    let closed = fs.checkpoint(fd);
+
+    /* Assert */
+
+    assert!(of_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(of_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(never_saved.is_empty());
+    assert!(closed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
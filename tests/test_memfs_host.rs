@@ -0,0 +1,165 @@
+use std::{fs, path::PathBuf};
+
+use memfs::host::CollisionMode;
+use memfs::memfs::MemFS;
+use memfs::utils::{FILE_MAX_SIZE, FileType, MemFSErrType, OpenFlag};
+
+fn host_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("memfs_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_load_from_disk_should_mirror_host_directory() {
+    /* Arrange */
+
+    let dir = host_dir("load");
+    fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+    fs::write(dir.join("top.txt"), b"top").unwrap();
+    fs::write(dir.join("sub/inner.bin"), [0u8, 1, 2, 255]).unwrap();
+    fs::write(dir.join("sub/empty"), b"").unwrap();
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("sub/inner.bin", dir.join("link")).unwrap();
+
+    let memfs = MemFS::new();
+
+    /* Action */
+
+    let result = memfs.load_from_disk(&dir);
+
+    /* Assert */
+
+    assert!(result.is_ok());
+    assert_eq!(read_file(&memfs, "/top.txt"), b"top");
+    assert_eq!(read_file(&memfs, "/sub/inner.bin"), [0u8, 1, 2, 255]);
+    assert_eq!(read_file(&memfs, "/sub/empty"), b"");
+    assert_eq!(memfs.stat("/sub/deeper").unwrap().file_type, FileType::Directory);
+
+    #[cfg(unix)]
+    assert_eq!(memfs.readlink("/link").unwrap(), "sub/inner.bin");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_from_disk_should_fail_on_existing_entry_large_file_and_missing_directory() {
+    /* Arrange */
+
+    let dir = host_dir("load_fail");
+    fs::write(dir.join("a"), b"a").unwrap();
+
+    let large_dir = host_dir("load_large");
+    fs::write(large_dir.join("large"), vec![0u8; FILE_MAX_SIZE + 1]).unwrap();
+
+    let memfs = MemFS::new();
+    memfs.mkdir("/a").unwrap();
+
+    /* Action */
+
+    let exists_result = memfs.load_from_disk(&dir);
+    let large_result = memfs.load_from_disk(&large_dir);
+    let missing_result = memfs.load_from_disk(dir.join("missing"));
+
+    /* Assert */
+
+    assert!(exists_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(large_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert!(missing_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(memfs.stat("/large").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&large_dir).unwrap();
+}
+
+#[test]
+fn test_dump_to_disk_should_write_tree_that_loads_back() {
+    /* Arrange */
+
+    let dir = host_dir("dump");
+    let memfs = MemFS::new();
+
+    memfs.mkdir("/sub").unwrap();
+    memfs.mkdir("/sub/empty").unwrap();
+    write_file(&memfs, "/top.txt", b"top");
+    write_file(&memfs, "/sub/inner.bin", &[0u8, 1, 2, 255]);
+    memfs.link("/top.txt", "/sub/alias").unwrap();
+
+    /* Action */
+
+    let report = memfs.dump_to_disk(dir.join("out")).unwrap();
+    let loaded = MemFS::new();
+    loaded.load_from_disk(dir.join("out")).unwrap();
+
+    /* Assert */
+
+    assert_eq!(report.directories, 2);
+    assert_eq!(report.files, 3);
+    assert!(report.failed.is_empty());
+    assert_eq!(fs::read(dir.join("out/sub/alias")).unwrap(), b"top");
+    assert_eq!(read_file(&loaded, "/sub/inner.bin"), [0u8, 1, 2, 255]);
+    assert_eq!(loaded.stat("/sub/empty").unwrap().file_type, FileType::Directory);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dump_to_disk_should_report_collisions_by_mode() {
+    /* Arrange */
+
+    let dir = host_dir("dump_collision");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/taken"), b"host").unwrap();
+    fs::create_dir(dir.join("blocked")).unwrap();
+
+    let memfs = MemFS::new();
+    memfs.mkdir("/sub").unwrap();
+    write_file(&memfs, "/sub/taken", b"memfs");
+    write_file(&memfs, "/sub/free", b"free");
+    write_file(&memfs, "/blocked", b"file");
+
+    /* Action */
+
+    let failed = memfs.dump_to_disk(&dir).unwrap();
+    let after_fail = fs::read(dir.join("sub/taken")).unwrap();
+    let skipped = memfs.dump_to_disk_with(&dir, CollisionMode::Skip).unwrap();
+    let overwritten = memfs.dump_to_disk_with(&dir, CollisionMode::Overwrite).unwrap();
+
+    /* Assert */
+
+    let failed_paths: Vec<&str> = failed.failed.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(failed_paths, vec!["/blocked", "/sub/taken"]);
+    assert!(failed.failed.iter().all(|(_, e)| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert_eq!(after_fail, b"host");
+    assert_eq!(fs::read(dir.join("sub/free")).unwrap(), b"free");
+
+    assert_eq!(skipped.skipped, 3);
+    assert!(skipped.failed.is_empty());
+
+    assert_eq!(overwritten.files, 2);
+    assert_eq!(overwritten.failed.len(), 1);
+    assert_eq!(overwritten.failed[0].0, "/blocked");
+    assert!(matches!(overwritten.failed[0].1.err_type, MemFSErrType::EISDIR));
+    assert_eq!(fs::read(dir.join("sub/taken")).unwrap(), b"memfs");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use memfs::inode::ROOT_INO;
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_nodes_should_get_unique_inode_numbers() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+
+    /* Action */
+
+    let inos = ["/", "/dir", "/dir/file"]
+        .into_iter()
+        .map(|path| fs.stat(path).unwrap().ino)
+        .collect::<Vec<_>>();
+
+    /* Assert */
+
+    assert_eq!(inos[0], ROOT_INO);
+    assert_eq!(inos.iter().collect::<HashSet<_>>().len(), inos.len());
+    assert_eq!(fs.stat("/link").unwrap().ino, inos[2]);
+}
+
+#[test]
+fn test_inode_number_should_be_shared_by_hard_links_and_kept_across_rename() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    let original = fs.stat("/file").unwrap().ino;
+
+    /* Action */
+
+    fs.link("/file", "/hard").unwrap();
+    fs.rename("/file", "/moved").unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/hard").unwrap().ino, original);
+    assert_eq!(fs.stat("/moved").unwrap().ino, original);
+}
+
+#[test]
+fn test_stat_inode_should_find_nodes_still_held() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"data").unwrap();
+    let dir_ino = fs.stat("/dir").unwrap().ino;
+    let file_ino = fs.stat("/file").unwrap().ino;
+
+    /* Action */
+
+    let dir = fs.stat_inode(dir_ino).unwrap();
+    fs.unlink("/file").unwrap();
+    let open_after_unlink = fs.stat_inode(file_ino).unwrap();
+    let never_allocated = fs.stat_inode(u64::MAX);
+    fs.close(fd).unwrap();
+
+    /* Assert */
+
+    assert!(matches!(dir.file_type, FileType::Directory));
+    assert_eq!(open_after_unlink.size, 4);
+    assert!(never_allocated.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(fs.stat_inode(ROOT_INO).unwrap().ino, ROOT_INO);
+}
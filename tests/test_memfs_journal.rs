@@ -0,0 +1,175 @@
+use std::time::{Duration, SystemTime};
+use std::{sync::Arc, thread};
+
+use memfs::journal::{Journal, JournalEntry};
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, OpenFlag, generate_random_vector};
+
+/// Lists every path under `dir` with the contents of files and the targets of symbolic links.
+fn dump(fs: &MemFS, dir: &str) -> Vec<(String, Vec<u8>)> {
+    let mut out = Vec::new();
+
+    for entry in fs.readdir(dir).unwrap() {
+        let path = format!("{}/{}", dir.trim_end_matches('/'), entry.name);
+
+        match entry.file_type {
+            FileType::Directory => {
+                out.push((path.clone(), Vec::new()));
+                out.extend(dump(fs, &path));
+            }
+            FileType::Symlink => out.push((path.clone(), fs.readlink(&path).unwrap().into_bytes())),
+            _ => {
+                let mut contents = vec![0u8; fs.stat(&path).unwrap().size];
+                let fd = fs.open(&path, OpenFlag::O_RDONLY).unwrap();
+                fs.pread(fd, &mut contents, 0).unwrap();
+                fs.close(fd).unwrap();
+                out.push((path, contents));
+            }
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_journal_replay_should_rebuild_tree_and_contents() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+    fs.mkdir("/gone").unwrap();
+    fs.mkdir("/gone/deeper").unwrap();
+
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"hello world").unwrap();
+    fs.pwrite(fd, b"HELLO", 0).unwrap();
+    fs.rename("/dir/file", "/dir/sub/moved").unwrap();
+    fs.write(fd, b", again").unwrap();
+    fs.close(fd).unwrap();
+
+    let fd = fs.open("/log", OpenFlag::O_CREAT | OpenFlag::O_WRONLY | OpenFlag::O_APPEND).unwrap();
+    fs.write(fd, b"one").unwrap();
+    fs.write(fd, b"two").unwrap();
+    fs.close(fd).unwrap();
+
+    fs.link("/log", "/dir/log").unwrap();
+    fs.symlink("/dir/sub/moved", "/link").unwrap();
+    fs.truncate("/log", 4).unwrap();
+    fs.unlink("/log").unwrap();
+    fs.remove_dir_all("/gone").unwrap();
+    let _ = fs.rmdir("/dir");
+
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    let journal = fs.journal().unwrap();
+    journal.replay(&fresh).unwrap();
+
+    /* Assert */
+
+    assert_eq!(dump(&fresh, "/"), dump(&fs, "/"));
+    assert!(!journal.entries().iter().any(|entry| matches!(entry, JournalEntry::Rmdir { .. })));
+    assert_eq!(
+        dump(&fresh, "/dir/sub"),
+        vec![("/dir/sub/moved".to_string(), b"HELLO world, again".to_vec())]
+    );
+}
+
+#[test]
+fn test_journal_prefix_should_rebuild_intermediate_state() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, b"first").unwrap();
+    fs.pwrite(fd, b"second", 0).unwrap();
+    fs.close(fd).unwrap();
+
+    let entries = fs.journal().unwrap().entries().to_vec();
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    Journal::new(entries[..2].to_vec()).replay(&fresh).unwrap();
+
+    /* Assert */
+
+    assert_eq!(entries.len(), 3);
+    assert!(matches!(&entries[2], JournalEntry::Write { offset: 0, data, .. } if data == b"second"));
+    assert_eq!(dump(&fresh, "/"), vec![("/file".to_string(), b"first".to_vec())]);
+}
+
+#[test]
+fn test_journal_should_order_concurrent_mutations() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().journal(true).build());
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                let path = format!("/file_{}", i);
+                let fd = fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+                for _ in 0..20 {
+                    fs.write(fd, &generate_random_vector(16)).unwrap();
+                }
+
+                fs.rename(&path, &format!("/renamed_{}", i)).unwrap();
+                fs.close(fd).unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    fs.journal().unwrap().replay(&fresh).unwrap();
+
+    /* Assert */
+
+    assert_eq!(dump(&fresh, "/"), dump(&fs, "/"));
+    assert!(MemFS::new().journal().is_none());
+}
+
+#[test]
+fn test_journal_replay_should_restore_permission_bits_owners_and_times() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.fchmod(fd, 0o600).unwrap();
+    fs.fchown(fd, Some(1000), None).unwrap();
+    fs.close(fd).unwrap();
+    fs.chmod("/dir", 0o750).unwrap();
+    fs.chown("/dir", None, Some(100)).unwrap();
+    fs.utimens("/dir/file", None, Some(mtime)).unwrap();
+
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    fs.journal().unwrap().replay(&fresh).unwrap();
+
+    /* Assert */
+
+    let journal = fs.journal().unwrap();
+    let entries = journal.entries();
+    assert!(entries.contains(&JournalEntry::Chmod { path: "/dir".to_string(), mode: 0o750 }));
+    assert!(entries.iter().any(|entry| matches!(entry, JournalEntry::Fchmod { mode: 0o600, .. })));
+
+    let (file, dir) = (fresh.stat("/dir/file").unwrap(), fresh.stat("/dir").unwrap());
+    assert_eq!((file.mode, file.uid, file.mtime), (0o600, 1000, mtime));
+    assert_eq!((dir.mode, dir.gid), (0o750, 100));
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use memfs::latency::{LatencyDistribution, LatencyProfile};
+use memfs::memfs::MemFS;
+use memfs::metrics::MemFSOp;
+use memfs::utils::OpenFlag;
+
+#[test]
+fn test_latency_should_delay_configured_operation() {
+    /* Arrange */
+
+    let profile = LatencyProfile::new().op(
+        MemFSOp::Mkdir,
+        LatencyDistribution::Uniform {
+            min: Duration::from_millis(20),
+            max: Duration::from_millis(25),
+        },
+    );
+    let fs = MemFS::builder().latency(profile).build();
+
+    /* Action */
+
+    let started = Instant::now();
+    fs.mkdir("/dir").unwrap();
+    let elapsed = started.elapsed();
+
+    /* Assert */
+
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(fs.stat("/dir").is_ok());
+}
+
+#[test]
+fn test_latency_should_delay_descriptor_calls_on_matching_paths() {
+    /* Arrange */
+
+    let profile = LatencyProfile::new().path("/slow/*", LatencyDistribution::Fixed(Duration::from_millis(30)));
+    let fs = MemFS::builder().latency(profile).build();
+    let mut buffer = vec![0u8; 8];
+
+    fs.mkdir("/slow").unwrap();
+    let fd = fs
+        .open("/slow/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+
+    /* Action */
+
+    let started = Instant::now();
+    fs.read(fd, &mut buffer).unwrap();
+    let elapsed = started.elapsed();
+
+    /* Assert */
+
+    assert!(elapsed >= Duration::from_millis(30));
+}
+
+#[test]
+fn test_write_bandwidth_should_be_shared_by_consecutive_writes() {
+    /* Arrange */
+
+    let profile = LatencyProfile::new().write_bandwidth(40 * 1024);
+    let fs = MemFS::builder().latency(profile).build();
+    let data = vec![b'b'; 1024];
+
+    let fd = fs
+        .open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+
+    /* Action */
+
+    let started = Instant::now();
+    fs.write(fd, &data).unwrap();
+    fs.write(fd, &data).unwrap();
+    let elapsed = started.elapsed();
+
+    /* Assert */
+
+    assert!(elapsed >= Duration::from_millis(50));
+    assert_eq!(fs.stat("/file").unwrap().size, 2048);
+}
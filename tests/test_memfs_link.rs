@@ -0,0 +1,161 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_link_should_share_file_between_names() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/file", b"old");
+
+    /* Action */
+
+    fs.link("/file", "/dir/alias").unwrap();
+    write_file(&fs, "/dir/alias", b"new");
+
+    /* Assert */
+
+    assert_eq!(read_file(&fs, "/file"), b"new");
+    assert_eq!(fs.stat("/file").unwrap().nlink, 2);
+    assert_eq!(fs.stat("/dir/alias").unwrap().nlink, 2);
+}
+
+#[test]
+fn test_unlink_should_keep_file_until_last_link_is_gone() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/file", b"data");
+    fs.link("/file", "/alias").unwrap();
+
+    /* Action */
+
+    fs.unlink("/file").unwrap();
+    let after_first = read_file(&fs, "/alias");
+    let nlink = fs.stat("/alias").unwrap().nlink;
+
+    let fd = fs.open("/alias", OpenFlag::O_RDONLY).unwrap();
+    fs.unlink("/alias").unwrap();
+
+    let mut buffer = vec![0u8; 4];
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(after_first, b"data");
+    assert_eq!(nlink, 1);
+    assert!(fs.stat("/alias").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read, 4);
+    assert_eq!(buffer, b"data");
+}
+
+#[test]
+fn test_link_should_fail_on_directory_existing_name_and_missing_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/file", b"f");
+    write_file(&fs, "/other", b"o");
+
+    /* Action */
+
+    let dir_result = fs.link("/dir", "/dir_alias");
+    let exists_result = fs.link("/file", "/other");
+    let missing_result = fs.link("/missing", "/alias");
+
+    /* Assert */
+
+    assert!(dir_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EPERM)));
+    assert!(exists_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(missing_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(fs.stat("/file").unwrap().nlink, 1);
+    assert_eq!(read_file(&fs, "/other"), b"o");
+}
+
+#[test]
+fn test_rename_should_drop_link_of_replaced_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/file", b"file");
+    write_file(&fs, "/other", b"other");
+    fs.link("/file", "/alias").unwrap();
+
+    /* Action */
+
+    let same_file = fs.rename("/file", "/alias");
+    let links_after_same = fs.stat("/file").unwrap().nlink;
+    fs.rename("/other", "/alias").unwrap();
+
+    /* Assert */
+
+    assert!(same_file.is_ok());
+    assert_eq!(links_after_same, 2);
+    assert_eq!(fs.stat("/file").unwrap().nlink, 1);
+    assert_eq!(read_file(&fs, "/alias"), b"other");
+}
+
+#[test]
+fn test_unlinked_open_file_should_stay_usable_until_last_close() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let blocks_free = fs.statfs().blocks_free;
+    let writer = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let reader = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let mut buffer = vec![0u8; 8];
+
+    /* Action */
+
+    fs.unlink("/file").unwrap();
+    let reopen = fs.open("/file", OpenFlag::O_RDONLY);
+
+    fs.write(writer, b"orphaned").unwrap();
+    let read = fs.read(reader, &mut buffer).unwrap();
+    let orphan = fs.fstat(writer).unwrap();
+
+    let recreated = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let recreated_size = fs.fstat(recreated).unwrap().size;
+    fs.close(recreated).unwrap();
+
+    fs.close(writer).unwrap();
+    fs.lseek(reader, 0, SeekFlag::SEEK_SET).unwrap();
+    let read_after_writer_closed = fs.read(reader, &mut buffer).unwrap();
+    let blocks_free_while_open = fs.statfs().blocks_free;
+    fs.close(reader).unwrap();
+
+    /* Assert */
+
+    assert!(reopen.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read, 8);
+    assert_eq!(buffer, b"orphaned");
+    assert_eq!(orphan.nlink, 0);
+    assert_eq!(orphan.size, 8);
+    assert_eq!(recreated_size, 0);
+    assert_ne!(fs.stat("/file").unwrap().ino, orphan.ino);
+    assert_eq!(read_after_writer_closed, 8);
+    assert_eq!(blocks_free_while_open, blocks_free);
+    assert!(fs.fstat(reader).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
@@ -0,0 +1,82 @@
+#![cfg(all(feature = "check-loom", feature = "coarse-grained"))]
+
+use std::sync::Arc;
+
+use loom::model::Builder;
+use loom::thread;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, Result};
+
+/// Stack of every thread of a scenario. Loom runs its threads on small stacks by default, which
+/// the calls of a file system outgrow.
+const STACK_SIZE: usize = 1 << 22;
+
+/// Explores interleavings with up to two preemptions, which keeps a scenario of two operations tractable.
+fn model(scenario: impl Fn() + Sync + Send + 'static) {
+    let scenario = Arc::new(scenario);
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(2);
+    builder.check(move || {
+        let scenario = scenario.clone();
+        spawn(move || scenario()).join().unwrap();
+    });
+}
+
+fn spawn<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> thread::JoinHandle<T> {
+    thread::Builder::new().stack_size(STACK_SIZE).spawn(f).unwrap()
+}
+
+fn create_exclusively(fs: &MemFS) -> Result<usize> {
+    fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_WRONLY)
+}
+
+#[test]
+fn test_exclusive_creation_should_succeed_exactly_once() {
+    model(|| {
+        /* Arrange */
+
+        let fs = Arc::new(MemFS::new());
+        let other = fs.clone();
+
+        /* Action */
+
+        let racer = spawn(move || create_exclusively(&other));
+        let mine = create_exclusively(&fs);
+        let theirs = racer.join().unwrap();
+
+        /* Assert */
+
+        assert!(mine.is_ok() != theirs.is_ok());
+        assert!(
+            [mine, theirs]
+                .into_iter()
+                .filter_map(Result::err)
+                .all(|e| matches!(e.err_type, MemFSErrType::EEXIST))
+        );
+    });
+}
+
+#[test]
+fn test_concurrent_appends_should_land_in_one_piece() {
+    model(|| {
+        /* Arrange */
+
+        let fs = Arc::new(MemFS::new());
+        let fd = fs.open("/log", OpenFlag::O_CREAT | OpenFlag::O_RDWR | OpenFlag::O_APPEND).unwrap();
+        let other_fd = fs.open("/log", OpenFlag::O_WRONLY | OpenFlag::O_APPEND).unwrap();
+        let other = fs.clone();
+        let mut contents = [0u8; 8];
+
+        /* Action */
+
+        let appender = spawn(move || other.write(other_fd, b"bbbb").unwrap());
+        fs.write(fd, b"aaaa").unwrap();
+        appender.join().unwrap();
+        let read = fs.pread(fd, &mut contents, 0).unwrap();
+
+        /* Assert */
+
+        assert_eq!(read, 8);
+        assert!(&contents == b"aaaabbbb" || &contents == b"bbbbaaaa");
+    });
+}
@@ -0,0 +1,66 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use memfs::maintenance::MaintenanceScheduler;
+use memfs::memfs::MemFS;
+
+#[test]
+fn test_scheduler_should_run_registered_tasks_periodically_until_cancelled() {
+    /* Arrange */
+
+    let scheduler = MaintenanceScheduler::new();
+    let fast = Arc::new(AtomicUsize::new(0));
+    let slow = Arc::new(AtomicUsize::new(0));
+
+    let fast_counter = fast.clone();
+    let slow_counter = slow.clone();
+
+    /* Action */
+
+    let fast_task = scheduler.schedule(Duration::from_millis(2), move || {
+        fast_counter.fetch_add(1, Ordering::Relaxed);
+    });
+    scheduler.schedule(Duration::from_secs(3600), move || {
+        slow_counter.fetch_add(1, Ordering::Relaxed);
+    });
+
+    thread::sleep(Duration::from_millis(60));
+    let cancelled = scheduler.cancel(fast_task);
+    let runs_at_cancel = fast.load(Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(20));
+
+    /* Assert */
+
+    assert!(cancelled);
+    assert!(!scheduler.cancel(fast_task));
+    assert!(runs_at_cancel >= 2);
+    assert!(fast.load(Ordering::Relaxed) <= runs_at_cancel + 1);
+    assert_eq!(slow.load(Ordering::Relaxed), 0);
+    assert_eq!(scheduler.task_count(), 1);
+}
+
+#[test]
+fn test_dropping_memfs_should_stop_maintenance_tasks() {
+    let fs = MemFS::new();
+    let counter = Arc::new(AtomicUsize::new(0));
+    let task_counter = counter.clone();
+
+    fs.maintenance()
+        .schedule(Duration::from_millis(1), move || {
+            task_counter.fetch_add(1, Ordering::Relaxed);
+        });
+    thread::sleep(Duration::from_millis(10));
+    drop(fs);
+
+    let runs_after_drop = counter.load(Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(10));
+
+    assert_eq!(counter.load(Ordering::Relaxed), runs_after_drop);
+    assert_eq!(Arc::strong_count(&counter), 1);
+}
@@ -0,0 +1,274 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{INLINE_FILE_SIZE, NUMBER_OF_MAXIMUM_FILES, OpenFlag, PAGE_SIZE, SeekFlag};
+
+#[test]
+fn test_compact_should_release_idle_blocks_without_reducing_capacity() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs
+        .open("/before.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &[b'b'; PAGE_SIZE]).unwrap();
+
+    /* Action */
+
+    let report = fs.compact();
+    let second_report = fs.compact();
+    let create_after_compaction = fs
+        .open("/after.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .and_then(|fd| fs.write(fd, &[b'a'; PAGE_SIZE]));
+
+    /* Assert */
+
+    let metrics = fs.metrics();
+
+    assert_eq!(
+        report.reclaimed_bytes,
+        ((NUMBER_OF_MAXIMUM_FILES - 1) * PAGE_SIZE) as u64
+    );
+    assert_eq!(second_report.reclaimed_bytes, 0);
+    assert!(create_after_compaction.is_ok());
+    assert_eq!(metrics.memory_blocks_free, NUMBER_OF_MAXIMUM_FILES - 2);
+    assert_eq!(metrics.memory_bytes_reclaimed, report.reclaimed_bytes);
+}
+
+#[test]
+fn test_background_compaction_should_run_on_maintenance_thread() {
+    let fs = MemFS::new();
+
+    let task = fs.enable_background_compaction(Duration::from_millis(5));
+    thread::sleep(Duration::from_millis(50));
+    fs.maintenance().cancel(task);
+
+    assert_eq!(
+        fs.metrics().memory_bytes_reclaimed,
+        (NUMBER_OF_MAXIMUM_FILES * PAGE_SIZE) as u64
+    );
+}
+
+#[test]
+fn test_unlinked_file_should_keep_contents_while_nodes_are_recycled() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let old = vec![b'o'; 64];
+    let mut buffer = vec![0u8; 64];
+
+    let fd = fs
+        .open("/kept.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &old).unwrap();
+    fs.unlink("/kept.txt").unwrap();
+
+    /* Action */
+
+    for round in 0..4 {
+        let data = vec![round as u8; 64];
+
+        for i in 0..200 {
+            let path = format!("/recycled_{}", i);
+            let other = fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+            fs.write(other, &data).unwrap();
+            fs.close(other).unwrap();
+            fs.unlink(&path).unwrap();
+        }
+
+        fs.mkdir("/recycled_dir").unwrap();
+        fs.rmdir("/recycled_dir").unwrap();
+    }
+
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, 64);
+    assert_eq!(buffer, old);
+    assert!(fs.close(fd).is_ok());
+}
+
+#[test]
+fn test_unlinked_file_should_keep_contents_while_emptied_chunks_are_freed() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let old = vec![b'o'; 64];
+    let mut buffer = vec![0u8; 64];
+
+    for i in 0..1000 {
+        let fd = fs.open(&format!("/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+        fs.close(fd).unwrap();
+    }
+
+    let fd = fs.open("/file_500", OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &old).unwrap();
+
+    /* Action */
+
+    for i in 0..1000 {
+        fs.unlink(&format!("/file_{}", i)).unwrap();
+    }
+
+    let all_recreated = (0..1000).all(|i| {
+        let path = format!("/again_{}", i);
+
+        fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
+            .and_then(|fd| fs.write(fd, &[b'n'; 64]).and_then(|_| fs.close(fd)))
+            .and_then(|_| fs.unlink(&path))
+            .is_ok()
+    });
+
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert!(all_recreated);
+    assert_eq!(read, 64);
+    assert_eq!(buffer, old);
+    assert!(fs.close(fd).is_ok());
+}
+
+#[test]
+fn test_nodes_should_be_recycled_safely_across_threads() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    /* Action */
+
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                let data = vec![t as u8 + 1; 32];
+                let mut buffer = vec![0u8; 32];
+                let dir = format!("/thread_{}", t);
+
+                (0..100).all(|_| {
+                    fs.mkdir(&dir).unwrap();
+                    let path = format!("{}/file", dir);
+                    let fd = fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+                    fs.write(fd, &data).unwrap();
+                    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+                    fs.read(fd, &mut buffer).unwrap();
+                    fs.close(fd).unwrap();
+                    fs.unlink(&path).unwrap();
+                    fs.rmdir(&dir).unwrap();
+
+                    buffer == data
+                })
+            })
+        })
+        .collect();
+
+    let all_consistent = handles.into_iter().all(|h| h.join().unwrap());
+
+    /* Assert */
+
+    assert!(all_consistent);
+}
+
+#[test]
+fn test_removed_files_should_return_blocks_to_pool() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = vec![b'd'; 64];
+
+    /* Action */
+
+    let all_created = (0..NUMBER_OF_MAXIMUM_FILES + 16).all(|i| {
+        let path = format!("/file_{}", i % 4);
+
+        fs.open(&path, OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
+            .and_then(|fd| fs.write(fd, &data).and_then(|_| fs.close(fd)))
+            .and_then(|_| fs.unlink(&path))
+            .is_ok()
+    });
+
+    /* Assert */
+
+    assert!(all_created);
+    assert!(fs.statfs().blocks_free > 0);
+}
+
+#[test]
+fn test_files_should_take_pages_as_they_grow() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let large = (0..3 * PAGE_SIZE + 5).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let mut read_back = vec![0u8; large.len()];
+    let mut gap = vec![1u8; PAGE_SIZE];
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    fs.open("/empty", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let free_with_empty = fs.statfs().blocks_free;
+
+    let small = fs.open("/small", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(small, &[b's'; 2 * INLINE_FILE_SIZE]).unwrap();
+    let free_with_small = fs.statfs().blocks_free;
+
+    let fd = fs.open("/large", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.pwrite(fd, &large, 0).unwrap();
+    let read = fs.pread(fd, &mut read_back, 0).unwrap();
+
+    let sparse = fs.open("/sparse", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.pwrite(sparse, b"end", 10 * PAGE_SIZE).unwrap();
+    fs.pread(sparse, &mut gap, 4 * PAGE_SIZE).unwrap();
+    let free_with_all = fs.statfs().blocks_free;
+
+    /* Assert */
+
+    assert_eq!(free_with_empty, blocks_free);
+    assert_eq!(free_with_small, blocks_free - 1);
+    assert_eq!(read, large.len());
+    assert_eq!(read_back, large);
+    assert!(gap.iter().all(|byte| *byte == 0));
+    assert_eq!(free_with_all, blocks_free - 6);
+}
+
+#[test]
+fn test_small_files_should_stay_inline_until_they_grow() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let mut buffer = vec![0u8; 3 * INLINE_FILE_SIZE];
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    let tiny_files = (0..64).all(|i| {
+        fs.open(&format!("/tiny_{}", i), OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
+            .and_then(|fd| fs.write(fd, &[i as u8; INLINE_FILE_SIZE]).and_then(|_| fs.close(fd)))
+            .is_ok()
+    });
+    let free_with_tiny_files = fs.statfs().blocks_free;
+
+    let fd = fs.open("/growing", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"head").unwrap();
+    fs.pwrite(fd, b"tail", 2 * INLINE_FILE_SIZE).unwrap();
+    let read = fs.pread(fd, &mut buffer, 0).unwrap();
+    let free_after_growing = fs.statfs().blocks_free;
+
+    fs.ftruncate(fd, 4).unwrap();
+    let free_after_shrinking = fs.statfs().blocks_free;
+
+    /* Assert */
+
+    assert!(tiny_files);
+    assert_eq!(free_with_tiny_files, blocks_free);
+    assert_eq!(read, 2 * INLINE_FILE_SIZE + 4);
+    assert_eq!(&buffer[..4], b"head");
+    assert!(buffer[4..2 * INLINE_FILE_SIZE].iter().all(|byte| *byte == 0));
+    assert_eq!(&buffer[2 * INLINE_FILE_SIZE..read], b"tail");
+    assert_eq!(free_after_growing, blocks_free - 1);
+    assert_eq!(free_after_shrinking, blocks_free - 1);
+}
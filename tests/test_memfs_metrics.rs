@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use memfs::latency::{LatencyDistribution, LatencyProfile};
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::metrics::MemFSOp;
+use memfs::utils::{MemFSErrType, OpenFlag, generate_random_vector};
+
+#[test]
+fn test_metrics_should_count_calls_errors_and_bytes() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = generate_random_vector(128);
+    let mut buffer = [0u8; 128];
+
+    /* Action */
+
+    let fd = fs
+        .open("/counted.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.open("/missing.txt", OpenFlag::O_RDONLY).unwrap_err();
+
+    let reader = fs.open("/counted.txt", OpenFlag::O_RDONLY).unwrap();
+    fs.read(reader, &mut buffer[..64]).unwrap();
+    fs.close(reader).unwrap();
+
+    /* Assert */
+
+    let metrics = fs.metrics();
+
+    assert_eq!(metrics.calls(MemFSOp::Open), 3);
+    assert_eq!(metrics.errors(MemFSOp::Open), 1);
+    assert_eq!(metrics.calls(MemFSOp::Close), 1);
+    assert_eq!(metrics.bytes_written, 128);
+    assert_eq!(metrics.bytes_read, 64);
+    assert_eq!(metrics.open_file_descriptors, 1);
+    assert_eq!(metrics.memory_blocks_total - metrics.memory_blocks_free, 0);
+}
+
+#[test]
+fn test_metrics_should_count_errors_by_type_and_latencies() {
+    /* Arrange */
+
+    let profile = LatencyProfile::new().op(MemFSOp::Mkdir, LatencyDistribution::Fixed(Duration::from_millis(2)));
+    let fs = MemFSBuilder::new().latency(profile).build();
+    let before = fs.metrics();
+
+    /* Action */
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir").unwrap_err();
+    fs.rmdir("/missing").unwrap_err();
+    fs.open("/dir", OpenFlag::O_WRONLY).unwrap_err();
+
+    let metrics = fs.metrics().since(&before);
+    let mkdir = metrics.latency(MemFSOp::Mkdir);
+
+    /* Assert */
+
+    assert_eq!(metrics.total_calls(), 4);
+    assert_eq!(metrics.errors_of_type(MemFSErrType::EEXIST), 1);
+    assert_eq!(metrics.errors_of_type(MemFSErrType::ENOENT), 1);
+    assert_eq!(metrics.errors_of_type(MemFSErrType::EISDIR), 1);
+    assert_eq!(mkdir.count(), 2);
+    assert!(mkdir.sum() >= Duration::from_millis(4));
+    assert!(mkdir.mean().unwrap() >= Duration::from_millis(2));
+    assert!(mkdir.percentile(0.5).unwrap() > Duration::from_millis(2));
+    assert_eq!(metrics.latency(MemFSOp::Open).count(), 1);
+    assert_eq!(metrics.latency(MemFSOp::Read).percentile(0.99), None);
+}
+
+#[cfg(feature = "prometheus")]
+#[test]
+fn test_encoded_metrics_should_follow_prometheus_text_format() {
+    let fs = MemFS::new();
+    fs.mkdir("/metrics").unwrap();
+    fs.mkdir("/metrics").unwrap_err();
+
+    let encoded = fs.encode_metrics();
+
+    assert!(encoded.contains("# TYPE memfs_operations_total counter"));
+    assert!(encoded.contains("memfs_operations_total{op=\"mkdir\"} 2"));
+    assert!(encoded.contains("memfs_operation_errors_total{op=\"mkdir\"} 1"));
+    assert!(encoded.contains("memfs_open_file_descriptors 0"));
+    assert!(encoded.contains("memfs_errors_total{type=\"EEXIST\"} 1"));
+    assert!(encoded.contains("memfs_operation_duration_seconds_bucket{op=\"mkdir\",le=\"+Inf\"} 2"));
+    assert!(encoded.contains("memfs_operation_duration_seconds_count{op=\"mkdir\"} 2"));
+    assert!(!encoded.contains("memfs_operation_duration_seconds_count{op=\"open\"}"));
+}
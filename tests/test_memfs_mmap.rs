@@ -0,0 +1,94 @@
+use memfs::memfs::MemFS;
+use memfs::mmap::MappingMode;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_read_only_mapping_should_follow_the_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"hello, world").unwrap();
+
+    /* Action */
+
+    let mut region = fs.mmap(fd, 7, 16, MappingMode::ReadOnly).unwrap();
+    let before = region.to_vec().unwrap();
+
+    fs.pwrite(fd, b"there", 7).unwrap();
+    let after_write = region.to_vec().unwrap();
+
+    fs.ftruncate(fd, 9).unwrap();
+    let after_truncate = region.to_vec().unwrap();
+
+    let mut buffer = [0u8; 4];
+    let read = region.read(1, &mut buffer).unwrap();
+    let past_end = region.read(16, &mut buffer).unwrap();
+    let write = region.write(0, b"x");
+
+    /* Assert */
+
+    assert_eq!(region.offset(), 7);
+    assert_eq!(region.len(), 16);
+    assert_eq!(before, b"world");
+    assert_eq!(after_write, b"there");
+    assert_eq!(after_truncate, b"th");
+    assert_eq!(&buffer[..read], b"h");
+    assert_eq!(past_end, 0);
+    assert!(write.is_err_and(|e| matches!(e.err_type, MemFSErrType::EACCES)));
+}
+
+#[test]
+fn test_copy_on_write_mapping_should_keep_writes_private() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"abcdef").unwrap();
+
+    let mut region = fs.mmap(fd, 2, 6, MappingMode::CopyOnWrite).unwrap();
+    fs.pwrite(fd, b"C", 2).unwrap();
+    let before_write = region.to_vec().unwrap();
+
+    /* Action */
+
+    region.write(1, b"XY").unwrap();
+    fs.pwrite(fd, b"F", 5).unwrap();
+
+    let past_region = region.write(5, b"zz");
+    fs.close(fd).unwrap();
+    fs.unlink("/file").unwrap();
+
+    /* Assert */
+
+    assert_eq!(before_write, b"Cdef");
+    assert_eq!(region.to_vec().unwrap(), b"CXYf\0\0");
+    assert!(past_region.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFAULT)));
+}
+
+#[test]
+fn test_mmap_should_check_descriptor_and_range() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, b"pinned").unwrap();
+    let snapshot = fs.open("/file", OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT).unwrap();
+    fs.pwrite(fd, b"PIN", 0).unwrap();
+
+    /* Action */
+
+    let of_write_only = fs.mmap(fd, 0, 4, MappingMode::ReadOnly);
+    let empty = fs.mmap(snapshot, 0, 0, MappingMode::ReadOnly);
+    let overflowing = fs.mmap(snapshot, usize::MAX, 2, MappingMode::ReadOnly);
+    let of_closed = fs.mmap(99, 0, 4, MappingMode::ReadOnly);
+    let pinned = fs.mmap(snapshot, 0, 16, MappingMode::ReadOnly).unwrap();
+
+    /* Assert */
+
+    assert!(of_write_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EACCES)));
+    assert!(empty.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(overflowing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(of_closed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert_eq!(pinned.to_vec().unwrap(), b"pinned");
+}
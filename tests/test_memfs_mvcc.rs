@@ -0,0 +1,114 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+#[test]
+fn test_snapshot_reader_should_not_observe_writes_until_reopen() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let old = vec![b'o'; 32];
+    let new = vec![b'n'; 48];
+    let mut buffer = vec![0u8; 64];
+
+    let writer = fs
+        .open("/pinned.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(writer, &old).unwrap();
+    let reader = fs
+        .open("/pinned.txt", OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT)
+        .unwrap();
+
+    /* Action */
+
+    fs.lseek(writer, 0, SeekFlag::SEEK_SET).unwrap();
+    fs.write(writer, &new).unwrap();
+
+    let pinned_end = fs.lseek(reader, 0, SeekFlag::SEEK_END).unwrap();
+    fs.lseek(reader, 0, SeekFlag::SEEK_SET).unwrap();
+    let pinned_read = fs.read(reader, &mut buffer).unwrap();
+    let pinned_contents = buffer[..pinned_read].to_vec();
+
+    fs.close(reader).unwrap();
+    let reopened = fs
+        .open("/pinned.txt", OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT)
+        .unwrap();
+    let reopened_read = fs.read(reopened, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(pinned_end, 32);
+    assert_eq!(pinned_contents, old);
+    assert_eq!(reopened_read, 48);
+    assert_eq!(buffer[..48], new[..]);
+}
+
+#[test]
+fn test_snapshot_flag_should_require_read_only_mode() {
+    let fs = MemFS::new();
+
+    let result = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR | OpenFlag::O_SNAPSHOT);
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
+
+#[test]
+fn test_snapshot_readers_should_never_see_torn_writes() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let stop = Arc::new(AtomicBool::new(false));
+    let fd = fs
+        .open("/hot.bin", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &vec![0u8; 1024]).unwrap();
+    fs.close(fd).unwrap();
+
+    let writer = {
+        let fs = fs.clone();
+        let stop = stop.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/hot.bin", OpenFlag::O_WRONLY).unwrap();
+            let mut round = 0u8;
+
+            while !stop.load(Ordering::Relaxed) {
+                round = round.wrapping_add(1);
+                fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+                fs.write(fd, &vec![round; 1024]).unwrap();
+            }
+        })
+    };
+
+    /* Action */
+
+    let mut torn = 0;
+
+    for _ in 0..500 {
+        let mut buffer = vec![0u8; 1024];
+        let fd = fs
+            .open("/hot.bin", OpenFlag::O_RDONLY | OpenFlag::O_SNAPSHOT)
+            .unwrap();
+
+        fs.read(fd, &mut buffer).unwrap();
+        fs.close(fd).unwrap();
+
+        if buffer.iter().any(|b| *b != buffer[0]) {
+            torn += 1;
+        }
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    writer.join().unwrap();
+
+    /* Assert */
+
+    assert_eq!(torn, 0);
+}
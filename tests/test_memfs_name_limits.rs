@@ -0,0 +1,83 @@
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::names::NameLimits;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_default_limits_should_be_those_of_linux() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let longest = format!("/{}", "n".repeat(255));
+    let too_long = format!("/{}", "n".repeat(256));
+    let deep = format!("{}/file", "/dir".repeat(1023));
+
+    /* Action */
+
+    let created = fs.mkdir(&longest);
+    let rejected = fs.mkdir(&too_long);
+    let lookup = fs.stat(&too_long);
+    let too_deep = fs.open(&deep, OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    assert!(created.is_ok());
+    assert!(rejected.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(lookup.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(too_deep.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+}
+
+#[test]
+fn test_configured_limits_should_bound_names_paths_and_symlink_targets() {
+    /* Arrange */
+
+    let limits = NameLimits {
+        name_max: 8,
+        path_max: 16,
+        ..NameLimits::default()
+    };
+    let fs = MemFSBuilder::new().name_limits(limits).build();
+
+    /* Action */
+
+    let fitting = fs.mkdir("/eight888");
+    let long_name = fs.mkdir("/ninenine9");
+    let long_path = fs.mkdir("/eight888/seven77");
+    let long_target = fs.symlink("/a/very/long/target", "/link");
+    let rename = fs.rename("/eight888", "/ninenine9");
+
+    /* Assert */
+
+    assert!(fitting.is_ok());
+    assert!(long_name.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(long_path.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(long_target.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(rename.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENAMETOOLONG)));
+    assert!(fs.stat("/eight888").is_ok());
+}
+
+#[test]
+fn test_names_with_nul_or_control_characters_should_be_rejected() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let strict = MemFSBuilder::new()
+        .name_limits(NameLimits {
+            reject_control_characters: true,
+            ..NameLimits::UNLIMITED
+        })
+        .build();
+
+    /* Action */
+
+    let nul = fs.mkdir("/a\0b");
+    let newline = fs.mkdir("/line\nbreak");
+    let strict_newline = strict.mkdir("/line\nbreak");
+    let unlimited = strict.mkdir(&format!("/{}", "n".repeat(1000)));
+
+    /* Assert */
+
+    assert!(nul.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(newline.is_ok());
+    assert!(strict_newline.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(unlimited.is_ok());
+}
@@ -0,0 +1,97 @@
+use memfs::memfs::{MemFS, MemFSBuilder};
+use memfs::names::NameMatching;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn names(fs: &MemFS, path: &str) -> Vec<String> {
+    fs.readdir(path).unwrap().into_iter().map(|entry| entry.name).collect()
+}
+
+#[test]
+fn test_case_insensitive_names_should_match_and_keep_their_case() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().name_matching(NameMatching::CASE_INSENSITIVE).build();
+    fs.mkdir("/Docs").unwrap();
+    let fd = fs.open("/Docs/ReadMe.TXT", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"hello").unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let stat = fs.stat("/docs/README.txt");
+    let duplicate = fs.mkdir("/DOCS");
+    let exclusive = fs.open("/docs/readme.txt", OpenFlag::O_CREAT | OpenFlag::O_EXCL | OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    assert_eq!(stat.unwrap().size, 5);
+    assert!(duplicate.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(exclusive.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert_eq!(names(&fs, "/"), ["Docs"]);
+    assert_eq!(names(&fs, "/DOCS"), ["ReadMe.TXT"]);
+}
+
+#[test]
+fn test_removing_and_renaming_should_use_any_matching_name() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().name_matching(NameMatching::CASE_INSENSITIVE).build();
+    fs.create_dir_all("/Dir/Sub").unwrap();
+    fs.open("/Dir/File", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.open("/Dir/Other", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    fs.rename("/dir/file", "/DIR/Moved").unwrap();
+    fs.rename("/dir/moved", "/dir/MOVED").unwrap();
+    fs.unlink("/dir/OTHER").unwrap();
+    fs.rmdir("/dir/sub").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+
+    /* Assert */
+
+    assert_eq!(names(&fs, "/Dir"), ["MOVED", "sub"]);
+    assert!(fs.stat("/dir/file").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_normalized_names_should_match_canonically_equivalent_names() {
+    /* Arrange */
+
+    let fs = MemFSBuilder::new().name_matching(NameMatching::CASE_INSENSITIVE_NORMALIZED).build();
+    let decomposed = "/Cafe\u{301}";
+    let composed = "/caf\u{e9}";
+
+    /* Action */
+
+    fs.mkdir(decomposed).unwrap();
+    let composed_lookup = fs.stat(composed);
+    let uppercase_lookup = fs.stat("/CAF\u{c9}");
+    let duplicate = fs.mkdir(composed);
+
+    /* Assert */
+
+    assert!(composed_lookup.is_ok());
+    assert!(uppercase_lookup.is_ok());
+    assert!(duplicate.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert_eq!(names(&fs, "/"), ["Cafe\u{301}"]);
+}
+
+#[test]
+fn test_exact_names_should_stay_distinct_by_default() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/DIR").unwrap();
+    fs.mkdir("/caf\u{e9}").unwrap();
+    fs.mkdir("/cafe\u{301}").unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.readdir("/").unwrap().len(), 4);
+    assert_eq!(NameMatching::default(), NameMatching::EXACT);
+}
@@ -0,0 +1,37 @@
+use std::sync::mpsc;
+
+use memfs::memfs::MemFS;
+use memfs::oplog::OpLogger;
+use memfs::utils::{OpenFlag, generate_random_vector};
+
+#[test]
+fn test_op_logger_should_emit_one_json_record_per_operation_in_order() {
+    /* Arrange */
+
+    let (sender, receiver) = mpsc::channel();
+    let mut fs = MemFS::new();
+    fs.set_op_logger(OpLogger::to_channel(sender));
+    let data = generate_random_vector(32);
+
+    /* Action */
+
+    fs.mkdir("/logs").unwrap();
+    let fd = fs
+        .open("/logs/\"quoted\".txt", OpenFlag::O_CREAT | OpenFlag::O_WRONLY)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.rmdir("/nowhere").unwrap_err();
+
+    /* Assert */
+
+    let records: Vec<String> = receiver.try_iter().collect();
+
+    assert_eq!(records.len(), 4);
+    assert!(records[0].starts_with("{\"op\":\"mkdir\",\"path\":\"/logs\",\"fd\":null,\"bytes\":null,\"result\":\"ok\""));
+    assert!(records[1].contains("\"path\":\"/logs/\\\"quoted\\\".txt\""));
+    assert!(records[1].contains(&format!("\"fd\":{fd}")));
+    assert!(records[2].contains("\"op\":\"write\""));
+    assert!(records[2].contains("\"bytes\":32"));
+    assert!(records[3].contains("\"result\":\"ENOENT\""));
+    assert!(records.iter().all(|r| r.contains("\"duration_ns\":") && r.ends_with('}')));
+}
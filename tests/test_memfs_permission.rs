@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use memfs::memfs::MemFS;
+use memfs::permission::{F_OK, R_OK, W_OK, X_OK};
+use memfs::process::{Credentials, MemFSProcess};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn permission_denied<T>(result: memfs::utils::Result<T>) -> bool {
+    result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EACCES))
+}
+
+fn not_permitted<T>(result: memfs::utils::Result<T>) -> bool {
+    result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EPERM))
+}
+
+#[test]
+fn test_new_nodes_should_get_default_modes_masked_by_umask() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let process = MemFSProcess::new(fs.clone());
+    process.umask(0o077);
+
+    /* Action */
+
+    let fd = fs.open("/plain", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    fs.mkdir("/dir").unwrap();
+
+    let fd = process.open("/private", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    process.close(fd).unwrap();
+    process.mkdir("/private_dir").unwrap();
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/").unwrap().mode, 0o755);
+    assert_eq!(fs.stat("/plain").unwrap().mode, 0o644);
+    assert_eq!(fs.stat("/dir").unwrap().mode, 0o755);
+    assert_eq!(fs.stat("/private").unwrap().mode, 0o600);
+    assert_eq!(fs.stat("/private_dir").unwrap().mode, 0o700);
+}
+
+#[test]
+fn test_chmod_and_fchmod_should_set_mode() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+
+    /* Action */
+
+    fs.chmod("/dir", 0o1700).unwrap();
+    fs.chmod("/link", 0o400).unwrap();
+    let through_link = fs.stat("/dir/file").unwrap().mode;
+    fs.fchmod(fd, 0o100640).unwrap();
+    let missing = fs.chmod("/missing", 0o600);
+    fs.close(fd).unwrap();
+    let closed = fs.fchmod(fd, 0o600);
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/dir").unwrap().mode, 0o1700);
+    assert_eq!(through_link, 0o400);
+    assert_eq!(fs.stat("/dir/file").unwrap().mode, 0o640);
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(closed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_permission_checks_should_deny_access_without_bits() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 1000));
+
+    fs.mkdir("/locked").unwrap();
+    fs.mkdir("/open").unwrap();
+    let fd = fs.open("/open/secret", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    let fd = fs.open("/open/readonly", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+
+    fs.chmod("/locked", 0o555).unwrap();
+    fs.chmod("/open", 0o777).unwrap();
+    fs.chmod("/open/secret", 0o000).unwrap();
+    fs.chmod("/open/readonly", 0o444).unwrap();
+
+    /* Action */
+
+    let create_in_locked = user.open("/locked/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY);
+    let mkdir_in_locked = user.mkdir("/locked/dir");
+    let read_secret = user.open("/open/secret", OpenFlag::O_RDONLY);
+    let write_readonly = user.open("/open/readonly", OpenFlag::O_RDWR);
+    let read_readonly = user.open("/open/readonly", OpenFlag::O_RDONLY);
+    let rename_out_of_locked = user.rename("/open/readonly", "/locked/readonly");
+    let unlink_secret = user.unlink("/open/secret");
+    let root_create = fs.open("/locked/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY);
+
+    /* Assert */
+
+    assert!(permission_denied(create_in_locked));
+    assert!(permission_denied(mkdir_in_locked));
+    assert!(permission_denied(read_secret));
+    assert!(permission_denied(write_readonly));
+    assert!(read_readonly.is_ok());
+    assert!(permission_denied(rename_out_of_locked));
+    assert!(unlink_secret.is_ok());
+    assert!(root_create.is_ok());
+}
+
+#[test]
+fn test_permission_checks_should_deny_removing_subtrees_without_parent_bits() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 1000));
+
+    fs.mkdir("/locked").unwrap();
+    fs.mkdir("/locked/tree").unwrap();
+    fs.mkdir("/locked/tree/sub").unwrap();
+    fs.mkdir("/open").unwrap();
+    fs.mkdir("/open/tree").unwrap();
+    fs.chmod("/locked", 0o555).unwrap();
+    fs.chmod("/locked/tree", 0o777).unwrap();
+    fs.chmod("/open", 0o777).unwrap();
+    fs.chmod("/open/tree", 0o777).unwrap();
+
+    /* Action */
+
+    let remove = user.run(|fs| fs.remove_dir_all("/locked/tree"));
+    let remove_lazy = user.run(|fs| fs.remove_dir_all_lazy("/locked/tree").map(|_| ()));
+    let detach = user.run(|fs| fs.detach("/locked/tree").map(|_| ()));
+    let remove_open = user.run(|fs| fs.remove_dir_all("/open/tree"));
+
+    /* Assert */
+
+    assert!(permission_denied(remove));
+    assert!(permission_denied(remove_lazy));
+    assert!(permission_denied(detach));
+    assert!(fs.stat("/locked/tree/sub").is_ok());
+    assert!(remove_open.is_ok());
+    assert!(fs.remove_dir_all("/locked/tree").is_ok());
+}
+
+#[test]
+fn test_permission_bits_should_be_ignored_without_checks() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 1000));
+    fs.mkdir("/dir").unwrap();
+    fs.chmod("/dir", 0o000).unwrap();
+
+    /* Action */
+
+    let fd = user.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    assert!(fd.is_ok());
+}
+
+#[test]
+fn test_new_nodes_should_be_owned_by_creator() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+
+    /* Action */
+
+    let fd = user.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    user.close(fd).unwrap();
+    user.mkdir("/dir").unwrap();
+    fs.mkdir("/root_dir").unwrap();
+
+    /* Assert */
+
+    let file = fs.stat("/file").unwrap();
+    let dir = fs.stat("/dir").unwrap();
+    let root_dir = fs.stat("/root_dir").unwrap();
+
+    assert_eq!((file.uid, file.gid), (1000, 100));
+    assert_eq!((dir.uid, dir.gid), (1000, 100));
+    assert_eq!((root_dir.uid, root_dir.gid), (0, 0));
+}
+
+#[test]
+fn test_chown_should_change_owner_and_restrict_non_root_callers() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let owner = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+    let other = MemFSProcess::with_credentials(fs.clone(), Credentials::new(2000, 200));
+    fs.chmod("/", 0o777).unwrap();
+
+    let fd = owner.run(|fs| fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR)).unwrap();
+
+    /* Action */
+
+    let give_away = owner.run(|fs| fs.chown("/file", Some(2000), None));
+    let foreign_group = owner.run(|fs| fs.chown("/file", None, Some(200)));
+    let by_other = other.run(|fs| fs.chown("/file", None, Some(200)));
+    let chmod_by_other = other.run(|fs| fs.chmod("/file", 0o777));
+    let keep_own_group = owner.run(|fs| fs.fchown(fd, Some(1000), Some(100)));
+    fs.chown("/file", None, Some(200)).unwrap();
+    let stat_after_root_chown = fs.stat("/file").unwrap();
+    fs.fchown(fd, Some(2000), None).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Assert */
+
+    assert!(not_permitted(give_away));
+    assert!(not_permitted(foreign_group));
+    assert!(not_permitted(by_other));
+    assert!(not_permitted(chmod_by_other));
+    assert!(keep_own_group.is_ok());
+    assert_eq!((stat_after_root_chown.uid, stat_after_root_chown.gid), (1000, 200));
+    assert_eq!(fs.stat("/file").unwrap().uid, 2000);
+}
+
+#[test]
+fn test_permission_checks_should_use_bits_of_matching_class() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let owner = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+    let member = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1001, 100));
+    let stranger = MemFSProcess::with_credentials(fs.clone(), Credentials::new(2000, 200));
+
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    fs.chown("/file", Some(1000), Some(100)).unwrap();
+    fs.chmod("/file", 0o204).unwrap();
+
+    /* Action */
+
+    let owner_read = owner.open("/file", OpenFlag::O_RDONLY);
+    let owner_write = owner.open("/file", OpenFlag::O_WRONLY);
+    let member_read = member.open("/file", OpenFlag::O_RDONLY);
+    let stranger_read = stranger.open("/file", OpenFlag::O_RDONLY);
+
+    /* Assert */
+
+    assert!(permission_denied(owner_read));
+    assert!(owner_write.is_ok());
+    assert!(permission_denied(member_read));
+    assert!(stranger_read.is_ok());
+}
+
+#[test]
+fn test_access_should_report_like_open() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    fs.chmod("/file", 0o604).unwrap();
+    fs.symlink("/file", "/link").unwrap();
+
+    /* Action */
+
+    let exists = user.access("/link", F_OK);
+    let readable = user.access("/file", R_OK);
+    let writable = user.access("/file", R_OK | W_OK);
+    let writable_by_root = fs.access("/file", R_OK | W_OK | X_OK);
+    let missing = user.access("/missing", F_OK);
+    let invalid = user.access("/file", 0o10);
+    let denied_open = user.open("/file", OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    assert!(exists.is_ok());
+    assert!(readable.is_ok());
+    assert!(permission_denied(writable));
+    assert!(permission_denied(denied_open));
+    assert!(writable_by_root.is_ok());
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(invalid.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
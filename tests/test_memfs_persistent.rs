@@ -0,0 +1,195 @@
+use std::{fs, path::PathBuf, time::SystemTime};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag, PAGE_SIZE, generate_random_vector};
+
+fn backing_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("memfs_persistent_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir.join("memfs.img")
+}
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let mut buffer = vec![0u8; fs.stat(path).unwrap().size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_reopened_file_system_should_keep_tree_and_contents() {
+    /* Arrange */
+
+    let path = backing_file("reopen");
+    let big = generate_random_vector(3 * PAGE_SIZE + 100);
+    let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+
+    let fs = MemFS::open_persistent_with_capacity(&path, 64).unwrap();
+    fs.create_dir_all("/a/b").unwrap();
+    write_file(&fs, "/a/small", b"inline contents");
+    write_file(&fs, "/a/b/big", &big);
+    fs.link("/a/b/big", "/big-link").unwrap();
+    fs.symlink("/a/small", "/a/b/to-small").unwrap();
+    fs.chmod("/a/small", 0o640).unwrap();
+    fs.utimens("/a/b", None, Some(mtime)).unwrap();
+
+    /* Action */
+
+    drop(fs);
+    let fs = MemFS::open_persistent_with_capacity(&path, 64).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_file(&fs, "/a/small"), b"inline contents");
+    assert_eq!(read_file(&fs, "/a/b/big"), big);
+    assert_eq!(fs.stat("/big-link").unwrap().ino, fs.stat("/a/b/big").unwrap().ino);
+    assert_eq!(fs.stat("/a/b/big").unwrap().nlink, 2);
+    assert_eq!(fs.readlink("/a/b/to-small").unwrap(), "/a/small");
+    assert_eq!(fs.stat("/a/small").unwrap().mode, 0o640);
+    assert_eq!(fs.stat("/a/b").unwrap().mtime, mtime);
+    assert_eq!(fs.stat("/a/b").unwrap().file_type, FileType::Directory);
+    assert_eq!(fs.statfs().blocks_used, 4);
+
+    drop(fs);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_sync_should_persist_tree_of_file_system_never_dropped() {
+    /* Arrange */
+
+    let path = backing_file("sync");
+    let data = generate_random_vector(PAGE_SIZE + 1);
+    let fs = MemFS::open_persistent_with_capacity(&path, 16).unwrap();
+    write_file(&fs, "/synced", &data);
+
+    /* Action */
+
+    fs.sync().unwrap();
+    write_file(&fs, "/unsynced", b"lost");
+    // As if the process crashed.
+    std::mem::forget(fs);
+
+    let fs = MemFS::open_persistent_with_capacity(&path, 16).unwrap();
+    let entries = fs.readdir("/").unwrap();
+
+    /* Assert */
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "synced");
+    assert_eq!(read_file(&fs, "/synced"), data);
+
+    drop(fs);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_crash_after_writing_tree_should_keep_previous_tree() {
+    /* Arrange */
+
+    let path = backing_file("torn");
+    let fs = MemFS::open_persistent_with_capacity(&path, 16).unwrap();
+    write_file(&fs, "/first", b"synced first");
+    fs.sync().unwrap();
+    let before = fs::read(&path).unwrap();
+
+    write_file(&fs, "/second", b"synced second");
+    fs.sync().unwrap();
+    std::mem::forget(fs);
+
+    /* Action */
+
+    // As if the process crashed once the second tree was written, before the header pointed to it.
+    let mut torn = fs::read(&path).unwrap();
+    torn[..PAGE_SIZE].copy_from_slice(&before[..PAGE_SIZE]);
+    fs::write(&path, &torn).unwrap();
+    let fs = MemFS::open_persistent_with_capacity(&path, 16).unwrap();
+    let entries = fs.readdir("/").unwrap();
+
+    /* Assert */
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(read_file(&fs, "/first"), b"synced first");
+
+    drop(fs);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_repeated_syncs_should_not_grow_backing_file() {
+    /* Arrange */
+
+    let path = backing_file("regrow");
+    let fs = MemFS::open_persistent_with_capacity(&path, 16).unwrap();
+    write_file(&fs, "/file", b"contents");
+    fs.sync().unwrap();
+    fs.sync().unwrap();
+    let len = fs::metadata(&path).unwrap().len();
+
+    /* Action */
+
+    for _ in 0..10 {
+        fs.sync().unwrap();
+    }
+
+    /* Assert */
+
+    assert!(fs::metadata(&path).unwrap().len() <= len);
+
+    drop(fs);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_removed_files_should_free_pages_of_backing_file() {
+    /* Arrange */
+
+    let path = backing_file("free");
+    let fs = MemFS::open_persistent_with_capacity(&path, 4).unwrap();
+    write_file(&fs, "/first", &generate_random_vector(4 * PAGE_SIZE));
+    fs.unlink("/first").unwrap();
+    drop(fs);
+
+    /* Action */
+
+    let fs = MemFS::open_persistent_with_capacity(&path, 4).unwrap();
+    let data = generate_random_vector(4 * PAGE_SIZE);
+    write_file(&fs, "/second", &data);
+
+    /* Assert */
+
+    assert!(fs.readdir("/").unwrap().iter().all(|entry| entry.name == "second"));
+    assert_eq!(read_file(&fs, "/second"), data);
+    assert_eq!(fs.statfs().blocks, 4);
+
+    drop(fs);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_open_persistent_should_reject_other_files() {
+    /* Arrange */
+
+    let path = backing_file("invalid");
+    fs::write(&path, b"not a backing file of memfs, but long enough for a header").unwrap();
+
+    /* Action */
+
+    let opened = MemFS::open_persistent(&path);
+
+    /* Assert */
+
+    assert!(opened.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
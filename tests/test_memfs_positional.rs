@@ -0,0 +1,112 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FILE_MAX_SIZE, MemFSErrType, OpenFlag, SeekFlag};
+
+#[test]
+fn test_pread_and_pwrite_should_not_move_file_offset() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let mut buffer = vec![0u8; 3];
+
+    fs.write(fd, b"abcdef").unwrap();
+    fs.lseek(fd, 1, SeekFlag::SEEK_SET).unwrap();
+
+    /* Action */
+
+    let written = fs.pwrite(fd, b"XY", 4).unwrap();
+    let read = fs.pread(fd, &mut buffer, 3).unwrap();
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+
+    /* Assert */
+
+    assert_eq!(written, 2);
+    assert_eq!(read, 3);
+    assert_eq!(buffer, b"dXY");
+    assert_eq!(offset, 1);
+}
+
+#[test]
+fn test_pwrite_past_end_should_zero_fill_gap() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let mut buffer = vec![0u8; 8];
+
+    fs.write(fd, b"ab").unwrap();
+
+    /* Action */
+
+    fs.pwrite(fd, b"z", 5).unwrap();
+    let read = fs.pread(fd, &mut buffer, 0).unwrap();
+    let past_end = fs.pread(fd, &mut buffer, 100).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, 6);
+    assert_eq!(&buffer[..6], b"ab\0\0\0z");
+    assert_eq!(past_end, 0);
+}
+
+#[test]
+fn test_pread_and_pwrite_should_check_descriptor_and_bounds() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    let read_fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let mut buffer = vec![0u8; 4];
+
+    /* Action */
+
+    let read_result = fs.pread(fd, &mut buffer, 0);
+    let write_result = fs.pwrite(read_fd, b"data", 0);
+    let large_result = fs.pwrite(fd, b"data", FILE_MAX_SIZE - 2);
+    let closed_result = fs.pread(100, &mut buffer, 0);
+
+    /* Assert */
+
+    assert!(read_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(write_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(large_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert!(closed_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_pwrite_should_let_threads_share_descriptor() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let threads = 8;
+    let chunk = 64;
+
+    /* Action */
+
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            let fs = fs.clone();
+            thread::spawn(move || {
+                let data = vec![b'a' + i as u8; chunk];
+                fs.pwrite(fd, &data, i * chunk).unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut buffer = vec![0u8; threads * chunk];
+    let read = fs.pread(fd, &mut buffer, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, threads * chunk);
+    for (i, range) in buffer.chunks(chunk).enumerate() {
+        assert!(range.iter().all(|b| *b == b'a' + i as u8));
+    }
+}
@@ -0,0 +1,130 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::process::{Credentials, DEFAULT_UMASK, MemFSProcess};
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+#[test]
+fn test_processes_should_keep_own_working_directories() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let dirs = ["/alpha", "/beta", "/gamma", "/delta"];
+
+    for dir in dirs {
+        fs.mkdir(dir).unwrap();
+    }
+
+    let processes: Vec<MemFSProcess> = dirs.iter().map(|_| MemFSProcess::new(fs.clone())).collect();
+
+    /* Action */
+
+    let handles: Vec<_> = processes
+        .iter()
+        .zip(dirs)
+        .map(|(process, dir)| {
+            let process = process.clone();
+
+            thread::spawn(move || {
+                process.chdir(dir).unwrap();
+
+                for i in 0..50 {
+                    let fd = process
+                        .open(&format!("file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                        .unwrap();
+                    process.close(fd).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    /* Assert */
+
+    for (process, dir) in processes.iter().zip(dirs) {
+        assert_eq!(process.getcwd().unwrap(), dir);
+        assert_eq!(process.readdir(".").unwrap().len(), 50);
+    }
+
+    assert_eq!(fs.getcwd().unwrap(), "/");
+    assert!(fs.stat("file_0").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_fork_should_copy_state_and_run_should_scope_it() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.mkdir("/home").unwrap();
+    fs.mkdir("/home/user").unwrap();
+
+    let parent = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+    parent.chdir("/home").unwrap();
+    let previous_umask = parent.umask(0o1077);
+    let fd = parent.open("/home/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let child = parent.fork();
+    child.chdir("user").unwrap();
+    let created = child.run(|fs| fs.mkdir("inner"));
+    let child_umask = child.umask(0);
+
+    /* Assert */
+
+    assert_eq!(previous_umask, DEFAULT_UMASK);
+    assert_eq!(child_umask, 0o077);
+    assert_eq!(child.credentials(), Credentials::new(1000, 100));
+    assert_eq!(child.raw_fd(fd).unwrap(), parent.raw_fd(fd).unwrap());
+    assert_eq!(MemFSProcess::new(fs.clone()).credentials(), Credentials::ROOT);
+    assert!(created.is_ok());
+    assert!(fs.stat("/home/user/inner").is_ok());
+    assert_eq!(parent.getcwd().unwrap(), "/home");
+    assert_eq!(child.getcwd().unwrap(), "/home/user");
+    assert_eq!(fs.getcwd().unwrap(), "/");
+}
+
+#[test]
+fn test_pass_fd_should_share_offset_and_flags_between_processes() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let sender = MemFSProcess::new(fs.clone());
+    let receiver = MemFSProcess::new(fs.clone());
+    let elsewhere = MemFSProcess::new(Arc::new(MemFS::new()));
+    let own_fd = receiver.open("/other", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let fd = sender.open("/log", OpenFlag::O_CREAT | OpenFlag::O_WRONLY | OpenFlag::O_APPEND).unwrap();
+    let raw_fd = sender.raw_fd(fd).unwrap();
+    sender.write(fd, b"first ").unwrap();
+
+    /* Action */
+
+    let passed = sender.pass_fd(fd, &receiver).unwrap();
+    receiver.write(passed, b"second ").unwrap();
+    let sender_offset = sender.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+    sender.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    receiver.write(passed, b"third").unwrap();
+    let read_through_passed = receiver.read(passed, &mut [0u8; 8]);
+    let duplicate = sender.dup(fd).unwrap();
+    sender.close(fd).unwrap();
+    let open_after_sender_closed = fs.lseek(raw_fd, 0, SeekFlag::SEEK_CUR).is_ok();
+    let duplicate_offset = sender.lseek(duplicate, 0, SeekFlag::SEEK_CUR).unwrap();
+    sender.close(duplicate).unwrap();
+    receiver.close(passed).unwrap();
+
+    /* Assert */
+
+    assert_eq!((own_fd, fd, passed, duplicate), (0, 0, 1, 1));
+    assert_eq!(sender_offset, 13);
+    assert!(read_through_passed.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(open_after_sender_closed);
+    assert_eq!(duplicate_offset, 18);
+    assert_eq!(fs.stat("/log").unwrap().size, 18);
+    assert!(fs.lseek(raw_fd, 0, SeekFlag::SEEK_CUR).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(receiver.close(passed).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(sender.pass_fd(fd, &receiver).is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(receiver.pass_fd(own_fd, &elsewhere).is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+}
@@ -0,0 +1,86 @@
+use memfs::memfs::MemFS;
+use memfs::quota::NODE_METADATA_BYTES;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn no_space_left<T>(result: memfs::utils::Result<T>) -> bool {
+    result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC))
+}
+
+#[test]
+fn test_usage_should_count_file_contents_and_metadata() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let initial = fs.usage();
+
+    /* Action */
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &[1u8; 100]).unwrap();
+    fs.pwrite(fd, &[2u8; 10], 40).unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+    let populated = fs.usage();
+
+    fs.ftruncate(fd, 30).unwrap();
+    fs.unlink("/dir/file").unwrap();
+    let unlinked_but_open = fs.usage();
+
+    fs.close(fd).unwrap();
+    fs.unlink("/link").unwrap();
+    fs.rmdir("/dir").unwrap();
+    let emptied = fs.usage();
+
+    /* Assert */
+
+    assert_eq!(initial.total_bytes(), 0);
+    assert_eq!(initial.quota, None);
+    assert_eq!(populated.data_bytes, 100);
+    assert_eq!(populated.metadata_bytes, 3 * NODE_METADATA_BYTES + "/dir/file".len() as u64);
+    assert_eq!(unlinked_but_open.data_bytes, 30);
+    assert_eq!(unlinked_but_open.metadata_bytes, populated.metadata_bytes);
+
+    // The lock-free backend drops removed nodes lazily, so their bytes are released a little later.
+    if !cfg!(feature = "lock-free") {
+        assert_eq!(emptied.total_bytes(), 0);
+    }
+}
+
+#[test]
+fn test_quota_should_reject_growth_past_budget_with_enospc() {
+    /* Arrange */
+
+    let quota = 2 * NODE_METADATA_BYTES + 100;
+    let fs = MemFS::builder().quota(quota).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &[1u8; 60]).unwrap();
+    fs.mkdir("/dir").unwrap();
+
+    /* Action */
+
+    let too_large_write = fs.write(fd, &[2u8; 41]);
+    let size_after_rejected_write = fs.fstat(fd).unwrap().size;
+    let fitting_write = fs.write(fd, &[2u8; 40]);
+    let too_large_truncate = fs.ftruncate(fd, 101);
+    let create_past_quota = fs.open("/other", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+    let reopen_existing = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+    let mkdir_past_quota = fs.mkdir("/dir/sub");
+    let symlink_past_quota = fs.symlink("/file", "/link");
+
+    fs.ftruncate(fd, 0).unwrap();
+    let write_after_truncate = fs.pwrite(fd, &[3u8; 100], 0);
+
+    /* Assert */
+
+    assert!(no_space_left(too_large_write));
+    assert_eq!(size_after_rejected_write, 60);
+    assert!(fitting_write.is_ok());
+    assert!(no_space_left(too_large_truncate));
+    assert!(no_space_left(create_past_quota));
+    assert!(reopen_existing.is_ok());
+    assert!(no_space_left(mkdir_past_quota));
+    assert!(no_space_left(symlink_past_quota));
+    assert!(write_after_truncate.is_ok());
+    assert_eq!(fs.usage().total_bytes(), quota);
+    assert_eq!(fs.usage().quota, Some(quota));
+}
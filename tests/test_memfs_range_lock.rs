@@ -0,0 +1,135 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use memfs::memfs::MemFS;
+use memfs::process::{MAIN_PID, MemFSProcess};
+use memfs::range_lock::{RangeLock, RangeLockConflict, RangeLockKind};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn lock(kind: RangeLockKind, start: u64, len: u64) -> RangeLock {
+    RangeLock::new(kind, start, len)
+}
+
+#[test]
+fn test_setlk_should_detect_conflicts_between_processes() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let first = MemFSProcess::new(fs.clone());
+    let second = MemFSProcess::new(fs.clone());
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    first.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 0, 10))).unwrap();
+    let overlapping = second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 5, 10)));
+    let adjacent = second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Read, 10, 0)));
+    let conflict = second.run(|fs| fs.getlk(fd, lock(RangeLockKind::Read, 0, 0))).unwrap();
+
+    first.run(|fs| fs.setlk(fd, lock(RangeLockKind::Unlock, 2, 2))).unwrap();
+    let in_hole = second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 2, 2)));
+    let past_hole = second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 4, 1)));
+    let across_second = first.run(|fs| fs.setlk(fd, lock(RangeLockKind::Read, 0, 10)));
+
+    /* Assert */
+
+    assert!(overlapping.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(adjacent.is_ok());
+    assert_eq!(
+        conflict,
+        Some(RangeLockConflict {
+            lock: lock(RangeLockKind::Write, 0, 10),
+            pid: first.pid(),
+        })
+    );
+    assert!(in_hole.is_ok());
+    assert!(past_hole.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert!(across_second.is_err_and(|e| matches!(e.err_type, MemFSErrType::EAGAIN)));
+    assert_ne!(first.pid(), MAIN_PID);
+}
+
+#[test]
+fn test_setlkw_should_wait_until_holder_closes_a_descriptor_of_the_file() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let holder = MemFSProcess::new(fs.clone());
+    let waiter = MemFSProcess::new(fs.clone());
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let other_fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    holder.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 0, 0))).unwrap();
+
+    /* Action */
+
+    let handle = thread::spawn(move || waiter.run(|fs| fs.setlkw(fd, lock(RangeLockKind::Write, 100, 1))));
+
+    thread::sleep(Duration::from_millis(50));
+    let finished_while_held = handle.is_finished();
+    holder.run(|fs| fs.close(other_fd)).unwrap();
+
+    /* Assert */
+
+    assert!(!finished_while_held);
+    assert!(handle.join().unwrap().is_ok());
+}
+
+#[test]
+fn test_setlkw_should_fail_with_edeadlk_on_lock_cycle() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let first = MemFSProcess::new(fs.clone());
+    let second = MemFSProcess::new(fs.clone());
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    first.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 0, 1))).unwrap();
+    second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Write, 1, 1))).unwrap();
+
+    /* Action */
+
+    let blocked = {
+        let first = first.clone();
+
+        thread::spawn(move || first.run(|fs| fs.setlkw(fd, lock(RangeLockKind::Write, 1, 1))))
+    };
+
+    // Lets the first process start waiting for the second.
+    let mut deadlock = Ok(());
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(10));
+        deadlock = second.run(|fs| fs.setlkw(fd, lock(RangeLockKind::Write, 0, 1)));
+
+        if deadlock.is_err() {
+            break;
+        }
+
+        second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Unlock, 0, 1))).unwrap();
+    }
+
+    second.run(|fs| fs.setlk(fd, lock(RangeLockKind::Unlock, 0, 0))).unwrap();
+
+    /* Assert */
+
+    assert!(deadlock.is_err_and(|e| matches!(e.err_type, MemFSErrType::EDEADLK)));
+    assert!(blocked.join().unwrap().is_ok());
+}
+
+#[test]
+fn test_setlk_should_check_descriptor_mode_and_range() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let read_only = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDONLY).unwrap();
+    let write_only = fs.open("/file", OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let write_on_read_only = fs.setlk(read_only, lock(RangeLockKind::Write, 0, 1));
+    let read_on_write_only = fs.setlk(write_only, lock(RangeLockKind::Read, 0, 1));
+    let overflowing = fs.setlk(read_only, lock(RangeLockKind::Read, u64::MAX, 2));
+
+    /* Assert */
+
+    assert!(write_on_read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(read_on_write_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(overflowing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(fs.setlk(read_only, lock(RangeLockKind::Read, 0, 0)).is_ok());
+}
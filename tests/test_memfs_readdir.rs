@@ -0,0 +1,120 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::readdir::ReadDirEntry;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+#[test]
+fn test_readdir_should_return_names_and_types_sorted_by_name() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let entries = fs.readdir("/dir").unwrap();
+    let empty = fs.readdir("/dir/sub").unwrap();
+
+    /* Assert */
+
+    assert_eq!(
+        entries,
+        vec![
+            ReadDirEntry {
+                name: "file".to_string(),
+                file_type: FileType::File,
+            },
+            ReadDirEntry {
+                name: "sub".to_string(),
+                file_type: FileType::Directory,
+            },
+        ]
+    );
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_readdir_should_fail_on_file_and_missing_path() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let file_result = fs.readdir("/file");
+    let missing_result = fs.readdir_iter("/missing");
+
+    /* Assert */
+
+    assert!(file_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(missing_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_readdir_iter_should_not_block_changes_of_directory() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/b").unwrap();
+
+    /* Action */
+
+    let mut names = Vec::new();
+
+    for entry in fs.readdir_iter("/").unwrap() {
+        fs.rmdir(&format!("/{}", entry.name)).unwrap();
+        fs.mkdir(&format!("/{}{}", entry.name, entry.name)).unwrap();
+        names.push(entry.name);
+    }
+
+    /* Assert */
+
+    assert_eq!(names, vec!["a", "b"]);
+    assert_eq!(fs.readdir_iter("/").unwrap().len(), 2);
+}
+
+#[test]
+fn test_readdir_should_list_consistent_entries_under_concurrent_inserts() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    fs.mkdir("/dir").unwrap();
+
+    /* Action */
+
+    let writer = {
+        let fs = fs.clone();
+        thread::spawn(move || {
+            for i in 0..200 {
+                fs.mkdir(&format!("/dir/{:03}", i)).unwrap();
+            }
+        })
+    };
+
+    let mut previous = 0;
+    let mut grew_monotonically = true;
+
+    while !writer.is_finished() {
+        let entries = fs.readdir("/dir").unwrap();
+        grew_monotonically &= entries.len() >= previous;
+        grew_monotonically &= entries.iter().all(|e| e.file_type == FileType::Directory);
+        previous = entries.len();
+    }
+
+    writer.join().unwrap();
+
+    /* Assert */
+
+    assert!(grew_monotonically);
+    assert_eq!(fs.readdir("/dir").unwrap().len(), 200);
+}
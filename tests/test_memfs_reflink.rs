@@ -0,0 +1,125 @@
+use memfs::memfs::MemFS;
+use memfs::trace::{ReplayMode, Trace, TraceRecorder};
+use memfs::utils::{INLINE_FILE_SIZE, MemFSErrType, OpenFlag};
+
+fn read_all(fs: &MemFS, path: &str) -> Vec<u8> {
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    let mut contents = vec![0u8; fs.stat(path).unwrap().size];
+    fs.pread(fd, &mut contents, 0).unwrap();
+    fs.close(fd).unwrap();
+
+    contents
+}
+
+#[test]
+fn test_reflink_should_keep_clone_and_source_apart_once_written() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let source = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(source, b"original").unwrap();
+
+    /* Action */
+
+    fs.reflink("/source", "/clone").unwrap();
+    let cloned = read_all(&fs, "/clone");
+
+    fs.pwrite(source, b"O", 0).unwrap();
+    let after_source_write = read_all(&fs, "/clone");
+
+    let clone = fs.open("/clone", OpenFlag::O_RDWR | OpenFlag::O_APPEND).unwrap();
+    fs.write(clone, b" clone").unwrap();
+
+    /* Assert */
+
+    assert_eq!(cloned, b"original");
+    assert_eq!(after_source_write, b"original");
+    assert_eq!(read_all(&fs, "/clone"), b"original clone");
+    assert_eq!(read_all(&fs, "/source"), b"Original");
+    assert_ne!(fs.stat("/clone").unwrap().ino, fs.stat("/source").unwrap().ino);
+}
+
+#[test]
+fn test_reflink_should_take_a_block_only_once_clone_is_written() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let shared = vec![b's'; 2 * INLINE_FILE_SIZE];
+    let source = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(source, &shared).unwrap();
+    let before = fs.statfs().blocks_free;
+
+    /* Action */
+
+    for i in 0..4 {
+        fs.reflink("/source", &format!("/clone_{}", i)).unwrap();
+    }
+
+    let after_clones = fs.statfs().blocks_free;
+    fs.truncate("/clone_0", 2).unwrap();
+    let after_truncate = fs.statfs().blocks_free;
+
+    /* Assert */
+
+    assert_eq!(after_clones, before);
+    assert_eq!(after_truncate, before - 1);
+    assert_eq!(read_all(&fs, "/clone_0"), b"ss");
+    assert_eq!(read_all(&fs, "/clone_3"), shared);
+    assert_eq!(fs.usage().data_bytes, (shared.len() * 4 + 2) as u64);
+}
+
+#[test]
+fn test_reflink_should_fail_on_directories_and_existing_targets() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let of_directory = fs.reflink("/dir", "/copy");
+    let of_missing = fs.reflink("/missing", "/copy");
+    let onto_existing = fs.reflink("/file", "/dir");
+    let into_missing_directory = fs.reflink("/file", "/missing/copy");
+
+    /* Assert */
+
+    assert!(of_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(of_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(onto_existing.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(into_missing_directory.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_reflink_should_be_traced_and_journaled() {
+    /* Arrange */
+
+    let mut fs = MemFS::builder().journal(true).build();
+    let recorder = TraceRecorder::new();
+    fs.set_trace_recorder(recorder.clone());
+
+    let fd = fs.open("/source", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, b"abc").unwrap();
+    fs.reflink("/source", "/clone").unwrap();
+    fs.truncate("/clone", 1).unwrap();
+    fs.write(fd, b"def").unwrap();
+
+    let trace = Trace::from_text(&recorder.trace().to_text()).unwrap();
+    let (traced, journaled) = (MemFS::new(), MemFS::new());
+
+    /* Action */
+
+    let report = trace.replay(&traced, ReplayMode::Sequential);
+    fs.journal().unwrap().replay(&journaled).unwrap();
+
+    /* Assert */
+
+    assert!(report.is_faithful());
+
+    for replayed in [&traced, &journaled] {
+        assert_eq!(read_all(replayed, "/clone"), b"a");
+        assert_eq!(read_all(replayed, "/source"), b"abcdef");
+    }
+}
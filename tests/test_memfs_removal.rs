@@ -0,0 +1,141 @@
+use memfs::memfs::MemFS;
+use memfs::removal::RemovalReport;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+fn create_tree(fs: &MemFS, root: &str, width: usize) {
+    fs.mkdir(root).unwrap();
+
+    for i in 0..width {
+        let dir = format!("{}/dir_{}", root, i);
+        fs.mkdir(&dir).unwrap();
+
+        for j in 0..width {
+            let fd = fs
+                .open(&format!("{}/file_{}", dir, j), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                .unwrap();
+            fs.close(fd).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_remove_dir_all_should_remove_nonempty_directory() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    create_tree(&fs, "/tree", 4);
+
+    /* Action */
+
+    let result = fs.remove_dir_all("/tree");
+
+    /* Assert */
+
+    assert!(result.is_ok());
+    assert!(fs.stat("/tree").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.mkdir("/tree").is_ok());
+}
+
+#[test]
+fn test_remove_dir_all_should_fail_on_file_and_root() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let file_result = fs.remove_dir_all("/file");
+    let root_result = fs.remove_dir_all("/");
+    let missing_result = fs.remove_dir_all_lazy("/missing");
+
+    /* Assert */
+
+    assert!(file_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(root_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBUSY)));
+    assert!(missing_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.stat("/file").is_ok());
+}
+
+#[test]
+fn test_remove_dir_all_lazy_should_detach_immediately_and_report_reclaimed_nodes() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    create_tree(&fs, "/tree", 8);
+
+    /* Action */
+
+    let handle = fs.remove_dir_all_lazy("/tree").unwrap();
+    let stat_after_detach = fs.stat("/tree/dir_0/file_0");
+    let recreate_result = fs.mkdir("/tree");
+    let report = handle.wait();
+
+    /* Assert */
+
+    assert!(stat_after_detach.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(recreate_result.is_ok());
+    assert_eq!(
+        report,
+        RemovalReport {
+            directories: 9,
+            files: 64,
+        }
+    );
+}
+
+#[test]
+fn test_remove_dir_all_lazy_should_keep_open_files_readable() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = vec![b'k'; 32];
+    let mut buffer = vec![0u8; 32];
+
+    fs.mkdir("/tree").unwrap();
+    fs.mkdir("/tree/inner").unwrap();
+    let fd = fs
+        .open("/tree/inner/kept.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+
+    /* Action */
+
+    fs.remove_dir_all_lazy("/tree").unwrap().wait();
+
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, 32);
+    assert_eq!(buffer, data);
+}
+
+#[test]
+fn test_remove_dir_all_lazy_should_reclaim_deep_tree() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let depth = 2000;
+
+    fs.mkdir("/deep").unwrap();
+    fs.chdir("/deep").unwrap();
+
+    for _ in 0..depth {
+        fs.mkdir("d").unwrap();
+        fs.chdir("d").unwrap();
+    }
+
+    fs.chdir("/").unwrap();
+
+    /* Action */
+
+    let report = fs.remove_dir_all_lazy("/deep").unwrap().wait();
+
+    /* Assert */
+
+    assert_eq!(report.directories, depth + 1);
+    assert_eq!(report.files, 0);
+}
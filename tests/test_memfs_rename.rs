@@ -0,0 +1,377 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{AT_FDCWD, FileType, MemFSErrType, OpenFlag, RenameFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_rename_should_move_file_across_directories() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/from").unwrap();
+    fs.mkdir("/to").unwrap();
+    write_file(&fs, "/from/file", b"contents");
+
+    /* Action */
+
+    let result = fs.rename("/from/file", "/to/moved");
+
+    /* Assert */
+
+    assert!(result.is_ok());
+    assert!(fs.stat("/from/file").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read_file(&fs, "/to/moved"), b"contents");
+}
+
+#[test]
+fn test_rename_should_replace_existing_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/new", b"new");
+    write_file(&fs, "/old", b"old");
+
+    /* Action */
+
+    fs.rename("/new", "/old").unwrap();
+
+    /* Assert */
+
+    assert!(fs.stat("/new").is_err());
+    assert_eq!(read_file(&fs, "/old"), b"new");
+}
+
+#[test]
+fn test_rename_should_move_directory_with_its_contents() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/a/dir").unwrap();
+    fs.mkdir("/b").unwrap();
+    fs.mkdir("/b/empty").unwrap();
+    write_file(&fs, "/a/dir/file", b"file");
+    write_file(&fs, "/b/marker", b"b");
+
+    /* Action */
+
+    fs.rename("/a/dir", "/b/empty").unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_file(&fs, "/b/empty/file"), b"file");
+    assert_eq!(read_file(&fs, "/b/empty/../marker"), b"b");
+    assert!(fs.stat("/a/dir").is_err());
+}
+
+#[test]
+fn test_rename_should_fail_on_mismatched_or_non_empty_target() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/full").unwrap();
+    write_file(&fs, "/full/file", b"f");
+    write_file(&fs, "/file", b"f");
+
+    /* Action */
+
+    let file_over_dir = fs.rename("/file", "/dir");
+    let dir_over_file = fs.rename("/dir", "/file");
+    let dir_over_full = fs.rename("/dir", "/full");
+    let missing = fs.rename("/missing", "/other");
+
+    /* Assert */
+
+    assert!(file_over_dir.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(dir_over_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(dir_over_full.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTEMPTY)));
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(fs.stat("/dir").unwrap().file_type, FileType::Directory);
+    assert_eq!(fs.stat("/file").unwrap().file_type, FileType::File);
+}
+
+#[test]
+fn test_rename_with_trailing_slash_should_only_move_directories() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/file", b"f");
+    write_file(&fs, "/other", b"o");
+
+    /* Action */
+
+    let file_with_slash = fs.rename("/file/", "/moved");
+    let onto_slash = fs.rename("/file", "/moved/");
+    let exchanged_with_file = fs.renameat2(AT_FDCWD, "/dir", AT_FDCWD, "/other/", RenameFlag::RENAME_EXCHANGE);
+    let dir_with_slashes = fs.rename("/dir/", "/renamed/");
+
+    /* Assert */
+
+    assert!(file_with_slash.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(onto_slash.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(exchanged_with_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(dir_with_slashes.is_ok());
+    assert_eq!(fs.stat("/renamed").unwrap().file_type, FileType::Directory);
+    assert_eq!(read_file(&fs, "/file"), b"f");
+    assert!(fs.stat("/moved").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_rename_should_fail_on_move_into_own_subtree() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+
+    /* Action */
+
+    let into_child = fs.rename("/dir", "/dir/sub/dir");
+    let onto_root = fs.rename("/dir", "/");
+
+    /* Assert */
+
+    assert!(into_child.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(onto_root.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBUSY)));
+    assert!(fs.stat("/dir/sub").is_ok());
+}
+
+#[test]
+fn test_rename_should_not_lose_directories_moved_concurrently() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    fs.mkdir("/x").unwrap();
+    fs.mkdir("/y").unwrap();
+
+    /* Action */
+
+    let handles: Vec<_> = [("/x", "/y/x"), ("/y", "/x/y")]
+        .into_iter()
+        .map(|(from, to)| {
+            let fs = fs.clone();
+            thread::spawn(move || fs.rename(from, to))
+        })
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    /* Assert */
+
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(fs.stat("/x/y").is_ok() || fs.stat("/y/x").is_ok());
+}
+
+#[test]
+fn test_rename_racing_unlink_should_never_both_succeed() {
+    let fs = Arc::new(MemFS::new());
+
+    for _ in 0..1000 {
+        /* Arrange */
+
+        let _ = fs.unlink("/b");
+        write_file(&fs, "/a", b"contents");
+
+        /* Action */
+
+        let rename = {
+            let fs = fs.clone();
+            thread::spawn(move || fs.rename("/a", "/b"))
+        };
+        let unlink = {
+            let fs = fs.clone();
+            thread::spawn(move || fs.unlink("/a"))
+        };
+        let renamed = rename.join().unwrap();
+        let unlinked = unlink.join().unwrap();
+
+        /* Assert */
+
+        assert!(renamed.is_ok() != unlinked.is_ok());
+        assert!(fs.stat("/a").is_err());
+
+        match renamed {
+            Ok(()) => assert_eq!(fs.stat("/b").unwrap().nlink, 1),
+            Err(e) => {
+                assert!(matches!(e.err_type, MemFSErrType::ENOENT));
+                assert!(fs.stat("/b").is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_renameat2_noreplace_should_not_replace_existing_entries() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    write_file(&fs, "/source", b"source");
+    write_file(&fs, "/taken", b"taken");
+    fs.link("/source", "/alias").unwrap();
+    let noreplace = RenameFlag::RENAME_NOREPLACE;
+
+    /* Action */
+
+    let onto_file = fs.renameat2(AT_FDCWD, "/source", AT_FDCWD, "/taken", noreplace);
+    let onto_link = fs.renameat2(AT_FDCWD, "/source", AT_FDCWD, "/alias", noreplace);
+    let onto_itself = fs.renameat2(AT_FDCWD, "/source", AT_FDCWD, "/source", noreplace);
+    fs.renameat2(AT_FDCWD, "/source", AT_FDCWD, "/moved", noreplace).unwrap();
+
+    /* Assert */
+
+    assert!(onto_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(onto_link.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(onto_itself.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert_eq!(read_file(&fs, "/taken"), b"taken");
+    assert_eq!(read_file(&fs, "/moved"), b"source");
+    assert_eq!(fs.stat("/alias").unwrap().nlink, 2);
+    assert!(fs.stat("/source").is_err());
+}
+
+#[test]
+fn test_renameat2_exchange_should_swap_entries_of_any_type() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/b").unwrap();
+    write_file(&fs, "/a/file", b"file");
+    fs.mkdir("/b/dir").unwrap();
+    write_file(&fs, "/b/dir/inner", b"inner");
+    let file_ino = fs.stat("/a/file").unwrap().ino;
+    let a = fs.open("/a", OpenFlag::O_DIRECTORY | OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    fs.renameat2(a, "file", AT_FDCWD, "/b/dir", RenameFlag::RENAME_EXCHANGE).unwrap();
+    fs.mkdir("/a/file/sub").unwrap();
+    fs.rmdir("/a/file/sub").unwrap();
+
+    let replayed = MemFS::new();
+    fs.journal().unwrap().replay(&replayed).unwrap();
+
+    /* Assert */
+
+    for fs in [&fs, &replayed] {
+        assert_eq!(read_file(fs, "/b/dir"), b"file");
+        assert_eq!(read_file(fs, "/a/file/inner"), b"inner");
+        assert_eq!(fs.stat("/b/dir").unwrap().nlink, 1);
+        assert!(matches!(fs.stat("/a/file").unwrap().file_type, FileType::Directory));
+    }
+
+    assert_eq!(fs.stat("/b/dir").unwrap().ino, file_ino);
+}
+
+#[test]
+fn test_renameat2_exchange_should_check_entries_and_flags() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+    write_file(&fs, "/file", b"file");
+    let exchange = RenameFlag::RENAME_EXCHANGE;
+
+    /* Action */
+
+    let with_missing = fs.renameat2(AT_FDCWD, "/file", AT_FDCWD, "/missing", exchange);
+    let from_missing = fs.renameat2(AT_FDCWD, "/missing", AT_FDCWD, "/file", exchange);
+    let both_flags = fs.renameat2(AT_FDCWD, "/file", AT_FDCWD, "/dir", exchange | RenameFlag::RENAME_NOREPLACE);
+    let into_subtree = fs.renameat2(AT_FDCWD, "/dir", AT_FDCWD, "/dir/sub", exchange);
+    let from_subtree = fs.renameat2(AT_FDCWD, "/dir/sub", AT_FDCWD, "/dir", exchange);
+
+    /* Assert */
+
+    assert!(with_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(from_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(both_flags.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(into_subtree.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(from_subtree.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(read_file(&fs, "/file"), b"file");
+    assert!(fs.stat("/dir/sub").is_ok());
+}
+
+#[test]
+fn test_renameat2_exchange_should_keep_both_entries_under_concurrent_swaps() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.mkdir("/left").unwrap();
+    fs.mkdir("/right").unwrap();
+    write_file(&fs, "/left/a", b"a");
+    write_file(&fs, "/right/b", b"b");
+
+    /* Action */
+
+    let handles: Vec<_> = [("/left/a", "/right/b"), ("/right/b", "/left/a")]
+        .into_iter()
+        .cycle()
+        .take(4)
+        .map(|(old, new)| {
+            let fs = fs.clone();
+            thread::spawn(move || {
+                for _ in 0..250 {
+                    fs.renameat2(AT_FDCWD, old, AT_FDCWD, new, RenameFlag::RENAME_EXCHANGE).unwrap();
+                }
+            })
+        })
+        .collect();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    /* Assert */
+
+    let mut contents = [read_file(&fs, "/left/a"), read_file(&fs, "/right/b")];
+    contents.sort();
+
+    assert_eq!(contents, [b"a".to_vec(), b"b".to_vec()]);
+    assert_eq!(fs.readdir("/left").unwrap().len(), 1);
+    assert_eq!(fs.readdir("/right").unwrap().len(), 1);
+    assert_eq!(fs.stat("/left/a").unwrap().nlink, 1);
+    assert_eq!(fs.stat("/right/b").unwrap().nlink, 1);
+}
+
+#[test]
+fn test_rename_onto_an_ancestor_should_fail_with_not_empty() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/outer").unwrap();
+    fs.mkdir("/outer/inner").unwrap();
+    write_file(&fs, "/outer/inner/file", b"file");
+
+    /* Action */
+
+    let file_onto_parent = fs.rename("/outer/inner/file", "/outer/inner");
+    let directory_onto_grandparent = fs.rename("/outer/inner", "/outer");
+
+    /* Assert */
+
+    assert!(file_onto_parent.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTEMPTY)));
+    assert!(directory_onto_grandparent.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTEMPTY)));
+    assert_eq!(read_file(&fs, "/outer/inner/file"), b"file");
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "serde")]
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, OpenFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_deserialize_should_restore_identical_tree() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/empty").unwrap();
+    write_file(&fs, "/dir/file", b"contents");
+    write_file(&fs, "/blank", b"");
+    fs.link("/dir/file", "/alias").unwrap();
+    fs.symlink("dir/file", "/link").unwrap();
+
+    /* Action */
+
+    let json = serde_json::to_string(&fs).unwrap();
+    let restored: MemFS = serde_json::from_str(&json).unwrap();
+
+    /* Assert */
+
+    assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+    assert_eq!(read_file(&restored, "/dir/file"), b"contents");
+    assert_eq!(read_file(&restored, "/link"), b"contents");
+    assert_eq!(read_file(&restored, "/blank"), b"");
+    assert_eq!(restored.readlink("/link").unwrap(), "dir/file");
+    assert_eq!(restored.stat("/dir/empty").unwrap().file_type, FileType::Directory);
+    assert_eq!(restored.readdir("/").unwrap().len(), 4);
+
+    write_file(&restored, "/alias", b"changed!");
+
+    assert_eq!(restored.stat("/dir/file").unwrap().nlink, 2);
+    assert_eq!(read_file(&restored, "/dir/file"), b"changed!");
+}
+
+#[test]
+fn test_deserialize_should_reject_invalid_image() {
+    /* Arrange */
+
+    let file_root = r#"{"File":[1,2,3]}"#;
+    let bad_name = r#"{"Directory":{"a/b":{"File":[]}}}"#;
+    let dangling_link = r#"{"Directory":{"a":{"Link":"/missing"}}}"#;
+
+    /* Action */
+
+    let file_root_result = serde_json::from_str::<MemFS>(file_root);
+    let bad_name_result = serde_json::from_str::<MemFS>(bad_name);
+    let dangling_link_result = serde_json::from_str::<MemFS>(dangling_link);
+
+    /* Assert */
+
+    assert!(file_root_result.is_err());
+    assert!(bad_name_result.is_err());
+    assert!(dangling_link_result.is_err());
+}
@@ -0,0 +1,274 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag, generate_random_vector};
+
+#[test]
+fn test_snapshot_view_should_keep_state_at_the_time_of_snapshot() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let before = generate_random_vector(16);
+    let after = generate_random_vector(16);
+    let mut buffer = vec![0u8; 16];
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs
+        .open("/dir/file.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &before).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let id = fs.take_snapshot().unwrap();
+
+    let fd = fs.open("/dir/file.txt", OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, &after).unwrap();
+    fs.close(fd).unwrap();
+    fs.mkdir("/created_later").unwrap();
+
+    let view = fs.open_snapshot(id).unwrap();
+    view.chdir("/dir").unwrap();
+    let view_fd = view.open("file.txt", OpenFlag::O_RDONLY).unwrap();
+    let read_size = view.read(view_fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_size, 16);
+    assert_eq!(buffer, before);
+    assert!(
+        view.chdir("/created_later")
+            .is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT))
+    );
+    assert!(view.close(view_fd).is_ok());
+}
+
+#[test]
+fn test_snapshot_view_should_reject_writes_with_erofs() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs
+        .open("/file.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.close(fd).unwrap();
+    let id = fs.take_snapshot().unwrap();
+    let view = fs.open_snapshot(id).unwrap();
+
+    /* Action */
+
+    let write_open = view.open("/file.txt", OpenFlag::O_WRONLY);
+    let create_open = view.open("/new.txt", OpenFlag::O_CREAT | OpenFlag::O_RDONLY);
+
+    /* Assert */
+
+    assert!(write_open.is_err_and(|e| matches!(e.err_type, MemFSErrType::EROFS)));
+    assert!(create_open.is_err_and(|e| matches!(e.err_type, MemFSErrType::EROFS)));
+}
+
+#[test]
+fn test_dropped_snapshot_should_not_be_opened() {
+    let fs = MemFS::new();
+    let id = fs.take_snapshot().unwrap();
+
+    let view = fs.open_snapshot(id).unwrap();
+    fs.drop_snapshot(id).unwrap();
+
+    assert!(
+        fs.open_snapshot(id)
+            .is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT))
+    );
+    assert!(
+        fs.drop_snapshot(id)
+            .is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT))
+    );
+    assert!(view.chdir("/").is_ok());
+}
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.ftruncate(fd, 0).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(view: &memfs::snapshot::MemFSView, path: &str) -> Vec<u8> {
+    let fd = view.open(path, OpenFlag::O_RDONLY).unwrap();
+    let mut buffer = vec![0u8; 64];
+    let read = view.read(fd, &mut buffer).unwrap();
+    view.close(fd).unwrap();
+
+    buffer.truncate(read);
+    buffer
+}
+
+#[test]
+fn test_snapshots_should_keep_contents_of_files_written_since() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    write_file(&fs, "/file", b"first");
+    write_file(&fs, "/untouched", b"same");
+
+    /* Action */
+
+    let first = fs.snapshot().unwrap();
+    write_file(&fs, "/file", b"second version");
+    let second = fs.snapshot().unwrap();
+    write_file(&fs, "/file", b"third");
+    fs.unlink("/untouched").unwrap();
+
+    /* Assert */
+
+    assert_eq!(read_file(&first.view(), "/file"), b"first");
+    assert_eq!(read_file(&second.view(), "/file"), b"second version");
+    assert_eq!(read_file(&first.clone().view(), "/untouched"), b"same");
+    assert_eq!(read_file(&second.view(), "/untouched"), b"same");
+}
+
+#[test]
+fn test_snapshot_view_should_read_consistently_while_files_are_written() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    write_file(&fs, "/file", &[0u8; 32]);
+    let snapshot = fs.snapshot().unwrap();
+
+    /* Action */
+
+    let writer = {
+        let fs = fs.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/file", OpenFlag::O_WRONLY).unwrap();
+
+            for round in 1..=200u8 {
+                fs.pwrite(fd, &[round; 32], 0).unwrap();
+            }
+
+            fs.close(fd).unwrap();
+        })
+    };
+
+    let view = snapshot.view();
+    let consistent = (0..200).all(|_| {
+        let fd = view.open("/file", OpenFlag::O_RDONLY).unwrap();
+        let mut buffer = [1u8; 32];
+        view.read(fd, &mut buffer).unwrap();
+        view.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+        view.close(fd).unwrap();
+
+        buffer == [0u8; 32]
+    });
+
+    writer.join().unwrap();
+
+    /* Assert */
+
+    assert!(consistent);
+}
+
+#[test]
+fn test_restore_should_bring_back_tree_contents_and_inode_numbers() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/dir/file", b"original");
+    fs.link("/dir/file", "/hard").unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+    let ino = fs.stat("/dir/file").unwrap().ino;
+    let usage = fs.usage();
+    let snapshot = fs.snapshot().unwrap();
+
+    write_file(&fs, "/dir/file", b"changed");
+    let open_fd = fs.open("/hard", OpenFlag::O_RDONLY).unwrap();
+    fs.rename("/dir", "/moved").unwrap();
+    fs.mkdir("/created_later").unwrap();
+
+    /* Action */
+
+    fs.restore(&snapshot).unwrap();
+
+    fs.link("/hard", "/another").unwrap();
+    let mut buffer = vec![0u8; 16];
+    let read_through_old_fd = fs.read(open_fd, &mut buffer).unwrap();
+    fs.close(open_fd).unwrap();
+
+    /* Assert */
+
+    let restored = fs.stat("/dir/file").unwrap();
+
+    assert_eq!(restored.ino, ino);
+    assert_eq!(restored.nlink, 3);
+    assert_eq!(fs.stat("/link").unwrap().ino, ino);
+    assert_eq!(read_file(&snapshot.view(), "/hard"), b"original");
+    assert_eq!(&buffer[..read_through_old_fd], b"changed");
+    assert!(fs.stat("/moved").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.stat("/created_later").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+
+    // Files removed since the snapshot stay charged until the snapshot sharing them is dropped.
+    drop(snapshot);
+    fs.unlink("/another").unwrap();
+
+    if !cfg!(feature = "lock-free") {
+        assert_eq!(fs.usage(), usage);
+    }
+}
+
+#[test]
+fn test_restore_should_bring_back_attributes_changed_since() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    write_file(&fs, "/file", b"contents");
+    fs.chmod("/file", 0o640).unwrap();
+    fs.chown("/file", Some(1000), Some(1000)).unwrap();
+    fs.utimens("/file", None, Some(mtime)).unwrap();
+    let snapshot = fs.snapshot().unwrap();
+
+    fs.chmod("/file", 0o777).unwrap();
+    fs.chown("/file", Some(0), Some(0)).unwrap();
+    fs.utimens("/file", None, Some(SystemTime::now())).unwrap();
+
+    /* Action */
+
+    fs.restore(&snapshot).unwrap();
+
+    /* Assert */
+
+    let restored = fs.stat("/file").unwrap();
+
+    assert_eq!(restored.mode, 0o640);
+    assert_eq!((restored.uid, restored.gid), (1000, 1000));
+    assert_eq!(restored.mtime, mtime);
+}
+
+#[test]
+fn test_restore_should_fail_without_changes_past_quota() {
+    /* Arrange */
+
+    let fs = MemFS::builder().quota(2048).build();
+    write_file(&fs, "/file", &[1u8; 1000]);
+    let snapshot = fs.snapshot().unwrap();
+    write_file(&fs, "/file", &[2u8; 1000]);
+
+    /* Action */
+
+    let restored = fs.restore(&snapshot);
+
+    /* Assert */
+
+    let fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let mut buffer = vec![0u8; 1000];
+    fs.read(fd, &mut buffer).unwrap();
+
+    assert!(restored.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOSPC)));
+    assert_eq!(buffer, vec![2u8; 1000]);
+}
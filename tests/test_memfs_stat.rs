@@ -0,0 +1,124 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag, PAGE_SIZE, generate_random_vector};
+
+#[test]
+fn test_stat_many_should_return_results_in_given_order() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = generate_random_vector(100);
+
+    fs.mkdir("/docs").unwrap();
+    fs.mkdir("/docs/drafts").unwrap();
+    let fd = fs
+        .open("/docs/a.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.close(fd).unwrap();
+    let fd = fs
+        .open("/docs/b.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.close(fd).unwrap();
+    fs.chdir("/docs").unwrap();
+
+    /* Action */
+
+    let stats = fs.stat_many(&[
+        "/docs/a.txt",
+        "/",
+        "drafts",
+        "/docs/missing",
+        "./b.txt",
+        "/docs/a.txt/inner",
+        "/nowhere/a.txt",
+    ]);
+
+    /* Assert */
+
+    assert_eq!(stats.len(), 7);
+
+    let a = stats[0].as_ref().unwrap();
+    assert_eq!(a.file_type, FileType::File);
+    assert_eq!(a.size, 100);
+
+    let root = stats[1].as_ref().unwrap();
+    assert_eq!(root.file_type, FileType::Directory);
+    assert_eq!(root.size, 1);
+
+    let drafts = stats[2].as_ref().unwrap();
+    assert_eq!(drafts.file_type, FileType::Directory);
+    assert_eq!(drafts.size, 0);
+
+    assert!(stats[3].as_ref().is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(stats[4].as_ref().unwrap().size, 0);
+    assert!(stats[5].as_ref().is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(stats[6].as_ref().is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
+
+#[test]
+fn test_stat_dir_entries_should_list_entries_sorted_by_name() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = generate_random_vector(10);
+
+    fs.mkdir("/dir").unwrap();
+    fs.mkdir("/dir/sub").unwrap();
+    for name in ["/dir/zeta", "/dir/alpha"] {
+        let fd = fs.open(name, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+        fs.write(fd, &data).unwrap();
+        fs.close(fd).unwrap();
+    }
+
+    /* Action */
+
+    let entries = fs.stat_dir_entries("/dir").unwrap();
+    let file_result = fs.stat_dir_entries("/dir/alpha");
+
+    /* Assert */
+
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["alpha", "sub", "zeta"]);
+    assert_eq!(entries[0].stat.size, 10);
+    assert_eq!(entries[1].stat.file_type, FileType::Directory);
+    assert!(file_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+}
+
+#[test]
+fn test_statfs_should_report_blocks_and_entries() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let initial = fs.statfs();
+
+    /* Action */
+
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, &generate_random_vector(PAGE_SIZE)).unwrap();
+    fs.close(fd).unwrap();
+    fs.link("/dir/file", "/hard").unwrap();
+    fs.symlink("/dir/file", "/link").unwrap();
+    let populated = fs.statfs();
+
+    let open = fs.open("/hard", OpenFlag::O_RDONLY).unwrap();
+    fs.unlink("/hard").unwrap();
+    fs.unlink("/dir/file").unwrap();
+    let unlinked_but_open = fs.statfs();
+    fs.close(open).unwrap();
+
+    /* Assert */
+
+    assert_eq!(initial.block_size, PAGE_SIZE);
+    assert_eq!(initial.blocks_used, 0);
+    assert_eq!(initial.blocks_free, initial.blocks);
+    assert_eq!(initial.entries, 1);
+    assert!(!initial.read_only);
+
+    assert_eq!(populated.blocks_used, 1);
+    assert_eq!(populated.blocks_free, initial.blocks - 1);
+    assert_eq!(populated.entries, 4);
+
+    assert_eq!(unlinked_but_open.blocks_used, 1);
+    assert_eq!(unlinked_but_open.entries, 4);
+}
@@ -0,0 +1,147 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErrType, OpenFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_symlink_should_resolve_to_target_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/dir/file", b"data");
+
+    /* Action */
+
+    fs.symlink("/dir/file", "/absolute").unwrap();
+    fs.symlink("file", "/dir/relative").unwrap();
+    write_file(&fs, "/dir/relative", b"new!");
+
+    /* Assert */
+
+    assert_eq!(read_file(&fs, "/absolute"), b"new!");
+    assert_eq!(fs.readlink("/absolute").unwrap(), "/dir/file");
+    assert_eq!(fs.readlink("/dir/relative").unwrap(), "file");
+    assert_eq!(fs.stat("/absolute").unwrap().file_type, FileType::File);
+    assert_eq!(fs.readdir("/").unwrap()[0].file_type, FileType::Symlink);
+}
+
+#[test]
+fn test_symlink_should_resolve_directories_in_the_middle_of_path() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.mkdir("/a").unwrap();
+    fs.mkdir("/a/b").unwrap();
+    fs.mkdir("/c").unwrap();
+    fs.symlink("../a/b", "/c/to_b").unwrap();
+    fs.symlink("/c", "/to_c").unwrap();
+
+    /* Action */
+
+    write_file(&fs, "/to_c/to_b/file", b"deep");
+    fs.chdir("/to_c/to_b").unwrap();
+    let relative = read_file(&fs, "file");
+
+    /* Assert */
+
+    assert_eq!(relative, b"deep");
+    assert_eq!(read_file(&fs, "/a/b/file"), b"deep");
+    assert_eq!(fs.stat("/to_c/to_b").unwrap().file_type, FileType::Directory);
+}
+
+#[test]
+fn test_symlink_should_fail_on_loop_dangling_target_and_existing_name() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    fs.symlink("/second", "/first").unwrap();
+    fs.symlink("/first", "/second").unwrap();
+    fs.symlink("/missing", "/dangling").unwrap();
+    write_file(&fs, "/file", b"f");
+
+    /* Action */
+
+    let loop_result = fs.stat("/first");
+    let loop_dir_result = fs.open("/first/file", OpenFlag::O_RDONLY);
+    let dangling_result = fs.open("/dangling", OpenFlag::O_RDONLY);
+    let exists_result = fs.symlink("/file", "/first");
+    let readlink_result = fs.readlink("/file");
+
+    /* Assert */
+
+    assert!(loop_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ELOOP)));
+    assert!(loop_dir_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ELOOP)));
+    assert!(dangling_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(exists_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(readlink_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(fs.readlink("/first").unwrap(), "/second");
+}
+
+#[test]
+fn test_unlink_and_rename_should_act_on_link_itself() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/file", b"data");
+    fs.symlink("/file", "/link").unwrap();
+
+    /* Action */
+
+    fs.rename("/link", "/moved").unwrap();
+    let moved_target = fs.readlink("/moved").unwrap();
+    fs.unlink("/moved").unwrap();
+
+    /* Assert */
+
+    assert_eq!(moved_target, "/file");
+    assert!(fs.readlink("/moved").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read_file(&fs, "/file"), b"data");
+}
+
+#[test]
+fn test_lookup_should_resolve_dots_after_symbolic_links() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/x").unwrap();
+    fs.mkdir("/x/y").unwrap();
+    fs.mkdir("/a").unwrap();
+    write_file(&fs, "/x/file", b"data");
+    fs.symlink("/x/y", "/a/link").unwrap();
+    fs.symlink("../file", "/x/y/up").unwrap();
+
+    /* Action */
+
+    let through_parent = fs.stat("/a/link/../file").unwrap().ino;
+    let through_relative = fs.stat("/a/./link//up").unwrap().ino;
+    let above_root = fs.stat("/../../a/link/.").unwrap().ino;
+    let past_file = fs.stat("/a/link/up/more");
+
+    /* Assert */
+
+    let file = fs.stat("/x/file").unwrap().ino;
+
+    assert_eq!(through_parent, file);
+    assert_eq!(through_relative, file);
+    assert_eq!(above_root, fs.stat("/x/y").unwrap().ino);
+    assert!(past_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+}
@@ -0,0 +1,176 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use memfs::memfs::MemFS;
+use memfs::process::{Credentials, MemFSProcess};
+use memfs::trace::{Trace, TraceCall, TraceRecorder};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+/// Lets the clock move on, so that a later stamp is strictly greater than an earlier one.
+fn tick() {
+    thread::sleep(Duration::from_millis(2));
+}
+
+#[test]
+fn test_reads_and_writes_should_stamp_file_times() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let before_create = SystemTime::now();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let created = fs.stat("/file").unwrap();
+    let mut buffer = [0u8; 4];
+
+    /* Action */
+
+    tick();
+    fs.write(fd, b"data").unwrap();
+    let written = fs.stat("/file").unwrap();
+
+    tick();
+    fs.pread(fd, &mut buffer, 0).unwrap();
+    let read = fs.stat("/file").unwrap();
+
+    tick();
+    fs.chmod("/file", 0o600).unwrap();
+    let chmodded = fs.stat("/file").unwrap();
+    fs.close(fd).unwrap();
+
+    /* Assert */
+
+    assert!(created.btime >= before_create);
+    assert_eq!(created.atime, created.btime);
+    assert_eq!(created.mtime, created.btime);
+
+    assert!(written.mtime > created.mtime);
+    assert_eq!(written.ctime, written.mtime);
+    assert_eq!(written.atime, created.atime);
+
+    assert!(read.atime > written.mtime);
+    assert_eq!(read.mtime, written.mtime);
+
+    assert!(chmodded.ctime > read.atime);
+    assert_eq!(chmodded.mtime, written.mtime);
+    assert_eq!(chmodded.btime, created.btime);
+}
+
+#[test]
+fn test_entry_changes_should_stamp_parent_directory() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let initial = fs.stat("/dir").unwrap();
+
+    /* Action */
+
+    tick();
+    fs.mkdir("/dir/sub").unwrap();
+    let after_mkdir = fs.stat("/dir").unwrap();
+
+    tick();
+    let fd = fs.open("/dir/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    let after_create = fs.stat("/dir").unwrap();
+
+    tick();
+    fs.unlink("/dir/file").unwrap();
+    let after_unlink = fs.stat("/dir").unwrap();
+
+    /* Assert */
+
+    assert!(after_mkdir.mtime > initial.mtime);
+    assert!(after_create.mtime > after_mkdir.mtime);
+    assert!(after_unlink.mtime > after_create.mtime);
+    assert_eq!(after_unlink.btime, initial.btime);
+}
+
+#[test]
+fn test_utimens_should_set_requested_times() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+    fs.symlink("/file", "/link").unwrap();
+
+    let atime = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let mtime = UNIX_EPOCH + Duration::from_nanos(2_000_000_000_123);
+    let original = fs.stat("/file").unwrap();
+
+    /* Action */
+
+    fs.utimens("/link", Some(atime), Some(mtime)).unwrap();
+    let both = fs.stat("/file").unwrap();
+
+    fs.utimens("/file", None, Some(atime)).unwrap();
+    let mtime_only = fs.stat("/file").unwrap();
+
+    let before_epoch = fs.utimens("/file", UNIX_EPOCH.checked_sub(Duration::from_secs(1)), None);
+    let missing = fs.utimens("/missing", Some(atime), None);
+
+    /* Assert */
+
+    assert_eq!(both.atime, atime);
+    assert_eq!(both.mtime, mtime);
+    assert!(both.ctime >= original.ctime);
+    assert_eq!(mtime_only.atime, atime);
+    assert_eq!(mtime_only.mtime, atime);
+    assert!(before_epoch.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(fs.stat("/file").unwrap().mtime, atime);
+}
+
+#[test]
+fn test_utimens_should_require_ownership_with_permission_checks() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::builder().permission_checks(true).build());
+    let user = MemFSProcess::with_credentials(fs.clone(), Credentials::new(1000, 100));
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let by_user = user.run(|fs| fs.utimens("/file", Some(UNIX_EPOCH), None));
+    fs.chown("/file", Some(1000), None).unwrap();
+    let by_owner = user.run(|fs| fs.utimens("/file", Some(UNIX_EPOCH), None));
+
+    /* Assert */
+
+    assert!(by_user.is_err_and(|e| matches!(e.err_type, MemFSErrType::EPERM)));
+    assert!(by_owner.is_ok());
+    assert_eq!(fs.stat("/file").unwrap().atime, UNIX_EPOCH);
+}
+
+#[test]
+fn test_utimens_should_round_trip_through_trace_text() {
+    /* Arrange */
+
+    let mut fs = MemFS::new();
+    let recorder = TraceRecorder::new();
+    fs.set_trace_recorder(recorder.clone());
+    fs.mkdir("/dir").unwrap();
+    let mtime = UNIX_EPOCH + Duration::from_nanos(1_700_000_000_000_000_001);
+
+    /* Action */
+
+    fs.utimens("/dir", None, Some(mtime)).unwrap();
+    let trace = recorder.trace();
+    let parsed = Trace::from_text(&trace.to_text()).unwrap();
+
+    /* Assert */
+
+    assert_eq!(parsed, trace);
+    assert_eq!(
+        parsed.events()[1].call,
+        TraceCall::Utimens {
+            path: "/dir".to_string(),
+            atime: None,
+            mtime: Some(mtime),
+        }
+    );
+}
@@ -0,0 +1,101 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let mut buffer = [0u8; 64];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer[..read].to_vec()
+}
+
+#[test]
+fn test_tmpfile_should_stay_unnamed_until_closed() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let blocks_free = fs.statfs().blocks_free;
+
+    /* Action */
+
+    let fd = fs.open("/dir", OpenFlag::O_TMPFILE | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"scratch").unwrap();
+
+    let mut buffer = [0u8; 7];
+    let read = fs.pread(fd, &mut buffer, 0).unwrap();
+    let stat = fs.fstat(fd).unwrap();
+    let listed = fs.readdir("/dir").unwrap().len();
+    let blocks_free_while_open = fs.statfs().blocks_free;
+    fs.close(fd).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, 7);
+    assert_eq!(&buffer, b"scratch");
+    assert_eq!(stat.nlink, 0);
+    assert_eq!(stat.size, 7);
+    assert_eq!(listed, 0);
+    assert_eq!(blocks_free_while_open, blocks_free);
+}
+
+#[test]
+fn test_linkat_should_give_tmpfile_a_name() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    fs.mkdir("/dir").unwrap();
+    let fd = fs.open("/dir", OpenFlag::O_TMPFILE | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, b"complete").unwrap();
+
+    /* Action */
+
+    fs.linkat(fd, "/dir/final").unwrap();
+    let taken = fs.linkat(fd, "/dir/final");
+    fs.linkat(fd, "/dir/second").unwrap();
+    fs.close(fd).unwrap();
+
+    let replayed = MemFS::new();
+    fs.journal().unwrap().replay(&replayed).unwrap();
+
+    /* Assert */
+
+    assert!(taken.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert_eq!(read_file(&fs, "/dir/final"), b"complete");
+    assert_eq!(fs.stat("/dir/final").unwrap().nlink, 2);
+    assert_eq!(fs.stat("/dir/final").unwrap().ino, fs.stat("/dir/second").unwrap().ino);
+    assert_eq!(read_file(&replayed, "/dir/final"), b"complete");
+    assert_eq!(read_file(&replayed, "/dir/second"), b"complete");
+}
+
+#[test]
+fn test_tmpfile_should_check_flags_and_directory() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/dir").unwrap();
+    let file = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let exclusive = fs
+        .open("/dir", OpenFlag::O_TMPFILE | OpenFlag::O_EXCL | OpenFlag::O_RDWR)
+        .unwrap();
+
+    /* Action */
+
+    let read_only = fs.open("/dir", OpenFlag::O_TMPFILE | OpenFlag::O_RDONLY);
+    let in_file = fs.open("/file", OpenFlag::O_TMPFILE | OpenFlag::O_RDWR);
+    let in_missing = fs.open("/missing", OpenFlag::O_TMPFILE | OpenFlag::O_RDWR);
+    let link_exclusive = fs.linkat(exclusive, "/dir/name");
+
+    fs.unlink("/file").unwrap();
+    let link_unlinked = fs.linkat(file, "/again");
+
+    /* Assert */
+
+    assert!(read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(in_file.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOTDIR)));
+    assert!(in_missing.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(link_exclusive.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(link_unlinked.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(fs.readdir("/dir").unwrap().is_empty());
+}
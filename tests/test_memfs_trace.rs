@@ -0,0 +1,129 @@
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::trace::{ReplayMode, Trace, TraceCall, TraceRecorder, TraceResult};
+use memfs::utils::{OpenFlag, SeekFlag, generate_random_vector};
+
+fn record_single_thread_workload() -> (MemFS, Trace) {
+    let mut fs = MemFS::new();
+    let recorder = TraceRecorder::new();
+    let data = generate_random_vector(64);
+    let mut buffer = vec![0u8; 64];
+
+    fs.set_trace_recorder(recorder.clone());
+
+    fs.mkdir("/dir with\ttab").unwrap();
+    fs.chdir("/dir with\ttab").unwrap();
+    let fd = fs
+        .open("file.bin", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+    let _ = fs.unlink("/missing");
+
+    (fs, recorder.trace())
+}
+
+#[test]
+fn test_trace_should_survive_text_round_trip() {
+    /* Arrange */
+
+    let (_, trace) = record_single_thread_workload();
+
+    /* Action */
+
+    let text = trace.to_text();
+    let parsed = Trace::from_text(&text).unwrap();
+
+    /* Assert */
+
+    assert_eq!(parsed, trace);
+    assert_eq!(trace.events().len(), 8);
+    assert!(matches!(&trace.events()[0].call, TraceCall::Mkdir { path } if path == "/dir with\ttab"));
+    assert_eq!(trace.events()[3].result, TraceResult::Ok(Some(64)));
+    assert_eq!(trace.events()[7].result, TraceResult::Err("ENOENT".to_string()));
+    assert!(Trace::from_text("0\t0\t0\t0\tjump\t/\tok").is_err());
+}
+
+#[test]
+fn test_replay_should_reproduce_recorded_results_sequentially() {
+    /* Arrange */
+
+    let (_, trace) = record_single_thread_workload();
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    let report = trace.replay(&fresh, ReplayMode::Sequential);
+
+    /* Assert */
+
+    assert_eq!(report.replayed, 8);
+    assert!(report.is_faithful(), "{:?}", report.mismatches);
+    assert!(fresh.stat("/dir with\ttab/file.bin").is_ok_and(|s| s.size == 64));
+}
+
+#[test]
+fn test_replay_should_report_mismatch_against_different_state() {
+    let (_, trace) = record_single_thread_workload();
+    let dirty = MemFS::new();
+    dirty.mkdir("/dir with\ttab").unwrap();
+
+    let report = trace.replay(&dirty, ReplayMode::Sequential);
+
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(report.mismatches[0].seq, 0);
+    assert_eq!(report.mismatches[0].actual, TraceResult::Err("EEXIST".to_string()));
+}
+
+#[test]
+fn test_interleaved_replay_should_keep_threads_of_recording() {
+    /* Arrange */
+
+    let mut fs = MemFS::new();
+    let recorder = TraceRecorder::new();
+    fs.set_trace_recorder(recorder.clone());
+    let fs = Arc::new(fs);
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                let data = generate_random_vector(32);
+                let fd = fs
+                    .open(&format!("/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                    .unwrap();
+
+                for _ in 0..10 {
+                    fs.write(fd, &data).unwrap();
+                }
+
+                fs.close(fd).unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let trace = recorder.trace();
+    let fresh = MemFS::new();
+
+    /* Action */
+
+    let report = trace.replay(&fresh, ReplayMode::Interleaved);
+
+    /* Assert */
+
+    let threads: std::collections::HashSet<u64> = trace.events().iter().map(|e| e.thread).collect();
+    assert_eq!(threads.len(), 4);
+    assert!(report.is_faithful(), "{:?}", report.mismatches);
+
+    for i in 0..4 {
+        assert!(fresh.stat(&format!("/file_{}", i)).is_ok_and(|s| s.size == 320));
+    }
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use memfs::memfs::MemFS;
+use memfs::utils::OpenFlag;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+type Fields = HashMap<String, String>;
+
+#[derive(Default)]
+struct FieldVisitor(Fields);
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+#[derive(Default)]
+struct Records {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, Fields>>,
+    entered: Mutex<Vec<u64>>,
+    events: Mutex<Vec<(Fields, Fields)>>,
+}
+
+/// Keeps the fields of every span, and of every event along with the fields of the span it happened in.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Records>);
+
+impl Recorder {
+    fn events(&self) -> Vec<(Fields, Fields)> {
+        self.0.events.lock().unwrap().clone()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut visitor = FieldVisitor::default();
+        attributes.record(&mut visitor);
+        self.0.spans.lock().unwrap().insert(id, visitor.0);
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        self.0.spans.lock().unwrap().get_mut(&span.into_u64()).unwrap().extend(visitor.0);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let span = self.0.entered.lock().unwrap().last().map(|id| self.0.spans.lock().unwrap()[id].clone());
+        self.0.events.lock().unwrap().push((span.unwrap_or_default(), visitor.0));
+    }
+
+    fn enter(&self, span: &Id) {
+        self.0.entered.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.0.entered.lock().unwrap().pop();
+    }
+}
+
+fn record(scenario: impl FnOnce(&MemFS)) -> Vec<(Fields, Fields)> {
+    let fs = MemFS::new();
+    let recorder = Recorder::default();
+
+    tracing::subscriber::with_default(recorder.clone(), || scenario(&fs));
+
+    recorder.events()
+}
+
+#[test]
+fn test_calls_should_be_traced_with_descriptor_path_and_result() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/traced", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let recorder = Recorder::default();
+
+    /* Action */
+
+    tracing::subscriber::with_default(recorder.clone(), || fs.write(fd, b"hello").unwrap());
+    let events = recorder.events();
+
+    /* Assert */
+
+    assert_eq!(events.len(), 1);
+
+    let (span, event) = &events[0];
+
+    assert_eq!(span["op"], "write");
+    assert_eq!(span["fd"], fd.to_string());
+    assert_eq!(span["path"], "/traced");
+    assert_eq!(event["message"], "done");
+    assert_eq!(event["value"], "5");
+    assert!(event.contains_key("latency_us"));
+}
+
+#[test]
+fn test_failures_should_be_traced_with_error() {
+    /* Arrange */
+
+    let path = "/missing/file";
+
+    /* Action */
+
+    let events = record(|fs| {
+        fs.open(path, OpenFlag::O_RDONLY).unwrap_err();
+        fs.stat(path).unwrap_err();
+    });
+
+    /* Assert */
+
+    assert_eq!(events.len(), 2);
+
+    for ((span, event), op) in events.iter().zip(["open", "stat"]) {
+        assert_eq!(span["op"], op);
+        assert_eq!(span["path"], path);
+        assert!(!span.contains_key("fd"));
+        assert_eq!(event["message"], "failed");
+        assert_eq!(event["errno"], "ENOENT");
+    }
+}
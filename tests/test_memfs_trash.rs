@@ -0,0 +1,114 @@
+use memfs::journal::JournalEntry;
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+fn read_all(fs: &MemFS, path: &str) -> String {
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    let mut buffer = [0u8; 256];
+    let read = fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    String::from_utf8(buffer[..read].to_vec()).unwrap()
+}
+
+fn write_file(fs: &MemFS, path: &str, contents: &str) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+    fs.write(fd, contents.as_bytes()).unwrap();
+    fs.close(fd).unwrap();
+}
+
+#[test]
+fn test_undelete_should_bring_back_unlinked_file() {
+    /* Arrange */
+
+    let fs = MemFS::builder().trash(true).build();
+    fs.create_dir_all("/data/logs").unwrap();
+    write_file(&fs, "/data/logs/app.log", "started\n");
+    fs.link("/data/logs/app.log", "/data/current.log").unwrap();
+    let before = fs.stat("/data/logs/app.log").unwrap();
+    fs.unlink("/data/logs/app.log").unwrap();
+    let unlinked = fs.stat("/data/logs/app.log");
+
+    /* Action */
+
+    let trashed = fs.trashed();
+    fs.undelete("/data/logs/app.log").unwrap();
+
+    /* Assert */
+
+    assert!(unlinked.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(trashed, vec!["/data/logs/app.log".to_string()]);
+    assert!(fs.trashed().is_empty());
+    assert_eq!(read_all(&fs, "/data/logs/app.log"), "started\n");
+
+    let after = fs.stat("/data/logs/app.log").unwrap();
+    assert_eq!(after.ino, before.ino);
+    assert_eq!(after.nlink, 2);
+    assert_eq!(fs.stat("/data/current.log").unwrap().ino, before.ino);
+}
+
+#[test]
+fn test_undelete_should_fail_where_nothing_can_be_put_back() {
+    /* Arrange */
+
+    let fs = MemFS::builder().trash(true).build();
+    let disabled = MemFS::new();
+    write_file(&disabled, "/file", "gone");
+    disabled.unlink("/file").unwrap();
+    write_file(&fs, "/config", "first");
+    fs.unlink("/config").unwrap();
+    write_file(&fs, "/config", "second");
+    fs.unlink("/config").unwrap();
+    write_file(&fs, "/config", "third");
+
+    /* Action */
+
+    let taken = fs.undelete("/config");
+    fs.unlink("/config").unwrap();
+    let latest = fs.undelete("/config");
+    let never_removed = fs.undelete("/other");
+    let not_enabled = disabled.undelete("/file");
+
+    /* Assert */
+
+    assert!(taken.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+    assert!(latest.is_ok());
+    assert_eq!(read_all(&fs, "/config"), "third");
+    assert_eq!(fs.trashed(), vec!["/config".to_string(), "/config".to_string()]);
+    assert!(never_removed.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert!(not_enabled.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert!(disabled.trashed().is_empty());
+}
+
+#[test]
+fn test_purge_trash_should_free_trashed_entries() {
+    /* Arrange */
+
+    let fs = MemFS::builder().trash(true).journal(true).build();
+    fs.mkdir("/tmp").unwrap();
+    write_file(&fs, "/tmp/a", "a");
+    write_file(&fs, "/tmp/b", "b");
+    fs.unlink("/tmp/a").unwrap();
+    fs.unlink("/tmp/b").unwrap();
+    fs.undelete("/tmp/a").unwrap();
+
+    /* Action */
+
+    let purged = fs.purge_trash();
+    let after_purge = fs.undelete("/tmp/b");
+
+    /* Assert */
+
+    assert_eq!(purged, 1);
+    assert!(fs.trashed().is_empty());
+    assert_eq!(fs.purge_trash(), 0);
+    assert!(after_purge.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+
+    let journal = fs.journal().unwrap();
+    let replayed = MemFS::builder().trash(true).build();
+    journal.replay(&replayed).unwrap();
+
+    assert!(journal.entries().contains(&JournalEntry::Undelete { path: "/tmp/a".to_string() }));
+    assert_eq!(read_all(&replayed, "/tmp/a"), "a");
+    assert_eq!(replayed.trashed(), vec!["/tmp/b".to_string()]);
+}
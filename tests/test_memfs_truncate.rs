@@ -0,0 +1,146 @@
+use std::{sync::Arc, thread};
+
+use memfs::crash::CrashModel;
+use memfs::memfs::MemFS;
+use memfs::utils::{FILE_MAX_SIZE, MemFSErrType, OpenFlag, SeekFlag};
+
+fn write_file(fs: &MemFS, path: &str, data: &[u8]) {
+    let fd = fs.open(path, OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, data).unwrap();
+    fs.close(fd).unwrap();
+}
+
+fn read_file(fs: &MemFS, path: &str) -> Vec<u8> {
+    let size = fs.stat(path).unwrap().size;
+    let mut buffer = vec![0u8; size];
+    let fd = fs.open(path, OpenFlag::O_RDONLY).unwrap();
+    fs.read(fd, &mut buffer).unwrap();
+    fs.close(fd).unwrap();
+
+    buffer
+}
+
+#[test]
+fn test_truncate_should_shrink_and_zero_fill_on_extension() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/file", b"abcdef");
+
+    /* Action */
+
+    fs.truncate("/file", 2).unwrap();
+    let shrunk = read_file(&fs, "/file");
+    fs.truncate("/file", 5).unwrap();
+
+    /* Assert */
+
+    assert_eq!(shrunk, b"ab");
+    assert_eq!(read_file(&fs, "/file"), b"ab\0\0\0");
+}
+
+#[test]
+fn test_ftruncate_should_require_writable_descriptor() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    write_file(&fs, "/file", b"data");
+    let read_fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    let write_fd = fs.open("/file", OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let read_result = fs.ftruncate(read_fd, 0);
+    let write_result = fs.ftruncate(write_fd, 1);
+
+    /* Assert */
+
+    assert!(read_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+    assert!(write_result.is_ok());
+    assert_eq!(fs.stat("/file").unwrap().size, 1);
+}
+
+#[test]
+fn test_truncate_should_fail_on_directory_and_oversized_length() {
+    /* Arrange */
+
+    let fs = MemFS::builder().version_on_write(true).build();
+
+    fs.mkdir("/dir").unwrap();
+    write_file(&fs, "/file", b"data");
+    let versions = fs.versions("/file").unwrap().len();
+
+    /* Action */
+
+    let dir_result = fs.truncate("/dir", 0);
+    let large_result = fs.truncate("/file", FILE_MAX_SIZE + 1);
+    let missing_result = fs.truncate("/missing", 0);
+
+    /* Assert */
+
+    assert!(dir_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EISDIR)));
+    assert!(large_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert!(missing_result.is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+    assert_eq!(read_file(&fs, "/file"), b"data");
+    assert_eq!(fs.versions("/file").unwrap().len(), versions);
+}
+
+#[test]
+fn test_truncate_should_not_expose_dropped_bytes_to_concurrent_readers() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+
+    write_file(&fs, "/file", &[b'x'; 64]);
+
+    /* Action */
+
+    let reader = {
+        let fs = fs.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+            let mut buffer = vec![0u8; 64];
+            let mut valid = true;
+
+            for _ in 0..200 {
+                fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+                let read = fs.read(fd, &mut buffer).unwrap();
+                valid &= buffer[..read].iter().all(|b| *b == b'x' || *b == 0);
+            }
+
+            valid
+        })
+    };
+
+    for i in 0..200 {
+        fs.truncate("/file", if i % 2 == 0 { 16 } else { 64 }).unwrap();
+    }
+
+    /* Assert */
+
+    assert!(reader.join().unwrap());
+}
+
+#[test]
+fn test_crash_should_undo_unsynced_truncate() {
+    /* Arrange */
+
+    let fs = MemFS::builder().crash_simulation(true).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+
+    fs.write(fd, b"durable").unwrap();
+    fs.fsync(fd).unwrap();
+
+    /* Action */
+
+    fs.ftruncate(fd, 3).unwrap();
+    let report = fs.crash(CrashModel::DropUnsynced).unwrap();
+
+    /* Assert */
+
+    assert_eq!(report.writes_dropped, 1);
+    assert_eq!(read_file(&fs, "/file"), b"durable");
+}
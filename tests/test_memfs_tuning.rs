@@ -0,0 +1,159 @@
+use memfs::memfs::MemFS;
+use memfs::tuning::{MapResize, MapTuning};
+use memfs::utils::{MemFSErrType, OpenFlag};
+
+#[test]
+fn test_tuned_maps_should_behave_the_same() {
+    let tunings = [
+        MapTuning {
+            shards: Some(1),
+            ..Default::default()
+        },
+        MapTuning {
+            shards: Some(3),
+            directory_capacity: 4,
+            descriptor_capacity: 1024,
+            ..Default::default()
+        },
+        MapTuning {
+            shards: Some(128),
+            resize: MapResize::Blocking,
+            ..Default::default()
+        },
+        MapTuning {
+            resize: MapResize::Incremental { chunk: 1 },
+            ..Default::default()
+        },
+        MapTuning {
+            descriptor_capacity: 64,
+            descriptor_shards: 7,
+            ..Default::default()
+        },
+    ];
+
+    for tuning in tunings {
+        /* Arrange */
+
+        let fs = MemFS::builder().map_tuning(tuning).build();
+
+        fs.mkdir("/dir").unwrap();
+        fs.mkdir("/dir/sub").unwrap();
+
+        /* Action */
+
+        let fds: Vec<usize> = (0..300)
+            .map(|i| {
+                fs.open(&format!("/dir/sub/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                    .unwrap()
+            })
+            .collect();
+
+        for fd in &fds {
+            fs.close(*fd).unwrap();
+        }
+
+        let entries = fs.stat_dir_entries("/dir/sub").unwrap();
+
+        /* Assert */
+
+        assert_eq!(entries.len(), 300, "tuning {tuning:?}");
+
+        for i in 0..300 {
+            assert!(fs.unlink(&format!("/dir/sub/file_{}", i)).is_ok(), "tuning {tuning:?}");
+        }
+
+        assert!(fs.rmdir("/dir/sub").is_ok(), "tuning {tuning:?}");
+    }
+}
+
+#[test]
+fn test_sharded_descriptor_table_should_keep_descriptors_apart() {
+    /* Arrange */
+
+    let fs = MemFS::builder()
+        .map_tuning(MapTuning {
+            descriptor_shards: 4,
+            ..Default::default()
+        })
+        .build();
+
+    /* Action */
+
+    let opened: Vec<Vec<usize>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..4)
+            .map(|t| {
+                let fs = &fs;
+                scope.spawn(move || {
+                    (0..50)
+                        .map(|i| {
+                            let fd = fs
+                                .open(&format!("/file_{}_{}", t, i), OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+                                .unwrap();
+                            fs.write(fd, format!("{}_{}", t, i).as_bytes()).unwrap();
+                            fd
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let open_count = fs.metrics().open_file_descriptors;
+    let sizes: Vec<usize> = opened.iter().flatten().map(|fd| fs.fstat(*fd).unwrap().size).collect();
+
+    for fd in opened.iter().flatten() {
+        fs.close(*fd).unwrap();
+    }
+
+    let closed_twice = fs.close(opened[0][0]);
+
+    /* Assert */
+
+    let mut fds: Vec<usize> = opened.into_iter().flatten().collect();
+    fds.sort();
+    fds.dedup();
+
+    assert_eq!(fds.len(), 200);
+    assert_eq!(open_count, 200);
+    assert!(sizes.iter().all(|size| (3..=4).contains(size)));
+    assert_eq!(fs.metrics().open_file_descriptors, 0);
+    assert!(closed_twice.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_sharded_descriptor_numbers_should_come_from_shard_of_thread() {
+    /* Arrange */
+
+    let fs = MemFS::builder()
+        .map_tuning(MapTuning {
+            descriptor_shards: 4,
+            ..Default::default()
+        })
+        .max_open_files(12)
+        .build();
+
+    /* Action */
+
+    let fds: Vec<usize> = (0..3)
+        .map(|i| fs.open(&format!("/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap())
+        .collect();
+    fs.close(fds[1]).unwrap();
+    let reused = fs.open("/file_1", OpenFlag::O_RDWR).unwrap();
+    let rest: Vec<usize> = (3..12)
+        .map(|i| fs.open(&format!("/file_{}", i), OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap())
+        .collect();
+    let past_limit = fs.open("/file_12", OpenFlag::O_CREAT | OpenFlag::O_RDWR);
+
+    /* Assert */
+
+    let mut all: Vec<usize> = fds.iter().chain(&rest).copied().collect();
+    all.sort();
+
+    assert!(fds.iter().all(|fd| fd % 4 == fds[0] % 4));
+    assert_eq!(fds[1..], [fds[0] + 4, fds[0] + 8]);
+    assert_eq!(reused, fds[1]);
+    assert_eq!(all, (0..12).collect::<Vec<_>>());
+    assert!(past_limit.is_err_and(|e| matches!(e.err_type, MemFSErrType::EMFILE)));
+}
@@ -0,0 +1,193 @@
+use std::io::{IoSlice, IoSliceMut};
+use std::{sync::Arc, thread};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{MemFSErrType, OpenFlag, SeekFlag};
+
+#[test]
+fn test_readv_should_scatter_into_buffers_in_order() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.write(fd, b"hello, world").unwrap();
+    fs.lseek(fd, 0, SeekFlag::SEEK_SET).unwrap();
+
+    let (mut first, mut second, mut third) = ([0u8; 5], [0u8; 2], [0u8; 8]);
+
+    /* Action */
+
+    let read = fs
+        .readv(
+            fd,
+            &mut [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second), IoSliceMut::new(&mut third)],
+        )
+        .unwrap();
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+
+    /* Assert */
+
+    assert_eq!(read, 12);
+    assert_eq!(offset, 12);
+    assert_eq!(&first, b"hello");
+    assert_eq!(&second, b", ");
+    assert_eq!(&third, b"world\0\0\0");
+}
+
+#[test]
+fn test_writev_should_write_buffers_as_one_write() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let read_only = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+
+    /* Action */
+
+    let written = fs
+        .writev(fd, &[IoSlice::new(b"abc"), IoSlice::new(b""), IoSlice::new(b"def")])
+        .unwrap();
+    let on_read_only = fs.writev(read_only, &[IoSlice::new(b"x")]);
+
+    let mut contents = [0u8; 16];
+    let read = fs.pread(fd, &mut contents, 0).unwrap();
+
+    /* Assert */
+
+    assert_eq!(written, 6);
+    assert_eq!(&contents[..read], b"abcdef");
+    assert_eq!(fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap(), 6);
+    assert!(on_read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_writev_should_append_records_in_one_piece() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let fd = fs
+        .open("/log", OpenFlag::O_CREAT | OpenFlag::O_WRONLY | OpenFlag::O_APPEND)
+        .unwrap();
+
+    /* Action */
+
+    let handles: Vec<_> = (b'a'..b'e')
+        .map(|tag| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    let (head, body) = ([tag], [tag; 6]);
+                    fs.writev(fd, &[IoSlice::new(&head), IoSlice::new(&body), IoSlice::new(b"\n")])
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut contents = vec![0u8; fs.stat("/log").unwrap().size];
+    let reader = fs.open("/log", OpenFlag::O_RDONLY).unwrap();
+    fs.read(reader, &mut contents).unwrap();
+
+    /* Assert */
+
+    assert_eq!(contents.len(), 4 * 20 * 8);
+
+    for record in contents.chunks(8) {
+        assert!(record[..7].iter().all(|&byte| byte == record[0]));
+        assert_eq!(record[7], b'\n');
+    }
+}
+
+#[test]
+fn test_append_all_should_write_at_end_whatever_the_offset() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    let fd = fs.open("/file", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    let read_only = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+    fs.write(fd, b"head").unwrap();
+    fs.lseek(fd, 1, SeekFlag::SEEK_SET).unwrap();
+
+    /* Action */
+
+    let written = fs.append_all(fd, &[IoSlice::new(b"-"), IoSlice::new(b"tail")]).unwrap();
+    let offset = fs.lseek(fd, 0, SeekFlag::SEEK_CUR).unwrap();
+    let on_read_only = fs.append_all(read_only, &[IoSlice::new(b"x")]);
+
+    let replayed = MemFS::new();
+    fs.journal().unwrap().replay(&replayed).unwrap();
+
+    let read_back = |fs: &MemFS| {
+        let mut contents = [0u8; 16];
+        let fd = fs.open("/file", OpenFlag::O_RDONLY).unwrap();
+        let read = fs.read(fd, &mut contents).unwrap();
+
+        contents[..read].to_vec()
+    };
+
+    /* Assert */
+
+    assert_eq!(written, 5);
+    assert_eq!(offset, 9);
+    assert_eq!(read_back(&fs), b"head-tail");
+    assert_eq!(read_back(&replayed), b"head-tail");
+    assert!(on_read_only.is_err_and(|e| matches!(e.err_type, MemFSErrType::EBADF)));
+}
+
+#[test]
+fn test_append_all_should_keep_records_whole_across_descriptors() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.open("/log", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+
+    /* Action */
+
+    let handles: Vec<_> = (b'a'..b'g')
+        .map(|tag| {
+            let fs = fs.clone();
+
+            thread::spawn(move || {
+                // Half of the writers append through O_APPEND descriptors, the others with append_all.
+                let with_flag = tag % 2 == 0;
+                let flag = if with_flag { OpenFlag::O_WRONLY | OpenFlag::O_APPEND } else { OpenFlag::O_WRONLY };
+                let fd = fs.open("/log", flag).unwrap();
+
+                for _ in 0..20 {
+                    let (head, body) = ([tag], [tag; 6]);
+                    let buffers = [IoSlice::new(&head), IoSlice::new(&body), IoSlice::new(b"\n")];
+
+                    if with_flag {
+                        fs.writev(fd, &buffers).unwrap();
+                    } else {
+                        fs.append_all(fd, &buffers).unwrap();
+                    }
+                }
+
+                fs.close(fd).unwrap();
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut contents = vec![0u8; fs.stat("/log").unwrap().size];
+    let reader = fs.open("/log", OpenFlag::O_RDONLY).unwrap();
+    fs.read(reader, &mut contents).unwrap();
+
+    /* Assert */
+
+    assert_eq!(contents.len(), 6 * 20 * 8);
+
+    for record in contents.chunks(8) {
+        assert!(record[..7].iter().all(|&byte| byte == record[0]));
+        assert_eq!(record[7], b'\n');
+    }
+}
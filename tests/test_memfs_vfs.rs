@@ -0,0 +1,87 @@
+#![cfg(feature = "vfs")]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use memfs::memfs::MemFS;
+use memfs::utils::OpenFlag;
+use memfs::vfs::MemFSVfs;
+use vfs::{VfsFileType, VfsPath, error::VfsErrorKind};
+
+#[test]
+fn test_vfs_path_should_create_read_and_list_entries() {
+    /* Arrange */
+
+    let root: VfsPath = MemFSVfs::new(MemFS::new()).into();
+
+    /* Action */
+
+    root.join("dir").unwrap().create_dir().unwrap();
+    root.join("dir/file.txt").unwrap().create_file().unwrap().write_all(b"hello").unwrap();
+    root.join("dir/file.txt").unwrap().append_file().unwrap().write_all(b" world").unwrap();
+
+    let contents = root.join("dir/file.txt").unwrap().read_to_string().unwrap();
+    let mut names: Vec<String> = root.join("dir").unwrap().read_dir().unwrap().map(|p| p.filename()).collect();
+    names.sort();
+
+    /* Assert */
+
+    assert_eq!(contents, "hello world");
+    assert_eq!(names, vec!["file.txt"]);
+    assert_eq!(root.join("dir").unwrap().metadata().unwrap().file_type, VfsFileType::Directory);
+    assert_eq!(root.join("dir/file.txt").unwrap().metadata().unwrap().len, 11);
+    assert!(!root.join("missing").unwrap().exists().unwrap());
+}
+
+#[test]
+fn test_vfs_file_should_seek_and_truncate_on_create() {
+    /* Arrange */
+
+    let root: VfsPath = MemFSVfs::new(MemFS::new()).into();
+    let path = root.join("file").unwrap();
+    path.create_file().unwrap().write_all(b"0123456789").unwrap();
+
+    /* Action */
+
+    let mut file = path.open_file().unwrap();
+    let mut tail = String::new();
+    file.seek(SeekFrom::End(-3)).unwrap();
+    file.read_to_string(&mut tail).unwrap();
+    drop(file);
+
+    path.create_file().unwrap().write_all(b"new").unwrap();
+
+    /* Assert */
+
+    assert_eq!(tail, "789");
+    assert_eq!(path.read_to_string().unwrap(), "new");
+}
+
+#[test]
+fn test_vfs_should_copy_move_remove_and_report_errors() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let root: VfsPath = MemFSVfs::from_shared(fs.clone()).into();
+    root.join("a").unwrap().create_file().unwrap().write_all(b"data").unwrap();
+
+    /* Action */
+
+    root.join("a").unwrap().copy_file(&root.join("b").unwrap()).unwrap();
+    root.join("b").unwrap().move_file(&root.join("c").unwrap()).unwrap();
+    root.join("a").unwrap().remove_file().unwrap();
+
+    let missing = root.join("a").unwrap().open_file();
+    root.join("d/e").unwrap().create_dir_all().unwrap();
+    let existing_dir = root.join("d").unwrap().create_dir();
+
+    /* Assert */
+
+    assert_eq!(root.join("c").unwrap().read_to_string().unwrap(), "data");
+    assert!(!root.join("b").unwrap().exists().unwrap());
+    assert!(missing.is_err_and(|e| matches!(e.kind(), VfsErrorKind::FileNotFound)));
+    assert!(existing_dir.is_err_and(|e| matches!(e.kind(), VfsErrorKind::DirectoryExists)));
+    assert!(root.join("d/e").unwrap().is_dir().unwrap());
+    assert!(fs.open("/c", OpenFlag::O_RDONLY).is_ok());
+    assert_eq!(fs.metrics().open_file_descriptors, 1);
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{FileType, MemFSErr, MemFSErrType, OpenFlag, SeekFlag};
+use memfs::virtual_file::VirtualFile;
+
+fn read_all(fs: &MemFS, fd: usize) -> String {
+    let mut buffer = [0u8; 256];
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    String::from_utf8(buffer[..read].to_vec()).unwrap()
+}
+
+#[test]
+fn test_contents_should_be_generated_on_every_open() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let opens = Arc::new(AtomicUsize::new(0));
+    let counter = opens.clone();
+    fs.create_dir_all("/proc/self").unwrap();
+    fs.create_virtual_file(
+        "/proc/self/stats",
+        VirtualFile::new(move || format!("opens {}\n", counter.fetch_add(1, Ordering::SeqCst) + 1).into_bytes()),
+    )
+    .unwrap();
+
+    /* Action */
+
+    let first = fs.open("/proc/self/stats", OpenFlag::O_RDONLY).unwrap();
+    let second = fs.open("/proc/self/stats", OpenFlag::O_RDONLY).unwrap();
+    let first_contents = read_all(&fs, first);
+    fs.lseek(first, 0, SeekFlag::SEEK_SET).unwrap();
+    let reread = read_all(&fs, first);
+    let second_contents = read_all(&fs, second);
+    let written = fs.open("/proc/self/stats", OpenFlag::O_WRONLY);
+
+    /* Assert */
+
+    assert_eq!(first_contents, "opens 1\n");
+    assert_eq!(reread, "opens 1\n");
+    assert_eq!(second_contents, "opens 2\n");
+    assert!(written.is_err_and(|e| matches!(e.err_type, MemFSErrType::EACCES)));
+    assert_eq!(opens.load(Ordering::SeqCst), 2);
+
+    let stat = fs.stat("/proc/self/stats").unwrap();
+    assert_eq!(stat.file_type, FileType::File);
+    assert_eq!(stat.size, 0);
+    assert_eq!(stat.mode, 0o444);
+}
+
+#[test]
+fn test_writes_should_reach_the_callback() {
+    /* Arrange */
+
+    let fs = MemFS::builder().journal(true).build();
+    let level = Arc::new(Mutex::new(String::from("info")));
+    let (reader, writer) = (level.clone(), level.clone());
+    fs.create_virtual_file(
+        "/log_level",
+        VirtualFile::new(move || reader.lock().unwrap().clone().into_bytes()).on_write(move |data| {
+            let value = std::str::from_utf8(data).map_err(|_| MemFSErr::invalid_value())?.trim();
+
+            if !["debug", "info", "warn"].contains(&value) {
+                return Err(MemFSErr::invalid_value());
+            }
+
+            *writer.lock().unwrap() = value.to_string();
+            Ok(())
+        }),
+    )
+    .unwrap();
+    let fd = fs.open("/log_level", OpenFlag::O_RDWR).unwrap();
+
+    /* Action */
+
+    let accepted = fs.write(fd, b"debug\n");
+    let rejected = fs.write(fd, b"loud");
+    let positioned = fs.pwrite(fd, b"warn", 100);
+    let opened_before = read_all(&fs, fd);
+    let reopened = fs.open("/log_level", OpenFlag::O_RDONLY).unwrap();
+
+    /* Assert */
+
+    assert_eq!(accepted.unwrap(), 6);
+    assert!(rejected.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(positioned.unwrap(), 4);
+    assert_eq!(opened_before, "info");
+    assert_eq!(read_all(&fs, reopened), "warn");
+    assert_eq!(fs.journal().unwrap().entries().len(), 0);
+}
+
+#[test]
+fn test_callback_may_look_the_file_system_up() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    let weak: Weak<MemFS> = Arc::downgrade(&fs);
+    fs.create_virtual_file(
+        "/entries",
+        VirtualFile::new(move || match weak.upgrade() {
+            Some(fs) => fs.readdir("/").unwrap().len().to_string().into_bytes(),
+            None => Vec::new(),
+        }),
+    )
+    .unwrap();
+    fs.mkdir("/dir").unwrap();
+
+    /* Action */
+
+    let fd = fs.open("/entries", OpenFlag::O_RDONLY).unwrap();
+    let listed = read_all(&fs, fd);
+    fs.copy("/entries", "/copied").unwrap();
+    let not_virtual = fs.virtual_file("/copied");
+    fs.unlink("/entries").unwrap();
+
+    /* Assert */
+
+    assert_eq!(listed, "2");
+    assert!(not_virtual.is_err_and(|e| matches!(e.err_type, MemFSErrType::EINVAL)));
+    assert_eq!(fs.stat("/copied").unwrap().size, 1);
+    assert_eq!(read_all(&fs, fd), "");
+    assert!(fs.stat("/entries").is_err_and(|e| matches!(e.err_type, MemFSErrType::ENOENT)));
+}
@@ -0,0 +1,183 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use memfs::memfs::MemFS;
+use memfs::utils::{OpenFlag, generate_random_vector};
+use memfs::watch::{WatchEvent, WatchEventKind, WatchItem, WatchMask, WatchNotification};
+
+fn event(kind: WatchEventKind, path: &str) -> Option<WatchItem> {
+    Some(WatchItem::Event(WatchEvent {
+        kind,
+        path: path.to_string(),
+    }))
+}
+
+#[test]
+fn test_watch_stream_should_report_changes_under_watched_directory_only() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/watched").unwrap();
+    fs.mkdir("/watched_sibling").unwrap();
+    let stream = fs.watch_stream("/watched").unwrap();
+    let data = generate_random_vector(16);
+
+    /* Action */
+
+    fs.chdir("/watched").unwrap();
+    let fd = fs
+        .open("./new.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.write(fd, &data).unwrap();
+    fs.open("new.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR)
+        .unwrap();
+    fs.mkdir("/watched_sibling/ignored").unwrap();
+    fs.unlink("/watched/new.txt").unwrap();
+
+    /* Assert */
+
+    assert_eq!(stream.try_next(), event(WatchEventKind::Create, "/watched/new.txt"));
+    assert_eq!(stream.try_next(), event(WatchEventKind::Modify, "/watched/new.txt"));
+    assert_eq!(stream.try_next(), event(WatchEventKind::Delete, "/watched/new.txt"));
+    assert_eq!(stream.try_next(), None);
+}
+
+#[test]
+fn test_watch_stream_should_report_lag_when_buffer_overflows() {
+    let fs = MemFS::new();
+    let stream = fs.watch_stream_with_capacity("/", 2).unwrap();
+
+    for i in 0..5 {
+        fs.mkdir(format!("/dir{i}").as_str()).unwrap();
+    }
+
+    assert_eq!(stream.try_next(), Some(WatchItem::Lagged(3)));
+    assert_eq!(stream.try_next(), event(WatchEventKind::Create, "/dir3"));
+    assert_eq!(stream.try_next(), event(WatchEventKind::Create, "/dir4"));
+    assert_eq!(stream.try_next(), None);
+}
+
+#[test]
+fn test_watch_stream_should_fail_on_nonexistent_path() {
+    let fs = MemFS::new();
+
+    let result = fs.watch_stream("/nowhere");
+
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_watch_stream_should_be_polled_as_stream_and_end_when_fs_is_dropped() {
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    let fs = MemFS::new();
+    let mut stream = fs.watch_stream("/").unwrap();
+    let mut cx = Context::from_waker(Waker::noop());
+
+    assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+
+    fs.mkdir("/async").unwrap();
+    drop(fs);
+
+    assert_eq!(
+        Pin::new(&mut stream).poll_next(&mut cx),
+        Poll::Ready(event(WatchEventKind::Create, "/async"))
+    );
+    assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+fn test_watch_should_deliver_events_of_other_threads_over_channel() {
+    /* Arrange */
+
+    let fs = Arc::new(MemFS::new());
+    fs.mkdir("/watched").unwrap();
+    let handle = fs.watch("/watched", WatchMask::all()).unwrap();
+
+    /* Action */
+
+    let writer = {
+        let fs = fs.clone();
+
+        thread::spawn(move || {
+            let fd = fs.open("/watched/file", OpenFlag::O_CREAT | OpenFlag::O_WRONLY).unwrap();
+            fs.write(fd, b"data").unwrap();
+            fs.close(fd).unwrap();
+            fs.rename("/watched/file", "/watched/renamed").unwrap();
+            fs.unlink("/watched/renamed").unwrap();
+        })
+    };
+
+    let received: Vec<_> = (0..4)
+        .map(|_| handle.recv_timeout(Duration::from_secs(5)))
+        .collect();
+    writer.join().unwrap();
+
+    /* Assert */
+
+    assert_eq!(
+        received,
+        vec![
+            Some(WatchNotification::Create { path: "/watched/file".to_string() }),
+            Some(WatchNotification::Modify { path: "/watched/file".to_string() }),
+            Some(WatchNotification::Move {
+                from: "/watched/file".to_string(),
+                to: "/watched/renamed".to_string(),
+            }),
+            Some(WatchNotification::Delete { path: "/watched/renamed".to_string() }),
+        ]
+    );
+    assert_eq!(handle.try_recv(), None);
+}
+
+#[test]
+fn test_watch_should_filter_by_mask_and_path() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    fs.mkdir("/watched").unwrap();
+    fs.mkdir("/outside").unwrap();
+    let moves = fs.watch("/watched", WatchMask::MOVE | WatchMask::DELETE).unwrap();
+    let stream = fs.watch_stream("/watched").unwrap();
+
+    /* Action */
+
+    fs.mkdir("/outside/dir").unwrap();
+    fs.rename("/outside/dir", "/watched/dir").unwrap();
+    fs.mkdir("/watched/ignored").unwrap();
+    fs.rmdir("/outside").unwrap();
+
+    /* Assert */
+
+    assert_eq!(
+        moves.try_recv(),
+        Some(WatchNotification::Move {
+            from: "/outside/dir".to_string(),
+            to: "/watched/dir".to_string(),
+        })
+    );
+    assert_eq!(moves.try_recv(), None);
+    assert_eq!(stream.try_next(), event(WatchEventKind::Create, "/watched/dir"));
+    assert_eq!(stream.try_next(), event(WatchEventKind::Create, "/watched/ignored"));
+    assert!(fs.watch("/nowhere", WatchMask::all()).is_err());
+}
+
+#[test]
+fn test_watch_should_end_when_fs_is_dropped() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let handle = fs.watch("/", WatchMask::CREATE).unwrap();
+
+    /* Action */
+
+    fs.mkdir("/last").unwrap();
+    drop(fs);
+
+    /* Assert */
+
+    assert_eq!(handle.recv(), Some(WatchNotification::Create { path: "/last".to_string() }));
+    assert_eq!(handle.recv(), None);
+}
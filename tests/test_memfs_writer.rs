@@ -0,0 +1,88 @@
+use memfs::memfs::MemFS;
+use memfs::utils::{FILE_MAX_SIZE, MemFSErrType, OpenFlag, generate_random_vector};
+
+#[test]
+fn test_writer_should_stream_chunks_into_new_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let data = generate_random_vector(3000);
+    let mut buffer = vec![0u8; 3000];
+
+    /* Action */
+
+    let mut writer = fs.create_writer_with_capacity("/stream.bin", 256).unwrap();
+
+    for chunk in data.chunks(100) {
+        writer.write_chunk(chunk).unwrap();
+    }
+
+    let buffered = writer.len();
+    let size = writer.finish().unwrap();
+
+    let fd = fs.open("/stream.bin", OpenFlag::O_RDONLY).unwrap();
+    let read = fs.read(fd, &mut buffer).unwrap();
+
+    /* Assert */
+
+    assert_eq!(buffered, 3000);
+    assert_eq!(size, 3000);
+    assert_eq!(fs.stat("/stream.bin").unwrap().size, 3000);
+    assert_eq!(read, 3000);
+    assert_eq!(buffer, data);
+}
+
+#[test]
+fn test_writer_should_reject_chunk_past_maximum_size() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let mut writer = fs.create_writer("/full.bin").unwrap();
+
+    writer.write_chunk(&vec![b'f'; FILE_MAX_SIZE - 1]).unwrap();
+
+    /* Action */
+
+    let result = writer.write_chunk(b"ab");
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EFBIG)));
+    assert_eq!(writer.finish().unwrap(), FILE_MAX_SIZE - 1);
+}
+
+#[test]
+fn test_writer_should_fail_on_existing_file() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+    let fd = fs.open("/exists.txt", OpenFlag::O_CREAT | OpenFlag::O_RDWR).unwrap();
+    fs.close(fd).unwrap();
+
+    /* Action */
+
+    let result = fs.create_writer("/exists.txt");
+
+    /* Assert */
+
+    assert!(result.is_err_and(|e| matches!(e.err_type, MemFSErrType::EEXIST)));
+}
+
+#[test]
+fn test_dropped_writer_should_write_buffered_bytes_and_close() {
+    /* Arrange */
+
+    let fs = MemFS::new();
+
+    /* Action */
+
+    {
+        let mut writer = fs.create_writer("/dropped.txt").unwrap();
+        writer.write_chunk(b"unfinished").unwrap();
+    }
+
+    /* Assert */
+
+    assert_eq!(fs.stat("/dropped.txt").unwrap().size, 10);
+    assert_eq!(fs.metrics().open_file_descriptors, 0);
+}